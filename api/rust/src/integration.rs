@@ -33,6 +33,7 @@ impl Into<String> for LogCode {
             LogCode::RelayNewEndDevice => "RELAY_NEW_END_DEVICE",
             LogCode::FCntDown => "F_CNT_DOWN",
             LogCode::Expired => "EXPIRED",
+            LogCode::DownlinkRetriesExhausted => "DOWNLINK_RETRIES_EXHAUSTED",
         }
         .to_string()
     }
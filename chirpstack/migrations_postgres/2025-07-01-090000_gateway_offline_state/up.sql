@@ -0,0 +1,2 @@
+alter table gateway
+  add column is_offline boolean not null default false;
@@ -0,0 +1,4 @@
+alter table alarm
+  drop column no_data_triggered_at,
+  drop column no_data_time,
+  drop column no_data;
@@ -0,0 +1,4 @@
+alter table alarm
+  add column no_data boolean null default false,
+  add column no_data_time integer null default 0,
+  add column no_data_triggered_at timestamp with time zone null;
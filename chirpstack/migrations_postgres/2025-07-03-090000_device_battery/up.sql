@@ -0,0 +1,10 @@
+create table device_battery (
+  dev_eui varchar(30) primary key,
+  device_type_id integer null,
+  voltage double precision null,
+  battery_level real null,
+  discharge_per_day real null,
+  days_remaining integer null,
+  low_battery_notified_at timestamp with time zone null,
+  updated_at timestamp with time zone not null
+);
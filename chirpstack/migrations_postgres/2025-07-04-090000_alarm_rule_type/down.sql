@@ -0,0 +1,4 @@
+alter table alarm
+  drop column rule_limit,
+  drop column rule_duration,
+  drop column rule_type;
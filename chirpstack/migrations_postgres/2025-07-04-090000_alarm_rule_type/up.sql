@@ -0,0 +1,4 @@
+alter table alarm
+  add column rule_type integer not null default 0,
+  add column rule_duration integer null,
+  add column rule_limit double precision null;
@@ -0,0 +1,2 @@
+drop table defrost_cycle;
+drop table defrost_schedule;
@@ -0,0 +1,29 @@
+create table defrost_schedule (
+  id serial primary key,
+  zone_id integer null,
+  dev_eui varchar(30) null,
+  start_time double precision not null,
+  duration integer not null,
+  learned boolean not null default false,
+  created_at timestamp with time zone not null,
+  updated_at timestamp with time zone not null,
+  check (zone_id is not null or dev_eui is not null)
+);
+
+create index idx_defrost_schedule_zone_id on defrost_schedule(zone_id);
+create index idx_defrost_schedule_dev_eui on defrost_schedule(dev_eui);
+
+create table defrost_cycle (
+  id serial primary key,
+  dev_eui varchar(30) not null,
+  schedule_id integer null references defrost_schedule on delete set null,
+  window_start timestamp with time zone not null,
+  started_at timestamp with time zone null,
+  ended_at timestamp with time zone null,
+  peak_temperature real null,
+  status integer not null,
+  created_at timestamp with time zone not null
+);
+
+create unique index idx_defrost_cycle_dev_eui_schedule_id_window_start on defrost_cycle(dev_eui, schedule_id, window_start);
+create index idx_defrost_cycle_created_at on defrost_cycle(created_at);
@@ -0,0 +1,3 @@
+alter table device_profile
+  drop column ts003_f_port,
+  drop column ts003_enabled;
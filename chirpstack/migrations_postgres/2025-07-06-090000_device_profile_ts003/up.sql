@@ -0,0 +1,3 @@
+alter table device_profile
+  add column ts003_enabled boolean null default false,
+  add column ts003_f_port smallint null;
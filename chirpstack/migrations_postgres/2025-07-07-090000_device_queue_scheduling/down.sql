@@ -0,0 +1,6 @@
+drop index idx_device_queue_item_coalesce_key;
+
+alter table device_queue_item
+  drop column coalesce_key,
+  drop column priority,
+  drop column scheduled_at;
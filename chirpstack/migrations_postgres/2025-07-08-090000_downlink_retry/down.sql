@@ -0,0 +1,6 @@
+alter table device_queue_item
+  drop column retry_count;
+
+alter table device_profile
+  drop column downlink_retry_backoff,
+  drop column downlink_retry_count;
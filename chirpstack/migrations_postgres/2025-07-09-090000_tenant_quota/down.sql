@@ -0,0 +1,4 @@
+alter table tenant
+  drop column over_quota_action,
+  drop column max_sms_count,
+  drop column max_message_count;
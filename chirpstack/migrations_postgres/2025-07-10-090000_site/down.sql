@@ -0,0 +1,8 @@
+alter table "user"
+  drop column site_id_list;
+
+drop index idx_zone_site_id;
+alter table zone
+  drop column site_id;
+
+drop table site;
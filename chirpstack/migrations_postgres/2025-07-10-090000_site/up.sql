@@ -0,0 +1,22 @@
+create table site (
+  site_id serial primary key,
+  tenant_id uuid not null references tenant on delete cascade,
+  site_name varchar(100) not null,
+  address text not null default '',
+  latitude double precision null,
+  longitude double precision null,
+  timezone varchar(64) not null default 'UTC',
+  site_order bigint not null default 0,
+  created_at timestamp with time zone not null,
+  updated_at timestamp with time zone not null
+);
+
+create index idx_site_tenant_id on site(tenant_id);
+
+alter table zone
+  add column site_id integer null references site on delete set null;
+
+create index idx_zone_site_id on zone(site_id);
+
+alter table "user"
+  add column site_id_list bigint[] null;
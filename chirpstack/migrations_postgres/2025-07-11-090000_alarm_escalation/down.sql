@@ -0,0 +1,4 @@
+drop table alarm_escalation;
+drop table escalation_policy_target;
+drop table escalation_step;
+drop table escalation_policy;
@@ -0,0 +1,48 @@
+create table escalation_policy (
+  id serial primary key,
+  tenant_id uuid not null references tenant on delete cascade,
+  name varchar(100) not null,
+  created_at timestamp with time zone not null,
+  updated_at timestamp with time zone not null
+);
+
+create index idx_escalation_policy_tenant_id on escalation_policy(tenant_id);
+
+create table escalation_step (
+  id serial primary key,
+  policy_id integer not null references escalation_policy on delete cascade,
+  level integer not null,
+  delay_minutes integer not null,
+  channel varchar(20) not null,
+  user_id uuid[] not null default '{}',
+  webhook_url text not null default '',
+  unique (policy_id, level)
+);
+
+create table escalation_policy_target (
+  id serial primary key,
+  policy_id integer not null references escalation_policy on delete cascade,
+  alarm_id integer null references alarm on delete cascade,
+  zone_id integer null references zone on delete cascade,
+  check ((alarm_id is null) <> (zone_id is null))
+);
+
+create unique index idx_escalation_policy_target_alarm_id on escalation_policy_target(alarm_id);
+create unique index idx_escalation_policy_target_zone_id on escalation_policy_target(zone_id);
+
+create table alarm_escalation (
+  id serial primary key,
+  alarm_id integer not null references alarm on delete cascade,
+  policy_id integer not null references escalation_policy on delete cascade,
+  dev_eui varchar(30) not null,
+  message text not null,
+  level integer not null default 0,
+  triggered_at timestamp with time zone not null,
+  next_at timestamp with time zone null,
+  acknowledged_at timestamp with time zone null,
+  acknowledged_by uuid null
+);
+
+-- At most one escalation in progress per alarm.
+create unique index idx_alarm_escalation_alarm_id_open on alarm_escalation(alarm_id) where next_at is not null and acknowledged_at is null;
+create index idx_alarm_escalation_next_at on alarm_escalation(next_at);
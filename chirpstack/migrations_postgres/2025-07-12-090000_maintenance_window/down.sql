@@ -0,0 +1,2 @@
+drop table alarm_snooze;
+drop table maintenance_window;
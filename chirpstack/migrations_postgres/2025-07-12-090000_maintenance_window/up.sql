@@ -0,0 +1,25 @@
+create table maintenance_window (
+  id serial primary key,
+  tenant_id uuid not null references tenant on delete cascade,
+  dev_eui varchar(30) null,
+  zone_id integer null references zone on delete cascade,
+  site_id integer null references site on delete cascade,
+  start_at timestamp with time zone not null,
+  end_at timestamp with time zone not null,
+  reason text not null default '',
+  created_by uuid null,
+  created_at timestamp with time zone not null,
+  check (num_nonnulls(dev_eui, zone_id, site_id) = 1),
+  check (end_at > start_at)
+);
+
+create index idx_maintenance_window_tenant_id on maintenance_window(tenant_id);
+create index idx_maintenance_window_end_at on maintenance_window(end_at);
+
+create table alarm_snooze (
+  alarm_id integer not null references alarm on delete cascade,
+  user_id uuid not null,
+  snoozed_until timestamp with time zone not null,
+  created_at timestamp with time zone not null,
+  primary key (alarm_id, user_id)
+);
@@ -0,0 +1,7 @@
+drop table device_data_daily;
+drop table device_data_hourly;
+drop table device_data;
+
+alter table tenant
+  drop column raw_data_retention_days,
+  drop column hourly_data_retention_months;
@@ -0,0 +1,86 @@
+alter table tenant
+  add column raw_data_retention_days integer not null default 0,
+  add column hourly_data_retention_months integer not null default 0;
+
+-- Sensor readings, partitioned by month. The readings of the legacy
+-- device_data_2025 table are copied by the migrate-sensor-data subcommand.
+create table device_data (
+  id serial not null,
+  dev_eui text not null,
+  device_type_id integer not null,
+  air_temperature numeric null,
+  air_humidity numeric null,
+  sol_temperature numeric null,
+  sol_water numeric null,
+  sol_conduct_soil numeric null,
+  submission_date timestamp not null default (now() at time zone 'utc'),
+  water_leak_status integer null,
+  water_leak_times integer null,
+  last_water_leak_duration integer null,
+  door_open_status integer null,
+  door_open_times integer null,
+  last_door_open_duration integer null,
+  batv numeric null,
+  ro1_status integer null,
+  ro2_status integer null,
+  ph_soil numeric null,
+  co2_ppm numeric null,
+  tvoc_ppm numeric null,
+  sensecap_light numeric null,
+  barometric_pressure numeric null,
+  status integer null,
+  current numeric null,
+  factor numeric null,
+  power numeric null,
+  power_sum numeric null,
+  voltage numeric null,
+  primary key (id, submission_date)
+) partition by range (submission_date);
+
+create index idx_device_data_dev_eui_submission_date on device_data(dev_eui, submission_date);
+
+-- Catches the readings for which no monthly partition exists.
+create table device_data_default partition of device_data default;
+
+-- The partitions of the following months are created by the sensor-data
+-- maintenance loop.
+do $$
+declare
+  m date := date_trunc('month', now() at time zone 'utc')::date;
+begin
+  for i in 0..1 loop
+    execute format(
+      'create table device_data_%s partition of device_data for values from (%L) to (%L)',
+      to_char(m, '"y"YYYY"m"MM'),
+      m,
+      (m + interval '1 month')::date
+    );
+    m := (m + interval '1 month')::date;
+  end loop;
+end $$;
+
+create table device_data_hourly (
+  dev_eui text not null,
+  metric varchar(50) not null,
+  bucket timestamp not null,
+  min_value double precision not null,
+  max_value double precision not null,
+  avg_value double precision not null,
+  sample_count integer not null,
+  primary key (dev_eui, metric, bucket)
+);
+
+create index idx_device_data_hourly_bucket on device_data_hourly(bucket);
+
+create table device_data_daily (
+  dev_eui text not null,
+  metric varchar(50) not null,
+  bucket timestamp not null,
+  min_value double precision not null,
+  max_value double precision not null,
+  avg_value double precision not null,
+  sample_count integer not null,
+  primary key (dev_eui, metric, bucket)
+);
+
+create index idx_device_data_daily_bucket on device_data_daily(bucket);
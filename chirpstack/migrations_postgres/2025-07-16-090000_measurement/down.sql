@@ -0,0 +1,62 @@
+alter view device_data_latest rename to device_data_latest_view;
+
+create table device_data_latest (
+    id integer not null,
+    dev_eui text not null primary key,
+    device_type_id integer not null,
+    org_id integer not null,
+    air_temperature numeric null,
+    air_humidity numeric null,
+    sol_temperature numeric null,
+    sol_water numeric null,
+    sol_conduct_soil numeric null,
+    submission_date timestamp null,
+    water_leak_status integer null,
+    water_leak_times integer null,
+    last_water_leak_duration integer null,
+    door_open_status integer null,
+    door_open_times integer null,
+    last_door_open_duration integer null,
+    batv numeric null,
+    ro1_status integer null,
+    ro2_status integer null,
+    ph_soil numeric null,
+    co2_ppm numeric null,
+    tvoc_ppm numeric null,
+    sensecap_light numeric null,
+    barometric_pressure numeric null,
+    current numeric null,
+    factor numeric null,
+    power numeric null,
+    voltage numeric null,
+    power_sum numeric null,
+    status integer null,
+    power_consumption integer null,
+    switch1 integer null,
+    switch2 integer null,
+    switch3 integer null,
+    switch4 integer null,
+    switch5 integer null,
+    switch6 integer null,
+    switch7 integer null,
+    switch8 integer null,
+    adc_1 varchar(50) null,
+    adc_2 varchar(50) null,
+    adv_1 varchar(50) null,
+    gpio_in_1 varchar(50) null,
+    gpio_in_2 varchar(50) null,
+    gpio_in_3 varchar(50) null,
+    gpio_in_4 varchar(50) null,
+    gpio_out_1 varchar(50) null,
+    gpio_out_2 varchar(50) null,
+    distance integer null,
+    position varchar(20) null,
+    temperature1 numeric null,
+    temperature2 numeric null
+);
+
+insert into device_data_latest select * from device_data_latest_view;
+
+drop view device_data_latest_view;
+drop table measurement_latest;
+drop table measurement;
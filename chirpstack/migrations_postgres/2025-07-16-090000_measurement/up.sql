@@ -0,0 +1,157 @@
+-- Normalised sensor readings, one row per device, measurement kind and
+-- timestamp. The kind matches the device-profile measurement key, or the
+-- legacy device_data column name for the built-in device types.
+create table measurement (
+    dev_eui bytea not null references device on delete cascade,
+    kind varchar(100) not null,
+    time timestamp with time zone not null,
+    unit varchar(20) not null default '',
+    value double precision null,
+    text_value varchar(100) null,
+    primary key (dev_eui, kind, time)
+);
+
+create index idx_measurement_time on measurement(time);
+
+-- Latest value of each measurement kind of each device.
+create table measurement_latest (
+    dev_eui bytea not null references device on delete cascade,
+    kind varchar(100) not null,
+    time timestamp with time zone not null,
+    unit varchar(20) not null default '',
+    value double precision null,
+    text_value varchar(100) null,
+    primary key (dev_eui, kind)
+);
+
+-- Copy the latest values of the wide device_data_latest table.
+insert into measurement_latest (dev_eui, kind, time, unit, value, text_value)
+select
+    d.dev_eui,
+    v.kind,
+    coalesce(l.submission_date, now() at time zone 'utc') at time zone 'utc',
+    v.unit,
+    v.value,
+    v.text_value
+from
+    device_data_latest l
+inner join device d
+    on encode(d.dev_eui, 'hex') = lower(l.dev_eui)
+cross join lateral (values
+    ('air_temperature', '°C', l.air_temperature::double precision, null),
+    ('air_humidity', '%', l.air_humidity::double precision, null),
+    ('sol_temperature', '°C', l.sol_temperature::double precision, null),
+    ('sol_water', '%', l.sol_water::double precision, null),
+    ('sol_conduct_soil', 'uS/cm', l.sol_conduct_soil::double precision, null),
+    ('water_leak_status', '', l.water_leak_status::double precision, null),
+    ('water_leak_times', '', l.water_leak_times::double precision, null),
+    ('last_water_leak_duration', 'min', l.last_water_leak_duration::double precision, null),
+    ('door_open_status', '', l.door_open_status::double precision, null),
+    ('door_open_times', '', l.door_open_times::double precision, null),
+    ('last_door_open_duration', 'min', l.last_door_open_duration::double precision, null),
+    ('batv', 'V', l.batv::double precision, null),
+    ('ro1_status', '', l.ro1_status::double precision, null),
+    ('ro2_status', '', l.ro2_status::double precision, null),
+    ('ph_soil', 'pH', l.ph_soil::double precision, null),
+    ('co2_ppm', 'ppm', l.co2_ppm::double precision, null),
+    ('tvoc_ppm', 'ppm', l.tvoc_ppm::double precision, null),
+    ('sensecap_light', 'lux', l.sensecap_light::double precision, null),
+    ('barometric_pressure', 'hPa', l.barometric_pressure::double precision, null),
+    ('current', 'mA', l.current::double precision, null),
+    ('factor', '', l.factor::double precision, null),
+    ('power', 'W', l.power::double precision, null),
+    ('voltage', 'V', l.voltage::double precision, null),
+    ('power_sum', 'Wh', l.power_sum::double precision, null),
+    ('status', '', l.status::double precision, null),
+    ('power_consumption', 'Wh', l.power_consumption::double precision, null),
+    ('switch1', '', l.switch1::double precision, null),
+    ('switch2', '', l.switch2::double precision, null),
+    ('switch3', '', l.switch3::double precision, null),
+    ('switch4', '', l.switch4::double precision, null),
+    ('switch5', '', l.switch5::double precision, null),
+    ('switch6', '', l.switch6::double precision, null),
+    ('switch7', '', l.switch7::double precision, null),
+    ('switch8', '', l.switch8::double precision, null),
+    ('adc_1', '', null, l.adc_1),
+    ('adc_2', '', null, l.adc_2),
+    ('adv_1', '', null, l.adv_1),
+    ('gpio_in_1', '', null, l.gpio_in_1),
+    ('gpio_in_2', '', null, l.gpio_in_2),
+    ('gpio_in_3', '', null, l.gpio_in_3),
+    ('gpio_in_4', '', null, l.gpio_in_4),
+    ('gpio_out_1', '', null, l.gpio_out_1),
+    ('gpio_out_2', '', null, l.gpio_out_2),
+    ('distance', 'mm', l.distance::double precision, null),
+    ('position', '', null, l.position),
+    ('temperature1', '°C', l.temperature1::double precision, null),
+    ('temperature2', '°C', l.temperature2::double precision, null)
+) as v(kind, unit, value, text_value)
+where
+    v.value is not null or v.text_value is not null;
+
+-- The wide latest-value table is replaced by a view on the normalised
+-- measurements, such that adding a measurement kind does not require a new
+-- column.
+drop table device_data_latest;
+
+create view device_data_latest as
+select
+    0 as id,
+    encode(d.dev_eui, 'hex') as dev_eui,
+    coalesce(d.device_type, 0) as device_type_id,
+    0 as org_id,
+    (max(m.value) filter (where m.kind = 'air_temperature'))::numeric as air_temperature,
+    (max(m.value) filter (where m.kind = 'air_humidity'))::numeric as air_humidity,
+    (max(m.value) filter (where m.kind = 'sol_temperature'))::numeric as sol_temperature,
+    (max(m.value) filter (where m.kind = 'sol_water'))::numeric as sol_water,
+    (max(m.value) filter (where m.kind = 'sol_conduct_soil'))::numeric as sol_conduct_soil,
+    (max(m.time) at time zone 'utc') as submission_date,
+    (max(m.value) filter (where m.kind = 'water_leak_status'))::integer as water_leak_status,
+    (max(m.value) filter (where m.kind = 'water_leak_times'))::integer as water_leak_times,
+    (max(m.value) filter (where m.kind = 'last_water_leak_duration'))::integer as last_water_leak_duration,
+    (max(m.value) filter (where m.kind = 'door_open_status'))::integer as door_open_status,
+    (max(m.value) filter (where m.kind = 'door_open_times'))::integer as door_open_times,
+    (max(m.value) filter (where m.kind = 'last_door_open_duration'))::integer as last_door_open_duration,
+    (max(m.value) filter (where m.kind = 'batv'))::numeric as batv,
+    (max(m.value) filter (where m.kind = 'ro1_status'))::integer as ro1_status,
+    (max(m.value) filter (where m.kind = 'ro2_status'))::integer as ro2_status,
+    (max(m.value) filter (where m.kind = 'ph_soil'))::numeric as ph_soil,
+    (max(m.value) filter (where m.kind = 'co2_ppm'))::numeric as co2_ppm,
+    (max(m.value) filter (where m.kind = 'tvoc_ppm'))::numeric as tvoc_ppm,
+    (max(m.value) filter (where m.kind = 'sensecap_light'))::numeric as sensecap_light,
+    (max(m.value) filter (where m.kind = 'barometric_pressure'))::numeric as barometric_pressure,
+    (max(m.value) filter (where m.kind = 'current'))::numeric as current,
+    (max(m.value) filter (where m.kind = 'factor'))::numeric as factor,
+    (max(m.value) filter (where m.kind = 'power'))::numeric as power,
+    (max(m.value) filter (where m.kind = 'voltage'))::numeric as voltage,
+    (max(m.value) filter (where m.kind = 'power_sum'))::numeric as power_sum,
+    (max(m.value) filter (where m.kind = 'status'))::integer as status,
+    (max(m.value) filter (where m.kind = 'power_consumption'))::integer as power_consumption,
+    (max(m.value) filter (where m.kind = 'switch1'))::integer as switch1,
+    (max(m.value) filter (where m.kind = 'switch2'))::integer as switch2,
+    (max(m.value) filter (where m.kind = 'switch3'))::integer as switch3,
+    (max(m.value) filter (where m.kind = 'switch4'))::integer as switch4,
+    (max(m.value) filter (where m.kind = 'switch5'))::integer as switch5,
+    (max(m.value) filter (where m.kind = 'switch6'))::integer as switch6,
+    (max(m.value) filter (where m.kind = 'switch7'))::integer as switch7,
+    (max(m.value) filter (where m.kind = 'switch8'))::integer as switch8,
+    (max(m.text_value) filter (where m.kind = 'adc_1'))::varchar(50) as adc_1,
+    (max(m.text_value) filter (where m.kind = 'adc_2'))::varchar(50) as adc_2,
+    (max(m.text_value) filter (where m.kind = 'adv_1'))::varchar(50) as adv_1,
+    (max(m.text_value) filter (where m.kind = 'gpio_in_1'))::varchar(50) as gpio_in_1,
+    (max(m.text_value) filter (where m.kind = 'gpio_in_2'))::varchar(50) as gpio_in_2,
+    (max(m.text_value) filter (where m.kind = 'gpio_in_3'))::varchar(50) as gpio_in_3,
+    (max(m.text_value) filter (where m.kind = 'gpio_in_4'))::varchar(50) as gpio_in_4,
+    (max(m.text_value) filter (where m.kind = 'gpio_out_1'))::varchar(50) as gpio_out_1,
+    (max(m.text_value) filter (where m.kind = 'gpio_out_2'))::varchar(50) as gpio_out_2,
+    (max(m.value) filter (where m.kind = 'distance'))::integer as distance,
+    (max(m.text_value) filter (where m.kind = 'position'))::varchar(20) as position,
+    (max(m.value) filter (where m.kind = 'temperature1'))::numeric as temperature1,
+    (max(m.value) filter (where m.kind = 'temperature2'))::numeric as temperature2
+from
+    measurement_latest m
+inner join device d
+    on d.dev_eui = m.dev_eui
+group by
+    d.dev_eui,
+    d.device_type;
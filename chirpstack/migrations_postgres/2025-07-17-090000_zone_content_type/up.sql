@@ -0,0 +1,20 @@
+-- Content types of zones (cold room, freezer, ...). The zone content_type
+-- column refers to the id of this table.
+create table zone_content_type (
+  id bigserial primary key,
+  name varchar(100) not null,
+  min_temperature double precision null,
+  max_temperature double precision null,
+  min_humidity double precision null,
+  max_humidity double precision null,
+  measurements text[] not null default '{}',
+  created_at timestamp with time zone not null,
+  updated_at timestamp with time zone not null
+);
+
+insert into zone_content_type (name, min_temperature, max_temperature, min_humidity, max_humidity, measurements, created_at, updated_at) values
+  ('Cold room', 0, 8, null, null, '{air_temperature,air_humidity,door_open_status}', now(), now()),
+  ('Freezer', -25, -15, null, null, '{air_temperature,door_open_status}', now(), now()),
+  ('Greenhouse', 10, 35, 40, 90, '{air_temperature,air_humidity,sol_temperature,sol_water,co2_ppm,sensecap_light}', now(), now()),
+  ('Office', 18, 27, 30, 60, '{air_temperature,air_humidity,co2_ppm,tvoc_ppm}', now(), now()),
+  ('Water tank', null, null, null, null, '{distance,water_leak_status}', now(), now());
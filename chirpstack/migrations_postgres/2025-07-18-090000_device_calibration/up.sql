@@ -0,0 +1,15 @@
+-- Calibration records of the device measurements. A record applies from
+-- effective_from until a record of the same device and kind with a later
+-- effective_from exists.
+create table device_calibration (
+  id serial primary key,
+  dev_eui bytea not null references device on delete cascade,
+  kind varchar(100) not null,
+  "offset" double precision not null default 0,
+  gain double precision not null default 1,
+  points jsonb not null default '[]',
+  effective_from timestamp with time zone not null,
+  certificate varchar(255) not null,
+  created_at timestamp with time zone not null,
+  unique (dev_eui, kind, effective_from)
+);
@@ -0,0 +1,23 @@
+-- Virtual devices compute their value using a formula over the measurements
+-- (or variables) of other devices. The device row of a virtual device uses
+-- the application and device-profile of its first input device.
+create table virtual_device (
+  dev_eui bytea primary key references device on delete cascade,
+  kind varchar(100) not null,
+  unit varchar(20) not null default '',
+  formula text not null,
+  created_at timestamp with time zone not null,
+  updated_at timestamp with time zone not null
+);
+
+-- Formula inputs. The name is the variable name used in the formula.
+create table virtual_device_input (
+  dev_eui bytea not null references virtual_device on delete cascade,
+  name varchar(100) not null,
+  input_dev_eui bytea not null references device on delete cascade,
+  kind varchar(100) not null,
+  aggregate varchar(20) not null default 'LATEST',
+  primary key (dev_eui, name)
+);
+
+create index idx_virtual_device_input_input_dev_eui on virtual_device_input(input_dev_eui);
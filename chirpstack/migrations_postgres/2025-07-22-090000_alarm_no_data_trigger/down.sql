@@ -0,0 +1,19 @@
+delete from alarm_state a
+  using alarm_state b
+  where a.alarm_id = b.alarm_id
+    and a.alarm_type = b.alarm_type
+    and a.dev_eui > b.dev_eui;
+
+alter table alarm_state
+  drop constraint alarm_state_pkey,
+  add primary key (alarm_id, alarm_type);
+
+alter table alarm
+  add column no_data_triggered_at timestamp with time zone null;
+
+update alarm a
+  set no_data_triggered_at = t.triggered_at
+  from alarm_no_data_trigger t
+  where t.alarm_id = a.id and t.dev_eui = lower(a.dev_eui);
+
+drop table alarm_no_data_trigger;
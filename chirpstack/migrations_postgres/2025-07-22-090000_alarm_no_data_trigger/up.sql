@@ -0,0 +1,20 @@
+create table alarm_no_data_trigger (
+  alarm_id integer not null references alarm on delete cascade,
+  dev_eui varchar(16) not null,
+  triggered_at timestamp with time zone not null,
+  primary key (alarm_id, dev_eui)
+);
+
+create index idx_alarm_no_data_trigger_dev_eui on alarm_no_data_trigger(dev_eui);
+
+insert into alarm_no_data_trigger (alarm_id, dev_eui, triggered_at)
+  select id, lower(dev_eui), no_data_triggered_at
+  from alarm
+  where no_data_triggered_at is not null and dev_eui <> '';
+
+alter table alarm
+  drop column no_data_triggered_at;
+
+alter table alarm_state
+  drop constraint alarm_state_pkey,
+  add primary key (alarm_id, alarm_type, dev_eui);
@@ -0,0 +1 @@
+drop table alarm_trigger;
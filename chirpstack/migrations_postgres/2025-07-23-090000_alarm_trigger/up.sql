@@ -0,0 +1,6 @@
+create table alarm_trigger (
+  alarm_id integer not null references alarm on delete cascade,
+  alarm_type varchar(50) not null,
+  triggered_at timestamp with time zone not null,
+  primary key (alarm_id, alarm_type)
+);
@@ -0,0 +1,2 @@
+alter table device_profile drop column ts003_f_port;
+alter table device_profile drop column ts003_enabled;
@@ -0,0 +1,2 @@
+alter table device_profile add column ts003_enabled boolean null default false;
+alter table device_profile add column ts003_f_port smallint null;
@@ -0,0 +1,5 @@
+drop index idx_device_queue_item_coalesce_key;
+
+alter table device_queue_item drop column coalesce_key;
+alter table device_queue_item drop column priority;
+alter table device_queue_item drop column scheduled_at;
@@ -0,0 +1,5 @@
+alter table device_queue_item add column scheduled_at datetime null;
+alter table device_queue_item add column priority smallint not null default 0;
+alter table device_queue_item add column coalesce_key varchar(100) null;
+
+create index idx_device_queue_item_coalesce_key on device_queue_item(dev_eui, coalesce_key);
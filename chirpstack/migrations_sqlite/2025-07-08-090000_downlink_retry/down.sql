@@ -0,0 +1,4 @@
+alter table device_queue_item drop column retry_count;
+
+alter table device_profile drop column downlink_retry_backoff;
+alter table device_profile drop column downlink_retry_count;
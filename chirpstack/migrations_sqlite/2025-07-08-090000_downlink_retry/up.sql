@@ -0,0 +1,4 @@
+alter table device_profile add column downlink_retry_count smallint null default 0;
+alter table device_profile add column downlink_retry_backoff integer null default 0;
+
+alter table device_queue_item add column retry_count smallint not null default 0;
@@ -0,0 +1,3 @@
+alter table tenant drop column over_quota_action;
+alter table tenant drop column max_sms_count;
+alter table tenant drop column max_message_count;
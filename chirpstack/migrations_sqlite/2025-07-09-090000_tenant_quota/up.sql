@@ -0,0 +1,3 @@
+alter table tenant add column max_message_count integer not null default 0;
+alter table tenant add column max_sms_count integer not null default 0;
+alter table tenant add column over_quota_action varchar(20) not null default 'WARN';
@@ -0,0 +1,59 @@
+drop table alarm_snooze;
+drop table maintenance_window;
+drop index idx_alarm_escalation_next_at;
+drop index idx_alarm_escalation_alarm_id_open;
+drop table alarm_escalation;
+drop table escalation_policy_target;
+drop table escalation_step;
+drop table escalation_policy;
+drop table defrost_cycle;
+drop table defrost_schedule;
+drop table device_battery;
+drop table notifications;
+drop table automation_rules;
+drop table door_alarm_date_time;
+drop table door_time_alarm;
+drop table alarm_automation_rules;
+drop table alarm_audit_log;
+drop table alarm_date_time;
+drop table alarm;
+drop table zone;
+drop table site;
+drop table uc300;
+drop table ltc2lb;
+drop table em400mud;
+drop table dds45lb;
+drop table am103;
+drop table device_data_latest;
+drop table device_data_2025;
+drop table sensors;
+drop table device_type_tb;
+
+alter table device drop column tenant_id;
+alter table device drop column device_type;
+alter table device drop column device_profile_name;
+alter table device drop column humadity_calibration;
+alter table device drop column temperature_calibration;
+alter table device drop column data_time;
+
+alter table device_profile drop column relay_params;
+alter table device_profile drop column class_c_params;
+alter table device_profile drop column class_b_params;
+alter table device_profile drop column abp_params;
+
+alter table tenant_user drop column is_visible;
+
+alter table tenant drop column kitchen_management_license;
+alter table tenant drop column pro_license;
+alter table tenant drop column license;
+alter table tenant drop column sms_count;
+
+alter table "user" drop column site_id_list;
+alter table "user" drop column web_key;
+alter table "user" drop column expo_key;
+alter table "user" drop column training;
+alter table "user" drop column zone_id_list;
+alter table "user" drop column username;
+alter table "user" drop column name;
+alter table "user" drop column phone_number;
+alter table "user" drop column android_key;
@@ -0,0 +1,483 @@
+-- The business tables below are created out-of-band on PostgreSQL. Arrays are
+-- stored as JSON encoded text, numeric values as real.
+
+-- user
+alter table "user" add column android_key varchar(250) null;
+alter table "user" add column phone_number varchar(50) not null default '';
+alter table "user" add column name text null;
+alter table "user" add column username text null;
+alter table "user" add column zone_id_list text null;
+alter table "user" add column training boolean not null default FALSE;
+alter table "user" add column expo_key varchar(250) null;
+alter table "user" add column web_key varchar(250) null;
+alter table "user" add column site_id_list text null;
+
+-- tenant
+alter table tenant add column sms_count integer null;
+alter table tenant add column license boolean null;
+alter table tenant add column pro_license boolean null;
+alter table tenant add column kitchen_management_license boolean null;
+
+-- tenant user
+alter table tenant_user add column is_visible boolean null default TRUE;
+
+-- device
+alter table device add column data_time integer null;
+alter table device add column temperature_calibration real null;
+alter table device add column humadity_calibration real null;
+alter table device add column device_profile_name varchar(100) null;
+alter table device add column device_type integer null;
+alter table device add column tenant_id text null;
+
+-- device profile
+alter table device_profile add column abp_params text null;
+alter table device_profile add column class_b_params text null;
+alter table device_profile add column class_c_params text null;
+alter table device_profile add column relay_params text null;
+
+-- device types
+create table device_type_tb (
+    id integer primary key,
+    device_profile_name text not null,
+    application_name text not null
+);
+
+create table sensors (
+    id integer not null,
+    sn varchar(50) null,
+    dev_eui varchar(50) not null primary key,
+    app_eui varchar(50) null,
+    app_key varchar(50) null,
+    dev_addr varchar(50) null,
+    netskey varchar(50) null,
+    appskey varchar(50) null
+);
+
+-- sensor data
+create table device_data_2025 (
+    id integer primary key,
+    dev_eui text not null,
+    device_type_id integer not null,
+    air_temperature real null,
+    air_humidity real null,
+    sol_temperature real null,
+    sol_water real null,
+    sol_conduct_soil real null,
+    submission_date datetime null default current_timestamp,
+    water_leak_status integer null,
+    water_leak_times integer null,
+    last_water_leak_duration integer null,
+    door_open_status integer null,
+    door_open_times integer null,
+    last_door_open_duration integer null,
+    batv real null,
+    ro1_status integer null,
+    ro2_status integer null,
+    ph_soil real null,
+    co2_ppm real null,
+    tvoc_ppm real null,
+    sensecap_light real null,
+    barometric_pressure real null,
+    status integer null,
+    current real null,
+    factor real null,
+    power real null,
+    power_sum real null,
+    voltage real null
+);
+
+create index idx_device_data_2025_dev_eui_submission_date on device_data_2025(dev_eui, submission_date);
+
+create table device_data_latest (
+    id integer not null default 0,
+    dev_eui text not null primary key,
+    device_type_id integer not null,
+    org_id integer not null default 0,
+    air_temperature real null,
+    air_humidity real null,
+    sol_temperature real null,
+    sol_water real null,
+    sol_conduct_soil real null,
+    submission_date datetime null default current_timestamp,
+    water_leak_status integer null,
+    water_leak_times integer null,
+    last_water_leak_duration integer null,
+    door_open_status integer null,
+    door_open_times integer null,
+    last_door_open_duration integer null,
+    batv real null,
+    ro1_status integer null,
+    ro2_status integer null,
+    ph_soil real null,
+    co2_ppm real null,
+    tvoc_ppm real null,
+    sensecap_light real null,
+    barometric_pressure real null,
+    current real null,
+    factor real null,
+    power real null,
+    voltage real null,
+    power_sum real null,
+    status integer null,
+    power_consumption integer null,
+    switch1 integer null,
+    switch2 integer null,
+    switch3 integer null,
+    switch4 integer null,
+    switch5 integer null,
+    switch6 integer null,
+    switch7 integer null,
+    switch8 integer null,
+    adc_1 varchar(50) null,
+    adc_2 varchar(50) null,
+    adv_1 varchar(50) null,
+    gpio_in_1 varchar(50) null,
+    gpio_in_2 varchar(50) null,
+    gpio_in_3 varchar(50) null,
+    gpio_in_4 varchar(50) null,
+    gpio_out_1 varchar(50) null,
+    gpio_out_2 varchar(50) null,
+    distance integer null,
+    position varchar(20) null,
+    temperature1 real null,
+    temperature2 real null
+);
+
+create table am103 (
+    id integer primary key,
+    dev_eui varchar(20) not null,
+    device_type_id integer not null,
+    org_id integer not null,
+    submission_date datetime null default current_timestamp,
+    air_temperature real null,
+    air_humidity real null,
+    co2_ppm real null,
+    batv integer null
+);
+
+create table dds45lb (
+    id integer primary key,
+    dev_eui varchar(45) not null,
+    device_type_id integer not null,
+    org_id integer not null,
+    submission_date datetime null default current_timestamp,
+    distance integer null,
+    batv real null
+);
+
+create table em400mud (
+    id integer primary key,
+    dev_eui varchar(20) not null,
+    device_type_id integer not null,
+    org_id integer not null,
+    submission_date datetime null default current_timestamp,
+    distance integer null,
+    position varchar(20) null,
+    air_temperature real null,
+    batv integer null
+);
+
+create table ltc2lb (
+    id integer primary key,
+    dev_eui varchar(255) not null,
+    temperature1 real null,
+    temperature2 real null,
+    batv real null,
+    org_id integer null,
+    device_type_id integer null,
+    submission_date datetime null default current_timestamp
+);
+
+create table uc300 (
+    id integer primary key,
+    dev_eui varchar(40) not null,
+    device_type_id integer null,
+    org_id integer null,
+    adc_1 varchar(20) null,
+    adc_2 varchar(20) null,
+    adv_1 varchar(20) null,
+    gpio_in_1 varchar(20) null,
+    gpio_in_2 varchar(20) null,
+    gpio_in_3 varchar(20) null,
+    gpio_in_4 varchar(20) null,
+    gpio_out_1 varchar(20) null,
+    gpio_out_2 varchar(20) null,
+    submission_date datetime null default current_timestamp
+);
+
+-- site and zone
+create table site (
+    site_id integer primary key,
+    tenant_id text not null references tenant on delete cascade,
+    site_name varchar(100) not null,
+    address text not null default '',
+    latitude double precision null,
+    longitude double precision null,
+    timezone varchar(64) not null default 'UTC',
+    site_order bigint not null default 0,
+    created_at datetime not null,
+    updated_at datetime not null
+);
+
+create index idx_site_tenant_id on site(tenant_id);
+
+create table zone (
+    zone_id integer primary key,
+    zone_name varchar(100) null,
+    zone_order bigint null,
+    content_type bigint null,
+    tanent_id text null,
+    devices text not null default '[]',
+    site_id integer null references site on delete set null
+);
+
+create index idx_zone_site_id on zone(site_id);
+
+-- alarm
+create table alarm (
+    id integer primary key,
+    dev_eui varchar(30) not null,
+    min_treshold double precision null,
+    max_treshold double precision null,
+    sms boolean null,
+    email boolean null,
+    temperature boolean null,
+    humadity boolean null,
+    ec boolean null,
+    door boolean null,
+    w_leak boolean null,
+    is_time_limit_active boolean null,
+    alarm_start_time double precision null,
+    alarm_stop_time double precision null,
+    zone_category integer null,
+    notification boolean null,
+    is_active boolean null default TRUE,
+    pressure boolean null,
+    notification_sound varchar(50) null,
+    distance boolean null,
+    defrost_time integer null,
+    user_id text not null default '[]',
+    no_data boolean null default FALSE,
+    no_data_time integer null default 0,
+    no_data_triggered_at datetime null,
+    rule_type integer not null default 0,
+    rule_duration integer null,
+    rule_limit double precision null
+);
+
+create index idx_alarm_dev_eui on alarm(dev_eui);
+
+create table alarm_date_time (
+    id integer primary key,
+    alarm_id integer not null references alarm on delete cascade,
+    alarm_day integer not null,
+    start_time double precision not null,
+    end_time double precision not null
+);
+
+create index idx_alarm_date_time_alarm_id on alarm_date_time(alarm_id);
+
+create table alarm_audit_log (
+    id integer primary key,
+    alarm_id integer not null,
+    dev_eui varchar(30) null,
+    change_type varchar(10) null,
+    changed_at datetime null default current_timestamp,
+    old_values text null,
+    new_values text null,
+    changed_by text null
+);
+
+create index idx_alarm_audit_log_alarm_id on alarm_audit_log(alarm_id);
+
+create table alarm_automation_rules (
+    id integer primary key,
+    alarm_id integer not null references alarm on delete cascade,
+    receiver_sensor varchar(50) not null,
+    action varchar(255) null,
+    created_at datetime null default current_timestamp,
+    updated_at datetime null default current_timestamp,
+    is_active boolean null default TRUE,
+    receiver_device_type integer null,
+    receiver_device_name text null,
+    user_id text null
+);
+
+create table door_time_alarm (
+    id integer primary key,
+    dev_eui varchar(30) null,
+    sms boolean null,
+    email boolean null,
+    notification boolean null,
+    submission_time datetime null default current_timestamp,
+    is_active boolean null default TRUE,
+    time bigint null,
+    user_id text null,
+    tenant_id text null
+);
+
+create table door_alarm_date_time (
+    id integer primary key,
+    alarm_id integer not null references door_time_alarm on delete cascade,
+    alarm_day integer not null,
+    start_time double precision not null,
+    end_time double precision not null
+);
+
+-- automation
+create table automation_rules (
+    id integer primary key,
+    sender_sensor varchar(50) null,
+    receiver_sensor varchar(50) null,
+    condition varchar(255) null,
+    action varchar(255) null,
+    created_at datetime null default current_timestamp,
+    updated_at datetime null default current_timestamp,
+    is_active boolean null default TRUE,
+    sender_device_type integer null,
+    receiver_device_type integer null,
+    sender_device_name text null,
+    receiver_device_name text null,
+    trigger_type varchar(50) null,
+    trigger_time datetime null,
+    tenant_id text null,
+    user_id text null
+);
+
+-- notifications
+create table notifications (
+    id integer primary key,
+    sender_id integer not null,
+    message varchar(255) not null,
+    category_id integer not null,
+    is_read boolean null default FALSE,
+    send_time datetime null,
+    read_time datetime null,
+    sender_ip varchar(50) null,
+    reader_ip varchar(50) null,
+    is_deleted boolean null default FALSE,
+    deleted_time datetime null,
+    dev_eui varchar(30) null,
+    device_name varchar(80) null,
+    receiver_id text not null default '[]'
+);
+
+-- battery
+create table device_battery (
+    dev_eui varchar(30) primary key,
+    device_type_id integer null,
+    voltage double precision null,
+    battery_level real null,
+    discharge_per_day real null,
+    days_remaining integer null,
+    low_battery_notified_at datetime null,
+    updated_at datetime not null
+);
+
+-- defrost
+create table defrost_schedule (
+    id integer primary key,
+    zone_id integer null,
+    dev_eui varchar(30) null,
+    start_time double precision not null,
+    duration integer not null,
+    learned boolean not null default FALSE,
+    created_at datetime not null,
+    updated_at datetime not null,
+    check (zone_id is not null or dev_eui is not null)
+);
+
+create index idx_defrost_schedule_zone_id on defrost_schedule(zone_id);
+create index idx_defrost_schedule_dev_eui on defrost_schedule(dev_eui);
+
+create table defrost_cycle (
+    id integer primary key,
+    dev_eui varchar(30) not null,
+    schedule_id integer null references defrost_schedule on delete set null,
+    window_start datetime not null,
+    started_at datetime null,
+    ended_at datetime null,
+    peak_temperature real null,
+    status integer not null,
+    created_at datetime not null
+);
+
+create unique index idx_defrost_cycle_dev_eui_schedule_id_window_start on defrost_cycle(dev_eui, schedule_id, window_start);
+create index idx_defrost_cycle_created_at on defrost_cycle(created_at);
+
+-- escalation
+create table escalation_policy (
+    id integer primary key,
+    tenant_id text not null references tenant on delete cascade,
+    name varchar(100) not null,
+    created_at datetime not null,
+    updated_at datetime not null
+);
+
+create index idx_escalation_policy_tenant_id on escalation_policy(tenant_id);
+
+create table escalation_step (
+    id integer primary key,
+    policy_id integer not null references escalation_policy on delete cascade,
+    level integer not null,
+    delay_minutes integer not null,
+    channel varchar(20) not null,
+    user_id text not null default '[]',
+    webhook_url text not null default '',
+    unique (policy_id, level)
+);
+
+create table escalation_policy_target (
+    id integer primary key,
+    policy_id integer not null references escalation_policy on delete cascade,
+    alarm_id integer null references alarm on delete cascade,
+    zone_id integer null references zone on delete cascade,
+    check ((alarm_id is null) <> (zone_id is null))
+);
+
+create unique index idx_escalation_policy_target_alarm_id on escalation_policy_target(alarm_id);
+create unique index idx_escalation_policy_target_zone_id on escalation_policy_target(zone_id);
+
+create table alarm_escalation (
+    id integer primary key,
+    alarm_id integer not null references alarm on delete cascade,
+    policy_id integer not null references escalation_policy on delete cascade,
+    dev_eui varchar(30) not null,
+    message text not null,
+    level integer not null default 0,
+    triggered_at datetime not null,
+    next_at datetime null,
+    acknowledged_at datetime null,
+    acknowledged_by text null
+);
+
+-- At most one escalation in progress per alarm.
+create unique index idx_alarm_escalation_alarm_id_open on alarm_escalation(alarm_id) where next_at is not null and acknowledged_at is null;
+create index idx_alarm_escalation_next_at on alarm_escalation(next_at);
+
+-- maintenance
+create table maintenance_window (
+    id integer primary key,
+    tenant_id text not null references tenant on delete cascade,
+    dev_eui varchar(30) null,
+    zone_id integer null references zone on delete cascade,
+    site_id integer null references site on delete cascade,
+    start_at datetime not null,
+    end_at datetime not null,
+    reason text not null default '',
+    created_by text null,
+    created_at datetime not null,
+    check ((dev_eui is not null) + (zone_id is not null) + (site_id is not null) = 1),
+    check (end_at > start_at)
+);
+
+create index idx_maintenance_window_tenant_id on maintenance_window(tenant_id);
+create index idx_maintenance_window_end_at on maintenance_window(end_at);
+
+create table alarm_snooze (
+    alarm_id integer not null references alarm on delete cascade,
+    user_id text not null,
+    snoozed_until datetime not null,
+    created_at datetime not null,
+    primary key (alarm_id, user_id)
+);
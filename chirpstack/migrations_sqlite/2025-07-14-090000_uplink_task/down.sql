@@ -0,0 +1 @@
+drop table uplink_task;
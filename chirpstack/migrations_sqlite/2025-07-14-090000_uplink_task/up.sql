@@ -0,0 +1,14 @@
+create table uplink_task (
+    id integer primary key,
+    dev_eui blob not null references device on delete cascade,
+    kind varchar(20) not null,
+    object text not null,
+    created_at datetime not null,
+    run_after datetime not null,
+    locked_until datetime null,
+    attempts integer not null default 0,
+    last_error text null
+);
+
+create index idx_uplink_task_dev_eui_id on uplink_task(dev_eui, id);
+create index idx_uplink_task_run_after on uplink_task(run_after);
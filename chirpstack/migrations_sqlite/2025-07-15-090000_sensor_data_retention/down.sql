@@ -0,0 +1,9 @@
+drop table device_data_daily;
+drop table device_data_hourly;
+
+alter table device_data rename to device_data_2025;
+drop index idx_device_data_dev_eui_submission_date;
+create index idx_device_data_2025_dev_eui_submission_date on device_data_2025(dev_eui, submission_date);
+
+alter table tenant drop column hourly_data_retention_months;
+alter table tenant drop column raw_data_retention_days;
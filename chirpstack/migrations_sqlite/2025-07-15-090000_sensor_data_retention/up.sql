@@ -0,0 +1,67 @@
+alter table tenant add column raw_data_retention_days integer not null default 0;
+alter table tenant add column hourly_data_retention_months integer not null default 0;
+
+-- SQLite does not support partitioning, the readings are stored in a single
+-- device_data table.
+create table device_data (
+    id integer primary key,
+    dev_eui text not null,
+    device_type_id integer not null,
+    air_temperature real null,
+    air_humidity real null,
+    sol_temperature real null,
+    sol_water real null,
+    sol_conduct_soil real null,
+    submission_date datetime not null default current_timestamp,
+    water_leak_status integer null,
+    water_leak_times integer null,
+    last_water_leak_duration integer null,
+    door_open_status integer null,
+    door_open_times integer null,
+    last_door_open_duration integer null,
+    batv real null,
+    ro1_status integer null,
+    ro2_status integer null,
+    ph_soil real null,
+    co2_ppm real null,
+    tvoc_ppm real null,
+    sensecap_light real null,
+    barometric_pressure real null,
+    status integer null,
+    current real null,
+    factor real null,
+    power real null,
+    power_sum real null,
+    voltage real null
+);
+
+insert into device_data select * from device_data_2025 where submission_date is not null;
+drop table device_data_2025;
+
+create index idx_device_data_dev_eui_submission_date on device_data(dev_eui, submission_date);
+
+create table device_data_hourly (
+    dev_eui text not null,
+    metric varchar(50) not null,
+    bucket datetime not null,
+    min_value double not null,
+    max_value double not null,
+    avg_value double not null,
+    sample_count integer not null,
+    primary key (dev_eui, metric, bucket)
+);
+
+create index idx_device_data_hourly_bucket on device_data_hourly(bucket);
+
+create table device_data_daily (
+    dev_eui text not null,
+    metric varchar(50) not null,
+    bucket datetime not null,
+    min_value double not null,
+    max_value double not null,
+    avg_value double not null,
+    sample_count integer not null,
+    primary key (dev_eui, metric, bucket)
+);
+
+create index idx_device_data_daily_bucket on device_data_daily(bucket);
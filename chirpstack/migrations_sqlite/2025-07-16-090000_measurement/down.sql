@@ -0,0 +1,61 @@
+create table device_data_latest_new (
+    id integer not null default 0,
+    dev_eui text not null primary key,
+    device_type_id integer not null,
+    org_id integer not null default 0,
+    air_temperature real null,
+    air_humidity real null,
+    sol_temperature real null,
+    sol_water real null,
+    sol_conduct_soil real null,
+    submission_date datetime null default current_timestamp,
+    water_leak_status integer null,
+    water_leak_times integer null,
+    last_water_leak_duration integer null,
+    door_open_status integer null,
+    door_open_times integer null,
+    last_door_open_duration integer null,
+    batv real null,
+    ro1_status integer null,
+    ro2_status integer null,
+    ph_soil real null,
+    co2_ppm real null,
+    tvoc_ppm real null,
+    sensecap_light real null,
+    barometric_pressure real null,
+    current real null,
+    factor real null,
+    power real null,
+    voltage real null,
+    power_sum real null,
+    status integer null,
+    power_consumption integer null,
+    switch1 integer null,
+    switch2 integer null,
+    switch3 integer null,
+    switch4 integer null,
+    switch5 integer null,
+    switch6 integer null,
+    switch7 integer null,
+    switch8 integer null,
+    adc_1 varchar(50) null,
+    adc_2 varchar(50) null,
+    adv_1 varchar(50) null,
+    gpio_in_1 varchar(50) null,
+    gpio_in_2 varchar(50) null,
+    gpio_in_3 varchar(50) null,
+    gpio_in_4 varchar(50) null,
+    gpio_out_1 varchar(50) null,
+    gpio_out_2 varchar(50) null,
+    distance integer null,
+    position varchar(20) null,
+    temperature1 real null,
+    temperature2 real null
+);
+
+insert into device_data_latest_new select * from device_data_latest;
+
+drop view device_data_latest;
+alter table device_data_latest_new rename to device_data_latest;
+drop table measurement_latest;
+drop table measurement;
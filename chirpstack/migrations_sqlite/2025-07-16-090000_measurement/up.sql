@@ -0,0 +1,752 @@
+-- Normalised sensor readings, one row per device, measurement kind and
+-- timestamp. The kind matches the device-profile measurement key, or the
+-- legacy device_data column name for the built-in device types.
+create table measurement (
+    dev_eui blob not null references device on delete cascade,
+    kind varchar(100) not null,
+    time datetime not null,
+    unit varchar(20) not null default '',
+    value double null,
+    text_value varchar(100) null,
+    primary key (dev_eui, kind, time)
+);
+
+create index idx_measurement_time on measurement(time);
+
+-- Latest value of each measurement kind of each device.
+create table measurement_latest (
+    dev_eui blob not null references device on delete cascade,
+    kind varchar(100) not null,
+    time datetime not null,
+    unit varchar(20) not null default '',
+    value double null,
+    text_value varchar(100) null,
+    primary key (dev_eui, kind)
+);
+
+-- Copy the latest values of the wide device_data_latest table.
+insert into measurement_latest (dev_eui, kind, time, unit, value, text_value)
+select
+    d.dev_eui,
+    'air_temperature',
+    coalesce(l.submission_date, current_timestamp),
+    '°C',
+    cast(l.air_temperature as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.air_temperature is not null
+union all
+select
+    d.dev_eui,
+    'air_humidity',
+    coalesce(l.submission_date, current_timestamp),
+    '%',
+    cast(l.air_humidity as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.air_humidity is not null
+union all
+select
+    d.dev_eui,
+    'sol_temperature',
+    coalesce(l.submission_date, current_timestamp),
+    '°C',
+    cast(l.sol_temperature as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.sol_temperature is not null
+union all
+select
+    d.dev_eui,
+    'sol_water',
+    coalesce(l.submission_date, current_timestamp),
+    '%',
+    cast(l.sol_water as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.sol_water is not null
+union all
+select
+    d.dev_eui,
+    'sol_conduct_soil',
+    coalesce(l.submission_date, current_timestamp),
+    'uS/cm',
+    cast(l.sol_conduct_soil as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.sol_conduct_soil is not null
+union all
+select
+    d.dev_eui,
+    'water_leak_status',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.water_leak_status as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.water_leak_status is not null
+union all
+select
+    d.dev_eui,
+    'water_leak_times',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.water_leak_times as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.water_leak_times is not null
+union all
+select
+    d.dev_eui,
+    'last_water_leak_duration',
+    coalesce(l.submission_date, current_timestamp),
+    'min',
+    cast(l.last_water_leak_duration as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.last_water_leak_duration is not null
+union all
+select
+    d.dev_eui,
+    'door_open_status',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.door_open_status as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.door_open_status is not null
+union all
+select
+    d.dev_eui,
+    'door_open_times',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.door_open_times as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.door_open_times is not null
+union all
+select
+    d.dev_eui,
+    'last_door_open_duration',
+    coalesce(l.submission_date, current_timestamp),
+    'min',
+    cast(l.last_door_open_duration as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.last_door_open_duration is not null
+union all
+select
+    d.dev_eui,
+    'batv',
+    coalesce(l.submission_date, current_timestamp),
+    'V',
+    cast(l.batv as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.batv is not null
+union all
+select
+    d.dev_eui,
+    'ro1_status',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.ro1_status as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.ro1_status is not null
+union all
+select
+    d.dev_eui,
+    'ro2_status',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.ro2_status as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.ro2_status is not null
+union all
+select
+    d.dev_eui,
+    'ph_soil',
+    coalesce(l.submission_date, current_timestamp),
+    'pH',
+    cast(l.ph_soil as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.ph_soil is not null
+union all
+select
+    d.dev_eui,
+    'co2_ppm',
+    coalesce(l.submission_date, current_timestamp),
+    'ppm',
+    cast(l.co2_ppm as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.co2_ppm is not null
+union all
+select
+    d.dev_eui,
+    'tvoc_ppm',
+    coalesce(l.submission_date, current_timestamp),
+    'ppm',
+    cast(l.tvoc_ppm as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.tvoc_ppm is not null
+union all
+select
+    d.dev_eui,
+    'sensecap_light',
+    coalesce(l.submission_date, current_timestamp),
+    'lux',
+    cast(l.sensecap_light as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.sensecap_light is not null
+union all
+select
+    d.dev_eui,
+    'barometric_pressure',
+    coalesce(l.submission_date, current_timestamp),
+    'hPa',
+    cast(l.barometric_pressure as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.barometric_pressure is not null
+union all
+select
+    d.dev_eui,
+    'current',
+    coalesce(l.submission_date, current_timestamp),
+    'mA',
+    cast(l.current as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.current is not null
+union all
+select
+    d.dev_eui,
+    'factor',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.factor as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.factor is not null
+union all
+select
+    d.dev_eui,
+    'power',
+    coalesce(l.submission_date, current_timestamp),
+    'W',
+    cast(l.power as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.power is not null
+union all
+select
+    d.dev_eui,
+    'voltage',
+    coalesce(l.submission_date, current_timestamp),
+    'V',
+    cast(l.voltage as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.voltage is not null
+union all
+select
+    d.dev_eui,
+    'power_sum',
+    coalesce(l.submission_date, current_timestamp),
+    'Wh',
+    cast(l.power_sum as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.power_sum is not null
+union all
+select
+    d.dev_eui,
+    'status',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.status as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.status is not null
+union all
+select
+    d.dev_eui,
+    'power_consumption',
+    coalesce(l.submission_date, current_timestamp),
+    'Wh',
+    cast(l.power_consumption as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.power_consumption is not null
+union all
+select
+    d.dev_eui,
+    'switch1',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.switch1 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.switch1 is not null
+union all
+select
+    d.dev_eui,
+    'switch2',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.switch2 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.switch2 is not null
+union all
+select
+    d.dev_eui,
+    'switch3',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.switch3 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.switch3 is not null
+union all
+select
+    d.dev_eui,
+    'switch4',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.switch4 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.switch4 is not null
+union all
+select
+    d.dev_eui,
+    'switch5',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.switch5 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.switch5 is not null
+union all
+select
+    d.dev_eui,
+    'switch6',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.switch6 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.switch6 is not null
+union all
+select
+    d.dev_eui,
+    'switch7',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.switch7 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.switch7 is not null
+union all
+select
+    d.dev_eui,
+    'switch8',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    cast(l.switch8 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.switch8 is not null
+union all
+select
+    d.dev_eui,
+    'adc_1',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.adc_1
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.adc_1 is not null
+union all
+select
+    d.dev_eui,
+    'adc_2',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.adc_2
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.adc_2 is not null
+union all
+select
+    d.dev_eui,
+    'adv_1',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.adv_1
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.adv_1 is not null
+union all
+select
+    d.dev_eui,
+    'gpio_in_1',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.gpio_in_1
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.gpio_in_1 is not null
+union all
+select
+    d.dev_eui,
+    'gpio_in_2',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.gpio_in_2
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.gpio_in_2 is not null
+union all
+select
+    d.dev_eui,
+    'gpio_in_3',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.gpio_in_3
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.gpio_in_3 is not null
+union all
+select
+    d.dev_eui,
+    'gpio_in_4',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.gpio_in_4
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.gpio_in_4 is not null
+union all
+select
+    d.dev_eui,
+    'gpio_out_1',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.gpio_out_1
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.gpio_out_1 is not null
+union all
+select
+    d.dev_eui,
+    'gpio_out_2',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.gpio_out_2
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.gpio_out_2 is not null
+union all
+select
+    d.dev_eui,
+    'distance',
+    coalesce(l.submission_date, current_timestamp),
+    'mm',
+    cast(l.distance as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.distance is not null
+union all
+select
+    d.dev_eui,
+    'position',
+    coalesce(l.submission_date, current_timestamp),
+    '',
+    null,
+    l.position
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.position is not null
+union all
+select
+    d.dev_eui,
+    'temperature1',
+    coalesce(l.submission_date, current_timestamp),
+    '°C',
+    cast(l.temperature1 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.temperature1 is not null
+union all
+select
+    d.dev_eui,
+    'temperature2',
+    coalesce(l.submission_date, current_timestamp),
+    '°C',
+    cast(l.temperature2 as double),
+    null
+from
+    device_data_latest l
+inner join device d
+    on lower(hex(d.dev_eui)) = lower(l.dev_eui)
+where
+    l.temperature2 is not null;
+
+-- The wide latest-value table is replaced by a view on the normalised
+-- measurements, such that adding a measurement kind does not require a new
+-- column.
+drop table device_data_latest;
+
+create view device_data_latest as
+select
+    0 as id,
+    lower(hex(d.dev_eui)) as dev_eui,
+    coalesce(d.device_type, 0) as device_type_id,
+    0 as org_id,
+    max(m.value) filter (where m.kind = 'air_temperature') as air_temperature,
+    max(m.value) filter (where m.kind = 'air_humidity') as air_humidity,
+    max(m.value) filter (where m.kind = 'sol_temperature') as sol_temperature,
+    max(m.value) filter (where m.kind = 'sol_water') as sol_water,
+    max(m.value) filter (where m.kind = 'sol_conduct_soil') as sol_conduct_soil,
+    max(m.time) as submission_date,
+    cast(max(m.value) filter (where m.kind = 'water_leak_status') as integer) as water_leak_status,
+    cast(max(m.value) filter (where m.kind = 'water_leak_times') as integer) as water_leak_times,
+    cast(max(m.value) filter (where m.kind = 'last_water_leak_duration') as integer) as last_water_leak_duration,
+    cast(max(m.value) filter (where m.kind = 'door_open_status') as integer) as door_open_status,
+    cast(max(m.value) filter (where m.kind = 'door_open_times') as integer) as door_open_times,
+    cast(max(m.value) filter (where m.kind = 'last_door_open_duration') as integer) as last_door_open_duration,
+    max(m.value) filter (where m.kind = 'batv') as batv,
+    cast(max(m.value) filter (where m.kind = 'ro1_status') as integer) as ro1_status,
+    cast(max(m.value) filter (where m.kind = 'ro2_status') as integer) as ro2_status,
+    max(m.value) filter (where m.kind = 'ph_soil') as ph_soil,
+    max(m.value) filter (where m.kind = 'co2_ppm') as co2_ppm,
+    max(m.value) filter (where m.kind = 'tvoc_ppm') as tvoc_ppm,
+    max(m.value) filter (where m.kind = 'sensecap_light') as sensecap_light,
+    max(m.value) filter (where m.kind = 'barometric_pressure') as barometric_pressure,
+    max(m.value) filter (where m.kind = 'current') as current,
+    max(m.value) filter (where m.kind = 'factor') as factor,
+    max(m.value) filter (where m.kind = 'power') as power,
+    max(m.value) filter (where m.kind = 'voltage') as voltage,
+    max(m.value) filter (where m.kind = 'power_sum') as power_sum,
+    cast(max(m.value) filter (where m.kind = 'status') as integer) as status,
+    cast(max(m.value) filter (where m.kind = 'power_consumption') as integer) as power_consumption,
+    cast(max(m.value) filter (where m.kind = 'switch1') as integer) as switch1,
+    cast(max(m.value) filter (where m.kind = 'switch2') as integer) as switch2,
+    cast(max(m.value) filter (where m.kind = 'switch3') as integer) as switch3,
+    cast(max(m.value) filter (where m.kind = 'switch4') as integer) as switch4,
+    cast(max(m.value) filter (where m.kind = 'switch5') as integer) as switch5,
+    cast(max(m.value) filter (where m.kind = 'switch6') as integer) as switch6,
+    cast(max(m.value) filter (where m.kind = 'switch7') as integer) as switch7,
+    cast(max(m.value) filter (where m.kind = 'switch8') as integer) as switch8,
+    max(m.text_value) filter (where m.kind = 'adc_1') as adc_1,
+    max(m.text_value) filter (where m.kind = 'adc_2') as adc_2,
+    max(m.text_value) filter (where m.kind = 'adv_1') as adv_1,
+    max(m.text_value) filter (where m.kind = 'gpio_in_1') as gpio_in_1,
+    max(m.text_value) filter (where m.kind = 'gpio_in_2') as gpio_in_2,
+    max(m.text_value) filter (where m.kind = 'gpio_in_3') as gpio_in_3,
+    max(m.text_value) filter (where m.kind = 'gpio_in_4') as gpio_in_4,
+    max(m.text_value) filter (where m.kind = 'gpio_out_1') as gpio_out_1,
+    max(m.text_value) filter (where m.kind = 'gpio_out_2') as gpio_out_2,
+    cast(max(m.value) filter (where m.kind = 'distance') as integer) as distance,
+    max(m.text_value) filter (where m.kind = 'position') as position,
+    max(m.value) filter (where m.kind = 'temperature1') as temperature1,
+    max(m.value) filter (where m.kind = 'temperature2') as temperature2
+from
+    measurement_latest m
+inner join device d
+    on d.dev_eui = m.dev_eui
+group by
+    d.dev_eui,
+    d.device_type;
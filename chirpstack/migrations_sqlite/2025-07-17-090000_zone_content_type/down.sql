@@ -0,0 +1 @@
+drop table zone_content_type;
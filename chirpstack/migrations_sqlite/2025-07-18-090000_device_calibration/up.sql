@@ -0,0 +1,15 @@
+-- Calibration records of the device measurements. A record applies from
+-- effective_from until a record of the same device and kind with a later
+-- effective_from exists.
+create table device_calibration (
+    id integer primary key,
+    dev_eui blob not null references device on delete cascade,
+    kind varchar(100) not null,
+    "offset" double not null default 0,
+    gain double not null default 1,
+    points text not null default '[]',
+    effective_from datetime not null,
+    certificate varchar(255) not null,
+    created_at datetime not null,
+    unique (dev_eui, kind, effective_from)
+);
@@ -0,0 +1,2 @@
+drop table virtual_device_input;
+drop table virtual_device;
@@ -0,0 +1,2 @@
+drop table device_link;
+drop table device_link_sample;
@@ -0,0 +1,30 @@
+-- Link samples, one per uplink. The gateway is the gateway which received the
+-- uplink with the best SNR. The margin is the SNR above the demodulation floor
+-- of the spreading-factor and is null for non-LoRa modulations.
+create table device_link_sample (
+  dev_eui varchar(30) not null,
+  time datetime not null,
+  gateway_id varchar(30) not null,
+  rssi integer not null,
+  snr real not null,
+  margin real null,
+  gateway_count integer not null,
+  primary key (dev_eui, time)
+);
+
+create index idx_device_link_sample_time on device_link_sample(time);
+
+-- Link quality of the device, derived from the recent link samples.
+create table device_link (
+  dev_eui varchar(30) primary key,
+  gateway_id varchar(30) null,
+  rssi real null,
+  snr real null,
+  margin real null,
+  margin_baseline real null,
+  signal varchar(20) not null,
+  uplink_count integer not null,
+  last_seen_at datetime not null,
+  degraded_notified_at datetime null,
+  updated_at datetime not null
+);
@@ -0,0 +1,3 @@
+drop table alarm_state;
+drop table webhook_delivery;
+drop table webhook_subscription;
@@ -0,0 +1,53 @@
+-- Webhook subscription of a tenant to the alarm and automation events. The
+-- payloads are signed with the secret of the subscription.
+create table webhook_subscription (
+  id integer primary key,
+  tenant_id text not null references tenant on delete cascade,
+  name varchar(100) not null,
+  url text not null,
+  secret varchar(100) not null,
+  events text not null default '[]',
+  is_disabled boolean not null default false,
+  created_at datetime not null,
+  updated_at datetime not null
+);
+
+create index idx_webhook_subscription_tenant_id on webhook_subscription(tenant_id);
+
+-- Event deliveries, this is both the retry queue and the delivery log. Failed
+-- deliveries are retried until the max attempts, after which they are
+-- dead-lettered.
+create table webhook_delivery (
+  id integer primary key,
+  subscription_id integer not null references webhook_subscription on delete cascade,
+  event varchar(50) not null,
+  payload text not null,
+  status varchar(20) not null,
+  attempts integer not null default 0,
+  run_after datetime not null,
+  locked_until datetime null,
+  response_status integer null,
+  last_error text null,
+  created_at datetime not null,
+  delivered_at datetime null
+);
+
+create index idx_webhook_delivery_subscription_id_created_at on webhook_delivery(subscription_id, created_at);
+create index idx_webhook_delivery_run_after on webhook_delivery(run_after) where status = 'PENDING';
+
+-- Raised alarms, by alarm type. The state is removed when the alarm is
+-- cleared, such that the raised and cleared events are only sent on the
+-- transitions.
+create table alarm_state (
+  alarm_id integer not null references alarm on delete cascade,
+  alarm_type varchar(50) not null,
+  dev_eui varchar(30) not null,
+  value real null,
+  message text not null,
+  raised_at datetime not null,
+  acknowledged_at datetime null,
+  acknowledged_by text null,
+  primary key (alarm_id, alarm_type)
+);
+
+create index idx_alarm_state_dev_eui on alarm_state(dev_eui);
@@ -0,0 +1,27 @@
+create table alarm_state_new (
+  alarm_id integer not null references alarm on delete cascade,
+  alarm_type varchar(50) not null,
+  dev_eui varchar(30) not null,
+  value real null,
+  message text not null,
+  raised_at datetime not null,
+  acknowledged_at datetime null,
+  acknowledged_by text null,
+  primary key (alarm_id, alarm_type)
+);
+
+insert or ignore into alarm_state_new select * from alarm_state;
+drop table alarm_state;
+alter table alarm_state_new rename to alarm_state;
+
+create index idx_alarm_state_dev_eui on alarm_state(dev_eui);
+
+alter table alarm add column no_data_triggered_at datetime null;
+
+update alarm
+  set no_data_triggered_at = (
+    select t.triggered_at from alarm_no_data_trigger t
+    where t.alarm_id = alarm.id and t.dev_eui = lower(alarm.dev_eui)
+  );
+
+drop table alarm_no_data_trigger;
@@ -0,0 +1,33 @@
+create table alarm_no_data_trigger (
+  alarm_id integer not null references alarm on delete cascade,
+  dev_eui varchar(16) not null,
+  triggered_at datetime not null,
+  primary key (alarm_id, dev_eui)
+);
+
+create index idx_alarm_no_data_trigger_dev_eui on alarm_no_data_trigger(dev_eui);
+
+insert into alarm_no_data_trigger (alarm_id, dev_eui, triggered_at)
+  select id, lower(dev_eui), no_data_triggered_at
+  from alarm
+  where no_data_triggered_at is not null and dev_eui <> '';
+
+alter table alarm drop column no_data_triggered_at;
+
+create table alarm_state_new (
+  alarm_id integer not null references alarm on delete cascade,
+  alarm_type varchar(50) not null,
+  dev_eui varchar(30) not null,
+  value real null,
+  message text not null,
+  raised_at datetime not null,
+  acknowledged_at datetime null,
+  acknowledged_by text null,
+  primary key (alarm_id, alarm_type, dev_eui)
+);
+
+insert into alarm_state_new select * from alarm_state;
+drop table alarm_state;
+alter table alarm_state_new rename to alarm_state;
+
+create index idx_alarm_state_dev_eui on alarm_state(dev_eui);
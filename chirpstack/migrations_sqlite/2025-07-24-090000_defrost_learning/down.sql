@@ -0,0 +1 @@
+drop table defrost_learning;
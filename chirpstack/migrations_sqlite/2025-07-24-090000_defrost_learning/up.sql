@@ -0,0 +1,11 @@
+create table defrost_learning (
+  dev_eui varchar(30) primary key,
+  created_at datetime not null,
+  learned_at datetime not null
+);
+
+insert into defrost_learning (dev_eui, created_at, learned_at)
+  select dev_eui, min(created_at), max(updated_at)
+  from defrost_schedule
+  where learned = true and dev_eui is not null
+  group by dev_eui;
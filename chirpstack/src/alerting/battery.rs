@@ -0,0 +1,351 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{Local, Utc};
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
+
+use crate::config;
+use crate::storage::{alarm, battery, device, get_async_db_conn, notification};
+use lrwn::EUI64;
+
+// Battery level (%) by voltage (V) for the Li-SOCl2 (3.6V) cells used by the Dragino sensors.
+// The points must be ordered by voltage. The discharge curve of these cells is very flat, most
+// of the capacity is used between 3.3V and 3.5V.
+const LI_SOCL2_3V6: &[(f64, f32)] = &[
+    (3.0, 0.0),
+    (3.1, 10.0),
+    (3.3, 40.0),
+    (3.45, 80.0),
+    (3.6, 100.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Curve {
+    // The sensor reports the battery voltage.
+    Voltage(&'static [(f64, f32)]),
+    // The sensor reports the battery level (%).
+    Percentage,
+}
+
+// Returns the battery curve for the given device type (see write_data_from_object_json).
+pub fn get_curve(device_type: i32) -> Option<Curve> {
+    match device_type {
+        // LSN50v2, LSE01, LWL01, LHT65, LAQ4, LSPH01, LTC2LB, DDS45LB
+        1 | 2 | 4 | 7 | 8 | 9 | 36 | 37 => Some(Curve::Voltage(LI_SOCL2_3V6)),
+        // EM300-TH, AM107, EM300-MCS, EM500-PT100, EM500-PP, EM400-MUD, AM103
+        12 | 13 | 16 | 20 | 21 | 33 | 35 => Some(Curve::Percentage),
+        _ => None,
+    }
+}
+
+// Returns the battery level (%) for the stored battery value of the given device type.
+pub fn get_battery_level(device_type: i32, value: f64) -> Option<f32> {
+    match get_curve(device_type)? {
+        Curve::Percentage => Some((value as f32).clamp(0.0, 100.0)),
+        Curve::Voltage(points) => {
+            // Some parsers report the voltage in mV.
+            let voltage = if value > 100.0 { value / 1000.0 } else { value };
+            Some(interpolate(points, voltage))
+        }
+    }
+}
+
+fn interpolate(points: &[(f64, f32)], voltage: f64) -> f32 {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return 0.0,
+    };
+
+    if voltage <= first.0 {
+        return first.1;
+    }
+    if voltage >= last.0 {
+        return last.1;
+    }
+
+    for w in points.windows(2) {
+        let ((v0, p0), (v1, p1)) = (w[0], w[1]);
+        if voltage <= v1 {
+            return p0 + ((voltage - v0) / (v1 - v0)) as f32 * (p1 - p0);
+        }
+    }
+
+    last.1
+}
+
+// Estimates the discharge (% per day) and the expected number of days remaining using a
+// least-squares fit of the battery level over time. The samples are (day, level) tuples. It
+// returns None when there are not enough samples or when the level is not decreasing.
+pub fn estimate_discharge(samples: &[(f64, f32)]) -> Option<(f32, i32)> {
+    if samples.len() < 3 {
+        return None;
+    }
+
+    let n = samples.len() as f64;
+    let mean_x = samples.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|(_, y)| *y as f64).sum::<f64>() / n;
+
+    let mut num = 0.0;
+    let mut den = 0.0;
+    for (x, y) in samples {
+        num += (x - mean_x) * (*y as f64 - mean_y);
+        den += (x - mean_x).powi(2);
+    }
+
+    if den == 0.0 {
+        return None;
+    }
+
+    let slope = num / den;
+    if slope >= 0.0 {
+        return None;
+    }
+
+    let discharge = -slope;
+    let level = samples.last().map(|(_, y)| *y as f64)?;
+    Some((discharge as f32, (level / discharge).floor() as i32))
+}
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.alarm.battery_check_interval.is_zero() {
+        info!("Battery tracking is disabled");
+        return;
+    }
+
+    info!("Setting up battery tracking loop");
+    tokio::spawn(async move {
+        battery_loop().await;
+    });
+}
+
+pub async fn battery_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting battery tracking loop run");
+
+        if let Err(err) = update_battery_states().await {
+            error!(error = %err, "Updating battery states failed");
+        } else {
+            trace!("Battery tracking loop run completed successfully");
+        }
+
+        sleep(conf.alarm.battery_check_interval).await;
+    }
+}
+
+pub async fn update_battery_states() -> Result<()> {
+    let conf = config::get();
+    let now = Utc::now();
+
+    let mut samples: BTreeMap<String, Vec<battery::BatterySample>> = BTreeMap::new();
+    for s in battery::get_samples(conf.alarm.battery_trend_days as i32).await? {
+        samples.entry(s.dev_eui.clone()).or_default().push(s);
+    }
+
+    let mut states: Vec<battery::DeviceBattery> = Vec::new();
+
+    for (dev_eui, samples) in &samples {
+        let device_type = match samples.last() {
+            Some(v) => v.device_type_id,
+            None => continue,
+        };
+
+        let levels: Vec<(f64, f32)> = samples
+            .iter()
+            .filter_map(|s| {
+                let day = (s.day - now.naive_utc()).num_hours() as f64 / 24.0;
+                get_battery_level(device_type, s.batv).map(|level| (day, level))
+            })
+            .collect();
+
+        let level = match levels.last() {
+            Some((_, level)) => *level,
+            None => continue,
+        };
+        let discharge = estimate_discharge(&levels);
+
+        states.push(battery::DeviceBattery {
+            dev_eui: dev_eui.clone(),
+            device_type_id: Some(device_type),
+            voltage: match get_curve(device_type) {
+                Some(Curve::Voltage(_)) => samples.last().map(|s| s.batv),
+                _ => None,
+            },
+            battery_level: Some(level),
+            discharge_per_day: discharge.map(|(d, _)| d),
+            days_remaining: discharge.map(|(_, d)| d),
+            updated_at: now,
+            ..Default::default()
+        });
+    }
+
+    // Devices for which no battery value is stored by the model parsers, fall back to the
+    // battery level reported through DevStatusAns.
+    for s in battery::get_device_status_levels().await? {
+        if samples.contains_key(&s.dev_eui) {
+            continue;
+        }
+
+        states.push(battery::DeviceBattery {
+            dev_eui: s.dev_eui,
+            device_type_id: s.device_type_id,
+            battery_level: Some(s.battery_level as f32),
+            updated_at: now,
+            ..Default::default()
+        });
+    }
+
+    trace!(
+        device_count = states.len(),
+        "Got battery states for this number of devices"
+    );
+
+    for state in states {
+        let state = battery::upsert(&state).await?;
+        let notified = state.low_battery_notified_at.is_some();
+
+        let low = state.battery_level.unwrap_or(100.0) <= conf.alarm.low_battery_level
+            || state.days_remaining.unwrap_or(i32::MAX) <= conf.alarm.low_battery_days as i32;
+
+        if low && !notified {
+            warn!(dev_eui = %state.dev_eui, battery_level = ?state.battery_level, days_remaining = ?state.days_remaining, "Low battery");
+
+            if let Err(e) = notify_low_battery(&state).await {
+                error!(dev_eui = %state.dev_eui, error = %e, "Notifying low battery failed");
+                continue;
+            }
+            battery::set_low_battery_notified(&state.dev_eui, Some(now)).await?;
+        } else if !low && notified {
+            // The battery has been replaced.
+            info!(dev_eui = %state.dev_eui, battery_level = ?state.battery_level, "Battery level recovered");
+            battery::set_low_battery_notified(&state.dev_eui, None).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn notify_low_battery(state: &battery::DeviceBattery) -> Result<()> {
+    let user_ids = battery::get_zone_user_ids(&state.dev_eui).await?;
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let dev = device::get(&EUI64::from_str(&state.dev_eui)?).await?;
+    let mut db_conn = get_async_db_conn().await?;
+    let zone_name = alarm::get_zone_name_by_dev_eui(db_conn.as_mut(), &state.dev_eui)
+        .await?
+        .unwrap_or_else(|| "Bilinmeyen Alan".to_string());
+
+    let mut message = format!(
+        "{} ortamındaki {} isimli sensörün pil seviyesi düşük: %{:.0}.",
+        zone_name,
+        dev.name,
+        state.battery_level.unwrap_or_default()
+    );
+    if let Some(days) = state.days_remaining {
+        message.push_str(&format!(" Tahmini kalan süre: {} gün.", days));
+    }
+
+    notification::create_notification(notification::Notification {
+        id: 0,
+        sender_id: 0,
+        receiver_id: user_ids.into_iter().map(Some).collect(),
+        message,
+        category_id: notification::CATEGORY_BATTERY,
+        is_read: Some(false),
+        send_time: Some(Local::now().naive_local()),
+        read_time: None,
+        sender_ip: Some("System".to_string()),
+        reader_ip: Some("".to_string()),
+        is_deleted: Some(false),
+        deleted_time: None,
+        dev_eui: Some(state.dev_eui.clone()),
+        device_name: Some(dev.name.clone()),
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_battery_level() {
+        struct Test {
+            device_type: i32,
+            value: f64,
+            expected: Option<f32>,
+        }
+
+        let tests = vec![
+            Test {
+                device_type: 1,
+                value: 3.6,
+                expected: Some(100.0),
+            },
+            Test {
+                device_type: 1,
+                value: 3.7,
+                expected: Some(100.0),
+            },
+            Test {
+                device_type: 1,
+                value: 3.2,
+                expected: Some(25.0),
+            },
+            Test {
+                device_type: 7,
+                value: 3200.0,
+                expected: Some(25.0),
+            },
+            Test {
+                device_type: 2,
+                value: 2.8,
+                expected: Some(0.0),
+            },
+            Test {
+                device_type: 12,
+                value: 85.0,
+                expected: Some(85.0),
+            },
+            Test {
+                device_type: 6,
+                value: 3.6,
+                expected: None,
+            },
+        ];
+
+        for tst in &tests {
+            let level = get_battery_level(tst.device_type, tst.value);
+            match (level, tst.expected) {
+                (Some(level), Some(expected)) => assert!((level - expected).abs() < 0.01),
+                (level, expected) => assert_eq!(expected, level),
+            }
+        }
+    }
+
+    #[test]
+    fn test_estimate_discharge() {
+        // Not enough samples.
+        assert_eq!(None, estimate_discharge(&[(0.0, 50.0), (1.0, 49.0)]));
+
+        // Not decreasing.
+        assert_eq!(
+            None,
+            estimate_discharge(&[(0.0, 50.0), (1.0, 50.0), (2.0, 51.0)])
+        );
+
+        // 2% per day, 40% left.
+        assert_eq!(
+            Some((2.0, 20)),
+            estimate_discharge(&[(-2.0, 44.0), (-1.0, 42.0), (0.0, 40.0)])
+        );
+    }
+}
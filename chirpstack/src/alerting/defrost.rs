@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{Duration, Local, NaiveDateTime, NaiveTime, Timelike, Utc};
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
+
+use crate::config;
+use crate::storage::{
+    alarm, alarm_rule, battery, defrost, device, get_async_db_conn, notification,
+};
+use lrwn::EUI64;
+
+// Temperature history (before the window) used to determine the baseline temperature.
+const BASELINE_HOURS: i64 = 2;
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.alarm.defrost_check_interval.is_zero() {
+        info!("Defrost tracking is disabled");
+        return;
+    }
+
+    info!("Setting up defrost tracking loop");
+    tokio::spawn(async move {
+        defrost_loop().await;
+    });
+}
+
+pub async fn defrost_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting defrost tracking loop run");
+
+        if let Err(err) = refresh_learned_schedules().await {
+            error!(error = %err, "Refreshing learned defrost schedules failed");
+        }
+
+        if let Err(err) = evaluate_defrost_windows().await {
+            error!(error = %err, "Evaluating defrost windows failed");
+        } else {
+            trace!("Defrost tracking loop run completed successfully");
+        }
+
+        sleep(conf.alarm.defrost_check_interval).await;
+    }
+}
+
+// Learns the defrost schedule of the device from its temperature history and replaces the
+// previously learned schedules of the device.
+pub async fn learn(dev_eui: &EUI64) -> Result<Vec<defrost::DefrostSchedule>> {
+    let conf = config::get();
+    let dev = device::get(dev_eui).await?;
+    let dev_eui = dev_eui.to_string();
+
+    let column = match alarm_rule::get_history_column(dev.device_type, "temperature") {
+        Some(v) => v,
+        None => return Err(anyhow!("Device has no temperature history")),
+    };
+
+    let today = alarm::local_now().date();
+    let from = alarm::from_local(
+        (today - Duration::days(conf.alarm.defrost_learning_days as i64)).and_time(midnight()),
+    );
+    let to = alarm::from_local(today.and_time(midnight()));
+
+    let mut days: BTreeMap<_, Vec<(f64, f32)>> = BTreeMap::new();
+    for (ts, value) in defrost::get_samples(&dev_eui, column, from, to).await? {
+        let ts = alarm::to_local(ts);
+        let minute = (ts.hour() * 60 + ts.minute()) as f64 + ts.second() as f64 / 60.0;
+        days.entry(ts.date()).or_default().push((minute, value));
+    }
+    let days: Vec<Vec<(f64, f32)>> = days.into_values().collect();
+
+    let learned = defrost::learn_schedule(&days, conf.alarm.defrost_temperature_rise);
+    info!(dev_eui = %dev_eui, days = days.len(), cycles = learned.len(), "Learned defrost schedule");
+
+    Ok(defrost::replace_learned_schedules(&dev_eui, &learned).await?)
+}
+
+async fn refresh_learned_schedules() -> Result<()> {
+    for dev_eui in defrost::get_learned_dev_euis(Utc::now() - Duration::days(1)).await? {
+        if let Err(e) = learn(&EUI64::from_str(&dev_eui)?).await {
+            warn!(dev_eui = %dev_eui, error = %e, "Learning defrost schedule failed");
+        }
+    }
+
+    Ok(())
+}
+
+// Evaluates the last defrost window of each (device) schedule once the window has ended.
+async fn evaluate_defrost_windows() -> Result<()> {
+    let conf = config::get();
+    let now = alarm::local_now();
+    let tolerance = Duration::seconds(conf.alarm.defrost_tolerance.as_secs() as i64);
+
+    for s in defrost::get_device_schedules().await? {
+        let start = midnight() + Duration::seconds((s.start_time * 3600.0) as i64);
+        let mut window_start = now.date().and_time(start) - tolerance;
+        if window_start > now {
+            window_start -= Duration::days(1);
+        }
+        let window_end = window_start + Duration::minutes(s.duration as i64) + tolerance * 2;
+
+        if window_end > now || window_start < alarm::to_local(s.created_at) {
+            continue;
+        }
+
+        let window_start_utc = alarm::from_local(window_start);
+        if defrost::cycle_exists(&s.dev_eui, window_start_utc).await? {
+            continue;
+        }
+
+        if let Err(e) = evaluate_window(&s, window_start, window_end, now).await {
+            error!(dev_eui = %s.dev_eui, schedule_id = s.schedule_id, error = %e, "Evaluating defrost window failed");
+        }
+    }
+
+    Ok(())
+}
+
+async fn evaluate_window(
+    s: &defrost::DeviceSchedule,
+    window_start: NaiveDateTime,
+    window_end: NaiveDateTime,
+    now: NaiveDateTime,
+) -> Result<()> {
+    let conf = config::get();
+    let column = match alarm_rule::get_history_column(s.device_type_id, "temperature") {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+
+    // The window is evaluated in the timezone of the alarm schedules.
+    let samples: Vec<(NaiveDateTime, f32)> = defrost::get_samples(
+        &s.dev_eui,
+        column,
+        alarm::from_local(window_start - Duration::hours(BASELINE_HOURS)),
+        alarm::from_local(now),
+    )
+    .await?
+    .into_iter()
+    .map(|(ts, v)| (alarm::to_local(ts), v))
+    .collect();
+
+    let minutes = |ts: NaiveDateTime| (ts - window_start).num_seconds() as f64 / 60.0;
+    let before: Vec<f32> = samples
+        .iter()
+        .filter(|(ts, _)| *ts < window_start)
+        .map(|(_, v)| *v)
+        .collect();
+    let after: Vec<(f64, f32)> = samples
+        .iter()
+        .filter(|(ts, _)| *ts >= window_start)
+        .map(|(ts, v)| (minutes(*ts), *v))
+        .collect();
+
+    // Without data within the window, the device silence is handled by the no-data alarms.
+    if !after.iter().any(|(m, _)| *m <= minutes(window_end)) {
+        return Ok(());
+    }
+
+    let baseline = match defrost::median(&before) {
+        Some(v) => v,
+        None => after.iter().map(|(_, v)| *v).fold(f32::INFINITY, f32::min),
+    };
+
+    let spike = defrost::detect_spikes(&after, baseline, conf.alarm.defrost_temperature_rise)
+        .into_iter()
+        .find(|s| s.start <= minutes(window_end));
+
+    let status = match spike {
+        None => defrost::CycleStatus::Missed,
+        Some(spike) => match spike.end {
+            Some(end) if end <= minutes(window_end) => defrost::CycleStatus::Completed,
+            _ => defrost::CycleStatus::TooLong,
+        },
+    };
+
+    let at = |m: f64| alarm::from_local(window_start + Duration::seconds((m * 60.0) as i64));
+    let created = defrost::create_cycle(defrost::NewDefrostCycle {
+        dev_eui: s.dev_eui.clone(),
+        schedule_id: Some(s.schedule_id),
+        window_start: alarm::from_local(window_start),
+        started_at: spike.map(|s| at(s.start)),
+        ended_at: spike.and_then(|s| s.end).map(at),
+        peak_temperature: spike.map(|s| s.peak),
+        status: status.into(),
+        created_at: Utc::now(),
+    })
+    .await?;
+
+    if created && status != defrost::CycleStatus::Completed {
+        let expected_start =
+            window_start + Duration::seconds(conf.alarm.defrost_tolerance.as_secs() as i64);
+
+        warn!(dev_eui = %s.dev_eui, schedule_id = s.schedule_id, status = ?status, "Defrost cycle anomaly");
+        notify_defrost(&s.dev_eui, status, expected_start).await?;
+    }
+
+    Ok(())
+}
+
+async fn notify_defrost(
+    dev_eui: &str,
+    status: defrost::CycleStatus,
+    expected_start: NaiveDateTime,
+) -> Result<()> {
+    let user_ids = battery::get_zone_user_ids(dev_eui).await?;
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let dev = device::get(&EUI64::from_str(dev_eui)?).await?;
+    let mut db_conn = get_async_db_conn().await?;
+    let zone_name = alarm::get_zone_name_by_dev_eui(db_conn.as_mut(), dev_eui)
+        .await?
+        .unwrap_or_else(|| "Bilinmeyen Alan".to_string());
+
+    let message = match status {
+        defrost::CycleStatus::Missed => format!(
+            "{} ortamındaki {} isimli sensörde {} saatinde beklenen defrost gerçekleşmedi.",
+            zone_name,
+            dev.name,
+            expected_start.format("%d.%m.%Y %H:%M")
+        ),
+        _ => format!(
+            "{} ortamındaki {} isimli sensörde {} saatinde başlayan defrost beklenenden uzun sürdü.",
+            zone_name,
+            dev.name,
+            expected_start.format("%d.%m.%Y %H:%M")
+        ),
+    };
+
+    notification::create_notification(notification::Notification {
+        id: 0,
+        sender_id: 0,
+        receiver_id: user_ids.into_iter().map(Some).collect(),
+        message,
+        category_id: notification::CATEGORY_DEFROST,
+        is_read: Some(false),
+        send_time: Some(Local::now().naive_local()),
+        read_time: None,
+        sender_ip: Some("System".to_string()),
+        reader_ip: Some("".to_string()),
+        is_deleted: Some(false),
+        deleted_time: None,
+        dev_eui: Some(dev_eui.to_string()),
+        device_name: Some(dev.name.clone()),
+    })
+    .await?;
+
+    Ok(())
+}
+
+fn midnight() -> NaiveTime {
+    NaiveTime::from_hms_opt(0, 0, 0).unwrap()
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use chrono::Local;
+use tracing::warn;
+
+use crate::downlink::retry;
+use crate::storage::device::Device;
+use crate::storage::{alarm, automation, battery, get_async_db_conn, notification, webhook};
+
+// Informs the users of the zone of the device that a confirmed downlink was not acknowledged,
+// mentioning when the downlink was sent by an automation rule. This is called by the uplink
+// workers for the persisted downlink failures.
+pub async fn handle_failure(dev: &Device, f: &retry::DownlinkFailure) -> Result<()> {
+    let dev_eui = dev.dev_eui.to_string();
+
+    let rules = automation::get_rules_for_receiver(&dev_eui).await?;
+    for rule in &rules {
+        warn!(dev_eui = %dev_eui, automation_id = rule.id, queue_item_id = %f.queue_item_id, "Automation action not acknowledged by receiver device");
+        webhook::automation_executed(rule, Some("Downlink not acknowledged by receiver device"))
+            .await;
+    }
+
+    let user_ids = battery::get_zone_user_ids(&dev_eui).await?;
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut db_conn = get_async_db_conn().await?;
+    let zone_name = alarm::get_zone_name_by_dev_eui(db_conn.as_mut(), &dev_eui)
+        .await?
+        .unwrap_or_else(|| "Bilinmeyen Alan".to_string());
+
+    let message = format!(
+        "{} ortamındaki {} isimli cihaza gönderilen {} {} tekrar denemesine rağmen cihaz tarafından onaylanmadı.",
+        zone_name,
+        dev.name,
+        if rules.is_empty() {
+            "komut"
+        } else {
+            "otomasyon komutu"
+        },
+        f.retry_count
+    );
+
+    notification::create_notification(notification::Notification {
+        id: 0,
+        sender_id: 0,
+        receiver_id: user_ids.into_iter().map(Some).collect(),
+        message,
+        category_id: notification::CATEGORY_DOWNLINK,
+        is_read: Some(false),
+        send_time: Some(Local::now().naive_local()),
+        read_time: None,
+        sender_ip: Some("System".to_string()),
+        reader_ip: Some("".to_string()),
+        is_deleted: Some(false),
+        deleted_time: None,
+        dev_eui: Some(dev_eui),
+        device_name: Some(dev.name.clone()),
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::{self, user, zone};
+    use crate::test;
+    use lrwn::EUI64;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_handle_failure() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+        let f = retry::DownlinkFailure {
+            queue_item_id: Uuid::new_v4(),
+            f_port: 10,
+            data: vec![1, 2, 3],
+            retry_count: 3,
+        };
+
+        // without zone users, there is nobody to notify
+        handle_failure(&d, &f).await.unwrap();
+
+        let z = zone::create(zone::Zone {
+            zone_name: Some("Soğuk oda".into()),
+            tanent_id: Some(dp.tenant_id.into()),
+            devices: vec![Some(format!("\\x{}", d.dev_eui))].into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let u = user::create(user::User {
+            email: "zone@example.com".into(),
+            zone_id_list: Some(vec![Some(z.zone_id as i64)].into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        handle_failure(&d, &f).await.unwrap();
+        let items = notification::list(u.id.into()).await.unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(notification::CATEGORY_DOWNLINK, items[0].category_id);
+        assert_eq!(Some(d.dev_eui.to_string()), items[0].dev_eui);
+        assert!(items[0].message.contains("3 tekrar"));
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::Result;
+use chrono::{Local, Utc};
+use reqwest::Client;
+use serde_json::json;
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
+use uuid::Uuid;
+
+use super::sms;
+use crate::config;
+use crate::storage::{escalation, fields, notification, webhook};
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.alarm.escalation_check_interval.is_zero() {
+        info!("Alarm escalation is disabled");
+        return;
+    }
+
+    info!("Setting up alarm escalation loop");
+    tokio::spawn(async move {
+        escalation_loop().await;
+    });
+}
+
+pub async fn escalation_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting alarm escalation loop run");
+
+        if let Err(err) = run().await {
+            error!(error = %err, "Alarm escalation loop run failed");
+        } else {
+            trace!("Alarm escalation loop run completed successfully");
+        }
+
+        sleep(conf.alarm.escalation_check_interval).await;
+    }
+}
+
+async fn run() -> Result<()> {
+    for e in escalation::get_due(Utc::now()).await? {
+        if let Err(err) = escalate(&e).await {
+            error!(alarm_id = e.alarm_id, escalation_id = e.id, error = %err, "Executing escalation step failed");
+        }
+    }
+
+    Ok(())
+}
+
+// Executes the next step of the escalation. The level is claimed before executing the step,
+// such that the step is not executed twice.
+async fn escalate(e: &escalation::AlarmEscalation) -> Result<()> {
+    let steps = escalation::get_steps(e.policy_id).await?;
+    let step = match steps.iter().find(|s| s.level == e.level + 1) {
+        Some(v) => v,
+        None => {
+            // The policy was updated and has less steps than already executed.
+            escalation::claim_level(e, e.level, None).await?;
+            return Ok(());
+        }
+    };
+    let next_delay = steps
+        .iter()
+        .find(|s| s.level == step.level + 1)
+        .map(|s| s.delay_minutes);
+
+    if !escalation::claim_level(e, step.level, next_delay).await? {
+        return Ok(());
+    }
+
+    let message = format!("Alarm onaylanmadı (seviye {}): {}", step.level, e.message);
+    let result = match step.channel {
+        fields::EscalationChannel::NOTIFICATION => notify(e, step, message).await,
+        fields::EscalationChannel::SMS => {
+            let user_ids: Vec<Uuid> = step.user_id.iter().flatten().copied().collect();
+            let res = notify(e, step, message.clone()).await;
+            match sms::send(&e.dev_eui, &user_ids, &message).await {
+                Ok(sent) => {
+                    info!(alarm_id = e.alarm_id, dev_eui = %e.dev_eui, sent = sent, "Escalation SMS sent");
+                    res
+                }
+                Err(err) => res.and(Err(err)),
+            }
+        }
+        fields::EscalationChannel::WEBHOOK => {
+            let res = call_webhook(e, step, message).await;
+            let outcome = if res.is_ok() { "success" } else { "error" };
+            notification::record_delivery("webhook", outcome);
+            res
+        }
+    };
+
+    if let Err(err) = &result {
+        warn!(alarm_id = e.alarm_id, level = step.level, error = %err, "Escalation step failed");
+    }
+
+    escalation::log_step(
+        e,
+        "ESCALATE",
+        None,
+        json!({
+            "level": step.level,
+            "channel": step.channel.to_string(),
+            "user_id": step.user_id,
+            "webhook_url": step.webhook_url,
+            "error": result.as_ref().err().map(|e| e.to_string()),
+        }),
+    )
+    .await?;
+
+    webhook::alarm_escalated(e, step.level, &step.channel.to_string()).await;
+
+    info!(alarm_id = e.alarm_id, escalation_id = e.id, level = step.level, channel = %step.channel, "Alarm escalated");
+    Ok(())
+}
+
+async fn notify(
+    e: &escalation::AlarmEscalation,
+    step: &escalation::EscalationStep,
+    message: String,
+) -> Result<()> {
+    notification::create_notification(notification::Notification {
+        id: 0,
+        sender_id: e.alarm_id,
+        receiver_id: step.user_id.clone(),
+        message,
+        category_id: notification::CATEGORY_ALARM,
+        is_read: Some(false),
+        send_time: Some(Local::now().naive_local()),
+        read_time: None,
+        sender_ip: Some("System".to_string()),
+        reader_ip: Some("".to_string()),
+        is_deleted: Some(false),
+        deleted_time: None,
+        dev_eui: Some(e.dev_eui.clone()),
+        device_name: None,
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn call_webhook(
+    e: &escalation::AlarmEscalation,
+    step: &escalation::EscalationStep,
+    message: String,
+) -> Result<()> {
+    let conf = config::get();
+    let client = Client::builder()
+        .timeout(conf.alarm.escalation_webhook_timeout)
+        .build()?;
+
+    client
+        .post(&step.webhook_url)
+        .json(&json!({
+            "alarmId": e.alarm_id,
+            "escalationId": e.id,
+            "devEui": e.dev_eui,
+            "level": step.level,
+            "message": message,
+            "triggeredAt": e.triggered_at.to_rfc3339(),
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(())
+}
@@ -0,0 +1,346 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Local, Utc};
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
+
+use crate::config;
+use crate::storage::{alarm, battery, device, device_link, get_async_db_conn, notification};
+use lrwn::EUI64;
+
+// Uplinks used to derive the current link quality. The uplinks before this window (within the
+// link trend days) are the baseline.
+const RECENT_HOURS: i64 = 24;
+
+// The minimum number of uplinks in both the recent window and the baseline before the link can
+// be flagged as degraded.
+const MIN_UPLINKS: i32 = 5;
+
+// Link margin (dB) thresholds. The margin is the SNR above the demodulation floor of the
+// spreading-factor, below 5 dB a few dB of extra attenuation (a door, a parked truck) is enough
+// to lose uplinks.
+const GOOD_MARGIN: f32 = 10.0;
+const FAIR_MARGIN: f32 = 5.0;
+
+// RSSI (dBm) thresholds. Close to the sensitivity of the gateway, the uplinks are sensitive to
+// interference even when the margin is fine.
+const GOOD_RSSI: f32 = -110.0;
+const FAIR_RSSI: f32 = -120.0;
+
+// Link quality of a device over a period, merged over all gateways.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    // Gateway with the best average SNR.
+    pub gateway_id: String,
+    pub uplink_count: i32,
+    pub rssi: f32,
+    pub snr: f32,
+    pub margin: Option<f32>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+// Returns the signal tag for the given link margin and RSSI. Without margin (non-LoRa
+// modulations), only the RSSI is used.
+pub fn get_signal(margin: Option<f32>, rssi: f32) -> &'static str {
+    let by_margin = match margin {
+        Some(v) if v < FAIR_MARGIN => 0,
+        Some(v) if v < GOOD_MARGIN => 1,
+        _ => 2,
+    };
+    let by_rssi = if rssi < FAIR_RSSI {
+        0
+    } else if rssi < GOOD_RSSI {
+        1
+    } else {
+        2
+    };
+
+    match by_margin.min(by_rssi) {
+        0 => device_link::SIGNAL_WEAK,
+        1 => device_link::SIGNAL_FAIR,
+        _ => device_link::SIGNAL_GOOD,
+    }
+}
+
+// Merges the per-gateway averages of a device, weighted by the number of uplinks.
+pub fn summarize(stats: &[device_link::GatewayLinkStats]) -> Option<Summary> {
+    let best = stats.iter().max_by(|a, b| {
+        a.snr
+            .total_cmp(&b.snr)
+            .then(a.uplink_count.cmp(&b.uplink_count))
+    })?;
+
+    let mut count = 0.0;
+    let mut rssi = 0.0;
+    let mut snr = 0.0;
+    let mut margin_count = 0.0;
+    let mut margin = 0.0;
+
+    for s in stats {
+        let n = s.uplink_count as f32;
+        count += n;
+        rssi += s.rssi * n;
+        snr += s.snr * n;
+        if let Some(v) = s.margin {
+            margin_count += n;
+            margin += v * n;
+        }
+    }
+
+    if count == 0.0 {
+        return None;
+    }
+
+    Some(Summary {
+        gateway_id: best.gateway_id.clone(),
+        uplink_count: count as i32,
+        rssi: rssi / count,
+        snr: snr / count,
+        margin: if margin_count > 0.0 {
+            Some(margin / margin_count)
+        } else {
+            None
+        },
+        last_seen_at: stats.iter().map(|s| s.last_seen_at).max()?,
+    })
+}
+
+// Returns the drop (dB) of the recent link margin compared to the baseline. It returns None when
+// there are not enough uplinks to compare.
+pub fn get_margin_drop(recent: Option<&Summary>, baseline: Option<&Summary>) -> Option<f32> {
+    let (recent, baseline) = (recent?, baseline?);
+    if recent.uplink_count < MIN_UPLINKS || baseline.uplink_count < MIN_UPLINKS {
+        return None;
+    }
+
+    Some(baseline.margin? - recent.margin?)
+}
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.alarm.link_check_interval.is_zero() {
+        info!("Link tracking is disabled");
+        return;
+    }
+
+    info!("Setting up link tracking loop");
+    tokio::spawn(async move {
+        link_loop().await;
+    });
+}
+
+pub async fn link_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting link tracking loop run");
+
+        if let Err(err) = update_link_states().await {
+            error!(error = %err, "Updating link states failed");
+        } else {
+            trace!("Link tracking loop run completed successfully");
+        }
+
+        sleep(conf.alarm.link_check_interval).await;
+    }
+}
+
+pub async fn update_link_states() -> Result<()> {
+    let conf = config::get();
+    let now = Utc::now();
+    let recent_since = now - Duration::hours(RECENT_HOURS);
+    let trend_since = now - Duration::days(conf.alarm.link_trend_days as i64);
+
+    let mut recent: BTreeMap<String, Vec<device_link::GatewayLinkStats>> = BTreeMap::new();
+    for s in device_link::get_gateway_stats(recent_since, now).await? {
+        recent.entry(s.dev_eui.clone()).or_default().push(s);
+    }
+
+    let mut baseline: BTreeMap<String, Vec<device_link::GatewayLinkStats>> = BTreeMap::new();
+    for s in device_link::get_gateway_stats(trend_since, recent_since).await? {
+        baseline.entry(s.dev_eui.clone()).or_default().push(s);
+    }
+
+    let dev_euis: BTreeSet<&String> = recent.keys().chain(baseline.keys()).collect();
+    trace!(
+        device_count = dev_euis.len(),
+        "Got link stats for this number of devices"
+    );
+
+    for dev_eui in dev_euis {
+        let r = recent.get(dev_eui).and_then(|v| summarize(v));
+        let b = baseline.get(dev_eui).and_then(|v| summarize(v));
+        let last_seen_at = match r.as_ref().or(b.as_ref()) {
+            Some(v) => v.last_seen_at,
+            None => continue,
+        };
+
+        // Without uplinks in the recent window, the device is out of coverage (or silent, which
+        // is covered by the no-data alarms).
+        let state = device_link::upsert(&device_link::DeviceLink {
+            dev_eui: dev_eui.clone(),
+            gateway_id: r.as_ref().map(|v| v.gateway_id.clone()),
+            rssi: r.as_ref().map(|v| v.rssi),
+            snr: r.as_ref().map(|v| v.snr),
+            margin: r.as_ref().and_then(|v| v.margin),
+            margin_baseline: b.as_ref().and_then(|v| v.margin),
+            signal: match &r {
+                Some(v) => get_signal(v.margin, v.rssi).to_string(),
+                None => device_link::SIGNAL_NONE.to_string(),
+            },
+            uplink_count: r.as_ref().map(|v| v.uplink_count).unwrap_or_default(),
+            last_seen_at,
+            updated_at: now,
+            ..Default::default()
+        })
+        .await?;
+
+        if device_link::set_signal_tag(&state.dev_eui, &state.signal).await? {
+            info!(dev_eui = %state.dev_eui, signal = %state.signal, "Device signal tag updated");
+        }
+
+        let margin_drop = match get_margin_drop(r.as_ref(), b.as_ref()) {
+            Some(v) => v,
+            None => continue,
+        };
+        let notified = state.degraded_notified_at.is_some();
+
+        if margin_drop >= conf.alarm.link_degradation_margin && !notified {
+            warn!(dev_eui = %state.dev_eui, margin = ?state.margin, margin_baseline = ?state.margin_baseline, "Link degraded");
+
+            if let Err(e) = notify_degraded(&state).await {
+                error!(dev_eui = %state.dev_eui, error = %e, "Notifying link degradation failed");
+                continue;
+            }
+            device_link::set_degraded_notified(&state.dev_eui, Some(now)).await?;
+        } else if margin_drop < conf.alarm.link_degradation_margin / 2.0 && notified {
+            // Half of the threshold, to avoid repeated notifications for a link which is around
+            // the threshold.
+            info!(dev_eui = %state.dev_eui, margin = ?state.margin, "Link recovered");
+            device_link::set_degraded_notified(&state.dev_eui, None).await?;
+        }
+    }
+
+    let count = device_link::delete_older_than(trend_since).await?;
+    trace!(count = count, "Deleted old link samples");
+
+    Ok(())
+}
+
+async fn notify_degraded(state: &device_link::DeviceLink) -> Result<()> {
+    let user_ids = battery::get_zone_user_ids(&state.dev_eui).await?;
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let dev = device::get(&EUI64::from_str(&state.dev_eui)?).await?;
+    let mut db_conn = get_async_db_conn().await?;
+    let zone_name = alarm::get_zone_name_by_dev_eui(db_conn.as_mut(), &state.dev_eui)
+        .await?
+        .unwrap_or_else(|| "Bilinmeyen Alan".to_string());
+
+    let message = format!(
+        "{} ortamındaki {} isimli sensörün bağlantı kalitesi düşüyor: sinyal payı {:.1} dB'den {:.1} dB'ye düştü.",
+        zone_name,
+        dev.name,
+        state.margin_baseline.unwrap_or_default(),
+        state.margin.unwrap_or_default()
+    );
+
+    notification::create_notification(notification::Notification {
+        id: 0,
+        sender_id: 0,
+        receiver_id: user_ids.into_iter().map(Some).collect(),
+        message,
+        category_id: notification::CATEGORY_LINK,
+        is_read: Some(false),
+        send_time: Some(Local::now().naive_local()),
+        read_time: None,
+        sender_ip: Some("System".to_string()),
+        reader_ip: Some("".to_string()),
+        is_deleted: Some(false),
+        deleted_time: None,
+        dev_eui: Some(state.dev_eui.clone()),
+        device_name: Some(dev.name.clone()),
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stats(
+        gateway_id: &str,
+        uplink_count: i32,
+        snr: f32,
+        margin: Option<f32>,
+    ) -> device_link::GatewayLinkStats {
+        device_link::GatewayLinkStats {
+            dev_eui: "0102030405060708".into(),
+            gateway_id: gateway_id.into(),
+            uplink_count,
+            rssi: -100.0,
+            snr,
+            margin,
+            last_seen_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_get_signal() {
+        assert_eq!(device_link::SIGNAL_GOOD, get_signal(Some(12.0), -90.0));
+        assert_eq!(device_link::SIGNAL_FAIR, get_signal(Some(7.5), -90.0));
+        assert_eq!(device_link::SIGNAL_WEAK, get_signal(Some(2.0), -90.0));
+
+        // the rssi caps the margin
+        assert_eq!(device_link::SIGNAL_FAIR, get_signal(Some(12.0), -115.0));
+        assert_eq!(device_link::SIGNAL_WEAK, get_signal(Some(12.0), -125.0));
+
+        // no margin
+        assert_eq!(device_link::SIGNAL_GOOD, get_signal(None, -90.0));
+        assert_eq!(device_link::SIGNAL_WEAK, get_signal(None, -125.0));
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(None, summarize(&[]));
+
+        let s = summarize(&[
+            stats("0101010101010101", 3, 2.0, Some(12.0)),
+            stats("0202020202020202", 1, 6.0, Some(16.0)),
+        ])
+        .unwrap();
+        assert_eq!("0202020202020202", s.gateway_id);
+        assert_eq!(4, s.uplink_count);
+        assert_eq!(3.0, s.snr);
+        assert_eq!(Some(13.0), s.margin);
+
+        // the margin is averaged over the uplinks which have a margin
+        let s = summarize(&[
+            stats("0101010101010101", 3, 2.0, None),
+            stats("0202020202020202", 1, 6.0, Some(16.0)),
+        ])
+        .unwrap();
+        assert_eq!(Some(16.0), s.margin);
+    }
+
+    #[test]
+    fn test_get_margin_drop() {
+        let recent = summarize(&[stats("0101010101010101", 10, 0.0, Some(4.0))]);
+        let baseline = summarize(&[stats("0101010101010101", 50, 6.0, Some(12.0))]);
+        assert_eq!(
+            Some(8.0),
+            get_margin_drop(recent.as_ref(), baseline.as_ref())
+        );
+
+        // not enough uplinks
+        let recent = summarize(&[stats("0101010101010101", 2, 0.0, Some(4.0))]);
+        assert_eq!(None, get_margin_drop(recent.as_ref(), baseline.as_ref()));
+        assert_eq!(None, get_margin_drop(None, baseline.as_ref()));
+    }
+}
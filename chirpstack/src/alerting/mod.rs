@@ -0,0 +1,18 @@
+pub mod battery;
+pub mod defrost;
+pub mod downlink;
+pub mod escalation;
+pub mod link;
+pub mod silence;
+pub mod simulation;
+pub mod sms;
+pub mod webhook;
+
+pub async fn setup() {
+    silence::setup().await;
+    battery::setup().await;
+    link::setup().await;
+    defrost::setup().await;
+    escalation::setup().await;
+    webhook::setup().await;
+}
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use chrono::Datelike;
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
+
+use crate::config;
+use crate::storage::{alarm, webhook};
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.alarm.no_data_check_interval.is_zero() {
+        info!("No-data alarm evaluation is disabled");
+        return;
+    }
+
+    info!("Setting up no-data alarm loop");
+    tokio::spawn(async move {
+        no_data_loop().await;
+    });
+}
+
+pub async fn no_data_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting no-data alarm loop run");
+
+        if let Err(err) = check_silent_devices().await {
+            error!(error = %err, "Checking silent devices failed");
+        } else {
+            trace!("No-data alarm loop run completed successfully");
+        }
+
+        sleep(conf.alarm.no_data_check_interval).await;
+    }
+}
+
+pub async fn check_silent_devices() -> Result<()> {
+    // Devices which sent data since their no-data alarm was triggered clear the alarm.
+    for (alarm_id, dev_eui) in alarm::reset_no_data_triggered().await? {
+        trace!(alarm_id = alarm_id, dev_eui = %dev_eui, "Device sent data again, no-data alarm cleared");
+        webhook::alarm_cleared(alarm_id, "no_data", &dev_eui, None).await;
+    }
+
+    let current_time = alarm::local_now();
+    let weekday = current_time.weekday().number_from_monday();
+
+    let alarms = alarm::get_no_data_alarms(weekday as i32).await?;
+    trace!(
+        alarm_count = alarms.len(),
+        "Got this number of no-data alarms for silent devices"
+    );
+
+    // An alarm can have multiple schedule entries for the same day, it must be
+    // triggered at most once per device.
+    let mut triggered: HashSet<(i32, String)> = HashSet::new();
+
+    for a in alarms {
+        if triggered.contains(&(a.id, a.dev_eui.clone()))
+            || !a.is_within_schedule(current_time.time())
+        {
+            continue;
+        }
+
+        warn!(alarm_id = a.id, dev_eui = %a.dev_eui, last_seen_at = %a.last_seen_at, silence_minutes = a.silence_minutes, "Device did not send data, triggering no-data alarm");
+
+        if let Err(e) = alarm::execute_no_data_alarm(&a, &current_time.to_string()).await {
+            error!(alarm_id = a.id, dev_eui = %a.dev_eui, error = %e, "Executing no-data alarm failed");
+            continue;
+        }
+
+        triggered.insert((a.id, a.dev_eui));
+    }
+
+    Ok(())
+}
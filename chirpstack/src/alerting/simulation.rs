@@ -0,0 +1,665 @@
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDateTime, TimeZone, Timelike, Utc};
+use diesel::sql_query;
+use diesel::sql_types::{Float, Text, Timestamp};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+
+use crate::config;
+use crate::storage::alarm_rule::{self, RuleType};
+use crate::storage::device::Device;
+use crate::storage::error::Error;
+use crate::storage::{alarm, defrost, get_async_db_conn, AsyncDbConnection};
+
+// Max. time range which can be simulated.
+pub const MAX_SIMULATION_DAYS: i64 = 31;
+
+// Candidate alarm configuration to simulate.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub min_treshold: f32,
+    pub max_treshold: f32,
+    pub temperature: bool,
+    pub humidity: bool,
+    pub ec: bool,
+    pub pressure: bool,
+    pub door: bool,
+    pub w_leak: bool,
+    pub no_data: bool,
+    pub no_data_time: i32,
+    pub zone_category: i64,
+    pub defrost_time: i64,
+    pub rule_type: RuleType,
+    pub rule_duration: Option<i32>,
+    pub rule_limit: Option<f64>,
+    pub is_time_limit_active: bool,
+    pub schedule: Vec<alarm::AlarmDateTime>,
+    pub sms: bool,
+    pub user_count: usize,
+}
+
+impl Default for Candidate {
+    fn default() -> Self {
+        Candidate {
+            min_treshold: 0.0,
+            max_treshold: 0.0,
+            temperature: false,
+            humidity: false,
+            ec: false,
+            pressure: false,
+            door: false,
+            w_leak: false,
+            no_data: false,
+            no_data_time: 0,
+            zone_category: 0,
+            defrost_time: 0,
+            rule_type: RuleType::Threshold,
+            rule_duration: None,
+            rule_limit: None,
+            is_time_limit_active: false,
+            schedule: vec![alarm::AlarmDateTime {
+                alarm_day: 0,
+                ..Default::default()
+            }],
+            sms: false,
+            user_count: 0,
+        }
+    }
+}
+
+impl Candidate {
+    fn get_rule_duration(&self) -> i32 {
+        self.rule_duration
+            .filter(|v| *v > 0)
+            .unwrap_or(alarm_rule::DEFAULT_RULE_DURATION)
+    }
+
+    // Returns true when the alarm is active at the given (UTC) time, based on its schedule.
+    fn is_scheduled(&self, t: NaiveDateTime) -> bool {
+        let t = alarm::to_local(Utc.from_utc_datetime(&t));
+        let weekday = t.weekday().number_from_monday() as i32;
+        self.schedule.iter().any(|s| {
+            (s.alarm_day == 0 || s.alarm_day == weekday)
+                && alarm::is_within_time_window(
+                    self.is_time_limit_active,
+                    s.start_time as f32,
+                    s.end_time as f32,
+                    t.time(),
+                )
+        })
+    }
+}
+
+// Incident which the candidate alarm would have produced. Consecutive triggering samples of the
+// same metric are grouped into a single incident, every triggering sample is a notification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub metric: String,
+    pub start_at: NaiveDateTime,
+    pub end_at: NaiveDateTime,
+    pub trigger_count: usize,
+    pub peak_value: f32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Simulation {
+    pub sample_count: usize,
+    pub incidents: Vec<Incident>,
+    pub notification_count: usize,
+    pub sms_count: usize,
+    // Triggering samples suppressed during the expected defrost cycles.
+    pub suppressed_count: usize,
+}
+
+#[derive(QueryableByName)]
+struct SampleRow {
+    #[diesel(sql_type = Timestamp)]
+    submission_date: NaiveDateTime,
+    #[diesel(sql_type = Float)]
+    value: f32,
+}
+
+#[derive(QueryableByName)]
+struct TimeRow {
+    #[diesel(sql_type = Timestamp)]
+    submission_date: NaiveDateTime,
+}
+
+// Validates the candidate alarm and the time range to simulate.
+pub fn validate(c: &Candidate, start: NaiveDateTime, end: NaiveDateTime) -> Result<(), Error> {
+    if end <= start {
+        return Err(Error::Validation("End must be after start".into()));
+    }
+    if end - start > Duration::days(MAX_SIMULATION_DAYS) {
+        return Err(Error::Validation(format!(
+            "Time range must not exceed {} days",
+            MAX_SIMULATION_DAYS
+        )));
+    }
+    if c.schedule.is_empty() {
+        return Err(Error::Validation(
+            "Alarm schedule (alarm_date_time) is required".into(),
+        ));
+    }
+    if c.rule_type == RuleType::ZoneDeviation {
+        return Err(Error::Validation(
+            "Zone deviation rules can not be simulated, the zone history is not stored".into(),
+        ));
+    }
+    Ok(())
+}
+
+// Replays the device history between start and end (local time) against the candidate alarm.
+// Nothing is written, the notifications that would have been sent are only counted.
+pub async fn simulate(
+    device: &Device,
+    c: &Candidate,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<Simulation> {
+    validate(c, start, end)?;
+
+    let dev_eui = device.dev_eui.to_string();
+    let lookback = c.get_rule_duration().max(c.defrost_time as i32).max(0);
+    let tolerance = (config::get().alarm.defrost_tolerance.as_secs() / 60) as f64;
+    let mut conn = get_async_db_conn().await?;
+    let mut out = Simulation::default();
+
+    for (metric, column) in get_metrics(device.device_type, c) {
+        // The history is stored calibrated, the calibration must not be applied again.
+        let series = get_series(&mut conn, &dev_eui, column, start, end, lookback).await?;
+
+        let mut triggered = evaluate_series(c, metric, &series, start);
+
+        // Temperature alarms are suppressed during the expected defrost cycles.
+        if metric == "temperature" {
+            for (i, (t, _)) in series.iter().enumerate() {
+                if !triggered[i] {
+                    continue;
+                }
+                let t = alarm::to_local(Utc.from_utc_datetime(t));
+                let minute = (t.hour() * 60 + t.minute()) as f64;
+                if defrost::is_defrost_expected(&mut conn, &dev_eui, minute, tolerance).await? {
+                    triggered[i] = false;
+                    out.suppressed_count += 1;
+                }
+            }
+        }
+
+        out.sample_count += series.iter().filter(|(t, _)| *t >= start).count();
+        out.incidents
+            .extend(group_incidents(c, metric, &series, &triggered));
+    }
+
+    if c.no_data {
+        let silence = match (c.no_data_time, device.data_time) {
+            (v, _) if v > 0 => v,
+            (_, Some(v)) if v > 0 => v * 2,
+            _ => 60,
+        };
+        let times = get_times(&mut conn, &dev_eui, start, end).await?;
+        out.incidents
+            .extend(get_no_data_incidents(c, &times, silence, end));
+    }
+
+    out.incidents.sort_by_key(|i| i.start_at);
+    out.notification_count = out.incidents.iter().map(|i| i.trigger_count).sum();
+    if c.sms {
+        out.sms_count = out.notification_count * c.user_count;
+    }
+
+    Ok(out)
+}
+
+// Returns the (alarm type, device_data column) tuples evaluated for the device, following
+// the evaluation of the uplinks by alarm::check_alarm.
+fn get_metrics(device_type: Option<i32>, c: &Candidate) -> Vec<(&'static str, &'static str)> {
+    let mut types: Vec<&'static str> = Vec::new();
+
+    match device_type {
+        Some(1) => {
+            if c.temperature {
+                types.push("temperature");
+            } else if c.humidity {
+                types.push("humidity");
+            }
+        }
+        Some(2) => {
+            if c.temperature {
+                types.push("temperature");
+            } else if c.humidity {
+                types.push("humidity");
+            } else if c.ec {
+                types.push("ec");
+            }
+        }
+        Some(3) | Some(16) => {
+            if c.door {
+                return vec![("door", "door_open_status")];
+            }
+        }
+        Some(4) | Some(10) | Some(14) | Some(18) | Some(19) => {
+            if c.w_leak {
+                return vec![("water_leak", "water_leak_status")];
+            }
+        }
+        Some(12) | Some(35) => {
+            if c.temperature {
+                types.push("temperature");
+            }
+            if c.humidity {
+                types.push("humidity");
+            }
+        }
+        Some(20) | Some(36) | Some(40) => {
+            if c.temperature {
+                types.push("temperature");
+            }
+        }
+        Some(21) => {
+            if c.pressure {
+                types.push("pressure");
+            }
+        }
+        _ => {}
+    }
+
+    types
+        .into_iter()
+        .filter_map(|t| alarm_rule::get_history_column(device_type, t).map(|col| (t, col)))
+        .collect()
+}
+
+// Returns the samples (oldest first) between start and end, including the lookback minutes
+// before start and the last sample before this lookback, such that the rules can be evaluated
+// for the first samples.
+async fn get_series(
+    conn: &mut AsyncDbConnection,
+    dev_eui: &str,
+    column: &str,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    lookback: i32,
+) -> Result<Vec<(NaiveDateTime, f32)>> {
+    let query = format!(
+        r#"
+        SELECT
+            submission_date,
+            {col}::float4 AS value
+        FROM device_data
+        WHERE dev_eui = $1
+          AND {col} IS NOT NULL
+          AND submission_date <= $3
+          AND submission_date >= (
+            SELECT COALESCE(MAX(submission_date), $2)
+            FROM device_data
+            WHERE dev_eui = $1
+              AND {col} IS NOT NULL
+              AND submission_date <= $2
+          )
+        ORDER BY submission_date
+        "#,
+        col = column
+    );
+
+    let rows: Vec<SampleRow> = sql_query(query)
+        .bind::<Text, _>(dev_eui)
+        .bind::<Timestamp, _>(start - Duration::minutes(lookback as i64))
+        .bind::<Timestamp, _>(end)
+        .load(conn)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.submission_date, r.value))
+        .collect())
+}
+
+// Returns the times of the uplinks between start and end, including the last uplink before
+// start.
+async fn get_times(
+    conn: &mut AsyncDbConnection,
+    dev_eui: &str,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<Vec<NaiveDateTime>> {
+    let rows: Vec<TimeRow> = sql_query(
+        r#"
+        SELECT submission_date
+        FROM device_data
+        WHERE dev_eui = $1
+          AND submission_date <= $3
+          AND submission_date >= (
+            SELECT COALESCE(MAX(submission_date), $2)
+            FROM device_data
+            WHERE dev_eui = $1
+              AND submission_date <= $2
+          )
+        ORDER BY submission_date
+        "#,
+    )
+    .bind::<Text, _>(dev_eui)
+    .bind::<Timestamp, _>(start)
+    .bind::<Timestamp, _>(end)
+    .load(conn)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.submission_date).collect())
+}
+
+// Returns for each sample if it would have triggered the alarm. The samples before start are
+// only used as history.
+fn evaluate_series(
+    c: &Candidate,
+    metric: &str,
+    series: &[(NaiveDateTime, f32)],
+    start: NaiveDateTime,
+) -> Vec<bool> {
+    series
+        .iter()
+        .enumerate()
+        .map(|(i, (t, v))| {
+            if *t < start || !c.is_scheduled(*t) {
+                return false;
+            }
+
+            match metric {
+                "door" | "water_leak" => *v == 1.0,
+                _ => evaluate_value(c, &series[..=i]),
+            }
+        })
+        .collect()
+}
+
+// Evaluates the last sample of the history (oldest first) against the alarm rule, following
+// alarm::check_threshold and alarm_rule::evaluate.
+fn evaluate_value(c: &Candidate, history: &[(NaiveDateTime, f32)]) -> bool {
+    let (t, value) = match history.last() {
+        Some(v) => *v,
+        None => return false,
+    };
+
+    match c.rule_type {
+        RuleType::Threshold => {
+            if value >= c.min_treshold && value <= c.max_treshold {
+                return false;
+            }
+            if c.zone_category != 1 {
+                return true;
+            }
+
+            let since = t - Duration::minutes(c.defrost_time);
+            let values: Vec<f32> = history
+                .iter()
+                .filter(|(st, _)| *st > since)
+                .map(|(_, v)| *v)
+                .collect();
+            alarm::is_ege_alarm(&values, c.max_treshold)
+        }
+        RuleType::Sustained => {
+            let duration = c.get_rule_duration();
+            alarm_rule::is_sustained(
+                &get_samples(history, duration),
+                c.min_treshold,
+                c.max_treshold,
+                duration as f64,
+            )
+        }
+        RuleType::RateOfChange => {
+            let limit = match c.rule_limit {
+                Some(v) if v > 0.0 => v as f32,
+                _ => return false,
+            };
+            let duration = c.get_rule_duration();
+            matches!(
+                alarm_rule::get_rate_of_change(&get_samples(history, duration), duration as f64),
+                Some(rate) if rate.abs() > limit
+            )
+        }
+        RuleType::ZoneDeviation => false,
+    }
+}
+
+// Returns the samples of the last duration minutes of the history, including the last sample
+// before this window, as (minutes ago, value) tuples, newest first.
+fn get_samples(history: &[(NaiveDateTime, f32)], duration: i32) -> Vec<(f64, f32)> {
+    let (now, _) = match history.last() {
+        Some(v) => *v,
+        None => return Vec::new(),
+    };
+
+    let mut out = Vec::new();
+    for (t, v) in history.iter().rev() {
+        let minutes_ago = (now - *t).num_seconds() as f64 / 60.0;
+        out.push((minutes_ago, *v));
+        if minutes_ago >= duration as f64 {
+            break;
+        }
+    }
+    out
+}
+
+fn group_incidents(
+    c: &Candidate,
+    metric: &str,
+    series: &[(NaiveDateTime, f32)],
+    triggered: &[bool],
+) -> Vec<Incident> {
+    let mut out: Vec<Incident> = Vec::new();
+    let mut current: Option<Incident> = None;
+
+    for ((t, v), trig) in series.iter().zip(triggered) {
+        if !trig {
+            if let Some(i) = current.take() {
+                out.push(i);
+            }
+            continue;
+        }
+
+        let i = current.get_or_insert_with(|| Incident {
+            metric: metric.to_string(),
+            start_at: *t,
+            end_at: *t,
+            trigger_count: 0,
+            peak_value: *v,
+        });
+        i.end_at = *t;
+        i.trigger_count += 1;
+        if get_excess(c, *v) > get_excess(c, i.peak_value) {
+            i.peak_value = *v;
+        }
+    }
+
+    out.extend(current);
+    out
+}
+
+// Returns how far the value is out of the min / max range.
+fn get_excess(c: &Candidate, value: f32) -> f32 {
+    (value - c.max_treshold).max(c.min_treshold - value)
+}
+
+// Returns the no-data incidents, one for every gap between uplinks exceeding the silence
+// minutes (as alarm::get_no_data_alarms triggers once per missing uplink).
+fn get_no_data_incidents(
+    c: &Candidate,
+    times: &[NaiveDateTime],
+    silence: i32,
+    end: NaiveDateTime,
+) -> Vec<Incident> {
+    let silence = Duration::minutes(silence as i64);
+    let mut out = Vec::new();
+
+    for (i, t) in times.iter().enumerate() {
+        let next = times.get(i + 1).copied().unwrap_or(end);
+        let trigger_at = *t + silence;
+        if trigger_at < next && trigger_at <= end && c.is_scheduled(trigger_at) {
+            out.push(Incident {
+                metric: "no_data".into(),
+                start_at: trigger_at,
+                end_at: next,
+                trigger_count: 1,
+                peak_value: (next - *t).num_minutes() as f32,
+            });
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn time(h: u32, m: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 7, 14)
+            .unwrap()
+            .and_hms_opt(h, m, 0)
+            .unwrap()
+    }
+
+    fn series(values: &[(u32, f32)]) -> Vec<(NaiveDateTime, f32)> {
+        values.iter().map(|(m, v)| (time(10, *m), *v)).collect()
+    }
+
+    #[test]
+    fn test_validate() {
+        let c = Candidate::default();
+
+        assert!(validate(&c, time(10, 0), time(11, 0)).is_ok());
+        assert!(validate(&c, time(11, 0), time(10, 0)).is_err());
+        assert!(validate(&c, time(10, 0), time(10, 0) + Duration::days(32)).is_err());
+        assert!(validate(
+            &Candidate {
+                schedule: vec![],
+                ..Default::default()
+            },
+            time(10, 0),
+            time(11, 0)
+        )
+        .is_err());
+        assert!(validate(
+            &Candidate {
+                rule_type: RuleType::ZoneDeviation,
+                ..Default::default()
+            },
+            time(10, 0),
+            time(11, 0)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_threshold() {
+        let c = Candidate {
+            min_treshold: 2.0,
+            max_treshold: 8.0,
+            ..Default::default()
+        };
+        let s = series(&[
+            (0, 9.0),
+            (10, 5.0),
+            (20, 9.0),
+            (30, 10.0),
+            (40, 1.0),
+            (50, 5.0),
+        ]);
+
+        // first sample is history
+        let triggered = evaluate_series(&c, "temperature", &s, time(10, 5));
+        assert_eq!(vec![false, false, true, true, true, false], triggered);
+
+        let incidents = group_incidents(&c, "temperature", &s, &triggered);
+        assert_eq!(
+            vec![Incident {
+                metric: "temperature".into(),
+                start_at: time(10, 20),
+                end_at: time(10, 40),
+                trigger_count: 3,
+                peak_value: 10.0,
+            }],
+            incidents
+        );
+    }
+
+    #[test]
+    fn test_ege() {
+        let c = Candidate {
+            min_treshold: 2.0,
+            max_treshold: 8.0,
+            zone_category: 1,
+            defrost_time: 15,
+            ..Default::default()
+        };
+        let s = series(&[(0, 5.0), (10, 9.0), (20, 9.5), (30, 9.5), (40, 10.0)]);
+
+        let triggered = evaluate_series(&c, "temperature", &s, time(10, 0));
+        assert_eq!(vec![false, false, true, false, true], triggered);
+    }
+
+    #[test]
+    fn test_sustained() {
+        let c = Candidate {
+            min_treshold: 2.0,
+            max_treshold: 8.0,
+            rule_type: RuleType::Sustained,
+            rule_duration: Some(15),
+            ..Default::default()
+        };
+        let s = series(&[(0, 5.0), (10, 9.0), (20, 9.0), (30, 9.0)]);
+
+        let triggered = evaluate_series(&c, "temperature", &s, time(10, 0));
+        assert_eq!(vec![false, false, false, true], triggered);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let c = Candidate {
+            min_treshold: 2.0,
+            max_treshold: 8.0,
+            schedule: vec![alarm::AlarmDateTime {
+                // Tuesdays only, 2025-07-14 is a Monday.
+                alarm_day: 2,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let s = series(&[(0, 9.0)]);
+
+        assert_eq!(
+            vec![false],
+            evaluate_series(&c, "temperature", &s, time(10, 0))
+        );
+    }
+
+    #[test]
+    fn test_door() {
+        let c = Candidate {
+            door: true,
+            ..Default::default()
+        };
+        let s = series(&[(0, 0.0), (10, 1.0), (20, 1.0), (30, 0.0), (40, 1.0)]);
+
+        let triggered = evaluate_series(&c, "door", &s, time(10, 0));
+        assert_eq!(2, group_incidents(&c, "door", &s, &triggered).len());
+    }
+
+    #[test]
+    fn test_no_data() {
+        let c = Candidate {
+            no_data: true,
+            ..Default::default()
+        };
+        let times = vec![time(10, 0), time(10, 30), time(12, 0)];
+
+        let incidents = get_no_data_incidents(&c, &times, 60, time(14, 0));
+        assert_eq!(
+            vec![(time(11, 30), time(12, 0)), (time(13, 0), time(14, 0))],
+            incidents
+                .iter()
+                .map(|i| (i.start_at, i.end_at))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::Result;
+use reqwest::Client;
+use serde_json::json;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config;
+use crate::storage::{notification, usage, user};
+
+// Sends the message as SMS to the phone numbers of the given users, through the configured SMS
+// gateway. Nothing is sent when no SMS gateway is configured or when the tenant of the device is
+// over its SMS quota. It returns the number of sent messages.
+pub async fn send(dev_eui: &str, user_ids: &[Uuid], message: &str) -> Result<usize> {
+    let conf = config::get();
+    if conf.alarm.sms_gateway_url.is_empty() {
+        return Ok(0);
+    }
+
+    let phone_numbers = user::get_phone_numbers(user_ids).await?;
+    if phone_numbers.is_empty() {
+        return Ok(0);
+    }
+
+    if !usage::check_device_sms_quota(dev_eui).await? {
+        notification::record_delivery("sms", "over_quota");
+        info!(dev_eui = %dev_eui, "Tenant is over SMS quota, SMS dropped");
+        return Ok(0);
+    }
+
+    let client = Client::builder()
+        .timeout(conf.alarm.sms_gateway_timeout)
+        .build()?;
+
+    let mut sent = 0;
+    for phone_number in &phone_numbers {
+        match post(&client, phone_number, message).await {
+            Ok(()) => {
+                notification::record_delivery("sms", "sent");
+                sent += 1;
+            }
+            Err(e) => {
+                notification::record_delivery("sms", "error");
+                warn!(dev_eui = %dev_eui, error = %e, "Sending SMS failed");
+            }
+        }
+    }
+
+    if sent > 0 {
+        usage::meter_device_sms(dev_eui, sent).await?;
+    }
+
+    Ok(sent)
+}
+
+async fn post(client: &Client, phone_number: &str, message: &str) -> Result<()> {
+    let conf = config::get();
+
+    let mut req = client.post(&conf.alarm.sms_gateway_url).json(&json!({
+        "to": phone_number,
+        "message": message,
+    }));
+    if !conf.alarm.sms_gateway_token.is_empty() {
+        req = req.bearer_auth(&conf.alarm.sms_gateway_token);
+    }
+
+    req.send().await?.error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test;
+    use httpmock::prelude::*;
+
+    #[tokio::test]
+    async fn test_send() {
+        let _guard = test::prepare().await;
+
+        let u = user::create(user::User {
+            email: "sms@example.com".into(),
+            phone_number: "+905551112233".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let user_ids = vec![u.id.into()];
+
+        // no SMS gateway configured
+        assert_eq!(
+            0,
+            send("0102030405060708", &user_ids, "alarm").await.unwrap()
+        );
+
+        let server = MockServer::start();
+        let mut mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/sms")
+                .header("Authorization", "Bearer secret")
+                .json_body(json!({
+                    "to": "+905551112233",
+                    "message": "alarm",
+                }));
+            then.status(200);
+        });
+
+        let mut conf = (*config::get()).clone();
+        conf.alarm.sms_gateway_url = server.url("/sms");
+        conf.alarm.sms_gateway_token = "secret".into();
+        config::set(conf);
+
+        assert_eq!(
+            1,
+            send("0102030405060708", &user_ids, "alarm").await.unwrap()
+        );
+        mock.assert();
+        mock.delete();
+
+        // the gateway fails
+        server.mock(|when, then| {
+            when.method(POST).path("/sms");
+            then.status(500);
+        });
+        assert_eq!(
+            0,
+            send("0102030405060708", &user_ids, "alarm").await.unwrap()
+        );
+    }
+}
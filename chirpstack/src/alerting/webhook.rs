@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE};
+use reqwest::Client;
+use sha2::Sha256;
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
+
+use crate::config;
+use crate::storage::{notification, webhook};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Deliveries claimed at once. The deliveries are posted one after the other, the claim lock
+// covers the timeout of all of them.
+const BATCH_SIZE: usize = 10;
+
+// Upper bound of the retry delay.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(6 * 60 * 60);
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.alarm.webhook_check_interval.is_zero() {
+        info!("Webhook deliveries are disabled");
+        return;
+    }
+
+    info!("Setting up webhook delivery loop");
+    tokio::spawn(async move {
+        webhook_loop().await;
+    });
+}
+
+pub async fn webhook_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting webhook delivery loop run");
+
+        if let Err(err) = run().await {
+            error!(error = %err, "Webhook delivery loop run failed");
+        } else {
+            trace!("Webhook delivery loop run completed successfully");
+        }
+
+        sleep(conf.alarm.webhook_check_interval).await;
+    }
+}
+
+async fn run() -> Result<()> {
+    let conf = config::get();
+    let client = Client::builder()
+        .timeout(conf.alarm.webhook_timeout)
+        .build()?;
+    let lock_timeout =
+        chrono::Duration::from_std(conf.alarm.webhook_timeout)? * (BATCH_SIZE as i32 + 1);
+
+    loop {
+        let items = webhook::claim(BATCH_SIZE, lock_timeout).await?;
+        for d in &items {
+            if let Err(err) = deliver(&client, d).await {
+                error!(id = d.id, error = %err, "Handling webhook delivery failed");
+            }
+        }
+
+        // A full batch means that there might be more deliveries which are due.
+        if items.len() < BATCH_SIZE {
+            break;
+        }
+    }
+
+    let count = webhook::delete_deliveries_older_than(
+        Utc::now() - chrono::Duration::days(conf.alarm.webhook_log_days as i64),
+    )
+    .await?;
+    trace!(count = count, "Deleted old webhook deliveries");
+
+    Ok(())
+}
+
+async fn deliver(client: &Client, d: &webhook::Delivery) -> Result<()> {
+    let conf = config::get();
+    let sub = webhook::get(d.subscription_id).await?;
+
+    if sub.is_disabled {
+        webhook::set_dead(d.id, None, "Webhook is disabled").await?;
+        return Ok(());
+    }
+
+    let timestamp = Utc::now().timestamp();
+    let res = client
+        .post(&sub.url)
+        .headers(get_headers(d, &sub.secret, timestamp)?)
+        .body(d.payload.clone())
+        .send()
+        .await;
+
+    let (status, err) = match res {
+        Ok(resp) => {
+            let status = resp.status();
+            if status.is_success() {
+                (Some(status.as_u16() as i32), None)
+            } else {
+                (
+                    Some(status.as_u16() as i32),
+                    Some(format!("Unexpected response status: {}", status)),
+                )
+            }
+        }
+        Err(e) => (e.status().map(|s| s.as_u16() as i32), Some(e.to_string())),
+    };
+
+    match err {
+        None => {
+            notification::record_delivery("webhook", "success");
+            webhook::set_delivered(d.id, status.unwrap_or_default()).await?;
+            info!(id = d.id, webhook_id = sub.id, event = %d.event, "Webhook event delivered");
+        }
+        Some(err) if d.attempts as u32 >= conf.alarm.webhook_max_attempts => {
+            notification::record_delivery("webhook", "dead");
+            webhook::set_dead(d.id, status, &err).await?;
+        }
+        Some(err) => {
+            notification::record_delivery("webhook", "error");
+            warn!(id = d.id, webhook_id = sub.id, attempts = d.attempts, error = %err, "Webhook delivery failed");
+            let delay = get_retry_delay(conf.alarm.webhook_retry_delay, d.attempts);
+            webhook::retry(d.id, chrono::Duration::from_std(delay)?, status, &err).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the request headers. The signature is the hex-encoded HMAC-SHA256 of
+// "{timestamp}.{payload}", using the webhook secret as key. Including the timestamp allows the
+// receiver to reject replayed requests.
+fn get_headers(d: &webhook::Delivery, secret: &str, timestamp: i64) -> Result<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(
+        HeaderName::from_static("x-webhook-event"),
+        HeaderValue::from_str(&d.event)?,
+    );
+    headers.insert(
+        HeaderName::from_static("x-webhook-delivery"),
+        HeaderValue::from_str(&d.id.to_string())?,
+    );
+    headers.insert(
+        HeaderName::from_static("x-webhook-timestamp"),
+        HeaderValue::from_str(&timestamp.to_string())?,
+    );
+    headers.insert(
+        HeaderName::from_static("x-webhook-signature"),
+        HeaderValue::from_str(&sign(secret, timestamp, &d.payload)?)?,
+    );
+    Ok(headers)
+}
+
+pub fn sign(secret: &str, timestamp: i64, payload: &str) -> Result<String> {
+    let mut m = HmacSha256::new_from_slice(secret.as_bytes())?;
+    m.update(format!("{}.{}", timestamp, payload).as_bytes());
+    Ok(format!("sha256={}", hex::encode(m.finalize().into_bytes())))
+}
+
+// Returns the delay before the next attempt, the retry delay is doubled on every attempt.
+fn get_retry_delay(retry_delay: Duration, attempts: i32) -> Duration {
+    let exp = (attempts - 1).clamp(0, 16) as u32;
+    retry_delay
+        .saturating_mul(2u32.pow(exp))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        assert_eq!(
+            "sha256=65f25f2f0f45f5dbb7af42ea37659af048223af1764c788fec8f7f69d04a338e",
+            sign("secret", 1700000000, r#"{"event":"alarm.raised"}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_retry_delay() {
+        let d = Duration::from_secs(30);
+        assert_eq!(Duration::from_secs(30), get_retry_delay(d, 1));
+        assert_eq!(Duration::from_secs(60), get_retry_delay(d, 2));
+        assert_eq!(Duration::from_secs(240), get_retry_delay(d, 4));
+        assert_eq!(MAX_RETRY_DELAY, get_retry_delay(d, 20));
+    }
+}
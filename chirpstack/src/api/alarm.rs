@@ -1,16 +1,28 @@
 use super::auth::validator::{self};
 use std::str::FromStr;
+use std::time::SystemTime;
 use tracing::info;
 
 use super::auth::AuthID;
-use super::helpers::{self};
+use super::error::ToStatus;
+use super::helpers::{self, FromProto, ToProto};
 use chirpstack_api::api;
 use chirpstack_api::api::alarm_service_server::AlarmService;
+use chirpstack_api::api::DefrostCycleStatus;
 use chirpstack_api::api::CreateDoorTimeResponse; // Import the correct AlarmDateTime type
 use lrwn::EUI64;
 
+use crate::alerting;
 use crate::storage::alarm::{self, AlarmDateTime, UpdateAlarm};
-use tonic::{Request, Response, Status};
+use crate::storage::alarm_rule;
+use crate::storage::defrost;
+use crate::storage::escalation;
+use crate::storage::maintenance;
+use crate::storage::webhook;
+use crate::storage::zone;
+use crate::storage::{application, device, site};
+use tonic::{Extensions, Request, Response, Status};
+use uuid::Uuid;
 
 pub struct Alarm {
     validator: validator::RequestValidator,
@@ -20,6 +32,36 @@ impl Alarm {
     pub fn new(validator: validator::RequestValidator) -> Self {
         Alarm { validator }
     }
+
+    // Validates that the user can manage the defrost schedules of the device and / or zone.
+    // The defrost windows suppress the temperature alarms, therefore this requires the same
+    // access as managing the tenant users.
+    async fn validate_defrost_target(
+        &self,
+        ext: &Extensions,
+        dev_eui: Option<EUI64>,
+        zone_id: i64,
+    ) -> Result<(), Status> {
+        if dev_eui.is_some() {
+            let tenant_id = get_tenant_id(dev_eui, 0, 0).await?;
+            self.validator
+                .validate(
+                    ext,
+                    validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+                )
+                .await?;
+        }
+        if zone_id != 0 {
+            let tenant_id = get_tenant_id(None, zone_id, 0).await?;
+            self.validator
+                .validate(
+                    ext,
+                    validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+                )
+                .await?;
+        }
+        Ok(())
+    }
 }
 
 #[tonic::async_trait]
@@ -46,9 +88,7 @@ impl AlarmService for Alarm {
         let mut response_alarms: Vec<api::Alarm> = Vec::new();
 
         for proto_alarm in req {
-            let dev_eui = EUI64::from_str(&proto_alarm.dev_eui).map_err(|_| {
-                Status::invalid_argument("Invalid dev_eui, must be a valid EUI64 string")
-            })?;
+            let dev_eui = get_alarm_dev_eui(proto_alarm).await?;
 
             if proto_alarm.min_treshold > proto_alarm.max_treshold {
                 return Err(Status::invalid_argument(
@@ -57,7 +97,7 @@ impl AlarmService for Alarm {
             }
 
             let alarm = alarm::NewAlarm {
-                dev_eui: dev_eui.to_string(),
+                dev_eui,
                 min_treshold: Some(proto_alarm.min_treshold as f64),
                 max_treshold: Some(proto_alarm.max_treshold as f64),
                 sms: Some(proto_alarm.sms),
@@ -74,6 +114,11 @@ impl AlarmService for Alarm {
                 pressure: Some(proto_alarm.pressure),
                 distance: Some(proto_alarm.distance),
                 defrost_time: Some(proto_alarm.defrost_time as i32),
+                no_data: Some(proto_alarm.no_data),
+                no_data_time: Some(proto_alarm.no_data_time as i32),
+                rule_type: proto_alarm.rule_type,
+                rule_duration: Some(proto_alarm.rule_duration as i32),
+                rule_limit: Some(proto_alarm.rule_limit),
                 is_time_limit_active: Some(proto_alarm.is_time_scheduled),
                 alarm_start_time: Some(proto_alarm.start_time as f64),
                 alarm_stop_time: Some(proto_alarm.end_time as f64),
@@ -122,6 +167,11 @@ impl AlarmService for Alarm {
                 notification_sound: stored_alarm.notification_sound.clone().unwrap_or_default(),
                 distance: stored_alarm.distance.unwrap_or(false),
                 defrost_time: stored_alarm.defrost_time.unwrap_or(0) as i64,
+                no_data: stored_alarm.no_data.unwrap_or(false),
+                no_data_time: stored_alarm.no_data_time.unwrap_or(0) as i64,
+                rule_type: stored_alarm.rule_type,
+                rule_duration: stored_alarm.rule_duration.unwrap_or(0) as i64,
+                rule_limit: stored_alarm.rule_limit.unwrap_or(0.0),
                 alarm_date_time: alarm_dates
                     .iter()
                     .map(|dt| api::AlarmDateTime {
@@ -185,6 +235,11 @@ impl AlarmService for Alarm {
             notification_sound: stored_alarm.notification_sound.unwrap_or_default(),
             distance: stored_alarm.distance.unwrap_or(false),
             defrost_time: stored_alarm.defrost_time.unwrap_or_default() as i64,
+            no_data: stored_alarm.no_data.unwrap_or(false),
+            no_data_time: stored_alarm.no_data_time.unwrap_or_default() as i64,
+            rule_type: stored_alarm.rule_type,
+            rule_duration: stored_alarm.rule_duration.unwrap_or_default() as i64,
+            rule_limit: stored_alarm.rule_limit.unwrap_or_default(),
             alarm_date_time: alarm_dates
                 .iter()
                 .map(|dt| api::AlarmDateTime {
@@ -253,6 +308,11 @@ impl AlarmService for Alarm {
                 notification_sound: alarm.notification_sound.unwrap_or_default(),
                 distance: alarm.distance.unwrap_or(false),
                 defrost_time: alarm.defrost_time.unwrap_or_default() as i64,
+                no_data: alarm.no_data.unwrap_or(false),
+                no_data_time: alarm.no_data_time.unwrap_or_default() as i64,
+                rule_type: alarm.rule_type,
+                rule_duration: alarm.rule_duration.unwrap_or_default() as i64,
+                rule_limit: alarm.rule_limit.unwrap_or_default(),
                 is_time_scheduled: alarm.is_time_limit_active.unwrap_or(false),
                 submission_date: Some(helpers::datetime_to_prost_timestamp(
                     (&chrono::Utc::now()).into(),
@@ -303,9 +363,7 @@ impl AlarmService for Alarm {
         let mut _response_alarms: Vec<api::Alarm> = Vec::new();
 
         if let Some(proto_alarm) = req {
-            let dev_eui = EUI64::from_str(&proto_alarm.dev_eui).map_err(|_| {
-                Status::invalid_argument("Invalid dev_eui, must be a valid EUI64 string")
-            })?;
+            get_alarm_dev_eui(proto_alarm).await?;
 
             if proto_alarm.min_treshold > proto_alarm.max_treshold {
                 return Err(Status::invalid_argument(
@@ -336,9 +394,15 @@ impl AlarmService for Alarm {
                     .user_ids
                     .iter()
                     .filter_map(|id| uuid::Uuid::parse_str(id).ok())
+                    .map(Some)
                     .collect(),
                 is_active: Some(true),
                 defrost_time: Some(proto_alarm.defrost_time as i32),
+                no_data: Some(proto_alarm.no_data),
+                no_data_time: Some(proto_alarm.no_data_time as i32),
+                rule_type: Some(proto_alarm.rule_type),
+                rule_duration: Some(proto_alarm.rule_duration as i32),
+                rule_limit: Some(proto_alarm.rule_limit),
             };
 
             // Build date filters (if provided)
@@ -421,9 +485,7 @@ impl AlarmService for Alarm {
         let mut response_alarms: Vec<api::Alarm> = Vec::new();
 
         for proto_alarm in req {
-            let dev_eui = EUI64::from_str(&proto_alarm.dev_eui).map_err(|_| {
-                Status::invalid_argument("Invalid dev_eui, must be a valid EUI64 string")
-            })?;
+            let dev_eui = get_alarm_dev_eui(proto_alarm).await?;
 
             if proto_alarm.min_treshold > proto_alarm.max_treshold {
                 return Err(Status::invalid_argument(
@@ -432,7 +494,7 @@ impl AlarmService for Alarm {
             }
 
             let alarm = alarm::NewAlarm {
-                dev_eui: dev_eui.to_string(),
+                dev_eui,
                 min_treshold: Some(proto_alarm.min_treshold as f64),
                 max_treshold: Some(proto_alarm.max_treshold as f64),
                 sms: Some(proto_alarm.sms),
@@ -449,6 +511,11 @@ impl AlarmService for Alarm {
                 pressure: Some(proto_alarm.pressure),
                 distance: Some(proto_alarm.distance),
                 defrost_time: Some(proto_alarm.defrost_time as i32),
+                no_data: Some(proto_alarm.no_data),
+                no_data_time: Some(proto_alarm.no_data_time as i32),
+                rule_type: proto_alarm.rule_type,
+                rule_duration: Some(proto_alarm.rule_duration as i32),
+                rule_limit: Some(proto_alarm.rule_limit),
                 is_time_limit_active: Some(proto_alarm.is_time_scheduled),
                 alarm_start_time: Some(proto_alarm.start_time as f64),
                 alarm_stop_time: Some(proto_alarm.end_time as f64),
@@ -497,6 +564,11 @@ impl AlarmService for Alarm {
                 notification_sound: stored_alarm.notification_sound.clone().unwrap_or_default(),
                 distance: stored_alarm.distance.unwrap_or(false),
                 defrost_time: stored_alarm.defrost_time.unwrap_or(0) as i64,
+                no_data: stored_alarm.no_data.unwrap_or(false),
+                no_data_time: stored_alarm.no_data_time.unwrap_or(0) as i64,
+                rule_type: stored_alarm.rule_type,
+                rule_duration: stored_alarm.rule_duration.unwrap_or(0) as i64,
+                rule_limit: stored_alarm.rule_limit.unwrap_or(0.0),
                 alarm_date_time: alarm_dates
                     .iter()
                     .map(|dt| api::AlarmDateTime {
@@ -651,9 +723,13 @@ impl AlarmService for Alarm {
                     .map(|id| uuid::Uuid::parse_str(id).ok())
                     .collect(),
             ),
-            tenant_id: Some(uuid::Uuid::parse_str(&req.organization_id).map_err(|_| {
-                Status::invalid_argument("Invalid tenant_id, must be a valid UUID string")
-            })?),
+            tenant_id: Some(
+                uuid::Uuid::parse_str(&req.organization_id)
+                    .map_err(|_| {
+                        Status::invalid_argument("Invalid tenant_id, must be a valid UUID string")
+                    })?
+                    .into(),
+            ),
             submission_time: None,
         };
 
@@ -777,9 +853,15 @@ impl AlarmService for Alarm {
                         .map(|id| uuid::Uuid::parse_str(id).ok())
                         .collect(),
                 ),
-                tenant_id: Some(uuid::Uuid::parse_str(&create_req.organization_id).map_err(
-                    |_| Status::invalid_argument("Invalid tenant_id, must be a valid UUID string"),
-                )?),
+                tenant_id: Some(
+                    uuid::Uuid::parse_str(&create_req.organization_id)
+                        .map_err(|_| {
+                            Status::invalid_argument(
+                                "Invalid tenant_id, must be a valid UUID string",
+                            )
+                        })?
+                        .into(),
+                ),
                 submission_time: None,
                 id: 0,
             };
@@ -814,9 +896,11 @@ impl AlarmService for Alarm {
             action: Some(alarm_automation.action.clone()),
             is_active: Some(true),
             user_id: Some(
-                uuid::Uuid::parse_str(&alarm_automation.user_id).map_err(|_| {
-                    Status::invalid_argument("Invalid user_id, must be a valid UUID string")
-                })?,
+                uuid::Uuid::parse_str(&alarm_automation.user_id)
+                    .map_err(|_| {
+                        Status::invalid_argument("Invalid user_id, must be a valid UUID string")
+                    })?
+                    .into(),
             ),
             receiver_device_type: Some(alarm_automation.receiver_device_type as i32),
             receiver_device_name: Some(alarm_automation.receiver_device_name.clone())
@@ -923,9 +1007,13 @@ impl AlarmService for Alarm {
             alarm_id: automation.alarm_id as i32,
             receiver_sensor: automation.receiver_sensor.clone(),
             action: Some(automation.action.clone()),
-            user_id: Some(uuid::Uuid::parse_str(&automation.user_id).map_err(|_| {
-                Status::invalid_argument("Invalid user_id, must be a valid UUID string")
-            })?),
+            user_id: Some(
+                uuid::Uuid::parse_str(&automation.user_id)
+                    .map_err(|_| {
+                        Status::invalid_argument("Invalid user_id, must be a valid UUID string")
+                    })?
+                    .into(),
+            ),
             receiver_device_type: Some(automation.receiver_device_type as i32),
             receiver_device_name: Some(automation.receiver_device_name.clone()),
             is_active: Some(automation.is_active),
@@ -988,4 +1076,1205 @@ impl AlarmService for Alarm {
 
         Ok(Response::new(api::GetAuditLogsResponse { result }))
     }
+
+    async fn create_defrost_schedule(
+        &self,
+        request: Request<api::CreateDefrostScheduleRequest>,
+    ) -> Result<Response<api::CreateDefrostScheduleResponse>, Status> {
+        let req = match &request.get_ref().schedule {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("schedule is missing"));
+            }
+        };
+
+        let dev_eui = if req.dev_eui.is_empty() {
+            None
+        } else {
+            Some(
+                EUI64::from_str(&req.dev_eui)
+                    .map_err(|_| Status::invalid_argument("Invalid dev_eui"))?,
+            )
+        };
+
+        self.validate_defrost_target(request.extensions(), dev_eui, req.zone_id)
+            .await?;
+
+        let s = defrost::create_schedule(defrost::NewDefrostSchedule {
+            zone_id: if req.zone_id == 0 {
+                None
+            } else {
+                Some(req.zone_id as i32)
+            },
+            dev_eui: dev_eui.map(|v| v.to_string()),
+            start_time: req.start_time,
+            duration: req.duration as i32,
+            learned: false,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::CreateDefrostScheduleResponse {
+            id: s.id as i64,
+        }))
+    }
+
+    async fn list_defrost_schedules(
+        &self,
+        request: Request<api::ListDefrostSchedulesRequest>,
+    ) -> Result<Response<api::ListDefrostSchedulesResponse>, Status> {
+        let req = request.get_ref();
+        let zone_id = if req.zone_id == 0 {
+            None
+        } else {
+            Some(req.zone_id as i32)
+        };
+        let dev_eui = if req.dev_eui.is_empty() {
+            None
+        } else {
+            Some(
+                EUI64::from_str(&req.dev_eui)
+                    .map_err(|_| Status::invalid_argument("Invalid dev_eui"))?,
+            )
+        };
+
+        if zone_id.is_none() && dev_eui.is_none() {
+            return Err(Status::invalid_argument("zone_id or dev_eui is required"));
+        }
+
+        if let Some(dev_eui) = dev_eui {
+            self.validator
+                .validate(
+                    request.extensions(),
+                    validator::ValidateDeviceAccess::new(validator::Flag::Read, dev_eui),
+                )
+                .await?;
+        }
+        if zone_id.is_some() {
+            let tenant_id = get_tenant_id(None, req.zone_id, 0).await?;
+            self.validator
+                .validate(
+                    request.extensions(),
+                    validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+                )
+                .await?;
+        }
+
+        let dev_eui = dev_eui.map(|v| v.to_string());
+        let items = defrost::list_schedules(zone_id, dev_eui.as_deref())
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListDefrostSchedulesResponse {
+            result: items.iter().map(defrost_schedule_to_api).collect(),
+        }))
+    }
+
+    async fn delete_defrost_schedule(
+        &self,
+        request: Request<api::DeleteDefrostScheduleRequest>,
+    ) -> Result<Response<()>, Status> {
+        let s = defrost::get_schedule(request.get_ref().id as i32)
+            .await
+            .map_err(|e| e.status())?;
+        let dev_eui = match &s.dev_eui {
+            Some(v) => Some(EUI64::from_str(v).map_err(|e| e.status())?),
+            None => None,
+        };
+
+        self.validate_defrost_target(
+            request.extensions(),
+            dev_eui,
+            s.zone_id.unwrap_or_default() as i64,
+        )
+        .await?;
+
+        defrost::delete_schedule(s.id)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn learn_defrost_schedule(
+        &self,
+        request: Request<api::LearnDefrostScheduleRequest>,
+    ) -> Result<Response<api::ListDefrostSchedulesResponse>, Status> {
+        let dev_eui = EUI64::from_str(&request.get_ref().dev_eui)
+            .map_err(|_| Status::invalid_argument("Invalid dev_eui"))?;
+
+        self.validate_defrost_target(request.extensions(), Some(dev_eui), 0)
+            .await?;
+
+        let items = alerting::defrost::learn(&dev_eui).await.map_err(|e| {
+            Status::internal(format!("Failed to learn defrost schedule: {}", e))
+        })?;
+
+        Ok(Response::new(api::ListDefrostSchedulesResponse {
+            result: items.iter().map(defrost_schedule_to_api).collect(),
+        }))
+    }
+
+    async fn stop_defrost_learning(
+        &self,
+        request: Request<api::StopDefrostLearningRequest>,
+    ) -> Result<Response<()>, Status> {
+        let dev_eui = EUI64::from_str(&request.get_ref().dev_eui)
+            .map_err(|_| Status::invalid_argument("Invalid dev_eui"))?;
+
+        self.validate_defrost_target(request.extensions(), Some(dev_eui), 0)
+            .await?;
+
+        defrost::stop_learning(&dev_eui.to_string())
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn list_defrost_cycles(
+        &self,
+        request: Request<api::ListDefrostCyclesRequest>,
+    ) -> Result<Response<api::ListDefrostCyclesResponse>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui)
+            .map_err(|_| Status::invalid_argument("Invalid dev_eui"))?;
+        let limit = if req.limit == 0 { 100 } else { req.limit as i64 };
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Read, dev_eui),
+            )
+            .await?;
+
+        let items = defrost::list_cycles(&dev_eui.to_string(), limit)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListDefrostCyclesResponse {
+            result: items
+                .iter()
+                .map(|c| api::DefrostCycle {
+                    id: c.id as i64,
+                    dev_eui: c.dev_eui.clone(),
+                    schedule_id: c.schedule_id.unwrap_or_default() as i64,
+                    window_start: Some(helpers::datetime_to_prost_timestamp(&c.window_start)),
+                    started_at: c
+                        .started_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    ended_at: c
+                        .ended_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    peak_temperature: c.peak_temperature.unwrap_or_default(),
+                    status: match defrost::CycleStatus::from(c.status) {
+                        defrost::CycleStatus::Completed => DefrostCycleStatus::DefrostCompleted,
+                        defrost::CycleStatus::Missed => DefrostCycleStatus::DefrostMissed,
+                        defrost::CycleStatus::TooLong => DefrostCycleStatus::DefrostTooLong,
+                    }
+                    .into(),
+                })
+                .collect(),
+        }))
+    }
+    async fn create_escalation_policy(
+        &self,
+        request: Request<api::CreateEscalationPolicyRequest>,
+    ) -> Result<Response<api::CreateEscalationPolicyResponse>, Status> {
+        let req_p = match &request.get_ref().policy {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("policy is missing"));
+            }
+        };
+        let tenant_id = Uuid::from_str(&req_p.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+            )
+            .await?;
+
+        let p = escalation::create_policy(
+            escalation::EscalationPolicy {
+                tenant_id: tenant_id.into(),
+                name: req_p.name.clone(),
+                ..Default::default()
+            },
+            &escalation_steps_from_proto(&req_p.steps)?,
+        )
+        .await
+        .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::CreateEscalationPolicyResponse {
+            id: p.id as i64,
+        }))
+    }
+
+    async fn get_escalation_policy(
+        &self,
+        request: Request<api::GetEscalationPolicyRequest>,
+    ) -> Result<Response<api::GetEscalationPolicyResponse>, Status> {
+        let p = escalation::get_policy(request.get_ref().id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, p.tenant_id.into()),
+            )
+            .await?;
+
+        let steps = escalation::get_steps(p.id).await.map_err(|e| e.status())?;
+
+        Ok(Response::new(api::GetEscalationPolicyResponse {
+            policy: Some(escalation_policy_to_api(&p, &steps)),
+            created_at: Some(helpers::datetime_to_prost_timestamp(&p.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&p.updated_at)),
+        }))
+    }
+
+    async fn update_escalation_policy(
+        &self,
+        request: Request<api::UpdateEscalationPolicyRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_p = match &request.get_ref().policy {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("policy is missing"));
+            }
+        };
+        let p = escalation::get_policy(req_p.id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    p.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        escalation::update_policy(
+            escalation::EscalationPolicy {
+                name: req_p.name.clone(),
+                ..p
+            },
+            &escalation_steps_from_proto(&req_p.steps)?,
+        )
+        .await
+        .map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn delete_escalation_policy(
+        &self,
+        request: Request<api::DeleteEscalationPolicyRequest>,
+    ) -> Result<Response<()>, Status> {
+        let p = escalation::get_policy(request.get_ref().id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    p.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        escalation::delete_policy(p.id)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn list_escalation_policies(
+        &self,
+        request: Request<api::ListEscalationPoliciesRequest>,
+    ) -> Result<Response<api::ListEscalationPoliciesResponse>, Status> {
+        let tenant_id = Uuid::from_str(&request.get_ref().tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let items = escalation::list_policies(&tenant_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut result = Vec::with_capacity(items.len());
+        for p in &items {
+            let steps = escalation::get_steps(p.id).await.map_err(|e| e.status())?;
+            result.push(escalation_policy_to_api(p, &steps));
+        }
+
+        Ok(Response::new(api::ListEscalationPoliciesResponse {
+            result,
+        }))
+    }
+
+    async fn set_escalation_policy(
+        &self,
+        request: Request<api::SetEscalationPolicyRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        if (req.alarm_id == 0) == (req.zone_id == 0) {
+            return Err(Status::invalid_argument(
+                "Either alarm_id or zone_id is required",
+            ));
+        }
+
+        let tenant_id = if req.zone_id != 0 {
+            get_tenant_id(None, req.zone_id, 0).await?
+        } else {
+            get_alarm_tenant_id(req.alarm_id as i32).await?
+        };
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+            )
+            .await?;
+
+        let policy_id = if req.policy_id == 0 {
+            None
+        } else {
+            let p = escalation::get_policy(req.policy_id as i32)
+                .await
+                .map_err(|e| e.status())?;
+
+            if Uuid::from(p.tenant_id) != tenant_id {
+                return Err(Status::invalid_argument(
+                    "Alarm or zone and escalation policy must belong to the same tenant",
+                ));
+            }
+
+            Some(p.id)
+        };
+
+        if req.zone_id != 0 {
+            escalation::set_zone_policy(req.zone_id as i32, policy_id)
+                .await
+                .map_err(|e| e.status())?;
+        } else {
+            escalation::set_alarm_policy(req.alarm_id as i32, policy_id)
+                .await
+                .map_err(|e| e.status())?;
+        }
+
+        Ok(Response::new(()))
+    }
+
+    async fn acknowledge_alarm(
+        &self,
+        request: Request<api::AcknowledgeAlarmRequest>,
+    ) -> Result<Response<api::AcknowledgeAlarmResponse>, Status> {
+        let user_id = match request.extensions().get::<AuthID>() {
+            Some(AuthID::User(id)) => *id,
+            _ => {
+                return Err(Status::unauthenticated("no user id"));
+            }
+        };
+
+        let alarm_id = request.get_ref().alarm_id as i32;
+        let tenant_id = get_alarm_tenant_id(alarm_id).await?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let items = escalation::acknowledge(alarm_id, &user_id)
+            .await
+            .map_err(|e| e.status())?;
+        webhook::alarm_acknowledged(alarm_id, &user_id, items.first()).await;
+
+        Ok(Response::new(api::AcknowledgeAlarmResponse {
+            acknowledged: items.len() as u32,
+        }))
+    }
+
+    async fn list_alarm_escalations(
+        &self,
+        request: Request<api::ListAlarmEscalationsRequest>,
+    ) -> Result<Response<api::ListAlarmEscalationsResponse>, Status> {
+        let req = request.get_ref();
+        let alarm_id = if req.alarm_id == 0 {
+            None
+        } else {
+            Some(req.alarm_id as i32)
+        };
+        let dev_eui = if req.dev_eui.is_empty() {
+            None
+        } else {
+            Some(
+                EUI64::from_str(&req.dev_eui)
+                    .map_err(|_| Status::invalid_argument("Invalid dev_eui"))?,
+            )
+        };
+
+        if alarm_id.is_none() && dev_eui.is_none() {
+            return Err(Status::invalid_argument("alarm_id or dev_eui is required"));
+        }
+
+        if let Some(alarm_id) = alarm_id {
+            let tenant_id = get_alarm_tenant_id(alarm_id).await?;
+            self.validator
+                .validate(
+                    request.extensions(),
+                    validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+                )
+                .await?;
+        }
+        if let Some(dev_eui) = dev_eui {
+            self.validator
+                .validate(
+                    request.extensions(),
+                    validator::ValidateDeviceAccess::new(validator::Flag::Read, dev_eui),
+                )
+                .await?;
+        }
+        let dev_eui = dev_eui.map(|v| v.to_string());
+        let limit = if req.limit == 0 {
+            100
+        } else {
+            req.limit as i64
+        };
+
+        let items = escalation::list(alarm_id, dev_eui.as_deref(), limit)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListAlarmEscalationsResponse {
+            result: items
+                .iter()
+                .map(|e| api::AlarmEscalation {
+                    id: e.id as i64,
+                    alarm_id: e.alarm_id as i64,
+                    policy_id: e.policy_id as i64,
+                    dev_eui: e.dev_eui.clone(),
+                    message: e.message.clone(),
+                    level: e.level,
+                    triggered_at: Some(helpers::datetime_to_prost_timestamp(&e.triggered_at)),
+                    next_at: e.next_at.as_ref().map(helpers::datetime_to_prost_timestamp),
+                    acknowledged_at: e
+                        .acknowledged_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                    acknowledged_by: e.acknowledged_by.map(|v| v.to_string()).unwrap_or_default(),
+                })
+                .collect(),
+        }))
+    }
+    async fn create_maintenance_window(
+        &self,
+        request: Request<api::CreateMaintenanceWindowRequest>,
+    ) -> Result<Response<api::CreateMaintenanceWindowResponse>, Status> {
+        let req_w = match &request.get_ref().window {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("window is missing"));
+            }
+        };
+        let user_id = match request.extensions().get::<AuthID>() {
+            Some(AuthID::User(id)) => Some(*id),
+            _ => None,
+        };
+
+        let dev_eui = if req_w.dev_eui.is_empty() {
+            None
+        } else {
+            Some(
+                EUI64::from_str(&req_w.dev_eui)
+                    .map_err(|_| Status::invalid_argument("Invalid dev_eui"))?,
+            )
+        };
+        let tenant_id = get_tenant_id(dev_eui, req_w.zone_id, req_w.site_id).await?;
+
+        // Any user of the tenant can start a maintenance window, e.g. the technician servicing
+        // a cold room.
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let start_at = match &req_w.start_at {
+            Some(v) => SystemTime::try_from(*v).map_err(|e| e.status())?.into(),
+            None => chrono::Utc::now(),
+        };
+        let end_at: chrono::DateTime<chrono::Utc> = SystemTime::try_from(
+            *req_w
+                .end_at
+                .as_ref()
+                .ok_or_else(|| Status::invalid_argument("end_at is missing"))?,
+        )
+        .map_err(|e| e.status())?
+        .into();
+
+        let w = maintenance::create(maintenance::MaintenanceWindow {
+            tenant_id: tenant_id.into(),
+            dev_eui: dev_eui.map(|v| v.to_string()),
+            zone_id: (req_w.zone_id != 0).then_some(req_w.zone_id as i32),
+            site_id: (req_w.site_id != 0).then_some(req_w.site_id as i32),
+            start_at,
+            end_at,
+            reason: req_w.reason.clone(),
+            created_by: user_id.map(|v| v.into()),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::CreateMaintenanceWindowResponse {
+            id: w.id as i64,
+        }))
+    }
+
+    async fn end_maintenance_window(
+        &self,
+        request: Request<api::EndMaintenanceWindowRequest>,
+    ) -> Result<Response<()>, Status> {
+        let w = maintenance::get(request.get_ref().id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, w.tenant_id.into()),
+            )
+            .await?;
+
+        maintenance::end(w.id).await.map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn delete_maintenance_window(
+        &self,
+        request: Request<api::DeleteMaintenanceWindowRequest>,
+    ) -> Result<Response<()>, Status> {
+        let w = maintenance::get(request.get_ref().id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    w.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        maintenance::delete(w.id).await.map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn list_maintenance_windows(
+        &self,
+        request: Request<api::ListMaintenanceWindowsRequest>,
+    ) -> Result<Response<api::ListMaintenanceWindowsResponse>, Status> {
+        let req = request.get_ref();
+        let tenant_id = Uuid::from_str(&req.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let items = maintenance::list(&tenant_id, req.include_ended)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListMaintenanceWindowsResponse {
+            result: items
+                .iter()
+                .map(|w| api::MaintenanceWindow {
+                    id: w.id as i64,
+                    tenant_id: w.tenant_id.to_string(),
+                    dev_eui: w.dev_eui.clone().unwrap_or_default(),
+                    zone_id: w.zone_id.unwrap_or_default() as i64,
+                    site_id: w.site_id.unwrap_or_default() as i64,
+                    start_at: Some(helpers::datetime_to_prost_timestamp(&w.start_at)),
+                    end_at: Some(helpers::datetime_to_prost_timestamp(&w.end_at)),
+                    reason: w.reason.clone(),
+                    created_by: w.created_by.map(|v| v.to_string()).unwrap_or_default(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&w.created_at)),
+                })
+                .collect(),
+        }))
+    }
+
+    async fn snooze_alarm(
+        &self,
+        request: Request<api::SnoozeAlarmRequest>,
+    ) -> Result<Response<()>, Status> {
+        let user_id = match request.extensions().get::<AuthID>() {
+            Some(AuthID::User(id)) => *id,
+            _ => {
+                return Err(Status::unauthenticated("no user id"));
+            }
+        };
+        let req = request.get_ref();
+        let tenant_id = get_alarm_tenant_id(req.alarm_id as i32).await?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        maintenance::snooze(req.alarm_id as i32, &user_id, req.minutes as i64)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn unsnooze_alarm(
+        &self,
+        request: Request<api::UnsnoozeAlarmRequest>,
+    ) -> Result<Response<()>, Status> {
+        let user_id = match request.extensions().get::<AuthID>() {
+            Some(AuthID::User(id)) => *id,
+            _ => {
+                return Err(Status::unauthenticated("no user id"));
+            }
+        };
+
+        let alarm_id = request.get_ref().alarm_id as i32;
+        let tenant_id = get_alarm_tenant_id(alarm_id).await?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        maintenance::unsnooze(alarm_id, &user_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn list_alarm_snoozes(
+        &self,
+        request: Request<()>,
+    ) -> Result<Response<api::ListAlarmSnoozesResponse>, Status> {
+        let user_id = match request.extensions().get::<AuthID>() {
+            Some(AuthID::User(id)) => *id,
+            _ => {
+                return Err(Status::unauthenticated("no user id"));
+            }
+        };
+
+        let items = maintenance::list_snoozes(&user_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListAlarmSnoozesResponse {
+            result: items
+                .iter()
+                .map(|s| api::AlarmSnooze {
+                    alarm_id: s.alarm_id as i64,
+                    snoozed_until: Some(helpers::datetime_to_prost_timestamp(&s.snoozed_until)),
+                })
+                .collect(),
+        }))
+    }
+    async fn simulate(
+        &self,
+        request: Request<api::SimulateAlarmRequest>,
+    ) -> Result<Response<api::SimulateAlarmResponse>, Status> {
+        let req = request.get_ref();
+        let req_a = match &req.alarm {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("alarm is missing"));
+            }
+        };
+        let dev_eui = EUI64::from_str(&req_a.dev_eui)
+            .map_err(|_| Status::invalid_argument("Invalid dev_eui"))?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Read, dev_eui),
+            )
+            .await?;
+
+        if req_a.min_treshold > req_a.max_treshold {
+            return Err(Status::invalid_argument(
+                "Maksimum değer minimum değerden küçük olamaz",
+            ));
+        }
+
+        let start: chrono::DateTime<chrono::Local> = SystemTime::try_from(
+            *req.start
+                .as_ref()
+                .ok_or_else(|| Status::invalid_argument("start is missing"))?,
+        )
+        .map_err(|e| e.status())?
+        .into();
+        let end: chrono::DateTime<chrono::Local> = SystemTime::try_from(
+            *req.end
+                .as_ref()
+                .ok_or_else(|| Status::invalid_argument("end is missing"))?,
+        )
+        .map_err(|e| e.status())?
+        .into();
+
+        let c = alerting::simulation::Candidate {
+            min_treshold: req_a.min_treshold,
+            max_treshold: req_a.max_treshold,
+            temperature: req_a.temperature,
+            humidity: req_a.humadity,
+            ec: req_a.ec,
+            pressure: req_a.pressure,
+            door: req_a.door,
+            w_leak: req_a.w_leak,
+            no_data: req_a.no_data,
+            no_data_time: req_a.no_data_time as i32,
+            zone_category: req_a.zone_category,
+            defrost_time: req_a.defrost_time,
+            rule_type: alarm_rule::RuleType::from(req_a.rule_type),
+            rule_duration: Some(req_a.rule_duration as i32),
+            rule_limit: Some(req_a.rule_limit),
+            is_time_limit_active: req_a.is_time_scheduled,
+            schedule: req_a
+                .alarm_date_time
+                .iter()
+                .map(|dt| alarm::AlarmDateTime {
+                    alarm_day: dt.alarm_day as i32,
+                    start_time: dt.alarm_start_time as f64,
+                    end_time: dt.alarm_end_time as f64,
+                    ..Default::default()
+                })
+                .collect(),
+            sms: req_a.sms,
+            user_count: req_a.user_ids.len(),
+        };
+        alerting::simulation::validate(&c, start.naive_local(), end.naive_local())
+            .map_err(|e| e.status())?;
+
+        let dev = device::get(&dev_eui).await.map_err(|e| e.status())?;
+        let out = alerting::simulation::simulate(&dev, &c, start.naive_local(), end.naive_local())
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::SimulateAlarmResponse {
+            sample_count: out.sample_count as u32,
+            incidents: out
+                .incidents
+                .iter()
+                .map(|i| api::SimulatedIncident {
+                    metric: i.metric.clone(),
+                    start_at: local_to_prost_timestamp(&i.start_at),
+                    end_at: local_to_prost_timestamp(&i.end_at),
+                    trigger_count: i.trigger_count as u32,
+                    peak_value: i.peak_value,
+                })
+                .collect(),
+            notification_count: out.notification_count as u32,
+            sms_count: out.sms_count as u32,
+            suppressed_count: out.suppressed_count as u32,
+        }))
+    }
+
+    async fn create_webhook(
+        &self,
+        request: Request<api::CreateWebhookRequest>,
+    ) -> Result<Response<api::CreateWebhookResponse>, Status> {
+        let req_w = match &request.get_ref().webhook {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("webhook is missing"));
+            }
+        };
+        let tenant_id = Uuid::from_str(&req_w.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+            )
+            .await?;
+
+        let w = webhook::create(webhook::Subscription {
+            tenant_id: tenant_id.into(),
+            name: req_w.name.clone(),
+            url: req_w.url.clone(),
+            secret: req_w.secret.clone(),
+            events: req_w.events.iter().cloned().map(Some).collect(),
+            is_disabled: req_w.is_disabled,
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::CreateWebhookResponse {
+            id: w.id as i64,
+            secret: w.secret,
+        }))
+    }
+
+    async fn get_webhook(
+        &self,
+        request: Request<api::GetWebhookRequest>,
+    ) -> Result<Response<api::GetWebhookResponse>, Status> {
+        let w = webhook::get(request.get_ref().id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, w.tenant_id.into()),
+            )
+            .await?;
+
+        Ok(Response::new(api::GetWebhookResponse {
+            webhook: Some(webhook_to_api(&w)),
+            created_at: Some(helpers::datetime_to_prost_timestamp(&w.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&w.updated_at)),
+        }))
+    }
+
+    async fn update_webhook(
+        &self,
+        request: Request<api::UpdateWebhookRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_w = match &request.get_ref().webhook {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("webhook is missing"));
+            }
+        };
+        let w = webhook::get(req_w.id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    w.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        webhook::update(webhook::Subscription {
+            name: req_w.name.clone(),
+            url: req_w.url.clone(),
+            secret: req_w.secret.clone(),
+            events: req_w.events.iter().cloned().map(Some).collect(),
+            is_disabled: req_w.is_disabled,
+            ..w
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn delete_webhook(
+        &self,
+        request: Request<api::DeleteWebhookRequest>,
+    ) -> Result<Response<()>, Status> {
+        let w = webhook::get(request.get_ref().id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    w.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        webhook::delete(w.id).await.map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+
+    async fn list_webhooks(
+        &self,
+        request: Request<api::ListWebhooksRequest>,
+    ) -> Result<Response<api::ListWebhooksResponse>, Status> {
+        let tenant_id = Uuid::from_str(&request.get_ref().tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let items = webhook::list(&tenant_id).await.map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListWebhooksResponse {
+            result: items.iter().map(webhook_to_api).collect(),
+        }))
+    }
+
+    async fn list_webhook_deliveries(
+        &self,
+        request: Request<api::ListWebhookDeliveriesRequest>,
+    ) -> Result<Response<api::ListWebhookDeliveriesResponse>, Status> {
+        let req = request.get_ref();
+        let w = webhook::get(req.webhook_id as i32)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, w.tenant_id.into()),
+            )
+            .await?;
+
+        let status: Vec<&str> = req.status().map(webhook_status_from_api).collect();
+        let limit = if req.limit == 0 {
+            100
+        } else {
+            req.limit as i64
+        };
+
+        let items = webhook::list_deliveries(w.id, &status, limit)
+            .await
+            .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListWebhookDeliveriesResponse {
+            result: items
+                .iter()
+                .map(|d| api::WebhookDelivery {
+                    id: d.id,
+                    webhook_id: d.subscription_id as i64,
+                    event: d.event.clone(),
+                    payload: d.payload.clone(),
+                    status: webhook_status_to_api(&d.status).into(),
+                    attempts: d.attempts,
+                    next_attempt_at: if d.status == webhook::STATUS_PENDING {
+                        Some(helpers::datetime_to_prost_timestamp(&d.run_after))
+                    } else {
+                        None
+                    },
+                    response_status: d.response_status.unwrap_or_default() as u32,
+                    last_error: d.last_error.clone().unwrap_or_default(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&d.created_at)),
+                    delivered_at: d
+                        .delivered_at
+                        .as_ref()
+                        .map(helpers::datetime_to_prost_timestamp),
+                })
+                .collect(),
+        }))
+    }
+
+    async fn retry_webhook_delivery(
+        &self,
+        request: Request<api::RetryWebhookDeliveryRequest>,
+    ) -> Result<Response<()>, Status> {
+        let d = webhook::get_delivery(request.get_ref().id)
+            .await
+            .map_err(|e| e.status())?;
+        let w = webhook::get(d.subscription_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    w.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        webhook::requeue(d.id).await.map_err(|e| e.status())?;
+
+        Ok(Response::new(()))
+    }
+}
+
+fn defrost_schedule_to_api(s: &defrost::DefrostSchedule) -> api::DefrostSchedule {
+    api::DefrostSchedule {
+        id: s.id as i64,
+        zone_id: s.zone_id.unwrap_or_default() as i64,
+        dev_eui: s.dev_eui.clone().unwrap_or_default(),
+        start_time: s.start_time,
+        duration: s.duration as i64,
+        learned: s.learned,
+        created_at: Some(helpers::datetime_to_prost_timestamp(&s.created_at)),
+    }
+}
+
+fn escalation_policy_to_api(
+    p: &escalation::EscalationPolicy,
+    steps: &[escalation::EscalationStep],
+) -> api::EscalationPolicy {
+    api::EscalationPolicy {
+        id: p.id as i64,
+        tenant_id: p.tenant_id.to_string(),
+        name: p.name.clone(),
+        steps: steps
+            .iter()
+            .map(|s| api::EscalationStep {
+                level: s.level,
+                delay_minutes: s.delay_minutes,
+                channel: s.channel.to_proto().into(),
+                user_ids: s.user_id.iter().flatten().map(|v| v.to_string()).collect(),
+                webhook_url: s.webhook_url.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn escalation_steps_from_proto(
+    steps: &[api::EscalationStep],
+) -> Result<Vec<escalation::EscalationStep>, Status> {
+    let mut out = Vec::with_capacity(steps.len());
+    for s in steps {
+        let mut user_id = Vec::with_capacity(s.user_ids.len());
+        for id in &s.user_ids {
+            user_id.push(Some(Uuid::from_str(id).map_err(|e| e.status())?));
+        }
+
+        out.push(escalation::EscalationStep {
+            level: s.level,
+            delay_minutes: s.delay_minutes,
+            channel: s.channel().from_proto(),
+            user_id: user_id.into(),
+            webhook_url: s.webhook_url.clone(),
+            ..Default::default()
+        });
+    }
+    Ok(out)
+}
+
+// The secret is not returned, it is only returned on create.
+fn webhook_to_api(w: &webhook::Subscription) -> api::Webhook {
+    api::Webhook {
+        id: w.id as i64,
+        tenant_id: w.tenant_id.to_string(),
+        name: w.name.clone(),
+        url: w.url.clone(),
+        secret: "".into(),
+        events: w.events.iter().flatten().cloned().collect(),
+        is_disabled: w.is_disabled,
+    }
+}
+
+fn webhook_status_to_api(s: &str) -> api::WebhookDeliveryStatus {
+    match s {
+        webhook::STATUS_DELIVERED => api::WebhookDeliveryStatus::WebhookDelivered,
+        webhook::STATUS_DEAD => api::WebhookDeliveryStatus::WebhookDead,
+        _ => api::WebhookDeliveryStatus::WebhookPending,
+    }
+}
+
+fn webhook_status_from_api(s: api::WebhookDeliveryStatus) -> &'static str {
+    match s {
+        api::WebhookDeliveryStatus::WebhookPending => webhook::STATUS_PENDING,
+        api::WebhookDeliveryStatus::WebhookDelivered => webhook::STATUS_DELIVERED,
+        api::WebhookDeliveryStatus::WebhookDead => webhook::STATUS_DEAD,
+    }
+}
+
+fn local_to_prost_timestamp(t: &chrono::NaiveDateTime) -> Option<prost_types::Timestamp> {
+    chrono::TimeZone::from_local_datetime(&chrono::Local, t)
+        .earliest()
+        .map(|v| helpers::datetime_to_prost_timestamp(&v.with_timezone(&chrono::Utc)))
+}
+
+// Returns the tenant of the device, zone or site, e.g. of a maintenance window or defrost
+// schedule.
+async fn get_tenant_id(dev_eui: Option<EUI64>, zone_id: i64, site_id: i64) -> Result<Uuid, Status> {
+    if let Some(dev_eui) = dev_eui {
+        let d = device::get(&dev_eui).await.map_err(|e| e.status())?;
+        let a = application::get(&d.application_id.into())
+            .await
+            .map_err(|e| e.status())?;
+        return Ok(a.tenant_id.into());
+    }
+
+    if zone_id != 0 {
+        let z = zone::get(&(zone_id as i32)).await.map_err(|e| e.status())?;
+        return z
+            .tanent_id
+            .map(Uuid::from)
+            .ok_or_else(|| Status::invalid_argument("Zone does not belong to a tenant"));
+    }
+
+    if site_id != 0 {
+        let s = site::get(site_id as i32).await.map_err(|e| e.status())?;
+        return Ok(s.tenant_id.into());
+    }
+
+    Err(Status::invalid_argument(
+        "dev_eui, zone_id or site_id is required",
+    ))
+}
+
+// Returns the tenant of the alarm, given by its device or by its zone for a zone no-data alarm.
+async fn get_alarm_tenant_id(alarm_id: i32) -> Result<Uuid, Status> {
+    let a = alarm::get_alarm(alarm_id).await.map_err(|e| e.status())?;
+
+    if a.dev_eui.is_empty() {
+        return get_tenant_id(None, a.zone_category.unwrap_or_default() as i64, 0).await;
+    }
+
+    let dev_eui = EUI64::from_str(&a.dev_eui).map_err(|e| e.status())?;
+    get_tenant_id(Some(dev_eui), 0, 0).await
+}
+
+// Returns the dev_eui to store for the alarm. A zone no-data alarm (no_data set without
+// dev_eui) is stored once without dev_eui, it applies to the devices which are in the zone given
+// by zone_category at the time the alarm is evaluated.
+async fn get_alarm_dev_eui(a: &api::Alarm) -> Result<String, Status> {
+    if a.no_data && a.dev_eui.is_empty() {
+        zone::get(&(a.zone_category as i32))
+            .await
+            .map_err(|e| Status::not_found(format!("Zone not found: {}", e)))?;
+        return Ok(String::new());
+    }
+
+    let dev_eui = EUI64::from_str(&a.dev_eui)
+        .map_err(|_| Status::invalid_argument("Invalid dev_eui, must be a valid EUI64 string"))?;
+    Ok(dev_eui.to_string())
 }
@@ -6,7 +6,6 @@ use chirpstack_api::api::automation_service_server::AutomationService;
 use chrono::{TimeZone, Utc};
 use tonic::{Request, Response, Status};
 use prost_types::Timestamp;
-use diesel::sql_types::Uuid as DieselUuid;
 pub struct Automation {
     validator: validator::RequestValidator,
 }
@@ -33,7 +32,8 @@ impl AutomationService for Automation {
         let mut automation = automation::Automation {
             id: 0,
             user_id: Some(uuid::Uuid::parse_str(&automation.user_id)
-                .map_err(|_| Status::invalid_argument("invalid user_id UUID"))?),
+                .map_err(|_| Status::invalid_argument("invalid user_id UUID"))?
+                .into()),
             sender_sensor: Some(automation.sender_sensor.clone()),
             receiver_sensor: Some(automation.receiver_sensor.clone()),
             condition: Some(automation.condition.clone()),
@@ -50,7 +50,8 @@ impl AutomationService for Automation {
                 .flatten(),
             tenant_id: if !automation.tenant_id.is_empty() {
                 Some(uuid::Uuid::parse_str(&automation.tenant_id)
-                    .map_err(|_| Status::invalid_argument("invalid tenant_id UUID"))?)
+                    .map_err(|_| Status::invalid_argument("invalid tenant_id UUID"))?
+                    .into())
             } else {
                 None
             },
@@ -266,7 +267,7 @@ impl AutomationService for Automation {
         // Prepare the automation struct for update
         let mut automation_update = automation::Automation {
             id: automation.id as i32,
-            user_id: Some(user_id),
+            user_id: Some(user_id.into()),
             sender_sensor: Some(automation.sender_sensor.clone()),
             receiver_sensor: Some(automation.receiver_sensor.clone()),
             condition: Some(automation.condition.clone()),
@@ -282,7 +283,8 @@ impl AutomationService for Automation {
                 .and_then(|ts| chrono::NaiveDateTime::from_timestamp_opt(ts.seconds, ts.nanos as u32)),
             tenant_id: if !automation.tenant_id.is_empty() {
                 Some(uuid::Uuid::parse_str(&automation.tenant_id)
-                    .map_err(|_| Status::invalid_argument("invalid tenant_id UUID"))?)
+                    .map_err(|_| Status::invalid_argument("invalid tenant_id UUID"))?
+                    .into())
             } else {
                 None
             },
@@ -17,13 +17,15 @@ use super::auth::validator;
 use super::error::ToStatus;
 use super::helpers::{self, FromProto, ToProto};
 use crate::storage::{
-    application,
+    application, battery, calibration,
     device::{self, DeviceClass},
-    device_keys, device_profile, device_queue,
+    device_keys, device_link, device_profile, device_queue,
     error::Error as StorageError,
-    fields, metrics, zone,
+    fields,
+    helpers::get_all_device_data,
+    metrics, sensor_data, usage, virtual_device, zone,
 };
-use crate::{codec, devaddr::get_random_dev_addr};
+use crate::{codec, config, devaddr::get_random_dev_addr};
 
 pub struct Device {
     validator: validator::RequestValidator,
@@ -124,8 +126,9 @@ impl DeviceService for Device {
         };
         // Direkt insert edebilirsin:
         d.tags.insert("status".to_string(), "active".to_string());
+        // The signal is derived from the uplinks by the link tracking.
         d.tags
-            .insert("signal".to_string(), "good-signal".to_string());
+            .insert("signal".to_string(), device_link::SIGNAL_NONE.to_string());
 
         match req_d.device_type {
             6 => {
@@ -190,6 +193,7 @@ impl DeviceService for Device {
             content_type: z.content_type,
             tanent_id: z.tanent_id,
             devices: Some(z.devices),
+            site_id: z.site_id,
         };
        let _= zone::update_internal(z.zone_id, zu).await.map_err(|e| e.status())?;
         Ok(resp)
@@ -1185,14 +1189,19 @@ impl DeviceService for Device {
             )
             .await?;
 
+        let (dev, _, t, dp) = get_all_device_data(dev_eui)
+            .await
+            .map_err(|e| e.status())?;
+        if !usage::check_quota(&t, usage::Quota::Messages)
+            .await
+            .map_err(|e| e.status())?
+        {
+            return Err(Status::resource_exhausted("tenant message quota exceeded"));
+        }
+
         let mut data = req_qi.data.clone();
 
         if let Some(obj) = &req_qi.object {
-            let dev = device::get(&dev_eui).await.map_err(|e| e.status())?;
-            let dp = device_profile::get(&dev.device_profile_id)
-                .await
-                .map_err(|e| e.status())?;
-
             data = codec::struct_to_binary(
                 dp.payload_codec_runtime,
                 req_qi.f_port as u8,
@@ -1223,6 +1232,21 @@ impl DeviceService for Device {
             } else {
                 None
             },
+            scheduled_at: if let Some(scheduled_at) = req_qi.scheduled_at {
+                let scheduled_at: std::time::SystemTime = scheduled_at
+                    .try_into()
+                    .map_err(|e: prost_types::TimestampError| e.status())?;
+                Some(scheduled_at.into())
+            } else {
+                None
+            },
+            priority: i16::try_from(req_qi.priority)
+                .map_err(|_| Status::invalid_argument("priority must be <= 32767"))?,
+            coalesce_key: if req_qi.coalesce_key.is_empty() {
+                None
+            } else {
+                Some(req_qi.coalesce_key.clone())
+            },
             data,
             ..Default::default()
         };
@@ -1301,6 +1325,13 @@ impl DeviceService for Device {
                         let v: std::time::SystemTime = v.into();
                         v.into()
                     }),
+                    scheduled_at: qi.scheduled_at.map(|v| {
+                        let v: std::time::SystemTime = v.into();
+                        v.into()
+                    }),
+                    priority: qi.priority as u32,
+                    coalesce_key: qi.coalesce_key.clone().unwrap_or_default(),
+                    retry_count: qi.retry_count as u32,
                 })
                 .collect(),
         });
@@ -1346,6 +1377,559 @@ impl DeviceService for Device {
 
         Ok(resp)
     }
+    async fn get_battery_report(
+        &self,
+        request: Request<api::GetDeviceBatteryReportRequest>,
+    ) -> Result<Response<api::GetDeviceBatteryReportResponse>, Status> {
+        let req = request.get_ref();
+        let tenant_id = Uuid::from_str(&req.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let conf = config::get();
+        let items = battery::get_report(
+            &tenant_id,
+            conf.alarm.low_battery_level,
+            conf.alarm.low_battery_days as i32,
+            req.only_low,
+        )
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetDeviceBatteryReportResponse {
+            result: items
+                .iter()
+                .map(|b| api::DeviceBatteryReportItem {
+                    dev_eui: b.dev_eui.clone(),
+                    name: b.device_name.clone(),
+                    zone_name: b.zone_name.clone().unwrap_or_default(),
+                    device_type: b.device_type_id.unwrap_or_default(),
+                    voltage: b.voltage.unwrap_or_default(),
+                    battery_level: b.battery_level.unwrap_or_default(),
+                    discharge_per_day: b.discharge_per_day.unwrap_or_default(),
+                    days_remaining: b.days_remaining.unwrap_or(-1),
+                    low_battery: b.low_battery,
+                    updated_at: Some(helpers::datetime_to_prost_timestamp(&b.updated_at)),
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-tenant_id", req.tenant_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_link_report(
+        &self,
+        request: Request<api::GetDeviceLinkReportRequest>,
+    ) -> Result<Response<api::GetDeviceLinkReportResponse>, Status> {
+        let req = request.get_ref();
+        let tenant_id = Uuid::from_str(&req.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let items = device_link::get_report(&tenant_id, req.only_weak)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetDeviceLinkReportResponse {
+            result: items
+                .iter()
+                .map(|l| api::DeviceLinkReportItem {
+                    dev_eui: l.dev_eui.clone(),
+                    name: l.device_name.clone(),
+                    zone_name: l.zone_name.clone().unwrap_or_default(),
+                    signal: l.signal.clone(),
+                    gateway_id: l.gateway_id.clone().unwrap_or_default(),
+                    gateway_name: l.gateway_name.clone().unwrap_or_default(),
+                    rssi: l.rssi.unwrap_or_default(),
+                    snr: l.snr.unwrap_or_default(),
+                    margin: l.margin,
+                    margin_baseline: l.margin_baseline,
+                    uplink_count: l.uplink_count,
+                    degraded: l.degraded,
+                    last_seen_at: Some(helpers::datetime_to_prost_timestamp(&l.last_seen_at)),
+                    updated_at: Some(helpers::datetime_to_prost_timestamp(&l.updated_at)),
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-tenant_id", req.tenant_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_sensor_data(
+        &self,
+        request: Request<api::GetDeviceSensorDataRequest>,
+    ) -> Result<Response<api::GetDeviceSensorDataResponse>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Read, dev_eui),
+            )
+            .await?;
+
+        let start = SystemTime::try_from(
+            *req.start
+                .as_ref()
+                .ok_or_else(|| anyhow!("start is None"))
+                .map_err(|e| e.status())?,
+        )
+        .map_err(|e| e.status())?;
+
+        let end = SystemTime::try_from(
+            *req.end
+                .as_ref()
+                .ok_or_else(|| anyhow!("end is None"))
+                .map_err(|e| e.status())?,
+        )
+        .map_err(|e| e.status())?;
+
+        // The sensor-data timestamps are stored as UTC without timezone.
+        let start = DateTime::<Utc>::from(start).naive_utc();
+        let end = DateTime::<Utc>::from(end).naive_utc();
+        let resolution = match req.resolution() {
+            api::SensorDataResolution::SensorDataAuto => {
+                sensor_data::Resolution::for_interval(start, end)
+            }
+            api::SensorDataResolution::SensorDataRaw => sensor_data::Resolution::Raw,
+            api::SensorDataResolution::SensorDataHourly => sensor_data::Resolution::Hourly,
+            api::SensorDataResolution::SensorDataDaily => sensor_data::Resolution::Daily,
+        };
+
+        let samples =
+            sensor_data::get_history(&dev_eui.to_string(), &req.metric, resolution, start, end)
+                .await
+                .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetDeviceSensorDataResponse {
+            resolution: resolution.to_proto().into(),
+            result: samples
+                .iter()
+                .map(|s| api::SensorDataSample {
+                    time: Some(helpers::datetime_to_prost_timestamp(&s.bucket.and_utc())),
+                    min: s.min_value,
+                    max: s.max_value,
+                    avg: s.avg_value,
+                    count: s.sample_count as u32,
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn create_calibration(
+        &self,
+        request: Request<api::CreateDeviceCalibrationRequest>,
+    ) -> Result<Response<api::CreateDeviceCalibrationResponse>, Status> {
+        let req_c = match &request.get_ref().calibration {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("calibration is missing"));
+            }
+        };
+        let dev_eui = EUI64::from_str(&req_c.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Update, dev_eui),
+            )
+            .await?;
+
+        let effective_from = SystemTime::try_from(
+            *req_c
+                .effective_from
+                .as_ref()
+                .ok_or_else(|| anyhow!("effective_from is None"))
+                .map_err(|e| e.status())?,
+        )
+        .map_err(|e| e.status())?;
+
+        let c = calibration::create(calibration::Calibration {
+            dev_eui,
+            kind: req_c.kind.clone(),
+            offset: req_c.offset,
+            // proto3 does not distinguish between 0 and not set, a gain of 0 is never valid.
+            gain: if req_c.gain == 0.0 { 1.0 } else { req_c.gain },
+            points: fields::CalibrationPoints::new(
+                req_c
+                    .points
+                    .iter()
+                    .map(|p| fields::CalibrationPoint {
+                        raw: p.raw,
+                        reference: p.reference,
+                    })
+                    .collect(),
+            ),
+            effective_from: DateTime::<Utc>::from(effective_from),
+            certificate: req_c.certificate.clone(),
+            ..Default::default()
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::CreateDeviceCalibrationResponse { id: c.id.into() });
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req_c.dev_eui.parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-calibration_id", c.id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn list_calibrations(
+        &self,
+        request: Request<api::ListDeviceCalibrationsRequest>,
+    ) -> Result<Response<api::ListDeviceCalibrationsResponse>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Read, dev_eui),
+            )
+            .await?;
+
+        let items = calibration::list(&dev_eui)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::ListDeviceCalibrationsResponse {
+            result: items
+                .iter()
+                .map(|c| api::DeviceCalibration {
+                    id: c.id.into(),
+                    dev_eui: c.dev_eui.to_string(),
+                    kind: c.kind.clone(),
+                    offset: c.offset,
+                    gain: c.gain,
+                    points: c
+                        .points
+                        .iter()
+                        .map(|p| api::DeviceCalibrationPoint {
+                            raw: p.raw,
+                            reference: p.reference,
+                        })
+                        .collect(),
+                    effective_from: Some(helpers::datetime_to_prost_timestamp(&c.effective_from)),
+                    certificate: c.certificate.clone(),
+                    created_at: Some(helpers::datetime_to_prost_timestamp(&c.created_at)),
+                })
+                .collect(),
+        });
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn delete_calibration(
+        &self,
+        request: Request<api::DeleteDeviceCalibrationRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let id = req.id as i32;
+        let c = calibration::get(id).await.map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Update, c.dev_eui),
+            )
+            .await?;
+
+        calibration::delete(id).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", c.dev_eui.to_string().parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-calibration_id", req.id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn create_virtual(
+        &self,
+        request: Request<api::CreateVirtualDeviceRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_vd = match &request.get_ref().virtual_device {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("virtual_device is missing"));
+            }
+        };
+        let dev_eui = EUI64::from_str(&req_vd.dev_eui).map_err(|e| e.status())?;
+        let inputs = virtual_inputs_from_proto(dev_eui, &req_vd.inputs)?;
+
+        // The virtual device is created in the application of its first input.
+        let first = inputs
+            .first()
+            .ok_or_else(|| Status::invalid_argument("inputs are missing"))?;
+        let input_dev = device::get(&first.input_dev_eui)
+            .await
+            .map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDevicesAccess::new(
+                    validator::Flag::Create,
+                    input_dev.application_id.into(),
+                ),
+            )
+            .await?;
+        for input in &inputs {
+            self.validator
+                .validate(
+                    request.extensions(),
+                    validator::ValidateDeviceAccess::new(
+                        validator::Flag::Read,
+                        input.input_dev_eui,
+                    ),
+                )
+                .await?;
+        }
+
+        // The zone must belong to the tenant of the input devices.
+        let zone = if req_vd.zone_id != 0 {
+            let z = zone::get(&(req_vd.zone_id as i32))
+                .await
+                .map_err(|e| e.status())?;
+            let tenant_id = z
+                .tanent_id
+                .map(Uuid::from)
+                .ok_or_else(|| Status::failed_precondition("zone does not belong to a tenant"))?;
+            let a = application::get(&input_dev.application_id.into())
+                .await
+                .map_err(|e| e.status())?;
+            if Uuid::from(a.tenant_id) != tenant_id {
+                return Err(Status::invalid_argument(
+                    "Zone and input devices must belong to the same tenant",
+                ));
+            }
+
+            self.validator
+                .validate(
+                    request.extensions(),
+                    validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+                )
+                .await?;
+
+            Some(z)
+        } else {
+            None
+        };
+
+        let mut d = device::Device {
+            dev_eui,
+            application_id: input_dev.application_id,
+            device_profile_id: input_dev.device_profile_id,
+            name: req_vd.name.clone(),
+            description: req_vd.description.clone(),
+            variables: fields::KeyValue::new(req_vd.variables.clone()),
+            ..Default::default()
+        };
+        d.tags.insert("status".to_string(), "active".to_string());
+
+        virtual_device::create(
+            d,
+            virtual_device::VirtualDevice {
+                dev_eui,
+                kind: req_vd.kind.clone(),
+                unit: req_vd.unit.clone(),
+                formula: req_vd.formula.clone(),
+                ..Default::default()
+            },
+            inputs,
+        )
+        .await
+        .map_err(|e| e.status())?;
+
+        if let Some(mut z) = zone {
+            z.devices
+                .push(Some(format!("\\x{}", req_vd.dev_eui.to_lowercase())));
+
+            let zu = zone::UpdateZone {
+                zone_name: z.zone_name,
+                zone_order: z.zone_order,
+                content_type: z.content_type,
+                tanent_id: z.tanent_id,
+                devices: Some(z.devices),
+                site_id: z.site_id,
+            };
+            zone::update_internal(z.zone_id, zu)
+                .await
+                .map_err(|e| e.status())?;
+        }
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req_vd.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_virtual(
+        &self,
+        request: Request<api::GetVirtualDeviceRequest>,
+    ) -> Result<Response<api::GetVirtualDeviceResponse>, Status> {
+        let req = request.get_ref();
+        let dev_eui = EUI64::from_str(&req.dev_eui).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Read, dev_eui),
+            )
+            .await?;
+
+        let d = device::get(&dev_eui).await.map_err(|e| e.status())?;
+        let vd = virtual_device::get(&dev_eui)
+            .await
+            .map_err(|e| e.status())?;
+        let inputs = virtual_device::get_inputs(&dev_eui)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetVirtualDeviceResponse {
+            virtual_device: Some(api::VirtualDevice {
+                dev_eui: vd.dev_eui.to_string(),
+                name: d.name.clone(),
+                description: d.description.clone(),
+                zone_id: 0,
+                kind: vd.kind.clone(),
+                unit: vd.unit.clone(),
+                formula: vd.formula.clone(),
+                inputs: inputs
+                    .iter()
+                    .map(|i| api::VirtualDeviceInput {
+                        name: i.name.clone(),
+                        dev_eui: i.input_dev_eui.to_string(),
+                        kind: i.kind.clone(),
+                        aggregate: match i.aggregate.as_str() {
+                            virtual_device::AGGREGATE_DAY_DELTA => {
+                                api::VirtualDeviceInputAggregate::VirtualInputDayDelta
+                            }
+                            virtual_device::AGGREGATE_VARIABLE => {
+                                api::VirtualDeviceInputAggregate::VirtualInputVariable
+                            }
+                            _ => api::VirtualDeviceInputAggregate::VirtualInputLatest,
+                        }
+                        .into(),
+                    })
+                    .collect(),
+                variables: d.variables.into_hashmap(),
+            }),
+            created_at: Some(helpers::datetime_to_prost_timestamp(&vd.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&vd.updated_at)),
+        });
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn update_virtual(
+        &self,
+        request: Request<api::UpdateVirtualDeviceRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_vd = match &request.get_ref().virtual_device {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("virtual_device is missing"));
+            }
+        };
+        let dev_eui = EUI64::from_str(&req_vd.dev_eui).map_err(|e| e.status())?;
+        let inputs = virtual_inputs_from_proto(dev_eui, &req_vd.inputs)?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateDeviceAccess::new(validator::Flag::Update, dev_eui),
+            )
+            .await?;
+        for input in &inputs {
+            self.validator
+                .validate(
+                    request.extensions(),
+                    validator::ValidateDeviceAccess::new(
+                        validator::Flag::Read,
+                        input.input_dev_eui,
+                    ),
+                )
+                .await?;
+        }
+
+        let vd = virtual_device::get(&dev_eui)
+            .await
+            .map_err(|e| e.status())?;
+        virtual_device::update(
+            virtual_device::VirtualDevice {
+                kind: req_vd.kind.clone(),
+                unit: req_vd.unit.clone(),
+                formula: req_vd.formula.clone(),
+                ..vd
+            },
+            inputs,
+        )
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-dev_eui", req_vd.dev_eui.parse().unwrap());
+
+        Ok(resp)
+    }
+}
+
+fn virtual_inputs_from_proto(
+    dev_eui: EUI64,
+    inputs: &[api::VirtualDeviceInput],
+) -> Result<Vec<virtual_device::Input>, Status> {
+    inputs
+        .iter()
+        .map(|i| {
+            Ok(virtual_device::Input {
+                dev_eui,
+                name: i.name.clone(),
+                input_dev_eui: EUI64::from_str(&i.dev_eui).map_err(|e| e.status())?,
+                kind: i.kind.clone(),
+                aggregate: match i.aggregate() {
+                    api::VirtualDeviceInputAggregate::VirtualInputLatest => {
+                        virtual_device::AGGREGATE_LATEST
+                    }
+                    api::VirtualDeviceInputAggregate::VirtualInputDayDelta => {
+                        virtual_device::AGGREGATE_DAY_DELTA
+                    }
+                    api::VirtualDeviceInputAggregate::VirtualInputVariable => {
+                        virtual_device::AGGREGATE_VARIABLE
+                    }
+                }
+                .to_string(),
+            })
+        })
+        .collect()
 }
 
 #[cfg(test)]
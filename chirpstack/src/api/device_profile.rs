@@ -80,6 +80,7 @@ impl DeviceProfileService for DeviceProfile {
                             fields::Measurement {
                                 name: v.name.clone(),
                                 kind: v.kind().from_proto(),
+                                unit: v.unit.clone(),
                             },
                         )
                     })
@@ -118,6 +119,10 @@ impl DeviceProfileService for DeviceProfile {
                 req_dp.relay_global_uplink_limit_bucket_size as i16,
             ),
             relay_overall_limit_bucket_size: Some(req_dp.relay_overall_limit_bucket_size as i16),
+            ts003_enabled: Some(req_dp.ts003_enabled),
+            ts003_f_port: Some(req_dp.ts003_f_port as i16),
+            downlink_retry_count: Some(req_dp.downlink_retry_count as i16),
+            downlink_retry_backoff: Some(req_dp.downlink_retry_backoff as i32),
             allow_roaming: req_dp.allow_roaming,
             rx1_delay: req_dp.rx1_delay as i16,
             ..Default::default()
@@ -191,6 +196,7 @@ impl DeviceProfileService for DeviceProfile {
                             api::Measurement {
                                 name: v.name.clone(),
                                 kind: v.kind.to_proto().into(),
+                                unit: v.unit.clone(),
                             },
                         )
                     })
@@ -230,6 +236,10 @@ impl DeviceProfileService for DeviceProfile {
                     .unwrap_or(0) as u32,
                 relay_overall_limit_bucket_size: dp.relay_overall_limit_bucket_size.unwrap_or(0)
                     as u32,
+                ts003_enabled: dp.ts003_enabled.unwrap_or(false),
+                ts003_f_port: dp.ts003_f_port.unwrap_or(0) as u32,
+                downlink_retry_count: dp.downlink_retry_count.unwrap_or(0) as u32,
+                downlink_retry_backoff: dp.downlink_retry_backoff.unwrap_or(0) as u32,
                 allow_roaming: dp.allow_roaming,
                 rx1_delay: dp.rx1_delay as u32,
                 relay_cad_periodicity: dp.relay_cad_periodicity.unwrap_or(0) as i32,
@@ -300,6 +310,7 @@ impl DeviceProfileService for DeviceProfile {
                             fields::Measurement {
                                 name: v.name.clone(),
                                 kind: v.kind().from_proto(),
+                                unit: v.unit.clone(),
                             },
                         )
                     })
@@ -338,6 +349,10 @@ impl DeviceProfileService for DeviceProfile {
                 req_dp.relay_global_uplink_limit_bucket_size as i16,
             ),
             relay_overall_limit_bucket_size: Some(req_dp.relay_overall_limit_bucket_size as i16),
+            ts003_enabled: Some(req_dp.ts003_enabled),
+            ts003_f_port: Some(req_dp.ts003_f_port as i16),
+            downlink_retry_count: Some(req_dp.downlink_retry_count as i16),
+            downlink_retry_backoff: Some(req_dp.downlink_retry_backoff as i32),
             allow_roaming: req_dp.allow_roaming,
             rx1_delay: req_dp.rx1_delay as i16,
             ..Default::default()
@@ -79,6 +79,7 @@ impl DeviceProfileTemplateService for DeviceProfileTemplate {
                             fields::Measurement {
                                 name: v.name.clone(),
                                 kind: v.kind().from_proto(),
+                                unit: v.unit.clone(),
                             },
                         )
                     })
@@ -151,6 +152,7 @@ impl DeviceProfileTemplateService for DeviceProfileTemplate {
                             api::Measurement {
                                 name: v.name.clone(),
                                 kind: v.kind.to_proto().into(),
+                                unit: v.unit.clone(),
                             },
                         )
                     })
@@ -220,6 +222,7 @@ impl DeviceProfileTemplateService for DeviceProfileTemplate {
                             fields::Measurement {
                                 name: v.name.clone(),
                                 kind: v.kind().from_proto(),
+                                unit: v.unit.clone(),
                             },
                         )
                     })
@@ -1,8 +1,10 @@
 use chrono::{DateTime, Utc};
 
 use crate::codec::Codec;
-use crate::storage::fields::{MeasurementKind, MulticastGroupSchedulingType};
-use crate::storage::{device::DeviceClass, metrics::Aggregation};
+use crate::storage::fields::{
+    EscalationChannel, MeasurementKind, MulticastGroupSchedulingType, OverQuotaAction,
+};
+use crate::storage::{device::DeviceClass, metrics::Aggregation, sensor_data::Resolution};
 use chirpstack_api::{api, common};
 use lrwn::region::{CommonName, MacVersion, Revision};
 
@@ -223,6 +225,56 @@ impl FromProto<MulticastGroupSchedulingType> for api::MulticastGroupSchedulingTy
     }
 }
 
+impl ToProto<api::OverQuotaAction> for OverQuotaAction {
+    fn to_proto(self) -> api::OverQuotaAction {
+        match self {
+            OverQuotaAction::WARN => api::OverQuotaAction::Warn,
+            OverQuotaAction::DROP => api::OverQuotaAction::Drop,
+            OverQuotaAction::THROTTLE => api::OverQuotaAction::Throttle,
+        }
+    }
+}
+
+impl FromProto<OverQuotaAction> for api::OverQuotaAction {
+    fn from_proto(self) -> OverQuotaAction {
+        match self {
+            api::OverQuotaAction::Warn => OverQuotaAction::WARN,
+            api::OverQuotaAction::Drop => OverQuotaAction::DROP,
+            api::OverQuotaAction::Throttle => OverQuotaAction::THROTTLE,
+        }
+    }
+}
+
+impl ToProto<api::EscalationChannel> for EscalationChannel {
+    fn to_proto(self) -> api::EscalationChannel {
+        match self {
+            EscalationChannel::NOTIFICATION => api::EscalationChannel::EscalationNotification,
+            EscalationChannel::SMS => api::EscalationChannel::EscalationSms,
+            EscalationChannel::WEBHOOK => api::EscalationChannel::EscalationWebhook,
+        }
+    }
+}
+
+impl FromProto<EscalationChannel> for api::EscalationChannel {
+    fn from_proto(self) -> EscalationChannel {
+        match self {
+            api::EscalationChannel::EscalationNotification => EscalationChannel::NOTIFICATION,
+            api::EscalationChannel::EscalationSms => EscalationChannel::SMS,
+            api::EscalationChannel::EscalationWebhook => EscalationChannel::WEBHOOK,
+        }
+    }
+}
+
+impl ToProto<api::SensorDataResolution> for Resolution {
+    fn to_proto(self) -> api::SensorDataResolution {
+        match self {
+            Resolution::Raw => api::SensorDataResolution::SensorDataRaw,
+            Resolution::Hourly => api::SensorDataResolution::SensorDataHourly,
+            Resolution::Daily => api::SensorDataResolution::SensorDataDaily,
+        }
+    }
+}
+
 impl ToProto<api::RelayModeActivation> for lrwn::RelayModeActivation {
     fn to_proto(self) -> api::RelayModeActivation {
         match self {
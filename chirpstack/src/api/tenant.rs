@@ -1,15 +1,19 @@
 use std::str::FromStr;
+use std::time::SystemTime;
 
+use chrono::{DateTime, Local, Utc};
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
 
 use chirpstack_api::api;
 use chirpstack_api::api::tenant_service_server::TenantService;
+use chirpstack_api::common;
 
 use super::auth::{validator, AuthID};
 use super::error::ToStatus;
-use super::helpers;
-use crate::storage::{fields, tenant, user};
+use super::helpers::{self, FromProto, ToProto};
+use crate::config;
+use crate::storage::{fields, metrics, tenant, usage, user};
 
 pub struct Tenant {
     validator: validator::RequestValidator,
@@ -50,6 +54,11 @@ impl TenantService for Tenant {
             private_gateways_up: req_tenant.private_gateways_up,
             private_gateways_down: req_tenant.private_gateways_down,
             tags: fields::KeyValue::new(req_tenant.tags.clone()),
+            max_message_count: req_tenant.max_message_count as i32,
+            max_sms_count: req_tenant.max_sms_count as i32,
+            over_quota_action: req_tenant.over_quota_action().from_proto(),
+            raw_data_retention_days: req_tenant.raw_data_retention_days as i32,
+            hourly_data_retention_months: req_tenant.hourly_data_retention_months as i32,
             ..Default::default()
         };
 
@@ -93,7 +102,12 @@ impl TenantService for Tenant {
                 tags: t.tags.into_hashmap(),
                 license_payment: true,
                 pro_license: true,
-                kitchen_management_license: false
+                kitchen_management_license: false,
+                max_message_count: t.max_message_count as u32,
+                max_sms_count: t.max_sms_count as u32,
+                over_quota_action: t.over_quota_action.to_proto().into(),
+                raw_data_retention_days: t.raw_data_retention_days as u32,
+                hourly_data_retention_months: t.hourly_data_retention_months as u32,
             }),
             created_at: Some(helpers::datetime_to_prost_timestamp(&t.created_at)),
             updated_at: Some(helpers::datetime_to_prost_timestamp(&t.updated_at)),
@@ -134,6 +148,11 @@ impl TenantService for Tenant {
             private_gateways_up: req_tenant.private_gateways_up,
             private_gateways_down: req_tenant.private_gateways_down,
             tags: fields::KeyValue::new(req_tenant.tags.clone()),
+            max_message_count: req_tenant.max_message_count as i32,
+            max_sms_count: req_tenant.max_sms_count as i32,
+            over_quota_action: req_tenant.over_quota_action().from_proto(),
+            raw_data_retention_days: req_tenant.raw_data_retention_days as i32,
+            hourly_data_retention_months: req_tenant.hourly_data_retention_months as i32,
             ..Default::default()
         })
         .await
@@ -461,6 +480,108 @@ impl TenantService for Tenant {
 
         Ok(resp)
     }
+
+    async fn get_usage(
+        &self,
+        request: Request<api::GetTenantUsageRequest>,
+    ) -> Result<Response<api::GetTenantUsageResponse>, Status> {
+        let req = request.get_ref();
+        let tenant_id = Uuid::from_str(&req.tenant_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let start = SystemTime::try_from(
+            *req.start
+                .as_ref()
+                .ok_or_else(|| anyhow!("start is None"))
+                .map_err(|e| e.status())?,
+        )
+        .map_err(|e| e.status())?;
+
+        let end = SystemTime::try_from(
+            *req.end
+                .as_ref()
+                .ok_or_else(|| anyhow!("end is None"))
+                .map_err(|e| e.status())?,
+        )
+        .map_err(|e| e.status())?;
+
+        let start: DateTime<Local> = start.into();
+        let end: DateTime<Local> = end.into();
+        let aggregation = req.aggregation().from_proto();
+
+        let usage_metrics = usage::get(&tenant_id, aggregation, start, end)
+            .await
+            .map_err(|e| e.status())?;
+        let totals = usage::get_totals(&usage_metrics);
+        let get_total = |name: &str| totals.get(name).cloned().unwrap_or(0.0);
+        let sms_unit_cost = config::get().metering.sms_unit_cost;
+
+        let mut sms = usage_to_metric(&usage_metrics, "SMS", &[usage::SMS_COUNT]);
+        sms.datasets.push(common::MetricDataset {
+            label: "sms_cost".to_string(),
+            data: usage_metrics
+                .iter()
+                .map(|row| {
+                    (row.metrics.get(usage::SMS_COUNT).cloned().unwrap_or(0.0) * sms_unit_cost)
+                        as f32
+                })
+                .collect(),
+        });
+
+        let mut resp = Response::new(api::GetTenantUsageResponse {
+            messages: Some(usage_to_metric(
+                &usage_metrics,
+                "Messages",
+                &[usage::UPLINK_COUNT, usage::DOWNLINK_COUNT],
+            )),
+            notifications: Some(usage_to_metric(
+                &usage_metrics,
+                "Notifications",
+                &[usage::NOTIFICATION_COUNT],
+            )),
+            sms: Some(sms),
+            uplink_count: get_total(usage::UPLINK_COUNT) as u64,
+            downlink_count: get_total(usage::DOWNLINK_COUNT) as u64,
+            notification_count: get_total(usage::NOTIFICATION_COUNT) as u64,
+            sms_count: get_total(usage::SMS_COUNT) as u64,
+            sms_cost: get_total(usage::SMS_COUNT) * sms_unit_cost,
+        });
+        resp.metadata_mut()
+            .insert("x-log-tenant_id", req.tenant_id.parse().unwrap());
+
+        Ok(resp)
+    }
+}
+
+fn usage_to_metric(records: &[metrics::Record], name: &str, labels: &[&str]) -> common::Metric {
+    common::Metric {
+        name: name.to_string(),
+        timestamps: records
+            .iter()
+            .map(|row| {
+                let ts: DateTime<Utc> = row.time.into();
+                let ts: pbjson_types::Timestamp = ts.into();
+                ts
+            })
+            .collect(),
+        datasets: labels
+            .iter()
+            .map(|label| common::MetricDataset {
+                label: label.to_string(),
+                data: records
+                    .iter()
+                    .map(|row| row.metrics.get(*label).cloned().unwrap_or(0.0) as f32)
+                    .collect(),
+            })
+            .collect(),
+        kind: common::MetricKind::Absolute.into(),
+    }
 }
 
 #[cfg(test)]
@@ -495,6 +616,8 @@ pub mod test {
                 can_have_gateways: true,
                 max_device_count: 10,
                 max_gateway_count: 3,
+                max_message_count: 1000,
+                over_quota_action: api::OverQuotaAction::Throttle.into(),
                 ..Default::default()
             }),
         };
@@ -521,6 +644,8 @@ pub mod test {
                 can_have_gateways: true,
                 max_device_count: 10,
                 max_gateway_count: 3,
+                max_message_count: 1000,
+                over_quota_action: api::OverQuotaAction::Throttle.into(),
                 ..Default::default()
             }),
             get_resp.get_ref().tenant
@@ -566,6 +691,21 @@ pub mod test {
             get_resp.get_ref().tenant
         );
 
+        // get usage
+        let usage_req = api::GetTenantUsageRequest {
+            tenant_id: create_resp.get_ref().id.clone(),
+            start: Some(SystemTime::now().into()),
+            end: Some(SystemTime::now().into()),
+            aggregation: common::Aggregation::Month.into(),
+        };
+        let mut usage_req = Request::new(usage_req);
+        usage_req
+            .extensions_mut()
+            .insert(AuthID::User(Into::<uuid::Uuid>::into(u.id).clone()));
+        let usage_resp = service.get_usage(usage_req).await.unwrap();
+        assert_eq!(0, usage_resp.get_ref().uplink_count);
+        assert_eq!(0, usage_resp.get_ref().sms_count);
+
         // list
         let list_req = api::ListTenantsRequest {
             search: "update".into(),
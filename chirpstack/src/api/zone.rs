@@ -3,12 +3,18 @@ use std::str::FromStr;
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
 
-use crate::storage::zone::{self, ZoneDataSerde, ZoneDeviceProfileSerde, ZoneDeviceSerde};
+use crate::config;
+use crate::storage::zone::{
+    self, ZoneAggregatesSerde, ZoneDataSerde, ZoneDeviceProfileSerde, ZoneDeviceSerde,
+    ZoneMeasurementSerde,
+};
+use crate::storage::{site, zone_content_type};
 use crate::{api::error::ToStatus, storage::zone::GetZonesItemSerde};
 use chirpstack_api::api::zone_service_server::ZoneService;
 use chirpstack_api::api::{self, GetZonesItem, ZoneDevice, ZoneDeviceProfile};
 
 use super::auth::{validator, AuthID};
+use super::helpers;
 
 pub struct Zone {
     validator: validator::RequestValidator,
@@ -41,13 +47,28 @@ impl ZoneService for Zone {
             )
             .await?;
 
+        let site_id = if req_app.site_id != 0 {
+            let st = site::get(req_app.site_id as i32)
+                .await
+                .map_err(|e| e.status())?;
+            if Uuid::from(st.tenant_id) != tenant_id {
+                return Err(Status::invalid_argument(
+                    "site does not belong to the organization",
+                ));
+            }
+            Some(st.site_id)
+        } else {
+            None
+        };
+
         let a = zone::Zone {
             zone_name: Some(req_app.zone_name.clone()),
             devices: req_app.devices.clone().into_iter().map(Some).collect(),
             zone_order: Some(req_app.order),
             zone_id: 0,
             content_type: Some(req_app.content_type),
-            tanent_id: Some(tenant_id),
+            tanent_id: Some(tenant_id.into()),
+            site_id,
         };
 
         // Call internal `create` function
@@ -114,9 +135,13 @@ impl ZoneService for Zone {
         };
         println!("🌀 For user: {}", user_id);
 
-        let zones = zone::list(Some(user_id.clone()), Some(tenant_id.clone()))
-            .await
-            .map_err(|e| e)?;
+        let site_id = if req.site_id != 0 {
+            Some(req.site_id as i32)
+        } else {
+            None
+        };
+
+        let zones = zone::list(Some(*user_id), Some(tenant_id), site_id).await?;
 
         let zones_proto: Vec<api::GetZonesItem> = zones
             .zones
@@ -124,7 +149,17 @@ impl ZoneService for Zone {
             .map(api::GetZonesItem::from)
             .collect();
 
-        let resp = api::ListZoneResponse { zones: zones_proto };
+        let resp = if req.group_by_site {
+            api::ListZoneResponse {
+                zones: vec![],
+                sites: group_by_site(zones_proto),
+            }
+        } else {
+            api::ListZoneResponse {
+                zones: zones_proto,
+                sites: vec![],
+            }
+        };
 
         Ok(Response::new(resp))
     }
@@ -158,7 +193,524 @@ impl ZoneService for Zone {
 
         Ok(resp)
     }
+
+    async fn set_site(
+        &self,
+        request: Request<api::SetZoneSiteRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let zone_id = i32::try_from(req.zone_id).map_err(|_| {
+            Status::invalid_argument(format!("zone_id {} is out of range for i32", req.zone_id))
+        })?;
+
+        let z = zone::get(&zone_id).await.map_err(|e| e.status())?;
+        let tenant_id = z
+            .tanent_id
+            .map(Uuid::from)
+            .ok_or_else(|| Status::failed_precondition("zone does not belong to a tenant"))?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+            )
+            .await?;
+
+        let site_id = if req.site_id != 0 {
+            Some(req.site_id as i32)
+        } else {
+            None
+        };
+
+        site::set_zone_site(zone_id, site_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-zone_id", zone_id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn create_site(
+        &self,
+        request: Request<api::CreateSiteRequest>,
+    ) -> Result<Response<api::CreateSiteResponse>, Status> {
+        let req_site = match &request.get_ref().site {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("site is missing"));
+            }
+        };
+        let tenant_id = Uuid::from_str(&req_site.org_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+            )
+            .await?;
+
+        let st = site::create(site_from_proto(req_site, tenant_id))
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::CreateSiteResponse {
+            site_id: st.site_id as i64,
+        });
+        resp.metadata_mut()
+            .insert("x-log-site_id", st.site_id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_site(
+        &self,
+        request: Request<api::GetSiteRequest>,
+    ) -> Result<Response<api::GetSiteResponse>, Status> {
+        let req = request.get_ref();
+        let st = get_site(req.site_id).await?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, st.tenant_id.into()),
+            )
+            .await?;
+
+        let zone_count = site::get_zone_count(st.site_id)
+            .await
+            .map_err(|e| e.status())?;
+        let user_ids = site::get_user_ids(st.site_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetSiteResponse {
+            created_at: Some(helpers::datetime_to_prost_timestamp(&st.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&st.updated_at)),
+            zone_count,
+            user_ids: user_ids.iter().map(|id| id.to_string()).collect(),
+            site: Some(st.into()),
+        });
+        resp.metadata_mut()
+            .insert("x-log-site_id", req.site_id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn update_site(
+        &self,
+        request: Request<api::UpdateSiteRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_site = match &request.get_ref().site {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("site is missing"));
+            }
+        };
+        let st = get_site(req_site.site_id).await?;
+        let tenant_id: Uuid = st.tenant_id.into();
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(validator::Flag::Create, tenant_id),
+            )
+            .await?;
+
+        let _ = site::update(site::Site {
+            site_id: st.site_id,
+            ..site_from_proto(req_site, tenant_id)
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-site_id", st.site_id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn delete_site(
+        &self,
+        request: Request<api::DeleteSiteRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let st = get_site(req.site_id).await?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    st.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        site::delete(st.site_id).await.map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-site_id", st.site_id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn list_sites(
+        &self,
+        request: Request<api::ListSitesRequest>,
+    ) -> Result<Response<api::ListSitesResponse>, Status> {
+        let req = request.get_ref();
+        let tenant_id = Uuid::from_str(&req.org_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let items = site::list(&tenant_id).await.map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListSitesResponse {
+            result: items.into_iter().map(|st| st.into()).collect(),
+        }))
+    }
+
+    async fn add_site_user(
+        &self,
+        request: Request<api::AddSiteUserRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let user_id = Uuid::from_str(&req.user_id).map_err(|e| e.status())?;
+        let st = get_site(req.site_id).await?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    st.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        site::add_user(st.site_id, &user_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-site_id", st.site_id.to_string().parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-user_id", req.user_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn remove_site_user(
+        &self,
+        request: Request<api::RemoveSiteUserRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req = request.get_ref();
+        let user_id = Uuid::from_str(&req.user_id).map_err(|e| e.status())?;
+        let st = get_site(req.site_id).await?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantUsersAccess::new(
+                    validator::Flag::Create,
+                    st.tenant_id.into(),
+                ),
+            )
+            .await?;
+
+        site::remove_user(st.site_id, &user_id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-site_id", st.site_id.to_string().parse().unwrap());
+        resp.metadata_mut()
+            .insert("x-log-user_id", req.user_id.parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_site_rollups(
+        &self,
+        request: Request<api::GetSiteRollupsRequest>,
+    ) -> Result<Response<api::GetSiteRollupsResponse>, Status> {
+        let req = request.get_ref();
+        let tenant_id = Uuid::from_str(&req.org_id).map_err(|e| e.status())?;
+
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateTenantAccess::new(validator::Flag::Read, tenant_id),
+            )
+            .await?;
+
+        let conf = config::get();
+        let hours = if req.hours == 0 { 24 } else { req.hours };
+        let items = site::get_rollups(
+            &tenant_id,
+            conf.alarm.low_battery_level,
+            conf.alarm.low_battery_days as i32,
+            hours as i32,
+        )
+        .await
+        .map_err(|e| e.status())?;
+
+        Ok(Response::new(api::GetSiteRollupsResponse {
+            result: items
+                .into_iter()
+                .map(|r| api::SiteRollup {
+                    site_id: r.site_id as i64,
+                    site_name: r.site_name,
+                    zone_count: r.zone_count,
+                    device_count: r.device_count,
+                    offline_device_count: r.offline_device_count,
+                    low_battery_device_count: r.low_battery_device_count,
+                    active_alarm_count: r.active_alarm_count,
+                    triggered_alarm_count: r.triggered_alarm_count,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn create_content_type(
+        &self,
+        request: Request<api::CreateZoneContentTypeRequest>,
+    ) -> Result<Response<api::CreateZoneContentTypeResponse>, Status> {
+        let req_ct = match &request.get_ref().content_type {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("content_type is missing"));
+            }
+        };
+
+        self.validator
+            .validate(request.extensions(), validator::ValidateIsAdmin::new())
+            .await?;
+
+        let ct = zone_content_type::create(content_type_from_proto(req_ct))
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::CreateZoneContentTypeResponse { id: ct.id });
+        resp.metadata_mut()
+            .insert("x-log-content_type_id", ct.id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn get_content_type(
+        &self,
+        request: Request<api::GetZoneContentTypeRequest>,
+    ) -> Result<Response<api::GetZoneContentTypeResponse>, Status> {
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateActiveUserOrKey::new(),
+            )
+            .await?;
+
+        let req = request.get_ref();
+        let ct = zone_content_type::get(req.id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(api::GetZoneContentTypeResponse {
+            created_at: Some(helpers::datetime_to_prost_timestamp(&ct.created_at)),
+            updated_at: Some(helpers::datetime_to_prost_timestamp(&ct.updated_at)),
+            content_type: Some(ct.into()),
+        });
+        resp.metadata_mut()
+            .insert("x-log-content_type_id", req.id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn update_content_type(
+        &self,
+        request: Request<api::UpdateZoneContentTypeRequest>,
+    ) -> Result<Response<()>, Status> {
+        let req_ct = match &request.get_ref().content_type {
+            Some(v) => v,
+            None => {
+                return Err(Status::invalid_argument("content_type is missing"));
+            }
+        };
+
+        self.validator
+            .validate(request.extensions(), validator::ValidateIsAdmin::new())
+            .await?;
+
+        zone_content_type::update(zone_content_type::ZoneContentType {
+            id: req_ct.id,
+            ..content_type_from_proto(req_ct)
+        })
+        .await
+        .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut().insert(
+            "x-log-content_type_id",
+            req_ct.id.to_string().parse().unwrap(),
+        );
+
+        Ok(resp)
+    }
+
+    async fn delete_content_type(
+        &self,
+        request: Request<api::DeleteZoneContentTypeRequest>,
+    ) -> Result<Response<()>, Status> {
+        self.validator
+            .validate(request.extensions(), validator::ValidateIsAdmin::new())
+            .await?;
+
+        let req = request.get_ref();
+        zone_content_type::delete(req.id)
+            .await
+            .map_err(|e| e.status())?;
+
+        let mut resp = Response::new(());
+        resp.metadata_mut()
+            .insert("x-log-content_type_id", req.id.to_string().parse().unwrap());
+
+        Ok(resp)
+    }
+
+    async fn list_content_types(
+        &self,
+        request: Request<()>,
+    ) -> Result<Response<api::ListZoneContentTypesResponse>, Status> {
+        self.validator
+            .validate(
+                request.extensions(),
+                validator::ValidateActiveUserOrKey::new(),
+            )
+            .await?;
+
+        let items = zone_content_type::list().await.map_err(|e| e.status())?;
+
+        Ok(Response::new(api::ListZoneContentTypesResponse {
+            result: items.into_iter().map(|ct| ct.into()).collect(),
+        }))
+    }
+}
+
+fn content_type_from_proto(ct: &api::ZoneContentType) -> zone_content_type::ZoneContentType {
+    zone_content_type::ZoneContentType {
+        name: ct.name.clone(),
+        min_temperature: ct.min_temperature,
+        max_temperature: ct.max_temperature,
+        min_humidity: ct.min_humidity,
+        max_humidity: ct.max_humidity,
+        measurements: ct.measurements.iter().cloned().map(Some).collect(),
+        ..Default::default()
+    }
+}
+
+async fn get_site(site_id: i64) -> Result<site::Site, Status> {
+    let site_id = i32::try_from(site_id).map_err(|_| {
+        Status::invalid_argument(format!("site_id {} is out of range for i32", site_id))
+    })?;
+    site::get(site_id).await.map_err(|e| e.status())
+}
+
+fn site_from_proto(s: &api::Site, tenant_id: Uuid) -> site::Site {
+    site::Site {
+        tenant_id: tenant_id.into(),
+        site_name: s.site_name.clone(),
+        address: s.address.clone(),
+        latitude: if s.latitude == 0.0 && s.longitude == 0.0 {
+            None
+        } else {
+            Some(s.latitude)
+        },
+        longitude: if s.latitude == 0.0 && s.longitude == 0.0 {
+            None
+        } else {
+            Some(s.longitude)
+        },
+        timezone: if s.timezone.is_empty() {
+            "UTC".into()
+        } else {
+            s.timezone.clone()
+        },
+        site_order: s.order,
+        ..Default::default()
+    }
+}
+
+// Groups the zones by site, keeping the order of the zones. The zones which do not belong to a
+// site are grouped under site_id 0.
+fn group_by_site(zones: Vec<api::GetZonesItem>) -> Vec<api::SiteZones> {
+    let mut out: Vec<api::SiteZones> = Vec::new();
+    for z in zones {
+        match out.iter_mut().find(|s| s.site_id == z.site_id) {
+            Some(s) => s.zones.push(z),
+            None => out.push(api::SiteZones {
+                site_id: z.site_id,
+                site_name: z.site_name.clone(),
+                zones: vec![z],
+            }),
+        }
+    }
+    out
+}
+
+impl From<site::Site> for api::Site {
+    fn from(s: site::Site) -> Self {
+        api::Site {
+            site_id: s.site_id as i64,
+            org_id: s.tenant_id.to_string(),
+            site_name: s.site_name,
+            address: s.address,
+            latitude: s.latitude.unwrap_or_default(),
+            longitude: s.longitude.unwrap_or_default(),
+            timezone: s.timezone,
+            order: s.site_order,
+        }
+    }
+}
+
+impl From<zone_content_type::ZoneContentType> for api::ZoneContentType {
+    fn from(ct: zone_content_type::ZoneContentType) -> Self {
+        api::ZoneContentType {
+            id: ct.id,
+            name: ct.name,
+            min_temperature: ct.min_temperature,
+            max_temperature: ct.max_temperature,
+            min_humidity: ct.min_humidity,
+            max_humidity: ct.max_humidity,
+            measurements: ct.measurements.into_iter().flatten().collect(),
+        }
+    }
+}
+
+impl From<ZoneAggregatesSerde> for api::ZoneAggregates {
+    fn from(a: ZoneAggregatesSerde) -> Self {
+        api::ZoneAggregates {
+            min_temperature: a.min_temperature,
+            avg_temperature: a.avg_temperature,
+            max_temperature: a.max_temperature,
+            open_door_count: a.open_door_count,
+            leak_count: a.leak_count,
+            offline_device_count: a.offline_device_count,
+            active_alarm_count: a.active_alarm_count,
+        }
+    }
 }
+
 impl From<zone::Zone> for api::Zone {
     fn from(z: zone::Zone) -> Self {
         api::Zone {
@@ -168,6 +720,7 @@ impl From<zone::Zone> for api::Zone {
             content_type: z.content_type.unwrap_or_default(),
             org_id: z.tanent_id.map(|id| id.to_string()).unwrap_or_default(),
             devices: z.devices.into_iter().filter_map(|d| d).collect(),
+            site_id: z.site_id.unwrap_or_default() as i64,
         }
     }
 }
@@ -193,6 +746,9 @@ impl From<GetZonesItemSerde> for GetZonesItem {
             order: item.order,
             devices: item.devices.into_iter().map(Into::into).collect(),
             content_type: item.contentType,
+            site_id: item.site_id.unwrap_or_default(),
+            site_name: item.site_name.unwrap_or_default(),
+            aggregates: Some(item.aggregates.into()),
         }
     }
 }
@@ -211,6 +767,20 @@ impl From<ZoneDeviceSerde> for ZoneDevice {
             humadity_calibration: d.humadity_calibration,
             variables: d.variables,
             tags: d.tags,
+            is_offline: d.is_offline,
+            measurements: d.measurements.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<ZoneMeasurementSerde> for chirpstack_api::api::ZoneMeasurement {
+    fn from(m: ZoneMeasurementSerde) -> Self {
+        Self {
+            kind: m.kind,
+            unit: m.unit,
+            value: m.value,
+            text_value: m.text_value.unwrap_or_default(),
+            time: Some(helpers::datetime_to_prost_timestamp(&m.time)),
         }
     }
 }
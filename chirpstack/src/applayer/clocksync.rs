@@ -0,0 +1,90 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::gpstime::ToGpsTime;
+use crate::storage::{device, device_queue};
+use crate::uplink::helpers;
+use chirpstack_api::gw;
+use lrwn::applayer::clocksync;
+
+pub async fn handle_uplink(
+    dev: &device::Device,
+    f_port: u8,
+    rx_info: &[gw::UplinkRxInfo],
+    data: &[u8],
+) -> Result<()> {
+    let rx_time: DateTime<Utc> = helpers::get_rx_timestamp_chrono(rx_info);
+    let gps_time = rx_time.to_gps_time().num_seconds();
+
+    let mut answers: Vec<clocksync::Payload> = Vec::new();
+
+    for pl in clocksync::Payload::from_slice_multi(true, data)? {
+        match pl {
+            clocksync::Payload::AppTimeReq(pl) => {
+                let time_correction = get_time_correction(gps_time, pl.device_time);
+
+                info!(dev_eui = %dev.dev_eui, device_time = pl.device_time, gps_time = gps_time, time_correction = time_correction, "AppTimeReq received");
+
+                // The device does not need an answer when its clock is correct, unless it
+                // explicitly requests one.
+                if time_correction != 0 || pl.param.ans_required {
+                    answers.push(clocksync::Payload::AppTimeAns(
+                        clocksync::AppTimeAnsPayload {
+                            time_correction,
+                            param: clocksync::AppTimeAnsPayloadParam {
+                                token_ans: pl.param.token_req,
+                            },
+                        },
+                    ));
+                }
+            }
+            clocksync::Payload::PackageVersionAns(pl) => {
+                info!(dev_eui = %dev.dev_eui, package_identifier = pl.package_identifier, package_version = pl.package_version, "PackageVersionAns received");
+            }
+            clocksync::Payload::DeviceAppTimePeriodicityAns(pl) => {
+                if pl.status.not_supported {
+                    warn!(dev_eui = %dev.dev_eui, "DeviceAppTimePeriodicityReq not supported by device");
+                } else {
+                    info!(dev_eui = %dev.dev_eui, device_time = pl.time, "DeviceAppTimePeriodicityAns received");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if answers.is_empty() {
+        return Ok(());
+    }
+
+    device_queue::enqueue_item(device_queue::DeviceQueueItem {
+        dev_eui: dev.dev_eui,
+        f_port: f_port as i16,
+        data: clocksync::Payload::to_vec_multi(&answers)?,
+        ..Default::default()
+    })
+    .await?;
+
+    Ok(())
+}
+
+// Returns the correction (in seconds) which the device must add to its clock. The device time
+// is the GPS time (in seconds) modulo 2^32.
+pub fn get_time_correction(gps_time: i64, device_time: u32) -> i32 {
+    (gps_time as u32).wrapping_sub(device_time) as i32
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_time_correction() {
+        assert_eq!(0, get_time_correction(1_400_000_000, 1_400_000_000));
+        assert_eq!(5, get_time_correction(1_400_000_005, 1_400_000_000));
+        assert_eq!(-5, get_time_correction(1_400_000_000, 1_400_000_005));
+
+        // GPS time beyond 2^32 seconds.
+        assert_eq!(3, get_time_correction((1 << 32) + 1, u32::MAX - 1));
+    }
+}
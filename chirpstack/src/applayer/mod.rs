@@ -0,0 +1,36 @@
+use anyhow::Result;
+use tracing::{error, trace};
+
+use crate::storage::{device, device_profile};
+use chirpstack_api::gw;
+
+pub mod clocksync;
+
+// Handles the uplink payloads of the Application Layer packages enabled in the device-profile.
+// Errors are logged, as these must not abort the handling of the uplink.
+pub async fn handle_uplink(
+    dev: &device::Device,
+    dp: &device_profile::DeviceProfile,
+    rx_info: &[gw::UplinkRxInfo],
+    f_port: u8,
+    data: &[u8],
+) {
+    if let Err(e) = _handle_uplink(dev, dp, rx_info, f_port, data).await {
+        error!(dev_eui = %dev.dev_eui, f_port = f_port, error = %e, "Handling application-layer uplink failed");
+    }
+}
+
+async fn _handle_uplink(
+    dev: &device::Device,
+    dp: &device_profile::DeviceProfile,
+    rx_info: &[gw::UplinkRxInfo],
+    f_port: u8,
+    data: &[u8],
+) -> Result<()> {
+    if dp.get_ts003_f_port() == Some(f_port) {
+        trace!(dev_eui = %dev.dev_eui, "Handling clock synchronization uplink");
+        return clocksync::handle_uplink(dev, f_port, rx_info, data).await;
+    }
+
+    Ok(())
+}
@@ -169,6 +169,19 @@ r#"
   # ChirpStack will be allowed.
   allow_unknown_gateways={{ gateway.allow_unknown_gateways }}
 
+  # Offline after missed stats.
+  #
+  # A gateway is marked offline when it did not send any stats for the given
+  # number of stats intervals. On marking a gateway offline (and when it comes
+  # back online), the users of the tenant are notified and a gateway state
+  # event is published to the integrations. Set this to 0 to disable.
+  offline_after_missed_stats={{ gateway.offline_after_missed_stats }}
+
+  # Offline check interval.
+  #
+  # The interval in which gateways are checked for missed stats.
+  offline_check_interval="{{ gateway.offline_check_interval }}"
+
 
 # Network related configuration.
 [network]
@@ -388,6 +401,12 @@ r#"
     # Event topic template.
     event_topic="{{ integration.mqtt.event_topic }}"
 
+    # Gateway event topic template.
+    #
+    # This is the topic used for publishing gateway events (e.g. the gateway
+    # went offline or came back online).
+    gateway_event_topic="{{ integration.mqtt.gateway_event_topic }}"
+
     # Command topic.
     #
     # This is the topic on which the MQTT subscribes for receiving (enqueue) commands.
@@ -570,6 +589,9 @@ r#"
     # Maximum execution time.
     max_execution_time="{{ codec.js.max_execution_time }}"
 
+    # Maximum memory usage (in bytes) for evaluating the formula of a virtual device.
+    max_formula_memory_usage={{ codec.js.max_formula_memory_usage }}
+
 
 # User authentication configuration.
 [user_authentication]
@@ -990,6 +1012,256 @@ r#"
   # default tileserver_url (OSM). If you configure a different tile-server, you
   # might need to update the map_attribution.
   map_attribution="{{ui.map_attribution}}"
+
+
+# Alarm configuration.
+[alarm]
+  # Timezone.
+  #
+  # The timezone of the alarm and defrost schedules. The schedules are
+  # evaluated against the time of day in this timezone, e.g. Europe/Istanbul.
+  timezone="{{ alarm.timezone }}"
+
+  # No-data check interval.
+  #
+  # The interval in which the no-data (device silence) alarms are evaluated.
+  # A notification is sent to the alarm recipients when a device did not send
+  # any data within the number of minutes configured in the alarm. Set this to
+  # 0s to disable the evaluation.
+  no_data_check_interval="{{ alarm.no_data_check_interval }}"
+
+  # Battery check interval.
+  #
+  # The interval in which the battery level, discharge rate and expected
+  # remaining days are calculated for all devices. Set this to 0s to disable
+  # battery tracking.
+  battery_check_interval="{{ alarm.battery_check_interval }}"
+
+  # Battery trend days.
+  #
+  # The number of days of battery history used to estimate the discharge rate.
+  battery_trend_days={{ alarm.battery_trend_days }}
+
+  # Low battery level (%).
+  #
+  # The users of the zone of a device are notified once the battery level of
+  # the device drops to this level.
+  low_battery_level={{ alarm.low_battery_level }}
+
+  # Low battery days.
+  #
+  # The users of the zone of a device are also notified when the device is
+  # expected to run out of battery within this number of days.
+  low_battery_days={{ alarm.low_battery_days }}
+
+  # Link check interval.
+  #
+  # The interval in which the link quality (RSSI, SNR and link margin) and the
+  # signal tag of the devices are derived from the uplinks of the last day.
+  # Set this to 0s to disable link tracking.
+  link_check_interval="{{ alarm.link_check_interval }}"
+
+  # Link trend days.
+  #
+  # The number of days of uplink history used as baseline for the link margin
+  # of a device. Older link history is removed.
+  link_trend_days={{ alarm.link_trend_days }}
+
+  # Link degradation margin (dB).
+  #
+  # The users of the zone of a device are notified once the link margin of
+  # the last day is this much below the baseline.
+  link_degradation_margin={{ alarm.link_degradation_margin }}
+
+  # Defrost check interval.
+  #
+  # The interval in which the defrost windows which have ended are evaluated.
+  # A notification is sent to the users of the zone of a device when the
+  # expected defrost cycle was missed or took longer than expected. The learned
+  # defrost schedules are refreshed once a day. Set this to 0s to disable the
+  # evaluation.
+  defrost_check_interval="{{ alarm.defrost_check_interval }}"
+
+  # Defrost tolerance.
+  #
+  # The margin before and after an expected defrost window. Temperature alarms
+  # are suppressed during the defrost window including this margin.
+  defrost_tolerance="{{ alarm.defrost_tolerance }}"
+
+  # Defrost temperature rise.
+  #
+  # The temperature rise (above the median temperature) which marks the start
+  # of a defrost cycle. The cycle ends when the temperature is back within half
+  # of this rise.
+  defrost_temperature_rise={{ alarm.defrost_temperature_rise }}
+
+  # Defrost learning days.
+  #
+  # The number of days of temperature history used to learn the defrost
+  # schedule of a device.
+  defrost_learning_days={{ alarm.defrost_learning_days }}
+
+  # Escalation check interval.
+  #
+  # The interval in which the escalations of the triggered alarms which have
+  # not been acknowledged are checked. When the delay of the next step of the
+  # escalation policy has passed, the step is executed. Set this to 0s to
+  # disable alarm escalation.
+  escalation_check_interval="{{ alarm.escalation_check_interval }}"
+
+  # Escalation webhook timeout.
+  #
+  # The timeout of the requests made by the webhook escalation steps.
+  escalation_webhook_timeout="{{ alarm.escalation_webhook_timeout }}"
+
+  # SMS gateway URL.
+  #
+  # The alarm and escalation SMS messages are posted to this URL, one request
+  # per phone number with a JSON body containing the "to" and "message" fields.
+  # When not set, no SMS messages are sent.
+  sms_gateway_url="{{ alarm.sms_gateway_url }}"
+
+  # SMS gateway token.
+  #
+  # When set, this token is sent as Bearer token in the Authorization header
+  # of the SMS gateway requests.
+  sms_gateway_token="{{ alarm.sms_gateway_token }}"
+
+  # SMS gateway timeout.
+  #
+  # The timeout of the SMS gateway requests.
+  sms_gateway_timeout="{{ alarm.sms_gateway_timeout }}"
+
+  # Webhook check interval.
+  #
+  # The interval in which the pending deliveries of the alarm and automation
+  # webhook subscriptions of the tenants are posted. Set this to 0s to disable
+  # the webhook deliveries within this instance.
+  webhook_check_interval="{{ alarm.webhook_check_interval }}"
+
+  # Webhook timeout.
+  #
+  # The timeout of the webhook delivery requests.
+  webhook_timeout="{{ alarm.webhook_timeout }}"
+
+  # Webhook retry delay.
+  #
+  # The delay before a failed delivery is retried, this delay is doubled on
+  # every attempt (max. 6 hours).
+  webhook_retry_delay="{{ alarm.webhook_retry_delay }}"
+
+  # Webhook max attempts.
+  #
+  # The maximum number of delivery attempts, after which the delivery is
+  # dead-lettered. Dead-lettered deliveries can be retried using the API.
+  webhook_max_attempts={{ alarm.webhook_max_attempts }}
+
+  # Webhook log days.
+  #
+  # The number of days the delivered and dead-lettered deliveries are kept in
+  # the delivery log.
+  webhook_log_days={{ alarm.webhook_log_days }}
+
+
+# Tenant usage metering configuration.
+#
+# The uplinks, downlinks, notifications and SMS messages are metered per
+# tenant. The monthly message and SMS quotas and the over-quota action are
+# configured per tenant.
+[metering]
+  # Throttle hourly message count.
+  #
+  # When a tenant with the THROTTLE over-quota action exceeds its monthly
+  # message quota, it is limited to this number of messages (uplinks and
+  # downlinks) per hour.
+  throttle_hourly_message_count={{ metering.throttle_hourly_message_count }}
+
+  # Throttle hourly SMS count.
+  #
+  # When a tenant with the THROTTLE over-quota action exceeds its monthly SMS
+  # quota, it is limited to this number of SMS messages per hour.
+  throttle_hourly_sms_count={{ metering.throttle_hourly_sms_count }}
+
+  # SMS unit cost.
+  #
+  # The cost of a single SMS message, used to report the SMS cost in the
+  # tenant usage.
+  sms_unit_cost={{ metering.sms_unit_cost }}
+
+
+# Uplink worker configuration.
+#
+# The decoded uplinks are not processed on the uplink path. The sensor-data
+# and alarm tasks are stored in the uplink_task table and processed by a pool
+# of workers. The tasks of a device are processed in order, a failed task is
+# retried before the next tasks of the device are processed.
+[uplink_worker]
+  # Number of workers.
+  #
+  # Set this to 0 to not process tasks within this instance, e.g. when the
+  # tasks are processed by other ChirpStack instances.
+  workers={{ uplink_worker.workers }}
+
+  # Batch size.
+  #
+  # The maximum number of tasks claimed by a worker at once.
+  batch_size={{ uplink_worker.batch_size }}
+
+  # Poll interval.
+  #
+  # The interval in which an idle worker checks for new tasks.
+  poll_interval="{{ uplink_worker.poll_interval }}"
+
+  # Lock timeout.
+  #
+  # A claimed task is locked for this duration. When the worker did not
+  # complete the task within this time (e.g. the instance stopped), the task
+  # is claimed again.
+  lock_timeout="{{ uplink_worker.lock_timeout }}"
+
+  # Retry delay.
+  #
+  # The delay before a failed task is retried, this delay is multiplied by
+  # the number of attempts.
+  retry_delay="{{ uplink_worker.retry_delay }}"
+
+  # Max attempts.
+  #
+  # The maximum number of attempts, after which a failing task is dropped.
+  max_attempts={{ uplink_worker.max_attempts }}
+
+
+# Sensor-data configuration.
+#
+# The sensor readings are stored in the monthly partitions of the device_data
+# table and are rolled up into the device_data_hourly and device_data_daily
+# tables. The daily rollups are kept forever. The retention can be overridden
+# per tenant.
+[sensor_data]
+  # Maintenance interval.
+  #
+  # The interval in which the rollups are updated, the partitions of the
+  # following months are created and the expired data is removed. Set this to
+  # 0s to disable the sensor-data maintenance.
+  maintenance_interval="{{ sensor_data.maintenance_interval }}"
+
+  # Raw retention days.
+  #
+  # The number of days the raw sensor readings are kept. Set this to 0 to
+  # keep the raw readings forever.
+  raw_retention_days={{ sensor_data.raw_retention_days }}
+
+  # Hourly retention months.
+  #
+  # The number of months the hourly rollups are kept. Set this to 0 to keep
+  # the hourly rollups forever.
+  hourly_retention_months={{ sensor_data.hourly_retention_months }}
+
+  # Premake partitions.
+  #
+  # The number of monthly partitions which are created ahead of the current
+  # month (PostgreSQL only).
+  premake_partitions={{ sensor_data.premake_partitions }}
 "#].join("\n");
 
     let mut reg = Handlebars::new();
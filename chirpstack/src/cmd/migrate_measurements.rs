@@ -0,0 +1,37 @@
+use anyhow::Result;
+use chrono::{Datelike, Months, NaiveDate};
+use tracing::info;
+
+use crate::storage::{self, measurement};
+
+pub async fn run() -> Result<()> {
+    storage::setup().await?;
+
+    info!("Migrating sensor data from device_data to measurement");
+
+    let (start, end) = match measurement::get_device_data_interval().await? {
+        Some(v) => v,
+        None => {
+            info!("There is no sensor data to migrate");
+            return Ok(());
+        }
+    };
+
+    // Migrate month by month, to keep the size of each copy (and transaction) bounded.
+    let mut month = NaiveDate::from_ymd_opt(start.year(), start.month(), 1).unwrap_or(start.date());
+    while month <= end.date() {
+        let next = month + Months::new(1);
+
+        let count = measurement::copy_device_data(
+            month.and_time(Default::default()),
+            next.and_time(Default::default()),
+        )
+        .await?;
+
+        info!(month = %month.format("%Y-%m"), count = count, "Sensor data of month migrated");
+        month = next;
+    }
+
+    info!("Sensor data migrated");
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use chrono::{Datelike, Months, NaiveDate};
+use tracing::info;
+
+use crate::storage::{self, sensor_data};
+
+pub async fn run(drop: bool) -> Result<()> {
+    storage::setup().await?;
+
+    if cfg!(feature = "sqlite") {
+        info!("The sensor data has been migrated by the SQLite database migration");
+        return Ok(());
+    }
+
+    info!("Migrating sensor data from device_data_2025 to device_data");
+
+    let (start, end) = match sensor_data::get_legacy_interval().await? {
+        Some(v) => v,
+        None => {
+            info!("There is no sensor data to migrate");
+            return Ok(());
+        }
+    };
+
+    // Migrate month by month, such that each month is copied into its own partition and the
+    // rollups of the month are computed before continuing with the next month.
+    let mut month = NaiveDate::from_ymd_opt(start.year(), start.month(), 1).unwrap_or(start.date());
+    while month <= end.date() {
+        let next = month + Months::new(1);
+
+        sensor_data::create_partition(month).await?;
+        let count = sensor_data::copy_legacy(
+            month.and_time(Default::default()),
+            next.and_time(Default::default()),
+        )
+        .await?;
+        sensor_data::update_rollups(
+            month.and_time(Default::default()),
+            next.and_time(Default::default()),
+        )
+        .await?;
+
+        info!(month = %month.format("%Y-%m"), count = count, "Sensor data of month migrated");
+        month = next;
+    }
+
+    sensor_data::reset_id_sequence().await?;
+
+    if drop {
+        sensor_data::drop_legacy().await?;
+    }
+
+    info!("Sensor data migrated");
+    Ok(())
+}
@@ -3,5 +3,7 @@ pub mod create_api_key;
 pub mod import_legacy_lorawan_devices_repository;
 pub mod import_lorawan_device_profiles;
 pub mod migrate_ds_to_pg;
+pub mod migrate_measurements;
+pub mod migrate_sensor_data;
 pub mod print_ds;
 pub mod root;
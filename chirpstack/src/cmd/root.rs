@@ -5,7 +5,9 @@ use signal_hook_tokio::Signals;
 use tracing::{info, warn};
 
 use crate::gateway;
-use crate::{adr, api, backend, downlink, integration, region, storage};
+use crate::{
+    adr, alerting, api, backend, downlink, integration, region, sensor_data, storage, uplink,
+};
 
 pub async fn run() -> Result<()> {
     info!(
@@ -20,7 +22,11 @@ pub async fn run() -> Result<()> {
     adr::setup().await?;
     integration::setup().await?;
     gateway::backend::setup().await?;
+    gateway::watchdog::setup().await;
     downlink::setup().await;
+    alerting::setup().await;
+    uplink::worker::setup().await;
+    sensor_data::setup().await;
     api::setup().await?;
 
     let mut signals = Signals::new([SIGINT, SIGTERM]).unwrap();
@@ -193,6 +193,47 @@ pub async fn encode(
     })
 }
 
+// Evaluates the given expression (e.g. the formula of a virtual device) with the given values
+// as global variables. The expression must evaluate to a finite number. As the expression is
+// evaluated for every uplink of the source devices, it runs on the blocking thread-pool with
+// both an execution time and memory limit.
+pub async fn evaluate(expression: &str, values: &HashMap<String, f64>) -> Result<f64> {
+    let expression = expression.to_string();
+    let values = values.clone();
+
+    tokio::task::spawn_blocking(move || -> Result<f64> {
+        let conf = config::get();
+        let max_run_ts = SystemTime::now() + conf.codec.js.max_execution_time;
+
+        let rt = rquickjs::Runtime::new()?;
+        rt.set_interrupt_handler(Some(Box::new(move || SystemTime::now() > max_run_ts)));
+        rt.set_memory_limit(conf.codec.js.max_formula_memory_usage);
+
+        let ctx = rquickjs::Context::full(&rt)?;
+
+        let v: f64 = ctx.with(|ctx| -> Result<f64> {
+            let globals = ctx.globals();
+            for (k, v) in &values {
+                globals.set(k.as_str(), *v)?;
+            }
+
+            let mut eval_options = rquickjs::context::EvalOptions::default();
+            eval_options.strict = false;
+
+            ctx.eval_with_options(format!("({})", expression), eval_options)
+                .catch(&ctx)
+                .map_err(|e| anyhow!("JS error: {}", e))
+        })?;
+
+        if !v.is_finite() {
+            return Err(anyhow!("Expression did not return a finite number"));
+        }
+
+        Ok(v)
+    })
+    .await?
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
@@ -402,4 +443,32 @@ pub mod test {
         let out = encode(10, &vars, &encoder, &input).await.unwrap();
         assert_eq!(vec![1], out);
     }
+
+    #[tokio::test]
+    pub async fn test_evaluate() {
+        let mut values: HashMap<String, f64> = HashMap::new();
+        values.insert("t".into(), 20.0);
+        values.insert("h".into(), 50.0);
+
+        // dew point (Magnus formula)
+        let dew_point = evaluate(
+            "243.04 * (Math.log(h / 100) + 17.625 * t / (243.04 + t)) / (17.625 - Math.log(h / 100) - 17.625 * t / (243.04 + t))",
+            &values,
+        )
+        .await
+        .unwrap();
+        assert_eq!(9.26, (dew_point * 100.0).round() / 100.0);
+
+        assert!(evaluate("t / 0", &values).await.is_err());
+        assert!(evaluate("foo * 2", &values).await.is_err());
+        assert!(evaluate("'abc'", &values).await.is_err());
+
+        // execution time and memory limits
+        assert!(evaluate("(() => { while (true) {} })()", &values)
+            .await
+            .is_err());
+        assert!(evaluate("'x'.repeat(64 * 1024 * 1024).length", &values)
+            .await
+            .is_err());
+    }
 }
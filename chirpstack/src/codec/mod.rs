@@ -105,6 +105,12 @@ pub async fn struct_to_binary(
     })
 }
 
+// Evaluates the formula of a virtual device. The formula is a JS expression, the values are
+// available as variables.
+pub async fn evaluate_formula(formula: &str, values: &HashMap<String, f64>) -> Result<f64> {
+    js::evaluate(formula, values).await
+}
+
 pub fn get_measurements(s: &pbjson_types::Struct) -> HashMap<String, pbjson_types::value::Kind> {
     let mut out: HashMap<String, pbjson_types::value::Kind> = HashMap::new();
 
@@ -33,6 +33,10 @@ pub struct Configuration {
     pub keks: Vec<Kek>,
     pub regions: Vec<Region>,
     pub ui: UI,
+    pub alarm: Alarm,
+    pub metering: Metering,
+    pub uplink_worker: UplinkWorker,
+    pub sensor_data: SensorData,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -138,6 +142,9 @@ pub struct Gateway {
     pub ca_cert: String,
     pub ca_key: String,
     pub allow_unknown_gateways: bool,
+    pub offline_after_missed_stats: u32,
+    #[serde(with = "humantime_serde")]
+    pub offline_check_interval: Duration,
 }
 
 impl Default for Gateway {
@@ -147,6 +154,8 @@ impl Default for Gateway {
             ca_cert: "".to_string(),
             ca_key: "".to_string(),
             allow_unknown_gateways: false,
+            offline_after_missed_stats: 3,
+            offline_check_interval: Duration::from_secs(60),
         }
     }
 }
@@ -271,6 +280,7 @@ pub struct Integration {
 pub struct MqttIntegration {
     pub client: MqttIntegrationClient,
     pub event_topic: String,
+    pub gateway_event_topic: String,
     pub command_topic: String,
     pub json: bool,
     pub server: String,
@@ -292,6 +302,8 @@ impl Default for MqttIntegration {
         MqttIntegration {
             client: Default::default(),
             event_topic: "application/{{application_id}}/device/{{dev_eui}}/event/{{event}}".into(),
+            gateway_event_topic: "tenant/{{tenant_id}}/gateway/{{gateway_id}}/event/{{event}}"
+                .into(),
             command_topic: "application/{{application_id}}/device/{{dev_eui}}/command/{{command}}"
                 .into(),
             json: true,
@@ -406,12 +418,14 @@ pub struct Codec {
 pub struct CodecJs {
     #[serde(with = "humantime_serde")]
     pub max_execution_time: Duration,
+    pub max_formula_memory_usage: usize,
 }
 
 impl Default for CodecJs {
     fn default() -> Self {
         CodecJs {
             max_execution_time: Duration::from_millis(100),
+            max_formula_memory_usage: 16 * 1024 * 1024,
         }
     }
 }
@@ -796,6 +810,141 @@ impl Default for UI {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Alarm {
+    pub timezone: chrono_tz::Tz,
+    #[serde(with = "humantime_serde")]
+    pub no_data_check_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub battery_check_interval: Duration,
+    pub battery_trend_days: u32,
+    pub low_battery_level: f32,
+    pub low_battery_days: u32,
+    #[serde(with = "humantime_serde")]
+    pub link_check_interval: Duration,
+    pub link_trend_days: u32,
+    pub link_degradation_margin: f32,
+    #[serde(with = "humantime_serde")]
+    pub defrost_check_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub defrost_tolerance: Duration,
+    pub defrost_temperature_rise: f32,
+    pub defrost_learning_days: u32,
+    #[serde(with = "humantime_serde")]
+    pub escalation_check_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub escalation_webhook_timeout: Duration,
+    pub sms_gateway_url: String,
+    pub sms_gateway_token: String,
+    #[serde(with = "humantime_serde")]
+    pub sms_gateway_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub webhook_check_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub webhook_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub webhook_retry_delay: Duration,
+    pub webhook_max_attempts: u32,
+    pub webhook_log_days: u32,
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Alarm {
+            timezone: chrono_tz::Europe::Istanbul,
+            no_data_check_interval: Duration::from_secs(60),
+            battery_check_interval: Duration::from_secs(60 * 60 * 6),
+            battery_trend_days: 30,
+            low_battery_level: 20.0,
+            low_battery_days: 30,
+            link_check_interval: Duration::from_secs(60 * 15),
+            link_trend_days: 7,
+            link_degradation_margin: 6.0,
+            defrost_check_interval: Duration::from_secs(60 * 5),
+            defrost_tolerance: Duration::from_secs(60 * 15),
+            defrost_temperature_rise: 5.0,
+            defrost_learning_days: 7,
+            escalation_check_interval: Duration::from_secs(60),
+            escalation_webhook_timeout: Duration::from_secs(10),
+            sms_gateway_url: "".into(),
+            sms_gateway_token: "".into(),
+            sms_gateway_timeout: Duration::from_secs(10),
+            webhook_check_interval: Duration::from_secs(10),
+            webhook_timeout: Duration::from_secs(10),
+            webhook_retry_delay: Duration::from_secs(30),
+            webhook_max_attempts: 8,
+            webhook_log_days: 30,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Metering {
+    pub throttle_hourly_message_count: u32,
+    pub throttle_hourly_sms_count: u32,
+    pub sms_unit_cost: f64,
+}
+
+impl Default for Metering {
+    fn default() -> Self {
+        Metering {
+            throttle_hourly_message_count: 60,
+            throttle_hourly_sms_count: 5,
+            sms_unit_cost: 0.0,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct UplinkWorker {
+    pub workers: usize,
+    pub batch_size: usize,
+    #[serde(with = "humantime_serde")]
+    pub poll_interval: Duration,
+    #[serde(with = "humantime_serde")]
+    pub lock_timeout: Duration,
+    #[serde(with = "humantime_serde")]
+    pub retry_delay: Duration,
+    pub max_attempts: u32,
+}
+
+impl Default for UplinkWorker {
+    fn default() -> Self {
+        UplinkWorker {
+            workers: 4,
+            batch_size: 100,
+            poll_interval: Duration::from_millis(500),
+            lock_timeout: Duration::from_secs(60),
+            retry_delay: Duration::from_secs(10),
+            max_attempts: 5,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SensorData {
+    #[serde(with = "humantime_serde")]
+    pub maintenance_interval: Duration,
+    pub raw_retention_days: u32,
+    pub hourly_retention_months: u32,
+    pub premake_partitions: u32,
+}
+
+impl Default for SensorData {
+    fn default() -> Self {
+        SensorData {
+            maintenance_interval: Duration::from_secs(60 * 15),
+            raw_retention_days: 0,
+            hourly_retention_months: 0,
+            premake_partitions: 2,
+        }
+    }
+}
+
 pub fn load(config_dir: &Path) -> Result<()> {
     let mut content: String = String::new();
 
@@ -11,7 +11,7 @@ use tracing::{debug, span, trace, warn, Instrument, Level};
 use crate::api::backend::get_async_receiver;
 use crate::api::helpers::{FromProto, ToProto};
 use crate::backend::roaming;
-use crate::downlink::{classb, error::Error, helpers, tx_ack};
+use crate::downlink::{classb, error::Error, helpers, retry, tx_ack};
 use crate::gpstime::{ToDateTime, ToGpsTime};
 use crate::storage;
 use crate::storage::{
@@ -502,9 +502,28 @@ impl Data {
             // Note that get_next_for_dev_eui only returns pending queue-items when they have
             // expired. For pending queue-items that have not yet expired, a NotFound is returned.
             if qi.is_pending {
-                device_queue::delete_item(&qi.id)
-                    .await
-                    .context("Delete device queue-item")?;
+                // Re-enqueue the queue-item in case the device-profile has a retry policy.
+                // Encrypted queue-items can not be retried as these are bound to the FCntDown.
+                let max_retries = self.device_profile.get_downlink_retry_count();
+                let retry = match max_retries {
+                    Some(v) => !qi.is_encrypted && (qi.retry_count.max(0) as u16) < v,
+                    None => false,
+                };
+
+                if retry {
+                    let backoff = retry::get_backoff(
+                        self.device_profile.downlink_retry_backoff.unwrap_or(0),
+                        qi.retry_count,
+                    );
+                    let scheduled_at = Utc::now() + chrono::Duration::from_std(backoff)?;
+                    device_queue::retry_item(&qi.id, scheduled_at)
+                        .await
+                        .context("Retry device queue-item")?;
+                } else {
+                    device_queue::delete_item(&qi.id)
+                        .await
+                        .context("Delete device queue-item")?;
+                }
 
                 let pl = integration_pb::AckEvent {
                     deduplication_id: match &self.uplink_frame_set {
@@ -523,8 +542,47 @@ impl Data {
 
                 integration::ack_event(self.application.id.into(), &self.device.variables, &pl)
                     .await;
+                if retry {
+                    warn!(dev_eui = %self.device.dev_eui, device_queue_item_id = %qi.id, retry_count = qi.retry_count + 1, "Device queue-item re-enqueued because of timeout");
+                    continue;
+                }
+
                 warn!(dev_eui = %self.device.dev_eui, device_queue_item_id = %qi.id, "Device queue-item discarded because of timeout");
 
+                if max_retries.is_some() {
+                    let pl = integration_pb::LogEvent {
+                        time: Some(Utc::now().into()),
+                        device_info: Some(device_info.clone()),
+                        level: integration_pb::LogLevel::Error.into(),
+                        code: integration_pb::LogCode::DownlinkRetriesExhausted.into(),
+                        description:
+                            "Device queue-item discarded because all retries have been exhausted"
+                                .to_string(),
+                        context: [
+                            ("queue_item_id".to_string(), qi.id.to_string()),
+                            ("retry_count".to_string(), qi.retry_count.to_string()),
+                        ]
+                        .iter()
+                        .cloned()
+                        .collect(),
+                    };
+
+                    integration::log_event(self.application.id.into(), &self.device.variables, &pl)
+                        .await;
+
+                    retry::enqueue_failure(
+                        self.device.dev_eui,
+                        &retry::DownlinkFailure {
+                            queue_item_id: *qi.id,
+                            f_port: qi.f_port as u8,
+                            data: qi.data.clone(),
+                            retry_count: qi.retry_count.max(0) as u16,
+                        },
+                    )
+                    .await
+                    .context("Enqueue downlink failure")?;
+                }
+
                 continue;
             }
 
@@ -7,6 +7,7 @@ pub mod error;
 mod helpers;
 pub mod join;
 pub mod multicast;
+pub mod retry;
 pub mod roaming;
 pub mod scheduler;
 pub mod tx_ack;
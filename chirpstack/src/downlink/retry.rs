@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::storage::{get_async_db_conn, uplink_task};
+use lrwn::EUI64;
+
+// The backoff doubles on every retry, up to 2^MAX_BACKOFF_EXP times the configured backoff.
+const MAX_BACKOFF_EXP: u32 = 10;
+
+/// Confirmed downlink that was not acknowledged by the device, after all the retries configured
+/// in the device-profile have been exhausted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownlinkFailure {
+    pub queue_item_id: Uuid,
+    pub f_port: u8,
+    pub data: Vec<u8>,
+    pub retry_count: u16,
+}
+
+/// Stores the downlink failure of the device as task, which is handled by the uplink workers
+/// (e.g. notifying the automation engine and users). As the task is persisted, the failure is
+/// not lost in case handling it fails or ChirpStack is restarted.
+pub async fn enqueue_failure(dev_eui: EUI64, f: &DownlinkFailure) -> Result<()> {
+    let mut c = get_async_db_conn().await?;
+    uplink_task::enqueue(
+        &mut c,
+        dev_eui,
+        &[uplink_task::KIND_DOWNLINK_FAILURE],
+        &serde_json::to_value(f)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Returns the delay before the next retry, given the backoff configured in the device-profile
+/// (in seconds) and the number of retries that have already been performed.
+pub fn get_backoff(backoff: i32, retry_count: i16) -> Duration {
+    let exp = (retry_count.max(0) as u32).min(MAX_BACKOFF_EXP);
+    Duration::from_secs(backoff.max(0) as u64 * (1 << exp))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage;
+    use crate::test;
+
+    #[test]
+    fn test_get_backoff() {
+        assert_eq!(Duration::from_secs(0), get_backoff(0, 3));
+        assert_eq!(Duration::from_secs(10), get_backoff(10, 0));
+        assert_eq!(Duration::from_secs(20), get_backoff(10, 1));
+        assert_eq!(Duration::from_secs(80), get_backoff(10, 3));
+        assert_eq!(Duration::from_secs(10240), get_backoff(10, 15));
+        assert_eq!(Duration::from_secs(0), get_backoff(-10, 1));
+    }
+
+    #[tokio::test]
+    async fn test_enqueue_failure() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+
+        let f = DownlinkFailure {
+            queue_item_id: Uuid::new_v4(),
+            f_port: 10,
+            data: vec![1, 2, 3],
+            retry_count: 3,
+        };
+        enqueue_failure(d.dev_eui, &f).await.unwrap();
+
+        let tasks = uplink_task::claim(10, chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+        assert_eq!(1, tasks.len());
+        assert_eq!(d.dev_eui, tasks[0].dev_eui);
+        assert_eq!(uplink_task::KIND_DOWNLINK_FAILURE, tasks[0].kind);
+        assert_eq!(
+            f,
+            serde_json::from_str::<DownlinkFailure>(&tasks[0].object).unwrap()
+        );
+    }
+}
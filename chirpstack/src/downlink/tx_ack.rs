@@ -11,7 +11,7 @@ use crate::storage::{
     device::{self, DeviceClass},
     device_profile, device_queue, downlink_frame,
     helpers::get_all_device_data,
-    multicast, tenant,
+    multicast, tenant, usage,
 };
 use crate::{integration, stream};
 use chirpstack_api::{common, gw, integration as integration_pb, internal, stream as stream_pb};
@@ -110,6 +110,7 @@ impl TxAck {
 
                     ctx.increment_a_f_cnt_down()?;
                     ctx.send_tx_ack_event().await?;
+                    ctx.meter_downlink().await?;
                 }
 
                 if ctx.is_mac_only_downlink() {
@@ -165,6 +166,7 @@ impl TxAck {
                 // Log tx ack event.
                 self.get_device_data_relayed().await?;
                 self.send_tx_ack_event_relayed().await?;
+                self.meter_downlink_relayed().await?;
             } else if self.is_mac_only_downlink_relayed() {
                 self.increment_n_f_cnt_down_relayed()?;
                 self.save_device_session_relayed().await?;
@@ -488,6 +490,18 @@ impl TxAck {
         Ok(())
     }
 
+    async fn meter_downlink(&self) -> Result<()> {
+        trace!("Metering downlink");
+        let tenant = self.tenant.as_ref().unwrap();
+        usage::record(&tenant.id, usage::DOWNLINK_COUNT, 1.0).await
+    }
+
+    async fn meter_downlink_relayed(&self) -> Result<()> {
+        trace!("Metering relayed downlink");
+        let tenant = self.tenant_relayed.as_ref().unwrap();
+        usage::record(&tenant.id, usage::DOWNLINK_COUNT, 1.0).await
+    }
+
     async fn send_tx_ack_event_relayed(&self) -> Result<()> {
         trace!("Sending relayed tx ack event");
 
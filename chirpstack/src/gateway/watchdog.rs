@@ -0,0 +1,141 @@
+use anyhow::Result;
+use chrono::{Local, Utc};
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
+
+use crate::config;
+use crate::integration;
+use crate::storage::{gateway, notification, tenant, usage};
+use chirpstack_api::integration as integration_pb;
+use lrwn::EUI64;
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.gateway.offline_after_missed_stats == 0 {
+        info!("Gateway offline detection is disabled");
+        return;
+    }
+
+    info!("Setting up gateway watchdog loop");
+    tokio::spawn(async move {
+        watchdog_loop().await;
+    });
+}
+
+pub async fn watchdog_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting gateway watchdog loop run");
+
+        if let Err(err) = mark_offline_gateways(conf.gateway.offline_after_missed_stats).await {
+            error!(error = %err, "Marking offline gateways failed");
+        } else {
+            trace!("Gateway watchdog loop run completed successfully");
+        }
+
+        sleep(conf.gateway.offline_check_interval).await;
+    }
+}
+
+pub async fn mark_offline_gateways(missed_intervals: u32) -> Result<()> {
+    let gateway_ids = gateway::get_missed_stats_gateway_ids(missed_intervals).await?;
+    trace!(
+        gateway_count = gateway_ids.len(),
+        "Got this number of gateways that missed their stats"
+    );
+
+    for gateway_id in gateway_ids {
+        let gw = gateway::partial_update(
+            gateway_id,
+            &gateway::GatewayChangeset {
+                is_offline: Some(true),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        warn!(gateway_id = %gateway_id, last_seen_at = ?gw.last_seen_at, "Gateway missed its stats, marking offline");
+        state_changed(&gw, false).await;
+    }
+
+    Ok(())
+}
+
+// Must be called when the gateway sent its stats. In case the gateway was marked offline, the
+// gateway is marked online again.
+pub async fn handle_stats(gateway_id: &EUI64) -> Result<()> {
+    if !gateway::set_online(gateway_id).await? {
+        return Ok(());
+    }
+
+    let gw = gateway::get(gateway_id).await?;
+    info!(gateway_id = %gateway_id, "Gateway is back online");
+    state_changed(&gw, true).await;
+
+    Ok(())
+}
+
+async fn state_changed(gw: &gateway::Gateway, online: bool) {
+    if let Err(e) = notify_tenant_users(gw, online).await {
+        error!(gateway_id = %gw.gateway_id, error = %e, "Notifying tenant users failed");
+    }
+
+    integration::gateway_state_event(
+        gw.tenant_id.into(),
+        &integration_pb::GatewayStateEvent {
+            time: Some(Utc::now().into()),
+            tenant_id: gw.tenant_id.to_string(),
+            gateway_id: gw.gateway_id.to_string(),
+            gateway_name: gw.name.clone(),
+            online,
+            last_seen_at: gw.last_seen_at.map(|v| v.into()),
+            stats_interval_secs: gw.stats_interval_secs as u32,
+            tags: gw.tags.into_hashmap(),
+        },
+    )
+    .await;
+}
+
+async fn notify_tenant_users(gw: &gateway::Gateway, online: bool) -> Result<()> {
+    let user_ids = tenant::get_user_ids(&gw.tenant_id.into()).await?;
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let message = if online {
+        format!(
+            "{} isimli ağ geçidi tekrar çevrimiçi. Sensör verileri yeniden alınıyor.",
+            gw.name
+        )
+    } else {
+        format!(
+            "{} isimli ağ geçidi {} tarihinden beri veri göndermiyor. Bu ağ geçidine bağlı sensörlerden veri alınamıyor.",
+            gw.name,
+            gw.last_seen_at
+                .map(|v| v.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default()
+        )
+    };
+
+    notification::create_notification(notification::Notification {
+        id: 0,
+        sender_id: 0,
+        receiver_id: user_ids.into_iter().map(Some).collect(),
+        message,
+        category_id: notification::CATEGORY_GATEWAY,
+        is_read: Some(false),
+        send_time: Some(Local::now().naive_local()),
+        read_time: None,
+        sender_ip: Some("System".to_string()),
+        reader_ip: Some("".to_string()),
+        is_deleted: Some(false),
+        deleted_time: None,
+        dev_eui: None,
+        device_name: Some(gw.name.clone()),
+    })
+    .await?;
+    usage::record(&gw.tenant_id, usage::NOTIFICATION_COUNT, 1.0).await?;
+
+    Ok(())
+}
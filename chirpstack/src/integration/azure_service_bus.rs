@@ -222,6 +222,14 @@ impl IntegrationTrait for Integration {
         self.publish("integration", &di.application_id, &di.dev_eui, &pl)
             .await
     }
+
+    async fn gateway_state_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        _pl: &integration::GatewayStateEvent,
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
 type HmacSha256 = Hmac<Sha256>;
@@ -182,6 +182,19 @@ impl IntegrationTrait for Integration {
 
         self.post_event("integration", b).await
     }
+
+    async fn gateway_state_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        pl: &integration::GatewayStateEvent,
+    ) -> Result<()> {
+        let b = match self.json {
+            true => serde_json::to_vec(&pl)?,
+            false => pl.encode_to_vec(),
+        };
+
+        self.post_event("gatewayState", b).await
+    }
 }
 
 #[cfg(test)]
@@ -174,6 +174,14 @@ impl IntegrationTrait for Integration {
     ) -> Result<()> {
         Ok(())
     }
+
+    async fn gateway_state_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        _pl: &integration::GatewayStateEvent,
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
 fn kind_to_string(k: &pbjson_types::value::Kind) -> String {
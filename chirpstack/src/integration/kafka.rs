@@ -219,6 +219,14 @@ impl<'a> IntegrationTrait for Integration<'a> {
         };
         self.publish_event("integration", key, &b).await
     }
+
+    async fn gateway_state_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        _pl: &integration::GatewayStateEvent,
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[cfg(all(test, feature = "test-integration-kafka"))]
@@ -18,6 +18,8 @@ lazy_static! {
     static ref LOCATION_EVENTS: RwLock<Vec<integration::LocationEvent>> = RwLock::new(Vec::new());
     static ref INTEGRATION_EVENTS: RwLock<Vec<integration::IntegrationEvent>> =
         RwLock::new(Vec::new());
+    static ref GATEWAY_STATE_EVENTS: RwLock<Vec<integration::GatewayStateEvent>> =
+        RwLock::new(Vec::new());
 }
 
 pub async fn reset() {
@@ -29,6 +31,7 @@ pub async fn reset() {
     STATUS_EVENTS.write().await.drain(..);
     LOCATION_EVENTS.write().await.drain(..);
     INTEGRATION_EVENTS.write().await.drain(..);
+    GATEWAY_STATE_EVENTS.write().await.drain(..);
 }
 
 pub struct Integration {}
@@ -106,6 +109,15 @@ impl IntegrationTrait for Integration {
         INTEGRATION_EVENTS.write().await.push(pl.clone());
         Ok(())
     }
+
+    async fn gateway_state_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        pl: &integration::GatewayStateEvent,
+    ) -> Result<()> {
+        GATEWAY_STATE_EVENTS.write().await.push(pl.clone());
+        Ok(())
+    }
 }
 
 pub async fn get_join_event() -> Option<integration::JoinEvent> {
@@ -191,3 +203,7 @@ pub async fn get_location_events() -> Vec<integration::LocationEvent> {
 pub async fn get_integration_events() -> Vec<integration::IntegrationEvent> {
     INTEGRATION_EVENTS.write().await.drain(..).collect()
 }
+
+pub async fn get_gateway_state_events() -> Vec<integration::GatewayStateEvent> {
+    GATEWAY_STATE_EVENTS.write().await.drain(..).collect()
+}
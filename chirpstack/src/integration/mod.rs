@@ -134,6 +134,12 @@ pub trait Integration {
         vars: &HashMap<String, String>,
         pl: &integration::IntegrationEvent,
     ) -> Result<()>;
+
+    async fn gateway_state_event(
+        &self,
+        vars: &HashMap<String, String>,
+        pl: &integration::GatewayStateEvent,
+    ) -> Result<()>;
 }
 
 // Returns a Vec of integrations for the given Application ID.
@@ -526,6 +532,59 @@ async fn _integration_event(
     Ok(())
 }
 
+pub async fn gateway_state_event(tenant_id: Uuid, pl: &integration::GatewayStateEvent) {
+    tokio::spawn({
+        let pl = pl.clone();
+
+        async move {
+            if let Err(err) = _gateway_state_event(tenant_id, &pl).await {
+                warn!(tenant_id = %tenant_id, error = %err.full(), "Gateway state event error");
+            }
+        }
+    });
+}
+
+async fn _gateway_state_event(tenant_id: Uuid, pl: &integration::GatewayStateEvent) -> Result<()> {
+    // Gateways are not bound to an application, therefore the event is sent to the
+    // integrations of every application within the tenant.
+    let apps = application::list(
+        i64::MAX,
+        0,
+        &application::Filters {
+            tenant_id: Some(tenant_id),
+            search: None,
+        },
+    )
+    .await
+    .context("List applications for tenant")?;
+
+    let mut app_ints = Vec::new();
+    for app in &apps {
+        app_ints.extend(
+            for_application_id(app.id.into())
+                .await
+                .context("Get integrations for application")?,
+        );
+    }
+
+    let vars = HashMap::new();
+    let global_ints = GLOBAL_INTEGRATIONS.read().await;
+    let mut futures = Vec::new();
+
+    for (i, _) in app_ints.iter().enumerate() {
+        futures.push(app_ints[i].gateway_state_event(&vars, pl));
+    }
+    for (i, _) in global_ints.iter().enumerate() {
+        futures.push(global_ints[i].gateway_state_event(&vars, pl));
+    }
+
+    for e in join_all(futures).await {
+        e?;
+    }
+
+    Ok(())
+}
+
 async fn handle_down_command(application_id: String, pl: integration::DownlinkCommand) {
     let err = async {
         info!(dev_eui = %pl.dev_eui, "Handling downlink command for device");
@@ -37,6 +37,13 @@ struct EventTopicContext {
     pub event: String,
 }
 
+#[derive(Serialize)]
+struct GatewayEventTopicContext {
+    pub tenant_id: String,
+    pub gateway_id: String,
+    pub event: String,
+}
+
 #[derive(Serialize)]
 struct CommandTopicContext {
     pub application_id: String,
@@ -52,6 +59,7 @@ impl<'a> Integration<'a> {
         let mut templates = Handlebars::new();
         templates.register_escape_fn(handlebars::no_escape);
         templates.register_template_string("event_topic", &conf.event_topic)?;
+        templates.register_template_string("gateway_event_topic", &conf.gateway_event_topic)?;
         templates.register_template_string("command_topic", &conf.command_topic)?;
 
         let command_topic = templates.render(
@@ -234,6 +242,22 @@ impl<'a> Integration<'a> {
         )?)
     }
 
+    fn get_gateway_event_topic(
+        &self,
+        tenant_id: &str,
+        gateway_id: &str,
+        event: &str,
+    ) -> Result<String> {
+        Ok(self.templates.render(
+            "gateway_event_topic",
+            &GatewayEventTopicContext {
+                tenant_id: tenant_id.to_string(),
+                gateway_id: gateway_id.to_string(),
+                event: event.to_string(),
+            },
+        )?)
+    }
+
     async fn publish_event(&self, topic: &str, b: Vec<u8>) -> Result<()> {
         info!(topic = %topic, "Publishing event");
         self.client.publish(topic, self.qos, false, b).await?;
@@ -396,6 +420,20 @@ impl IntegrationTrait for Integration<'_> {
 
         self.publish_event(&topic, b).await
     }
+
+    async fn gateway_state_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        pl: &integration::GatewayStateEvent,
+    ) -> Result<()> {
+        let topic = self.get_gateway_event_topic(&pl.tenant_id, &pl.gateway_id, "state")?;
+        let b = match self.json {
+            true => serde_json::to_vec(&pl)?,
+            false => pl.encode_to_vec(),
+        };
+
+        self.publish_event(&topic, b).await
+    }
 }
 
 async fn message_callback(
@@ -123,6 +123,14 @@ impl IntegrationTrait for Integration {
         let b = pl.encode_to_vec();
         stream::event::log_event_for_device("integration", &dev_info.dev_eui, &b).await
     }
+
+    async fn gateway_state_event(
+        &self,
+        _vars: &HashMap<String, String>,
+        _pl: &integration::GatewayStateEvent,
+    ) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -20,7 +20,9 @@ use tracing_subscriber::{filter, prelude::*};
 use lrwn::EUI64;
 
 mod adr;
+mod alerting;
 mod api;
+mod applayer;
 mod backend;
 mod certificate;
 mod cmd;
@@ -36,6 +38,7 @@ mod maccommand;
 mod monitoring;
 mod region;
 mod sensitivity;
+mod sensor_data;
 mod storage;
 mod stream;
 #[cfg(test)]
@@ -88,6 +91,16 @@ enum Commands {
 
     /// Migrate device-sessions from Redis to PostgreSQL.
     MigrateDeviceSessionsToPostgres {},
+
+    /// Migrate the device_data sensor data to the normalised measurement table.
+    MigrateMeasurements {},
+
+    /// Migrate the device_data_2025 sensor data to the partitioned device_data table.
+    MigrateSensorData {
+        /// Drop the device_data_2025 table after migrating.
+        #[arg(long)]
+        drop: bool,
+    },
 }
 
 #[tokio::main]
@@ -132,6 +145,8 @@ async fn main() -> Result<()> {
         }
         Some(Commands::CreateApiKey { name }) => cmd::create_api_key::run(name).await?,
         Some(Commands::MigrateDeviceSessionsToPostgres {}) => cmd::migrate_ds_to_pg::run().await?,
+        Some(Commands::MigrateMeasurements {}) => cmd::migrate_measurements::run().await?,
+        Some(Commands::MigrateSensorData { drop }) => cmd::migrate_sensor_data::run(*drop).await?,
         None => cmd::root::run().await?,
     }
 
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use tokio::time::sleep;
+use tracing::{error, info, trace};
+
+use crate::config;
+use crate::storage::sensor_data;
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.sensor_data.maintenance_interval.is_zero() {
+        info!("Sensor-data maintenance is disabled");
+        return;
+    }
+
+    info!("Setting up sensor-data maintenance loop");
+    tokio::spawn(async move {
+        maintenance_loop().await;
+    });
+}
+
+pub async fn maintenance_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting sensor-data maintenance loop run");
+
+        if let Err(err) = run().await {
+            error!(error = %err, "Sensor-data maintenance loop run failed");
+        } else {
+            trace!("Sensor-data maintenance loop run completed successfully");
+        }
+
+        sleep(conf.sensor_data.maintenance_interval).await;
+    }
+}
+
+async fn run() -> Result<()> {
+    let conf = config::get();
+    let now = Utc::now().naive_utc();
+
+    sensor_data::create_partitions(conf.sensor_data.premake_partitions).await?;
+
+    // Re-compute the rollups since the previous run, including a margin for the readings which
+    // were stored while the previous run was in progress.
+    let interval = Duration::from_std(conf.sensor_data.maintenance_interval)?;
+    sensor_data::update_rollups(now - interval * 2, now).await?;
+
+    sensor_data::apply_retention().await?;
+
+    Ok(())
+}
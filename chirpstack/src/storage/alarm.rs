@@ -1,25 +1,35 @@
+use super::alarm_rule;
 use super::application::Application;
+use super::defrost;
 use super::device::Device;
+use super::escalation;
+use super::fields::sql_types::{Timestamptz, Uuid as DieselUuid, UuidArray};
+use super::maintenance;
 use super::notification;
-use super::{error::Error, get_async_db_conn};
-use crate::storage::schema_postgres::alarm;
-use crate::storage::schema_postgres::alarm_audit_log;
-use crate::storage::schema_postgres::alarm_automation_rules;
-use crate::storage::schema_postgres::alarm_date_time;
+use super::virtual_device;
+use super::webhook;
+use super::{error::Error, fields, get_async_db_conn, AsyncDbConnection};
+use crate::config;
+use crate::monitoring::prometheus;
+use crate::storage::schema::alarm;
+use crate::storage::schema::alarm_audit_log;
+use crate::storage::schema::alarm_automation_rules;
+use crate::storage::schema::alarm_date_time;
+use crate::storage::schema::alarm_no_data_trigger;
+use crate::storage::schema::alarm_trigger;
 use anyhow::{Context, Result};
-use bigdecimal::ToPrimitive;
 use chirpstack_api::api;
-use chrono::NaiveDateTime;
 use chrono::NaiveTime;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use chrono::{Datelike, Local, Timelike};
 use diesel::deserialize::QueryableByName;
-use diesel::pg::Pg;
 use diesel::prelude::*;
 use diesel::sql_query;
-use diesel::sql_types::Uuid as DieselUuid;
 use diesel::sql_types::*;
-use diesel_async::AsyncPgConnection;
 use diesel_async::RunQueryDsl;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -27,6 +37,45 @@ use std::fmt::Write;
 use tracing::{info, warn};
 use uuid::Uuid;
 
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct EvaluationLabels {
+    device_type: String,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct RaisedLabels {
+    kind: String,
+}
+
+lazy_static! {
+    static ref EVALUATION_COUNTER: Family<EvaluationLabels, Counter> = {
+        let counter = Family::<EvaluationLabels, Counter>::default();
+        prometheus::register(
+            "alarm_evaluation_count",
+            "Number of alarms evaluated against uplinks by device type",
+            counter.clone(),
+        );
+        counter
+    };
+    static ref RAISED_COUNTER: Family<RaisedLabels, Counter> = {
+        let counter = Family::<RaisedLabels, Counter>::default();
+        prometheus::register(
+            "alarm_raised_count",
+            "Number of alarms raised by kind",
+            counter.clone(),
+        );
+        counter
+    };
+}
+
+fn inc_raised(kind: &str) {
+    RAISED_COUNTER
+        .get_or_create(&RaisedLabels {
+            kind: kind.to_string(),
+        })
+        .inc();
+}
+
 #[derive(
     Queryable,
     QueryableByName,
@@ -61,7 +110,12 @@ pub struct Alarm {
     pub notification_sound: Option<String>,
     pub distance: Option<bool>,
     pub defrost_time: Option<i32>,
-    pub user_id: Vec<Option<Uuid>>,
+    pub user_id: fields::UuidArray,
+    pub no_data: Option<bool>,
+    pub no_data_time: Option<i32>,
+    pub rule_type: i32,
+    pub rule_duration: Option<i32>,
+    pub rule_limit: Option<f64>,
 }
 
 #[derive(Insertable)]
@@ -87,7 +141,12 @@ pub struct NewAlarm {
     pub notification_sound: Option<String>,
     pub distance: Option<bool>,
     pub defrost_time: Option<i32>,
-    pub user_id: Vec<Option<Uuid>>,
+    pub user_id: fields::UuidArray,
+    pub no_data: Option<bool>,
+    pub no_data_time: Option<i32>,
+    pub rule_type: i32,
+    pub rule_duration: Option<i32>,
+    pub rule_limit: Option<f64>,
 }
 impl Default for Alarm {
     fn default() -> Self {
@@ -111,9 +170,14 @@ impl Default for Alarm {
             is_active: Some(true),
             pressure: None,
             notification_sound: Some("default".to_string()),
-            user_id: vec![None],
+            user_id: vec![None].into(),
             distance: None,
             defrost_time: Some(0),
+            no_data: Some(false),
+            no_data_time: Some(0),
+            rule_type: 0,
+            rule_duration: None,
+            rule_limit: None,
         }
     }
 }
@@ -139,14 +203,19 @@ impl Default for NewAlarm {
             is_active: Some(true),
             pressure: None,
             notification_sound: Some("default".to_string()),
-            user_id: vec![None],
+            user_id: vec![None].into(),
             distance: None,
             defrost_time: Some(0),
+            no_data: Some(false),
+            no_data_time: Some(0),
+            rule_type: 0,
+            rule_duration: None,
+            rule_limit: None,
         }
     }
 }
 #[derive(Debug, QueryableByName, Serialize, Deserialize)]
-#[diesel(check_for_backend(Pg))]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 pub struct OrganizationAlarmRaw {
     #[diesel(sql_type = Integer)]
     pub id: i32,
@@ -184,8 +253,8 @@ pub struct OrganizationAlarmRaw {
     #[diesel(sql_type = Nullable<Bool>)]
     pub w_leak: Option<bool>,
 
-    #[diesel(sql_type = Array<Nullable<DieselUuid>>)]
-    pub user_id: Vec<Option<Uuid>>,
+    #[diesel(sql_type = UuidArray)]
+    pub user_id: fields::UuidArray,
 
     #[diesel(sql_type = Nullable<Bool>)]
     pub is_time_limit_active: Option<bool>,
@@ -225,6 +294,21 @@ pub struct OrganizationAlarmRaw {
 
     #[diesel(sql_type = Nullable<Integer>)]
     pub defrost_time: Option<i32>,
+
+    #[diesel(sql_type = Nullable<Bool>)]
+    pub no_data: Option<bool>,
+
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub no_data_time: Option<i32>,
+
+    #[diesel(sql_type = Integer)]
+    pub rule_type: i32,
+
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub rule_duration: Option<i32>,
+
+    #[diesel(sql_type = Nullable<Double>)]
+    pub rule_limit: Option<f64>,
     // pub alarm_date_time: Option<AlarmDateTime>,
 }
 #[derive(Debug, Serialize, Deserialize)]
@@ -255,6 +339,11 @@ pub struct OrganizationAlarm {
     pub distance: Option<bool>,
     pub time: Option<i32>,
     pub defrost_time: Option<i32>,
+    pub no_data: Option<bool>,
+    pub no_data_time: Option<i32>,
+    pub rule_type: i32,
+    pub rule_duration: Option<i32>,
+    pub rule_limit: Option<f64>,
     pub alarm_date_time: Option<Vec<AlarmDateTime>>,
 }
 
@@ -269,7 +358,7 @@ pub struct AlarmDateTime {
 }
 
 #[derive(Queryable, QueryableByName, Debug, Clone, Serialize, Deserialize)]
-#[diesel(check_for_backend(Pg))]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 
 pub struct AlarmWithDates {
     #[diesel(sql_type = Int8)]
@@ -308,8 +397,8 @@ pub struct AlarmWithDates {
     #[diesel(sql_type = Bool)]
     pub w_leak: bool,
 
-    #[diesel(sql_type = Array<Nullable<DieselUuid>>)]
-    pub user_id: Vec<Option<Uuid>>,
+    #[diesel(sql_type = UuidArray)]
+    pub user_id: fields::UuidArray,
 
     #[diesel(sql_type = Nullable<Text>)]
     pub ip_address: Option<String>,
@@ -370,23 +459,141 @@ pub struct AlarmWithDates {
 
     #[diesel(sql_type = BigInt)]
     pub defrost_time: i64,
+
+    #[diesel(sql_type = Integer)]
+    pub rule_type: i32,
+
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub rule_duration: Option<i32>,
+
+    #[diesel(sql_type = Nullable<Double>)]
+    pub rule_limit: Option<f64>,
+
+    // The comma separated alarm types for which the alarm is triggered (see alarm_trigger).
+    #[diesel(sql_type = Nullable<Text>)]
+    pub triggered_types: Option<String>,
+
+    // The comma separated alarm types for which the alarm is raised for the device (see
+    // webhook::alarm_raised).
+    #[diesel(sql_type = Nullable<Text>)]
+    pub raised_types: Option<String>,
 }
 
 impl AlarmWithDates {
+    // Returns true when the alarm is triggered for the given alarm type.
+    pub fn is_triggered(&self, alarm_type: &str) -> bool {
+        self.triggered_types
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .any(|v| v == alarm_type)
+    }
+
+    // Returns true when the alarm is raised for the given alarm type. Only then the alarm needs
+    // to be cleared, such that clearing does not hit the database on every uplink.
+    pub fn is_raised(&self, alarm_type: &str) -> bool {
+        self.raised_types
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .any(|v| v == alarm_type)
+    }
+
     pub fn is_within_schedule(&self, current_time: NaiveTime) -> bool {
-        if !self.is_time_limit_active {
-            return true;
-        }
+        is_within_time_window(
+            self.is_time_limit_active,
+            self.start_time,
+            self.end_time,
+            current_time,
+        )
+    }
+}
 
-        let time = current_time.hour() as f32 + current_time.minute() as f32 / 60.0 + 3.0;
-        let adjusted_time = if time >= 24.0 { time - 24.0 } else { time };
+#[derive(QueryableByName, Debug, Clone)]
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
+pub struct NoDataAlarm {
+    #[diesel(sql_type = Integer)]
+    pub id: i32,
 
-        if self.end_time > self.start_time {
-            self.start_time < adjusted_time && adjusted_time < self.end_time
-        } else {
-            (self.start_time < adjusted_time && adjusted_time < 24.0)
-                || (0.0 < adjusted_time && adjusted_time < self.end_time)
-        }
+    #[diesel(sql_type = Text)]
+    pub dev_eui: String,
+
+    #[diesel(sql_type = Text)]
+    pub device_name: String,
+
+    #[diesel(sql_type = Nullable<Text>)]
+    pub zone_name: Option<String>,
+
+    #[diesel(sql_type = UuidArray)]
+    pub user_id: fields::UuidArray,
+
+    #[diesel(sql_type = Bool)]
+    pub is_time_limit_active: bool,
+
+    #[diesel(sql_type = Double)]
+    pub start_time: f64,
+
+    #[diesel(sql_type = Double)]
+    pub end_time: f64,
+
+    #[diesel(sql_type = Integer)]
+    pub silence_minutes: i32,
+
+    #[diesel(sql_type = Timestamptz)]
+    pub last_seen_at: DateTime<Utc>,
+}
+
+impl NoDataAlarm {
+    pub fn is_within_schedule(&self, current_time: NaiveTime) -> bool {
+        is_within_time_window(
+            self.is_time_limit_active,
+            self.start_time as f32,
+            self.end_time as f32,
+            current_time,
+        )
+    }
+}
+
+// Returns the current time in the timezone of the alarm schedules (alarm.timezone).
+pub fn local_now() -> NaiveDateTime {
+    to_local(Utc::now())
+}
+
+// Returns the given timestamp in the timezone of the alarm schedules.
+pub fn to_local(ts: DateTime<Utc>) -> NaiveDateTime {
+    ts.with_timezone(&config::get().alarm.timezone)
+        .naive_local()
+}
+
+// Returns the given timestamp in the timezone of the alarm schedules as UTC timestamp.
+pub fn from_local(ts: NaiveDateTime) -> DateTime<Utc> {
+    match ts
+        .and_local_timezone(config::get().alarm.timezone)
+        .earliest()
+    {
+        Some(v) => v.with_timezone(&Utc),
+        None => Utc.from_utc_datetime(&ts),
+    }
+}
+
+// Returns true when the given time (in the timezone of the alarm schedules) is within the
+// start and end time (hours of the day) of the schedule.
+pub fn is_within_time_window(
+    is_time_limit_active: bool,
+    start_time: f32,
+    end_time: f32,
+    current_time: NaiveTime,
+) -> bool {
+    if !is_time_limit_active {
+        return true;
+    }
+
+    let time = current_time.hour() as f32 + current_time.minute() as f32 / 60.0;
+
+    if end_time > start_time {
+        start_time < time && time < end_time
+    } else {
+        (start_time < time && time < 24.0) || (0.0 < time && time < end_time)
     }
 }
 #[derive(AsChangeset, Debug)]
@@ -399,7 +606,7 @@ pub struct UpdateAlarmAutomation {
     pub is_active: Option<bool>,
     pub receiver_device_type: Option<i32>,
     pub receiver_device_name: Option<String>,
-    pub user_id: Option<Uuid>,
+    pub user_id: Option<fields::Uuid>,
 }
 
 #[derive(Queryable, QueryableByName, Insertable, Debug, Serialize, Deserialize, Clone)]
@@ -410,9 +617,9 @@ pub struct AlarmAuditLog {
     pub dev_eui: Option<String>,
     pub change_type: Option<String>,
     pub changed_at: Option<NaiveDateTime>,
-    pub changed_by: Option<Uuid>,
-    pub old_values: Option<serde_json::Value>,
-    pub new_values: Option<serde_json::Value>,
+    pub changed_by: Option<fields::Uuid>,
+    pub old_values: Option<fields::JsonValue>,
+    pub new_values: Option<fields::JsonValue>,
 }
 
 impl Default for AlarmDateTime {
@@ -453,11 +660,11 @@ pub struct DoorTimeAlarm {
     #[diesel(sql_type = Nullable<Int8>)]
     pub time: Option<i64>,
 
-    #[diesel(sql_type = Nullable<Array<Nullable<DieselUuid>>>)]
-    pub user_id: Option<Vec<Option<Uuid>>>,
+    #[diesel(sql_type = Nullable<UuidArray>)]
+    pub user_id: Option<fields::UuidArray>,
 
     #[diesel(sql_type = Nullable<DieselUuid>)]
-    pub tenant_id: Option<Uuid>,
+    pub tenant_id: Option<fields::Uuid>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -476,9 +683,14 @@ pub struct UpdateAlarm {
     pub notification: Option<bool>,
     pub is_time_limit_active: Option<bool>,
     pub notification_sound: Option<String>,
-    pub user_id: Vec<uuid::Uuid>,
+    pub user_id: fields::UuidArray,
     pub is_active: Option<bool>,
     pub defrost_time: Option<i32>,
+    pub no_data: Option<bool>,
+    pub no_data_time: Option<i32>,
+    pub rule_type: Option<i32>,
+    pub rule_duration: Option<i32>,
+    pub rule_limit: Option<f64>,
 }
 
 #[derive(Queryable, Selectable, Debug)]
@@ -493,7 +705,7 @@ pub struct AlarmAutomation {
     pub is_active: Option<bool>,
     pub receiver_device_type: Option<i32>,
     pub receiver_device_name: Option<String>,
-    pub user_id: Option<Uuid>,
+    pub user_id: Option<fields::Uuid>,
 }
 
 #[derive(Insertable, Debug)]
@@ -505,7 +717,7 @@ pub struct NewAlarmAutomation {
     pub is_active: Option<bool>,
     pub receiver_device_type: Option<i32>,
     pub receiver_device_name: Option<String>,
-    pub user_id: Option<Uuid>,
+    pub user_id: Option<fields::Uuid>,
 }
 
 pub async fn create(
@@ -579,7 +791,20 @@ pub async fn get_alarm_dates(alarm_id: i32) -> Result<Vec<AlarmDateTime>, Error>
 pub async fn get_organization_alarm_list(tenant_id: Uuid) -> Result<Vec<OrganizationAlarm>, Error> {
     let mut conn = get_async_db_conn().await?;
     info!("Alarm get_organization_alarm_list start");
-    let raw_alarms = diesel::sql_query(
+    let (device_join, zone_join, tenant_id_p) = if cfg!(feature = "sqlite") {
+        (
+            "lower(hex(d.dev_eui)) = a.dev_eui",
+            r#"EXISTS (SELECT 1 FROM json_each(z.devices) AS zd WHERE zd.value = '\x' || lower(hex(d.dev_eui)))"#,
+            "?1",
+        )
+    } else {
+        (
+            r#"d.dev_eui::text = '\x' || a.dev_eui"#,
+            "d.dev_eui::text = ANY(z.devices)",
+            "$1",
+        )
+    };
+    let raw_alarms = diesel::sql_query(format!(
         r#"
         SELECT 
             a.id,
@@ -607,14 +832,20 @@ pub async fn get_organization_alarm_list(tenant_id: Uuid) -> Result<Vec<Organiza
             a.notification_sound,
             a.distance,
             0 AS time,
-            a.defrost_time
+            a.defrost_time,
+            a.no_data,
+            a.no_data_time,
+            a.rule_type,
+            a.rule_duration,
+            a.rule_limit
         FROM alarm AS a
-        INNER JOIN device AS d ON d.dev_eui::text = '\x' || a.dev_eui
-        INNER JOIN zone AS z ON d.dev_eui::text = ANY(z.devices)
-        WHERE d.tenant_id = $1
-        "#,
-    )
-    .bind::<DieselUuid, _>(tenant_id)
+        LEFT JOIN device AS d ON {device_join}
+        INNER JOIN zone AS z ON {zone_join}
+            OR (a.dev_eui = '' AND z.zone_id = a.zone_category)
+        WHERE COALESCE(d.tenant_id, z.tanent_id) = {tenant_id_p}
+        "#
+    ))
+    .bind::<DieselUuid, _>(fields::Uuid::from(tenant_id))
     .load::<OrganizationAlarmRaw>(&mut conn)
     .await
     .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
@@ -689,7 +920,7 @@ pub fn map_alarm_with_dates(
         ec: raw.ec,
         door: raw.door,
         w_leak: raw.w_leak,
-        user_id: raw.user_id,
+        user_id: raw.user_id.into_inner(),
         is_time_limit_active: raw.is_time_limit_active,
         alarm_start_time: raw.alarm_start_time,
         alarm_stop_time: raw.alarm_stop_time,
@@ -703,6 +934,11 @@ pub fn map_alarm_with_dates(
         distance: raw.distance,
         time: raw.time,
         defrost_time: raw.defrost_time,
+        no_data: raw.no_data,
+        no_data_time: raw.no_data_time,
+        rule_type: raw.rule_type,
+        rule_duration: raw.rule_duration,
+        rule_limit: raw.rule_limit,
         alarm_date_time: Some(dates),
     }
 }
@@ -746,6 +982,12 @@ pub async fn update_alarm(
         .await
         .map_err(|e| Error::from_diesel(e, updated_alarm.dev_eui.to_string()))?;
 
+    // The updated alarm is evaluated from scratch.
+    diesel::delete(alarm_trigger::table.filter(alarm_trigger::alarm_id.eq(updated_alarm.id)))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, updated_alarm.dev_eui.to_string()))?;
+
     for df in &date_filters {
         let new_df = (
             alarm_date_time::alarm_id.eq(updated_alarm.id),
@@ -796,7 +1038,15 @@ pub async fn delete_alarm(alarm_id: i32, sent_user_id: Uuid) -> Result<(), Error
 pub async fn delete_user_alarm(user_id: Uuid, sent_user_id: Uuid) -> Result<(), Error> {
     let mut conn = get_async_db_conn().await?;
 
-    let query = r#"
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+    UPDATE alarm
+    SET user_id = (SELECT json_group_array(u.value) FROM json_each(alarm.user_id) AS u WHERE u.value IS NOT ?1)
+    WHERE EXISTS (SELECT 1 FROM json_each(alarm.user_id) AS u WHERE u.value = ?1)
+    RETURNING *
+    "#
+    } else {
+        r#"
     WITH updated_rows AS (
         UPDATE alarm
         SET user_id = array_remove(user_id, $1::uuid)
@@ -804,10 +1054,11 @@ pub async fn delete_user_alarm(user_id: Uuid, sent_user_id: Uuid) -> Result<(),
         RETURNING *
     )
     SELECT * FROM updated_rows;
-    "#;
+    "#
+    };
 
     let alarms: Vec<Alarm> = sql_query(query)
-        .bind::<DieselUuid, _>(user_id)
+        .bind::<DieselUuid, _>(fields::Uuid::from(user_id))
         .load(&mut conn)
         .await
         .map_err(|e| Error::from_diesel(e, user_id.to_string()))?;
@@ -920,9 +1171,9 @@ pub async fn log_audit(
             alarm_audit_log::alarm_id.eq(alarm_id as i32),
             alarm_audit_log::dev_eui.eq(dev_eui),
             alarm_audit_log::change_type.eq(change_type),
-            alarm_audit_log::changed_by.eq(Some(user_id as Uuid)),
-            alarm_audit_log::old_values.eq(previous_value),
-            alarm_audit_log::new_values.eq(new_value),
+            alarm_audit_log::changed_by.eq(Some(fields::Uuid::from(user_id))),
+            alarm_audit_log::old_values.eq(previous_value.map(fields::JsonValue::from)),
+            alarm_audit_log::new_values.eq(new_value.map(fields::JsonValue::from)),
         ))
         .execute(&mut conn)
         .await
@@ -940,14 +1191,25 @@ pub async fn create_door_time_alarm(
     let mut conn = get_async_db_conn().await?;
 
     // Insert into door_time_alarm table and get the created alarm with id
-    let insert_query = r#"
+    let insert_query = if cfg!(feature = "sqlite") {
+        r#"
+        INSERT INTO door_time_alarm (
+            dev_eui, time, is_active, sms, notification, email, user_id, submission_time, tenant_id
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, CURRENT_TIMESTAMP, ?8
+        )
+        RETURNING id, dev_eui, time, is_active, sms, notification, email, user_id, submission_time, tenant_id
+    "#
+    } else {
+        r#"
         INSERT INTO door_time_alarm (
             dev_eui, time, is_active, sms, notification, email, user_id, submission_time, tenant_id
         ) VALUES (
             $1, $2, $3, $4, $5, $6, $7, NOW(), $8
         )
         RETURNING id, dev_eui, time, is_active, sms, notification, email, user_id, submission_time, tenant_id
-    "#;
+    "#
+    };
 
     let created_alarm: DoorTimeAlarm = sql_query(insert_query)
         .bind::<Nullable<Text>, _>(&door_time_alarm.dev_eui)
@@ -956,7 +1218,7 @@ pub async fn create_door_time_alarm(
         .bind::<Nullable<Bool>, _>(door_time_alarm.sms)
         .bind::<Nullable<Bool>, _>(door_time_alarm.notification)
         .bind::<Nullable<Bool>, _>(door_time_alarm.email)
-        .bind::<Nullable<Array<Nullable<DieselUuid>>>, _>(&door_time_alarm.user_id)
+        .bind::<Nullable<UuidArray>, _>(&door_time_alarm.user_id)
         .bind::<Nullable<DieselUuid>, _>(door_time_alarm.tenant_id)
         .get_result(&mut conn)
         .await
@@ -973,10 +1235,17 @@ pub async fn create_door_time_alarm(
 
     // Insert time schedule entries
     for alarm_date_time in &time_schedule {
-        let insert_time_query = r#"
+        let insert_time_query = if cfg!(feature = "sqlite") {
+            r#"
+            INSERT INTO door_alarm_date_time (alarm_id, alarm_day, start_time, end_time)
+            VALUES (?1, ?2, ?3, ?4)
+        "#
+        } else {
+            r#"
             INSERT INTO door_alarm_date_time (alarm_id, alarm_day, start_time, end_time)
             VALUES ($1, $2, $3, $4)
-        "#;
+        "#
+        };
         sql_query(insert_time_query)
             .bind::<Int4, _>(created_alarm.id)
             .bind::<Int4, _>(alarm_date_time.alarm_day)
@@ -1006,11 +1275,15 @@ pub async fn create_door_time_alarm(
 pub async fn delete_door_time_alarm(door_alarm_id: i32) -> Result<(), Error> {
     let mut conn = get_async_db_conn().await?;
 
-    let select_sql = r#"
+    let id_p = if cfg!(feature = "sqlite") { "?1" } else { "$1" };
+
+    let select_sql = format!(
+        r#"
         SELECT id, dev_eui, time, is_active, sms, notification, email,
                 submission_time, user_id, tenant_id
-        FROM door_time_alarm WHERE id = $1
-    "#;
+        FROM door_time_alarm WHERE id = {id_p}
+    "#
+    );
 
     let _door_alarm: DoorTimeAlarm = sql_query(select_sql)
         .bind::<Int4, _>(door_alarm_id)
@@ -1018,18 +1291,22 @@ pub async fn delete_door_time_alarm(door_alarm_id: i32) -> Result<(), Error> {
         .await
         .map_err(|e| Error::from_diesel(e, door_alarm_id.to_string()))?;
 
-    let update_sql = r#"
-        UPDATE door_time_alarm SET is_active = FALSE WHERE id = $1
-    "#;
+    let update_sql = format!(
+        r#"
+        UPDATE door_time_alarm SET is_active = FALSE WHERE id = {id_p}
+    "#
+    );
     sql_query(update_sql)
         .bind::<Int4, _>(door_alarm_id)
         .execute(&mut conn)
         .await
         .map_err(|e| Error::from_diesel(e, door_alarm_id.to_string()))?;
 
-    let update_automation_sql = r#"
-        UPDATE automation_rules SET is_active = FALSE WHERE condition = $1
-    "#;
+    let update_automation_sql = format!(
+        r#"
+        UPDATE automation_rules SET is_active = FALSE WHERE condition = {id_p}
+    "#
+    );
     sql_query(update_automation_sql)
         .bind::<Int4, _>(door_alarm_id)
         .execute(&mut conn)
@@ -1045,7 +1322,8 @@ pub async fn list_door_time_alarms(
 ) -> Result<Vec<api::CreateDoorTimeResponse>, Error> {
     let mut conn = get_async_db_conn().await?;
 
-    let query = r#"
+    let query = format!(
+        r#"
     SELECT 
         id, 
         dev_eui, 
@@ -1058,8 +1336,10 @@ pub async fn list_door_time_alarms(
         user_id, 
         tenant_id
     FROM door_time_alarm
-    WHERE dev_eui = $1
-"#;
+    WHERE dev_eui = {}
+"#,
+        if cfg!(feature = "sqlite") { "?1" } else { "$1" }
+    );
 
     let rows: Vec<DoorTimeAlarm> = sql_query(query)
         .bind::<Text, _>(&dev_eui)
@@ -1092,13 +1372,14 @@ pub async fn list_door_time_alarms(
 pub async fn get_alarm_audit_logs(dev_eui: &str) -> Result<Vec<AlarmAuditLog>, Error> {
     let mut conn = get_async_db_conn().await?;
 
-    let logs = diesel::sql_query(
+    let logs = diesel::sql_query(format!(
         r#"
         SELECT * 
         FROM alarm_audit_log
-        WHERE dev_eui = $1
+        WHERE dev_eui = {}
         "#,
-    )
+        if cfg!(feature = "sqlite") { "?1" } else { "$1" }
+    ))
     .bind::<diesel::sql_types::Text, _>(dev_eui)
     .load::<AlarmAuditLog>(&mut conn)
     .await
@@ -1108,7 +1389,7 @@ pub async fn get_alarm_audit_logs(dev_eui: &str) -> Result<Vec<AlarmAuditLog>, E
 }
 
 pub async fn check_alarm(
-    db: &mut AsyncPgConnection,
+    db: &mut AsyncDbConnection,
     app: &Application,
     device: &Device,
     object_json: &Value,
@@ -1121,7 +1402,7 @@ pub async fn check_alarm(
         }
     }
 
-    let current_time = Local::now().naive_local();
+    let current_time = local_now();
     let weekday = current_time.weekday().number_from_monday();
 
     let alarms: Vec<AlarmWithDates> =
@@ -1139,20 +1420,23 @@ pub async fn check_alarm(
             continue;
         }
 
+        EVALUATION_COUNTER
+            .get_or_create(&EvaluationLabels {
+                device_type: device
+                    .device_type
+                    .map(|v| v.to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+            })
+            .inc();
+
         match device.device_type {
             Some(1) => {
                 if alarm.temperature {
                     if let Some(Value::String(temp)) = object_json.get("temperature") {
                         if let Ok(t) = temp.parse::<f32>() {
-                            let calibration = device
-                                .temperature_calibration
-                                .as_ref()
-                                .and_then(|v| v.to_f32())
-                                .unwrap_or(0.0);
-                            let value = t + calibration;
                             check_threshold(
                                 &alarm,
-                                value,
+                                t,
                                 device,
                                 "temperature",
                                 &current_time.to_string(),
@@ -1164,15 +1448,9 @@ pub async fn check_alarm(
                 } else if alarm.humadity {
                     if let Some(Value::String(hum)) = object_json.get("humidity") {
                         if let Ok(h) = hum.parse::<f32>() {
-                            let calibration = device
-                                .humadity_calibration
-                                .as_ref()
-                                .and_then(|v| v.to_f32())
-                                .unwrap_or(0.0);
-                            let value = h + calibration;
                             check_threshold(
                                 &alarm,
-                                value,
+                                h,
                                 device,
                                 "humidity",
                                 &current_time.to_string(),
@@ -1242,6 +1520,14 @@ pub async fn check_alarm(
                                 db,
                             )
                             .await?;
+                        } else if alarm.is_raised("door") {
+                            webhook::alarm_cleared(
+                                alarm.id as i32,
+                                "door",
+                                &device.dev_eui.to_string(),
+                                None,
+                            )
+                            .await;
                         }
                     }
                 }
@@ -1262,6 +1548,14 @@ pub async fn check_alarm(
                                 db,
                             )
                             .await?;
+                        } else if alarm.is_raised("water_leak") {
+                            webhook::alarm_cleared(
+                                alarm.id as i32,
+                                "water_leak",
+                                &device.dev_eui.to_string(),
+                                None,
+                            )
+                            .await;
                         }
                     }
                 }
@@ -1269,15 +1563,9 @@ pub async fn check_alarm(
             Some(12) | Some(35) => {
                 if alarm.temperature {
                     if let Some(Value::Number(temp)) = object_json.get("temperature") {
-                        let calibration = device
-                            .temperature_calibration
-                            .as_ref()
-                            .and_then(|v| v.to_f32())
-                            .unwrap_or(0.0);
-                        let value = temp.as_f64().unwrap_or(0.0) as f32 + calibration;
                         check_threshold(
                             &alarm,
-                            value,
+                            temp.as_f64().unwrap_or(0.0) as f32,
                             device,
                             "temperature",
                             &current_time.to_string(),
@@ -1288,17 +1576,9 @@ pub async fn check_alarm(
                 }
                 if alarm.humadity {
                     if let Some(Value::Number(hum)) = object_json.get("humidity") {
-                        let base_value = hum.as_f64().unwrap_or(0.0) as f32;
-                        let calibration = device
-                            .humadity_calibration
-                            .as_ref()
-                            .and_then(|v| v.to_f32())
-                            .unwrap_or(0.0);
-                        let value = base_value + calibration;
-
                         check_threshold(
                             &alarm,
-                            value,
+                            hum.as_f64().unwrap_or(0.0) as f32,
                             device,
                             "humidity",
                             &current_time.to_string(),
@@ -1404,6 +1684,25 @@ pub async fn check_alarm(
                     }
                 }
             }
+            Some(virtual_device::DEVICE_TYPE) => {
+                // The object of a virtual device holds its computed value, the thresholds apply
+                // to this value regardless of the metric of the alarm.
+                if !alarm.door && !alarm.w_leak {
+                    if let (Some(Value::String(kind)), Some(Value::Number(v))) =
+                        (object_json.get("kind"), object_json.get("value"))
+                    {
+                        check_threshold(
+                            &alarm,
+                            v.as_f64().unwrap_or(0.0) as f32,
+                            device,
+                            kind,
+                            &current_time.to_string(),
+                            db,
+                        )
+                        .await?;
+                    }
+                }
+            }
             _ => {}
         }
     }
@@ -1412,22 +1711,31 @@ pub async fn check_alarm(
 }
 
 pub async fn get_active_alarms_with_schedule(
-    conn: &mut AsyncPgConnection,
+    conn: &mut AsyncDbConnection,
     dev_eui: &str,
     weekday: i32,
 ) -> anyhow::Result<Vec<AlarmWithDates>> {
-    let query = r#"
+    let (string_agg, dev_eui_p, weekday_p) = if cfg!(feature = "sqlite") {
+        ("group_concat", "?1", "?2")
+    } else {
+        ("string_agg", "$1", "$2")
+    };
+    let query = format!(
+        r#"
         SELECT 
             alrm.*, 
             alrmDate.alarm_day AS alarm_day,
             alrmDate.start_time AS alarm_start_time,
-            alrmDate.end_time AS alarm_end_time
+            alrmDate.end_time AS alarm_end_time,
+            (SELECT {string_agg}(t.alarm_type, ',') FROM alarm_trigger AS t WHERE t.alarm_id = alrm.id) AS triggered_types,
+            (SELECT {string_agg}(s.alarm_type, ',') FROM alarm_state AS s WHERE s.alarm_id = alrm.id AND s.dev_eui = {dev_eui_p}) AS raised_types
         FROM alarm AS alrm 
         INNER JOIN alarm_date_time alrmDate ON alrm.id = alrmDate.alarm_id 
-        WHERE dev_eui = $1 
-          AND (alrmDate.alarm_day = 0 OR alrmDate.alarm_day = $2) 
+        WHERE dev_eui = {dev_eui_p} 
+          AND (alrmDate.alarm_day = 0 OR alrmDate.alarm_day = {weekday_p}) 
           AND is_active = true
-    "#;
+    "#
+    );
 
     let results = diesel::sql_query(query)
         .bind::<Text, _>(dev_eui)
@@ -1438,15 +1746,199 @@ pub async fn get_active_alarms_with_schedule(
     Ok(results)
 }
 
+// Returns the active no-data alarms (per schedule day) of the devices that did not send any
+// uplink within the configured number of minutes and for which no notification has been sent
+// since their last uplink. When the alarm does not define the number of minutes, twice the
+// device data interval (data_time) is used, falling back to 60 minutes. A zone alarm (without
+// dev_eui) applies to the devices which are currently in its zone, it is returned per device.
+pub async fn get_no_data_alarms(weekday: i32) -> Result<Vec<NoDataAlarm>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT
+            a.id,
+            lower(hex(d.dev_eui)) AS dev_eui,
+            d.name AS device_name,
+            (SELECT z.zone_name FROM zone AS z, json_each(z.devices) AS zd WHERE zd.value = '\x' || lower(hex(d.dev_eui)) LIMIT 1) AS zone_name,
+            a.user_id,
+            COALESCE(a.is_time_limit_active, false) AS is_time_limit_active,
+            adt.start_time,
+            adt.end_time,
+            COALESCE(NULLIF(a.no_data_time, 0), NULLIF(d.data_time, 0) * 2, 60) AS silence_minutes,
+            d.last_seen_at
+        FROM alarm AS a
+        INNER JOIN device AS d ON lower(hex(d.dev_eui)) = a.dev_eui
+            OR (a.dev_eui = '' AND EXISTS (
+                SELECT 1 FROM zone AS z, json_each(z.devices) AS zd
+                WHERE z.zone_id = a.zone_category AND zd.value = '\x' || lower(hex(d.dev_eui))
+            ))
+        INNER JOIN alarm_date_time AS adt ON adt.alarm_id = a.id
+        WHERE a.no_data = true
+          AND a.is_active = true
+          AND (adt.alarm_day = 0 OR adt.alarm_day = ?1)
+          AND COALESCE(json_extract(d.tags, '$.status'), 'active') = 'active'
+          AND d.last_seen_at IS NOT NULL
+          AND datetime(d.last_seen_at) < datetime('now', printf('-%d minutes', COALESCE(NULLIF(a.no_data_time, 0), NULLIF(d.data_time, 0) * 2, 60)))
+          AND NOT EXISTS (
+            SELECT 1 FROM alarm_no_data_trigger AS t
+            WHERE t.alarm_id = a.id AND t.dev_eui = lower(hex(d.dev_eui)) AND julianday(t.triggered_at) >= julianday(d.last_seen_at)
+          )
+        "#
+    } else {
+        r#"
+        SELECT
+            a.id,
+            encode(d.dev_eui, 'hex') AS dev_eui,
+            d.name AS device_name,
+            (SELECT z.zone_name FROM zone AS z WHERE d.dev_eui::text = ANY(z.devices) LIMIT 1) AS zone_name,
+            a.user_id,
+            COALESCE(a.is_time_limit_active, false) AS is_time_limit_active,
+            adt.start_time,
+            adt.end_time,
+            COALESCE(NULLIF(a.no_data_time, 0), NULLIF(d.data_time, 0) * 2, 60) AS silence_minutes,
+            d.last_seen_at
+        FROM alarm AS a
+        INNER JOIN device AS d ON d.dev_eui::text = '\x' || a.dev_eui
+            OR (a.dev_eui = '' AND EXISTS (
+                SELECT 1 FROM zone AS z WHERE z.zone_id = a.zone_category AND d.dev_eui::text = ANY(z.devices)
+            ))
+        INNER JOIN alarm_date_time AS adt ON adt.alarm_id = a.id
+        WHERE a.no_data = true
+          AND a.is_active = true
+          AND (adt.alarm_day = 0 OR adt.alarm_day = $1)
+          AND COALESCE(d.tags->>'status', 'active') = 'active'
+          AND d.last_seen_at IS NOT NULL
+          AND d.last_seen_at < now() - make_interval(mins => COALESCE(NULLIF(a.no_data_time, 0), NULLIF(d.data_time, 0) * 2, 60))
+          AND NOT EXISTS (
+            SELECT 1 FROM alarm_no_data_trigger AS t
+            WHERE t.alarm_id = a.id AND t.dev_eui = encode(d.dev_eui, 'hex') AND t.triggered_at >= d.last_seen_at
+          )
+        "#
+    };
+    let alarms = diesel::sql_query(query)
+        .bind::<Integer, _>(weekday)
+        .load::<NoDataAlarm>(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, "no-data alarms".to_string()))?;
+
+    Ok(alarms)
+}
+
+// Stores that the no-data alarm was triggered for the device.
+pub async fn set_no_data_triggered(alarm_id: i32, dev_eui: &str) -> Result<(), Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    diesel::insert_into(alarm_no_data_trigger::table)
+        .values((
+            alarm_no_data_trigger::alarm_id.eq(alarm_id),
+            alarm_no_data_trigger::dev_eui.eq(dev_eui),
+            alarm_no_data_trigger::triggered_at.eq(Utc::now()),
+        ))
+        .on_conflict((
+            alarm_no_data_trigger::alarm_id,
+            alarm_no_data_trigger::dev_eui,
+        ))
+        .do_update()
+        .set(alarm_no_data_trigger::triggered_at.eq(Utc::now()))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+
+    Ok(())
+}
+
+// Removes the triggered no-data alarms of the devices which sent data since, it returns the
+// (alarm id, dev_eui) pairs of the removed triggers.
+pub async fn reset_no_data_triggered() -> Result<Vec<(i32, String)>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let dev_eui = if cfg!(feature = "sqlite") {
+        "lower(hex(d.dev_eui))"
+    } else {
+        "encode(d.dev_eui, 'hex')"
+    };
+    let items: Vec<NoDataTrigger> = diesel::sql_query(format!(
+        r#"
+        DELETE FROM alarm_no_data_trigger
+        WHERE EXISTS (
+            SELECT 1 FROM device AS d
+            WHERE {dev_eui} = alarm_no_data_trigger.dev_eui
+              AND d.last_seen_at > alarm_no_data_trigger.triggered_at
+        )
+        RETURNING alarm_id, dev_eui
+        "#
+    ))
+    .load(&mut conn)
+    .await
+    .map_err(|e| Error::from_diesel(e, "no-data alarm triggers".to_string()))?;
+
+    Ok(items.into_iter().map(|t| (t.alarm_id, t.dev_eui)).collect())
+}
+
+#[derive(QueryableByName)]
+struct NoDataTrigger {
+    #[diesel(sql_type = Integer)]
+    alarm_id: i32,
+    #[diesel(sql_type = Text)]
+    dev_eui: String,
+}
+
+pub async fn execute_no_data_alarm(alarm: &NoDataAlarm, date: &str) -> anyhow::Result<()> {
+    inc_raised("no_data");
+
+    let mut message = String::new();
+    write!(
+        &mut message,
+        "{} tarihinde {} ortamındaki {} isimli sensörden {} dakikadır veri alınamıyor. Son veri zamanı: {}",
+        date,
+        alarm.zone_name.as_deref().unwrap_or("Bilinmeyen Alan"),
+        alarm.device_name,
+        alarm.silence_minutes,
+        to_local(alarm.last_seen_at).format("%Y-%m-%d %H:%M")
+    )?;
+
+    let notification = notification::Notification {
+        sender_id: alarm.id,
+        receiver_id: alarm.user_id.clone(),
+        message: message.clone(),
+        category_id: notification::CATEGORY_ALARM,
+        is_read: Some(false),
+        send_time: Some(Local::now().naive_local()),
+        sender_ip: Some("System".to_string()),
+        reader_ip: Some("".to_string()),
+        is_deleted: Some(false),
+        device_name: Some(alarm.device_name.clone()),
+        dev_eui: Some(alarm.dev_eui.clone()),
+        deleted_time: None,
+        id: 0,
+        read_time: None,
+    };
+
+    notify_alarm(alarm.id, notification).await?;
+    set_no_data_triggered(alarm.id, &alarm.dev_eui).await?;
+    webhook::alarm_raised(alarm.id, "no_data", &alarm.dev_eui, None, &message).await;
+
+    Ok(())
+}
+
 pub async fn get_zone_name_by_dev_eui(
-    conn: &mut AsyncPgConnection,
+    conn: &mut AsyncDbConnection,
     dev_eui: &str,
 ) -> anyhow::Result<Option<String>> {
-    let query = r#"
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT zone_name 
+        FROM zone, json_each(zone.devices) AS zd
+        WHERE zd.value = '\x' || ?1
+    "#
+    } else {
+        r#"
         SELECT zone_name 
         FROM zone 
         WHERE '\\x' || $1 = ANY(zone.devices)
-    "#;
+    "#
+    };
     #[derive(QueryableByName)]
     struct ZoneName {
         #[diesel(sql_type = Text)]
@@ -1463,8 +1955,47 @@ pub async fn check_threshold(
     device: &Device,
     alarm_type: &str,
     date: &str,
-    conn: &mut AsyncPgConnection,
+    conn: &mut AsyncDbConnection,
 ) -> anyhow::Result<()> {
+    // Temperature alarms are suppressed during the expected defrost cycles.
+    if alarm_type == "temperature" {
+        let now = local_now();
+        let minute = (now.hour() * 60 + now.minute()) as f64;
+        let tolerance = (config::get().alarm.defrost_tolerance.as_secs() / 60) as f64;
+        if defrost::is_defrost_expected(conn, &alarm.dev_eui, minute, tolerance).await? {
+            info!(alarm_id = alarm.id, dev_eui = %alarm.dev_eui, value = value, "Alarm suppressed during defrost window");
+            return Ok(());
+        }
+    }
+
+    if alarm_rule::RuleType::from(alarm.rule_type) != alarm_rule::RuleType::Threshold {
+        // The rule alarms notify once when the rule is violated, not on every uplink while the
+        // rule stays violated.
+        match alarm_rule::evaluate(alarm, value, device, alarm_type, conn).await? {
+            Some(violation) => {
+                if !alarm.is_triggered(alarm_type)
+                    && set_triggered(conn, alarm.id as i32, alarm_type).await?
+                {
+                    execute_rule_alarm(alarm, device, alarm_type, &violation, date, conn).await?;
+                }
+            }
+            None => {
+                if alarm.is_triggered(alarm_type)
+                    && reset_triggered(conn, alarm.id as i32, alarm_type).await?
+                {
+                    webhook::alarm_cleared(
+                        alarm.id as i32,
+                        alarm_type,
+                        &device.dev_eui.to_string(),
+                        Some(value),
+                    )
+                    .await;
+                }
+            }
+        }
+        return Ok(());
+    }
+
     if value < alarm.min_treshold || value > alarm.max_treshold {
         match alarm.zone_category_id {
             1 => {
@@ -1490,18 +2021,118 @@ pub async fn check_threshold(
                 return Ok(());
             }
         }
+    } else if alarm.is_raised(alarm_type) {
+        webhook::alarm_cleared(
+            alarm.id as i32,
+            alarm_type,
+            &device.dev_eui.to_string(),
+            Some(value),
+        )
+        .await;
     }
     Ok(())
 }
 
+// Sends the alarm message as SMS to the notified users, when SMS is enabled for the alarm.
+async fn send_alarm_sms(alarm: &AlarmWithDates, device: &Device, user_ids: &[Uuid], message: &str) {
+    if !alarm.sms || user_ids.is_empty() {
+        return;
+    }
+
+    if let Err(e) = crate::alerting::sms::send(&device.dev_eui.to_string(), user_ids, message).await
+    {
+        warn!(alarm_id = alarm.id, dev_eui = %device.dev_eui, error = %e, "Sending alarm SMS failed");
+    }
+}
+
+// Sends the alarm notification, leaving out the users who snoozed the alarm. The notification
+// is suppressed during a maintenance window of the device or when all the users snoozed the
+// alarm, the suppressed alarm is then recorded in the audit log. It returns the notified users.
+async fn notify_alarm(
+    alarm_id: i32,
+    mut n: notification::Notification,
+) -> anyhow::Result<Vec<Uuid>> {
+    let dev_eui = n.dev_eui.clone().unwrap_or_default();
+
+    if let Some(w) = maintenance::get_active_for_device(&dev_eui).await? {
+        info!(alarm_id = alarm_id, dev_eui = %dev_eui, maintenance_window_id = w.id, "Alarm notification suppressed during maintenance window");
+        notification::record_delivery("notification", "suppressed");
+        log_suppressed(
+            alarm_id,
+            &dev_eui,
+            serde_json::json!({
+                "message": n.message,
+                "reason": "maintenance",
+                "maintenance_window_id": w.id,
+            }),
+        )
+        .await?;
+        return Ok(Vec::new());
+    }
+
+    let snoozed = maintenance::get_snoozed_user_ids(alarm_id).await?;
+    n.receiver_id
+        .retain(|id| !id.map(|id| snoozed.contains(&id)).unwrap_or_default());
+    let user_ids: Vec<Uuid> = n.receiver_id.iter().flatten().copied().collect();
+    if user_ids.is_empty() && !snoozed.is_empty() {
+        info!(alarm_id = alarm_id, dev_eui = %dev_eui, "Alarm notification suppressed, snoozed by all users");
+        notification::record_delivery("notification", "suppressed");
+        log_suppressed(
+            alarm_id,
+            &dev_eui,
+            serde_json::json!({
+                "message": n.message,
+                "reason": "snooze",
+                "user_id": snoozed,
+            }),
+        )
+        .await?;
+        return Ok(Vec::new());
+    }
+
+    let message = n.message.clone();
+    notification::create_notification(n).await?;
+    start_escalation(alarm_id, &dev_eui, &message).await;
+    Ok(user_ids)
+}
+
+// Records the suppressed alarm in the audit log, the changed_by is not set as the alarm was
+// suppressed by the system.
+async fn log_suppressed(alarm_id: i32, dev_eui: &str, new_value: Value) -> Result<(), Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    diesel::insert_into(alarm_audit_log::table)
+        .values((
+            alarm_audit_log::alarm_id.eq(alarm_id),
+            alarm_audit_log::dev_eui.eq(dev_eui),
+            alarm_audit_log::change_type.eq("SUPPRESS"),
+            alarm_audit_log::new_values.eq(Some(fields::JsonValue::from(new_value))),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+
+    Ok(())
+}
+
+// Starts the escalation of the triggered alarm in case an escalation policy applies. Failing
+// to start the escalation must not fail the alarm itself.
+async fn start_escalation(alarm_id: i32, dev_eui: &str, message: &str) {
+    if let Err(e) = escalation::start(alarm_id, dev_eui, message).await {
+        warn!(alarm_id = alarm_id, dev_eui = %dev_eui, error = %e, "Starting alarm escalation failed");
+    }
+}
+
 pub async fn execute_alarm(
     alarm: &AlarmWithDates,
     value: f32,
     device: &Device,
     alarm_type: &str,
     date: &str,
-    conn: &mut AsyncPgConnection,
+    conn: &mut AsyncDbConnection,
 ) -> anyhow::Result<()> {
+    inc_raised("threshold");
+
     let zone_name = get_zone_name_by_dev_eui(conn, &device.dev_eui.to_string())
         .await
         .unwrap_or(Some("Bilinmeyen Alan".to_string()));
@@ -1569,7 +2200,106 @@ pub async fn execute_alarm(
         sender_id: alarm.id as i32,
         receiver_id: alarm.user_id.clone(),
         message: message.clone(),
-        category_id: 1,
+        category_id: notification::CATEGORY_ALARM,
+        is_read: Some(false),
+        send_time: Some(Local::now().naive_local()),
+        sender_ip: Some("System".to_string()),
+        reader_ip: Some("".to_string()),
+        is_deleted: Some(false),
+        device_name: Some(device.name.clone()),
+        dev_eui: Some(device.dev_eui.to_string()),
+        deleted_time: None,
+        id: 0,
+        read_time: None,
+    };
+
+    let user_ids = notify_alarm(alarm.id as i32, notification).await?;
+    send_alarm_sms(alarm, device, &user_ids, &message).await;
+    webhook::alarm_raised(
+        alarm.id as i32,
+        alarm_type,
+        &device.dev_eui.to_string(),
+        Some(value),
+        &message,
+    )
+    .await;
+    Ok(())
+}
+
+// Stores that the alarm is triggered for the alarm type, it returns false when it was already
+// triggered.
+pub async fn set_triggered(
+    conn: &mut AsyncDbConnection,
+    alarm_id: i32,
+    alarm_type: &str,
+) -> Result<bool, Error> {
+    let ra = diesel::insert_into(alarm_trigger::table)
+        .values((
+            alarm_trigger::alarm_id.eq(alarm_id),
+            alarm_trigger::alarm_type.eq(alarm_type),
+            alarm_trigger::triggered_at.eq(Utc::now()),
+        ))
+        .on_conflict_do_nothing()
+        .execute(conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+    Ok(ra != 0)
+}
+
+// Removes the triggered state of the alarm for the alarm type, it returns false when it was not
+// triggered.
+pub async fn reset_triggered(
+    conn: &mut AsyncDbConnection,
+    alarm_id: i32,
+    alarm_type: &str,
+) -> Result<bool, Error> {
+    let ra = diesel::delete(
+        alarm_trigger::table
+            .filter(alarm_trigger::alarm_id.eq(alarm_id))
+            .filter(alarm_trigger::alarm_type.eq(alarm_type)),
+    )
+    .execute(conn)
+    .await
+    .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+    Ok(ra != 0)
+}
+
+pub async fn execute_rule_alarm(
+    alarm: &AlarmWithDates,
+    device: &Device,
+    alarm_type: &str,
+    violation: &str,
+    date: &str,
+    conn: &mut AsyncDbConnection,
+) -> anyhow::Result<()> {
+    inc_raised("rule");
+
+    let zone_name = get_zone_name_by_dev_eui(conn, &device.dev_eui.to_string())
+        .await
+        .unwrap_or(Some("Bilinmeyen Alan".to_string()));
+
+    let label = match alarm_type {
+        "temperature" => "sıcaklık",
+        "humidity" => "nem",
+        "pressure" => "basınç",
+        "ec" => "iletkenlik",
+        v => v,
+    };
+
+    let message = format!(
+        "{} tarihinde {} ortamındaki {} isimli sensörün {} değeri {}",
+        date,
+        zone_name.as_deref().unwrap_or("Bilinmeyen Alan"),
+        device.name,
+        label,
+        violation
+    );
+
+    let notification = notification::Notification {
+        sender_id: alarm.id as i32,
+        receiver_id: alarm.user_id.clone(),
+        message: message.clone(),
+        category_id: notification::CATEGORY_ALARM,
         is_read: Some(false),
         send_time: Some(Local::now().naive_local()),
         sender_ip: Some("System".to_string()),
@@ -1582,7 +2312,16 @@ pub async fn execute_alarm(
         read_time: None,
     };
 
-    notification::create_notification(notification).await?;
+    let user_ids = notify_alarm(alarm.id as i32, notification).await?;
+    send_alarm_sms(alarm, device, &user_ids, &message).await;
+    webhook::alarm_raised(
+        alarm.id as i32,
+        alarm_type,
+        &device.dev_eui.to_string(),
+        None,
+        &message,
+    )
+    .await;
     Ok(())
 }
 
@@ -1592,8 +2331,10 @@ pub async fn execute_alarm2(
     device: &Device,
     alarm_type: &str,
     date: &str,
-    conn: &mut AsyncPgConnection,
+    conn: &mut AsyncDbConnection,
 ) -> anyhow::Result<()> {
+    inc_raised("threshold");
+
     // Get zone name
     let zone_name = get_zone_name_by_dev_eui(conn, &device.dev_eui.to_string())
         .await
@@ -1606,7 +2347,11 @@ pub async fn execute_alarm2(
         name: String,
     }
     let organization_name: String = if let Some(org_id) = device.tenant_id {
-        let org_query = r#"SELECT name FROM public.tenant WHERE id = $1"#;
+        let org_query = if cfg!(feature = "sqlite") {
+            "SELECT name FROM tenant WHERE id = ?1"
+        } else {
+            "SELECT name FROM public.tenant WHERE id = $1"
+        };
         let org_rows: Vec<OrganizationRow> = sql_query(org_query)
             .bind::<Nullable<DieselUuid>, _>(device.tenant_id)
             .load(conn)
@@ -1670,8 +2415,8 @@ pub async fn execute_alarm2(
     let notification = notification::Notification {
         sender_id: alarm.id as i32,
         receiver_id: alarm.user_id.clone(),
-        message,
-        category_id: 1,
+        message: message.clone(),
+        category_id: notification::CATEGORY_ALARM,
         is_read: Some(false),
         send_time: Some(Local::now().naive_local()),
         sender_ip: Some("system".to_string()),
@@ -1684,7 +2429,16 @@ pub async fn execute_alarm2(
         read_time: None,
     };
 
-    notification::create_notification(notification).await?;
+    let user_ids = notify_alarm(alarm.id as i32, notification).await?;
+    send_alarm_sms(alarm, device, &user_ids, &message).await;
+    webhook::alarm_raised(
+        alarm.id as i32,
+        alarm_type,
+        &device.dev_eui.to_string(),
+        Some(value),
+        &message,
+    )
+    .await;
     Ok(())
 }
 
@@ -1697,17 +2451,26 @@ struct TemperatureRow {
 pub async fn ege_method(
     value: f32,
     alarm: &AlarmWithDates,
-    conn: &mut AsyncPgConnection,
+    conn: &mut AsyncDbConnection,
 ) -> anyhow::Result<bool> {
     let interval = alarm.defrost_time;
 
     // First: get values from the last `interval` minutes
-    let query = format!(
-        "SELECT air_temperature FROM device_data_2025 \
-         WHERE dev_eui = $1 AND submission_date > now() - interval '{} minute' \
-         ORDER BY submission_date ASC",
-        interval
-    );
+    let query = if cfg!(feature = "sqlite") {
+        format!(
+            "SELECT air_temperature FROM device_data \
+             WHERE dev_eui = ?1 AND datetime(submission_date) > datetime('now', '-{} minutes') \
+             ORDER BY submission_date ASC",
+            interval
+        )
+    } else {
+        format!(
+            "SELECT air_temperature FROM device_data \
+             WHERE dev_eui = $1 AND submission_date > now() - interval '{} minute' \
+             ORDER BY submission_date ASC",
+            interval
+        )
+    };
 
     let mut res_45_min: Vec<f32> = sql_query(&query)
         .bind::<diesel::sql_types::Text, _>(&alarm.dev_eui)
@@ -1719,10 +2482,11 @@ pub async fn ege_method(
 
     // If empty, fallback to most recent value
     if res_45_min.is_empty() {
-        res_45_min = sql_query(
-            "SELECT air_temperature FROM device_data_2025 \
-             WHERE dev_eui = $1 ORDER BY submission_date DESC LIMIT 1",
-        )
+        res_45_min = sql_query(format!(
+            "SELECT air_temperature FROM device_data \
+             WHERE dev_eui = {} ORDER BY submission_date DESC LIMIT 1",
+            if cfg!(feature = "sqlite") { "?1" } else { "$1" }
+        ))
         .bind::<diesel::sql_types::Text, _>(&alarm.dev_eui)
         .load::<TemperatureRow>(conn)
         .await?
@@ -1737,21 +2501,27 @@ pub async fn ege_method(
         return Ok(false);
     }
 
+    Ok(is_ege_alarm(&res_45_min, alarm.max_treshold))
+}
+
+// Returns true when all the values (oldest first) are above max_treshold and the last value is
+// still rising.
+pub fn is_ege_alarm(values: &[f32], max_treshold: f32) -> bool {
     // Threshold check
-    if res_45_min.iter().any(|&t| t < alarm.max_treshold) {
-        return Ok(false);
+    if values.is_empty() || values.iter().any(|&t| t < max_treshold) {
+        return false;
     }
 
     // Trend analysis
-    if res_45_min.len() > 1 {
-        let last = res_45_min[res_45_min.len() - 1];
-        let previous = res_45_min[res_45_min.len() - 2];
+    if values.len() > 1 {
+        let last = values[values.len() - 1];
+        let previous = values[values.len() - 2];
         if last <= previous {
-            return Ok(false);
+            return false;
         }
     }
 
-    Ok(true)
+    true
 }
 
 pub async fn create_alarm_automation(alarm_automation: NewAlarmAutomation) -> Result<(), Error> {
@@ -1824,3 +2594,218 @@ pub async fn update_alarm_automation(
     info!(updated_alarm_automation.id, "Alarm automation updated");
     Ok(updated_alarm_automation)
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{self, device, zone};
+    use crate::test;
+    use chrono::Duration;
+    use lrwn::EUI64;
+
+    async fn set_last_seen(dev_eui: EUI64, last_seen_at: DateTime<Utc>) {
+        device::partial_update(
+            dev_eui,
+            &device::DeviceChangeset {
+                last_seen_at: Some(Some(last_seen_at)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    async fn get_device_alarms(conn: &mut AsyncDbConnection) -> Vec<AlarmWithDates> {
+        get_active_alarms_with_schedule(conn, "0102030405060708", 1)
+            .await
+            .unwrap()
+    }
+
+    fn dev_euis(alarms: &[NoDataAlarm]) -> Vec<String> {
+        let mut out: Vec<String> = alarms.iter().map(|a| a.dev_eui.clone()).collect();
+        out.sort();
+        out
+    }
+
+    #[tokio::test]
+    async fn test_no_data_alarms() {
+        let _guard = test::prepare().await;
+
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d1 = device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+        let d2 = device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 9]),
+            dp.id.into(),
+            Some(d1.application_id.into()),
+        )
+        .await;
+        let silent_since = Utc::now() - Duration::hours(2);
+        set_last_seen(d1.dev_eui, silent_since).await;
+        set_last_seen(d2.dev_eui, silent_since).await;
+
+        let z = zone::create(zone::Zone {
+            zone_name: Some("Soğuk oda".into()),
+            tanent_id: Some(dp.tenant_id.into()),
+            devices: vec![Some(format!("\\x{}", d1.dev_eui))].into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // the zone alarm is stored once, without dev_eui
+        let a = create(
+            NewAlarm {
+                zone_category: Some(z.zone_id),
+                no_data: Some(true),
+                no_data_time: Some(30),
+                ..Default::default()
+            },
+            vec![AlarmDateTime {
+                id: 1,
+                alarm_day: 0,
+                start_time: 0.0,
+                end_time: 24.0,
+                ..Default::default()
+            }],
+            Uuid::new_v4(),
+        )
+        .await
+        .unwrap();
+        assert_eq!("", get_alarm(a.id).await.unwrap().dev_eui);
+
+        let alarms = get_no_data_alarms(1).await.unwrap();
+        assert_eq!(vec![d1.dev_eui.to_string()], dev_euis(&alarms));
+        assert_eq!(a.id, alarms[0].id);
+        assert_eq!(30, alarms[0].silence_minutes);
+
+        // a device added to the zone is covered without updating the alarm
+        zone::update_internal(
+            z.zone_id,
+            zone::UpdateZone {
+                zone_name: None,
+                zone_order: None,
+                content_type: None,
+                tanent_id: None,
+                devices: Some(
+                    vec![
+                        Some(format!("\\x{}", d1.dev_eui)),
+                        Some(format!("\\x{}", d2.dev_eui)),
+                    ]
+                    .into(),
+                ),
+                site_id: None,
+            },
+        )
+        .await
+        .unwrap();
+        let alarms = get_no_data_alarms(1).await.unwrap();
+        assert_eq!(
+            vec![d1.dev_eui.to_string(), d2.dev_eui.to_string()],
+            dev_euis(&alarms)
+        );
+
+        // trigger is per device
+        let a2 = alarms
+            .iter()
+            .find(|a| a.dev_eui == d2.dev_eui.to_string())
+            .unwrap();
+        execute_no_data_alarm(a2, "2025-07-22 10:00:00")
+            .await
+            .unwrap();
+        let alarms = get_no_data_alarms(1).await.unwrap();
+        assert_eq!(vec![d1.dev_eui.to_string()], dev_euis(&alarms));
+
+        // nothing to reset while the device is silent
+        assert!(reset_no_data_triggered().await.unwrap().is_empty());
+
+        // data from the device resets the trigger
+        set_last_seen(d2.dev_eui, Utc::now() + Duration::seconds(1)).await;
+        assert_eq!(
+            vec![(a.id, d2.dev_eui.to_string())],
+            reset_no_data_triggered().await.unwrap()
+        );
+        assert!(reset_no_data_triggered().await.unwrap().is_empty());
+
+        // silent again, triggered again
+        set_last_seen(d2.dev_eui, silent_since).await;
+        let alarms = get_no_data_alarms(1).await.unwrap();
+        assert_eq!(
+            vec![d1.dev_eui.to_string(), d2.dev_eui.to_string()],
+            dev_euis(&alarms)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_alarm_trigger() {
+        let _guard = test::prepare().await;
+
+        let a = create(
+            NewAlarm {
+                dev_eui: "0102030405060708".into(),
+                temperature: Some(true),
+                humadity: Some(true),
+                rule_type: alarm_rule::RuleType::Sustained.into(),
+                ..Default::default()
+            },
+            vec![AlarmDateTime {
+                id: 1,
+                alarm_day: 0,
+                start_time: 0.0,
+                end_time: 24.0,
+                ..Default::default()
+            }],
+            Uuid::new_v4(),
+        )
+        .await
+        .unwrap();
+
+        let mut conn = get_async_db_conn().await.unwrap();
+
+        let alarms = get_device_alarms(&mut conn).await;
+        assert_eq!(1, alarms.len());
+        assert!(!alarms[0].is_triggered("temperature"));
+
+        // only the first violation is a transition
+        assert!(set_triggered(&mut conn, a.id, "temperature").await.unwrap());
+        assert!(!set_triggered(&mut conn, a.id, "temperature").await.unwrap());
+
+        let alarms = get_device_alarms(&mut conn).await;
+        assert!(alarms[0].is_triggered("temperature"));
+        assert!(!alarms[0].is_triggered("humidity"));
+
+        // the state is per alarm type
+        assert!(set_triggered(&mut conn, a.id, "humidity").await.unwrap());
+        let alarms = get_device_alarms(&mut conn).await;
+        assert!(alarms[0].is_triggered("temperature"));
+        assert!(alarms[0].is_triggered("humidity"));
+
+        // only the first reset is a transition
+        assert!(reset_triggered(&mut conn, a.id, "temperature")
+            .await
+            .unwrap());
+        assert!(!reset_triggered(&mut conn, a.id, "temperature")
+            .await
+            .unwrap());
+
+        let alarms = get_device_alarms(&mut conn).await;
+        assert!(!alarms[0].is_triggered("temperature"));
+        assert!(alarms[0].is_triggered("humidity"));
+
+        // the raised state is per device
+        assert!(!alarms[0].is_raised("temperature"));
+        webhook::alarm_raised(a.id, "temperature", "0102030405060708", Some(8.0), "").await;
+        webhook::alarm_raised(a.id, "humidity", "0202030405060708", Some(80.0), "").await;
+        let alarms = get_device_alarms(&mut conn).await;
+        assert!(alarms[0].is_raised("temperature"));
+        assert!(!alarms[0].is_raised("humidity"));
+
+        webhook::alarm_cleared(a.id, "temperature", "0102030405060708", Some(4.0)).await;
+        let alarms = get_device_alarms(&mut conn).await;
+        assert!(!alarms[0].is_raised("temperature"));
+    }
+}
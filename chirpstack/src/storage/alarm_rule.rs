@@ -0,0 +1,369 @@
+use anyhow::Result;
+use diesel::sql_query;
+use diesel::sql_types::{Double, Float, Integer, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use tracing::warn;
+
+use super::alarm::AlarmWithDates;
+use super::device::Device;
+use super::AsyncDbConnection;
+
+// Default window (minutes) for the rules that require a duration when the alarm does not define
+// one.
+pub const DEFAULT_RULE_DURATION: i32 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleType {
+    // The value is below min_treshold or above max_treshold.
+    Threshold,
+    // The value stayed below min_treshold or above max_treshold for rule_duration minutes.
+    Sustained,
+    // The value changed more than rule_limit per minute over the last rule_duration minutes.
+    RateOfChange,
+    // The value deviates more than rule_limit from the average of the other zone devices.
+    ZoneDeviation,
+}
+
+impl From<i32> for RuleType {
+    fn from(v: i32) -> Self {
+        match v {
+            1 => RuleType::Sustained,
+            2 => RuleType::RateOfChange,
+            3 => RuleType::ZoneDeviation,
+            _ => RuleType::Threshold,
+        }
+    }
+}
+
+impl From<RuleType> for i32 {
+    fn from(v: RuleType) -> Self {
+        match v {
+            RuleType::Threshold => 0,
+            RuleType::Sustained => 1,
+            RuleType::RateOfChange => 2,
+            RuleType::ZoneDeviation => 3,
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct HistoryRow {
+    #[diesel(sql_type = Double)]
+    minutes_ago: f64,
+    #[diesel(sql_type = Float)]
+    value: f32,
+}
+
+#[derive(QueryableByName)]
+struct ValueRow {
+    #[diesel(sql_type = Float)]
+    value: f32,
+}
+
+// Returns the device_data column holding the history of the given alarm type.
+pub fn get_history_column(device_type: Option<i32>, alarm_type: &str) -> Option<&'static str> {
+    match (device_type, alarm_type) {
+        (Some(2), "temperature") => Some("sol_temperature"),
+        (Some(2), "humidity") => Some("sol_water"),
+        (Some(2), "ec") => Some("sol_conduct_soil"),
+        (_, "temperature") => Some("air_temperature"),
+        (_, "humidity") => Some("air_humidity"),
+        (_, "co2") => Some("co2_ppm"),
+        (_, "pressure") => Some("barometric_pressure"),
+        _ => None,
+    }
+}
+
+// Returns true when all the samples (newest first, as (minutes ago, value) tuples) up to
+// duration minutes ago are out of the min / max range.
+pub fn is_sustained(samples: &[(f64, f32)], min: f32, max: f32, duration: f64) -> bool {
+    for (minutes_ago, value) in samples {
+        if *value >= min && *value <= max {
+            return false;
+        }
+
+        if *minutes_ago >= duration {
+            return true;
+        }
+    }
+
+    // Not enough history.
+    false
+}
+
+// Returns the change per minute between the oldest sample within the window and the newest
+// sample. The samples are (minutes ago, value) tuples, newest first.
+pub fn get_rate_of_change(samples: &[(f64, f32)], window: f64) -> Option<f32> {
+    let (newest_ago, newest) = samples.first()?;
+    let (oldest_ago, oldest) = samples.iter().rfind(|(ago, _)| *ago <= window)?;
+
+    let minutes = oldest_ago - newest_ago;
+    if minutes <= 0.0 {
+        return None;
+    }
+
+    Some((newest - oldest) / minutes as f32)
+}
+
+// Returns the average of the zone values and the deviation of the value from this average.
+pub fn get_deviation(value: f32, zone_values: &[f32]) -> Option<(f32, f32)> {
+    if zone_values.is_empty() {
+        return None;
+    }
+
+    let avg = zone_values.iter().sum::<f32>() / zone_values.len() as f32;
+    Some((avg, value - avg))
+}
+
+// Evaluates the (non-threshold) rule of the alarm. When triggered, it returns the description
+// of the rule violation which is used in the alarm notification.
+pub async fn evaluate(
+    alarm: &AlarmWithDates,
+    value: f32,
+    device: &Device,
+    alarm_type: &str,
+    conn: &mut AsyncDbConnection,
+) -> Result<Option<String>> {
+    let rule_type = RuleType::from(alarm.rule_type);
+    let column = match get_history_column(device.device_type, alarm_type) {
+        Some(v) => v,
+        None => {
+            warn!(alarm_id = alarm.id, device_type = ?device.device_type, alarm_type = %alarm_type, rule_type = ?rule_type, "No history available for alarm rule");
+            return Ok(None);
+        }
+    };
+    let duration = alarm
+        .rule_duration
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_RULE_DURATION);
+
+    match rule_type {
+        RuleType::Threshold => Ok(None),
+        RuleType::Sustained => {
+            let samples = get_history(conn, &alarm.dev_eui, column, duration).await?;
+            if is_sustained(
+                &samples,
+                alarm.min_treshold,
+                alarm.max_treshold,
+                duration as f64,
+            ) {
+                Ok(Some(format!(
+                    "{} dakikadır alarm aralığı dışında. Şu anki değeri: {:.2}",
+                    duration, value
+                )))
+            } else {
+                Ok(None)
+            }
+        }
+        RuleType::RateOfChange => {
+            let limit = match alarm.rule_limit {
+                Some(v) if v > 0.0 => v as f32,
+                _ => return Ok(None),
+            };
+
+            let samples = get_history(conn, &alarm.dev_eui, column, duration).await?;
+            match get_rate_of_change(&samples, duration as f64) {
+                Some(rate) if rate.abs() > limit => Ok(Some(format!(
+                    "dakikada {:.2} değişti (limit: {:.2}). Şu anki değeri: {:.2}",
+                    rate, limit, value
+                ))),
+                _ => Ok(None),
+            }
+        }
+        RuleType::ZoneDeviation => {
+            let limit = match alarm.rule_limit {
+                Some(v) if v > 0.0 => v as f32,
+                _ => return Ok(None),
+            };
+
+            let zone_values = get_zone_values(conn, &alarm.dev_eui, column, duration).await?;
+            match get_deviation(value, &zone_values) {
+                Some((avg, deviation)) if deviation.abs() > limit => Ok(Some(format!(
+                    "ortam ortalamasından {:.2} sapıyor. Şu anki değeri: {:.2}, ortam ortalaması: {:.2}",
+                    deviation, value, avg
+                ))),
+                _ => Ok(None),
+            }
+        }
+    }
+}
+
+// Returns the samples of the last duration minutes, including the last sample before this
+// window, newest first.
+async fn get_history(
+    conn: &mut AsyncDbConnection,
+    dev_eui: &str,
+    column: &str,
+    duration: i32,
+) -> Result<Vec<(f64, f32)>> {
+    let query = if cfg!(feature = "sqlite") {
+        format!(
+            r#"
+            SELECT
+                (julianday('now') - julianday(submission_date)) * 1440.0 AS minutes_ago,
+                CAST({col} AS REAL) AS value
+            FROM device_data
+            WHERE dev_eui = ?1
+              AND {col} IS NOT NULL
+              AND julianday(submission_date) >= (
+                SELECT COALESCE(
+                    MAX(julianday(submission_date)),
+                    julianday('now', printf('-%d minutes', ?2))
+                )
+                FROM device_data
+                WHERE dev_eui = ?1
+                  AND {col} IS NOT NULL
+                  AND julianday(submission_date) <= julianday('now', printf('-%d minutes', ?2))
+              )
+            ORDER BY julianday(submission_date) DESC
+            "#,
+            col = column
+        )
+    } else {
+        format!(
+            r#"
+        SELECT
+            EXTRACT(EPOCH FROM (now() - submission_date))::float8 / 60.0 AS minutes_ago,
+            {col}::float4 AS value
+        FROM device_data
+        WHERE dev_eui = $1
+          AND {col} IS NOT NULL
+          AND submission_date >= (
+            SELECT COALESCE(MAX(submission_date), now() - make_interval(mins => $2))
+            FROM device_data
+            WHERE dev_eui = $1
+              AND {col} IS NOT NULL
+              AND submission_date <= now() - make_interval(mins => $2)
+          )
+        ORDER BY submission_date DESC
+        "#,
+            col = column
+        )
+    };
+
+    let rows: Vec<HistoryRow> = sql_query(query)
+        .bind::<Text, _>(dev_eui)
+        .bind::<Integer, _>(duration)
+        .load(conn)
+        .await?;
+
+    Ok(rows.into_iter().map(|r| (r.minutes_ago, r.value)).collect())
+}
+
+// Returns the latest values of the other devices within the zone(s) of the device, which have
+// been received within the last duration minutes.
+async fn get_zone_values(
+    conn: &mut AsyncDbConnection,
+    dev_eui: &str,
+    column: &str,
+    duration: i32,
+) -> Result<Vec<f32>> {
+    // SQLite has no arrays, the zone devices are a JSON array. As device_data_latest holds a
+    // single row per device, selecting the devices through a sub-query returns each device once.
+    let query = if cfg!(feature = "sqlite") {
+        format!(
+            r#"
+            SELECT CAST(dl.{col} AS REAL) AS value
+            FROM device_data_latest AS dl
+            WHERE '\x' || dl.dev_eui IN (
+                SELECT zd.value
+                FROM zone AS z, json_each(z.devices) AS zd
+                WHERE EXISTS (
+                    SELECT 1 FROM json_each(z.devices) AS zs WHERE zs.value = '\x' || ?1
+                )
+              )
+              AND dl.dev_eui <> ?1
+              AND dl.{col} IS NOT NULL
+              AND julianday(dl.submission_date) > julianday('now', printf('-%d minutes', ?2))
+            "#,
+            col = column
+        )
+    } else {
+        format!(
+            r#"
+        SELECT DISTINCT ON (dl.dev_eui) dl.{col}::float4 AS value
+        FROM device_data_latest AS dl
+        INNER JOIN zone AS z ON '\x' || dl.dev_eui = ANY(z.devices)
+        WHERE '\x' || $1 = ANY(z.devices)
+          AND dl.dev_eui <> $1
+          AND dl.{col} IS NOT NULL
+          AND dl.submission_date > (now() AT TIME ZONE 'UTC') - make_interval(mins => $2)
+        "#,
+            col = column
+        )
+    };
+
+    let rows: Vec<ValueRow> = sql_query(query)
+        .bind::<Text, _>(dev_eui)
+        .bind::<Integer, _>(duration)
+        .load(conn)
+        .await?;
+
+    Ok(rows.into_iter().map(|r| r.value).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_sustained() {
+        // Out of range for more than 30 minutes.
+        assert!(is_sustained(
+            &[(0.0, 10.0), (10.0, 9.0), (25.0, 8.5), (35.0, 8.1)],
+            2.0,
+            8.0,
+            30.0
+        ));
+
+        // Back in range within the window.
+        assert!(!is_sustained(
+            &[(0.0, 10.0), (10.0, 7.0), (25.0, 8.5), (35.0, 8.1)],
+            2.0,
+            8.0,
+            30.0
+        ));
+
+        // Not enough history.
+        assert!(!is_sustained(&[(0.0, 10.0), (10.0, 9.0)], 2.0, 8.0, 30.0));
+
+        // Below min.
+        assert!(is_sustained(&[(0.0, 1.0), (40.0, 1.5)], 2.0, 8.0, 30.0));
+    }
+
+    #[test]
+    fn test_get_rate_of_change() {
+        assert_eq!(None, get_rate_of_change(&[], 15.0));
+        assert_eq!(None, get_rate_of_change(&[(0.0, 4.0)], 15.0));
+
+        // 3 degrees in 10 minutes, the sample outside the window is ignored.
+        let rate =
+            get_rate_of_change(&[(0.0, 7.0), (5.0, 5.0), (10.0, 4.0), (20.0, 0.0)], 15.0).unwrap();
+        assert!((rate - 0.3).abs() < 0.0001);
+
+        // Decreasing.
+        let rate = get_rate_of_change(&[(0.0, 2.0), (10.0, 4.0)], 15.0).unwrap();
+        assert!((rate + 0.2).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_get_deviation() {
+        assert_eq!(None, get_deviation(5.0, &[]));
+        assert_eq!(Some((4.0, 6.0)), get_deviation(10.0, &[3.0, 5.0]));
+        assert_eq!(Some((4.0, -2.0)), get_deviation(2.0, &[3.0, 5.0]));
+    }
+
+    #[test]
+    fn test_rule_type() {
+        for rt in [
+            RuleType::Threshold,
+            RuleType::Sustained,
+            RuleType::RateOfChange,
+            RuleType::ZoneDeviation,
+        ] {
+            assert_eq!(rt, RuleType::from(i32::from(rt)));
+        }
+        assert_eq!(RuleType::Threshold, RuleType::from(42));
+    }
+}
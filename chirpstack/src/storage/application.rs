@@ -387,9 +387,9 @@ pub async fn get_internal(device_type_id: i64) -> Result<Uuid, Error> {
         .order_by(a::created_at.desc())
         .select(a::id);
 
-    let result: Option<Uuid> = query.first::<Uuid>(&mut conn).await.optional()?;
+    let result: Option<fields::Uuid> = query.first(&mut conn).await.optional()?;
     match result {
-        Some(uuid) => Ok(uuid),
+        Some(uuid) => Ok(uuid.into()),
         None => Err(anyhow::anyhow!(
             "application not found for device_type_id {}",
             device_type_id
@@ -1,5 +1,6 @@
-use super::{error::Error, get_async_db_conn};
-use crate::storage::schema_postgres::automation_rules;
+use super::fields::sql_types::Uuid as DieselUuid;
+use super::{error::Error, fields, get_async_db_conn};
+use crate::storage::schema::automation_rules;
 use base64::engine::general_purpose::STANDARD as base64_engine;
 use base64::Engine;
 use base64::{engine::general_purpose as base64_engine, Engine as _};
@@ -10,9 +11,11 @@ use diesel::prelude::*;
 use diesel::sql_query;
 use diesel::sql_types::Nullable;
 use diesel::sql_types::*;
-use diesel::sql_types::Uuid as DieselUuid;
 use diesel::sql_types::{Bool, Integer, Text};
 use diesel_async::RunQueryDsl;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::str::FromStr;
@@ -20,10 +23,49 @@ use std::time::Duration;
 use tokio::time::sleep;
 use uuid::Uuid;
 use super::application::{self, get as get_application};
+use super::virtual_device;
+use super::webhook;
 use super::device::{self, get as get_device};
 use super::device_profile::{self, get as get_device_profile};
 use super::device_queue::{self, enqueue_item};
 use lrwn::EUI64;
+use crate::monitoring::prometheus;
+
+// Automation downlinks (e.g. relay commands) are sent before downlinks enqueued with the
+// default priority (e.g. bulk configuration downlinks).
+const AUTOMATION_QUEUE_PRIORITY: u32 = 100;
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct EvaluationLabels {
+    result: String,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct ActionLabels {
+    outcome: String,
+}
+
+lazy_static! {
+    static ref EVALUATION_COUNTER: Family<EvaluationLabels, Counter> = {
+        let counter = Family::<EvaluationLabels, Counter>::default();
+        prometheus::register(
+            "automation_evaluation_count",
+            "Number of automation rule condition evaluations by result",
+            counter.clone(),
+        );
+        counter
+    };
+    static ref ACTION_COUNTER: Family<ActionLabels, Counter> = {
+        let counter = Family::<ActionLabels, Counter>::default();
+        prometheus::register(
+            "automation_action_count",
+            "Number of executed automation rule actions by outcome",
+            counter.clone(),
+        );
+        counter
+    };
+}
+
 #[derive(Debug, Queryable, Insertable, AsChangeset, QueryableByName)]
 #[diesel(table_name = automation_rules)]
 pub struct Automation {
@@ -56,9 +98,9 @@ pub struct Automation {
     #[diesel(sql_type = Nullable<Timestamp>)]
     pub trigger_time: Option<NaiveDateTime>,
     #[diesel(sql_type = Nullable<DieselUuid>)]
-    pub tenant_id: Option<uuid::Uuid>,
+    pub tenant_id: Option<fields::Uuid>,
     #[diesel(sql_type = Nullable<DieselUuid>)]
-    pub user_id: Option<Uuid>,
+    pub user_id: Option<fields::Uuid>,
 }
 
 pub struct AutomationFilters {
@@ -144,13 +186,13 @@ pub async fn list_automation_rules(
 
     let mut query = automation_rules::table.into_boxed();
 
-    query = query.filter(automation_rules::user_id.eq(filters.user_id));
+    query = query.filter(automation_rules::user_id.eq(fields::Uuid::from(filters.user_id)));
 
     if let Some(sender_sensor) = filters.sender_sensor {
         query = query.filter(automation_rules::sender_sensor.eq(sender_sensor));
     }
 
-    query = query.filter(automation_rules::tenant_id.eq(filters.tenant_id));
+    query = query.filter(automation_rules::tenant_id.eq(fields::Uuid::from(filters.tenant_id)));
 
     let automations: Vec<Automation> = query.load(&mut conn).await?;
 
@@ -205,6 +247,26 @@ pub async fn check_automation_rules(device_dev_eui: &str) -> Result<Vec<Automati
     Ok(rules)
 }
 
+// Returns the active automation rules that send their action to the given (receiver) device.
+pub async fn get_rules_for_receiver(dev_eui: &str) -> Result<Vec<Automation>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let rules: Vec<Automation> = sql_query(format!(
+        r#"
+        SELECT * FROM automation_rules
+        WHERE lower(receiver_sensor) = lower({})
+        AND is_active = TRUE
+    "#,
+        if cfg!(feature = "sqlite") { "?1" } else { "$1" }
+    ))
+    .bind::<Text, _>(dev_eui)
+    .load(&mut conn)
+    .await
+    .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+
+    Ok(rules)
+}
+
 pub async fn check_automation_alarm_rules(alarm_id: i64) -> Result<Vec<Automation>, Error> {
     let mut conn = get_async_db_conn().await?;
 
@@ -223,8 +285,20 @@ pub async fn check_automation_alarm_rules(alarm_id: i64) -> Result<Vec<Automatio
 pub async fn check_time_triggered_rules() -> Result<Vec<Automation>, Error> {
     let mut conn = get_async_db_conn().await?;
 
-    // The query logic is ported from the original SQL.
-    let query = r#"
+    // The query logic is ported from the original SQL. The condition holds the comma separated
+    // week days (0 is Sunday) and the time of the day, e.g. "1,2,3;08:30".
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT * FROM automation_rules
+        WHERE trigger_type = 'time'
+        AND is_active = TRUE
+        AND instr(condition, ';') > 0
+        AND ',' || replace(substr(condition, 1, instr(condition, ';') - 1), ' ', '') || ','
+            LIKE '%,' || strftime('%w', 'now', '+3 hours') || ',%'
+        AND strftime('%H:%M', 'now', '+3 hours') = trim(substr(condition, instr(condition, ';') + 1))
+    "#
+    } else {
+        r#"
         SELECT * FROM automation_rules
         WHERE trigger_type = 'time'
         AND is_active = TRUE
@@ -233,7 +307,8 @@ pub async fn check_time_triggered_rules() -> Result<Vec<Automation>, Error> {
             FROM unnest(string_to_array(split_part(condition, ';', 1), ',')) AS day
         ) @> ARRAY[EXTRACT(DOW FROM NOW() + INTERVAL '3 hours')::integer]
         AND TO_CHAR(NOW() + INTERVAL '3 hours', 'HH24:MI') = trim(split_part(condition, ';', 2))
-    "#;
+    "#
+    };
 
     let rules: Vec<Automation> = sql_query(query)
         .load(&mut conn)
@@ -243,10 +318,27 @@ pub async fn check_time_triggered_rules() -> Result<Vec<Automation>, Error> {
     Ok(rules)
 }
 
+// Evaluates the condition of the rule against the decoded payload of the sender device. The
+// payload is expected to be calibrated already (see Calibrations::apply_object).
 pub async fn check_automation_condition(
     object_json: &str,
     rule: &Automation,
 ) -> Result<bool, Error> {
+    let res = evaluate_condition(object_json, rule).await;
+    let result = match &res {
+        Ok(true) => "match",
+        Ok(false) => "no_match",
+        Err(_) => "error",
+    };
+    EVALUATION_COUNTER
+        .get_or_create(&EvaluationLabels {
+            result: result.to_string(),
+        })
+        .inc();
+    res
+}
+
+async fn evaluate_condition(object_json: &str, rule: &Automation) -> Result<bool, Error> {
     let values: Vec<&str> = match &rule.condition {
         Some(cond) => cond.split(',').collect(),
         None => return Err(Error::Validation("No condition specified".to_string())),
@@ -396,6 +488,27 @@ pub async fn check_automation_condition(
                 Err(Error::Validation("Missing parameter".to_string()))
             }
         }
+        virtual_device::DEVICE_TYPE => {
+            // Virtual device, the computed value is not calibrated again.
+            let value: Value = serde_json::from_str(object_json)
+                .map_err(|e| Error::Validation(format!("Error parsing JSON: {}", e)))?;
+            let f: f32 = values.get(2).and_then(|v| v.parse().ok()).unwrap_or(0.0);
+
+            if let Some(param) = values.as_slice().first() {
+                if *param == "value" {
+                    let v = value.get("value").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
+                    match values.get(1).copied() {
+                        Some("over") => Ok(v > f),
+                        Some("below") => Ok(v < f),
+                        _ => Err(Error::Validation("Invalid comparison".to_string())),
+                    }
+                } else {
+                    Err(Error::Validation("Invalid parameter".to_string()))
+                }
+            } else {
+                Err(Error::Validation("Missing parameter".to_string()))
+            }
+        }
         _ => Err(Error::Validation("Unsupported device type".to_string())),
     }
 }
@@ -418,11 +531,14 @@ pub async fn check_state(rule: &Automation) -> Result<bool, Error> {
                 None => return Ok(false),
             };
 
-            let query = r#"
+            let query = format!(
+                r#"
                 SELECT gpio_out_1, gpio_out_2
                 FROM device_data_latest
-                WHERE dev_eui = $1
-            "#;
+                WHERE dev_eui = {}
+            "#,
+                if cfg!(feature = "sqlite") { "?1" } else { "$1" }
+            );
 
             let state: Option<CurrentState> = sql_query(query)
                 .bind::<Text, _>(receiver_sensor)
@@ -472,13 +588,16 @@ pub async fn check_state(rule: &Automation) -> Result<bool, Error> {
                 None => return Ok(false),
             };
 
-            let query = r#"
+            let query = format!(
+                r#"
                 SELECT adc_1, adc_2, adv_1, gpio_in_1, gpio_in_2, gpio_in_3, gpio_in_4, gpio_out_1, gpio_out_2
                 FROM uc300
-                WHERE dev_eui = $1
+                WHERE dev_eui = {}
                 ORDER BY id DESC
                 LIMIT 1
-            "#;
+            "#,
+                if cfg!(feature = "sqlite") { "?1" } else { "$1" }
+            );
 
             let last_row: Option<LastRow> = sql_query(query)
                 .bind::<Text, _>(receiver_sensor)
@@ -505,6 +624,18 @@ pub async fn check_state(rule: &Automation) -> Result<bool, Error> {
 }
 
 pub async fn execute_automation_action(rule: &Automation) -> Result<(), Error> {
+    let res = execute_action(rule).await;
+    ACTION_COUNTER
+        .get_or_create(&ActionLabels {
+            outcome: if res.is_ok() { "success" } else { "error" }.to_string(),
+        })
+        .inc();
+    let error = res.as_ref().err().map(|e| e.to_string());
+    webhook::automation_executed(rule, error.as_deref()).await;
+    res
+}
+
+async fn execute_action(rule: &Automation) -> Result<(), Error> {
     let receiver_sensor = rule
         .receiver_sensor
         .as_ref()
@@ -516,7 +647,11 @@ pub async fn execute_automation_action(rule: &Automation) -> Result<(), Error> {
         Some(t) => {
             return Ok(());
         }
-        None => return Err(Error::Validation("Missing receiver_device_type".to_string())),
+        None => {
+            return Err(Error::Validation(
+                "Missing receiver_device_type".to_string(),
+            ))
+        }
     };
 
     let action = rule
@@ -570,22 +705,18 @@ pub async fn execute_automation_action(rule: &Automation) -> Result<(), Error> {
         //     .send()
         //     .await
         //     .map_err(|e| Error::Validation(format!("HTTP error: {}", e)))?;
-
     }
 
     Ok(())
 }
 
-pub async fn enqueue_device_queue_item(
-    dev_eui: &str,
-    port: u32,
-    data: &[u8],
-) -> Result<(), Error> {
+pub async fn enqueue_device_queue_item(dev_eui: &str, port: u32, data: &[u8]) -> Result<(), Error> {
     let device_queue_item = DeviceQueueItem {
         dev_eui: dev_eui.to_string(),
         confirmed: true,
         f_port: port as u32,
         data: data.to_vec(),
+        priority: AUTOMATION_QUEUE_PRIORITY,
         ..Default::default()
     };
 
@@ -597,9 +728,7 @@ pub async fn enqueue_device_queue_item(
     Ok(())
 }
 
-pub async fn enqueue(
-    request: &EnqueueDeviceQueueItemRequest,
-) -> Result<(), Error> {
+pub async fn enqueue(request: &EnqueueDeviceQueueItemRequest) -> Result<(), Error> {
     let queue_item = match &request.queue_item {
         Some(q) => q,
         None => {
@@ -613,7 +742,9 @@ pub async fn enqueue(
 
     let dev_eui = &queue_item.dev_eui;
     if dev_eui.len() != 16 {
-        return Err(Error::Validation("dev_eui must be 16 hex chars".to_string()));
+        return Err(Error::Validation(
+            "dev_eui must be 16 hex chars".to_string(),
+        ));
     }
 
     // Fetch device and metadata
@@ -643,6 +774,17 @@ pub async fn enqueue(
         confirmed: queue_item.confirmed,
         f_port: queue_item.f_port as i16,
         data,
+        scheduled_at: queue_item.scheduled_at.and_then(|v| {
+            let v: std::time::SystemTime = v.try_into().ok()?;
+            Some(v.into())
+        }),
+        priority: i16::try_from(queue_item.priority)
+            .map_err(|_| Error::Validation("priority must be <= 32767".to_string()))?,
+        coalesce_key: if queue_item.coalesce_key.is_empty() {
+            None
+        } else {
+            Some(queue_item.coalesce_key.clone())
+        },
         // Optionally set these based on your gRPC message or leave as default
         ..Default::default()
     };
@@ -652,3 +794,46 @@ pub async fn enqueue(
 
     Ok(())
 }
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_get_rules_for_receiver() {
+        let _guard = test::prepare().await;
+
+        let mut conn = get_async_db_conn().await.unwrap();
+        for (receiver, is_active) in [
+            ("0102030405060708", true),
+            ("0102030405060708", false),
+            ("0202030405060708", true),
+        ] {
+            diesel::insert_into(automation_rules::table)
+                .values((
+                    automation_rules::receiver_sensor.eq(receiver),
+                    automation_rules::is_active.eq(is_active),
+                ))
+                .execute(&mut conn)
+                .await
+                .unwrap();
+        }
+
+        // only the active rules, the dev_eui is matched case-insensitive
+        let rules = get_rules_for_receiver("0102030405060708").await.unwrap();
+        assert_eq!(1, rules.len());
+        assert_eq!(Some(true), rules[0].is_active);
+        assert_eq!(
+            1,
+            get_rules_for_receiver("0102030405060708".to_uppercase().as_str())
+                .await
+                .unwrap()
+                .len()
+        );
+        assert!(get_rules_for_receiver("0302030405060708")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Double, Float, Integer, Nullable, Text, Timestamp};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::fields::sql_types::{Timestamptz, Uuid as SqlUuid};
+use super::schema::device_battery;
+use super::{error::Error, fields, get_async_db_conn};
+
+#[derive(Queryable, Insertable, AsChangeset, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = device_battery)]
+pub struct DeviceBattery {
+    pub dev_eui: String,
+    pub device_type_id: Option<i32>,
+    pub voltage: Option<f64>,
+    pub battery_level: Option<f32>,
+    pub discharge_per_day: Option<f32>,
+    pub days_remaining: Option<i32>,
+    pub low_battery_notified_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for DeviceBattery {
+    fn default() -> Self {
+        DeviceBattery {
+            dev_eui: String::new(),
+            device_type_id: None,
+            voltage: None,
+            battery_level: None,
+            discharge_per_day: None,
+            days_remaining: None,
+            low_battery_notified_at: None,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+// Daily average of the battery voltage (or percentage, depending on the model) as stored by the
+// model parsers in device_data.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct BatterySample {
+    #[diesel(sql_type = Text)]
+    pub dev_eui: String,
+
+    #[diesel(sql_type = Integer)]
+    pub device_type_id: i32,
+
+    #[diesel(sql_type = Timestamp)]
+    pub day: NaiveDateTime,
+
+    #[diesel(sql_type = Double)]
+    pub batv: f64,
+}
+
+// Battery level (percentage) as reported by the device through the DevStatusAns mac-command.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct DeviceStatusBattery {
+    #[diesel(sql_type = Text)]
+    pub dev_eui: String,
+
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub device_type_id: Option<i32>,
+
+    #[diesel(sql_type = Double)]
+    pub battery_level: f64,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+pub struct BatteryReportItem {
+    #[diesel(sql_type = Text)]
+    pub dev_eui: String,
+
+    #[diesel(sql_type = Text)]
+    pub device_name: String,
+
+    #[diesel(sql_type = Nullable<Text>)]
+    pub zone_name: Option<String>,
+
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub device_type_id: Option<i32>,
+
+    #[diesel(sql_type = Nullable<Double>)]
+    pub voltage: Option<f64>,
+
+    #[diesel(sql_type = Nullable<Float>)]
+    pub battery_level: Option<f32>,
+
+    #[diesel(sql_type = Nullable<Float>)]
+    pub discharge_per_day: Option<f32>,
+
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub days_remaining: Option<i32>,
+
+    #[diesel(sql_type = Bool)]
+    pub low_battery: bool,
+
+    #[diesel(sql_type = Timestamptz)]
+    pub updated_at: DateTime<Utc>,
+}
+
+// Creates or updates the battery state of the device. The low-battery notification timestamp is
+// not touched, use set_low_battery_notified for that.
+pub async fn upsert(state: &DeviceBattery) -> Result<DeviceBattery, Error> {
+    let b: DeviceBattery = diesel::insert_into(device_battery::table)
+        .values(state)
+        .on_conflict(device_battery::dev_eui)
+        .do_update()
+        .set((
+            device_battery::device_type_id.eq(&state.device_type_id),
+            device_battery::voltage.eq(&state.voltage),
+            device_battery::battery_level.eq(&state.battery_level),
+            device_battery::discharge_per_day.eq(&state.discharge_per_day),
+            device_battery::days_remaining.eq(&state.days_remaining),
+            device_battery::updated_at.eq(&state.updated_at),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, state.dev_eui.clone()))?;
+    Ok(b)
+}
+
+pub async fn set_low_battery_notified(
+    dev_eui: &str,
+    notified_at: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    diesel::update(device_battery::dsl::device_battery.find(dev_eui))
+        .set(device_battery::low_battery_notified_at.eq(notified_at))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(())
+}
+
+// Returns the daily battery averages of the given number of days, ordered by dev_eui and day.
+pub async fn get_samples(days: i32) -> Result<Vec<BatterySample>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT
+            dev_eui,
+            MAX(device_type_id) AS device_type_id,
+            datetime(submission_date, 'start of day') AS day,
+            AVG(batv) AS batv
+        FROM device_data
+        WHERE datetime(submission_date) > datetime('now', printf('-%d days', ?1))
+          AND batv IS NOT NULL
+          AND batv > 0
+        GROUP BY dev_eui, day
+        ORDER BY dev_eui, day
+        "#
+    } else {
+        r#"
+        SELECT
+            dev_eui,
+            MAX(device_type_id) AS device_type_id,
+            date_trunc('day', submission_date) AS day,
+            AVG(batv)::float8 AS batv
+        FROM device_data
+        WHERE submission_date > now() - make_interval(days => $1)
+          AND batv IS NOT NULL
+          AND batv > 0
+        GROUP BY dev_eui, day
+        ORDER BY dev_eui, day
+        "#
+    };
+
+    let samples = diesel::sql_query(query)
+        .bind::<Integer, _>(days)
+        .load::<BatterySample>(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, "battery samples".to_string()))?;
+
+    Ok(samples)
+}
+
+pub async fn get_device_status_levels() -> Result<Vec<DeviceStatusBattery>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let levels = diesel::sql_query(if cfg!(feature = "sqlite") {
+        r#"
+        SELECT
+            lower(hex(dev_eui)) AS dev_eui,
+            device_type AS device_type_id,
+            CAST(battery_level AS REAL) AS battery_level
+        FROM device
+        WHERE battery_level IS NOT NULL
+        "#
+    } else {
+        r#"
+        SELECT
+            encode(dev_eui, 'hex') AS dev_eui,
+            device_type AS device_type_id,
+            battery_level::float8 AS battery_level
+        FROM device
+        WHERE battery_level IS NOT NULL
+        "#
+    })
+    .load::<DeviceStatusBattery>(&mut conn)
+    .await
+    .map_err(|e| Error::from_diesel(e, "device battery levels".to_string()))?;
+
+    Ok(levels)
+}
+
+// Returns the ids of the users which have the zone of the device assigned, directly or through
+// the site of the zone.
+pub async fn get_zone_user_ids(dev_eui: &str) -> Result<Vec<Uuid>, Error> {
+    #[derive(QueryableByName)]
+    struct UserIdRow {
+        #[diesel(sql_type = SqlUuid)]
+        id: fields::Uuid,
+    }
+
+    let mut conn = get_async_db_conn().await?;
+
+    // SQLite has no arrays, the zone devices and user zone / site lists are JSON arrays.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT DISTINCT u.id
+        FROM "user" AS u
+        INNER JOIN zone AS z ON z.zone_id IN (SELECT value FROM json_each(u.zone_id_list))
+            OR z.site_id IN (SELECT value FROM json_each(u.site_id_list))
+        WHERE EXISTS (SELECT 1 FROM json_each(z.devices) AS zd WHERE zd.value = '\x' || ?1)
+        "#
+    } else {
+        r#"
+        SELECT DISTINCT u.id
+        FROM public.user AS u
+        INNER JOIN zone AS z ON z.zone_id = ANY(u.zone_id_list) OR z.site_id = ANY(u.site_id_list)
+        WHERE '\x' || $1 = ANY(z.devices)
+        "#
+    };
+
+    let rows: Vec<UserIdRow> = diesel::sql_query(query)
+        .bind::<Text, _>(dev_eui)
+        .load(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+
+    Ok(rows.into_iter().map(|r| r.id.into()).collect())
+}
+
+// Returns the battery report of the tenant devices, the devices with the lowest battery level
+// first. A device is flagged as low-battery when its level is at or below low_battery_level or
+// when it is expected to die within low_battery_days. When only_low is set, only the flagged
+// devices are returned.
+pub async fn get_report(
+    tenant_id: &Uuid,
+    low_battery_level: f32,
+    low_battery_days: i32,
+    only_low: bool,
+) -> Result<Vec<BatteryReportItem>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT * FROM (
+            SELECT
+                b.dev_eui,
+                d.name AS device_name,
+                (
+                    SELECT z.zone_name FROM zone AS z, json_each(z.devices) AS zd
+                    WHERE zd.value = '\x' || lower(hex(d.dev_eui)) LIMIT 1
+                ) AS zone_name,
+                b.device_type_id,
+                b.voltage,
+                b.battery_level,
+                b.discharge_per_day,
+                b.days_remaining,
+                COALESCE(b.battery_level <= ?2 OR b.days_remaining <= ?3, false) AS low_battery,
+                b.updated_at
+            FROM device_battery AS b
+            INNER JOIN device AS d ON lower(hex(d.dev_eui)) = b.dev_eui
+            WHERE d.tenant_id = ?1
+        ) AS r
+        WHERE ?4 = false OR r.low_battery = true
+        ORDER BY r.battery_level ASC NULLS LAST, r.dev_eui
+        "#
+    } else {
+        r#"
+        SELECT * FROM (
+            SELECT
+                b.dev_eui,
+                d.name AS device_name,
+                (SELECT z.zone_name FROM zone AS z WHERE d.dev_eui::text = ANY(z.devices) LIMIT 1) AS zone_name,
+                b.device_type_id,
+                b.voltage,
+                b.battery_level,
+                b.discharge_per_day,
+                b.days_remaining,
+                COALESCE(b.battery_level <= $2 OR b.days_remaining <= $3, false) AS low_battery,
+                b.updated_at
+            FROM device_battery AS b
+            INNER JOIN device AS d ON d.dev_eui::text = '\x' || b.dev_eui
+            WHERE d.tenant_id = $1
+        ) AS r
+        WHERE $4 = false OR r.low_battery = true
+        ORDER BY r.battery_level ASC NULLS LAST, r.dev_eui
+        "#
+    };
+
+    let items = diesel::sql_query(query)
+        .bind::<SqlUuid, _>(fields::Uuid::from(tenant_id))
+        .bind::<Float, _>(low_battery_level)
+        .bind::<Integer, _>(low_battery_days)
+        .bind::<Bool, _>(only_low)
+        .load::<BatteryReportItem>(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+
+    Ok(items)
+}
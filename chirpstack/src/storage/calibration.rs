@@ -0,0 +1,602 @@
+use std::collections::HashMap;
+
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde_json::Value;
+use tracing::info;
+
+use super::device::Device;
+use super::error::Error;
+use super::schema::device_calibration;
+use super::{fields, get_async_db_conn};
+use lrwn::EUI64;
+
+// Kinds to which the temperature and humidity offsets of the device (the fields that preceded
+// the calibration records) apply, by device type. These are only used for the kinds for which
+// the device has no calibration record.
+const LEGACY_KINDS: &[(i32, &str, &str)] = &[
+    (1, "air_temperature", "air_humidity"),
+    (2, "sol_temperature", "sol_water"),
+    (7, "air_temperature", "air_humidity"),
+    (8, "air_temperature", "air_humidity"),
+    (9, "sol_temperature", ""),
+    (12, "air_temperature", "air_humidity"),
+    (13, "air_temperature", "air_humidity"),
+    (20, "air_temperature", ""),
+    (35, "air_temperature", "air_humidity"),
+    (36, "air_temperature", ""),
+];
+
+// Keys of the decoded object holding the readings of the built-in device types, with the
+// measurement kind of which the calibration applies. As the sensor-data writer, the alarms and
+// the automations use different keys for some device types, all of these are listed. For the
+// other device types, the calibration of the (flattened) object key applies, matching the
+// measurements of the device-profile.
+const OBJECT_KINDS: &[(i32, &str, &str)] = &[
+    (1, "temp_c_sht", "air_temperature"),
+    (1, "hum_sht", "air_humidity"),
+    (1, "temperature", "air_temperature"),
+    (1, "humidity", "air_humidity"),
+    (1, "Temperature", "air_temperature"),
+    (1, "Humidity", "air_humidity"),
+    (2, "temp_SOIL", "sol_temperature"),
+    (2, "water_SOIL", "sol_water"),
+    (2, "temperature_soil", "sol_temperature"),
+    (2, "water_soil", "sol_water"),
+    (7, "TempC_SHT", "air_temperature"),
+    (7, "Hum_SHT", "air_humidity"),
+    (8, "TempC_SHT", "air_temperature"),
+    (8, "Hum_SHT", "air_humidity"),
+    (9, "TEMP_SOIL", "sol_temperature"),
+    (12, "temperature", "air_temperature"),
+    (12, "humidity", "air_humidity"),
+    (12, "Temperature", "air_temperature"),
+    (12, "Humidity", "air_humidity"),
+    (13, "temperature", "air_temperature"),
+    (13, "humidity", "air_humidity"),
+    (20, "temperature", "air_temperature"),
+    (33, "distance", "distance"),
+    (33, "Distance", "distance"),
+    (35, "temperature", "air_temperature"),
+    (35, "humidity", "air_humidity"),
+    (36, "temperature1", "air_temperature"),
+    (36, "temperature2", "sol_temperature"),
+    (37, "Distance", "distance"),
+];
+
+// Calibration of a device measurement. The (optional) curve maps the raw value to the
+// reference value by linear interpolation between the points, after which the gain and offset
+// are applied. A calibration applies from effective_from until it is superseded by a
+// calibration of the same kind with a later effective_from.
+#[derive(Queryable, PartialEq, Debug, Clone)]
+#[diesel(table_name = device_calibration)]
+pub struct Calibration {
+    pub id: i32,
+    pub dev_eui: EUI64,
+    pub kind: String,
+    pub offset: f64,
+    pub gain: f64,
+    pub points: fields::CalibrationPoints,
+    pub effective_from: DateTime<Utc>,
+    pub certificate: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        Calibration {
+            id: 0,
+            dev_eui: EUI64::default(),
+            kind: "".into(),
+            offset: 0.0,
+            gain: 1.0,
+            points: fields::CalibrationPoints::default(),
+            effective_from: now,
+            certificate: "".into(),
+            created_at: now,
+        }
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = device_calibration)]
+struct NewCalibration {
+    dev_eui: EUI64,
+    kind: String,
+    offset: f64,
+    gain: f64,
+    points: fields::CalibrationPoints,
+    effective_from: DateTime<Utc>,
+    certificate: String,
+    created_at: DateTime<Utc>,
+}
+
+impl Calibration {
+    fn validate(&self) -> Result<(), Error> {
+        if self.kind.trim().is_empty() {
+            return Err(Error::Validation("Calibration kind cannot be empty".into()));
+        }
+        if self.certificate.trim().is_empty() {
+            return Err(Error::Validation(
+                "Calibration certificate reference cannot be empty".into(),
+            ));
+        }
+        if !self.offset.is_finite() || !self.gain.is_finite() || self.gain == 0.0 {
+            return Err(Error::Validation(
+                "Calibration gain must be non-zero and offset must be finite".into(),
+            ));
+        }
+        if self.points.len() == 1 {
+            return Err(Error::Validation(
+                "Calibration curve must have at least two points".into(),
+            ));
+        }
+        for (i, p) in self.points.iter().enumerate() {
+            if !p.raw.is_finite() || !p.reference.is_finite() {
+                return Err(Error::Validation(
+                    "Calibration curve points must be finite".into(),
+                ));
+            }
+            if i > 0 && p.raw <= self.points[i - 1].raw {
+                return Err(Error::Validation(
+                    "Calibration curve points must be ordered by raw value, without duplicates"
+                        .into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Returns the calibrated value. Raw values outside the curve are extrapolated using the
+    // first or last segment.
+    pub fn apply(&self, v: f64) -> f64 {
+        let v = match self.points.len() {
+            0 | 1 => v,
+            n => {
+                let i = self.points[1..n - 1]
+                    .iter()
+                    .position(|p| v < p.raw)
+                    .unwrap_or(n - 2);
+                let (a, b) = (self.points[i], self.points[i + 1]);
+                a.reference + (v - a.raw) * (b.reference - a.reference) / (b.raw - a.raw)
+            }
+        };
+        v * self.gain + self.offset
+    }
+}
+
+// Calibrations of a device by measurement kind, effective at a given time. These are loaded
+// once for each uplink and applied to the readings before these are stored and before these
+// are evaluated by the alarms and automations.
+#[derive(Debug, Clone, Default)]
+pub struct Calibrations(HashMap<String, Calibration>);
+
+impl Calibrations {
+    // Returns the calibrated value of the given measurement kind, or the value as-is when there
+    // is no calibration for the kind.
+    pub fn apply(&self, kind: &str, v: f64) -> f64 {
+        match self.0.get(kind) {
+            Some(c) => c.apply(v),
+            None => v,
+        }
+    }
+
+    // Returns the decoded object of the device, with the readings calibrated. This is done once
+    // for each uplink, before the readings are stored and evaluated by the alarms and the
+    // automations.
+    pub fn apply_object(&self, device_type: Option<i32>, object: &Value) -> Value {
+        let mut out = object.clone();
+        let fields = match &mut out {
+            Value::Object(v) if !self.0.is_empty() => v,
+            _ => return out,
+        };
+
+        let device_type = device_type.unwrap_or_default();
+        let kinds: Vec<(&str, &str)> = OBJECT_KINDS
+            .iter()
+            .filter(|(t, _, _)| *t == device_type)
+            .map(|(_, key, kind)| (*key, *kind))
+            .collect();
+
+        if kinds.is_empty() {
+            for (k, v) in fields.iter_mut() {
+                self.apply_nested(k, v);
+            }
+        } else {
+            for (key, kind) in kinds {
+                if is_missing_reading(device_type, key, object) {
+                    continue;
+                }
+                if let Some(v) = fields.get_mut(key) {
+                    self.apply_value(kind, v);
+                }
+            }
+        }
+
+        out
+    }
+
+    fn apply_nested(&self, key: &str, v: &mut Value) {
+        match v {
+            Value::Object(fields) => {
+                for (k, v) in fields.iter_mut() {
+                    self.apply_nested(&format!("{}_{}", key, k), v);
+                }
+            }
+            Value::Array(values) => {
+                for (i, v) in values.iter_mut().enumerate() {
+                    self.apply_nested(&format!("{}_{}", key, i), v);
+                }
+            }
+            v => self.apply_value(key, v),
+        }
+    }
+
+    // Calibrates the numeric value of the given kind. Codecs often return numeric values as
+    // strings (e.g. "21.50"), these are kept as string with the same number of decimals (if
+    // any).
+    fn apply_value(&self, kind: &str, v: &mut Value) {
+        if !self.0.contains_key(kind) {
+            return;
+        }
+
+        match v {
+            Value::Number(n) => {
+                if let Some(n) = n
+                    .as_f64()
+                    .and_then(|n| serde_json::Number::from_f64(self.apply(kind, n)))
+                {
+                    *v = Value::Number(n);
+                }
+            }
+            Value::String(s) => {
+                let s = s.trim();
+                if let Ok(n) = s.parse::<f64>() {
+                    *v = Value::String(match s.split_once('.') {
+                        Some((_, d)) => format!("{:.*}", d.len(), self.apply(kind, n)),
+                        None => self.apply(kind, n).to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn insert_legacy(&mut self, kind: &str, offset: Option<&fields::BigDecimal>) {
+        let offset = offset.and_then(|v| v.to_f64()).unwrap_or(0.0);
+        if kind.is_empty() || offset == 0.0 {
+            return;
+        }
+        self.0.entry(kind.to_string()).or_insert(Calibration {
+            kind: kind.to_string(),
+            offset,
+            ..Default::default()
+        });
+    }
+}
+
+// Returns true when the value of the object key is a sentinel for a missing reading (e.g. a
+// disconnected probe). These are not calibrated, such that these are still skipped by the
+// sensor-data writer and the alarms.
+fn is_missing_reading(device_type: i32, key: &str, object: &Value) -> bool {
+    let v = match object.get(key) {
+        Some(v) => v,
+        None => return false,
+    };
+
+    match device_type {
+        1 => v.as_str() == Some("-45"),
+        2 | 9 => v.as_str() == Some("0.00"),
+        12 => {
+            object.get("temperature").and_then(|v| v.as_f64()) == Some(0.0)
+                && object.get("humidity").and_then(|v| v.as_f64()) == Some(0.0)
+        }
+        35 => v.as_f64() == Some(0.0),
+        33 => v.as_f64().map(|v| v <= 0.0).unwrap_or_default(),
+        _ => false,
+    }
+}
+
+pub async fn create(c: Calibration) -> Result<Calibration, Error> {
+    c.validate()?;
+
+    let new = NewCalibration {
+        dev_eui: c.dev_eui,
+        kind: c.kind,
+        offset: c.offset,
+        gain: c.gain,
+        points: c.points,
+        effective_from: c.effective_from,
+        certificate: c.certificate,
+        created_at: Utc::now(),
+    };
+
+    let c: Calibration = diesel::insert_into(device_calibration::table)
+        .values(&new)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, new.dev_eui.to_string()))?;
+    info!(id = c.id, dev_eui = %c.dev_eui, kind = %c.kind, certificate = %c.certificate, "Device calibration created");
+    Ok(c)
+}
+
+pub async fn get(id: i32) -> Result<Calibration, Error> {
+    let c = device_calibration::dsl::device_calibration
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(c)
+}
+
+pub async fn delete(id: i32) -> Result<(), Error> {
+    let ra = diesel::delete(device_calibration::dsl::device_calibration.find(id))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    if ra == 0 {
+        return Err(Error::NotFound(id.to_string()));
+    }
+    info!(id = id, "Device calibration deleted");
+    Ok(())
+}
+
+pub async fn list(dev_eui: &EUI64) -> Result<Vec<Calibration>, Error> {
+    let items = device_calibration::dsl::device_calibration
+        .filter(device_calibration::dsl::dev_eui.eq(dev_eui))
+        .order_by((
+            device_calibration::dsl::kind,
+            device_calibration::dsl::effective_from,
+        ))
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(items)
+}
+
+// Returns the calibrations of the device which are effective at the given time. For the kinds
+// without calibration record, the temperature and humidity offsets of the device are used.
+pub async fn get_effective(device: &Device, at: DateTime<Utc>) -> Result<Calibrations, Error> {
+    let items: Vec<Calibration> = device_calibration::dsl::device_calibration
+        .filter(device_calibration::dsl::dev_eui.eq(&device.dev_eui))
+        .filter(device_calibration::dsl::effective_from.le(at))
+        .order_by((
+            device_calibration::dsl::kind,
+            device_calibration::dsl::effective_from.desc(),
+        ))
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, device.dev_eui.to_string()))?;
+
+    let mut out = Calibrations::default();
+    for c in items {
+        out.0.entry(c.kind.clone()).or_insert(c);
+    }
+
+    if let Some((_, temperature, humidity)) = LEGACY_KINDS
+        .iter()
+        .find(|(t, _, _)| Some(*t) == device.device_type)
+    {
+        out.insert_legacy(temperature, device.temperature_calibration.as_ref());
+        out.insert_legacy(humidity, device.humadity_calibration.as_ref());
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage;
+    use crate::test;
+    use chrono::Duration;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    fn curve(points: &[(f64, f64)]) -> fields::CalibrationPoints {
+        fields::CalibrationPoints::new(
+            points
+                .iter()
+                .map(|(raw, reference)| fields::CalibrationPoint {
+                    raw: *raw,
+                    reference: *reference,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_apply() {
+        let c = Calibration {
+            offset: 0.5,
+            gain: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(20.5, c.apply(10.0));
+
+        let c = Calibration {
+            points: curve(&[(0.0, 1.0), (10.0, 11.0), (20.0, 31.0)]),
+            ..Default::default()
+        };
+        assert_eq!(1.0, c.apply(0.0));
+        assert_eq!(6.0, c.apply(5.0));
+        assert_eq!(21.0, c.apply(15.0));
+        assert_eq!(31.0, c.apply(20.0));
+
+        // extrapolation
+        assert_eq!(-1.0, c.apply(-2.0));
+        assert_eq!(41.0, c.apply(25.0));
+
+        let c = Calibration {
+            points: curve(&[(0.0, 0.0), (10.0, 20.0)]),
+            offset: -1.0,
+            ..Default::default()
+        };
+        assert_eq!(9.0, c.apply(5.0));
+    }
+
+    #[test]
+    fn test_validate() {
+        let c = Calibration {
+            kind: "air_temperature".into(),
+            certificate: "CERT-2025-001".into(),
+            ..Default::default()
+        };
+        assert!(c.validate().is_ok());
+
+        assert!(Calibration {
+            certificate: "".into(),
+            ..c.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(Calibration {
+            gain: 0.0,
+            ..c.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(Calibration {
+            points: curve(&[(0.0, 0.0)]),
+            ..c.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(Calibration {
+            points: curve(&[(10.0, 10.0), (0.0, 0.0)]),
+            ..c.clone()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_apply_object() {
+        let cals = Calibrations(
+            [
+                ("air_temperature", 0.5, 1.0),
+                ("distance", 10.0, 1.0),
+                ("nested_level", 0.0, 2.0),
+            ]
+            .into_iter()
+            .map(|(kind, offset, gain)| {
+                (
+                    kind.to_string(),
+                    Calibration {
+                        kind: kind.to_string(),
+                        offset,
+                        gain,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect(),
+        );
+
+        // built-in device type, numeric strings keep their number of decimals
+        let object = json!({"temp_c_sht": "21.50", "temperature": "21", "hum_sht": "45.10"});
+        assert_eq!(
+            json!({"temp_c_sht": "22.00", "temperature": "21.5", "hum_sht": "45.10"}),
+            cals.apply_object(Some(1), &object)
+        );
+        assert_eq!(
+            json!({"temperature": 21.0, "humidity": 50.0}),
+            cals.apply_object(Some(12), &json!({"temperature": 20.5, "humidity": 50.0}))
+        );
+        assert_eq!(
+            json!({"Distance": 1210.0}),
+            cals.apply_object(Some(37), &json!({"Distance": 1200}))
+        );
+
+        // missing readings are not calibrated
+        assert_eq!(
+            json!({"temp_c_sht": "-45", "hum_sht": "0"}),
+            cals.apply_object(Some(1), &json!({"temp_c_sht": "-45", "hum_sht": "0"}))
+        );
+        assert_eq!(
+            json!({"temperature": 0.0, "humidity": 0.0}),
+            cals.apply_object(Some(12), &json!({"temperature": 0.0, "humidity": 0.0}))
+        );
+        assert_eq!(
+            json!({"distance": 0}),
+            cals.apply_object(Some(33), &json!({"distance": 0}))
+        );
+
+        // other device types use the (flattened) object key
+        assert_eq!(
+            json!({"air_temperature": 20.5, "nested": {"level": 4.0}, "temperature": 20.0}),
+            cals.apply_object(
+                Some(40),
+                &json!({"air_temperature": 20.0, "nested": {"level": 2}, "temperature": 20.0})
+            )
+        );
+        assert_eq!(
+            json!({"temperature": 20.0}),
+            Calibrations::default().apply_object(Some(12), &json!({"temperature": 20.0}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_calibration() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let mut d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+        d.device_type = Some(12);
+        d.temperature_calibration = Some(bigdecimal::BigDecimal::from_str("0.5").unwrap().into());
+        d.humadity_calibration = Some(bigdecimal::BigDecimal::from_str("-2").unwrap().into());
+
+        let now = Utc::now();
+        let c1 = create(Calibration {
+            dev_eui: d.dev_eui,
+            kind: "air_temperature".into(),
+            offset: 1.0,
+            effective_from: now - Duration::days(30),
+            certificate: "CERT-1".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let c2 = create(Calibration {
+            dev_eui: d.dev_eui,
+            kind: "air_temperature".into(),
+            offset: 2.0,
+            effective_from: now - Duration::days(1),
+            certificate: "CERT-2".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(c1, get(c1.id).await.unwrap());
+        assert_eq!(
+            vec![c1.clone(), c2.clone()],
+            list(&d.dev_eui).await.unwrap()
+        );
+
+        // the latest effective record is used, the humidity uses the legacy offset
+        let cals = get_effective(&d, now).await.unwrap();
+        assert_eq!(22.0, cals.apply("air_temperature", 20.0));
+        assert_eq!(48.0, cals.apply("air_humidity", 50.0));
+        assert_eq!(400.0, cals.apply("co2_ppm", 400.0));
+
+        let cals = get_effective(&d, now - Duration::days(7)).await.unwrap();
+        assert_eq!(21.0, cals.apply("air_temperature", 20.0));
+
+        // before the first record, the legacy offset is used
+        let cals = get_effective(&d, now - Duration::days(60)).await.unwrap();
+        assert_eq!(20.5, cals.apply("air_temperature", 20.0));
+
+        delete(c2.id).await.unwrap();
+        assert!(delete(c2.id).await.is_err());
+        assert_eq!(vec![c1], list(&d.dev_eui).await.unwrap());
+    }
+}
@@ -1,26 +1,73 @@
 use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive}; // ✅ use correct BigDecimal crate
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use diesel::prelude::*;
 // use diesel::query_dsl::methods::OnConflictDsl;
-use diesel::upsert::excluded;
 use diesel::Identifiable;
 use diesel::Insertable;
 use diesel::Queryable;
 use diesel_async::RunQueryDsl;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
 use serde::Deserialize;
 use serde_json::to_string_pretty;
 use serde_json::Value;
+use std::num::{ParseFloatError, ParseIntError};
 use tracing::info;
 
+use crate::monitoring::prometheus;
 use crate::storage::device::Device;
+use crate::storage::device_profile;
+use crate::storage::measurement::{self, IntoValue};
+use crate::storage::{db_transaction, fields, get_async_db_conn, AsyncDbConnection};
 // ⚠️ no `use crate::storage::fields::*;` here to avoid name conflicts
-use crate::storage::schema::{
-    am103, dds45lb, device_data_2025, device_data_latest, em400mud, ltc2lb,
-};
+use crate::storage::schema::{am103, dds45lb, device_data, em400mud, ltc2lb};
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct DeviceDataLabels {
+    device_type: String,
+    status: String,
+}
+
+lazy_static! {
+    static ref DEVICE_DATA_WRITE_COUNTER: Family<DeviceDataLabels, Counter> = {
+        let counter = Family::<DeviceDataLabels, Counter>::default();
+        prometheus::register(
+            "device_data_write_count",
+            "Number of decoded uplinks handled by the sensor-data writer by device type and status",
+            counter.clone(),
+        );
+        counter
+    };
+}
+
+// Outcome of writing the decoded uplink to the sensor-data tables.
+enum WriteStatus {
+    Inserted,
+    // The payload contains a sentinel value (e.g. a disconnected probe).
+    Skipped,
+    Unsupported,
+}
+
+impl WriteStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            WriteStatus::Inserted => "inserted",
+            WriteStatus::Skipped => "skipped",
+            WriteStatus::Unsupported => "unsupported",
+        }
+    }
+}
+
+// Returns the value as the numeric field of the backend, numeric columns are stored as double by
+// SQLite.
+fn numeric(v: &Option<BigDecimal>) -> Option<fields::BigDecimal> {
+    v.clone().map(fields::BigDecimal::from)
+}
 
 #[derive(Debug, Clone, Queryable, QueryableByName, Identifiable)]
-#[diesel(table_name = device_data_2025)]
-pub struct DeviceData2025 {
+#[diesel(table_name = device_data)]
+pub struct DeviceData {
     pub id: i32,
     pub dev_eui: String,
     pub device_type_id: i32,
@@ -29,7 +76,7 @@ pub struct DeviceData2025 {
     pub sol_temperature: Option<rust_decimal::Decimal>,
     pub sol_water: Option<rust_decimal::Decimal>,
     pub sol_conduct_soil: Option<rust_decimal::Decimal>,
-    pub submission_date: Option<NaiveDateTime>,
+    pub submission_date: NaiveDateTime,
     pub water_leak_status: Option<i32>,
     pub water_leak_times: Option<i32>,
     pub last_water_leak_duration: Option<i32>,
@@ -53,12 +100,12 @@ pub struct DeviceData2025 {
 }
 
 #[derive(Debug, Insertable)]
-#[diesel(table_name = device_data_2025)]
-pub struct NewDeviceData2025<'a> {
+#[diesel(table_name = device_data)]
+pub struct NewDeviceData<'a> {
     pub dev_eui: &'a str,
-    pub air_temperature: Option<BigDecimal>,
-    pub air_humidity: Option<BigDecimal>,
-    pub batv: Option<BigDecimal>,
+    pub air_temperature: Option<fields::BigDecimal>,
+    pub air_humidity: Option<fields::BigDecimal>,
+    pub batv: Option<fields::BigDecimal>,
     // pub org_id: i32,
     pub device_type_id: i32,
 }
@@ -246,7 +293,7 @@ pub struct DDS45LB {
     #[serde(rename = "Bat")]
     pub battery: f32,
     #[serde(rename = "Distance")]
-    pub distance: i64,
+    pub distance: f64,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -261,7 +308,7 @@ pub struct LTC2LB {
 pub struct EM400MUD {
     #[serde(rename = "battery")]
     pub battery: i64,
-    pub distance: i64,
+    pub distance: f64,
     pub position: Option<String>,
     pub temperature: f32,
 }
@@ -303,12 +350,51 @@ pub struct EM300ZLDJSON {
     pub water_leak: i32,
 }
 
+// Writes the decoded (and calibrated) uplink, received at the given time, to the sensor-data
+// tables. All the values are written in a single transaction, as the uplink task is retried on
+// error.
 pub async fn write_data_from_object_json(
-    conn: &mut diesel_async::AsyncPgConnection,
     device: &Device,
     object_json: &Value,
-    // org_id: i64,
+    time: DateTime<Utc>,
 ) -> anyhow::Result<()> {
+    let mut c = get_async_db_conn().await?;
+    let res = db_transaction::<WriteStatus, anyhow::Error, _>(&mut c, |c| {
+        Box::pin(async move { write_data(c, device, object_json, time).await })
+    })
+    .await;
+
+    DEVICE_DATA_WRITE_COUNTER
+        .get_or_create(&DeviceDataLabels {
+            device_type: device.device_type.unwrap_or_default().to_string(),
+            status: get_write_status(&res).to_string(),
+        })
+        .inc();
+
+    res.map(|_| ())
+}
+
+// Returns the status label of the write_data_from_object_json outcome.
+fn get_write_status(res: &anyhow::Result<WriteStatus>) -> &'static str {
+    match res {
+        Ok(v) => v.as_str(),
+        Err(e) if is_parse_error(e) => "parse_error",
+        Err(_) => "error",
+    }
+}
+
+// Returns true when the error is caused by a payload which could not be parsed, rather than
+// by the storage.
+fn is_parse_error(e: &anyhow::Error) -> bool {
+    e.is::<serde_json::Error>() || e.is::<ParseFloatError>() || e.is::<ParseIntError>()
+}
+
+async fn write_data(
+    conn: &mut AsyncDbConnection,
+    device: &Device,
+    object_json: &Value,
+    time: DateTime<Utc>,
+) -> anyhow::Result<WriteStatus> {
     match to_string_pretty(device) {
         Ok(json) => info!("Device object:\n{}", json),
         Err(e) => info!("Failed to serialize device object: {}", e),
@@ -318,55 +404,44 @@ pub async fn write_data_from_object_json(
             let parsed: LSN50V2JSON = serde_json::from_value(object_json.clone())?;
         
             if parsed.temp_c_sht == "-45" {
-                return Ok(()); // skip invalid reading
+                return Ok(WriteStatus::Skipped); // skip invalid reading
             }
         
             let temp_raw = parsed.temp_c_sht.parse::<f32>()?;
             let hum_raw = parsed.hum_sht.parse::<f32>()?;
         
-            let temp_cal = device.temperature_calibration.as_ref().and_then(|v| v.to_f32()).unwrap_or(0.0);
-            let hum_cal = device.humadity_calibration.as_ref().and_then(|v| v.to_f32()).unwrap_or(0.0);
-        
-            let temp = temp_raw + temp_cal;
-            let hum = hum_raw + hum_cal;
+            let temp = temp_raw;
+            let hum = hum_raw;
             let dev_eui_string = device.dev_eui.to_string();
         
             let air_temp = BigDecimal::from_f32(temp);
             let air_hum = BigDecimal::from_f32(hum);
             let batv = BigDecimal::from_f32(parsed.batv);
         
-            // Insert device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::air_temperature.eq(air_temp.clone()),
-                    device_data_2025::air_humidity.eq(air_hum.clone()),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::air_temperature.eq(numeric(&air_temp)),
+                    device_data::air_humidity.eq(numeric(&air_hum)),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(1)),
+                    device_data::submission_date.eq(time.naive_utc()),
                 ))
                 .execute(conn)
                 .await?;
         
-            // Upsert device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::air_temperature.eq(air_temp.clone()),
-                    device_data_latest::air_humidity.eq(air_hum.clone()),
-                    device_data_latest::batv.eq(batv.clone()),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::air_temperature.eq(excluded(device_data_latest::air_temperature)),
-                    device_data_latest::air_humidity.eq(excluded(device_data_latest::air_humidity)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id.eq(excluded(device_data_latest::device_type_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("air_temperature", air_temp.clone().into_value()),
+                    ("air_humidity", air_hum.clone().into_value()),
+                    ("batv", batv.clone().into_value()),
+                ],
+            )
+            .await?;
         }
         
         Some(2) => {
@@ -376,52 +451,42 @@ pub async fn write_data_from_object_json(
                 let temp_raw = parsed.temp_soil.parse::<f32>()?;
                 let water_raw = parsed.water_soil.parse::<f32>()?;
         
-                let temp_cal = device.temperature_calibration.as_ref().and_then(|v| v.to_f32()).unwrap_or(0.0);
-                let hum_cal = device.humadity_calibration.as_ref().and_then(|v| v.to_f32()).unwrap_or(0.0);
-        
-                let temp = BigDecimal::from_f32(temp_raw + temp_cal);
-                let water = BigDecimal::from_f32(water_raw + hum_cal);
+                let temp = BigDecimal::from_f32(temp_raw);
+                let water = BigDecimal::from_f32(water_raw);
                 let conduct = BigDecimal::from_f32(parsed.conduct_soil);
                 let batv = BigDecimal::from_f32(parsed.battery);
                 let dev_eui_string = device.dev_eui.to_string();
         
-                // Insert device_data_2025
-                diesel::insert_into(device_data_2025::table)
+                // Insert device_data
+                diesel::insert_into(device_data::table)
                     .values((
-                        device_data_2025::dev_eui.eq(&dev_eui_string),
-                        device_data_2025::sol_temperature.eq(temp.clone()),
-                        device_data_2025::sol_water.eq(water.clone()),
-                        device_data_2025::sol_conduct_soil.eq(conduct.clone()),
-                        device_data_2025::batv.eq(batv.clone()),
-                        device_data_2025::device_type_id.eq(device.device_type.unwrap_or(2)),
+                        device_data::dev_eui.eq(&dev_eui_string),
+                        device_data::sol_temperature.eq(numeric(&temp)),
+                        device_data::sol_water.eq(numeric(&water)),
+                        device_data::sol_conduct_soil.eq(numeric(&conduct)),
+                        device_data::batv.eq(numeric(&batv)),
+                        device_data::device_type_id.eq(device.device_type.unwrap_or(2)),
+                        device_data::submission_date.eq(time.naive_utc()),
                     ))
                     .execute(conn)
                     .await?;
         
-                // Upsert device_data_latest
-                diesel::insert_into(device_data_latest::table)
-                    .values((
-                        device_data_latest::dev_eui.eq(&dev_eui_string),
-                        device_data_latest::sol_temperature.eq(temp),
-                        device_data_latest::sol_water.eq(water),
-                        device_data_latest::sol_conduct_soil.eq(conduct),
-                        device_data_latest::batv.eq(batv),
-                        device_data_latest::device_type_id.eq(device.device_type.unwrap_or(2)),
-                    ))
-                    .on_conflict(device_data_latest::dev_eui)
-                    .do_update()
-                    .set((
-                        device_data_latest::sol_temperature.eq(excluded(device_data_latest::sol_temperature)),
-                        device_data_latest::sol_water.eq(excluded(device_data_latest::sol_water)),
-                        device_data_latest::sol_conduct_soil.eq(excluded(device_data_latest::sol_conduct_soil)),
-                        device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                        device_data_latest::device_type_id.eq(excluded(device_data_latest::device_type_id)),
-                        device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                    ))
-                    .execute(conn)
-                    .await?;
+                measurement::save_values(
+                    conn,
+                    device.dev_eui,
+                    time,
+                    &[
+                        ("sol_temperature", temp.into_value()),
+                        ("sol_water", water.into_value()),
+                        ("sol_conduct_soil", conduct.into_value()),
+                        ("batv", batv.into_value()),
+                    ],
+                )
+                .await?;
         
                 info!("Inserted LSE01 data for dev_eui={}", dev_eui_string);
+            } else {
+                return Ok(WriteStatus::Skipped);
             }
         }
         
@@ -429,36 +494,35 @@ pub async fn write_data_from_object_json(
             let parsed: LDS01JSON = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
         
-            diesel::insert_into(device_data_2025::table)
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::door_open_status.eq(parsed.door_status as i32),
-                    device_data_2025::door_open_times.eq(parsed.door_open_times as i32),
-                    device_data_2025::last_door_open_duration.eq(parsed.last_door_open_duration as i32),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(3)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::door_open_status.eq(parsed.door_status as i32),
+                    device_data::door_open_times.eq(parsed.door_open_times as i32),
+                    device_data::last_door_open_duration.eq(parsed.last_door_open_duration as i32),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(3)),
+                    device_data::submission_date.eq(time.naive_utc()),
                 ))
                 .execute(conn)
                 .await?;
         
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::door_open_status.eq(parsed.door_status as i32),
-                    device_data_latest::door_open_times.eq(parsed.door_open_times as i32),
-                    device_data_latest::last_door_open_duration.eq(parsed.last_door_open_duration as i32),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(3)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::door_open_status.eq(excluded(device_data_latest::door_open_status)),
-                    device_data_latest::door_open_times.eq(excluded(device_data_latest::door_open_times)),
-                    device_data_latest::last_door_open_duration.eq(excluded(device_data_latest::last_door_open_duration)),
-                    device_data_latest::device_type_id.eq(excluded(device_data_latest::device_type_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("door_open_status", (parsed.door_status as i32).into_value()),
+                    (
+                        "door_open_times",
+                        (parsed.door_open_times as i32).into_value(),
+                    ),
+                    (
+                        "last_door_open_duration",
+                        (parsed.last_door_open_duration as i32).into_value(),
+                    ),
+                ],
+            )
+            .await?;
         
             info!("Inserted LDS01 data for dev_eui={}", dev_eui_string);
         }
@@ -468,39 +532,34 @@ pub async fn write_data_from_object_json(
             let batv = BigDecimal::from_f32(parsed.battery);
             let dev_eui_string = device.dev_eui.to_string();
         
-            diesel::insert_into(device_data_2025::table)
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::water_leak_status.eq(parsed.water_status),
-                    device_data_2025::water_leak_times.eq(parsed.water_leak_times),
-                    device_data_2025::last_water_leak_duration.eq(parsed.last_water_leak_duration),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(4)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::water_leak_status.eq(parsed.water_status),
+                    device_data::water_leak_times.eq(parsed.water_leak_times),
+                    device_data::last_water_leak_duration.eq(parsed.last_water_leak_duration),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(4)),
+                    device_data::submission_date.eq(time.naive_utc()),
                 ))
                 .execute(conn)
                 .await?;
         
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::water_leak_status.eq(parsed.water_status),
-                    device_data_latest::water_leak_times.eq(parsed.water_leak_times),
-                    device_data_latest::last_water_leak_duration.eq(parsed.last_water_leak_duration),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(4)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::water_leak_status.eq(excluded(device_data_latest::water_leak_status)),
-                    device_data_latest::water_leak_times.eq(excluded(device_data_latest::water_leak_times)),
-                    device_data_latest::last_water_leak_duration.eq(excluded(device_data_latest::last_water_leak_duration)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id.eq(excluded(device_data_latest::device_type_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("water_leak_status", parsed.water_status.into_value()),
+                    ("water_leak_times", parsed.water_leak_times.into_value()),
+                    (
+                        "last_water_leak_duration",
+                        parsed.last_water_leak_duration.into_value(),
+                    ),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
         
             info!("Inserted LWL01 water leak data for dev_eui={}", dev_eui_string);
         }
@@ -508,36 +567,23 @@ pub async fn write_data_from_object_json(
             let parsed: LT22222L = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    // device_data_latest::org_id.eq(device.organization_id.unwrap_or(1)),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(6)),
-                    device_data_latest::ro1_status.eq(parsed.ro1_status),
-                    device_data_latest::ro2_status.eq(parsed.ro2_status),
-                    device_data_latest::gpio_in_1.eq(&parsed.gpio_in_1),
-                    device_data_latest::gpio_in_2.eq(&parsed.gpio_in_2),
-                    device_data_latest::gpio_out_1.eq(&parsed.gpio_out_1),
-                    device_data_latest::gpio_out_2.eq(&parsed.gpio_out_2),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::ro1_status.eq(parsed.ro1_status),
-                    device_data_latest::ro2_status.eq(parsed.ro2_status),
-                    device_data_latest::gpio_in_1.eq(&parsed.gpio_in_1),
-                    device_data_latest::gpio_in_2.eq(&parsed.gpio_in_2),
-                    device_data_latest::gpio_out_1.eq(&parsed.gpio_out_1),
-                    device_data_latest::gpio_out_2.eq(&parsed.gpio_out_2),
-                    // device_data_latest::org_id.eq(device.organization_id.unwrap_or(1)),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(6)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("ro1_status", parsed.ro1_status.into_value()),
+                    ("ro2_status", parsed.ro2_status.into_value()),
+                    ("gpio_in_1", parsed.gpio_in_1.clone().into_value()),
+                    ("gpio_in_2", parsed.gpio_in_2.clone().into_value()),
+                    ("gpio_out_1", parsed.gpio_out_1.clone().into_value()),
+                    ("gpio_out_2", parsed.gpio_out_2.clone().into_value()),
+                ],
+            )
+            .await?;
 
             info!(
-                "Inserted/Updated LT22222L into device_data_latest for dev_eui={}",
+                "Inserted/Updated LT22222L measurements for dev_eui={}",
                 dev_eui_string
             );
         }
@@ -548,55 +594,34 @@ pub async fn write_data_from_object_json(
             let temp_raw = parsed.temperature.parse::<f32>()?;
             let hum_raw = parsed.humidity.parse::<f32>()?;
 
-            let temp_cal = device
-                .temperature_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-            let hum_cal = device
-                .humadity_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-
-            let temperature = BigDecimal::from_f32(temp_raw + temp_cal);
-            let humidity = BigDecimal::from_f32(hum_raw + hum_cal);
+            let temperature = BigDecimal::from_f32(temp_raw);
+            let humidity = BigDecimal::from_f32(hum_raw);
             let batv = BigDecimal::from_f32(parsed.battery);
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::air_temperature.eq(temperature.clone()),
-                    device_data_2025::air_humidity.eq(humidity.clone()),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(7)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::air_temperature.eq(numeric(&temperature)),
+                    device_data::air_humidity.eq(numeric(&humidity)),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(7)),
+                    device_data::submission_date.eq(time.naive_utc()),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::air_temperature.eq(temperature),
-                    device_data_latest::air_humidity.eq(humidity),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(7)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::air_temperature
-                        .eq(excluded(device_data_latest::air_temperature)),
-                    device_data_latest::air_humidity.eq(excluded(device_data_latest::air_humidity)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("air_temperature", temperature.into_value()),
+                    ("air_humidity", humidity.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!("Inserted LHT65 data for dev_eui={}", dev_eui_string);
         }
@@ -604,63 +629,41 @@ pub async fn write_data_from_object_json(
             let parsed: LAQ4JSON = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            let temp_cal = device
-                .temperature_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-            let hum_cal = device
-                .humadity_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-
-            let temperature = BigDecimal::from_f32(parsed.temperature + temp_cal);
-            let humidity = BigDecimal::from_f32(parsed.humidity + hum_cal);
+            let temperature =
+                BigDecimal::from_f32(parsed.temperature);
+            let humidity = BigDecimal::from_f32(parsed.humidity);
             let co2 = BigDecimal::from_f32(parsed.co2_ppm);
             let tvoc = BigDecimal::from_f32(parsed.tvoc_ppm);
             let batv = BigDecimal::from_f32(parsed.battery);
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::air_temperature.eq(temperature.clone()),
-                    device_data_2025::air_humidity.eq(humidity.clone()),
-                    device_data_2025::co2_ppm.eq(co2.clone()),
-                    device_data_2025::tvoc_ppm.eq(tvoc.clone()),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(8)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::air_temperature.eq(numeric(&temperature)),
+                    device_data::air_humidity.eq(numeric(&humidity)),
+                    device_data::co2_ppm.eq(numeric(&co2)),
+                    device_data::tvoc_ppm.eq(numeric(&tvoc)),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(8)),
+                    device_data::submission_date.eq(time.naive_utc()),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::air_temperature.eq(temperature),
-                    device_data_latest::air_humidity.eq(humidity),
-                    device_data_latest::co2_ppm.eq(co2),
-                    device_data_latest::tvoc_ppm.eq(tvoc),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(8)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::air_temperature
-                        .eq(excluded(device_data_latest::air_temperature)),
-                    device_data_latest::air_humidity.eq(excluded(device_data_latest::air_humidity)),
-                    device_data_latest::co2_ppm.eq(excluded(device_data_latest::co2_ppm)),
-                    device_data_latest::tvoc_ppm.eq(excluded(device_data_latest::tvoc_ppm)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("air_temperature", temperature.into_value()),
+                    ("air_humidity", humidity.into_value()),
+                    ("co2_ppm", co2.into_value()),
+                    ("tvoc_ppm", tvoc.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!("Inserted LAQ4 data for dev_eui={}", dev_eui_string);
         }
@@ -673,102 +676,45 @@ pub async fn write_data_from_object_json(
                 let temp_raw = parsed.temp_soil.parse::<f32>()?;
                 let ph_raw = parsed.ph_soil.parse::<f32>()?;
 
-                let temp_cal = device
-                    .temperature_calibration
-                    .as_ref()
-                    .and_then(|v| v.to_f32())
-                    .unwrap_or(0.0);
-
-                let sol_temperature = BigDecimal::from_f32(temp_raw + temp_cal);
+                let sol_temperature =
+                    BigDecimal::from_f32(temp_raw);
                 let ph_soil = BigDecimal::from_f32(ph_raw);
                 let batv = BigDecimal::from_f32(parsed.battery);
 
-                // Insert into device_data_2025
-                diesel::insert_into(device_data_2025::table)
+                // Insert into device_data
+                diesel::insert_into(device_data::table)
                     .values((
-                        device_data_2025::dev_eui.eq(&dev_eui_string),
-                        device_data_2025::sol_temperature.eq(sol_temperature.clone()),
-                        device_data_2025::ph_soil.eq(ph_soil.clone()),
-                        device_data_2025::batv.eq(batv.clone()),
-                        device_data_2025::device_type_id.eq(device.device_type.unwrap_or(9)),
+                        device_data::dev_eui.eq(&dev_eui_string),
+                        device_data::sol_temperature.eq(numeric(&sol_temperature)),
+                        device_data::ph_soil.eq(numeric(&ph_soil)),
+                        device_data::batv.eq(numeric(&batv)),
+                        device_data::device_type_id.eq(device.device_type.unwrap_or(9)),
+                        device_data::submission_date.eq(time.naive_utc()),
                     ))
                     .execute(conn)
                     .await?;
 
-                // Upsert into device_data_latest
-                diesel::insert_into(device_data_latest::table)
-                    .values((
-                        device_data_latest::dev_eui.eq(&dev_eui_string),
-                        device_data_latest::sol_temperature.eq(sol_temperature),
-                        device_data_latest::ph_soil.eq(ph_soil),
-                        device_data_latest::batv.eq(batv),
-                        device_data_latest::device_type_id.eq(device.device_type.unwrap_or(9)),
-                    ))
-                    .on_conflict(device_data_latest::dev_eui)
-                    .do_update()
-                    .set((
-                        device_data_latest::sol_temperature
-                            .eq(excluded(device_data_latest::sol_temperature)),
-                        device_data_latest::ph_soil.eq(excluded(device_data_latest::ph_soil)),
-                        device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                        device_data_latest::device_type_id
-                            .eq(excluded(device_data_latest::device_type_id)),
-                        device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                    ))
-                    .execute(conn)
-                    .await?;
+                measurement::save_values(
+                    conn,
+                    device.dev_eui,
+                    time,
+                    &[
+                        ("sol_temperature", sol_temperature.into_value()),
+                        ("ph_soil", ph_soil.into_value()),
+                        ("batv", batv.into_value()),
+                    ],
+                )
+                .await?;
 
                 info!(
                     "Inserted LSPH01 soil pH data for dev_eui={}",
                     dev_eui_string
                 );
+            } else {
+                return Ok(WriteStatus::Skipped);
             }
         }
 
-        // Some(11) => {
-        //     let parsed: SensecapMessage = serde_json::from_value(object_json.clone())?;
-        //     let dev_eui_string = device.dev_eui.to_string();
-
-        //     if let Some(light) = parsed.messages.first() {
-        //         if light.r#type == "report_telemetry" {
-        //             let sensecap_light = BigDecimal::from_f32(light.measurement_value);
-
-        //             // Insert into device_data_2025
-        //             diesel::insert_into(device_data_2025::table)
-        //                 .values((
-        //                     device_data_2025::dev_eui.eq(&dev_eui_string),
-        //                     device_data_2025::sensecap_light.eq(sensecap_light.clone()),
-        //                     device_data_2025::device_type_id.eq(device.device_type.unwrap_or(11)),
-        //                     // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
-        //                 ))
-        //                 .execute(conn)
-        //                 .await?;
-
-        //             // Upsert into device_data_latest
-        //             diesel::insert_into(device_data_latest::table)
-        //                 .values((
-        //                     device_data_latest::dev_eui.eq(&dev_eui_string),
-        //                     device_data_latest::sensecap_light.eq(sensecap_light),
-        //                     device_data_latest::device_type_id.eq(device.device_type.unwrap_or(11)),
-        //                 ))
-        //                 .on_conflict(device_data_latest::dev_eui)
-        //                 .do_update()
-        //                 .set((
-        //                     device_data_latest::sensecap_light.eq(excluded(device_data_latest::sensecap_light)),
-        //                     device_data_latest::device_type_id.eq(excluded(device_data_latest::device_type_id)),
-        //                     device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-        //                 ))
-        //                 .execute(conn)
-        //                 .await?;
-
-        //             info!("Inserted SenseCAP light telemetry for dev_eui={}", dev_eui_string);
-        //         } else {
-        //             tracing::warn!("Unexpected message type: {:?}", light.r#type);
-        //         }
-        //     } else {
-        //         tracing::warn!("No messages found in SenseCAP payload");
-        //     }
-        // }
         Some(12) => {
             let parsed: EM300THJSON = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
@@ -776,61 +722,39 @@ pub async fn write_data_from_object_json(
             // Skip zeroed payloads
             if parsed.temperature == 0.0 && parsed.humidity == 0.0 {
                 info!("EM300TH has zeroed data, skipping...");
-                return Ok(());
+                return Ok(WriteStatus::Skipped);
             }
 
-            let temp_cal = device
-                .temperature_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-            let hum_cal = device
-                .humadity_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-
-            let temperature = BigDecimal::from_f32(parsed.temperature + temp_cal);
-            let humidity = BigDecimal::from_f32(parsed.humidity + hum_cal);
+            let temperature =
+                BigDecimal::from_f32(parsed.temperature);
+            let humidity = BigDecimal::from_f32(parsed.humidity);
             let batv = BigDecimal::from_f32(parsed.battery);
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::air_temperature.eq(temperature.clone()),
-                    device_data_2025::air_humidity.eq(humidity.clone()),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(12)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::air_temperature.eq(numeric(&temperature)),
+                    device_data::air_humidity.eq(numeric(&humidity)),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(12)),
+                    device_data::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::air_temperature.eq(temperature),
-                    device_data_latest::air_humidity.eq(humidity),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(12)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::air_temperature
-                        .eq(excluded(device_data_latest::air_temperature)),
-                    device_data_latest::air_humidity.eq(excluded(device_data_latest::air_humidity)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("air_temperature", temperature.into_value()),
+                    ("air_humidity", humidity.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!(
                 "Inserted EM300TH temperature & humidity data for dev_eui={}",
@@ -842,71 +766,45 @@ pub async fn write_data_from_object_json(
             let parsed: AM107JSON = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            let temp_cal = device
-                .temperature_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-            let hum_cal = device
-                .humadity_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-
-            let temperature = BigDecimal::from_f32(parsed.temperature + temp_cal);
-            let humidity = BigDecimal::from_f32(parsed.humidity + hum_cal);
+            let temperature =
+                BigDecimal::from_f32(parsed.temperature);
+            let humidity = BigDecimal::from_f32(parsed.humidity);
             let co2 = BigDecimal::from_f32(parsed.co2);
             let tvoc = BigDecimal::from_f32(parsed.tvoc);
             let pressure = BigDecimal::from_f32(parsed.pressure);
             let batv = BigDecimal::from_f32(parsed.battery);
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::air_temperature.eq(temperature.clone()),
-                    device_data_2025::air_humidity.eq(humidity.clone()),
-                    device_data_2025::co2_ppm.eq(co2.clone()),
-                    device_data_2025::tvoc_ppm.eq(tvoc.clone()),
-                    device_data_2025::barometric_pressure.eq(pressure.clone()),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(13)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::air_temperature.eq(numeric(&temperature)),
+                    device_data::air_humidity.eq(numeric(&humidity)),
+                    device_data::co2_ppm.eq(numeric(&co2)),
+                    device_data::tvoc_ppm.eq(numeric(&tvoc)),
+                    device_data::barometric_pressure.eq(numeric(&pressure)),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(13)),
+                    device_data::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::air_temperature.eq(temperature),
-                    device_data_latest::air_humidity.eq(humidity),
-                    device_data_latest::co2_ppm.eq(co2),
-                    device_data_latest::tvoc_ppm.eq(tvoc),
-                    device_data_latest::barometric_pressure.eq(pressure),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(13)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::air_temperature
-                        .eq(excluded(device_data_latest::air_temperature)),
-                    device_data_latest::air_humidity.eq(excluded(device_data_latest::air_humidity)),
-                    device_data_latest::co2_ppm.eq(excluded(device_data_latest::co2_ppm)),
-                    device_data_latest::tvoc_ppm.eq(excluded(device_data_latest::tvoc_ppm)),
-                    device_data_latest::barometric_pressure
-                        .eq(excluded(device_data_latest::barometric_pressure)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("air_temperature", temperature.into_value()),
+                    ("air_humidity", humidity.into_value()),
+                    ("co2_ppm", co2.into_value()),
+                    ("tvoc_ppm", tvoc.into_value()),
+                    ("barometric_pressure", pressure.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!(
                 "Inserted AM107 air quality data for dev_eui={}",
@@ -921,37 +819,25 @@ pub async fn write_data_from_object_json(
             // Store alarm as water_leak_status (DB compatibility)
             let alarm_status = parsed.alarm;
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::water_leak_status.eq(alarm_status),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(14)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::water_leak_status.eq(alarm_status),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(14)),
+                    device_data::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::water_leak_status.eq(alarm_status),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(14)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::water_leak_status
-                        .eq(excluded(device_data_latest::water_leak_status)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[("water_leak_status", alarm_status.into_value())],
+            )
+            .await?;
 
             info!(
                 "Inserted WS101 alarm press event for dev_eui={}",
@@ -965,40 +851,29 @@ pub async fn write_data_from_object_json(
             let batv = BigDecimal::from_f32(parsed.battery);
             let door_status = parsed.door_status;
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::door_open_status.eq(door_status),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(16)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::door_open_status.eq(door_status),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(16)),
+                    device_data::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::door_open_status.eq(door_status),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(16)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::door_open_status
-                        .eq(excluded(device_data_latest::door_open_status)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("door_open_status", door_status.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!(
                 "Inserted EM300-MCS door sensor data for dev_eui={}",
@@ -1010,37 +885,25 @@ pub async fn write_data_from_object_json(
             let parsed: EM300ZLDJSON = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::water_leak_status.eq(parsed.water_leak),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(18)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::water_leak_status.eq(parsed.water_leak),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(18)),
+                    device_data::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::water_leak_status.eq(parsed.water_leak),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(18)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::water_leak_status
-                        .eq(excluded(device_data_latest::water_leak_status)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[("water_leak_status", parsed.water_leak.into_value())],
+            )
+            .await?;
 
             info!(
                 "Inserted EM300-ZLD leak status for dev_eui={}",
@@ -1052,35 +915,24 @@ pub async fn write_data_from_object_json(
             let parsed: EM300ZLDJSON = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            diesel::insert_into(device_data_2025::table)
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::water_leak_status.eq(parsed.water_leak),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(19)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::water_leak_status.eq(parsed.water_leak),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(19)),
+                    device_data::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::water_leak_status.eq(parsed.water_leak),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(19)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::water_leak_status
-                        .eq(excluded(device_data_latest::water_leak_status)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[("water_leak_status", parsed.water_leak.into_value())],
+            )
+            .await?;
 
             info!(
                 "Inserted EM300-ZLD (device_type=19) water leak status for dev_eui={}",
@@ -1091,48 +943,33 @@ pub async fn write_data_from_object_json(
             let parsed: EM500PT100JSON = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            let temp_cal = device
-                .temperature_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-            let temperature = BigDecimal::from_f32(parsed.temperature + temp_cal);
+            let temperature =
+                BigDecimal::from_f32(parsed.temperature);
             let batv = BigDecimal::from_f32(parsed.battery);
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::air_temperature.eq(temperature.clone()),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(20)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::air_temperature.eq(numeric(&temperature)),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(20)),
+                    device_data::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::air_temperature.eq(temperature),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(20)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::air_temperature
-                        .eq(excluded(device_data_latest::air_temperature)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("air_temperature", temperature.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!(
                 "Inserted EM500-PT100 temp data for dev_eui={}",
@@ -1150,40 +987,29 @@ pub async fn write_data_from_object_json(
             let barometric_pressure = BigDecimal::from_f32(pressure_hpa);
             let batv = BigDecimal::from_f32(parsed.battery);
 
-            // Insert into device_data_2025
-            diesel::insert_into(device_data_2025::table)
+            // Insert into device_data
+            diesel::insert_into(device_data::table)
                 .values((
-                    device_data_2025::dev_eui.eq(&dev_eui_string),
-                    device_data_2025::barometric_pressure.eq(barometric_pressure.clone()),
-                    device_data_2025::batv.eq(batv.clone()),
-                    device_data_2025::device_type_id.eq(device.device_type.unwrap_or(21)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    device_data::dev_eui.eq(&dev_eui_string),
+                    device_data::barometric_pressure.eq(numeric(&barometric_pressure)),
+                    device_data::batv.eq(numeric(&batv)),
+                    device_data::device_type_id.eq(device.device_type.unwrap_or(21)),
+                    device_data::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::barometric_pressure.eq(barometric_pressure),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(21)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::barometric_pressure
-                        .eq(excluded(device_data_latest::barometric_pressure)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("barometric_pressure", barometric_pressure.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!(
                 "Inserted EM500-PP barometric pressure for dev_eui={}",
@@ -1204,35 +1030,20 @@ pub async fn write_data_from_object_json(
 
             if parsed.current > 0.0 {
                 // Insert into WS522 table (optional - skip if not defined in your schema)
-                // Insert into device_data_latest
-                diesel::insert_into(device_data_latest::table)
-                    .values((
-                        device_data_latest::dev_eui.eq(&dev_eui_string),
-                        device_data_latest::current.eq(current),
-                        device_data_latest::factor.eq(factor),
-                        device_data_latest::power.eq(power),
-                        device_data_latest::voltage.eq(voltage),
-                        device_data_latest::power_sum.eq(power_sum),
-                        device_data_latest::status.eq(status),
-                        device_data_latest::device_type_id.eq(device.device_type.unwrap_or(24)),
-                        // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                    ))
-                    .on_conflict(device_data_latest::dev_eui)
-                    .do_update()
-                    .set((
-                        device_data_latest::current.eq(excluded(device_data_latest::current)),
-                        device_data_latest::factor.eq(excluded(device_data_latest::factor)),
-                        device_data_latest::power.eq(excluded(device_data_latest::power)),
-                        device_data_latest::voltage.eq(excluded(device_data_latest::voltage)),
-                        device_data_latest::power_sum.eq(excluded(device_data_latest::power_sum)),
-                        device_data_latest::status.eq(excluded(device_data_latest::status)),
-                        device_data_latest::device_type_id
-                            .eq(excluded(device_data_latest::device_type_id)),
-                        // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                        device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                    ))
-                    .execute(conn)
-                    .await?;
+                measurement::save_values(
+                    conn,
+                    device.dev_eui,
+                    time,
+                    &[
+                        ("current", current.into_value()),
+                        ("factor", factor.into_value()),
+                        ("power", power.into_value()),
+                        ("voltage", voltage.into_value()),
+                        ("power_sum", power_sum.into_value()),
+                        ("status", status.into_value()),
+                    ],
+                )
+                .await?;
 
                 info!(
                     "WS522: full power data inserted for dev_eui={}",
@@ -1240,15 +1051,12 @@ pub async fn write_data_from_object_json(
                 );
             } else {
                 // Only update `status`
-                diesel::update(
-                    device_data_latest::table
-                        .filter(device_data_latest::dev_eui.eq(&dev_eui_string)),
+                measurement::save_values(
+                    conn,
+                    device.dev_eui,
+                    time,
+                    &[("status", status.into_value())],
                 )
-                .set((
-                    device_data_latest::status.eq(status),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
                 .await?;
 
                 info!("WS522: only status updated for dev_eui={}", dev_eui_string);
@@ -1267,48 +1075,27 @@ pub async fn write_data_from_object_json(
 
             // If we have power factor, insert full record
             if parsed.power_factor > 0.0 {
-                diesel::insert_into(device_data_latest::table)
-                    .values((
-                        device_data_latest::dev_eui.eq(&dev_eui_string),
-                        device_data_latest::power.eq(power),
-                        device_data_latest::power_sum.eq(power_consumption),
-                        device_data_latest::factor.eq(factor),
-                        device_data_latest::current.eq(current),
-                        device_data_latest::voltage.eq(voltage),
-                        device_data_latest::switch1.eq(parsed.switch1),
-                        device_data_latest::switch2.eq(parsed.switch2),
-                        device_data_latest::switch3.eq(parsed.switch3),
-                        device_data_latest::switch4.eq(parsed.switch4),
-                        device_data_latest::switch5.eq(parsed.switch5),
-                        device_data_latest::switch6.eq(parsed.switch6),
-                        device_data_latest::switch7.eq(parsed.switch7),
-                        device_data_latest::switch8.eq(parsed.switch8),
-                        device_data_latest::device_type_id.eq(device.device_type.unwrap_or(27)),
-                        // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                    ))
-                    .on_conflict(device_data_latest::dev_eui)
-                    .do_update()
-                    .set((
-                        device_data_latest::power.eq(excluded(device_data_latest::power)),
-                        device_data_latest::power_sum.eq(excluded(device_data_latest::power_sum)),
-                        device_data_latest::factor.eq(excluded(device_data_latest::factor)),
-                        device_data_latest::current.eq(excluded(device_data_latest::current)),
-                        device_data_latest::voltage.eq(excluded(device_data_latest::voltage)),
-                        device_data_latest::switch1.eq(excluded(device_data_latest::switch1)),
-                        device_data_latest::switch2.eq(excluded(device_data_latest::switch2)),
-                        device_data_latest::switch3.eq(excluded(device_data_latest::switch3)),
-                        device_data_latest::switch4.eq(excluded(device_data_latest::switch4)),
-                        device_data_latest::switch5.eq(excluded(device_data_latest::switch5)),
-                        device_data_latest::switch6.eq(excluded(device_data_latest::switch6)),
-                        device_data_latest::switch7.eq(excluded(device_data_latest::switch7)),
-                        device_data_latest::switch8.eq(excluded(device_data_latest::switch8)),
-                        device_data_latest::device_type_id
-                            .eq(excluded(device_data_latest::device_type_id)),
-                        // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                        device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                    ))
-                    .execute(conn)
-                    .await?;
+                measurement::save_values(
+                    conn,
+                    device.dev_eui,
+                    time,
+                    &[
+                        ("power", power.into_value()),
+                        ("power_sum", power_consumption.into_value()),
+                        ("factor", factor.into_value()),
+                        ("current", current.into_value()),
+                        ("voltage", voltage.into_value()),
+                        ("switch1", parsed.switch1.into_value()),
+                        ("switch2", parsed.switch2.into_value()),
+                        ("switch3", parsed.switch3.into_value()),
+                        ("switch4", parsed.switch4.into_value()),
+                        ("switch5", parsed.switch5.into_value()),
+                        ("switch6", parsed.switch6.into_value()),
+                        ("switch7", parsed.switch7.into_value()),
+                        ("switch8", parsed.switch8.into_value()),
+                    ],
+                )
+                .await?;
 
                 info!(
                     "Inserted WS558 full power and switch data for dev_eui={}",
@@ -1316,22 +1103,21 @@ pub async fn write_data_from_object_json(
                 );
             } else {
                 // Power data missing — update only switches
-                diesel::update(
-                    device_data_latest::table
-                        .filter(device_data_latest::dev_eui.eq(&dev_eui_string)),
+                measurement::save_values(
+                    conn,
+                    device.dev_eui,
+                    time,
+                    &[
+                        ("switch1", parsed.switch1.into_value()),
+                        ("switch2", parsed.switch2.into_value()),
+                        ("switch3", parsed.switch3.into_value()),
+                        ("switch4", parsed.switch4.into_value()),
+                        ("switch5", parsed.switch5.into_value()),
+                        ("switch6", parsed.switch6.into_value()),
+                        ("switch7", parsed.switch7.into_value()),
+                        ("switch8", parsed.switch8.into_value()),
+                    ],
                 )
-                .set((
-                    device_data_latest::switch1.eq(parsed.switch1),
-                    device_data_latest::switch2.eq(parsed.switch2),
-                    device_data_latest::switch3.eq(parsed.switch3),
-                    device_data_latest::switch4.eq(parsed.switch4),
-                    device_data_latest::switch5.eq(parsed.switch5),
-                    device_data_latest::switch6.eq(parsed.switch6),
-                    device_data_latest::switch7.eq(parsed.switch7),
-                    device_data_latest::switch8.eq(parsed.switch8),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
                 .await?;
 
                 info!(
@@ -1345,41 +1131,24 @@ pub async fn write_data_from_object_json(
             let parsed: UC300Json = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            // Update device_data_latest with full GPIO/ADC state
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::adc_1.eq(parsed.adc_1.clone()),
-                    device_data_latest::adc_2.eq(parsed.adc_2.clone()),
-                    device_data_latest::adv_1.eq(parsed.adv_1.clone()),
-                    device_data_latest::gpio_in_1.eq(parsed.gpio_in_1.clone()),
-                    device_data_latest::gpio_in_2.eq(parsed.gpio_in_2.clone()),
-                    device_data_latest::gpio_in_3.eq(parsed.gpio_in_3.clone()),
-                    device_data_latest::gpio_in_4.eq(parsed.gpio_in_4.clone()),
-                    device_data_latest::gpio_out_1.eq(parsed.gpio_out_1.clone()),
-                    device_data_latest::gpio_out_2.eq(parsed.gpio_out_2.clone()),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(28)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::adc_1.eq(excluded(device_data_latest::adc_1)),
-                    device_data_latest::adc_2.eq(excluded(device_data_latest::adc_2)),
-                    device_data_latest::adv_1.eq(excluded(device_data_latest::adv_1)),
-                    device_data_latest::gpio_in_1.eq(excluded(device_data_latest::gpio_in_1)),
-                    device_data_latest::gpio_in_2.eq(excluded(device_data_latest::gpio_in_2)),
-                    device_data_latest::gpio_in_3.eq(excluded(device_data_latest::gpio_in_3)),
-                    device_data_latest::gpio_in_4.eq(excluded(device_data_latest::gpio_in_4)),
-                    device_data_latest::gpio_out_1.eq(excluded(device_data_latest::gpio_out_1)),
-                    device_data_latest::gpio_out_2.eq(excluded(device_data_latest::gpio_out_2)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            // Store the full GPIO/ADC state
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("adc_1", parsed.adc_1.clone().into_value()),
+                    ("adc_2", parsed.adc_2.clone().into_value()),
+                    ("adv_1", parsed.adv_1.clone().into_value()),
+                    ("gpio_in_1", parsed.gpio_in_1.clone().into_value()),
+                    ("gpio_in_2", parsed.gpio_in_2.clone().into_value()),
+                    ("gpio_in_3", parsed.gpio_in_3.clone().into_value()),
+                    ("gpio_in_4", parsed.gpio_in_4.clone().into_value()),
+                    ("gpio_out_1", parsed.gpio_out_1.clone().into_value()),
+                    ("gpio_out_2", parsed.gpio_out_2.clone().into_value()),
+                ],
+            )
+            .await?;
 
             info!(
                 "Inserted UC300 GPIO/ADC state for dev_eui={}",
@@ -1391,52 +1160,26 @@ pub async fn write_data_from_object_json(
             let parsed: EM400MUD = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            if parsed.distance > 0 {
+            if parsed.distance > 0.0 {
                 let temperature = BigDecimal::from_f32(parsed.temperature);
                 let batv = Some(parsed.battery as i32); // 👈 convert to correct type
-                let distance = Some(parsed.distance as i32);
+                let distance = Some(parsed.distance.round() as i32);
 
-                // Insert into device_data_2025
+                // Insert into device_data
                 diesel::insert_into(em400mud::table)
                     .values((
                         em400mud::dev_eui.eq(&dev_eui_string),
-                        em400mud::air_temperature.eq(temperature.clone()),
+                        em400mud::air_temperature.eq(numeric(&temperature)),
                         em400mud::batv.eq(batv),
                         em400mud::distance.eq(distance),
                         em400mud::device_type_id.eq(device.device_type.unwrap_or(33)),
-                        // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                        em400mud::submission_date.eq(time.naive_utc()),
+                        // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                         em400mud::position.eq(parsed.position.clone()),
                     ))
                     .execute(conn)
                     .await?;
 
-                // Upsert into device_data_latest
-                // diesel::insert_into(device_data_latest::table)
-                //     .values((
-                //         device_data_latest::dev_eui.eq(&dev_eui_string),
-                //         device_data_latest::air_temperature.eq(temperature),
-                //         device_data_latest::batv.eq(batv),
-                //         device_data_latest::distance.eq(distance),
-                //         device_data_latest::device_type_id.eq(device.device_type.unwrap_or(33)),
-                //         // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                //         device_data_latest::position.eq(parsed.position.clone()),
-                //     ))
-                //     .on_conflict(device_data_latest::dev_eui)
-                //     .do_update()
-                //     .set((
-                //         device_data_latest::air_temperature
-                //             .eq(excluded(device_data_latest::air_temperature)),
-                //         device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                //         // device_data_latest::distance.eq(excluded(device_data_latest::distance)),
-                //         // device_data_latest::position.eq(excluded(device_data_latest::position)),
-                //         device_data_latest::device_type_id
-                //             .eq(excluded(device_data_latest::device_type_id)),
-                //         // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                //         device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                //     ))
-                //     .execute(conn)
-                //     .await?;
-
                 info!("Inserted EM400-MUD data for dev_eui={}", dev_eui_string);
             } else {
                 info!(
@@ -1451,64 +1194,29 @@ pub async fn write_data_from_object_json(
             let dev_eui_string = device.dev_eui.to_string();
 
             if parsed.temperature != 0.0 && parsed.humidity != 0.0 {
-                let temp_cal = device
-                    .temperature_calibration
-                    .as_ref()
-                    .and_then(|v| v.to_f32())
-                    .unwrap_or(0.0);
-                let hum_cal = device
-                    .humadity_calibration
-                    .as_ref()
-                    .and_then(|v| v.to_f32())
-                    .unwrap_or(0.0);
-
-                let temperature = BigDecimal::from_f32(parsed.temperature + temp_cal);
-                let humidity = BigDecimal::from_f32(parsed.humidity + hum_cal);
+
+                let temperature =
+                    BigDecimal::from_f32(parsed.temperature);
+                let humidity =
+                    BigDecimal::from_f32(parsed.humidity);
                 let co2 = BigDecimal::from_f32(parsed.co2);
                 let batv = BigDecimal::from_i64(parsed.battery).and_then(|v| v.to_i32());
 
-                // Insert into device_data_2025
+                // Insert into device_data
                 diesel::insert_into(am103::table)
                     .values((
                         am103::dev_eui.eq(&dev_eui_string),
-                        am103::air_temperature.eq(temperature.clone()),
-                        am103::air_humidity.eq(humidity.clone()),
-                        am103::co2_ppm.eq(co2.clone()),
+                        am103::air_temperature.eq(numeric(&temperature)),
+                        am103::air_humidity.eq(numeric(&humidity)),
+                        am103::co2_ppm.eq(numeric(&co2)),
                         am103::batv.eq(batv.clone()),
                         am103::device_type_id.eq(device.device_type.unwrap_or(35)),
-                        // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                        am103::submission_date.eq(time.naive_utc()),
+                        // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                     ))
                     .execute(conn)
                     .await?;
 
-                // Upsert into device_data_latest
-                // diesel::insert_into(device_data_latest::table)
-                //     .values((
-                //         device_data_latest::dev_eui.eq(&dev_eui_string),
-                //         device_data_latest::air_temperature.eq(temperature),
-                //         device_data_latest::air_humidity.eq(humidity),
-                //         device_data_latest::co2_ppm.eq(co2),
-                //         device_data_latest::batv.eq(batv),
-                //         device_data_latest::device_type_id.eq(device.device_type.unwrap_or(35)),
-                //         // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                //     ))
-                //     .on_conflict(device_data_latest::dev_eui)
-                //     .do_update()
-                //     .set((
-                //         device_data_latest::air_temperature
-                //             .eq(excluded(device_data_latest::air_temperature)),
-                //         device_data_latest::air_humidity
-                //             .eq(excluded(device_data_latest::air_humidity)),
-                //         device_data_latest::co2_ppm.eq(excluded(device_data_latest::co2_ppm)),
-                //         device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                //         device_data_latest::device_type_id
-                //             .eq(excluded(device_data_latest::device_type_id)),
-                //         // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                //         device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                //     ))
-                //     .execute(conn)
-                //     .await?;
-
                 info!(
                     "Inserted AM103 air quality data for dev_eui={}",
                     dev_eui_string
@@ -1525,53 +1233,37 @@ pub async fn write_data_from_object_json(
             let parsed: LTC2LB = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            let temp_cal = device
-                .temperature_calibration
-                .as_ref()
-                .and_then(|v| v.to_f32())
-                .unwrap_or(0.0);
-            let temperature1 = BigDecimal::from_f32(parsed.temperature1 + temp_cal);
-            let temperature2 = BigDecimal::from_f32(parsed.temperature2); // optional to calibrate
+            let temperature1 =
+                BigDecimal::from_f32(parsed.temperature1);
+            let temperature2 =
+                BigDecimal::from_f32(parsed.temperature2);
             let batv = BigDecimal::from_f32(parsed.battery);
 
-            // Insert into device_data_2025
+            // Insert into device_data
             diesel::insert_into(ltc2lb::table)
                 .values((
                     ltc2lb::dev_eui.eq(&dev_eui_string),
-                    ltc2lb::temperature1.eq(temperature1.clone()),
-                    ltc2lb::temperature2.eq(temperature2.clone()),
-                    ltc2lb::batv.eq(batv.clone()),
+                    ltc2lb::temperature1.eq(numeric(&temperature1)),
+                    ltc2lb::temperature2.eq(numeric(&temperature2)),
+                    ltc2lb::batv.eq(numeric(&batv)),
                     ltc2lb::device_type_id.eq(device.device_type.unwrap_or(36)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    ltc2lb::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::air_temperature.eq(temperature1),
-                    device_data_latest::sol_temperature.eq(temperature2),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(36)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::air_temperature
-                        .eq(excluded(device_data_latest::air_temperature)),
-                    device_data_latest::sol_temperature
-                        .eq(excluded(device_data_latest::sol_temperature)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("air_temperature", temperature1.into_value()),
+                    ("sol_temperature", temperature2.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!(
                 "Inserted LTC2LB dual-temp data for dev_eui={}",
@@ -1583,45 +1275,36 @@ pub async fn write_data_from_object_json(
             let parsed: DDS45LB = serde_json::from_value(object_json.clone())?;
             let dev_eui_string = device.dev_eui.to_string();
 
-            let distance: Option<i32> = BigDecimal::from_i64(parsed.distance)
-                .and_then(|v| v.to_i64())
-                .and_then(|v| i32::try_from(v).ok());
+            let distance: Option<i32> =
+                BigDecimal::from_f64(parsed.distance.round())
+                    .and_then(|v| v.to_i64())
+                    .and_then(|v| i32::try_from(v).ok());
 
             let batv = BigDecimal::from_f32(parsed.battery);
 
-            // Insert into device_data_2025
+            // Insert into device_data
             diesel::insert_into(dds45lb::table)
                 .values((
                     dds45lb::dev_eui.eq(&dev_eui_string),
                     dds45lb::distance.eq(distance),
-                    dds45lb::batv.eq(batv.clone()),
+                    dds45lb::batv.eq(numeric(&batv)),
                     dds45lb::device_type_id.eq(device.device_type.unwrap_or(37)),
-                    // device_data_2025::org_id.eq(device.org_id.unwrap_or(1)),
+                    dds45lb::submission_date.eq(time.naive_utc()),
+                    // device_data::org_id.eq(device.org_id.unwrap_or(1)),
                 ))
                 .execute(conn)
                 .await?;
 
-            // Upsert into device_data_latest
-            diesel::insert_into(device_data_latest::table)
-                .values((
-                    device_data_latest::dev_eui.eq(&dev_eui_string),
-                    device_data_latest::distance.eq(distance),
-                    device_data_latest::batv.eq(batv),
-                    device_data_latest::device_type_id.eq(device.device_type.unwrap_or(37)),
-                    // device_data_latest::org_id.eq(device.org_id.unwrap_or(1)),
-                ))
-                .on_conflict(device_data_latest::dev_eui)
-                .do_update()
-                .set((
-                    device_data_latest::distance.eq(excluded(device_data_latest::distance)),
-                    device_data_latest::batv.eq(excluded(device_data_latest::batv)),
-                    device_data_latest::device_type_id
-                        .eq(excluded(device_data_latest::device_type_id)),
-                    // device_data_latest::org_id.eq(excluded(device_data_latest::org_id)),
-                    device_data_latest::submission_date.eq(chrono::Utc::now().naive_utc()),
-                ))
-                .execute(conn)
-                .await?;
+            measurement::save_values(
+                conn,
+                device.dev_eui,
+                time,
+                &[
+                    ("distance", distance.into_value()),
+                    ("batv", batv.into_value()),
+                ],
+            )
+            .await?;
 
             info!(
                 "Inserted DDS45LB distance + battery for dev_eui={}",
@@ -1630,9 +1313,144 @@ pub async fn write_data_from_object_json(
         }
 
         _ => {
-            tracing::warn!("Unsupported device type: {:?}", device.device_type);
+            // Device types without a built-in writer are stored using the measurements of the
+            // device-profile.
+            let dp = device_profile::get(&device.device_profile_id).await?;
+            let count = measurement::save_object(
+                conn,
+                device.dev_eui,
+                time,
+                &dp.measurements,
+                object_json,
+            )
+            .await?;
+            if count == 0 {
+                tracing::warn!("Unsupported device type: {:?}", device.device_type);
+                return Ok(WriteStatus::Unsupported);
+            }
         }
     }
 
-    Ok(())
+    Ok(WriteStatus::Inserted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::storage::{device, device_profile};
+    use crate::test;
+    use chrono::TimeZone;
+    use lrwn::EUI64;
+    use serde_json::json;
+
+    fn get_write_count(device_type: i32, status: &str) -> u64 {
+        DEVICE_DATA_WRITE_COUNTER
+            .get_or_create(&DeviceDataLabels {
+                device_type: device_type.to_string(),
+                status: status.to_string(),
+            })
+            .get()
+    }
+
+    #[test]
+    fn test_write_status() {
+        assert_eq!("inserted", get_write_status(&Ok(WriteStatus::Inserted)));
+        assert_eq!("skipped", get_write_status(&Ok(WriteStatus::Skipped)));
+        assert_eq!(
+            "unsupported",
+            get_write_status(&Ok(WriteStatus::Unsupported))
+        );
+
+        let e = serde_json::from_value::<LSN50V2JSON>(json!({})).unwrap_err();
+        assert_eq!("parse_error", get_write_status(&Err(e.into())));
+        let e = "abc".parse::<f32>().unwrap_err();
+        assert_eq!("parse_error", get_write_status(&Err(e.into())));
+        let e = "abc".parse::<i32>().unwrap_err();
+        assert_eq!("parse_error", get_write_status(&Err(e.into())));
+
+        let e = anyhow!("connection refused");
+        assert_eq!("error", get_write_status(&Err(e)));
+    }
+
+    #[tokio::test]
+    async fn test_write_counter() {
+        let _guard = test::prepare().await;
+        let dp = device_profile::test::create_device_profile(None).await;
+        let d = device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+
+        struct Test {
+            name: String,
+            device_type: i32,
+            object: Value,
+            expected_status: &'static str,
+            expected_error: bool,
+        }
+
+        let tests = vec![
+            Test {
+                name: "unsupported device type".into(),
+                device_type: 9999,
+                object: json!({}),
+                expected_status: "unsupported",
+                expected_error: false,
+            },
+            Test {
+                name: "sentinel value".into(),
+                device_type: 1,
+                object: json!({"batv": 3.6, "hum_sht": "0", "temp_c_sht": "-45"}),
+                expected_status: "skipped",
+                expected_error: false,
+            },
+            Test {
+                name: "invalid payload".into(),
+                device_type: 1,
+                object: json!({"batv": 3.6}),
+                expected_status: "parse_error",
+                expected_error: true,
+            },
+            Test {
+                name: "invalid value".into(),
+                device_type: 1,
+                object: json!({"batv": 3.6, "hum_sht": "0", "temp_c_sht": "n/a"}),
+                expected_status: "parse_error",
+                expected_error: true,
+            },
+        ];
+
+        for tst in &tests {
+            println!("> {}", tst.name);
+            let dev = Device {
+                device_type: Some(tst.device_type),
+                ..d.clone()
+            };
+            let count = get_write_count(tst.device_type, tst.expected_status);
+
+            let res = write_data_from_object_json(&dev, &tst.object, Utc::now()).await;
+            assert_eq!(tst.expected_error, res.is_err());
+            assert_eq!(
+                count + 1,
+                get_write_count(tst.device_type, tst.expected_status)
+            );
+        }
+
+        // the reading is stored using the time at which the uplink was received
+        let time = Utc.with_ymd_and_hms(2025, 7, 14, 10, 0, 0).unwrap();
+        let dev = Device {
+            device_type: Some(1),
+            ..d.clone()
+        };
+        let object = json!({"batv": 3.6, "hum_sht": "45.5", "temp_c_sht": "21.5"});
+        write_data_from_object_json(&dev, &object, time)
+            .await
+            .unwrap();
+
+        let latest = measurement::test::get_latest(&d.dev_eui).await.unwrap();
+        assert_eq!(3, latest.len());
+        assert!(latest.iter().all(|m| m.time == time));
+    }
 }
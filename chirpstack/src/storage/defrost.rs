@@ -0,0 +1,822 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Float, Integer, Nullable, Text, Timestamp};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+
+use super::fields::sql_types::Timestamptz;
+use super::schema::{defrost_cycle, defrost_learning, defrost_schedule};
+use super::{db_transaction, error::Error, get_async_db_conn, sensor_data, AsyncDbConnection};
+
+const MINUTES_PER_DAY: f64 = 1440.0;
+
+// Max distance (minutes) between the start of two temperature spikes on different days to be
+// considered the same (learned) defrost cycle.
+const LEARN_CLUSTER_MINUTES: f64 = 45.0;
+
+// Fraction of the learning days on which a spike must be seen at (about) the same time of day,
+// before it is considered a defrost cycle.
+const LEARN_MIN_DAY_RATIO: f64 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CycleStatus {
+    // The defrost cycle started and ended within the expected window.
+    Completed,
+    // No defrost cycle was detected within the expected window.
+    Missed,
+    // The defrost cycle did not end within the expected window.
+    TooLong,
+}
+
+impl From<i32> for CycleStatus {
+    fn from(v: i32) -> Self {
+        match v {
+            1 => CycleStatus::Missed,
+            2 => CycleStatus::TooLong,
+            _ => CycleStatus::Completed,
+        }
+    }
+}
+
+impl From<CycleStatus> for i32 {
+    fn from(v: CycleStatus) -> Self {
+        match v {
+            CycleStatus::Completed => 0,
+            CycleStatus::Missed => 1,
+            CycleStatus::TooLong => 2,
+        }
+    }
+}
+
+// Expected defrost window of a zone or device. The start_time is in hours of the (local) day,
+// like the alarm schedules, the duration in minutes.
+#[derive(Queryable, QueryableByName, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = defrost_schedule)]
+pub struct DefrostSchedule {
+    pub id: i32,
+    pub zone_id: Option<i32>,
+    pub dev_eui: Option<String>,
+    pub start_time: f64,
+    pub duration: i32,
+    pub learned: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = defrost_schedule)]
+pub struct NewDefrostSchedule {
+    pub zone_id: Option<i32>,
+    pub dev_eui: Option<String>,
+    pub start_time: f64,
+    pub duration: i32,
+    pub learned: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl NewDefrostSchedule {
+    fn validate(&self) -> Result<(), Error> {
+        if self.zone_id.is_none() && self.dev_eui.as_deref().unwrap_or_default().is_empty() {
+            return Err(Error::Validation(
+                "zone_id or dev_eui must be set".to_string(),
+            ));
+        }
+        if !(0.0..24.0).contains(&self.start_time) {
+            return Err(Error::Validation(
+                "start_time must be between 0 and 24".to_string(),
+            ));
+        }
+        if self.duration <= 0 || self.duration as f64 >= MINUTES_PER_DAY {
+            return Err(Error::Validation(
+                "duration must be between 1 and 1439 minutes".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Queryable, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = defrost_cycle)]
+pub struct DefrostCycle {
+    pub id: i32,
+    pub dev_eui: String,
+    pub schedule_id: Option<i32>,
+    pub window_start: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub peak_temperature: Option<f32>,
+    pub status: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = defrost_cycle)]
+pub struct NewDefrostCycle {
+    pub dev_eui: String,
+    pub schedule_id: Option<i32>,
+    pub window_start: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub peak_temperature: Option<f32>,
+    pub status: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+// Defrost schedule, expanded to the devices of the zone in case of a zone schedule.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct DeviceSchedule {
+    #[diesel(sql_type = Integer)]
+    pub schedule_id: i32,
+
+    #[diesel(sql_type = Text)]
+    pub dev_eui: String,
+
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub device_type_id: Option<i32>,
+
+    #[diesel(sql_type = Double)]
+    pub start_time: f64,
+
+    #[diesel(sql_type = Integer)]
+    pub duration: i32,
+
+    #[diesel(sql_type = Timestamptz)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(QueryableByName)]
+struct SampleRow {
+    #[diesel(sql_type = Timestamp)]
+    submission_date: NaiveDateTime,
+    #[diesel(sql_type = Float)]
+    value: f32,
+}
+
+// Temperature spike, the start and end are expressed in the same minutes as the samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spike {
+    pub start: f64,
+    pub end: Option<f64>,
+    pub peak: f32,
+}
+
+// Returns true when the given minute of the day falls within the defrost window (including the
+// tolerance before and after). Windows crossing midnight are handled.
+pub fn in_window(start_minute: f64, duration: f64, tolerance: f64, minute: f64) -> bool {
+    let from = (start_minute - tolerance).rem_euclid(MINUTES_PER_DAY);
+    let length = duration + 2.0 * tolerance;
+    if length >= MINUTES_PER_DAY {
+        return true;
+    }
+
+    (minute - from).rem_euclid(MINUTES_PER_DAY) <= length
+}
+
+pub fn median(values: &[f32]) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut values = values.to_vec();
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
+
+// Returns the temperature spikes within the (minute, value) samples, ordered by minute. A spike
+// starts when the value rises more than rise above the baseline and ends when it is back within
+// half of rise above the baseline. The end of the last spike is None when it is still ongoing.
+pub fn detect_spikes(samples: &[(f64, f32)], baseline: f32, rise: f32) -> Vec<Spike> {
+    let mut spikes = Vec::new();
+    let mut current: Option<Spike> = None;
+
+    for (minute, value) in samples {
+        match current.as_mut() {
+            None => {
+                if *value > baseline + rise {
+                    current = Some(Spike {
+                        start: *minute,
+                        end: None,
+                        peak: *value,
+                    });
+                }
+            }
+            Some(spike) => {
+                if *value > spike.peak {
+                    spike.peak = *value;
+                }
+                if *value <= baseline + rise / 2.0 {
+                    spike.end = Some(*minute);
+                    spikes.push(*spike);
+                    current = None;
+                }
+            }
+        }
+    }
+
+    if let Some(spike) = current {
+        spikes.push(spike);
+    }
+
+    spikes
+}
+
+// Learns the defrost schedule from the (minute of day, value) samples of multiple days. It
+// returns (start minute, duration) tuples for the spikes which are seen on most of the days at
+// about the same time of day.
+pub fn learn_schedule(days: &[Vec<(f64, f32)>], rise: f32) -> Vec<(f64, f64)> {
+    if days.len() < 3 {
+        return Vec::new();
+    }
+
+    // (day index, start minute, duration)
+    let mut spikes: Vec<(usize, f64, f64)> = Vec::new();
+    for (i, samples) in days.iter().enumerate() {
+        let values: Vec<f32> = samples.iter().map(|(_, v)| *v).collect();
+        let baseline = match median(&values) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        for spike in detect_spikes(samples, baseline, rise) {
+            if let Some(end) = spike.end {
+                spikes.push((i, spike.start, end - spike.start));
+            }
+        }
+    }
+    spikes.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let min_days = (days.len() as f64 * LEARN_MIN_DAY_RATIO).ceil() as usize;
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < spikes.len() {
+        let mut j = i;
+        while j < spikes.len() && spikes[j].1 - spikes[i].1 <= LEARN_CLUSTER_MINUTES {
+            j += 1;
+        }
+
+        let cluster = &spikes[i..j];
+        let mut cluster_days: Vec<usize> = cluster.iter().map(|s| s.0).collect();
+        cluster_days.sort_unstable();
+        cluster_days.dedup();
+
+        if cluster_days.len() >= min_days {
+            let starts: Vec<f32> = cluster.iter().map(|s| s.1 as f32).collect();
+            let durations: Vec<f32> = cluster.iter().map(|s| s.2 as f32).collect();
+            if let (Some(start), Some(duration)) = (median(&starts), median(&durations)) {
+                out.push((start as f64, (duration as f64).ceil().max(1.0)));
+            }
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+
+    out
+}
+
+pub async fn create_schedule(s: NewDefrostSchedule) -> Result<DefrostSchedule, Error> {
+    s.validate()?;
+
+    let s: DefrostSchedule = diesel::insert_into(defrost_schedule::table)
+        .values(&s)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "defrost schedule".to_string()))?;
+    Ok(s)
+}
+
+pub async fn get_schedule(id: i32) -> Result<DefrostSchedule, Error> {
+    let s = defrost_schedule::dsl::defrost_schedule
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(s)
+}
+
+pub async fn delete_schedule(id: i32) -> Result<(), Error> {
+    let ra = diesel::delete(defrost_schedule::dsl::defrost_schedule.find(id))
+        .execute(&mut get_async_db_conn().await?)
+        .await?;
+    if ra == 0 {
+        return Err(Error::NotFound(id.to_string()));
+    }
+    Ok(())
+}
+
+// Returns the schedules of the zone and / or device. For a device, this includes the schedules
+// of the zones containing the device.
+pub async fn list_schedules(
+    zone_id: Option<i32>,
+    dev_eui: Option<&str>,
+) -> Result<Vec<DefrostSchedule>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    // SQLite has no arrays, the zone devices are a JSON array.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT s.*
+        FROM defrost_schedule AS s
+        WHERE (?1 IS NOT NULL AND s.zone_id = ?1)
+           OR (?2 IS NOT NULL AND (
+                s.dev_eui = ?2
+                OR s.zone_id IN (
+                    SELECT z.zone_id FROM zone AS z, json_each(z.devices) AS zd
+                    WHERE zd.value = '\x' || ?2
+                )
+           ))
+        ORDER BY s.start_time, s.id
+        "#
+    } else {
+        r#"
+        SELECT s.*
+        FROM defrost_schedule AS s
+        WHERE ($1 IS NOT NULL AND s.zone_id = $1)
+           OR ($2 IS NOT NULL AND (
+                s.dev_eui = $2
+                OR s.zone_id IN (SELECT z.zone_id FROM zone AS z WHERE '\x' || $2 = ANY(z.devices))
+           ))
+        ORDER BY s.start_time, s.id
+        "#
+    };
+
+    let items = diesel::sql_query(query)
+        .bind::<Nullable<Integer>, _>(zone_id)
+        .bind::<Nullable<Text>, _>(dev_eui)
+        .load::<DefrostSchedule>(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, "defrost schedules".to_string()))?;
+
+    Ok(items)
+}
+
+// Replaces the learned schedules of the device by the given (start minute, duration) tuples.
+// The device is put in learning mode (when it was not yet), its schedules are refreshed by
+// the defrost loop until the learning is stopped, also when no schedule was learned.
+pub async fn replace_learned_schedules(
+    dev_eui: &str,
+    schedules: &[(f64, f64)],
+) -> Result<Vec<DefrostSchedule>, Error> {
+    let dev_eui = dev_eui.to_string();
+    let schedules = schedules.to_vec();
+    let mut c = get_async_db_conn().await?;
+
+    db_transaction::<Vec<DefrostSchedule>, Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            let now = Utc::now();
+
+            // Keep the creation timestamp of the learning, windows before this timestamp are
+            // not evaluated.
+            let created_at: DateTime<Utc> = diesel::insert_into(defrost_learning::table)
+                .values((
+                    defrost_learning::dev_eui.eq(&dev_eui),
+                    defrost_learning::created_at.eq(now),
+                    defrost_learning::learned_at.eq(now),
+                ))
+                .on_conflict(defrost_learning::dev_eui)
+                .do_update()
+                .set(defrost_learning::learned_at.eq(now))
+                .returning(defrost_learning::created_at)
+                .get_result(c)
+                .await
+                .map_err(|e| Error::from_diesel(e, dev_eui.clone()))?;
+
+            diesel::delete(
+                defrost_schedule::dsl::defrost_schedule
+                    .filter(defrost_schedule::dsl::dev_eui.eq(&dev_eui))
+                    .filter(defrost_schedule::dsl::learned.eq(true)),
+            )
+            .execute(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, dev_eui.clone()))?;
+
+            let items: Vec<NewDefrostSchedule> = schedules
+                .iter()
+                .map(|(start, duration)| NewDefrostSchedule {
+                    zone_id: None,
+                    dev_eui: Some(dev_eui.clone()),
+                    start_time: start / 60.0,
+                    duration: *duration as i32,
+                    learned: true,
+                    created_at,
+                    updated_at: now,
+                })
+                .collect();
+
+            if items.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            // One insert per schedule, SQLite does not support batch inserts.
+            let mut out = Vec::with_capacity(items.len());
+            for item in &items {
+                out.push(
+                    diesel::insert_into(defrost_schedule::table)
+                        .values(item)
+                        .get_result(c)
+                        .await
+                        .map_err(|e| Error::from_diesel(e, dev_eui.clone()))?,
+                );
+            }
+            Ok(out)
+        })
+    })
+    .await
+}
+
+// Stops learning the defrost schedule of the device and deletes its learned schedules.
+pub async fn stop_learning(dev_eui: &str) -> Result<(), Error> {
+    let dev_eui = dev_eui.to_string();
+    let mut c = get_async_db_conn().await?;
+
+    db_transaction::<(), Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            let ra = diesel::delete(defrost_learning::dsl::defrost_learning.find(&dev_eui))
+                .execute(c)
+                .await
+                .map_err(|e| Error::from_diesel(e, dev_eui.clone()))?;
+            if ra == 0 {
+                return Err(Error::NotFound(dev_eui));
+            }
+
+            diesel::delete(
+                defrost_schedule::dsl::defrost_schedule
+                    .filter(defrost_schedule::dsl::dev_eui.eq(&dev_eui))
+                    .filter(defrost_schedule::dsl::learned.eq(true)),
+            )
+            .execute(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, dev_eui.clone()))?;
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+// Returns the devices in learning mode of which the schedules have not been learned since the
+// given timestamp.
+pub async fn get_learned_dev_euis(learned_before: DateTime<Utc>) -> Result<Vec<String>, Error> {
+    let dev_euis: Vec<String> = defrost_learning::dsl::defrost_learning
+        .select(defrost_learning::dsl::dev_eui)
+        .filter(defrost_learning::dsl::learned_at.lt(learned_before))
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "defrost learning".to_string()))?;
+
+    Ok(dev_euis)
+}
+
+// Returns all the defrost schedules, expanded to the devices of the zone for zone schedules.
+pub async fn get_device_schedules() -> Result<Vec<DeviceSchedule>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT
+            s.id AS schedule_id,
+            lower(hex(d.dev_eui)) AS dev_eui,
+            d.device_type AS device_type_id,
+            s.start_time,
+            s.duration,
+            s.created_at
+        FROM defrost_schedule AS s
+        LEFT JOIN zone AS z ON z.zone_id = s.zone_id
+        INNER JOIN device AS d
+            ON lower(hex(d.dev_eui)) = s.dev_eui
+            OR '\x' || lower(hex(d.dev_eui)) IN (SELECT value FROM json_each(z.devices))
+        "#
+    } else {
+        r#"
+        SELECT
+            s.id AS schedule_id,
+            encode(d.dev_eui, 'hex') AS dev_eui,
+            d.device_type AS device_type_id,
+            s.start_time,
+            s.duration,
+            s.created_at
+        FROM defrost_schedule AS s
+        LEFT JOIN zone AS z ON z.zone_id = s.zone_id
+        INNER JOIN device AS d
+            ON d.dev_eui::text = '\x' || s.dev_eui
+            OR d.dev_eui::text = ANY(z.devices)
+        "#
+    };
+
+    let items = diesel::sql_query(query)
+        .load::<DeviceSchedule>(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, "defrost schedules".to_string()))?;
+
+    Ok(items)
+}
+
+// Returns true when a defrost cycle is expected for the device at the given minute of the
+// (local) day, according to its own schedules or the schedules of its zones.
+pub async fn is_defrost_expected(
+    conn: &mut AsyncDbConnection,
+    dev_eui: &str,
+    minute: f64,
+    tolerance: f64,
+) -> Result<bool, Error> {
+    #[derive(QueryableByName)]
+    struct WindowRow {
+        #[diesel(sql_type = Double)]
+        start_time: f64,
+        #[diesel(sql_type = Integer)]
+        duration: i32,
+    }
+
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT s.start_time, s.duration
+        FROM defrost_schedule AS s
+        WHERE s.dev_eui = ?1
+           OR s.zone_id IN (
+                SELECT z.zone_id FROM zone AS z, json_each(z.devices) AS zd
+                WHERE zd.value = '\x' || ?1
+           )
+        "#
+    } else {
+        r#"
+        SELECT s.start_time, s.duration
+        FROM defrost_schedule AS s
+        WHERE s.dev_eui = $1
+           OR s.zone_id IN (SELECT z.zone_id FROM zone AS z WHERE '\x' || $1 = ANY(z.devices))
+        "#
+    };
+
+    let rows: Vec<WindowRow> = diesel::sql_query(query)
+        .bind::<Text, _>(dev_eui)
+        .load(conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+
+    Ok(rows
+        .iter()
+        .any(|r| in_window(r.start_time * 60.0, r.duration as f64, tolerance, minute)))
+}
+
+// Returns the (timestamp, value) samples of the given device_data column within the given time
+// range, ordered by timestamp. The submission_date of device_data is stored in UTC.
+pub async fn get_samples(
+    dev_eui: &str,
+    column: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<(DateTime<Utc>, f32)>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let query = format!(
+        r#"
+        SELECT submission_date, {value} AS value
+        FROM device_data
+        WHERE dev_eui = {p1}
+          AND {col} IS NOT NULL
+          AND submission_date >= {p2}
+          AND submission_date <= {p3}
+        ORDER BY submission_date
+        "#,
+        col = column,
+        value = if cfg!(feature = "sqlite") {
+            format!("CAST({} AS REAL)", column)
+        } else {
+            format!("{}::float4", column)
+        },
+        p1 = sensor_data::placeholder(1),
+        p2 = sensor_data::placeholder(2),
+        p3 = sensor_data::placeholder(3),
+    );
+
+    let rows: Vec<SampleRow> = diesel::sql_query(query)
+        .bind::<Text, _>(dev_eui)
+        .bind::<Timestamp, _>(from.naive_utc())
+        .bind::<Timestamp, _>(to.naive_utc())
+        .load(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|r| (Utc.from_utc_datetime(&r.submission_date), r.value))
+        .collect())
+}
+
+// Stores the evaluated defrost cycle. It returns false when the window of the schedule was
+// already evaluated for this device.
+pub async fn create_cycle(c: NewDefrostCycle) -> Result<bool, Error> {
+    let ra = diesel::insert_into(defrost_cycle::table)
+        .values(&c)
+        .on_conflict((
+            defrost_cycle::dev_eui,
+            defrost_cycle::schedule_id,
+            defrost_cycle::window_start,
+        ))
+        .do_nothing()
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, c.dev_eui.clone()))?;
+    Ok(ra != 0)
+}
+
+// Returns true when a cycle was already evaluated for a window of the device starting around
+// the given timestamp. The schedule itself is not taken into account as the learned schedules
+// are re-created (and might shift slightly) when they are refreshed.
+pub async fn cycle_exists(dev_eui: &str, window_start: DateTime<Utc>) -> Result<bool, Error> {
+    let margin = chrono::Duration::minutes(LEARN_CLUSTER_MINUTES as i64);
+    let count: i64 = defrost_cycle::dsl::defrost_cycle
+        .filter(defrost_cycle::dsl::dev_eui.eq(dev_eui))
+        .filter(defrost_cycle::dsl::window_start.gt(window_start - margin))
+        .filter(defrost_cycle::dsl::window_start.lt(window_start + margin))
+        .count()
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(count != 0)
+}
+
+pub async fn list_cycles(dev_eui: &str, limit: i64) -> Result<Vec<DefrostCycle>, Error> {
+    let items = defrost_cycle::dsl::defrost_cycle
+        .filter(defrost_cycle::dsl::dev_eui.eq(dev_eui))
+        .order_by(defrost_cycle::dsl::window_start.desc())
+        .limit(limit)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(items)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_in_window() {
+        // 06:00 for 30 minutes, 10 minutes tolerance.
+        assert!(in_window(360.0, 30.0, 10.0, 350.0));
+        assert!(in_window(360.0, 30.0, 10.0, 375.0));
+        assert!(in_window(360.0, 30.0, 10.0, 400.0));
+        assert!(!in_window(360.0, 30.0, 10.0, 349.0));
+        assert!(!in_window(360.0, 30.0, 10.0, 401.0));
+
+        // Crossing midnight: 23:50 for 30 minutes.
+        assert!(in_window(1430.0, 30.0, 0.0, 1435.0));
+        assert!(in_window(1430.0, 30.0, 0.0, 10.0));
+        assert!(!in_window(1430.0, 30.0, 0.0, 30.0));
+
+        // Tolerance crossing midnight: 00:05 with 10 minutes tolerance.
+        assert!(in_window(5.0, 20.0, 10.0, 1438.0));
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(None, median(&[]));
+        assert_eq!(Some(2.0), median(&[3.0, 1.0, 2.0]));
+        assert_eq!(Some(2.5), median(&[4.0, 1.0, 2.0, 3.0]));
+    }
+
+    #[test]
+    fn test_detect_spikes() {
+        let samples = vec![
+            (0.0, -18.0),
+            (10.0, -17.5),
+            (20.0, -8.0),
+            (30.0, 2.0),
+            (40.0, -10.0),
+            (50.0, -16.0),
+            (60.0, -18.0),
+            (70.0, -5.0),
+        ];
+
+        assert_eq!(
+            vec![
+                Spike {
+                    start: 20.0,
+                    end: Some(50.0),
+                    peak: 2.0,
+                },
+                Spike {
+                    start: 70.0,
+                    end: None,
+                    peak: -5.0,
+                },
+            ],
+            detect_spikes(&samples, -18.0, 5.0)
+        );
+
+        assert!(detect_spikes(&samples[..2], -18.0, 5.0).is_empty());
+    }
+
+    #[test]
+    fn test_learn_schedule() {
+        // Defrost at 06:00 (30 minutes) every day, at 18:00 only on the first day.
+        let day = |extra: bool, offset: f64| -> Vec<(f64, f32)> {
+            let mut out = Vec::new();
+            let mut minute = 0.0;
+            while minute < MINUTES_PER_DAY {
+                let m = minute - offset;
+                let value =
+                    if (360.0..390.0).contains(&m) || (extra && (1080.0..1110.0).contains(&m)) {
+                        5.0
+                    } else {
+                        -18.0
+                    };
+                out.push((minute, value));
+                minute += 10.0;
+            }
+            out
+        };
+
+        let days = vec![
+            day(true, 0.0),
+            day(false, 10.0),
+            day(false, 0.0),
+            day(false, 20.0),
+        ];
+        assert_eq!(vec![(365.0, 30.0)], learn_schedule(&days, 5.0));
+
+        // Not enough days.
+        assert!(learn_schedule(&days[..2], 5.0).is_empty());
+    }
+
+    #[test]
+    fn test_cycle_status() {
+        for s in [
+            CycleStatus::Completed,
+            CycleStatus::Missed,
+            CycleStatus::TooLong,
+        ] {
+            assert_eq!(s, CycleStatus::from(i32::from(s)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_learning() {
+        let _guard = test::prepare().await;
+        let dev_eui = "0102030405060708";
+
+        // learn
+        let items = replace_learned_schedules(dev_eui, &[(360.0, 30.0), (1080.0, 25.0)])
+            .await
+            .unwrap();
+        assert_eq!(2, items.len());
+        assert_eq!(6.0, items[0].start_time);
+        let created_at = items[0].created_at;
+
+        let in_learning = |d: Vec<String>| d.contains(&dev_eui.to_string());
+        assert!(in_learning(
+            get_learned_dev_euis(Utc::now() + chrono::Duration::minutes(1))
+                .await
+                .unwrap()
+        ));
+        assert!(!in_learning(
+            get_learned_dev_euis(Utc::now() - chrono::Duration::days(1))
+                .await
+                .unwrap()
+        ));
+
+        // re-learn without pattern, the device stays in learning mode
+        let items = replace_learned_schedules(dev_eui, &[]).await.unwrap();
+        assert!(items.is_empty());
+        assert_eq!(0, get_learned_count(dev_eui).await);
+        assert!(in_learning(
+            get_learned_dev_euis(Utc::now() + chrono::Duration::minutes(1))
+                .await
+                .unwrap()
+        ));
+
+        // re-learn keeps the creation timestamp of the learning
+        let items = replace_learned_schedules(dev_eui, &[(120.0, 20.0)])
+            .await
+            .unwrap();
+        assert_eq!(created_at, items[0].created_at);
+        assert_eq!(1, get_learned_count(dev_eui).await);
+
+        // stop
+        stop_learning(dev_eui).await.unwrap();
+        assert_eq!(0, get_learned_count(dev_eui).await);
+        assert!(!in_learning(
+            get_learned_dev_euis(Utc::now() + chrono::Duration::minutes(1))
+                .await
+                .unwrap()
+        ));
+        assert!(stop_learning(dev_eui).await.is_err());
+    }
+
+    async fn get_learned_count(dev_eui: &str) -> i64 {
+        defrost_schedule::dsl::defrost_schedule
+            .filter(defrost_schedule::dsl::dev_eui.eq(dev_eui))
+            .filter(defrost_schedule::dsl::learned.eq(true))
+            .count()
+            .get_result(&mut get_async_db_conn().await.unwrap())
+            .await
+            .unwrap()
+    }
+}
@@ -91,7 +91,7 @@ impl serialize::ToSql<Text, diesel::sqlite::Sqlite> for DeviceClass {
 
 #[derive(Queryable, QueryableByName, Selectable, PartialEq, Debug, Clone, Serialize)]
 #[diesel(table_name = device)]
-#[diesel(check_for_backend(diesel::pg::Pg))] // ✅ HINT for Diesel to implement Selectable<Pg>
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 
 pub struct Device {
     pub dev_eui: EUI64,
@@ -895,6 +895,8 @@ pub async fn get_with_class_b_c_queue_items(limit: usize) -> Result<Vec<Device>>
                                             -- pending queue-item with timeout_after in the future
                                             (dq.is_pending = true and dq.timeout_after > ?2)
                                         )
+                                        -- queue-item scheduled in the future
+                                        and (dq.scheduled_at is null or dq.scheduled_at <= ?2)
                                 )
                             order by d.dev_eui
                             limit ?1
@@ -928,6 +930,8 @@ pub async fn get_with_class_b_c_queue_items(limit: usize) -> Result<Vec<Device>>
                                             -- pending queue-item with timeout_after in the future
                                             (dq.is_pending = true and dq.timeout_after > $2)
                                         )
+                                        -- queue-item scheduled in the future
+                                        and (dq.scheduled_at is null or dq.scheduled_at <= $2)
                                 )
                             order by d.dev_eui
                             limit $1
@@ -1223,9 +1227,31 @@ pub mod test {
         // device in class C / downlink is pending but has expired.
         qi.is_pending = true;
         qi.timeout_after = Some(Utc::now() - Duration::try_seconds(10).unwrap());
-        let _ = device_queue::update_item(qi).await.unwrap();
+        let qi = device_queue::update_item(qi).await.unwrap();
         let res = get_with_class_b_c_queue_items(10).await.unwrap();
         assert_eq!(1, res.len());
+
+        // device in class C / downlink is scheduled in the future.
+        device_queue::delete_item(&qi.id).await.unwrap();
+        let _ = device_queue::enqueue_item(device_queue::DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 1,
+            scheduled_at: Some(Utc::now() + Duration::try_seconds(10).unwrap()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let _ = partial_update(
+            d.dev_eui,
+            &DeviceChangeset {
+                scheduler_run_after: Some(None),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let res = get_with_class_b_c_queue_items(10).await.unwrap();
+        assert_eq!(0, res.len());
     }
 
     #[test]
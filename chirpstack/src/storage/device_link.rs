@@ -0,0 +1,419 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Float, Integer, Nullable, Text};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use super::fields::sql_types::{Timestamptz, Uuid as SqlUuid};
+use super::schema::{device_link, device_link_sample};
+use super::{error::Error, fields, get_async_db_conn};
+
+// Signal tag values, see the device tags.
+pub const SIGNAL_GOOD: &str = "good-signal";
+pub const SIGNAL_FAIR: &str = "fair-signal";
+pub const SIGNAL_WEAK: &str = "weak-signal";
+pub const SIGNAL_NONE: &str = "no-signal";
+
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = device_link_sample)]
+pub struct LinkSample {
+    pub dev_eui: String,
+    pub time: DateTime<Utc>,
+    pub gateway_id: String,
+    pub rssi: i32,
+    pub snr: f32,
+    pub margin: Option<f32>,
+    pub gateway_count: i32,
+}
+
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = device_link)]
+pub struct DeviceLink {
+    pub dev_eui: String,
+    pub gateway_id: Option<String>,
+    pub rssi: Option<f32>,
+    pub snr: Option<f32>,
+    pub margin: Option<f32>,
+    pub margin_baseline: Option<f32>,
+    pub signal: String,
+    pub uplink_count: i32,
+    pub last_seen_at: DateTime<Utc>,
+    pub degraded_notified_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for DeviceLink {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        DeviceLink {
+            dev_eui: String::new(),
+            gateway_id: None,
+            rssi: None,
+            snr: None,
+            margin: None,
+            margin_baseline: None,
+            signal: SIGNAL_NONE.into(),
+            uplink_count: 0,
+            last_seen_at: now,
+            degraded_notified_at: None,
+            updated_at: now,
+        }
+    }
+}
+
+// Averages of the link samples of a device, per gateway.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct GatewayLinkStats {
+    #[diesel(sql_type = Text)]
+    pub dev_eui: String,
+
+    #[diesel(sql_type = Text)]
+    pub gateway_id: String,
+
+    #[diesel(sql_type = Integer)]
+    pub uplink_count: i32,
+
+    #[diesel(sql_type = Float)]
+    pub rssi: f32,
+
+    #[diesel(sql_type = Float)]
+    pub snr: f32,
+
+    #[diesel(sql_type = Nullable<Float>)]
+    pub margin: Option<f32>,
+
+    #[diesel(sql_type = Timestamptz)]
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[derive(QueryableByName, Debug, Clone)]
+pub struct LinkReportItem {
+    #[diesel(sql_type = Text)]
+    pub dev_eui: String,
+
+    #[diesel(sql_type = Text)]
+    pub device_name: String,
+
+    #[diesel(sql_type = Nullable<Text>)]
+    pub zone_name: Option<String>,
+
+    #[diesel(sql_type = Text)]
+    pub signal: String,
+
+    #[diesel(sql_type = Nullable<Text>)]
+    pub gateway_id: Option<String>,
+
+    #[diesel(sql_type = Nullable<Text>)]
+    pub gateway_name: Option<String>,
+
+    #[diesel(sql_type = Nullable<Float>)]
+    pub rssi: Option<f32>,
+
+    #[diesel(sql_type = Nullable<Float>)]
+    pub snr: Option<f32>,
+
+    #[diesel(sql_type = Nullable<Float>)]
+    pub margin: Option<f32>,
+
+    #[diesel(sql_type = Nullable<Float>)]
+    pub margin_baseline: Option<f32>,
+
+    #[diesel(sql_type = Integer)]
+    pub uplink_count: i32,
+
+    #[diesel(sql_type = Bool)]
+    pub degraded: bool,
+
+    #[diesel(sql_type = Timestamptz)]
+    pub last_seen_at: DateTime<Utc>,
+
+    #[diesel(sql_type = Timestamptz)]
+    pub updated_at: DateTime<Utc>,
+}
+
+pub async fn save_sample(s: &LinkSample) -> Result<(), Error> {
+    diesel::insert_into(device_link_sample::table)
+        .values(s)
+        .on_conflict_do_nothing()
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, s.dev_eui.clone()))?;
+    Ok(())
+}
+
+// Returns the averages of the link samples received within the given interval, per device and
+// gateway.
+pub async fn get_gateway_stats(
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<GatewayLinkStats>, Error> {
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT
+            dev_eui,
+            gateway_id,
+            COUNT(*) AS uplink_count,
+            AVG(rssi) AS rssi,
+            AVG(snr) AS snr,
+            AVG(margin) AS margin,
+            MAX(time) AS last_seen_at
+        FROM device_link_sample
+        WHERE julianday(time) > julianday(?1) AND julianday(time) <= julianday(?2)
+        GROUP BY dev_eui, gateway_id
+        ORDER BY dev_eui, gateway_id
+        "#
+    } else {
+        r#"
+        SELECT
+            dev_eui,
+            gateway_id,
+            COUNT(*)::int4 AS uplink_count,
+            AVG(rssi)::float4 AS rssi,
+            AVG(snr)::float4 AS snr,
+            AVG(margin)::float4 AS margin,
+            MAX(time) AS last_seen_at
+        FROM device_link_sample
+        WHERE time > $1 AND time <= $2
+        GROUP BY dev_eui, gateway_id
+        ORDER BY dev_eui, gateway_id
+        "#
+    };
+
+    let stats = diesel::sql_query(query)
+        .bind::<Timestamptz, _>(since)
+        .bind::<Timestamptz, _>(until)
+        .load::<GatewayLinkStats>(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "link stats".to_string()))?;
+
+    Ok(stats)
+}
+
+// Creates or updates the link state of the device. The degradation notification timestamp is not
+// touched, use set_degraded_notified for that.
+pub async fn upsert(state: &DeviceLink) -> Result<DeviceLink, Error> {
+    let l: DeviceLink = diesel::insert_into(device_link::table)
+        .values(state)
+        .on_conflict(device_link::dev_eui)
+        .do_update()
+        .set((
+            device_link::gateway_id.eq(&state.gateway_id),
+            device_link::rssi.eq(&state.rssi),
+            device_link::snr.eq(&state.snr),
+            device_link::margin.eq(&state.margin),
+            device_link::margin_baseline.eq(&state.margin_baseline),
+            device_link::signal.eq(&state.signal),
+            device_link::uplink_count.eq(&state.uplink_count),
+            device_link::last_seen_at.eq(&state.last_seen_at),
+            device_link::updated_at.eq(&state.updated_at),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, state.dev_eui.clone()))?;
+    Ok(l)
+}
+
+pub async fn set_degraded_notified(
+    dev_eui: &str,
+    notified_at: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    diesel::update(device_link::dsl::device_link.find(dev_eui))
+        .set(device_link::degraded_notified_at.eq(notified_at))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(())
+}
+
+// Sets the signal tag of the device. It returns false when the tag already had this value.
+pub async fn set_signal_tag(dev_eui: &str, signal: &str) -> Result<bool, Error> {
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        UPDATE device
+        SET tags = json_set(tags, '$.signal', ?2)
+        WHERE lower(hex(dev_eui)) = ?1
+          AND json_extract(tags, '$.signal') IS NOT ?2
+        "#
+    } else {
+        r#"
+        UPDATE device
+        SET tags = jsonb_set(tags, '{signal}', to_jsonb($2::text))
+        WHERE dev_eui = decode($1, 'hex')
+          AND tags->>'signal' IS DISTINCT FROM $2
+        "#
+    };
+
+    let ra = diesel::sql_query(query)
+        .bind::<Text, _>(dev_eui)
+        .bind::<Text, _>(signal)
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(ra != 0)
+}
+
+// Deletes the link samples older than the given timestamp and the link state of the devices
+// which have not been seen since.
+pub async fn delete_older_than(before: DateTime<Utc>) -> Result<usize, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let ra = diesel::delete(device_link_sample::dsl::device_link_sample)
+        .filter(device_link_sample::dsl::time.lt(before))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, "link samples".to_string()))?;
+    diesel::delete(device_link::dsl::device_link)
+        .filter(device_link::dsl::last_seen_at.lt(before))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, "link states".to_string()))?;
+
+    Ok(ra)
+}
+
+// Returns the link report of the tenant devices, the devices with the lowest margin first. A
+// device is flagged as degraded once its degradation has been notified. When only_weak is set,
+// only the devices with a weak (or no) signal and the degraded devices are returned.
+pub async fn get_report(tenant_id: &Uuid, only_weak: bool) -> Result<Vec<LinkReportItem>, Error> {
+    // SQLite has no arrays, the zone devices are a JSON array.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT
+            l.dev_eui,
+            d.name AS device_name,
+            (
+                SELECT z.zone_name FROM zone AS z, json_each(z.devices) AS zd
+                WHERE zd.value = '\x' || lower(hex(d.dev_eui)) LIMIT 1
+            ) AS zone_name,
+            l.signal,
+            l.gateway_id,
+            g.name AS gateway_name,
+            l.rssi,
+            l.snr,
+            l.margin,
+            l.margin_baseline,
+            l.uplink_count,
+            l.degraded_notified_at IS NOT NULL AS degraded,
+            l.last_seen_at,
+            l.updated_at
+        FROM device_link AS l
+        INNER JOIN device AS d ON lower(hex(d.dev_eui)) = l.dev_eui
+        LEFT JOIN gateway AS g ON lower(hex(g.gateway_id)) = l.gateway_id
+        WHERE d.tenant_id = ?1
+          AND (?2 = false OR l.signal IN (?3, ?4) OR l.degraded_notified_at IS NOT NULL)
+        ORDER BY l.margin ASC NULLS FIRST, l.snr ASC NULLS FIRST, l.dev_eui
+        "#
+    } else {
+        r#"
+        SELECT
+            l.dev_eui,
+            d.name AS device_name,
+            (SELECT z.zone_name FROM zone AS z WHERE d.dev_eui::text = ANY(z.devices) LIMIT 1) AS zone_name,
+            l.signal,
+            l.gateway_id,
+            g.name AS gateway_name,
+            l.rssi,
+            l.snr,
+            l.margin,
+            l.margin_baseline,
+            l.uplink_count,
+            l.degraded_notified_at IS NOT NULL AS degraded,
+            l.last_seen_at,
+            l.updated_at
+        FROM device_link AS l
+        INNER JOIN device AS d ON d.dev_eui::text = '\x' || l.dev_eui
+        LEFT JOIN gateway AS g ON g.gateway_id = decode(l.gateway_id, 'hex')
+        WHERE d.tenant_id = $1
+          AND ($2 = false OR l.signal IN ($3, $4) OR l.degraded_notified_at IS NOT NULL)
+        ORDER BY l.margin ASC NULLS FIRST, l.snr ASC NULLS FIRST, l.dev_eui
+        "#
+    };
+
+    let items = diesel::sql_query(query)
+        .bind::<SqlUuid, _>(fields::Uuid::from(tenant_id))
+        .bind::<Bool, _>(only_weak)
+        .bind::<Text, _>(SIGNAL_WEAK)
+        .bind::<Text, _>(SIGNAL_NONE)
+        .load::<LinkReportItem>(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+
+    Ok(items)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+    use chrono::Duration;
+
+    #[tokio::test]
+    async fn test_device_link() {
+        let _guard = test::prepare().await;
+        let now = Utc::now();
+
+        for (i, (gateway_id, snr)) in [
+            ("0101010101010101", 2.0),
+            ("0101010101010101", 4.0),
+            ("0202020202020202", 8.0),
+        ]
+        .iter()
+        .enumerate()
+        {
+            save_sample(&LinkSample {
+                dev_eui: "0102030405060708".into(),
+                time: now - Duration::minutes(i as i64),
+                gateway_id: gateway_id.to_string(),
+                rssi: -100,
+                snr: *snr,
+                margin: Some(snr + 20.0),
+                gateway_count: 1,
+            })
+            .await
+            .unwrap();
+        }
+
+        // stats
+        let stats = get_gateway_stats(now - Duration::hours(1), now)
+            .await
+            .unwrap();
+        assert_eq!(2, stats.len());
+        assert_eq!("0101010101010101", stats[0].gateway_id);
+        assert_eq!(2, stats[0].uplink_count);
+        assert_eq!(3.0, stats[0].snr);
+        assert_eq!(Some(23.0), stats[0].margin);
+        assert_eq!(1, stats[1].uplink_count);
+
+        // upsert keeps the notification timestamp
+        let state = upsert(&DeviceLink {
+            dev_eui: "0102030405060708".into(),
+            signal: SIGNAL_GOOD.into(),
+            uplink_count: 3,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        set_degraded_notified(&state.dev_eui, Some(now))
+            .await
+            .unwrap();
+        let state = upsert(&DeviceLink {
+            signal: SIGNAL_WEAK.into(),
+            ..state
+        })
+        .await
+        .unwrap();
+        assert_eq!(SIGNAL_WEAK, state.signal);
+        assert!(state.degraded_notified_at.is_some());
+
+        // delete
+        assert_eq!(
+            3,
+            delete_older_than(now + Duration::minutes(1)).await.unwrap()
+        );
+        assert!(get_gateway_stats(now - Duration::hours(1), now)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -20,8 +20,8 @@ use chirpstack_api::internal;
 #[derive(Clone, Queryable, Insertable, Debug, PartialEq, Eq)]
 #[diesel(table_name = device_profile)]
 pub struct DeviceProfile {
-    pub id: Uuid,
-    pub tenant_id: Uuid,
+    pub id: fields::Uuid,
+    pub tenant_id: fields::Uuid,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub name: String,
@@ -44,10 +44,10 @@ pub struct DeviceProfile {
     pub region_config_id: Option<String>,
     pub allow_roaming: bool,
     pub rx1_delay: i16,
-    pub abp_params: Option<serde_json::Value>,
-    pub class_b_params: Option<serde_json::Value>,
-    pub class_c_params: Option<serde_json::Value>,
-    pub relay_params: Option<serde_json::Value>,
+    pub abp_params: Option<fields::JsonValue>,
+    pub class_b_params: Option<fields::JsonValue>,
+    pub class_c_params: Option<fields::JsonValue>,
+    pub relay_params: Option<fields::JsonValue>,
     pub class_b_timeout: Option<i32>,
     pub class_b_ping_slot_nb_k: Option<i32>,
     pub class_b_ping_slot_dr: Option<i16>,
@@ -79,6 +79,10 @@ pub struct DeviceProfile {
     pub relay_notify_limit_bucket_size: Option<i16>,
     pub relay_global_uplink_limit_bucket_size: Option<i16>,
     pub relay_overall_limit_bucket_size: Option<i16>,
+    pub ts003_enabled: Option<bool>,
+    pub ts003_f_port: Option<i16>,
+    pub downlink_retry_count: Option<i16>,
+    pub downlink_retry_backoff: Option<i32>,
 }
 
 impl DeviceProfile {
@@ -91,6 +95,28 @@ impl DeviceProfile {
             return Err(Error::Validation("RX1 Delay must be between 0 - 15".into()));
         }
 
+        if let Some(f_port) = self.ts003_f_port {
+            if !(0..=223).contains(&f_port) {
+                return Err(Error::Validation(
+                    "TS003 FPort must be between 0 - 223".into(),
+                ));
+            }
+        }
+
+        if let Some(count) = self.downlink_retry_count {
+            if !(0..=15).contains(&count) {
+                return Err(Error::Validation(
+                    "Downlink retry count must be between 0 - 15".into(),
+                ));
+            }
+        }
+
+        if self.downlink_retry_backoff.unwrap_or(0) < 0 {
+            return Err(Error::Validation(
+                "Downlink retry backoff must not be negative".into(),
+            ));
+        }
+
         Ok(())
     }
 }
@@ -100,8 +126,8 @@ impl Default for DeviceProfile {
         let now = Utc::now();
 
         DeviceProfile {
-            id: Uuid::new_v4(),
-            tenant_id: Uuid::nil(),
+            id: Uuid::new_v4().into(),
+            tenant_id: Uuid::nil().into(),
             created_at: now,
             updated_at: now,
             name: "".into(),
@@ -157,6 +183,10 @@ impl Default for DeviceProfile {
             relay_notify_limit_bucket_size: Some(0),
             relay_global_uplink_limit_bucket_size: Some(0),
             relay_overall_limit_bucket_size: Some(0),
+            ts003_enabled: Some(false),
+            ts003_f_port: Some(0),
+            downlink_retry_count: Some(0),
+            downlink_retry_backoff: Some(0),
             allow_roaming: false,
             rx1_delay: 0,
         }
@@ -164,6 +194,28 @@ impl Default for DeviceProfile {
 }
 
 impl DeviceProfile {
+    // Returns the FPort of the Application Layer Clock Synchronization package, None when the
+    // package is not enabled.
+    pub fn get_ts003_f_port(&self) -> Option<u8> {
+        if !self.ts003_enabled.unwrap_or(false) {
+            return None;
+        }
+
+        match self.ts003_f_port.unwrap_or(0) {
+            0 => Some(lrwn::LA_FPORT_CLOCK_SYNC),
+            v => Some(v as u8),
+        }
+    }
+
+    // Returns the max. number of times an unacknowledged confirmed downlink is re-enqueued, None
+    // when the retry policy is not enabled.
+    pub fn get_downlink_retry_count(&self) -> Option<u16> {
+        match self.downlink_retry_count.unwrap_or(0) {
+            0 => None,
+            v => Some(v as u16),
+        }
+    }
+
     pub fn reset_session_to_boot_params(&self, ds: &mut internal::DeviceSession) {
         ds.mac_version = self.mac_version.to_proto().into();
         ds.class_b_ping_slot_dr = self.class_b_ping_slot_dr.unwrap_or(0) as u32;
@@ -247,9 +299,9 @@ pub async fn get_internal(device_type_id: i64) -> Result<Uuid, Error> {
         .order_by(dp::created_at.desc())
         .select(dp::id);
 
-    let result: Option<Uuid> = query.first::<Uuid>(&mut conn).await.optional()?;
+    let result: Option<fields::Uuid> = query.first(&mut conn).await.optional()?;
     match result {
-        Some(uuid) => Ok(uuid),
+        Some(uuid) => Ok(uuid.into()),
         None => Err(anyhow::anyhow!(
             "device_profile not found for device_type_id {}",
             device_type_id
@@ -318,6 +370,10 @@ pub async fn update(dp: DeviceProfile) -> Result<DeviceProfile, Error> {
             device_profile::relay_global_uplink_limit_bucket_size
                 .eq(&dp.relay_global_uplink_limit_bucket_size),
             device_profile::relay_overall_limit_bucket_size.eq(&dp.relay_overall_limit_bucket_size),
+            device_profile::ts003_enabled.eq(&dp.ts003_enabled),
+            device_profile::ts003_f_port.eq(&dp.ts003_f_port),
+            device_profile::downlink_retry_count.eq(&dp.downlink_retry_count),
+            device_profile::downlink_retry_backoff.eq(&dp.downlink_retry_backoff),
             device_profile::allow_roaming.eq(&dp.allow_roaming),
             device_profile::rx1_delay.eq(&dp.rx1_delay),
         ))
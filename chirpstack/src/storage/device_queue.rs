@@ -6,7 +6,7 @@ use tracing::info;
 use uuid::Uuid;
 
 use super::schema::device_queue_item;
-use super::{error::Error, fields, get_async_db_conn};
+use super::{db_transaction, error::Error, fields, get_async_db_conn};
 use lrwn::EUI64;
 
 #[derive(Queryable, Insertable, PartialEq, Eq, Debug, Clone)]
@@ -23,6 +23,10 @@ pub struct DeviceQueueItem {
     pub timeout_after: Option<DateTime<Utc>>,
     pub is_encrypted: bool,
     pub expires_at: Option<DateTime<Utc>>,
+    pub scheduled_at: Option<DateTime<Utc>>,
+    pub priority: i16,
+    pub coalesce_key: Option<String>,
+    pub retry_count: i16,
 }
 
 impl DeviceQueueItem {
@@ -39,6 +43,14 @@ impl DeviceQueueItem {
             ));
         }
 
+        if let Some(k) = &self.coalesce_key {
+            if k.is_empty() || k.len() > 100 {
+                return Err(Error::Validation(
+                    "Coalesce key must be between 1 - 100 characters".to_string(),
+                ));
+            }
+        }
+
         Ok(())
     }
 }
@@ -59,19 +71,49 @@ impl Default for DeviceQueueItem {
             timeout_after: None,
             is_encrypted: false,
             expires_at: None,
+            scheduled_at: None,
+            priority: 0,
+            coalesce_key: None,
+            retry_count: 0,
         }
     }
 }
 
+/// Enqueues the given queue-item. In case the queue-item has a coalesce key, queue-items of the
+/// same device with the same key that have not yet been sent are replaced by this queue-item.
 pub async fn enqueue_item(qi: DeviceQueueItem) -> Result<DeviceQueueItem, Error> {
     qi.validate()?;
 
-    let qi: DeviceQueueItem = diesel::insert_into(device_queue_item::table)
-        .values(&qi)
-        .get_result(&mut get_async_db_conn().await?)
-        .await
-        .map_err(|e| Error::from_diesel(e, qi.id.to_string()))?;
-    info!(id = %qi.id, dev_eui = %qi.dev_eui, "Device queue-item enqueued");
+    let mut c = get_async_db_conn().await?;
+    let (qi, coalesced): (DeviceQueueItem, usize) =
+        db_transaction::<(DeviceQueueItem, usize), Error, _>(&mut c, |c| {
+            Box::pin(async move {
+                let coalesced = match &qi.coalesce_key {
+                    Some(k) => diesel::delete(
+                        device_queue_item::dsl::device_queue_item.filter(
+                            device_queue_item::dev_eui
+                                .eq(&qi.dev_eui)
+                                .and(device_queue_item::coalesce_key.eq(k))
+                                .and(device_queue_item::is_pending.eq(false)),
+                        ),
+                    )
+                    .execute(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, qi.dev_eui.to_string()))?,
+                    None => 0,
+                };
+
+                let qi: DeviceQueueItem = diesel::insert_into(device_queue_item::table)
+                    .values(&qi)
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, qi.id.to_string()))?;
+
+                Ok((qi, coalesced))
+            })
+        })
+        .await?;
+    info!(id = %qi.id, dev_eui = %qi.dev_eui, coalesced = coalesced, "Device queue-item enqueued");
     Ok(qi)
 }
 
@@ -99,6 +141,25 @@ pub async fn update_item(qi: DeviceQueueItem) -> Result<DeviceQueueItem, Error>
     Ok(qi)
 }
 
+/// Resets the pending queue-item such that it will be sent again (using a new FCntDown), not
+/// before the given scheduled_at timestamp. This increments the retry_count of the queue-item.
+pub async fn retry_item(id: &Uuid, scheduled_at: DateTime<Utc>) -> Result<DeviceQueueItem, Error> {
+    let qi: DeviceQueueItem =
+        diesel::update(device_queue_item::dsl::device_queue_item.find(&fields::Uuid::from(id)))
+            .set((
+                device_queue_item::is_pending.eq(false),
+                device_queue_item::f_cnt_down.eq(None::<i64>),
+                device_queue_item::timeout_after.eq(None::<DateTime<Utc>>),
+                device_queue_item::scheduled_at.eq(Some(scheduled_at)),
+                device_queue_item::retry_count.eq(device_queue_item::retry_count + 1),
+            ))
+            .get_result(&mut get_async_db_conn().await?)
+            .await
+            .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    info!(id = %qi.id, dev_eui = %qi.dev_eui, retry_count = qi.retry_count, "Device queue-item re-enqueued for retry");
+    Ok(qi)
+}
+
 pub async fn delete_item(id: &Uuid) -> Result<(), Error> {
     let ra =
         diesel::delete(device_queue_item::dsl::device_queue_item.find(&fields::Uuid::from(id)))
@@ -112,10 +173,24 @@ pub async fn delete_item(id: &Uuid) -> Result<(), Error> {
 }
 
 /// It returns the device queue-item and a bool indicating if there are more items in the queue.
+///
+/// Queue-items scheduled in the future are ignored. The pending queue-item (if any) is always
+/// returned first, followed by the queue-items with the highest priority (FIFO within the same
+/// priority).
 pub async fn get_next_for_dev_eui(dev_eui: &EUI64) -> Result<(DeviceQueueItem, bool), Error> {
     let items: Vec<DeviceQueueItem> = device_queue_item::dsl::device_queue_item
-        .filter(device_queue_item::dev_eui.eq(&dev_eui))
-        .order_by(device_queue_item::created_at)
+        .filter(
+            device_queue_item::dev_eui.eq(&dev_eui).and(
+                device_queue_item::scheduled_at
+                    .is_null()
+                    .or(device_queue_item::scheduled_at.le(Utc::now())),
+            ),
+        )
+        .order_by((
+            device_queue_item::is_pending.desc(),
+            device_queue_item::priority.desc(),
+            device_queue_item::created_at,
+        ))
         .limit(2)
         .load(&mut get_async_db_conn().await?)
         .await
@@ -303,4 +378,106 @@ pub mod test {
         let max_f_cnt = get_max_f_cnt_down(d.dev_eui).await.unwrap();
         assert_eq!(Some(10), max_f_cnt);
     }
+
+    #[tokio::test]
+    async fn test_scheduling_priority_and_coalescing() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+
+        // scheduled in the future
+        let qi_scheduled = enqueue_item(DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 10,
+            data: vec![0x01],
+            scheduled_at: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(get_next_for_dev_eui(&d.dev_eui).await.is_err());
+
+        // default priority
+        let qi_bulk = enqueue_item(DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 10,
+            data: vec![0x02],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // higher priority, enqueued later
+        let qi_relay = enqueue_item(DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 10,
+            data: vec![0x03],
+            priority: 10,
+            coalesce_key: Some("relay-1".into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let (qi, more) = get_next_for_dev_eui(&d.dev_eui).await.unwrap();
+        assert_eq!(qi_relay, qi);
+        assert!(more);
+
+        // coalesce the unsent relay queue-item
+        let qi_relay_new = enqueue_item(DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 10,
+            data: vec![0x04],
+            priority: 10,
+            coalesce_key: Some("relay-1".into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(get_item(&qi_relay.id).await.is_err());
+        let (qi, _) = get_next_for_dev_eui(&d.dev_eui).await.unwrap();
+        assert_eq!(qi_relay_new, qi);
+
+        // pending queue-items are not coalesced
+        let mut qi = qi_relay_new.clone();
+        qi.is_pending = true;
+        update_item(qi).await.unwrap();
+        enqueue_item(DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 10,
+            data: vec![0x05],
+            priority: 10,
+            coalesce_key: Some("relay-1".into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert!(get_item(&qi_relay_new.id).await.is_ok());
+
+        let items = get_for_dev_eui(&d.dev_eui).await.unwrap();
+        assert_eq!(4, items.len());
+        assert!(items.iter().any(|qi| qi.id == qi_scheduled.id));
+        assert!(items.iter().any(|qi| qi.id == qi_bulk.id));
+
+        // retry the pending queue-item
+        let qi = retry_item(&qi_relay_new.id, Utc::now()).await.unwrap();
+        assert!(!qi.is_pending);
+        assert_eq!(None, qi.f_cnt_down);
+        assert_eq!(1, qi.retry_count);
+
+        // invalid coalesce key
+        assert!(enqueue_item(DeviceQueueItem {
+            dev_eui: d.dev_eui,
+            f_port: 10,
+            coalesce_key: Some("".into()),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+    }
 }
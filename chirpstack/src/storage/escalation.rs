@@ -0,0 +1,759 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use diesel_async::RunQueryDsl;
+use serde_json::json;
+use tracing::info;
+use uuid::Uuid;
+
+use super::error::Error;
+use super::schema::{
+    alarm_audit_log, alarm_escalation, escalation_policy, escalation_policy_target, escalation_step,
+};
+use super::{db_transaction, fields, get_async_db_conn, AsyncDbConnection};
+
+// Escalation policy of a tenant. A policy can be used by multiple alarms and zones, when an
+// alarm triggers, the escalation steps are executed one after the other until the alarm is
+// acknowledged.
+#[derive(Queryable, PartialEq, Debug, Clone)]
+#[diesel(table_name = escalation_policy)]
+pub struct EscalationPolicy {
+    pub id: i32,
+    pub tenant_id: fields::Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        EscalationPolicy {
+            id: 0,
+            tenant_id: Uuid::nil().into(),
+            name: "".into(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+impl EscalationPolicy {
+    fn validate(&self) -> Result<(), Error> {
+        if self.name.trim().is_empty() {
+            return Err(Error::Validation(
+                "Escalation policy name cannot be empty".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Escalation step. The step is executed when the alarm has not been acknowledged delay_minutes
+// after the previous step (or after the alarm triggered in case of the first step).
+#[derive(Queryable, PartialEq, Debug, Clone)]
+#[diesel(table_name = escalation_step)]
+pub struct EscalationStep {
+    pub id: i32,
+    pub policy_id: i32,
+    pub level: i32,
+    pub delay_minutes: i32,
+    pub channel: fields::EscalationChannel,
+    pub user_id: fields::UuidArray,
+    pub webhook_url: String,
+}
+
+impl Default for EscalationStep {
+    fn default() -> Self {
+        EscalationStep {
+            id: 0,
+            policy_id: 0,
+            level: 0,
+            delay_minutes: 0,
+            channel: fields::EscalationChannel::NOTIFICATION,
+            user_id: fields::UuidArray::default(),
+            webhook_url: "".into(),
+        }
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = escalation_step)]
+struct NewEscalationStep {
+    policy_id: i32,
+    level: i32,
+    delay_minutes: i32,
+    channel: fields::EscalationChannel,
+    user_id: fields::UuidArray,
+    webhook_url: String,
+}
+
+// Escalation of a triggered alarm. The level is the last executed step level (0 when no step
+// has been executed yet), next_at is None once all the steps have been executed.
+#[derive(Queryable, QueryableByName, PartialEq, Debug, Clone)]
+#[diesel(table_name = alarm_escalation)]
+pub struct AlarmEscalation {
+    pub id: i32,
+    pub alarm_id: i32,
+    pub policy_id: i32,
+    pub dev_eui: String,
+    pub message: String,
+    pub level: i32,
+    pub triggered_at: DateTime<Utc>,
+    pub next_at: Option<DateTime<Utc>>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub acknowledged_by: Option<fields::Uuid>,
+}
+
+// Validates the steps and returns them ordered by level. The levels must be unique, starting
+// at 1.
+fn validate_steps(steps: &[EscalationStep]) -> Result<Vec<EscalationStep>, Error> {
+    if steps.is_empty() {
+        return Err(Error::Validation(
+            "Escalation policy must have at least one step".into(),
+        ));
+    }
+
+    let mut steps = steps.to_vec();
+    steps.sort_by_key(|s| s.level);
+
+    for (i, s) in steps.iter().enumerate() {
+        if s.level != i as i32 + 1 {
+            return Err(Error::Validation(
+                "Escalation step levels must be consecutive, starting at 1".into(),
+            ));
+        }
+        if s.delay_minutes < 0 {
+            return Err(Error::Validation(
+                "Escalation step delay must not be negative".into(),
+            ));
+        }
+        match s.channel {
+            fields::EscalationChannel::NOTIFICATION | fields::EscalationChannel::SMS => {
+                if s.user_id.iter().flatten().next().is_none() {
+                    return Err(Error::Validation(format!(
+                        "Escalation step {} must have at least one user",
+                        s.level
+                    )));
+                }
+            }
+            fields::EscalationChannel::WEBHOOK => {
+                if !s.webhook_url.starts_with("http://") && !s.webhook_url.starts_with("https://") {
+                    return Err(Error::Validation(format!(
+                        "Escalation step {} must have a http(s) webhook URL",
+                        s.level
+                    )));
+                }
+            }
+        }
+    }
+
+    Ok(steps)
+}
+
+// The steps are inserted one by one, as diesel-async does not support batch inserts for
+// SQLite.
+async fn insert_steps(
+    conn: &mut AsyncDbConnection,
+    policy_id: i32,
+    steps: &[EscalationStep],
+) -> Result<(), Error> {
+    for s in steps {
+        diesel::insert_into(escalation_step::table)
+            .values(&NewEscalationStep {
+                policy_id,
+                level: s.level,
+                delay_minutes: s.delay_minutes,
+                channel: s.channel,
+                user_id: s.user_id.clone(),
+                webhook_url: s.webhook_url.clone(),
+            })
+            .execute(conn)
+            .await
+            .map_err(|e| Error::from_diesel(e, policy_id.to_string()))?;
+    }
+    Ok(())
+}
+
+pub async fn create_policy(
+    p: EscalationPolicy,
+    steps: &[EscalationStep],
+) -> Result<EscalationPolicy, Error> {
+    p.validate()?;
+    let steps = validate_steps(steps)?;
+
+    let mut c = get_async_db_conn().await?;
+    let p: EscalationPolicy = db_transaction::<EscalationPolicy, Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            let now = Utc::now();
+            let p: EscalationPolicy = diesel::insert_into(escalation_policy::table)
+                .values((
+                    escalation_policy::tenant_id.eq(&p.tenant_id),
+                    escalation_policy::name.eq(&p.name),
+                    escalation_policy::created_at.eq(now),
+                    escalation_policy::updated_at.eq(now),
+                ))
+                .get_result(c)
+                .await
+                .map_err(|e| Error::from_diesel(e, p.name.clone()))?;
+
+            insert_steps(c, p.id, &steps).await?;
+
+            Ok(p)
+        })
+    })
+    .await?;
+
+    info!(policy_id = p.id, tenant_id = %p.tenant_id, "Escalation policy created");
+    Ok(p)
+}
+
+pub async fn get_policy(id: i32) -> Result<EscalationPolicy, Error> {
+    let p = escalation_policy::dsl::escalation_policy
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(p)
+}
+
+pub async fn get_steps(policy_id: i32) -> Result<Vec<EscalationStep>, Error> {
+    let items = escalation_step::dsl::escalation_step
+        .filter(escalation_step::dsl::policy_id.eq(policy_id))
+        .order_by(escalation_step::dsl::level)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, policy_id.to_string()))?;
+    Ok(items)
+}
+
+// Updates the policy and replaces its steps. Escalations in progress continue with the new
+// steps.
+pub async fn update_policy(
+    p: EscalationPolicy,
+    steps: &[EscalationStep],
+) -> Result<EscalationPolicy, Error> {
+    p.validate()?;
+    let steps = validate_steps(steps)?;
+
+    let mut c = get_async_db_conn().await?;
+    let p: EscalationPolicy = db_transaction::<EscalationPolicy, Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            let p: EscalationPolicy =
+                diesel::update(escalation_policy::dsl::escalation_policy.find(p.id))
+                    .set((
+                        escalation_policy::updated_at.eq(Utc::now()),
+                        escalation_policy::name.eq(&p.name),
+                    ))
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, p.id.to_string()))?;
+
+            diesel::delete(
+                escalation_step::dsl::escalation_step
+                    .filter(escalation_step::dsl::policy_id.eq(p.id)),
+            )
+            .execute(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, p.id.to_string()))?;
+
+            insert_steps(c, p.id, &steps).await?;
+
+            Ok(p)
+        })
+    })
+    .await?;
+
+    info!(policy_id = p.id, "Escalation policy updated");
+    Ok(p)
+}
+
+pub async fn delete_policy(id: i32) -> Result<(), Error> {
+    let ra = diesel::delete(escalation_policy::dsl::escalation_policy.find(id))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    if ra == 0 {
+        return Err(Error::NotFound(id.to_string()));
+    }
+    info!(policy_id = id, "Escalation policy deleted");
+    Ok(())
+}
+
+pub async fn list_policies(tenant_id: &Uuid) -> Result<Vec<EscalationPolicy>, Error> {
+    let items = escalation_policy::dsl::escalation_policy
+        .filter(escalation_policy::dsl::tenant_id.eq(fields::Uuid::from(tenant_id)))
+        .order_by(escalation_policy::dsl::name)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+    Ok(items)
+}
+
+// Sets the escalation policy of the alarm, or removes it when policy_id is None.
+pub async fn set_alarm_policy(alarm_id: i32, policy_id: Option<i32>) -> Result<(), Error> {
+    let mut c = get_async_db_conn().await?;
+    db_transaction::<(), Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            diesel::delete(
+                escalation_policy_target::dsl::escalation_policy_target
+                    .filter(escalation_policy_target::dsl::alarm_id.eq(alarm_id)),
+            )
+            .execute(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+
+            if let Some(policy_id) = policy_id {
+                diesel::insert_into(escalation_policy_target::table)
+                    .values((
+                        escalation_policy_target::policy_id.eq(policy_id),
+                        escalation_policy_target::alarm_id.eq(Some(alarm_id)),
+                    ))
+                    .execute(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, policy_id.to_string()))?;
+            }
+
+            Ok(())
+        })
+    })
+    .await?;
+
+    info!(alarm_id = alarm_id, policy_id = ?policy_id, "Alarm escalation policy set");
+    Ok(())
+}
+
+// Sets the escalation policy of the zone, or removes it when policy_id is None. The zone
+// policy applies to the alarms of the zone devices which do not have a policy set.
+pub async fn set_zone_policy(zone_id: i32, policy_id: Option<i32>) -> Result<(), Error> {
+    let mut c = get_async_db_conn().await?;
+    db_transaction::<(), Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            diesel::delete(
+                escalation_policy_target::dsl::escalation_policy_target
+                    .filter(escalation_policy_target::dsl::zone_id.eq(zone_id)),
+            )
+            .execute(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, zone_id.to_string()))?;
+
+            if let Some(policy_id) = policy_id {
+                diesel::insert_into(escalation_policy_target::table)
+                    .values((
+                        escalation_policy_target::policy_id.eq(policy_id),
+                        escalation_policy_target::zone_id.eq(Some(zone_id)),
+                    ))
+                    .execute(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, policy_id.to_string()))?;
+            }
+
+            Ok(())
+        })
+    })
+    .await?;
+
+    info!(zone_id = zone_id, policy_id = ?policy_id, "Zone escalation policy set");
+    Ok(())
+}
+
+// Starts the escalation of the triggered alarm, using the policy of the alarm or else the
+// policy of the zone of the device. It returns None when no policy applies or when an
+// escalation is already in progress for the alarm.
+pub async fn start(
+    alarm_id: i32,
+    dev_eui: &str,
+    message: &str,
+) -> Result<Option<AlarmEscalation>, Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    // SQLite has no arrays, the zone devices are a JSON array. The INSERT ... SELECT needs a WHERE
+    // clause in SQLite, else the ON CONFLICT is parsed as a join constraint.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        WITH policy AS (
+            SELECT t.policy_id, 0 AS prio
+            FROM escalation_policy_target AS t
+            WHERE t.alarm_id = ?1
+            UNION ALL
+            SELECT t.policy_id, 1 AS prio
+            FROM escalation_policy_target AS t
+            INNER JOIN zone AS z ON z.zone_id = t.zone_id
+            WHERE EXISTS (SELECT 1 FROM json_each(z.devices) AS zd WHERE zd.value = '\x' || ?2)
+            ORDER BY prio
+            LIMIT 1
+        )
+        INSERT INTO alarm_escalation (alarm_id, policy_id, dev_eui, message, level, triggered_at, next_at)
+        SELECT
+            ?1,
+            p.policy_id,
+            ?2,
+            ?3,
+            0,
+            ?4,
+            strftime('%Y-%m-%d %H:%M:%f+00:00', ?4, printf('%+d minutes', COALESCE(
+                (SELECT s.delay_minutes FROM escalation_step AS s WHERE s.policy_id = p.policy_id ORDER BY s.level LIMIT 1),
+                0
+            )))
+        FROM policy AS p
+        WHERE true
+        ON CONFLICT (alarm_id) WHERE next_at IS NOT NULL AND acknowledged_at IS NULL DO NOTHING
+        RETURNING *
+        "#
+    } else {
+        r#"
+        WITH policy AS (
+            SELECT t.policy_id, 0 AS prio
+            FROM escalation_policy_target AS t
+            WHERE t.alarm_id = $1
+            UNION ALL
+            SELECT t.policy_id, 1 AS prio
+            FROM escalation_policy_target AS t
+            INNER JOIN zone AS z ON z.zone_id = t.zone_id
+            WHERE '\x' || $2 = ANY(z.devices)
+            ORDER BY prio
+            LIMIT 1
+        )
+        INSERT INTO alarm_escalation (alarm_id, policy_id, dev_eui, message, level, triggered_at, next_at)
+        SELECT
+            $1,
+            p.policy_id,
+            $2,
+            $3,
+            0,
+            $4,
+            $4 + make_interval(mins => COALESCE(
+                (SELECT s.delay_minutes FROM escalation_step AS s WHERE s.policy_id = p.policy_id ORDER BY s.level LIMIT 1),
+                0
+            ))
+        FROM policy AS p
+        ON CONFLICT (alarm_id) WHERE next_at IS NOT NULL AND acknowledged_at IS NULL DO NOTHING
+        RETURNING *
+        "#
+    };
+
+    let items: Vec<AlarmEscalation> = diesel::sql_query(query)
+        .bind::<Integer, _>(alarm_id)
+        .bind::<Text, _>(dev_eui)
+        .bind::<Text, _>(message)
+        .bind::<fields::sql_types::Timestamptz, _>(Utc::now())
+        .load(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+
+    let e = items.into_iter().next();
+    if let Some(e) = &e {
+        info!(
+            alarm_id = alarm_id,
+            escalation_id = e.id,
+            policy_id = e.policy_id,
+            "Alarm escalation started"
+        );
+    }
+    Ok(e)
+}
+
+// Returns the escalations of which the next step is due.
+pub async fn get_due(now: DateTime<Utc>) -> Result<Vec<AlarmEscalation>, Error> {
+    let items = alarm_escalation::dsl::alarm_escalation
+        .filter(alarm_escalation::dsl::next_at.le(now))
+        .filter(alarm_escalation::dsl::acknowledged_at.is_null())
+        .order_by(alarm_escalation::dsl::next_at)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "alarm escalations".to_string()))?;
+    Ok(items)
+}
+
+// Claims the given escalation level, such that a step is executed only once when multiple
+// instances are running. The next step is due next_delay minutes from now, or never in case of
+// None. It returns false when the escalation was acknowledged or already claimed.
+pub async fn claim_level(
+    e: &AlarmEscalation,
+    level: i32,
+    next_delay: Option<i32>,
+) -> Result<bool, Error> {
+    let next_at = next_delay.map(|d| Utc::now() + Duration::minutes(d as i64));
+
+    let ra = diesel::update(
+        alarm_escalation::dsl::alarm_escalation
+            .find(e.id)
+            .filter(alarm_escalation::dsl::level.eq(e.level))
+            .filter(alarm_escalation::dsl::acknowledged_at.is_null()),
+    )
+    .set((
+        alarm_escalation::level.eq(level),
+        alarm_escalation::next_at.eq(next_at),
+    ))
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|err| Error::from_diesel(err, e.id.to_string()))?;
+
+    Ok(ra == 1)
+}
+
+// Acknowledges the escalations in progress of the alarm, this stops the escalation. It returns
+// the acknowledged escalations.
+pub async fn acknowledge(alarm_id: i32, user_id: &Uuid) -> Result<Vec<AlarmEscalation>, Error> {
+    let items: Vec<AlarmEscalation> = diesel::update(
+        alarm_escalation::dsl::alarm_escalation
+            .filter(alarm_escalation::dsl::alarm_id.eq(alarm_id))
+            .filter(alarm_escalation::dsl::acknowledged_at.is_null()),
+    )
+    .set((
+        alarm_escalation::acknowledged_at.eq(Some(Utc::now())),
+        alarm_escalation::acknowledged_by.eq(Some(fields::Uuid::from(user_id))),
+        alarm_escalation::next_at.eq(None::<DateTime<Utc>>),
+    ))
+    .get_results(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+
+    for e in &items {
+        log_step(e, "ACK", Some(*user_id), json!({ "level": e.level })).await?;
+    }
+
+    info!(alarm_id = alarm_id, user_id = %user_id, count = items.len(), "Alarm escalation acknowledged");
+    Ok(items)
+}
+
+// Returns the escalations of the alarm and / or device, the most recent first.
+pub async fn list(
+    alarm_id: Option<i32>,
+    dev_eui: Option<&str>,
+    limit: i64,
+) -> Result<Vec<AlarmEscalation>, Error> {
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT *
+        FROM alarm_escalation
+        WHERE (?1 IS NULL OR alarm_id = ?1)
+            AND (?2 IS NULL OR dev_eui = ?2)
+        ORDER BY julianday(triggered_at) DESC
+        LIMIT ?3
+        "#
+    } else {
+        r#"
+        SELECT *
+        FROM alarm_escalation
+        WHERE ($1 IS NULL OR alarm_id = $1)
+            AND ($2 IS NULL OR dev_eui = $2)
+        ORDER BY triggered_at DESC
+        LIMIT $3
+        "#
+    };
+
+    let items = diesel::sql_query(query)
+        .bind::<Nullable<Integer>, _>(alarm_id)
+        .bind::<Nullable<Text>, _>(dev_eui)
+        .bind::<BigInt, _>(limit)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "alarm escalations".to_string()))?;
+    Ok(items)
+}
+
+// Records the escalation step in the alarm audit log. The changed_by is None for the steps
+// executed by the escalation task.
+pub async fn log_step(
+    e: &AlarmEscalation,
+    change_type: &str,
+    changed_by: Option<Uuid>,
+    values: serde_json::Value,
+) -> Result<(), Error> {
+    diesel::insert_into(alarm_audit_log::table)
+        .values((
+            alarm_audit_log::alarm_id.eq(e.alarm_id),
+            alarm_audit_log::dev_eui.eq(&e.dev_eui),
+            alarm_audit_log::change_type.eq(change_type),
+            alarm_audit_log::changed_by.eq(changed_by.map(fields::Uuid::from)),
+            alarm_audit_log::new_values.eq(Some(fields::JsonValue::from(json!({
+                "escalation_id": e.id,
+                "policy_id": e.policy_id,
+                "step": values,
+            })))),
+        ))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|err| Error::from_diesel(err, e.alarm_id.to_string()))?;
+    Ok(())
+}
+
+// Sets the next_at of the escalation, this is used by the tests to make an escalation due.
+#[cfg(test)]
+async fn set_next_at(id: i32, next_at: DateTime<Utc>) -> Result<(), Error> {
+    let query = if cfg!(feature = "sqlite") {
+        "UPDATE alarm_escalation SET next_at = ?2 WHERE id = ?1"
+    } else {
+        "UPDATE alarm_escalation SET next_at = $2 WHERE id = $1"
+    };
+
+    diesel::sql_query(query)
+        .bind::<Integer, _>(id)
+        .bind::<fields::sql_types::Timestamptz, _>(next_at)
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{alarm, tenant};
+    use crate::test;
+
+    #[test]
+    fn test_validate_steps() {
+        let users = vec![Some(Uuid::new_v4())];
+
+        // no steps
+        assert!(validate_steps(&[]).is_err());
+
+        // ordered by level
+        let steps = validate_steps(&[
+            EscalationStep {
+                level: 2,
+                delay_minutes: 15,
+                channel: fields::EscalationChannel::WEBHOOK,
+                webhook_url: "https://example.com/call".into(),
+                ..Default::default()
+            },
+            EscalationStep {
+                level: 1,
+                user_id: users.clone().into(),
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+        assert_eq!(
+            vec![1, 2],
+            steps.iter().map(|s| s.level).collect::<Vec<_>>()
+        );
+
+        // gap in levels
+        assert!(validate_steps(&[EscalationStep {
+            level: 2,
+            user_id: users.clone().into(),
+            ..Default::default()
+        }])
+        .is_err());
+
+        // no users
+        assert!(validate_steps(&[EscalationStep {
+            level: 1,
+            channel: fields::EscalationChannel::SMS,
+            ..Default::default()
+        }])
+        .is_err());
+
+        // invalid webhook url
+        assert!(validate_steps(&[EscalationStep {
+            level: 1,
+            channel: fields::EscalationChannel::WEBHOOK,
+            webhook_url: "example.com".into(),
+            ..Default::default()
+        }])
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_escalation() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::test::create_tenant().await;
+        let user_id = Uuid::new_v4();
+
+        let p = create_policy(
+            EscalationPolicy {
+                tenant_id: t.id,
+                name: "Gece vardiyası".into(),
+                ..Default::default()
+            },
+            &[
+                EscalationStep {
+                    level: 1,
+                    delay_minutes: 0,
+                    user_id: vec![Some(user_id)].into(),
+                    ..Default::default()
+                },
+                EscalationStep {
+                    level: 2,
+                    delay_minutes: 15,
+                    channel: fields::EscalationChannel::SMS,
+                    user_id: vec![Some(user_id)].into(),
+                    ..Default::default()
+                },
+            ],
+        )
+        .await
+        .unwrap();
+
+        let steps = get_steps(p.id).await.unwrap();
+        assert_eq!(2, steps.len());
+        assert_eq!(fields::EscalationChannel::SMS, steps[1].channel);
+
+        // list
+        let items = list_policies(&t.id.into()).await.unwrap();
+        assert_eq!(vec![p.clone()], items);
+
+        // update
+        let p = update_policy(
+            EscalationPolicy {
+                name: "Gece".into(),
+                ..p
+            },
+            &steps[..1],
+        )
+        .await
+        .unwrap();
+        assert_eq!("Gece", p.name);
+        assert_eq!(1, get_steps(p.id).await.unwrap().len());
+
+        // no escalation without target
+        let a = alarm::create(
+            alarm::NewAlarm {
+                dev_eui: "0102030405060708".into(),
+                ..Default::default()
+            },
+            vec![],
+            user_id,
+        )
+        .await
+        .unwrap();
+        assert_eq!(None, start(a.id, "0102030405060708", "test").await.unwrap());
+
+        // start
+        set_alarm_policy(a.id, Some(p.id)).await.unwrap();
+        let e = start(a.id, "0102030405060708", "test")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(0, e.level);
+
+        // in progress
+        assert_eq!(None, start(a.id, "0102030405060708", "test").await.unwrap());
+
+        // due
+        set_next_at(e.id, Utc::now() - Duration::minutes(1))
+            .await
+            .unwrap();
+        let due = get_due(Utc::now()).await.unwrap();
+        assert_eq!(1, due.len());
+        assert!(claim_level(&due[0], 1, Some(15)).await.unwrap());
+        assert!(!claim_level(&due[0], 1, Some(15)).await.unwrap());
+        assert!(get_due(Utc::now()).await.unwrap().is_empty());
+
+        // acknowledge
+        let acked = acknowledge(a.id, &user_id).await.unwrap();
+        assert_eq!(1, acked.len());
+        assert_eq!(Some(user_id.into()), acked[0].acknowledged_by);
+        assert_eq!(1, list(Some(a.id), None, 10).await.unwrap().len());
+
+        // delete
+        set_alarm_policy(a.id, None).await.unwrap();
+        delete_policy(p.id).await.unwrap();
+        assert!(delete_policy(p.id).await.is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::ops::{Deref, DerefMut};
+
+use diesel::backend::Backend;
+#[cfg(feature = "postgres")]
+use diesel::pg::Pg;
+#[cfg(feature = "postgres")]
+use diesel::sql_types::{Array, Int8, Nullable};
+use diesel::{deserialize, serialize};
+#[cfg(feature = "sqlite")]
+use diesel::{sql_types::Text, sqlite::Sqlite};
+use serde::{Deserialize, Serialize};
+
+// Array columns are stored as native arrays by PostgreSQL and as JSON encoded arrays by SQLite,
+// which has no array type.
+macro_rules! array_field {
+    ($name:ident, $item:ty, $pg_type:ty) => {
+        #[derive(
+            Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow,
+        )]
+        #[serde(transparent)]
+        #[cfg_attr(feature = "postgres", diesel(sql_type = Array<Nullable<$pg_type>>))]
+        #[cfg_attr(feature = "sqlite", diesel(sql_type = Text))]
+        pub struct $name(Vec<Option<$item>>);
+
+        impl $name {
+            pub fn new(v: Vec<Option<$item>>) -> Self {
+                $name(v)
+            }
+
+            pub fn into_inner(self) -> Vec<Option<$item>> {
+                self.0
+            }
+        }
+
+        impl From<Vec<Option<$item>>> for $name {
+            fn from(v: Vec<Option<$item>>) -> Self {
+                $name(v)
+            }
+        }
+
+        impl From<Vec<$item>> for $name {
+            fn from(v: Vec<$item>) -> Self {
+                $name(v.into_iter().map(Some).collect())
+            }
+        }
+
+        impl FromIterator<Option<$item>> for $name {
+            fn from_iter<I: IntoIterator<Item = Option<$item>>>(iter: I) -> Self {
+                $name(iter.into_iter().collect())
+            }
+        }
+
+        impl From<$name> for Vec<Option<$item>> {
+            fn from(v: $name) -> Self {
+                v.0
+            }
+        }
+
+        impl IntoIterator for $name {
+            type Item = Option<$item>;
+            type IntoIter = std::vec::IntoIter<Option<$item>>;
+
+            fn into_iter(self) -> Self::IntoIter {
+                self.0.into_iter()
+            }
+        }
+
+        impl Deref for $name {
+            type Target = Vec<Option<$item>>;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl DerefMut for $name {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.0
+            }
+        }
+
+        #[cfg(feature = "postgres")]
+        impl deserialize::FromSql<Array<Nullable<$pg_type>>, Pg> for $name {
+            fn from_sql(value: <Pg as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+                let v = <Vec<Option<$item>> as deserialize::FromSql<
+                    Array<Nullable<$pg_type>>,
+                    Pg,
+                >>::from_sql(value)?;
+                Ok($name(v))
+            }
+        }
+
+        #[cfg(feature = "postgres")]
+        impl serialize::ToSql<Array<Nullable<$pg_type>>, Pg> for $name {
+            fn to_sql<'b>(&'b self, out: &mut serialize::Output<'b, '_, Pg>) -> serialize::Result {
+                <Vec<Option<$item>> as serialize::ToSql<Array<Nullable<$pg_type>>, Pg>>::to_sql(
+                    &self.0,
+                    &mut out.reborrow(),
+                )
+            }
+        }
+
+        #[cfg(feature = "sqlite")]
+        impl deserialize::FromSql<Text, Sqlite> for $name
+        where
+            *const str: deserialize::FromSql<Text, Sqlite>,
+        {
+            fn from_sql(value: <Sqlite as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+                let s = <*const str as deserialize::FromSql<Text, Sqlite>>::from_sql(value)?;
+                let v: Vec<Option<$item>> = serde_json::from_str(unsafe { &*s })?;
+                Ok($name(v))
+            }
+        }
+
+        #[cfg(feature = "sqlite")]
+        impl serialize::ToSql<Text, Sqlite> for $name {
+            fn to_sql<'b>(
+                &'b self,
+                out: &mut serialize::Output<'b, '_, Sqlite>,
+            ) -> serialize::Result {
+                out.set_value(serde_json::to_string(&self.0)?);
+                Ok(serialize::IsNull::No)
+            }
+        }
+    };
+}
+
+array_field!(TextArray, String, diesel::sql_types::Text);
+array_field!(UuidArray, uuid::Uuid, diesel::sql_types::Uuid);
+array_field!(BigIntArray, i64, Int8);
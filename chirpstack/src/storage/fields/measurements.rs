@@ -13,6 +13,8 @@ use serde::{Deserialize, Serialize};
 pub struct Measurement {
     pub name: String,
     pub kind: MeasurementKind,
+    #[serde(default)]
+    pub unit: String,
 }
 
 #[allow(clippy::upper_case_acronyms)]
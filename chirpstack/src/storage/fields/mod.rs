@@ -1,17 +1,27 @@
+mod array;
 mod big_decimal;
+mod calibration_points;
 mod dev_nonces;
 mod device_session;
+mod escalation_channel;
+mod json_value;
 mod key_value;
 mod measurements;
 mod multicast_group_scheduling_type;
+mod over_quota_action;
 mod uuid;
 
+pub use array::{BigIntArray, TextArray, UuidArray};
 pub use big_decimal::BigDecimal;
+pub use calibration_points::{CalibrationPoint, CalibrationPoints};
 pub use dev_nonces::DevNonces;
 pub use device_session::DeviceSession;
+pub use escalation_channel::EscalationChannel;
+pub use json_value::JsonValue;
 pub use key_value::KeyValue;
 pub use measurements::*;
 pub use multicast_group_scheduling_type::MulticastGroupSchedulingType;
+pub use over_quota_action::OverQuotaAction;
 pub use uuid::Uuid;
 
 #[cfg(feature = "postgres")]
@@ -21,6 +31,9 @@ pub mod sql_types {
     pub type JsonT = diesel::sql_types::Jsonb;
 
     pub type Uuid = diesel::sql_types::Uuid;
+
+    pub type UuidArray =
+        diesel::sql_types::Array<diesel::sql_types::Nullable<diesel::sql_types::Uuid>>;
 }
 
 #[cfg(feature = "sqlite")]
@@ -34,4 +47,7 @@ pub mod sql_types {
 
     // Sqlite has no native json type so use text
     pub type Uuid = diesel::sql_types::Text;
+
+    // Sqlite has no array type, arrays are stored as JSON encoded text
+    pub type UuidArray = diesel::sql_types::Text;
 }
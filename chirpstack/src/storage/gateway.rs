@@ -16,7 +16,7 @@ pub type RelayId = DevAddr;
 
 #[derive(Queryable,Selectable, Insertable, PartialEq, Debug)]
 #[diesel(table_name = gateway)]
-#[diesel(check_for_backend(diesel::pg::Pg))]  // Required for `.as_returning()` with Postgres
+#[cfg_attr(feature = "postgres", diesel(check_for_backend(diesel::pg::Pg)))]
 pub struct Gateway {
     pub gateway_id: EUI64,
     pub tenant_id: fields::Uuid,
@@ -32,6 +32,7 @@ pub struct Gateway {
     pub tls_certificate: Option<Vec<u8>>,
     pub tags: fields::KeyValue,
     pub properties: fields::KeyValue,
+    pub is_offline: bool,
 }
 
 impl Gateway {
@@ -62,6 +63,7 @@ impl Default for Gateway {
             stats_interval_secs: 30,
             tags: fields::KeyValue::new(HashMap::new()),
             properties: fields::KeyValue::new(HashMap::new()),
+            is_offline: false,
         }
     }
 }
@@ -75,6 +77,7 @@ pub struct GatewayChangeset {
     pub longitude: Option<f64>,
     pub altitude: Option<f32>,
     pub tls_certificate: Option<Option<Vec<u8>>>,
+    pub is_offline: Option<bool>,
 }
 
 #[derive(Queryable, PartialEq, Debug)]
@@ -261,6 +264,21 @@ pub async fn partial_update(gateway_id: EUI64, gw: &GatewayChangeset) -> Result<
     Ok(gw)
 }
 
+// Clears the offline flag of the given gateway. It returns true when the gateway was marked
+// offline before.
+pub async fn set_online(gateway_id: &EUI64) -> Result<bool, Error> {
+    let ra = diesel::update(
+        gateway::dsl::gateway
+            .find(&gateway_id)
+            .filter(gateway::dsl::is_offline.eq(true)),
+    )
+    .set(gateway::is_offline.eq(false))
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, gateway_id.to_string()))?;
+    Ok(ra != 0)
+}
+
 pub async fn delete(gateway_id: &EUI64) -> Result<(), Error> {
     let ra = diesel::delete(gateway::dsl::gateway.find(&gateway_id))
         .execute(&mut get_async_db_conn().await?)
@@ -411,6 +429,56 @@ pub async fn get_counts_by_state(tenant_id: &Option<Uuid>) -> Result<GatewayCoun
     Ok(counts)
 }
 
+#[derive(QueryableByName)]
+struct GatewayIdRow {
+    #[diesel(sql_type = diesel::sql_types::Binary)]
+    gateway_id: EUI64,
+}
+
+// Returns the IDs of the gateways which are currently marked online, but which did not send
+// any stats within the given number of stats intervals.
+#[cfg(feature = "postgres")]
+pub async fn get_missed_stats_gateway_ids(missed_intervals: u32) -> Result<Vec<EUI64>, Error> {
+    let rows: Vec<GatewayIdRow> = diesel::sql_query(
+        r#"
+        select
+            gateway_id
+        from
+            gateway
+        where
+            is_offline = false
+            and last_seen_at is not null
+            and (now() - make_interval(secs => stats_interval_secs * $1)) > last_seen_at
+    "#,
+    )
+    .bind::<diesel::sql_types::Integer, _>(missed_intervals as i32)
+    .load(&mut get_async_db_conn().await?)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.gateway_id).collect())
+}
+
+// Returns the IDs of the gateways which are currently marked online, but which did not send
+// any stats within the given number of stats intervals.
+#[cfg(feature = "sqlite")]
+pub async fn get_missed_stats_gateway_ids(missed_intervals: u32) -> Result<Vec<EUI64>, Error> {
+    let rows: Vec<GatewayIdRow> = diesel::sql_query(
+        r#"
+        select
+            gateway_id
+        from
+            gateway
+        where
+            is_offline = false
+            and last_seen_at is not null
+            and (unixepoch('now') - unixepoch(last_seen_at)) > (stats_interval_secs * ?1)
+    "#,
+    )
+    .bind::<diesel::sql_types::Integer, _>(missed_intervals as i32)
+    .load(&mut get_async_db_conn().await?)
+    .await?;
+    Ok(rows.into_iter().map(|r| r.gateway_id).collect())
+}
+
 pub async fn create_relay_gateway(relay: RelayGateway) -> Result<RelayGateway, Error> {
     let relay: RelayGateway = diesel::insert_into(relay_gateway::table)
         .values(&relay)
@@ -696,6 +764,58 @@ pub mod test {
         assert!(delete(&gw.gateway_id).await.is_err());
     }
 
+    #[tokio::test]
+    async fn test_get_missed_stats_gateway_ids() {
+        let _guard = test::prepare().await;
+        let gw = create_gateway(EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8])).await;
+
+        // never seen
+        assert!(get_missed_stats_gateway_ids(3).await.unwrap().is_empty());
+
+        // seen within the stats interval
+        partial_update(
+            gw.gateway_id,
+            &GatewayChangeset {
+                last_seen_at: Some(Some(Utc::now())),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(get_missed_stats_gateway_ids(3).await.unwrap().is_empty());
+
+        // missed three stats intervals
+        partial_update(
+            gw.gateway_id,
+            &GatewayChangeset {
+                last_seen_at: Some(Some(Utc::now() - chrono::Duration::seconds(100))),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            vec![gw.gateway_id],
+            get_missed_stats_gateway_ids(3).await.unwrap()
+        );
+
+        // already marked offline
+        partial_update(
+            gw.gateway_id,
+            &GatewayChangeset {
+                is_offline: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(get_missed_stats_gateway_ids(3).await.unwrap().is_empty());
+
+        // back online
+        assert!(set_online(&gw.gateway_id).await.unwrap());
+        assert!(!set_online(&gw.gateway_id).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_relay_gateway() {
         let _guard = test::prepare().await;
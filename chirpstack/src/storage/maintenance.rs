@@ -0,0 +1,417 @@
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Integer, Text};
+use diesel_async::RunQueryDsl;
+use tracing::info;
+use uuid::Uuid;
+
+use super::error::Error;
+use super::schema::{alarm_snooze, maintenance_window};
+use super::{fields, get_async_db_conn};
+
+// Max. snooze duration of an alarm.
+const MAX_SNOOZE_MINUTES: i64 = 60 * 24;
+
+// Maintenance window of a device, zone or site. During the window, the alarm notifications of
+// the affected devices are suppressed. The readings and the triggered alarms are still
+// recorded.
+#[derive(Queryable, QueryableByName, PartialEq, Debug, Clone)]
+#[diesel(table_name = maintenance_window)]
+pub struct MaintenanceWindow {
+    pub id: i32,
+    pub tenant_id: fields::Uuid,
+    pub dev_eui: Option<String>,
+    pub zone_id: Option<i32>,
+    pub site_id: Option<i32>,
+    pub start_at: DateTime<Utc>,
+    pub end_at: DateTime<Utc>,
+    pub reason: String,
+    pub created_by: Option<fields::Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Default for MaintenanceWindow {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        MaintenanceWindow {
+            id: 0,
+            tenant_id: Uuid::nil().into(),
+            dev_eui: None,
+            zone_id: None,
+            site_id: None,
+            start_at: now,
+            end_at: now,
+            reason: "".into(),
+            created_by: None,
+            created_at: now,
+        }
+    }
+}
+
+impl MaintenanceWindow {
+    fn validate(&self) -> Result<(), Error> {
+        let targets = [
+            self.dev_eui.is_some(),
+            self.zone_id.is_some(),
+            self.site_id.is_some(),
+        ];
+        if targets.iter().filter(|v| **v).count() != 1 {
+            return Err(Error::Validation(
+                "Maintenance window must have exactly one of dev_eui, zone_id or site_id".into(),
+            ));
+        }
+        if self.end_at <= self.start_at {
+            return Err(Error::Validation(
+                "Maintenance window end must be after its start".into(),
+            ));
+        }
+        if self.end_at <= Utc::now() {
+            return Err(Error::Validation(
+                "Maintenance window end must be in the future".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = alarm_snooze)]
+pub struct AlarmSnooze {
+    pub alarm_id: i32,
+    pub user_id: fields::Uuid,
+    pub snoozed_until: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub async fn create(w: MaintenanceWindow) -> Result<MaintenanceWindow, Error> {
+    w.validate()?;
+
+    let w: MaintenanceWindow = diesel::insert_into(maintenance_window::table)
+        .values((
+            maintenance_window::tenant_id.eq(&w.tenant_id),
+            maintenance_window::dev_eui.eq(&w.dev_eui),
+            maintenance_window::zone_id.eq(&w.zone_id),
+            maintenance_window::site_id.eq(&w.site_id),
+            maintenance_window::start_at.eq(&w.start_at),
+            maintenance_window::end_at.eq(&w.end_at),
+            maintenance_window::reason.eq(&w.reason),
+            maintenance_window::created_by.eq(&w.created_by),
+            maintenance_window::created_at.eq(Utc::now()),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, w.tenant_id.to_string()))?;
+
+    info!(id = w.id, tenant_id = %w.tenant_id, start_at = %w.start_at, end_at = %w.end_at, "Maintenance window created");
+    Ok(w)
+}
+
+pub async fn get(id: i32) -> Result<MaintenanceWindow, Error> {
+    let w = maintenance_window::dsl::maintenance_window
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(w)
+}
+
+// Ends the maintenance window now, in case it has not ended yet. A window which has not started
+// yet ends at its start.
+pub async fn end(id: i32) -> Result<MaintenanceWindow, Error> {
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        UPDATE maintenance_window
+        SET end_at = strftime(
+            '%Y-%m-%d %H:%M:%f+00:00',
+            max(julianday(start_at, '+1 seconds'), min(julianday(end_at), julianday(?2)))
+        )
+        WHERE id = ?1
+        RETURNING *
+        "#
+    } else {
+        r#"
+        UPDATE maintenance_window
+        SET end_at = GREATEST(start_at + interval '1 second', LEAST(end_at, $2))
+        WHERE id = $1
+        RETURNING *
+        "#
+    };
+
+    let w: MaintenanceWindow = diesel::sql_query(query)
+        .bind::<Integer, _>(id)
+        .bind::<fields::sql_types::Timestamptz, _>(Utc::now())
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+
+    info!(id = w.id, end_at = %w.end_at, "Maintenance window ended");
+    Ok(w)
+}
+
+pub async fn delete(id: i32) -> Result<(), Error> {
+    let ra = diesel::delete(maintenance_window::dsl::maintenance_window.find(id))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    if ra == 0 {
+        return Err(Error::NotFound(id.to_string()));
+    }
+    info!(id = id, "Maintenance window deleted");
+    Ok(())
+}
+
+// Returns the maintenance windows of the tenant, the windows which have ended are only
+// returned when include_ended is set.
+pub async fn list(tenant_id: &Uuid, include_ended: bool) -> Result<Vec<MaintenanceWindow>, Error> {
+    let mut q = maintenance_window::dsl::maintenance_window
+        .filter(maintenance_window::dsl::tenant_id.eq(fields::Uuid::from(tenant_id)))
+        .into_boxed();
+
+    if !include_ended {
+        q = q.filter(maintenance_window::dsl::end_at.gt(Utc::now()));
+    }
+
+    let items = q
+        .order_by(maintenance_window::dsl::start_at.desc())
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+    Ok(items)
+}
+
+// Returns the active maintenance window of the device, directly or through its zones and the
+// sites of these zones.
+pub async fn get_active_for_device(dev_eui: &str) -> Result<Option<MaintenanceWindow>, Error> {
+    // SQLite has no arrays, the zone devices are a JSON array.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT mw.*
+        FROM maintenance_window AS mw
+        WHERE julianday(mw.start_at) <= julianday(?2)
+            AND julianday(mw.end_at) > julianday(?2)
+            AND (
+                mw.dev_eui = ?1
+                OR EXISTS (
+                    SELECT 1
+                    FROM zone AS z, json_each(z.devices) AS zd
+                    WHERE zd.value = '\x' || ?1
+                        AND (z.zone_id = mw.zone_id OR z.site_id = mw.site_id)
+                )
+            )
+        ORDER BY julianday(mw.end_at) DESC
+        LIMIT 1
+        "#
+    } else {
+        r#"
+        SELECT mw.*
+        FROM maintenance_window AS mw
+        WHERE mw.start_at <= $2
+            AND mw.end_at > $2
+            AND (
+                mw.dev_eui = $1
+                OR EXISTS (
+                    SELECT 1
+                    FROM zone AS z
+                    WHERE '\x' || $1 = ANY(z.devices)
+                        AND (z.zone_id = mw.zone_id OR z.site_id = mw.site_id)
+                )
+            )
+        ORDER BY mw.end_at DESC
+        LIMIT 1
+        "#
+    };
+
+    let items: Vec<MaintenanceWindow> = diesel::sql_query(query)
+        .bind::<Text, _>(dev_eui)
+        .bind::<fields::sql_types::Timestamptz, _>(Utc::now())
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+
+    Ok(items.into_iter().next())
+}
+
+// Snoozes the alarm for the user. Snoozing an alarm which is already snoozed replaces the
+// previous snooze.
+pub async fn snooze(alarm_id: i32, user_id: &Uuid, minutes: i64) -> Result<AlarmSnooze, Error> {
+    if minutes <= 0 || minutes > MAX_SNOOZE_MINUTES {
+        return Err(Error::Validation(format!(
+            "Snooze minutes must be between 1 and {}",
+            MAX_SNOOZE_MINUTES
+        )));
+    }
+
+    let now = Utc::now();
+    let s = AlarmSnooze {
+        alarm_id,
+        user_id: user_id.into(),
+        snoozed_until: now + Duration::minutes(minutes),
+        created_at: now,
+    };
+
+    let s: AlarmSnooze = diesel::insert_into(alarm_snooze::table)
+        .values(&s)
+        .on_conflict((alarm_snooze::alarm_id, alarm_snooze::user_id))
+        .do_update()
+        .set((
+            alarm_snooze::snoozed_until.eq(&s.snoozed_until),
+            alarm_snooze::created_at.eq(&s.created_at),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+
+    info!(alarm_id = alarm_id, user_id = %user_id, snoozed_until = %s.snoozed_until, "Alarm snoozed");
+    Ok(s)
+}
+
+pub async fn unsnooze(alarm_id: i32, user_id: &Uuid) -> Result<(), Error> {
+    diesel::delete(
+        alarm_snooze::dsl::alarm_snooze
+            .filter(alarm_snooze::dsl::alarm_id.eq(alarm_id))
+            .filter(alarm_snooze::dsl::user_id.eq(fields::Uuid::from(user_id))),
+    )
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+
+    info!(alarm_id = alarm_id, user_id = %user_id, "Alarm snooze removed");
+    Ok(())
+}
+
+// Returns the users who have snoozed the alarm, the expired snoozes are removed.
+pub async fn get_snoozed_user_ids(alarm_id: i32) -> Result<Vec<Uuid>, Error> {
+    let mut c = get_async_db_conn().await?;
+
+    diesel::delete(
+        alarm_snooze::dsl::alarm_snooze
+            .filter(alarm_snooze::dsl::alarm_id.eq(alarm_id))
+            .filter(alarm_snooze::dsl::snoozed_until.le(Utc::now())),
+    )
+    .execute(&mut c)
+    .await
+    .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+
+    let items: Vec<fields::Uuid> = alarm_snooze::dsl::alarm_snooze
+        .select(alarm_snooze::dsl::user_id)
+        .filter(alarm_snooze::dsl::alarm_id.eq(alarm_id))
+        .load(&mut c)
+        .await
+        .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+    Ok(items.into_iter().map(Uuid::from).collect())
+}
+
+// Returns the snoozes of the user which have not expired.
+pub async fn list_snoozes(user_id: &Uuid) -> Result<Vec<AlarmSnooze>, Error> {
+    let items = alarm_snooze::dsl::alarm_snooze
+        .filter(alarm_snooze::dsl::user_id.eq(fields::Uuid::from(user_id)))
+        .filter(alarm_snooze::dsl::snoozed_until.gt(Utc::now()))
+        .order_by(alarm_snooze::dsl::snoozed_until)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, user_id.to_string()))?;
+    Ok(items)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{alarm, tenant};
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_maintenance_window() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::test::create_tenant().await;
+        let tenant_id: Uuid = t.id.into();
+
+        // no target
+        assert!(create(MaintenanceWindow {
+            tenant_id: t.id,
+            end_at: Utc::now() + Duration::hours(1),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+
+        // ended
+        assert!(create(MaintenanceWindow {
+            tenant_id: t.id,
+            dev_eui: Some("0102030405060708".into()),
+            start_at: Utc::now() - Duration::hours(2),
+            end_at: Utc::now() - Duration::hours(1),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+
+        // create
+        let w = create(MaintenanceWindow {
+            tenant_id: t.id,
+            dev_eui: Some("0102030405060708".into()),
+            start_at: Utc::now() - Duration::minutes(1),
+            end_at: Utc::now() + Duration::hours(1),
+            reason: "Soğuk oda temizliği".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(w, get(w.id).await.unwrap());
+
+        // active
+        assert_eq!(
+            Some(w.id),
+            get_active_for_device("0102030405060708")
+                .await
+                .unwrap()
+                .map(|v| v.id)
+        );
+        assert_eq!(
+            None,
+            get_active_for_device("0807060504030201").await.unwrap()
+        );
+
+        // end
+        let w = end(w.id).await.unwrap();
+        assert!(w.end_at <= Utc::now());
+        assert_eq!(
+            None,
+            get_active_for_device("0102030405060708").await.unwrap()
+        );
+        assert!(list(&tenant_id, false).await.unwrap().is_empty());
+        assert_eq!(1, list(&tenant_id, true).await.unwrap().len());
+
+        // delete
+        delete(w.id).await.unwrap();
+        assert!(delete(w.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_snooze() {
+        let _guard = test::prepare().await;
+
+        let user_id = Uuid::new_v4();
+        let a = alarm::create(
+            alarm::NewAlarm {
+                dev_eui: "0102030405060708".into(),
+                ..Default::default()
+            },
+            vec![],
+            user_id,
+        )
+        .await
+        .unwrap();
+
+        assert!(snooze(a.id, &user_id, 0).await.is_err());
+
+        snooze(a.id, &user_id, 30).await.unwrap();
+        snooze(a.id, &user_id, 60).await.unwrap();
+        assert_eq!(vec![user_id], get_snoozed_user_ids(a.id).await.unwrap());
+        assert_eq!(1, list_snoozes(&user_id).await.unwrap().len());
+
+        unsnooze(a.id, &user_id).await.unwrap();
+        assert!(get_snoozed_user_ids(a.id).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,568 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Binary, Double, Nullable, Text, Timestamp};
+use diesel_async::RunQueryDsl;
+use tracing::info;
+
+use super::error::Error;
+use super::schema::measurement;
+use super::{fields, get_async_db_conn, sensor_data, AsyncDbConnection};
+use lrwn::EUI64;
+
+// Units of the measurement kinds written by the built-in device types. These kinds match the
+// columns of the legacy device_data and device_data_latest tables.
+const UNITS: &[(&str, &str)] = &[
+    ("air_temperature", "°C"),
+    ("air_humidity", "%"),
+    ("sol_temperature", "°C"),
+    ("sol_water", "%"),
+    ("sol_conduct_soil", "uS/cm"),
+    ("last_water_leak_duration", "min"),
+    ("last_door_open_duration", "min"),
+    ("batv", "V"),
+    ("ph_soil", "pH"),
+    ("co2_ppm", "ppm"),
+    ("tvoc_ppm", "ppm"),
+    ("sensecap_light", "lux"),
+    ("barometric_pressure", "hPa"),
+    ("current", "mA"),
+    ("power", "W"),
+    ("voltage", "V"),
+    ("power_sum", "Wh"),
+    ("power_consumption", "Wh"),
+    ("distance", "mm"),
+    ("temperature1", "°C"),
+    ("temperature2", "°C"),
+];
+
+// The legacy device_data columns which are copied by copy_device_data.
+const DEVICE_DATA_COLUMNS: &[&str] = &[
+    "air_temperature",
+    "air_humidity",
+    "sol_temperature",
+    "sol_water",
+    "sol_conduct_soil",
+    "water_leak_status",
+    "water_leak_times",
+    "last_water_leak_duration",
+    "door_open_status",
+    "door_open_times",
+    "last_door_open_duration",
+    "batv",
+    "ro1_status",
+    "ro2_status",
+    "ph_soil",
+    "co2_ppm",
+    "tvoc_ppm",
+    "sensecap_light",
+    "barometric_pressure",
+    "status",
+    "current",
+    "factor",
+    "power",
+    "power_sum",
+    "voltage",
+];
+
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = measurement)]
+pub struct Measurement {
+    pub dev_eui: EUI64,
+    pub kind: String,
+    pub time: DateTime<Utc>,
+    pub unit: String,
+    pub value: Option<f64>,
+    pub text_value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+pub trait IntoValue {
+    fn into_value(self) -> Option<Value>;
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Number(self))
+    }
+}
+
+impl IntoValue for f32 {
+    fn into_value(self) -> Option<Value> {
+        // Round-trip through the string representation, to avoid f32 artifacts
+        // (e.g. 21.5999984741 instead of 21.6).
+        self.to_string().parse::<f64>().ok().map(Value::Number)
+    }
+}
+
+impl IntoValue for i32 {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Number(self.into()))
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Number(self as f64))
+    }
+}
+
+impl IntoValue for BigDecimal {
+    fn into_value(self) -> Option<Value> {
+        self.to_f64().map(Value::Number)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Text(self))
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Option<Value> {
+        self.and_then(|v| v.into_value())
+    }
+}
+
+// Returns the unit of the given built-in measurement kind.
+pub fn get_unit(kind: &str) -> &'static str {
+    UNITS
+        .iter()
+        .find(|(k, _)| *k == kind)
+        .map(|(_, u)| *u)
+        .unwrap_or_default()
+}
+
+// Stores the given built-in measurement values of the device, measured at the given time.
+// Values which are None are skipped.
+pub async fn save_values(
+    conn: &mut AsyncDbConnection,
+    dev_eui: EUI64,
+    time: DateTime<Utc>,
+    values: &[(&str, Option<Value>)],
+) -> Result<(), Error> {
+    let items: Vec<Measurement> = values
+        .iter()
+        .filter_map(|(kind, value)| {
+            value
+                .clone()
+                .map(|v| new_measurement(dev_eui, kind, get_unit(kind), time, v))
+        })
+        .collect();
+
+    save(conn, &items).await
+}
+
+// Stores the values of the decoded object for which the device-profile defines a measurement.
+// This is used for device types without a built-in sensor-data writer. It returns the number
+// of stored values.
+pub async fn save_object(
+    conn: &mut AsyncDbConnection,
+    dev_eui: EUI64,
+    time: DateTime<Utc>,
+    measurements: &fields::Measurements,
+    object: &serde_json::Value,
+) -> Result<usize, Error> {
+    let items: Vec<Measurement> = get_object_values(object)
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let m = measurements.get(&key)?;
+            let value = match m.kind {
+                fields::MeasurementKind::UNKNOWN => return None,
+                fields::MeasurementKind::STRING => match value {
+                    serde_json::Value::String(v) => Value::Text(v),
+                    v => Value::Text(v.to_string()),
+                },
+                // Codecs often return numeric values as strings (e.g. "21.50").
+                _ => match value {
+                    serde_json::Value::Number(v) => Value::Number(v.as_f64()?),
+                    serde_json::Value::String(v) => Value::Number(v.trim().parse().ok()?),
+                    serde_json::Value::Bool(v) => Value::Number(if v { 1.0 } else { 0.0 }),
+                    _ => return None,
+                },
+            };
+            Some(new_measurement(dev_eui, &key, &m.unit, time, value))
+        })
+        .collect();
+
+    save(conn, &items).await?;
+    Ok(items.len())
+}
+
+// Stores the measurements and updates the latest values of the device. Storing a measurement
+// again (e.g. when retrying) is a no-op and an older measurement does not replace the latest
+// value.
+pub async fn save(conn: &mut AsyncDbConnection, items: &[Measurement]) -> Result<(), Error> {
+    // The upsert is a raw query, as diesel does not support the WHERE clause of the
+    // ON CONFLICT DO UPDATE for SQLite.
+    let upsert_latest = format!(
+        r#"
+        insert into measurement_latest (dev_eui, kind, time, unit, value, text_value)
+        values ({p1}, {p2}, {p3}, {p4}, {p5}, {p6})
+        on conflict (dev_eui, kind) do update set
+            time = excluded.time,
+            unit = excluded.unit,
+            value = excluded.value,
+            text_value = excluded.text_value
+        where
+            measurement_latest.time <= excluded.time
+        "#,
+        p1 = sensor_data::placeholder(1),
+        p2 = sensor_data::placeholder(2),
+        p3 = sensor_data::placeholder(3),
+        p4 = sensor_data::placeholder(4),
+        p5 = sensor_data::placeholder(5),
+        p6 = sensor_data::placeholder(6),
+    );
+
+    for item in items {
+        diesel::insert_into(measurement::table)
+            .values(item)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .await
+            .map_err(|e| Error::from_diesel(e, item.dev_eui.to_string()))?;
+
+        diesel::sql_query(&upsert_latest)
+            .bind::<Binary, _>(&item.dev_eui)
+            .bind::<Text, _>(&item.kind)
+            .bind::<fields::sql_types::Timestamptz, _>(&item.time)
+            .bind::<Text, _>(&item.unit)
+            .bind::<Nullable<Double>, _>(&item.value)
+            .bind::<Nullable<Text>, _>(&item.text_value)
+            .execute(conn)
+            .await
+            .map_err(|e| Error::from_diesel(e, item.dev_eui.to_string()))?;
+    }
+
+    Ok(())
+}
+
+// Removes the measurements of the tenant devices which are older than cutoff.
+pub async fn delete_before(tenant_id: &fields::Uuid, cutoff: NaiveDateTime) -> Result<(), Error> {
+    let count = diesel::sql_query(format!(
+        r#"
+        delete from
+            measurement
+        where
+            time < {p2}
+            and dev_eui in (
+                select
+                    d.dev_eui
+                from
+                    device d
+                inner join application a
+                    on a.id = d.application_id
+                where
+                    a.tenant_id = {p1}
+            )
+        "#,
+        p1 = sensor_data::placeholder(1),
+        p2 = sensor_data::placeholder(2),
+    ))
+    .bind::<fields::sql_types::Uuid, _>(tenant_id)
+    .bind::<fields::sql_types::Timestamptz, _>(cutoff.and_utc())
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+
+    if count > 0 {
+        info!(tenant_id = %tenant_id, count = count, "Expired measurements removed");
+    }
+
+    Ok(())
+}
+
+// Returns the interval of the readings in the device_data table, None when the table is empty.
+pub async fn get_device_data_interval() -> Result<Option<(NaiveDateTime, NaiveDateTime)>, Error> {
+    #[derive(QueryableByName)]
+    struct IntervalRow {
+        #[diesel(sql_type = Nullable<Timestamp>)]
+        min_date: Option<NaiveDateTime>,
+        #[diesel(sql_type = Nullable<Timestamp>)]
+        max_date: Option<NaiveDateTime>,
+    }
+
+    let row: IntervalRow = diesel::sql_query(
+        r#"
+        select
+            min(submission_date) as min_date,
+            max(submission_date) as max_date
+        from
+            device_data
+        "#,
+    )
+    .get_result(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, "device_data".into()))?;
+
+    Ok(row.min_date.zip(row.max_date))
+}
+
+// Copies the device_data readings within the given interval into the measurement table.
+// Readings which have already been copied are skipped. It returns the number of copied
+// values.
+pub async fn copy_device_data(start: NaiveDateTime, end: NaiveDateTime) -> Result<usize, Error> {
+    let (dev_eui, time, value) = if cfg!(feature = "sqlite") {
+        (
+            "lower(hex(d.dev_eui))",
+            "dd.submission_date",
+            "cast({} as double)",
+        )
+    } else {
+        (
+            "encode(d.dev_eui, 'hex')",
+            "dd.submission_date at time zone 'utc'",
+            "{}::double precision",
+        )
+    };
+
+    let selects = DEVICE_DATA_COLUMNS
+        .iter()
+        .map(|c| {
+            format!(
+                r#"
+                select
+                    d.dev_eui,
+                    '{column}',
+                    {time},
+                    '{unit}',
+                    {value},
+                    null
+                from
+                    device_data dd
+                inner join device d
+                    on {dev_eui} = lower(dd.dev_eui)
+                where
+                    dd.submission_date >= {p1}
+                    and dd.submission_date < {p2}
+                    and dd.{column} is not null
+                "#,
+                column = c,
+                time = time,
+                unit = get_unit(c),
+                value = value.replace("{}", &format!("dd.{}", c)),
+                dev_eui = dev_eui,
+                p1 = sensor_data::placeholder(1),
+                p2 = sensor_data::placeholder(2),
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(" union all ");
+
+    diesel::sql_query(format!(
+        r#"
+        insert into measurement (dev_eui, kind, time, unit, value, text_value)
+        select * from ({}) as r where true
+        on conflict do nothing
+        "#,
+        selects
+    ))
+    .bind::<Timestamp, _>(start)
+    .bind::<Timestamp, _>(end)
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, "device_data".into()))
+}
+
+fn new_measurement(
+    dev_eui: EUI64,
+    kind: &str,
+    unit: &str,
+    time: DateTime<Utc>,
+    value: Value,
+) -> Measurement {
+    let (value, text_value) = match value {
+        Value::Number(v) => (Some(v), None),
+        Value::Text(v) => (None, Some(v)),
+    };
+
+    Measurement {
+        dev_eui,
+        kind: kind.to_string(),
+        time,
+        unit: unit.to_string(),
+        value,
+        text_value,
+    }
+}
+
+// Returns the (flattened) values of the decoded object, using the same key format as the
+// device-profile measurements (e.g. {"a": {"b": 1}} results in a_b).
+fn get_object_values(object: &serde_json::Value) -> HashMap<String, serde_json::Value> {
+    let mut out = HashMap::new();
+    if let serde_json::Value::Object(fields) = object {
+        for (k, v) in fields {
+            _get_object_values(&mut out, k, v);
+        }
+    }
+    out
+}
+
+fn _get_object_values(
+    out: &mut HashMap<String, serde_json::Value>,
+    prefix: &str,
+    v: &serde_json::Value,
+) {
+    match v {
+        serde_json::Value::Null => {}
+        serde_json::Value::Object(fields) => {
+            for (k, v) in fields {
+                _get_object_values(out, &format!("{}_{}", prefix, k), v);
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for (i, v) in values.iter().enumerate() {
+                _get_object_values(out, &format!("{}_{}", prefix, i), v);
+            }
+        }
+        v => {
+            out.insert(prefix.to_string(), v.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage;
+    use crate::storage::schema::measurement_latest;
+    use crate::test;
+
+    pub async fn get_latest(dev_eui: &EUI64) -> Result<Vec<Measurement>, Error> {
+        measurement_latest::dsl::measurement_latest
+            .select((
+                measurement_latest::dev_eui,
+                measurement_latest::kind,
+                measurement_latest::time,
+                measurement_latest::unit,
+                measurement_latest::value,
+                measurement_latest::text_value,
+            ))
+            .filter(measurement_latest::dev_eui.eq(dev_eui))
+            .order_by(measurement_latest::kind)
+            .load(&mut get_async_db_conn().await?)
+            .await
+            .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))
+    }
+
+    #[test]
+    fn test_get_object_values() {
+        let object = serde_json::json!({
+            "TempC_SHT": "21.50",
+            "BatV": 3.6,
+            "nested": {"a": 1, "b": [true, null]},
+            "empty": null,
+        });
+
+        let values = get_object_values(&object);
+        assert_eq!(4, values.len());
+        assert_eq!(serde_json::json!("21.50"), values["TempC_SHT"]);
+        assert_eq!(serde_json::json!(3.6), values["BatV"]);
+        assert_eq!(serde_json::json!(1), values["nested_a"]);
+        assert_eq!(serde_json::json!(true), values["nested_b_0"]);
+    }
+
+    #[test]
+    fn test_into_value() {
+        assert_eq!(Some(Value::Number(21.6)), 21.6f32.into_value());
+        assert_eq!(Some(Value::Number(1.0)), Some(1i32).into_value());
+        assert_eq!(None, None::<BigDecimal>.into_value());
+        assert_eq!(
+            Some(Value::Text("on".into())),
+            "on".to_string().into_value()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_measurement() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+
+        let mut c = get_async_db_conn().await.unwrap();
+        save_values(
+            &mut c,
+            d.dev_eui,
+            Utc::now(),
+            &[
+                ("air_temperature", 21.5f32.into_value()),
+                ("air_humidity", None::<f32>.into_value()),
+                ("gpio_in_1", "on".to_string().into_value()),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let latest = get_latest(&d.dev_eui).await.unwrap();
+        assert_eq!(2, latest.len());
+        assert_eq!("air_temperature", latest[0].kind);
+        assert_eq!("°C", latest[0].unit);
+        assert_eq!(Some(21.5), latest[0].value);
+        assert_eq!("gpio_in_1", latest[1].kind);
+        assert_eq!(Some("on".to_string()), latest[1].text_value);
+
+        // an older measurement (e.g. a retried uplink) does not replace the latest value
+        save_values(
+            &mut c,
+            d.dev_eui,
+            Utc::now() - chrono::Duration::minutes(10),
+            &[("air_temperature", 18.0f32.into_value())],
+        )
+        .await
+        .unwrap();
+        let latest = get_latest(&d.dev_eui).await.unwrap();
+        assert_eq!(Some(21.5), latest[0].value);
+
+        // the device-profile defines which values of the object are stored
+        let measurements = fields::Measurements::new(
+            [
+                (
+                    "temperature".to_string(),
+                    fields::Measurement {
+                        name: "Temperature".into(),
+                        kind: fields::MeasurementKind::GAUGE,
+                        unit: "°C".into(),
+                    },
+                ),
+                (
+                    "Firmware".to_string(),
+                    fields::Measurement {
+                        name: "".into(),
+                        kind: fields::MeasurementKind::UNKNOWN,
+                        unit: "".into(),
+                    },
+                ),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let object = serde_json::json!({"temperature": "22.10", "Firmware": "1.2"});
+        assert_eq!(
+            1,
+            save_object(&mut c, d.dev_eui, Utc::now(), &measurements, &object)
+                .await
+                .unwrap()
+        );
+
+        let latest = get_latest(&d.dev_eui).await.unwrap();
+        assert_eq!(3, latest.len());
+        assert_eq!("temperature", latest[2].kind);
+        assert_eq!("°C", latest[2].unit);
+        assert_eq!(Some(22.1), latest[2].value);
+    }
+}
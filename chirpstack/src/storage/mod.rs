@@ -14,22 +14,30 @@ use crate::config;
 
 pub mod notification;
 pub mod alarm;
+pub mod alarm_rule;
 pub mod api_key;
 pub mod application;
 pub mod automation;
+pub mod battery;
+pub mod calibration;
+pub mod defrost;
 pub mod device;
 pub mod device_gateway;
 pub mod device_keys;
+pub mod device_link;
 pub mod device_profile;
 pub mod device_profile_template;
 pub mod device_queue;
 pub mod device_session;
 pub mod downlink_frame;
 pub mod error;
+pub mod escalation;
 pub mod fields;
 pub mod gateway;
 pub mod helpers;
+pub mod maintenance;
 pub mod mac_command;
+pub mod measurement;
 pub mod metrics;
 pub mod multicast;
 pub mod passive_roaming;
@@ -42,11 +50,18 @@ mod schema_postgres;
 #[cfg(feature = "sqlite")]
 mod schema_sqlite;
 pub mod search;
+pub mod sensor_data;
+pub mod site;
 #[cfg(feature = "sqlite")]
 mod sqlite;
 pub mod tenant;
+pub mod uplink_task;
+pub mod usage;
 pub mod user;
+pub mod virtual_device;
+pub mod webhook;
 pub mod zone;
+pub mod zone_content_type;
 pub mod data_uplink;
 use crate::monitoring::prometheus;
 
@@ -69,13 +84,16 @@ pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations_postg
 #[cfg(feature = "sqlite")]
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations_sqlite");
 
+#[cfg(feature = "postgres")]
+pub use diesel_async::AsyncPgConnection as AsyncDbConnection;
 #[cfg(feature = "postgres")]
 pub use postgres::{
     db_transaction, get_async_db_conn, AsyncPgPoolConnection as AsyncDbPoolConnection,
 };
 #[cfg(feature = "sqlite")]
 pub use sqlite::{
-    db_transaction, get_async_db_conn, AsyncSqlitePoolConnection as AsyncDbPoolConnection,
+    db_transaction, get_async_db_conn, AsyncSqliteConnection as AsyncDbConnection,
+    AsyncSqlitePoolConnection as AsyncDbPoolConnection,
 };
 
 #[derive(Clone)]
@@ -1,16 +1,56 @@
-use crate::storage::schema_postgres::notifications::dsl as notif_dsl;
+use crate::storage::schema::notifications::dsl as notif_dsl;
 use chrono::NaiveDateTime;
 use diesel::{prelude::*};
-use diesel::sql_types::{Uuid as SqlUuid, Bool};
+#[cfg(feature = "sqlite")]
+use diesel::sql_types::{Bool, Text};
 
 use diesel_async::RunQueryDsl;
-use tracing::info;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use tracing::{info, warn};
 use uuid::Uuid;
-use super::{error::Error, get_async_db_conn};
+use super::{error::Error, fields, get_async_db_conn, usage};
+use crate::monitoring::prometheus;
 
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct DeliveryLabels {
+    channel: String,
+    outcome: String,
+}
+
+lazy_static! {
+    static ref DELIVERY_COUNTER: Family<DeliveryLabels, Counter> = {
+        let counter = Family::<DeliveryLabels, Counter>::default();
+        prometheus::register(
+            "notification_delivery_count",
+            "Number of alarm notification deliveries by channel and outcome",
+            counter.clone(),
+        );
+        counter
+    };
+}
+
+// Records a notification delivery attempt, e.g. channel "sms" with outcome "over_quota".
+pub fn record_delivery(channel: &str, outcome: &str) {
+    DELIVERY_COUNTER
+        .get_or_create(&DeliveryLabels {
+            channel: channel.to_string(),
+            outcome: outcome.to_string(),
+        })
+        .inc();
+}
+
+// Notification categories.
+pub const CATEGORY_ALARM: i32 = 1;
+pub const CATEGORY_GATEWAY: i32 = 2;
+pub const CATEGORY_BATTERY: i32 = 3;
+pub const CATEGORY_DEFROST: i32 = 4;
+pub const CATEGORY_DOWNLINK: i32 = 5;
+pub const CATEGORY_LINK: i32 = 6;
 
 #[derive(Debug, Clone, PartialEq, Eq, Insertable, Queryable)]
-#[diesel(table_name = crate::storage::schema_postgres::notifications)]
+#[diesel(table_name = crate::storage::schema::notifications)]
 pub struct Notification {
     pub id: i32,
     pub sender_id: i32,
@@ -25,11 +65,11 @@ pub struct Notification {
     pub deleted_time: Option<NaiveDateTime>,
     pub dev_eui: Option<String>,
     pub device_name: Option<String>,
-    pub receiver_id: Vec<Option<Uuid>>,
+    pub receiver_id: fields::UuidArray,
 }
 
 #[derive(Debug, Insertable)]
-#[diesel(table_name = crate::storage::schema_postgres::notifications)]
+#[diesel(table_name = crate::storage::schema::notifications)]
 pub struct NewNotification {
     pub sender_id: i32,
     pub message: String,
@@ -43,7 +83,7 @@ pub struct NewNotification {
     pub deleted_time: Option<NaiveDateTime>,
     pub dev_eui: Option<String>,
     pub device_name: Option<String>,
-    pub receiver_id: Vec<Option<Uuid>>,
+    pub receiver_id: fields::UuidArray,
 }
 
 impl Notification {
@@ -85,7 +125,7 @@ impl Default for NewNotification {
             deleted_time: None,
             dev_eui: Some("".to_string()),
             device_name: Some("".to_string()),
-            receiver_id: vec![],
+            receiver_id: fields::UuidArray::default(),
         }
     }
 }
@@ -111,12 +151,27 @@ pub async fn create_notification(notification: Notification) -> Result<Notificat
 
     let conn = &mut get_async_db_conn().await?;
 
-    let generated_id: i32 = diesel::insert_into(notif_dsl::notifications)
+    let generated_id: i32 = match diesel::insert_into(notif_dsl::notifications)
         .values(&new)
         .returning(notif_dsl::id)
         .get_result(conn)
         .await
-        .map_err(|e| Error::from_diesel(e, "insert notification".into()))?;
+    {
+        Ok(v) => {
+            record_delivery("notification", "success");
+            v
+        }
+        Err(e) => {
+            record_delivery("notification", "error");
+            return Err(Error::from_diesel(e, "insert notification".into()));
+        }
+    };
+
+    if let Some(dev_eui) = notification.dev_eui.as_deref().filter(|v| !v.is_empty()) {
+        if let Err(e) = usage::record_device(dev_eui, usage::NOTIFICATION_COUNT, 1.0).await {
+            warn!(dev_eui = %dev_eui, error = %e, "Metering notification failed");
+        }
+    }
 
     Ok(Notification {
         id: generated_id,
@@ -139,9 +194,26 @@ pub async fn get_notification(notification_id: i32) -> Result<Notification, Erro
 pub async fn list(user_id: Uuid) -> Result<Vec<Notification>, Error> {
     let mut conn = get_async_db_conn().await?;
 
-    let results = notif_dsl::notifications
-        .filter(notif_dsl::receiver_id.contains(vec![Some(user_id)]))
+    let mut q = notif_dsl::notifications
         .filter(notif_dsl::is_deleted.eq(Some(false)))
+        .into_boxed();
+
+    #[cfg(feature = "postgres")]
+    {
+        q = q.filter(notif_dsl::receiver_id.contains(fields::UuidArray::from(vec![user_id])));
+    }
+    #[cfg(feature = "sqlite")]
+    {
+        q = q.filter(
+            diesel::dsl::sql::<Bool>(
+                "exists (select 1 from json_each(notifications.receiver_id) where value = ",
+            )
+            .bind::<Text, _>(user_id.to_string())
+            .sql(")"),
+        );
+    }
+
+    let results = q
         .order(notif_dsl::id.desc())
         .limit(500)
         .load::<Notification>(&mut conn)
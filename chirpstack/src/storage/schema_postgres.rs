@@ -26,6 +26,11 @@ diesel::table! {
         distance -> Nullable<Bool>,
         defrost_time -> Nullable<Int4>,
         user_id -> Array<Nullable<Uuid>>,
+        no_data -> Nullable<Bool>,
+        no_data_time -> Nullable<Int4>,
+        rule_type -> Int4,
+        rule_duration -> Nullable<Int4>,
+        rule_limit -> Nullable<Float8>,
     }
 }
 
@@ -71,6 +76,64 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    alarm_escalation (id) {
+        id -> Int4,
+        alarm_id -> Int4,
+        policy_id -> Int4,
+        #[max_length = 30]
+        dev_eui -> Varchar,
+        message -> Text,
+        level -> Int4,
+        triggered_at -> Timestamptz,
+        next_at -> Nullable<Timestamptz>,
+        acknowledged_at -> Nullable<Timestamptz>,
+        acknowledged_by -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    alarm_no_data_trigger (alarm_id, dev_eui) {
+        alarm_id -> Int4,
+        #[max_length = 16]
+        dev_eui -> Varchar,
+        triggered_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    alarm_snooze (alarm_id, user_id) {
+        alarm_id -> Int4,
+        user_id -> Uuid,
+        snoozed_until -> Timestamptz,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    alarm_state (alarm_id, alarm_type, dev_eui) {
+        alarm_id -> Int4,
+        #[max_length = 50]
+        alarm_type -> Varchar,
+        #[max_length = 30]
+        dev_eui -> Varchar,
+        value -> Nullable<Float4>,
+        message -> Text,
+        raised_at -> Timestamptz,
+        acknowledged_at -> Nullable<Timestamptz>,
+        acknowledged_by -> Nullable<Uuid>,
+    }
+}
+
+diesel::table! {
+    alarm_trigger (alarm_id, alarm_type) {
+        alarm_id -> Int4,
+        #[max_length = 50]
+        alarm_type -> Varchar,
+        triggered_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     am103 (id) {
         id -> Int4,
@@ -161,6 +224,44 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    defrost_cycle (id) {
+        id -> Int4,
+        #[max_length = 30]
+        dev_eui -> Varchar,
+        schedule_id -> Nullable<Int4>,
+        window_start -> Timestamptz,
+        started_at -> Nullable<Timestamptz>,
+        ended_at -> Nullable<Timestamptz>,
+        peak_temperature -> Nullable<Float4>,
+        status -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    defrost_learning (dev_eui) {
+        #[max_length = 30]
+        dev_eui -> Varchar,
+        created_at -> Timestamptz,
+        learned_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    defrost_schedule (id) {
+        id -> Int4,
+        zone_id -> Nullable<Int4>,
+        #[max_length = 30]
+        dev_eui -> Nullable<Varchar>,
+        start_time -> Float8,
+        duration -> Int4,
+        learned -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     device (dev_eui) {
         dev_eui -> Bytea,
@@ -201,7 +302,37 @@ diesel::table! {
 }
 
 diesel::table! {
-    device_data_2025 (id) {
+    device_battery (dev_eui) {
+        #[max_length = 30]
+        dev_eui -> Varchar,
+        device_type_id -> Nullable<Int4>,
+        voltage -> Nullable<Float8>,
+        battery_level -> Nullable<Float4>,
+        discharge_per_day -> Nullable<Float4>,
+        days_remaining -> Nullable<Int4>,
+        low_battery_notified_at -> Nullable<Timestamptz>,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    device_calibration (id) {
+        id -> Int4,
+        dev_eui -> Bytea,
+        #[max_length = 100]
+        kind -> Varchar,
+        offset -> Float8,
+        gain -> Float8,
+        points -> Jsonb,
+        effective_from -> Timestamptz,
+        #[max_length = 255]
+        certificate -> Varchar,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    device_data (id) {
         id -> Int4,
         dev_eui -> Text,
         device_type_id -> Int4,
@@ -210,7 +341,7 @@ diesel::table! {
         sol_temperature -> Nullable<Numeric>,
         sol_water -> Nullable<Numeric>,
         sol_conduct_soil -> Nullable<Numeric>,
-        submission_date -> Nullable<Timestamp>,
+        submission_date -> Timestamp,
         water_leak_status -> Nullable<Int4>,
         water_leak_times -> Nullable<Int4>,
         last_water_leak_duration -> Nullable<Int4>,
@@ -234,6 +365,32 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    device_data_daily (dev_eui, metric, bucket) {
+        dev_eui -> Text,
+        #[max_length = 50]
+        metric -> Varchar,
+        bucket -> Timestamp,
+        min_value -> Float8,
+        max_value -> Float8,
+        avg_value -> Float8,
+        sample_count -> Int4,
+    }
+}
+
+diesel::table! {
+    device_data_hourly (dev_eui, metric, bucket) {
+        dev_eui -> Text,
+        #[max_length = 50]
+        metric -> Varchar,
+        bucket -> Timestamp,
+        min_value -> Float8,
+        max_value -> Float8,
+        avg_value -> Float8,
+        sample_count -> Int4,
+    }
+}
+
 diesel::table! {
     device_data_latest (dev_eui) {
         id -> Int4,
@@ -313,6 +470,39 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    device_link (dev_eui) {
+        #[max_length = 30]
+        dev_eui -> Varchar,
+        #[max_length = 30]
+        gateway_id -> Nullable<Varchar>,
+        rssi -> Nullable<Float4>,
+        snr -> Nullable<Float4>,
+        margin -> Nullable<Float4>,
+        margin_baseline -> Nullable<Float4>,
+        #[max_length = 20]
+        signal -> Varchar,
+        uplink_count -> Int4,
+        last_seen_at -> Timestamptz,
+        degraded_notified_at -> Nullable<Timestamptz>,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    device_link_sample (dev_eui, time) {
+        #[max_length = 30]
+        dev_eui -> Varchar,
+        time -> Timestamptz,
+        #[max_length = 30]
+        gateway_id -> Varchar,
+        rssi -> Int4,
+        snr -> Float4,
+        margin -> Nullable<Float4>,
+        gateway_count -> Int4,
+    }
+}
+
 diesel::table! {
     device_profile (id) {
         id -> Uuid,
@@ -381,6 +571,10 @@ diesel::table! {
         relay_notify_limit_bucket_size -> Nullable<Int2>,
         relay_global_uplink_limit_bucket_size -> Nullable<Int2>,
         relay_overall_limit_bucket_size -> Nullable<Int2>,
+        ts003_enabled -> Nullable<Bool>,
+        ts003_f_port -> Nullable<Int2>,
+        downlink_retry_count -> Nullable<Int2>,
+        downlink_retry_backoff -> Nullable<Int4>,
     }
 }
 
@@ -441,6 +635,10 @@ diesel::table! {
         timeout_after -> Nullable<Timestamptz>,
         is_encrypted -> Bool,
         expires_at -> Nullable<Timestamptz>,
+        scheduled_at -> Nullable<Timestamptz>,
+        priority -> Int2,
+        coalesce_key -> Nullable<Varchar>,
+        retry_count -> Int2,
     }
 }
 
@@ -494,6 +692,39 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    escalation_policy (id) {
+        id -> Int4,
+        tenant_id -> Uuid,
+        #[max_length = 100]
+        name -> Varchar,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    escalation_policy_target (id) {
+        id -> Int4,
+        policy_id -> Int4,
+        alarm_id -> Nullable<Int4>,
+        zone_id -> Nullable<Int4>,
+    }
+}
+
+diesel::table! {
+    escalation_step (id) {
+        id -> Int4,
+        policy_id -> Int4,
+        level -> Int4,
+        delay_minutes -> Int4,
+        #[max_length = 20]
+        channel -> Varchar,
+        user_id -> Array<Nullable<Uuid>>,
+        webhook_url -> Text,
+    }
+}
+
 diesel::table! {
     fuota_deployment (id) {
         id -> Uuid,
@@ -585,6 +816,7 @@ diesel::table! {
         tls_certificate -> Nullable<Bytea>,
         tags -> Jsonb,
         properties -> Jsonb,
+        is_offline -> Bool,
     }
 }
 
@@ -602,6 +834,50 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    maintenance_window (id) {
+        id -> Int4,
+        tenant_id -> Uuid,
+        #[max_length = 30]
+        dev_eui -> Nullable<Varchar>,
+        zone_id -> Nullable<Int4>,
+        site_id -> Nullable<Int4>,
+        start_at -> Timestamptz,
+        end_at -> Timestamptz,
+        reason -> Text,
+        created_by -> Nullable<Uuid>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    measurement (dev_eui, kind, time) {
+        dev_eui -> Bytea,
+        #[max_length = 100]
+        kind -> Varchar,
+        time -> Timestamptz,
+        #[max_length = 20]
+        unit -> Varchar,
+        value -> Nullable<Float8>,
+        #[max_length = 100]
+        text_value -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    measurement_latest (dev_eui, kind) {
+        dev_eui -> Bytea,
+        #[max_length = 100]
+        kind -> Varchar,
+        time -> Timestamptz,
+        #[max_length = 20]
+        unit -> Varchar,
+        value -> Nullable<Float8>,
+        #[max_length = 100]
+        text_value -> Nullable<Varchar>,
+    }
+}
+
 diesel::table! {
     multicast_group (id) {
         id -> Uuid,
@@ -742,6 +1018,23 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    site (site_id) {
+        site_id -> Int4,
+        tenant_id -> Uuid,
+        #[max_length = 100]
+        site_name -> Varchar,
+        address -> Text,
+        latitude -> Nullable<Float8>,
+        longitude -> Nullable<Float8>,
+        #[max_length = 64]
+        timezone -> Varchar,
+        site_order -> Int8,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     tenant (id) {
         id -> Uuid,
@@ -760,6 +1053,12 @@ diesel::table! {
         license -> Nullable<Bool>,
         pro_license -> Nullable<Bool>,
         kitchen_management_license -> Nullable<Bool>,
+        max_message_count -> Int4,
+        max_sms_count -> Int4,
+        #[max_length = 20]
+        over_quota_action -> Varchar,
+        raw_data_retention_days -> Int4,
+        hourly_data_retention_months -> Int4,
     }
 }
 
@@ -805,6 +1104,21 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    uplink_task (id) {
+        id -> Int8,
+        dev_eui -> Bytea,
+        #[max_length = 20]
+        kind -> Varchar,
+        object -> Text,
+        created_at -> Timestamptz,
+        run_after -> Timestamptz,
+        locked_until -> Nullable<Timestamptz>,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+    }
+}
+
 diesel::table! {
     user (id) {
         id -> Uuid,
@@ -830,6 +1144,68 @@ diesel::table! {
         expo_key -> Nullable<Varchar>,
         #[max_length = 250]
         web_key -> Nullable<Varchar>,
+        site_id_list -> Nullable<Array<Nullable<Int8>>>,
+    }
+}
+
+diesel::table! {
+    virtual_device (dev_eui) {
+        dev_eui -> Bytea,
+        #[max_length = 100]
+        kind -> Varchar,
+        #[max_length = 20]
+        unit -> Varchar,
+        formula -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    virtual_device_input (dev_eui, name) {
+        dev_eui -> Bytea,
+        #[max_length = 100]
+        name -> Varchar,
+        input_dev_eui -> Bytea,
+        #[max_length = 100]
+        kind -> Varchar,
+        #[max_length = 20]
+        aggregate -> Varchar,
+    }
+}
+
+diesel::table! {
+    webhook_delivery (id) {
+        id -> Int8,
+        subscription_id -> Int4,
+        #[max_length = 50]
+        event -> Varchar,
+        payload -> Text,
+        #[max_length = 20]
+        status -> Varchar,
+        attempts -> Int4,
+        run_after -> Timestamptz,
+        locked_until -> Nullable<Timestamptz>,
+        response_status -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamptz,
+        delivered_at -> Nullable<Timestamptz>,
+    }
+}
+
+diesel::table! {
+    webhook_subscription (id) {
+        id -> Int4,
+        tenant_id -> Uuid,
+        #[max_length = 100]
+        name -> Varchar,
+        url -> Text,
+        #[max_length = 100]
+        secret -> Varchar,
+        events -> Array<Nullable<Text>>,
+        is_disabled -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
     }
 }
 
@@ -882,6 +1258,7 @@ diesel::table! {
         content_type -> Nullable<Int8>,
         tanent_id -> Nullable<Uuid>,
         devices -> Array<Nullable<Text>>,
+        site_id -> Nullable<Int4>,
     }
 }
 
@@ -896,14 +1273,39 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    zone_content_type (id) {
+        id -> Int8,
+        #[max_length = 100]
+        name -> Varchar,
+        min_temperature -> Nullable<Float8>,
+        max_temperature -> Nullable<Float8>,
+        min_humidity -> Nullable<Float8>,
+        max_humidity -> Nullable<Float8>,
+        measurements -> Array<Nullable<Text>>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::joinable!(alarm_escalation -> alarm (alarm_id));
+diesel::joinable!(alarm_escalation -> escalation_policy (policy_id));
+diesel::joinable!(alarm_no_data_trigger -> alarm (alarm_id));
+diesel::joinable!(alarm_snooze -> alarm (alarm_id));
+diesel::joinable!(alarm_state -> alarm (alarm_id));
+diesel::joinable!(alarm_trigger -> alarm (alarm_id));
 diesel::joinable!(api_key -> tenant (tenant_id));
 diesel::joinable!(application -> tenant (tenant_id));
 diesel::joinable!(application_integration -> application (application_id));
 diesel::joinable!(device -> application (application_id));
 diesel::joinable!(device -> device_profile (device_profile_id));
+diesel::joinable!(device_calibration -> device (dev_eui));
 diesel::joinable!(device_keys -> device (dev_eui));
 diesel::joinable!(device_profile -> tenant (tenant_id));
 diesel::joinable!(device_queue_item -> device (dev_eui));
+diesel::joinable!(escalation_policy -> tenant (tenant_id));
+diesel::joinable!(escalation_policy_target -> escalation_policy (policy_id));
+diesel::joinable!(escalation_step -> escalation_policy (policy_id));
 diesel::joinable!(fuota_deployment -> application (application_id));
 diesel::joinable!(fuota_deployment -> device_profile (device_profile_id));
 diesel::joinable!(fuota_deployment_device -> device (dev_eui));
@@ -912,7 +1314,13 @@ diesel::joinable!(fuota_deployment_gateway -> fuota_deployment (fuota_deployment
 diesel::joinable!(fuota_deployment_gateway -> gateway (gateway_id));
 diesel::joinable!(fuota_deployment_job -> fuota_deployment (fuota_deployment_id));
 diesel::joinable!(gateway -> tenant (tenant_id));
+diesel::joinable!(maintenance_window -> site (site_id));
+diesel::joinable!(maintenance_window -> tenant (tenant_id));
+diesel::joinable!(maintenance_window -> zone (zone_id));
+diesel::joinable!(measurement -> device (dev_eui));
+diesel::joinable!(measurement_latest -> device (dev_eui));
 diesel::joinable!(multicast_group -> application (application_id));
+diesel::joinable!(defrost_cycle -> defrost_schedule (schedule_id));
 diesel::joinable!(multicast_group_device -> device (dev_eui));
 diesel::joinable!(multicast_group_device -> multicast_group (multicast_group_id));
 diesel::joinable!(multicast_group_gateway -> gateway (gateway_id));
@@ -920,24 +1328,45 @@ diesel::joinable!(multicast_group_gateway -> multicast_group (multicast_group_id
 diesel::joinable!(multicast_group_queue_item -> gateway (gateway_id));
 diesel::joinable!(multicast_group_queue_item -> multicast_group (multicast_group_id));
 diesel::joinable!(relay_gateway -> tenant (tenant_id));
+diesel::joinable!(site -> tenant (tenant_id));
 diesel::joinable!(tenant_user -> tenant (tenant_id));
 diesel::joinable!(tenant_user -> user (user_id));
+diesel::joinable!(uplink_task -> device (dev_eui));
+diesel::joinable!(virtual_device -> device (dev_eui));
+diesel::joinable!(virtual_device_input -> virtual_device (dev_eui));
+diesel::joinable!(webhook_delivery -> webhook_subscription (subscription_id));
+diesel::joinable!(webhook_subscription -> tenant (tenant_id));
+diesel::joinable!(zone -> site (site_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
     alarm,
     alarm_audit_log,
     alarm_automation_rules,
     alarm_date_time,
+    alarm_escalation,
+    alarm_no_data_trigger,
+    alarm_snooze,
+    alarm_state,
+    alarm_trigger,
     am103,
     api_key,
     application,
     application_integration,
     automation_rules,
     dds45lb,
+    defrost_cycle,
+    defrost_learning,
+    defrost_schedule,
     device,
-    device_data_2025,
+    device_calibration,
+    device_battery,
+    device_data,
+    device_data_daily,
+    device_data_hourly,
     device_data_latest,
     device_keys,
+    device_link,
+    device_link_sample,
     device_profile,
     device_profile_template,
     device_queue_item,
@@ -945,12 +1374,18 @@ diesel::allow_tables_to_appear_in_same_query!(
     door_alarm_date_time,
     door_time_alarm,
     em400mud,
+    escalation_policy,
+    escalation_policy_target,
+    escalation_step,
     fuota_deployment,
     fuota_deployment_device,
     fuota_deployment_gateway,
     fuota_deployment_job,
     gateway,
     ltc2lb,
+    maintenance_window,
+    measurement,
+    measurement_latest,
     multicast_group,
     multicast_group_device,
     multicast_group_gateway,
@@ -960,12 +1395,19 @@ diesel::allow_tables_to_appear_in_same_query!(
     relay_gateway,
     sanitize_logs,
     sensors,
+    site,
     tenant,
     tenant_user,
     uc300,
+    uplink_task,
     user,
+    virtual_device,
+    virtual_device_input,
+    webhook_delivery,
+    webhook_subscription,
     ws522,
     ws558,
     zone,
     zone_clean,
+    zone_content_type,
 );
@@ -1,5 +1,142 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    alarm (id) {
+        id -> Integer,
+        dev_eui -> Text,
+        min_treshold -> Nullable<Double>,
+        max_treshold -> Nullable<Double>,
+        sms -> Nullable<Bool>,
+        email -> Nullable<Bool>,
+        temperature -> Nullable<Bool>,
+        humadity -> Nullable<Bool>,
+        ec -> Nullable<Bool>,
+        door -> Nullable<Bool>,
+        w_leak -> Nullable<Bool>,
+        is_time_limit_active -> Nullable<Bool>,
+        alarm_start_time -> Nullable<Double>,
+        alarm_stop_time -> Nullable<Double>,
+        zone_category -> Nullable<Integer>,
+        notification -> Nullable<Bool>,
+        is_active -> Nullable<Bool>,
+        pressure -> Nullable<Bool>,
+        notification_sound -> Nullable<Text>,
+        distance -> Nullable<Bool>,
+        defrost_time -> Nullable<Integer>,
+        user_id -> Text,
+        no_data -> Nullable<Bool>,
+        no_data_time -> Nullable<Integer>,
+        rule_type -> Integer,
+        rule_duration -> Nullable<Integer>,
+        rule_limit -> Nullable<Double>,
+    }
+}
+
+diesel::table! {
+    alarm_audit_log (id) {
+        id -> Integer,
+        alarm_id -> Integer,
+        dev_eui -> Nullable<Text>,
+        change_type -> Nullable<Text>,
+        changed_at -> Nullable<Timestamp>,
+        old_values -> Nullable<Text>,
+        new_values -> Nullable<Text>,
+        changed_by -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    alarm_automation_rules (id) {
+        id -> Integer,
+        alarm_id -> Integer,
+        receiver_sensor -> Text,
+        action -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+        is_active -> Nullable<Bool>,
+        receiver_device_type -> Nullable<Integer>,
+        receiver_device_name -> Nullable<Text>,
+        user_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    alarm_date_time (id) {
+        alarm_id -> Integer,
+        alarm_day -> Integer,
+        start_time -> Double,
+        end_time -> Double,
+        id -> Integer,
+    }
+}
+
+diesel::table! {
+    alarm_escalation (id) {
+        id -> Integer,
+        alarm_id -> Integer,
+        policy_id -> Integer,
+        dev_eui -> Text,
+        message -> Text,
+        level -> Integer,
+        triggered_at -> TimestamptzSqlite,
+        next_at -> Nullable<TimestamptzSqlite>,
+        acknowledged_at -> Nullable<TimestamptzSqlite>,
+        acknowledged_by -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    alarm_no_data_trigger (alarm_id, dev_eui) {
+        alarm_id -> Integer,
+        dev_eui -> Text,
+        triggered_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    alarm_snooze (alarm_id, user_id) {
+        alarm_id -> Integer,
+        user_id -> Text,
+        snoozed_until -> TimestamptzSqlite,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    alarm_state (alarm_id, alarm_type, dev_eui) {
+        alarm_id -> Integer,
+        alarm_type -> Text,
+        dev_eui -> Text,
+        value -> Nullable<Float>,
+        message -> Text,
+        raised_at -> TimestamptzSqlite,
+        acknowledged_at -> Nullable<TimestamptzSqlite>,
+        acknowledged_by -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    alarm_trigger (alarm_id, alarm_type) {
+        alarm_id -> Integer,
+        alarm_type -> Text,
+        triggered_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    am103 (id) {
+        id -> Integer,
+        dev_eui -> Text,
+        device_type_id -> Integer,
+        org_id -> Integer,
+        submission_date -> Nullable<Timestamp>,
+        air_temperature -> Nullable<Double>,
+        air_humidity -> Nullable<Double>,
+        co2_ppm -> Nullable<Double>,
+        batv -> Nullable<Integer>,
+    }
+}
+
 diesel::table! {
     api_key (id) {
         id -> Text,
@@ -33,6 +170,74 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    automation_rules (id) {
+        id -> Integer,
+        sender_sensor -> Nullable<Text>,
+        receiver_sensor -> Nullable<Text>,
+        condition -> Nullable<Text>,
+        action -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+        is_active -> Nullable<Bool>,
+        sender_device_type -> Nullable<Integer>,
+        receiver_device_type -> Nullable<Integer>,
+        sender_device_name -> Nullable<Text>,
+        receiver_device_name -> Nullable<Text>,
+        trigger_type -> Nullable<Text>,
+        trigger_time -> Nullable<Timestamp>,
+        tenant_id -> Nullable<Text>,
+        user_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    dds45lb (id) {
+        id -> Integer,
+        dev_eui -> Text,
+        device_type_id -> Integer,
+        org_id -> Integer,
+        submission_date -> Nullable<Timestamp>,
+        distance -> Nullable<Integer>,
+        batv -> Nullable<Double>,
+    }
+}
+
+diesel::table! {
+    defrost_cycle (id) {
+        id -> Integer,
+        dev_eui -> Text,
+        schedule_id -> Nullable<Integer>,
+        window_start -> TimestamptzSqlite,
+        started_at -> Nullable<TimestamptzSqlite>,
+        ended_at -> Nullable<TimestamptzSqlite>,
+        peak_temperature -> Nullable<Float>,
+        status -> Integer,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    defrost_learning (dev_eui) {
+        dev_eui -> Text,
+        created_at -> TimestamptzSqlite,
+        learned_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    defrost_schedule (id) {
+        id -> Integer,
+        zone_id -> Nullable<Integer>,
+        dev_eui -> Nullable<Text>,
+        start_time -> Double,
+        duration -> Integer,
+        learned -> Bool,
+        created_at -> TimestamptzSqlite,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
 diesel::table! {
     device (dev_eui) {
         dev_eui -> Binary,
@@ -60,6 +265,154 @@ diesel::table! {
         join_eui -> Binary,
         secondary_dev_addr -> Nullable<Binary>,
         device_session -> Nullable<Binary>,
+        data_time -> Nullable<Integer>,
+        temperature_calibration -> Nullable<Double>,
+        humadity_calibration -> Nullable<Double>,
+        device_profile_name -> Nullable<Text>,
+        device_type -> Nullable<Integer>,
+        tenant_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    device_battery (dev_eui) {
+        dev_eui -> Text,
+        device_type_id -> Nullable<Integer>,
+        voltage -> Nullable<Double>,
+        battery_level -> Nullable<Float>,
+        discharge_per_day -> Nullable<Float>,
+        days_remaining -> Nullable<Integer>,
+        low_battery_notified_at -> Nullable<TimestamptzSqlite>,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    device_calibration (id) {
+        id -> Integer,
+        dev_eui -> Binary,
+        kind -> Text,
+        offset -> Double,
+        gain -> Double,
+        points -> Text,
+        effective_from -> TimestamptzSqlite,
+        certificate -> Text,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    device_data (id) {
+        id -> Integer,
+        dev_eui -> Text,
+        device_type_id -> Integer,
+        air_temperature -> Nullable<Double>,
+        air_humidity -> Nullable<Double>,
+        sol_temperature -> Nullable<Double>,
+        sol_water -> Nullable<Double>,
+        sol_conduct_soil -> Nullable<Double>,
+        submission_date -> Timestamp,
+        water_leak_status -> Nullable<Integer>,
+        water_leak_times -> Nullable<Integer>,
+        last_water_leak_duration -> Nullable<Integer>,
+        door_open_status -> Nullable<Integer>,
+        door_open_times -> Nullable<Integer>,
+        last_door_open_duration -> Nullable<Integer>,
+        batv -> Nullable<Double>,
+        ro1_status -> Nullable<Integer>,
+        ro2_status -> Nullable<Integer>,
+        ph_soil -> Nullable<Double>,
+        co2_ppm -> Nullable<Double>,
+        tvoc_ppm -> Nullable<Double>,
+        sensecap_light -> Nullable<Double>,
+        barometric_pressure -> Nullable<Double>,
+        status -> Nullable<Integer>,
+        current -> Nullable<Double>,
+        factor -> Nullable<Double>,
+        power -> Nullable<Double>,
+        power_sum -> Nullable<Double>,
+        voltage -> Nullable<Double>,
+    }
+}
+
+diesel::table! {
+    device_data_daily (dev_eui, metric, bucket) {
+        dev_eui -> Text,
+        metric -> Text,
+        bucket -> Timestamp,
+        min_value -> Double,
+        max_value -> Double,
+        avg_value -> Double,
+        sample_count -> Integer,
+    }
+}
+
+diesel::table! {
+    device_data_hourly (dev_eui, metric, bucket) {
+        dev_eui -> Text,
+        metric -> Text,
+        bucket -> Timestamp,
+        min_value -> Double,
+        max_value -> Double,
+        avg_value -> Double,
+        sample_count -> Integer,
+    }
+}
+
+diesel::table! {
+    device_data_latest (dev_eui) {
+        id -> Integer,
+        dev_eui -> Text,
+        device_type_id -> Integer,
+        org_id -> Integer,
+        air_temperature -> Nullable<Double>,
+        air_humidity -> Nullable<Double>,
+        sol_temperature -> Nullable<Double>,
+        sol_water -> Nullable<Double>,
+        sol_conduct_soil -> Nullable<Double>,
+        submission_date -> Nullable<Timestamp>,
+        water_leak_status -> Nullable<Integer>,
+        water_leak_times -> Nullable<Integer>,
+        last_water_leak_duration -> Nullable<Integer>,
+        door_open_status -> Nullable<Integer>,
+        door_open_times -> Nullable<Integer>,
+        last_door_open_duration -> Nullable<Integer>,
+        batv -> Nullable<Double>,
+        ro1_status -> Nullable<Integer>,
+        ro2_status -> Nullable<Integer>,
+        ph_soil -> Nullable<Double>,
+        co2_ppm -> Nullable<Double>,
+        tvoc_ppm -> Nullable<Double>,
+        sensecap_light -> Nullable<Double>,
+        barometric_pressure -> Nullable<Double>,
+        current -> Nullable<Double>,
+        factor -> Nullable<Double>,
+        power -> Nullable<Double>,
+        voltage -> Nullable<Double>,
+        power_sum -> Nullable<Double>,
+        status -> Nullable<Integer>,
+        power_consumption -> Nullable<Integer>,
+        switch1 -> Nullable<Integer>,
+        switch2 -> Nullable<Integer>,
+        switch3 -> Nullable<Integer>,
+        switch4 -> Nullable<Integer>,
+        switch5 -> Nullable<Integer>,
+        switch6 -> Nullable<Integer>,
+        switch7 -> Nullable<Integer>,
+        switch8 -> Nullable<Integer>,
+        adc_1 -> Nullable<Text>,
+        adc_2 -> Nullable<Text>,
+        adv_1 -> Nullable<Text>,
+        gpio_in_1 -> Nullable<Text>,
+        gpio_in_2 -> Nullable<Text>,
+        gpio_in_3 -> Nullable<Text>,
+        gpio_in_4 -> Nullable<Text>,
+        gpio_out_1 -> Nullable<Text>,
+        gpio_out_2 -> Nullable<Text>,
+        distance -> Nullable<Integer>,
+        position -> Nullable<Text>,
+        temperature1 -> Nullable<Double>,
+        temperature2 -> Nullable<Double>,
     }
 }
 
@@ -75,6 +428,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    device_link (dev_eui) {
+        dev_eui -> Text,
+        gateway_id -> Nullable<Text>,
+        rssi -> Nullable<Float>,
+        snr -> Nullable<Float>,
+        margin -> Nullable<Float>,
+        margin_baseline -> Nullable<Float>,
+        signal -> Text,
+        uplink_count -> Integer,
+        last_seen_at -> TimestamptzSqlite,
+        degraded_notified_at -> Nullable<TimestamptzSqlite>,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    device_link_sample (dev_eui, time) {
+        dev_eui -> Text,
+        time -> TimestamptzSqlite,
+        gateway_id -> Text,
+        rssi -> Integer,
+        snr -> Float,
+        margin -> Nullable<Float>,
+        gateway_count -> Integer,
+    }
+}
+
 diesel::table! {
     device_profile (id) {
         id -> Text,
@@ -92,15 +473,6 @@ diesel::table! {
         supports_otaa -> Bool,
         supports_class_b -> Bool,
         supports_class_c -> Bool,
-        class_b_timeout -> Integer,
-        class_b_ping_slot_nb_k -> Integer,
-        class_b_ping_slot_dr -> SmallInt,
-        class_b_ping_slot_freq -> BigInt,
-        class_c_timeout -> Integer,
-        abp_rx1_delay -> SmallInt,
-        abp_rx1_dr_offset -> SmallInt,
-        abp_rx2_dr -> SmallInt,
-        abp_rx2_freq -> BigInt,
         tags -> Text,
         payload_codec_script -> Text,
         flush_queue_on_activate -> Bool,
@@ -108,30 +480,47 @@ diesel::table! {
         measurements -> Text,
         auto_detect_measurements -> Bool,
         region_config_id -> Nullable<Text>,
-        is_relay -> Bool,
-        is_relay_ed -> Bool,
-        relay_ed_relay_only -> Bool,
-        relay_enabled -> Bool,
-        relay_cad_periodicity -> SmallInt,
-        relay_default_channel_index -> SmallInt,
-        relay_second_channel_freq -> BigInt,
-        relay_second_channel_dr -> SmallInt,
-        relay_second_channel_ack_offset -> SmallInt,
-        relay_ed_activation_mode -> SmallInt,
-        relay_ed_smart_enable_level -> SmallInt,
-        relay_ed_back_off -> SmallInt,
-        relay_ed_uplink_limit_bucket_size -> SmallInt,
-        relay_ed_uplink_limit_reload_rate -> SmallInt,
-        relay_join_req_limit_reload_rate -> SmallInt,
-        relay_notify_limit_reload_rate -> SmallInt,
-        relay_global_uplink_limit_reload_rate -> SmallInt,
-        relay_overall_limit_reload_rate -> SmallInt,
-        relay_join_req_limit_bucket_size -> SmallInt,
-        relay_notify_limit_bucket_size -> SmallInt,
-        relay_global_uplink_limit_bucket_size -> SmallInt,
-        relay_overall_limit_bucket_size -> SmallInt,
         allow_roaming -> Bool,
         rx1_delay -> SmallInt,
+        abp_params -> Nullable<Text>,
+        class_b_params -> Nullable<Text>,
+        class_c_params -> Nullable<Text>,
+        relay_params -> Nullable<Text>,
+        class_b_timeout -> Nullable<Integer>,
+        class_b_ping_slot_nb_k -> Nullable<Integer>,
+        class_b_ping_slot_dr -> Nullable<SmallInt>,
+        class_b_ping_slot_freq -> Nullable<BigInt>,
+        class_c_timeout -> Nullable<Integer>,
+        abp_rx1_delay -> Nullable<SmallInt>,
+        abp_rx1_dr_offset -> Nullable<SmallInt>,
+        abp_rx2_dr -> Nullable<SmallInt>,
+        abp_rx2_freq -> Nullable<BigInt>,
+        is_relay -> Nullable<Bool>,
+        is_relay_ed -> Nullable<Bool>,
+        relay_ed_relay_only -> Nullable<Bool>,
+        relay_enabled -> Nullable<Bool>,
+        relay_cad_periodicity -> Nullable<SmallInt>,
+        relay_default_channel_index -> Nullable<SmallInt>,
+        relay_second_channel_freq -> Nullable<BigInt>,
+        relay_second_channel_dr -> Nullable<SmallInt>,
+        relay_second_channel_ack_offset -> Nullable<SmallInt>,
+        relay_ed_activation_mode -> Nullable<SmallInt>,
+        relay_ed_smart_enable_level -> Nullable<SmallInt>,
+        relay_ed_back_off -> Nullable<SmallInt>,
+        relay_ed_uplink_limit_bucket_size -> Nullable<SmallInt>,
+        relay_ed_uplink_limit_reload_rate -> Nullable<SmallInt>,
+        relay_join_req_limit_reload_rate -> Nullable<SmallInt>,
+        relay_notify_limit_reload_rate -> Nullable<SmallInt>,
+        relay_global_uplink_limit_reload_rate -> Nullable<SmallInt>,
+        relay_overall_limit_reload_rate -> Nullable<SmallInt>,
+        relay_join_req_limit_bucket_size -> Nullable<SmallInt>,
+        relay_notify_limit_bucket_size -> Nullable<SmallInt>,
+        relay_global_uplink_limit_bucket_size -> Nullable<SmallInt>,
+        relay_overall_limit_bucket_size -> Nullable<SmallInt>,
+        ts003_enabled -> Nullable<Bool>,
+        ts003_f_port -> Nullable<SmallInt>,
+        downlink_retry_count -> Nullable<SmallInt>,
+        downlink_retry_backoff -> Nullable<Integer>,
     }
 }
 
@@ -184,6 +573,88 @@ diesel::table! {
         timeout_after -> Nullable<TimestamptzSqlite>,
         is_encrypted -> Bool,
         expires_at -> Nullable<TimestamptzSqlite>,
+        scheduled_at -> Nullable<TimestamptzSqlite>,
+        priority -> SmallInt,
+        coalesce_key -> Nullable<Text>,
+        retry_count -> SmallInt,
+    }
+}
+
+diesel::table! {
+    device_type_tb (id) {
+        id -> Integer,
+        device_profile_name -> Text,
+        application_name -> Text,
+    }
+}
+
+diesel::table! {
+    door_alarm_date_time (id) {
+        id -> Integer,
+        alarm_id -> Integer,
+        alarm_day -> Integer,
+        start_time -> Double,
+        end_time -> Double,
+    }
+}
+
+diesel::table! {
+    door_time_alarm (id) {
+        id -> Integer,
+        dev_eui -> Nullable<Text>,
+        sms -> Nullable<Bool>,
+        email -> Nullable<Bool>,
+        notification -> Nullable<Bool>,
+        submission_time -> Nullable<Timestamp>,
+        is_active -> Nullable<Bool>,
+        time -> Nullable<BigInt>,
+        user_id -> Nullable<Text>,
+        tenant_id -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    em400mud (id) {
+        id -> Integer,
+        dev_eui -> Text,
+        device_type_id -> Integer,
+        org_id -> Integer,
+        submission_date -> Nullable<Timestamp>,
+        distance -> Nullable<Integer>,
+        position -> Nullable<Text>,
+        air_temperature -> Nullable<Double>,
+        batv -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    escalation_policy (id) {
+        id -> Integer,
+        tenant_id -> Text,
+        name -> Text,
+        created_at -> TimestamptzSqlite,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    escalation_policy_target (id) {
+        id -> Integer,
+        policy_id -> Integer,
+        alarm_id -> Nullable<Integer>,
+        zone_id -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    escalation_step (id) {
+        id -> Integer,
+        policy_id -> Integer,
+        level -> Integer,
+        delay_minutes -> Integer,
+        channel -> Text,
+        user_id -> Text,
+        webhook_url -> Text,
     }
 }
 
@@ -203,6 +674,57 @@ diesel::table! {
         tls_certificate -> Nullable<Binary>,
         tags -> Text,
         properties -> Text,
+        is_offline -> Bool,
+    }
+}
+
+diesel::table! {
+    ltc2lb (id) {
+        id -> Integer,
+        dev_eui -> Text,
+        temperature1 -> Nullable<Double>,
+        temperature2 -> Nullable<Double>,
+        batv -> Nullable<Double>,
+        org_id -> Nullable<Integer>,
+        device_type_id -> Nullable<Integer>,
+        submission_date -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    maintenance_window (id) {
+        id -> Integer,
+        tenant_id -> Text,
+        dev_eui -> Nullable<Text>,
+        zone_id -> Nullable<Integer>,
+        site_id -> Nullable<Integer>,
+        start_at -> TimestamptzSqlite,
+        end_at -> TimestamptzSqlite,
+        reason -> Text,
+        created_by -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    measurement (dev_eui, kind, time) {
+        dev_eui -> Binary,
+        kind -> Text,
+        time -> TimestamptzSqlite,
+        unit -> Text,
+        value -> Nullable<Double>,
+        text_value -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    measurement_latest (dev_eui, kind) {
+        dev_eui -> Binary,
+        kind -> Text,
+        time -> TimestamptzSqlite,
+        unit -> Text,
+        value -> Nullable<Double>,
+        text_value -> Nullable<Text>,
     }
 }
 
@@ -257,6 +779,25 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    notifications (id) {
+        id -> Integer,
+        sender_id -> Integer,
+        message -> Text,
+        category_id -> Integer,
+        is_read -> Nullable<Bool>,
+        send_time -> Nullable<Timestamp>,
+        read_time -> Nullable<Timestamp>,
+        sender_ip -> Nullable<Text>,
+        reader_ip -> Nullable<Text>,
+        is_deleted -> Nullable<Bool>,
+        deleted_time -> Nullable<Timestamp>,
+        dev_eui -> Nullable<Text>,
+        device_name -> Nullable<Text>,
+        receiver_id -> Text,
+    }
+}
+
 diesel::table! {
     relay_device (relay_dev_eui, dev_eui) {
         relay_dev_eui -> Binary,
@@ -279,6 +820,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sensors (dev_eui) {
+        id -> Integer,
+        sn -> Nullable<Text>,
+        dev_eui -> Text,
+        app_eui -> Nullable<Text>,
+        app_key -> Nullable<Text>,
+        dev_addr -> Nullable<Text>,
+        netskey -> Nullable<Text>,
+        appskey -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    site (site_id) {
+        site_id -> Integer,
+        tenant_id -> Text,
+        site_name -> Text,
+        address -> Text,
+        latitude -> Nullable<Double>,
+        longitude -> Nullable<Double>,
+        timezone -> Text,
+        site_order -> BigInt,
+        created_at -> TimestamptzSqlite,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
 diesel::table! {
     tenant (id) {
         id -> Text,
@@ -292,6 +861,15 @@ diesel::table! {
         private_gateways_up -> Bool,
         private_gateways_down -> Bool,
         tags -> Text,
+        sms_count -> Nullable<Integer>,
+        license -> Nullable<Bool>,
+        pro_license -> Nullable<Bool>,
+        kitchen_management_license -> Nullable<Bool>,
+        max_message_count -> Integer,
+        max_sms_count -> Integer,
+        over_quota_action -> Text,
+        raw_data_retention_days -> Integer,
+        hourly_data_retention_months -> Integer,
     }
 }
 
@@ -304,6 +882,40 @@ diesel::table! {
         is_admin -> Bool,
         is_device_admin -> Bool,
         is_gateway_admin -> Bool,
+        is_visible -> Nullable<Bool>,
+    }
+}
+
+diesel::table! {
+    uc300 (id) {
+        id -> Integer,
+        dev_eui -> Text,
+        device_type_id -> Nullable<Integer>,
+        org_id -> Nullable<Integer>,
+        adc_1 -> Nullable<Text>,
+        adc_2 -> Nullable<Text>,
+        adv_1 -> Nullable<Text>,
+        gpio_in_1 -> Nullable<Text>,
+        gpio_in_2 -> Nullable<Text>,
+        gpio_in_3 -> Nullable<Text>,
+        gpio_in_4 -> Nullable<Text>,
+        gpio_out_1 -> Nullable<Text>,
+        gpio_out_2 -> Nullable<Text>,
+        submission_date -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    uplink_task (id) {
+        id -> BigInt,
+        dev_eui -> Binary,
+        kind -> Text,
+        object -> Text,
+        created_at -> TimestamptzSqlite,
+        run_after -> TimestamptzSqlite,
+        locked_until -> Nullable<TimestamptzSqlite>,
+        attempts -> Integer,
+        last_error -> Nullable<Text>,
     }
 }
 
@@ -319,18 +931,121 @@ diesel::table! {
         email_verified -> Bool,
         password_hash -> Text,
         note -> Text,
+        android_key -> Nullable<Text>,
+        phone_number -> Text,
+        name -> Nullable<Text>,
+        username -> Nullable<Text>,
+        zone_id_list -> Nullable<Text>,
+        training -> Bool,
+        expo_key -> Nullable<Text>,
+        web_key -> Nullable<Text>,
+        site_id_list -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    virtual_device (dev_eui) {
+        dev_eui -> Binary,
+        kind -> Text,
+        unit -> Text,
+        formula -> Text,
+        created_at -> TimestamptzSqlite,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    virtual_device_input (dev_eui, name) {
+        dev_eui -> Binary,
+        name -> Text,
+        input_dev_eui -> Binary,
+        kind -> Text,
+        aggregate -> Text,
+    }
+}
+
+diesel::table! {
+    webhook_delivery (id) {
+        id -> BigInt,
+        subscription_id -> Integer,
+        event -> Text,
+        payload -> Text,
+        status -> Text,
+        attempts -> Integer,
+        run_after -> TimestamptzSqlite,
+        locked_until -> Nullable<TimestamptzSqlite>,
+        response_status -> Nullable<Integer>,
+        last_error -> Nullable<Text>,
+        created_at -> TimestamptzSqlite,
+        delivered_at -> Nullable<TimestamptzSqlite>,
+    }
+}
+
+diesel::table! {
+    webhook_subscription (id) {
+        id -> Integer,
+        tenant_id -> Text,
+        name -> Text,
+        url -> Text,
+        secret -> Text,
+        events -> Text,
+        is_disabled -> Bool,
+        created_at -> TimestamptzSqlite,
+        updated_at -> TimestamptzSqlite,
+    }
+}
+
+diesel::table! {
+    zone (zone_id) {
+        zone_id -> Integer,
+        zone_name -> Nullable<Text>,
+        zone_order -> Nullable<BigInt>,
+        content_type -> Nullable<BigInt>,
+        tanent_id -> Nullable<Text>,
+        devices -> Text,
+        site_id -> Nullable<Integer>,
+    }
+}
+
+diesel::table! {
+    zone_content_type (id) {
+        id -> BigInt,
+        name -> Text,
+        min_temperature -> Nullable<Double>,
+        max_temperature -> Nullable<Double>,
+        min_humidity -> Nullable<Double>,
+        max_humidity -> Nullable<Double>,
+        measurements -> Text,
+        created_at -> TimestamptzSqlite,
+        updated_at -> TimestamptzSqlite,
     }
 }
 
+diesel::joinable!(alarm_escalation -> alarm (alarm_id));
+diesel::joinable!(alarm_escalation -> escalation_policy (policy_id));
+diesel::joinable!(alarm_no_data_trigger -> alarm (alarm_id));
+diesel::joinable!(alarm_snooze -> alarm (alarm_id));
+diesel::joinable!(alarm_state -> alarm (alarm_id));
+diesel::joinable!(alarm_trigger -> alarm (alarm_id));
 diesel::joinable!(api_key -> tenant (tenant_id));
 diesel::joinable!(application -> tenant (tenant_id));
 diesel::joinable!(application_integration -> application (application_id));
+diesel::joinable!(defrost_cycle -> defrost_schedule (schedule_id));
 diesel::joinable!(device -> application (application_id));
 diesel::joinable!(device -> device_profile (device_profile_id));
+diesel::joinable!(device_calibration -> device (dev_eui));
 diesel::joinable!(device_keys -> device (dev_eui));
 diesel::joinable!(device_profile -> tenant (tenant_id));
 diesel::joinable!(device_queue_item -> device (dev_eui));
+diesel::joinable!(escalation_policy -> tenant (tenant_id));
+diesel::joinable!(escalation_policy_target -> escalation_policy (policy_id));
+diesel::joinable!(escalation_step -> escalation_policy (policy_id));
 diesel::joinable!(gateway -> tenant (tenant_id));
+diesel::joinable!(maintenance_window -> site (site_id));
+diesel::joinable!(maintenance_window -> tenant (tenant_id));
+diesel::joinable!(maintenance_window -> zone (zone_id));
+diesel::joinable!(measurement -> device (dev_eui));
+diesel::joinable!(measurement_latest -> device (dev_eui));
 diesel::joinable!(multicast_group -> application (application_id));
 diesel::joinable!(multicast_group_device -> device (dev_eui));
 diesel::joinable!(multicast_group_device -> multicast_group (multicast_group_id));
@@ -339,26 +1054,78 @@ diesel::joinable!(multicast_group_gateway -> multicast_group (multicast_group_id
 diesel::joinable!(multicast_group_queue_item -> gateway (gateway_id));
 diesel::joinable!(multicast_group_queue_item -> multicast_group (multicast_group_id));
 diesel::joinable!(relay_gateway -> tenant (tenant_id));
+diesel::joinable!(site -> tenant (tenant_id));
 diesel::joinable!(tenant_user -> tenant (tenant_id));
 diesel::joinable!(tenant_user -> user (user_id));
+diesel::joinable!(uplink_task -> device (dev_eui));
+diesel::joinable!(virtual_device -> device (dev_eui));
+diesel::joinable!(virtual_device_input -> virtual_device (dev_eui));
+diesel::joinable!(webhook_delivery -> webhook_subscription (subscription_id));
+diesel::joinable!(webhook_subscription -> tenant (tenant_id));
+diesel::joinable!(zone -> site (site_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    alarm,
+    alarm_audit_log,
+    alarm_automation_rules,
+    alarm_date_time,
+    alarm_escalation,
+    alarm_no_data_trigger,
+    alarm_snooze,
+    alarm_state,
+    alarm_trigger,
+    am103,
     api_key,
     application,
     application_integration,
+    automation_rules,
+    dds45lb,
+    defrost_cycle,
+    defrost_learning,
+    defrost_schedule,
     device,
+    device_calibration,
+    device_battery,
+    device_data,
+    device_data_daily,
+    device_data_hourly,
+    device_data_latest,
     device_keys,
+    device_link,
+    device_link_sample,
     device_profile,
     device_profile_template,
     device_queue_item,
+    device_type_tb,
+    door_alarm_date_time,
+    door_time_alarm,
+    em400mud,
+    escalation_policy,
+    escalation_policy_target,
+    escalation_step,
     gateway,
+    ltc2lb,
+    maintenance_window,
+    measurement,
+    measurement_latest,
     multicast_group,
     multicast_group_device,
     multicast_group_gateway,
     multicast_group_queue_item,
+    notifications,
     relay_device,
     relay_gateway,
+    sensors,
+    site,
     tenant,
     tenant_user,
+    uc300,
+    uplink_task,
     user,
+    virtual_device,
+    virtual_device_input,
+    webhook_delivery,
+    webhook_subscription,
+    zone,
+    zone_content_type,
 );
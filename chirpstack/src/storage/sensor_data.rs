@@ -0,0 +1,770 @@
+use anyhow::Result;
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Double, Integer, Nullable, Text, Timestamp};
+use diesel_async::RunQueryDsl;
+use tracing::{info, warn};
+
+use super::error::Error;
+use super::schema::{device_data_daily, device_data_hourly, tenant};
+use super::{db_transaction, fields, get_async_db_conn, measurement};
+use crate::config;
+
+// The numeric device_data columns which are rolled up.
+pub const METRICS: &[&str] = &[
+    "air_temperature",
+    "air_humidity",
+    "sol_temperature",
+    "sol_water",
+    "sol_conduct_soil",
+    "batv",
+    "ph_soil",
+    "co2_ppm",
+    "tvoc_ppm",
+    "sensecap_light",
+    "barometric_pressure",
+    "current",
+    "factor",
+    "power",
+    "power_sum",
+    "voltage",
+];
+
+// The per-model tables holding the raw readings, these are not rolled up.
+#[cfg(feature = "postgres")]
+const MODEL_TABLES: &[&str] = &[
+    "am103", "dds45lb", "em400mud", "ltc2lb", "uc300", "ws522", "ws558",
+];
+#[cfg(feature = "sqlite")]
+const MODEL_TABLES: &[&str] = &["am103", "dds45lb", "em400mud", "ltc2lb", "uc300"];
+
+// The device_data columns, used to copy the legacy device_data_2025 readings.
+const COLUMNS: &str = "id, dev_eui, device_type_id, air_temperature, air_humidity, \
+    sol_temperature, sol_water, sol_conduct_soil, submission_date, water_leak_status, \
+    water_leak_times, last_water_leak_duration, door_open_status, door_open_times, \
+    last_door_open_duration, batv, ro1_status, ro2_status, ph_soil, co2_ppm, tvoc_ppm, \
+    sensecap_light, barometric_pressure, status, current, factor, power, power_sum, voltage";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Raw,
+    Hourly,
+    Daily,
+}
+
+impl Resolution {
+    // Returns the resolution used for reading the given interval, such that the number of
+    // returned samples stays reasonable.
+    pub fn for_interval(start: NaiveDateTime, end: NaiveDateTime) -> Resolution {
+        let span = end - start;
+        if span <= Duration::days(2) {
+            Resolution::Raw
+        } else if span <= Duration::days(90) {
+            Resolution::Hourly
+        } else {
+            Resolution::Daily
+        }
+    }
+}
+
+// Sensor-data sample. In case of raw readings, min, max and avg are equal to the reading and
+// the sample count is 1.
+#[derive(Queryable, QueryableByName, PartialEq, Debug, Clone)]
+pub struct Sample {
+    #[diesel(sql_type = Timestamp)]
+    pub bucket: NaiveDateTime,
+    #[diesel(sql_type = Double)]
+    pub min_value: f64,
+    #[diesel(sql_type = Double)]
+    pub max_value: f64,
+    #[diesel(sql_type = Double)]
+    pub avg_value: f64,
+    #[diesel(sql_type = Integer)]
+    pub sample_count: i32,
+}
+
+// Returns the samples of the given metric within the start (inclusive) and end (exclusive)
+// interval, ordered by time.
+pub async fn get_history(
+    dev_eui: &str,
+    metric: &str,
+    resolution: Resolution,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+) -> Result<Vec<Sample>, Error> {
+    if !METRICS.contains(&metric) {
+        return Err(Error::Validation(format!("Invalid metric: {}", metric)));
+    }
+
+    let mut c = get_async_db_conn().await?;
+    let samples: Vec<Sample> = match resolution {
+        Resolution::Raw => {
+            let value = if cfg!(feature = "sqlite") {
+                format!("cast({} as real)", metric)
+            } else {
+                format!("{}::float8", metric)
+            };
+            let query = format!(
+                r#"
+                select
+                    submission_date as bucket,
+                    {value} as min_value,
+                    {value} as max_value,
+                    {value} as avg_value,
+                    1 as sample_count
+                from
+                    device_data
+                where
+                    dev_eui = {p1}
+                    and {metric} is not null
+                    and submission_date >= {p2}
+                    and submission_date < {p3}
+                order by
+                    submission_date
+                "#,
+                value = value,
+                metric = metric,
+                p1 = placeholder(1),
+                p2 = placeholder(2),
+                p3 = placeholder(3),
+            );
+
+            diesel::sql_query(query)
+                .bind::<Text, _>(dev_eui)
+                .bind::<Timestamp, _>(start)
+                .bind::<Timestamp, _>(end)
+                .load(&mut c)
+                .await
+        }
+        Resolution::Hourly => {
+            device_data_hourly::dsl::device_data_hourly
+                .select((
+                    device_data_hourly::bucket,
+                    device_data_hourly::min_value,
+                    device_data_hourly::max_value,
+                    device_data_hourly::avg_value,
+                    device_data_hourly::sample_count,
+                ))
+                .filter(device_data_hourly::dev_eui.eq(dev_eui))
+                .filter(device_data_hourly::metric.eq(metric))
+                .filter(device_data_hourly::bucket.ge(start))
+                .filter(device_data_hourly::bucket.lt(end))
+                .order_by(device_data_hourly::bucket)
+                .load(&mut c)
+                .await
+        }
+        Resolution::Daily => {
+            device_data_daily::dsl::device_data_daily
+                .select((
+                    device_data_daily::bucket,
+                    device_data_daily::min_value,
+                    device_data_daily::max_value,
+                    device_data_daily::avg_value,
+                    device_data_daily::sample_count,
+                ))
+                .filter(device_data_daily::dev_eui.eq(dev_eui))
+                .filter(device_data_daily::metric.eq(metric))
+                .filter(device_data_daily::bucket.ge(start))
+                .filter(device_data_daily::bucket.lt(end))
+                .order_by(device_data_daily::bucket)
+                .load(&mut c)
+                .await
+        }
+    }
+    .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+
+    Ok(samples)
+}
+
+// Updates the hourly and daily rollups of the days overlapping with the given interval. The
+// rollups are re-computed from the start of the day of start, such that the daily rollups
+// include all the hours of the day.
+pub async fn update_rollups(start: NaiveDateTime, end: NaiveDateTime) -> Result<(), Error> {
+    let start = start.date().and_time(Default::default());
+    let end = if end.time() == Default::default() {
+        end
+    } else {
+        end.date().and_time(Default::default()) + Duration::days(1)
+    };
+
+    let hourly_bucket = if cfg!(feature = "sqlite") {
+        "strftime('%Y-%m-%d %H:00:00', submission_date)"
+    } else {
+        "date_trunc('hour', submission_date)"
+    };
+    let daily_bucket = if cfg!(feature = "sqlite") {
+        "strftime('%Y-%m-%d 00:00:00', bucket)"
+    } else {
+        "date_trunc('day', bucket)"
+    };
+
+    let hourly = METRICS
+        .iter()
+        .map(|m| {
+            let value = if cfg!(feature = "sqlite") {
+                format!("cast({} as real)", m)
+            } else {
+                format!("{}::float8", m)
+            };
+            format!(
+                r#"
+                select
+                    dev_eui,
+                    '{metric}' as metric,
+                    {bucket} as bucket,
+                    min({value}) as min_value,
+                    max({value}) as max_value,
+                    avg({value}) as avg_value,
+                    count({metric}) as sample_count
+                from
+                    device_data
+                where
+                    submission_date >= {p1}
+                    and submission_date < {p2}
+                    and {metric} is not null
+                group by
+                    dev_eui,
+                    {bucket}
+                "#,
+                metric = m,
+                value = value,
+                bucket = hourly_bucket,
+                p1 = placeholder(1),
+                p2 = placeholder(2),
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(" union all ");
+
+    let mut c = get_async_db_conn().await?;
+
+    diesel::sql_query(format!(
+        r#"
+        insert into device_data_hourly (
+            dev_eui, metric, bucket, min_value, max_value, avg_value, sample_count
+        )
+        select * from ({}) as r where true
+        on conflict (dev_eui, metric, bucket) do update set
+            min_value = excluded.min_value,
+            max_value = excluded.max_value,
+            avg_value = excluded.avg_value,
+            sample_count = excluded.sample_count
+        "#,
+        hourly
+    ))
+    .bind::<Timestamp, _>(start)
+    .bind::<Timestamp, _>(end)
+    .execute(&mut c)
+    .await
+    .map_err(|e| Error::from_diesel(e, "hourly rollups".into()))?;
+
+    diesel::sql_query(format!(
+        r#"
+        insert into device_data_daily (
+            dev_eui, metric, bucket, min_value, max_value, avg_value, sample_count
+        )
+        select
+            dev_eui,
+            metric,
+            {bucket} as bucket,
+            min(min_value),
+            max(max_value),
+            sum(avg_value * sample_count) / sum(sample_count),
+            sum(sample_count)
+        from
+            device_data_hourly
+        where
+            bucket >= {p1}
+            and bucket < {p2}
+        group by
+            dev_eui,
+            metric,
+            {bucket}
+        on conflict (dev_eui, metric, bucket) do update set
+            min_value = excluded.min_value,
+            max_value = excluded.max_value,
+            avg_value = excluded.avg_value,
+            sample_count = excluded.sample_count
+        "#,
+        bucket = daily_bucket,
+        p1 = placeholder(1),
+        p2 = placeholder(2),
+    ))
+    .bind::<Timestamp, _>(start)
+    .bind::<Timestamp, _>(end)
+    .execute(&mut c)
+    .await
+    .map_err(|e| Error::from_diesel(e, "daily rollups".into()))?;
+
+    Ok(())
+}
+
+// Creates the monthly device_data partitions of the current month and the given number of
+// following months. SQLite does not support partitioning, in which case this is a no-op.
+pub async fn create_partitions(premake: u32) -> Result<(), Error> {
+    if cfg!(feature = "sqlite") {
+        return Ok(());
+    }
+
+    let now = Utc::now().date_naive();
+    let month = NaiveDate::from_ymd_opt(now.year(), now.month(), 1).unwrap_or(now);
+    for i in 0..=premake {
+        create_partition(month + Months::new(i)).await?;
+    }
+
+    Ok(())
+}
+
+// Creates the device_data partition of the month of the given date, if it does not yet exist.
+// When the default partition already holds readings of this month (e.g. the maintenance loop
+// did not run in time), the partition can't be created while the default partition is
+// attached. In this case the default partition is detached, the readings are moved to the new
+// partition and the default partition is attached again.
+pub async fn create_partition(month: NaiveDate) -> Result<(), Error> {
+    if cfg!(feature = "sqlite") {
+        return Ok(());
+    }
+
+    #[derive(QueryableByName)]
+    struct ExistsRow {
+        #[diesel(sql_type = Bool)]
+        partition: bool,
+        #[diesel(sql_type = Bool)]
+        default_rows: bool,
+    }
+
+    let start = NaiveDate::from_ymd_opt(month.year(), month.month(), 1).unwrap_or(month);
+    let end = start + Months::new(1);
+    let name = partition_name(start);
+    let range = format!(
+        "submission_date >= '{}' and submission_date < '{}'",
+        start, end
+    );
+
+    let mut c = get_async_db_conn().await?;
+    db_transaction::<(), Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            let exists: ExistsRow = diesel::sql_query(format!(
+                r#"
+                select
+                    to_regclass('{}') is not null as partition,
+                    exists (select 1 from device_data_default where {}) as default_rows
+                "#,
+                name, range
+            ))
+            .get_result(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, name.clone()))?;
+
+            if exists.partition {
+                return Ok(());
+            }
+
+            let create = format!(
+                "create table {} partition of device_data for values from ('{}') to ('{}')",
+                name, start, end
+            );
+            let queries = if exists.default_rows {
+                vec![
+                    "alter table device_data detach partition device_data_default".into(),
+                    create,
+                    format!(
+                        "insert into {} ({}) select {} from device_data_default where {}",
+                        name, COLUMNS, COLUMNS, range
+                    ),
+                    format!("delete from device_data_default where {}", range),
+                    "alter table device_data attach partition device_data_default default".into(),
+                ]
+            } else {
+                vec![create]
+            };
+
+            for query in queries {
+                diesel::sql_query(query)
+                    .execute(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, name.clone()))?;
+            }
+
+            if exists.default_rows {
+                info!(partition = %name, "Sensor-data readings moved from the default partition");
+            }
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+// Drops the device_data partitions which only contain readings before the given date.
+async fn drop_partitions(before: NaiveDate) -> Result<(), Error> {
+    #[derive(QueryableByName)]
+    struct PartitionRow {
+        #[diesel(sql_type = Text)]
+        name: String,
+    }
+
+    let mut c = get_async_db_conn().await?;
+    let partitions: Vec<PartitionRow> = diesel::sql_query(
+        r#"
+        select
+            c.relname::text as name
+        from
+            pg_inherits i
+        inner join pg_class c
+            on c.oid = i.inhrelid
+        inner join pg_class p
+            on p.oid = i.inhparent
+        where
+            p.relname = 'device_data'
+        "#,
+    )
+    .load(&mut c)
+    .await
+    .map_err(|e| Error::from_diesel(e, "device_data partitions".into()))?;
+
+    for p in partitions {
+        let start = match parse_partition_name(&p.name) {
+            Some(v) => v,
+            None => continue,
+        };
+
+        if start + Months::new(1) <= before {
+            diesel::sql_query(format!("drop table {}", p.name))
+                .execute(&mut c)
+                .await
+                .map_err(|e| Error::from_diesel(e, p.name.clone()))?;
+            info!(partition = %p.name, "Sensor-data partition dropped");
+        }
+    }
+
+    Ok(())
+}
+
+// Removes the raw readings and hourly rollups which are older than the retention of the
+// tenant, or the configured retention when the tenant does not define one. In case all tenants
+// have a raw retention, the expired partitions are dropped.
+pub async fn apply_retention() -> Result<()> {
+    let conf = config::get();
+    let now = Utc::now().naive_utc();
+
+    let tenants: Vec<(fields::Uuid, i32, i32)> = tenant::dsl::tenant
+        .select((
+            tenant::id,
+            tenant::raw_data_retention_days,
+            tenant::hourly_data_retention_months,
+        ))
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+
+    let mut max_raw_days = Some(0);
+    for (tenant_id, raw_days, hourly_months) in &tenants {
+        let raw_days = get_retention(*raw_days, conf.sensor_data.raw_retention_days);
+        let hourly_months = get_retention(*hourly_months, conf.sensor_data.hourly_retention_months);
+
+        max_raw_days = match (max_raw_days, raw_days) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            _ => None,
+        };
+
+        if let Some(days) = raw_days {
+            let cutoff = now - Duration::days(days as i64);
+            delete_before(tenant_id, "device_data", "submission_date", cutoff).await?;
+            for table in MODEL_TABLES {
+                delete_before(tenant_id, table, "submission_date", cutoff).await?;
+            }
+            measurement::delete_before(tenant_id, cutoff).await?;
+        }
+
+        if let Some(months) = hourly_months {
+            if let Some(cutoff) = now.checked_sub_months(Months::new(months)) {
+                delete_before(tenant_id, "device_data_hourly", "bucket", cutoff).await?;
+            }
+        }
+    }
+
+    if let Some(days) = max_raw_days.filter(|v| *v > 0 && !tenants.is_empty()) {
+        if !cfg!(feature = "sqlite") {
+            drop_partitions((now - Duration::days(days as i64)).date()).await?;
+        }
+    }
+
+    Ok(())
+}
+
+// Deletes the rows of the tenant devices of which the given time column is before cutoff.
+async fn delete_before(
+    tenant_id: &fields::Uuid,
+    table: &str,
+    column: &str,
+    cutoff: NaiveDateTime,
+) -> Result<(), Error> {
+    let dev_eui = if cfg!(feature = "sqlite") {
+        "lower(hex(d.dev_eui))"
+    } else {
+        "encode(d.dev_eui, 'hex')"
+    };
+
+    let count = diesel::sql_query(format!(
+        r#"
+        delete from
+            {table}
+        where
+            {column} < {p2}
+            and dev_eui in (
+                select
+                    {dev_eui}
+                from
+                    device d
+                inner join application a
+                    on a.id = d.application_id
+                where
+                    a.tenant_id = {p1}
+            )
+        "#,
+        table = table,
+        column = column,
+        dev_eui = dev_eui,
+        p1 = placeholder(1),
+        p2 = placeholder(2),
+    ))
+    .bind::<fields::sql_types::Uuid, _>(tenant_id)
+    .bind::<Timestamp, _>(cutoff)
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, table.to_string()))?;
+
+    if count > 0 {
+        info!(tenant_id = %tenant_id, table = %table, count = count, "Expired sensor data removed");
+    }
+
+    Ok(())
+}
+
+// Returns the interval of the readings in the legacy device_data_2025 table, None when the
+// table is empty.
+pub async fn get_legacy_interval() -> Result<Option<(NaiveDateTime, NaiveDateTime)>, Error> {
+    #[derive(QueryableByName)]
+    struct IntervalRow {
+        #[diesel(sql_type = Nullable<Timestamp>)]
+        min_date: Option<NaiveDateTime>,
+        #[diesel(sql_type = Nullable<Timestamp>)]
+        max_date: Option<NaiveDateTime>,
+    }
+
+    let row: IntervalRow = diesel::sql_query(
+        r#"
+        select
+            min(submission_date) as min_date,
+            max(submission_date) as max_date
+        from
+            device_data_2025
+        "#,
+    )
+    .get_result(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, "device_data_2025".into()))?;
+
+    Ok(row.min_date.zip(row.max_date))
+}
+
+// Copies the legacy device_data_2025 readings within the given interval into device_data.
+// Readings which have already been copied are skipped. It returns the number of copied rows.
+pub async fn copy_legacy(start: NaiveDateTime, end: NaiveDateTime) -> Result<usize, Error> {
+    diesel::sql_query(format!(
+        r#"
+        insert into device_data ({columns})
+        select
+            {columns}
+        from
+            device_data_2025
+        where
+            submission_date >= $1
+            and submission_date < $2
+        on conflict do nothing
+        "#,
+        columns = COLUMNS,
+    ))
+    .bind::<Timestamp, _>(start)
+    .bind::<Timestamp, _>(end)
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, "device_data_2025".into()))
+}
+
+// Aligns the device_data id sequence with the copied legacy readings.
+pub async fn reset_id_sequence() -> Result<(), Error> {
+    diesel::sql_query(
+        r#"
+        select
+            setval(
+                pg_get_serial_sequence('device_data', 'id'),
+                greatest(
+                    (select coalesce(max(id), 0) from device_data),
+                    (select last_value from device_data_id_seq)
+                )
+            )
+        "#,
+    )
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, "device_data_id_seq".into()))?;
+
+    Ok(())
+}
+
+// Drops the legacy device_data_2025 table, this must only be called after all readings have
+// been copied.
+pub async fn drop_legacy() -> Result<(), Error> {
+    diesel::sql_query("drop table device_data_2025")
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "device_data_2025".into()))?;
+    warn!("Legacy device_data_2025 table dropped");
+    Ok(())
+}
+
+// Returns the retention of the tenant, falling back to the configured retention. None means
+// that the data is kept forever.
+fn get_retention(tenant: i32, default: u32) -> Option<u32> {
+    match tenant {
+        v if v > 0 => Some(v as u32),
+        _ if default > 0 => Some(default),
+        _ => None,
+    }
+}
+
+fn partition_name(month: NaiveDate) -> String {
+    format!("device_data_y{}m{:02}", month.year(), month.month())
+}
+
+// Returns the first day of the month of the given partition, None when the name is not a
+// monthly partition (e.g. the default partition).
+fn parse_partition_name(name: &str) -> Option<NaiveDate> {
+    let s = name.strip_prefix("device_data_y")?;
+    let (year, month) = s.split_once('m')?;
+    NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)
+}
+
+pub(super) fn placeholder(i: usize) -> String {
+    if cfg!(feature = "sqlite") {
+        format!("?{}", i)
+    } else {
+        format!("${}", i)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::test;
+
+    #[test]
+    fn test_resolution_for_interval() {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+
+        let tests = vec![
+            (Duration::hours(1), Resolution::Raw),
+            (Duration::days(2), Resolution::Raw),
+            (Duration::days(3), Resolution::Hourly),
+            (Duration::days(90), Resolution::Hourly),
+            (Duration::days(365), Resolution::Daily),
+        ];
+
+        for (span, expected) in tests {
+            assert_eq!(expected, Resolution::for_interval(start, start + span));
+        }
+    }
+
+    #[test]
+    fn test_get_retention() {
+        assert_eq!(None, get_retention(0, 0));
+        assert_eq!(Some(30), get_retention(0, 30));
+        assert_eq!(Some(90), get_retention(90, 30));
+        assert_eq!(Some(30), get_retention(-1, 30));
+    }
+
+    #[test]
+    fn test_partition_name() {
+        let month = NaiveDate::from_ymd_opt(2025, 7, 1).unwrap();
+        assert_eq!("device_data_y2025m07", partition_name(month));
+        assert_eq!(Some(month), parse_partition_name("device_data_y2025m07"));
+        assert_eq!(None, parse_partition_name("device_data_default"));
+        assert_eq!(None, parse_partition_name("device_data_y2025m13"));
+    }
+
+    #[cfg(feature = "postgres")]
+    #[tokio::test]
+    async fn test_create_partition() {
+        let _guard = test::prepare().await;
+
+        #[derive(QueryableByName)]
+        struct PartitionRow {
+            #[diesel(sql_type = Text)]
+            name: String,
+        }
+
+        let get_partitions = || async {
+            let rows: Vec<PartitionRow> = diesel::sql_query(
+                "select tableoid::regclass::text as name from device_data order by submission_date",
+            )
+            .load(&mut get_async_db_conn().await.unwrap())
+            .await
+            .unwrap();
+            rows.into_iter().map(|r| r.name).collect::<Vec<String>>()
+        };
+
+        // Readings of months without partition are stored in the default partition.
+        diesel::sql_query(
+            r#"
+            insert into device_data
+                (dev_eui, device_type_id, submission_date)
+            values
+                ('0102030405060708', 1, '2099-01-15 10:00:00'),
+                ('0102030405060708', 1, '2099-02-15 10:00:00')
+            "#,
+        )
+        .execute(&mut get_async_db_conn().await.unwrap())
+        .await
+        .unwrap();
+        assert_eq!(
+            vec!["device_data_default", "device_data_default"],
+            get_partitions().await
+        );
+
+        // The readings of the month are moved to the created partition.
+        let month = NaiveDate::from_ymd_opt(2099, 1, 1).unwrap();
+        create_partition(month).await.unwrap();
+        assert_eq!(
+            vec!["device_data_y2099m01", "device_data_default"],
+            get_partitions().await
+        );
+
+        // Creating an existing partition is a no-op.
+        create_partition(month).await.unwrap();
+        assert_eq!(
+            vec!["device_data_y2099m01", "device_data_default"],
+            get_partitions().await
+        );
+
+        // The default partition is attached again.
+        diesel::sql_query(
+            "insert into device_data (dev_eui, device_type_id, submission_date) \
+                values ('0102030405060708', 1, '2099-03-15 10:00:00')",
+        )
+        .execute(&mut get_async_db_conn().await.unwrap())
+        .await
+        .unwrap();
+        assert_eq!(
+            vec![
+                "device_data_y2099m01",
+                "device_data_default",
+                "device_data_default"
+            ],
+            get_partitions().await
+        );
+    }
+}
@@ -0,0 +1,641 @@
+use chrono::{DateTime, Utc};
+use diesel::dsl;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Float, Integer, Text};
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use uuid::Uuid;
+
+use super::error::Error;
+use super::fields::sql_types::Uuid as SqlUuid;
+use super::schema::{site, zone};
+use super::{fields, get_async_db_conn, notification};
+
+// Site (location) of a tenant, e.g. a restaurant or warehouse. A site groups the zones (e.g.
+// cold rooms) at the same location.
+#[derive(Queryable, PartialEq, Debug, Clone, Serialize, Deserialize)]
+#[diesel(table_name = site)]
+pub struct Site {
+    pub site_id: i32,
+    pub tenant_id: fields::Uuid,
+    pub site_name: String,
+    pub address: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub timezone: String,
+    pub site_order: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        Site {
+            site_id: 0,
+            tenant_id: Uuid::nil().into(),
+            site_name: "".into(),
+            address: "".into(),
+            latitude: None,
+            longitude: None,
+            timezone: "UTC".into(),
+            site_order: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = site)]
+struct NewSite {
+    tenant_id: fields::Uuid,
+    site_name: String,
+    address: String,
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    timezone: String,
+    site_order: i64,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl Site {
+    fn validate(&self) -> Result<(), Error> {
+        if self.site_name.trim().is_empty() {
+            return Err(Error::Validation("Site name cannot be empty".into()));
+        }
+        if let Some(lat) = self.latitude {
+            if !(-90.0..=90.0).contains(&lat) {
+                return Err(Error::Validation(
+                    "latitude must be between -90 and 90".into(),
+                ));
+            }
+        }
+        if let Some(lon) = self.longitude {
+            if !(-180.0..=180.0).contains(&lon) {
+                return Err(Error::Validation(
+                    "longitude must be between -180 and 180".into(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Alarm and device-health rollup of a site.
+#[derive(QueryableByName, Debug, Clone, PartialEq)]
+pub struct SiteRollup {
+    #[diesel(sql_type = Integer)]
+    pub site_id: i32,
+
+    #[diesel(sql_type = Text)]
+    pub site_name: String,
+
+    #[diesel(sql_type = BigInt)]
+    pub zone_count: i64,
+
+    #[diesel(sql_type = BigInt)]
+    pub device_count: i64,
+
+    // Devices which did not send any data within their no-data alarm duration (or twice their
+    // data interval).
+    #[diesel(sql_type = BigInt)]
+    pub offline_device_count: i64,
+
+    #[diesel(sql_type = BigInt)]
+    pub low_battery_device_count: i64,
+
+    // Configured and active alarms of the site devices.
+    #[diesel(sql_type = BigInt)]
+    pub active_alarm_count: i64,
+
+    // Triggered alarms (alarm notifications) within the requested period.
+    #[diesel(sql_type = BigInt)]
+    pub triggered_alarm_count: i64,
+}
+
+// Postgres rejects unknown timezone names in AT TIME ZONE, this avoids storing sites of which
+// the local time can not be calculated.
+#[cfg(feature = "postgres")]
+async fn validate_timezone(timezone: &str) -> Result<(), Error> {
+    #[derive(QueryableByName)]
+    struct TimezoneRow {
+        #[diesel(sql_type = diesel::sql_types::Bool)]
+        valid: bool,
+    }
+
+    let row: TimezoneRow = diesel::sql_query(
+        "SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1) AS valid",
+    )
+    .bind::<Text, _>(timezone)
+    .get_result(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, timezone.to_string()))?;
+
+    if !row.valid {
+        return Err(Error::Validation(format!("Invalid timezone: {}", timezone)));
+    }
+    Ok(())
+}
+
+// SQLite has no timezone database, the timezone names are validated against the one of
+// chrono-tz.
+#[cfg(feature = "sqlite")]
+async fn validate_timezone(timezone: &str) -> Result<(), Error> {
+    if timezone.parse::<chrono_tz::Tz>().is_err() {
+        return Err(Error::Validation(format!("Invalid timezone: {}", timezone)));
+    }
+    Ok(())
+}
+
+pub async fn create(s: Site) -> Result<Site, Error> {
+    s.validate()?;
+    validate_timezone(&s.timezone).await?;
+
+    let now = Utc::now();
+    let new = NewSite {
+        tenant_id: s.tenant_id,
+        site_name: s.site_name,
+        address: s.address,
+        latitude: s.latitude,
+        longitude: s.longitude,
+        timezone: s.timezone,
+        site_order: s.site_order,
+        created_at: now,
+        updated_at: now,
+    };
+
+    let s: Site = diesel::insert_into(site::table)
+        .values(&new)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "insert site".to_string()))?;
+    info!(site_id = s.site_id, tenant_id = %s.tenant_id, "Site created");
+    Ok(s)
+}
+
+pub async fn get(site_id: i32) -> Result<Site, Error> {
+    let s = site::dsl::site
+        .find(site_id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, site_id.to_string()))?;
+    Ok(s)
+}
+
+pub async fn update(s: Site) -> Result<Site, Error> {
+    s.validate()?;
+    validate_timezone(&s.timezone).await?;
+
+    let s: Site = diesel::update(site::dsl::site.find(s.site_id))
+        .set((
+            site::updated_at.eq(Utc::now()),
+            site::site_name.eq(&s.site_name),
+            site::address.eq(&s.address),
+            site::latitude.eq(&s.latitude),
+            site::longitude.eq(&s.longitude),
+            site::timezone.eq(&s.timezone),
+            site::site_order.eq(&s.site_order),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, s.site_id.to_string()))?;
+    info!(site_id = s.site_id, "Site updated");
+    Ok(s)
+}
+
+// Deletes the site. The zones of the site are kept, but no longer belong to a site.
+pub async fn delete(site_id: i32) -> Result<(), Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let ra = diesel::delete(site::dsl::site.find(site_id))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, site_id.to_string()))?;
+    if ra == 0 {
+        return Err(Error::NotFound(site_id.to_string()));
+    }
+
+    // SQLite has no arrays, the user site list is a JSON array.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        UPDATE "user"
+        SET site_id_list = (
+            SELECT json_group_array(us.value) FROM json_each(site_id_list) AS us WHERE us.value IS NOT ?1
+        )
+        WHERE EXISTS (SELECT 1 FROM json_each(site_id_list) AS us WHERE us.value = ?1)
+        "#
+    } else {
+        r#"UPDATE "user" SET site_id_list = array_remove(site_id_list, $1::bigint) WHERE $1::bigint = ANY(site_id_list)"#
+    };
+
+    diesel::sql_query(query)
+        .bind::<Integer, _>(site_id)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, site_id.to_string()))?;
+
+    info!(site_id = site_id, "Site deleted");
+    Ok(())
+}
+
+pub async fn list(tenant_id: &Uuid) -> Result<Vec<Site>, Error> {
+    let items = site::dsl::site
+        .filter(site::dsl::tenant_id.eq(fields::Uuid::from(tenant_id)))
+        .order_by((site::dsl::site_order, site::dsl::site_name))
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+    Ok(items)
+}
+
+pub async fn get_zone_count(site_id: i32) -> Result<i64, Error> {
+    let count = zone::dsl::zone
+        .select(dsl::count_star())
+        .filter(zone::dsl::site_id.eq(site_id))
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, site_id.to_string()))?;
+    Ok(count)
+}
+
+// Moves the zone to the given site, or removes it from its site when site_id is None. The site
+// must belong to the tenant of the zone.
+pub async fn set_zone_site(zone_id: i32, site_id: Option<i32>) -> Result<(), Error> {
+    let mut conn = get_async_db_conn().await?;
+
+    let ra = match site_id {
+        Some(site_id) => diesel::sql_query(if cfg!(feature = "sqlite") {
+            r#"
+            UPDATE zone AS z
+            SET site_id = s.site_id
+            FROM site AS s
+            WHERE z.zone_id = ?1 AND s.site_id = ?2 AND s.tenant_id = z.tanent_id
+            "#
+        } else {
+            r#"
+            UPDATE zone AS z
+            SET site_id = s.site_id
+            FROM site AS s
+            WHERE z.zone_id = $1 AND s.site_id = $2 AND s.tenant_id = z.tanent_id
+            "#
+        })
+        .bind::<Integer, _>(zone_id)
+        .bind::<Integer, _>(site_id)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, zone_id.to_string()))?,
+        None => diesel::update(zone::dsl::zone.find(zone_id))
+            .set(zone::site_id.eq(None::<i32>))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| Error::from_diesel(e, zone_id.to_string()))?,
+    };
+    if ra == 0 {
+        return Err(Error::NotFound(zone_id.to_string()));
+    }
+
+    info!(zone_id = zone_id, site_id = ?site_id, "Zone site set");
+    Ok(())
+}
+
+// Assigns the user to the site. Site users have access to all the zones of the site, the user
+// must be a user of the tenant of the site.
+pub async fn add_user(site_id: i32, user_id: &Uuid) -> Result<(), Error> {
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        UPDATE "user" AS u
+        SET site_id_list = json_insert(COALESCE(u.site_id_list, '[]'), '$[#]', ?2)
+        WHERE u.id = ?1
+            AND NOT EXISTS (SELECT 1 FROM json_each(u.site_id_list) AS us WHERE us.value = ?2)
+            AND EXISTS (
+                SELECT 1
+                FROM tenant_user AS tu
+                INNER JOIN site AS s ON s.tenant_id = tu.tenant_id
+                WHERE tu.user_id = u.id AND s.site_id = ?2
+            )
+        "#
+    } else {
+        r#"
+        UPDATE "user" AS u
+        SET site_id_list = array_append(COALESCE(u.site_id_list, ARRAY[]::bigint[]), $2::bigint)
+        WHERE u.id = $1
+            AND NOT ($2::bigint = ANY(COALESCE(u.site_id_list, ARRAY[]::bigint[])))
+            AND EXISTS (
+                SELECT 1
+                FROM tenant_user AS tu
+                INNER JOIN site AS s ON s.tenant_id = tu.tenant_id
+                WHERE tu.user_id = u.id AND s.site_id = $2
+            )
+        "#
+    };
+
+    let ra = diesel::sql_query(query)
+        .bind::<SqlUuid, _>(fields::Uuid::from(user_id))
+        .bind::<Integer, _>(site_id)
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, user_id.to_string()))?;
+
+    if ra == 0 && !get_user_ids(site_id).await?.contains(user_id) {
+        return Err(Error::NotFound(user_id.to_string()));
+    }
+
+    info!(site_id = site_id, user_id = %user_id, "Site user added");
+    Ok(())
+}
+
+pub async fn remove_user(site_id: i32, user_id: &Uuid) -> Result<(), Error> {
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        UPDATE "user"
+        SET site_id_list = (
+            SELECT json_group_array(us.value) FROM json_each(site_id_list) AS us WHERE us.value IS NOT ?2
+        )
+        WHERE id = ?1 AND EXISTS (SELECT 1 FROM json_each(site_id_list) AS us WHERE us.value = ?2)
+        "#
+    } else {
+        r#"UPDATE "user" SET site_id_list = array_remove(site_id_list, $2::bigint) WHERE id = $1 AND $2::bigint = ANY(site_id_list)"#
+    };
+
+    let ra = diesel::sql_query(query)
+        .bind::<SqlUuid, _>(fields::Uuid::from(user_id))
+        .bind::<Integer, _>(site_id)
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, user_id.to_string()))?;
+
+    if ra == 0 {
+        return Err(Error::NotFound(user_id.to_string()));
+    }
+
+    info!(site_id = site_id, user_id = %user_id, "Site user removed");
+    Ok(())
+}
+
+pub async fn get_user_ids(site_id: i32) -> Result<Vec<Uuid>, Error> {
+    #[derive(QueryableByName)]
+    struct UserIdRow {
+        #[diesel(sql_type = SqlUuid)]
+        id: fields::Uuid,
+    }
+
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT u.id
+        FROM "user" AS u
+        WHERE EXISTS (SELECT 1 FROM json_each(u.site_id_list) AS us WHERE us.value = ?1)
+        ORDER BY u.email
+        "#
+    } else {
+        r#"SELECT u.id FROM "user" AS u WHERE $1::bigint = ANY(u.site_id_list) ORDER BY u.email"#
+    };
+
+    let rows: Vec<UserIdRow> = diesel::sql_query(query)
+        .bind::<Integer, _>(site_id)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, site_id.to_string()))?;
+
+    Ok(rows.into_iter().map(|r| r.id.into()).collect())
+}
+
+// Returns the alarm and device-health rollups of the sites of the tenant. The battery
+// thresholds are the same as used by the battery report, the triggered alarms are counted over
+// the last hours.
+pub async fn get_rollups(
+    tenant_id: &Uuid,
+    low_battery_level: f32,
+    low_battery_days: i32,
+    hours: i32,
+) -> Result<Vec<SiteRollup>, Error> {
+    // SQLite has no arrays, the zone devices are a JSON array. The device EUIs of site_device are
+    // hex encoded, as stored by the alarms, notifications and battery states.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        WITH site_device AS (
+            SELECT DISTINCT
+                z.site_id,
+                lower(hex(d.dev_eui)) AS dev_eui,
+                COALESCE(datetime(d.last_seen_at) < datetime('now', printf('-%d minutes', COALESCE(
+                    (SELECT MIN(NULLIF(a.no_data_time, 0)) FROM alarm AS a
+                        WHERE (a.dev_eui = lower(hex(d.dev_eui)) OR (a.dev_eui = '' AND EXISTS (
+                            SELECT 1 FROM zone AS az, json_each(az.devices) AS azd
+                            WHERE az.zone_id = a.zone_category AND azd.value = '\x' || lower(hex(d.dev_eui))
+                        ))) AND a.no_data = true AND a.is_active = true),
+                    NULLIF(d.data_time, 0) * 2,
+                    60
+                ))), true) AS is_offline
+            FROM zone AS z, json_each(z.devices) AS zd
+            INNER JOIN device AS d ON '\x' || lower(hex(d.dev_eui)) = zd.value
+            WHERE z.site_id IS NOT NULL
+        )
+        SELECT
+            s.site_id,
+            s.site_name,
+            (SELECT count(*) FROM zone AS z WHERE z.site_id = s.site_id) AS zone_count,
+            count(sd.dev_eui) AS device_count,
+            count(sd.dev_eui) FILTER (WHERE sd.is_offline) AS offline_device_count,
+            count(b.dev_eui) FILTER (WHERE b.battery_level <= ?2 OR b.days_remaining <= ?3) AS low_battery_device_count,
+            (
+                SELECT count(*)
+                FROM alarm AS a
+                INNER JOIN site_device AS ad ON a.dev_eui = ad.dev_eui
+                WHERE ad.site_id = s.site_id AND a.is_active = true
+            ) + (
+                SELECT count(*)
+                FROM alarm AS a
+                INNER JOIN zone AS az ON az.zone_id = a.zone_category
+                WHERE a.dev_eui = '' AND az.site_id = s.site_id AND a.is_active = true
+            ) AS active_alarm_count,
+            (
+                SELECT count(*)
+                FROM notifications AS n
+                INNER JOIN site_device AS nd ON n.dev_eui = nd.dev_eui
+                WHERE nd.site_id = s.site_id
+                    AND n.category_id = ?4
+                    AND datetime(n.send_time) >= datetime('now', 'localtime', printf('-%d hours', ?5))
+            ) AS triggered_alarm_count
+        FROM site AS s
+        LEFT JOIN site_device AS sd ON sd.site_id = s.site_id
+        LEFT JOIN device_battery AS b ON b.dev_eui = sd.dev_eui
+        WHERE s.tenant_id = ?1
+        GROUP BY s.site_id, s.site_name, s.site_order
+        ORDER BY s.site_order, s.site_name
+        "#
+    } else {
+        r#"
+        WITH site_device AS (
+            SELECT DISTINCT
+                z.site_id,
+                d.dev_eui,
+                COALESCE(d.last_seen_at < now() - make_interval(mins => COALESCE(
+                    (SELECT MIN(NULLIF(a.no_data_time, 0)) FROM alarm AS a
+                        WHERE ('\x' || a.dev_eui = d.dev_eui::text OR (a.dev_eui = '' AND EXISTS (
+                            SELECT 1 FROM zone AS az WHERE az.zone_id = a.zone_category AND d.dev_eui::text = ANY(az.devices)
+                        ))) AND a.no_data = true AND a.is_active = true),
+                    NULLIF(d.data_time, 0) * 2,
+                    60
+                )), true) AS is_offline
+            FROM zone AS z
+            INNER JOIN device AS d ON d.dev_eui::text = ANY(z.devices)
+            WHERE z.site_id IS NOT NULL
+        )
+        SELECT
+            s.site_id,
+            s.site_name,
+            (SELECT count(*) FROM zone AS z WHERE z.site_id = s.site_id) AS zone_count,
+            count(sd.dev_eui) AS device_count,
+            count(sd.dev_eui) FILTER (WHERE sd.is_offline) AS offline_device_count,
+            count(b.dev_eui) FILTER (WHERE b.battery_level <= $2 OR b.days_remaining <= $3) AS low_battery_device_count,
+            (
+                SELECT count(*)
+                FROM alarm AS a
+                INNER JOIN site_device AS ad ON '\x' || a.dev_eui = ad.dev_eui::text
+                WHERE ad.site_id = s.site_id AND a.is_active = true
+            ) + (
+                SELECT count(*)
+                FROM alarm AS a
+                INNER JOIN zone AS az ON az.zone_id = a.zone_category
+                WHERE a.dev_eui = '' AND az.site_id = s.site_id AND a.is_active = true
+            ) AS active_alarm_count,
+            (
+                SELECT count(*)
+                FROM notifications AS n
+                INNER JOIN site_device AS nd ON '\x' || n.dev_eui = nd.dev_eui::text
+                WHERE nd.site_id = s.site_id
+                    AND n.category_id = $4
+                    AND n.send_time >= localtimestamp - make_interval(hours => $5)
+            ) AS triggered_alarm_count
+        FROM site AS s
+        LEFT JOIN site_device AS sd ON sd.site_id = s.site_id
+        LEFT JOIN device_battery AS b ON '\x' || b.dev_eui = sd.dev_eui::text
+        WHERE s.tenant_id = $1
+        GROUP BY s.site_id, s.site_name, s.site_order
+        ORDER BY s.site_order, s.site_name
+        "#
+    };
+
+    let items = diesel::sql_query(query)
+        .bind::<SqlUuid, _>(fields::Uuid::from(tenant_id))
+        .bind::<Float, _>(low_battery_level)
+        .bind::<Integer, _>(low_battery_days)
+        .bind::<Integer, _>(notification::CATEGORY_ALARM)
+        .bind::<Integer, _>(hours)
+        .load::<SiteRollup>(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+
+    Ok(items)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{tenant, user, zone as zone_storage};
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_site() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::test::create_tenant().await;
+        let tenant_id: Uuid = t.id.into();
+
+        // create
+        let mut s = create(Site {
+            tenant_id: t.id,
+            site_name: "Kadıköy".into(),
+            address: "Moda Cd. 1, İstanbul".into(),
+            latitude: Some(40.98),
+            longitude: Some(29.03),
+            timezone: "Europe/Istanbul".into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // invalid timezone
+        assert!(create(Site {
+            tenant_id: t.id,
+            site_name: "Invalid".into(),
+            timezone: "Europe/Kadikoy".into(),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+
+        // get
+        let s_get = get(s.site_id).await.unwrap();
+        assert_eq!(s, s_get);
+
+        // update
+        s.site_name = "Kadıköy Moda".into();
+        s.site_order = 2;
+        let s_up = update(s.clone()).await.unwrap();
+        assert_eq!(s.site_name, s_up.site_name);
+        assert_eq!(2, s_up.site_order);
+
+        // list
+        let items = list(&tenant_id).await.unwrap();
+        assert_eq!(1, items.len());
+
+        // zone
+        let z = zone_storage::create(zone_storage::Zone {
+            zone_name: Some("Soğuk oda 1".into()),
+            tanent_id: Some(tenant_id.into()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        set_zone_site(z.zone_id, Some(s.site_id)).await.unwrap();
+        assert_eq!(
+            Some(s.site_id),
+            zone_storage::get(&z.zone_id).await.unwrap().site_id
+        );
+        assert_eq!(1, get_zone_count(s.site_id).await.unwrap());
+
+        // user
+        let u = user::test::create_user().await;
+        let user_id: Uuid = u.id.into();
+        assert!(add_user(s.site_id, &user_id).await.is_err());
+        tenant::add_user(tenant::TenantUser {
+            tenant_id: t.id,
+            user_id: u.id,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        add_user(s.site_id, &user_id).await.unwrap();
+        add_user(s.site_id, &user_id).await.unwrap();
+        assert_eq!(vec![user_id], get_user_ids(s.site_id).await.unwrap());
+
+        // rollups
+        let rollups = get_rollups(&tenant_id, 20.0, 30, 24).await.unwrap();
+        assert_eq!(
+            vec![SiteRollup {
+                site_id: s.site_id,
+                site_name: "Kadıköy Moda".into(),
+                zone_count: 1,
+                device_count: 0,
+                offline_device_count: 0,
+                low_battery_device_count: 0,
+                active_alarm_count: 0,
+                triggered_alarm_count: 0,
+            }],
+            rollups
+        );
+
+        remove_user(s.site_id, &user_id).await.unwrap();
+        assert!(remove_user(s.site_id, &user_id).await.is_err());
+
+        // delete
+        set_zone_site(z.zone_id, None).await.unwrap();
+        assert_eq!(None, zone_storage::get(&z.zone_id).await.unwrap().site_id);
+        delete(s.site_id).await.unwrap();
+        assert!(delete(s.site_id).await.is_err());
+    }
+}
@@ -16,8 +16,9 @@ use scoped_futures::ScopedBoxFuture;
 
 use crate::config;
 
-pub type AsyncSqlitePool = DeadpoolPool<SyncConnectionWrapper<SqliteConnection>>;
-pub type AsyncSqlitePoolConnection = DeadpoolObject<SyncConnectionWrapper<SqliteConnection>>;
+pub type AsyncSqliteConnection = SyncConnectionWrapper<SqliteConnection>;
+pub type AsyncSqlitePool = DeadpoolPool<AsyncSqliteConnection>;
+pub type AsyncSqlitePoolConnection = DeadpoolObject<AsyncSqliteConnection>;
 
 lazy_static! {
     static ref ASYNC_SQLITE_POOL: RwLock<Option<AsyncSqlitePool>> = RwLock::new(None);
@@ -1,12 +1,13 @@
 use std::collections::HashMap;
 
 use super::error::Error;
+use super::fields::sql_types::{JsonT, Uuid as SqlUuid};
 use super::schema::{tenant, tenant_user, user, zone};
 use super::{fields, get_async_db_conn};
 use anyhow::Result;
 use chrono::{DateTime, Utc};
 use diesel::sql_query;
-use diesel::sql_types::{Bool, Json, Text, Uuid as SqlUuid};
+use diesel::sql_types::{Bool, Text};
 use diesel::{dsl, prelude::*};
 use diesel_async::RunQueryDsl;
 use serde::Deserialize;
@@ -32,6 +33,11 @@ pub struct Tenant {
     pub license: Option<bool>,
     pub pro_license: Option<bool>,
     pub kitchen_management_license: Option<bool>,
+    pub max_message_count: i32,
+    pub max_sms_count: i32,
+    pub over_quota_action: fields::OverQuotaAction,
+    pub raw_data_retention_days: i32,
+    pub hourly_data_retention_months: i32,
 }
 
 impl Tenant {
@@ -39,6 +45,12 @@ impl Tenant {
         if self.name.is_empty() {
             return Err(Error::Validation("name is not set".into()));
         }
+        if self.max_message_count < 0 || self.max_sms_count < 0 {
+            return Err(Error::Validation("quota must not be negative".into()));
+        }
+        if self.raw_data_retention_days < 0 || self.hourly_data_retention_months < 0 {
+            return Err(Error::Validation("retention must not be negative".into()));
+        }
         Ok(())
     }
 }
@@ -63,6 +75,11 @@ impl Default for Tenant {
             license: None,
             pro_license: None,
             kitchen_management_license: None,
+            max_message_count: 0,
+            max_sms_count: 0,
+            over_quota_action: fields::OverQuotaAction::WARN,
+            raw_data_retention_days: 0,
+            hourly_data_retention_months: 0,
         }
     }
 }
@@ -127,7 +144,7 @@ pub struct TenantUserListItem {
 #[derive(Debug, QueryableByName)]
 pub struct UserRow {
     #[sql_type = "SqlUuid"]
-    pub user_id: Uuid,
+    pub user_id: fields::Uuid,
 
     #[sql_type = "Text"]
     pub email: String,
@@ -150,8 +167,8 @@ pub struct UserRow {
     #[sql_type = "Bool"]
     pub is_admin: bool,
 
-    #[sql_type = "Json"]
-    pub zones: serde_json::Value,
+    #[sql_type = "JsonT"]
+    pub zones: fields::JsonValue,
 }
 
 #[derive(Default, Clone)]
@@ -195,6 +212,11 @@ pub async fn update(t: Tenant) -> Result<Tenant, Error> {
             tenant::private_gateways_up.eq(&t.private_gateways_up),
             tenant::private_gateways_down.eq(&t.private_gateways_down),
             tenant::tags.eq(&t.tags),
+            tenant::max_message_count.eq(&t.max_message_count),
+            tenant::max_sms_count.eq(&t.max_sms_count),
+            tenant::over_quota_action.eq(&t.over_quota_action),
+            tenant::raw_data_retention_days.eq(&t.raw_data_retention_days),
+            tenant::hourly_data_retention_months.eq(&t.hourly_data_retention_months),
         ))
         .get_result(&mut get_async_db_conn().await?)
         .await
@@ -328,7 +350,42 @@ pub async fn get_users(
     limit: i64,
     offset: i64,
 ) -> Result<Vec<TenantUserListItem>, Box<dyn std::error::Error>> {
-    let query = r#"
+    // SQLite has no arrays, the user zone list is a JSON array.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT
+            u.id AS user_id,
+            u.email AS email,
+            u.username AS username,
+            u.name AS name,
+            u.phone_number AS phone_number,
+            ou.is_gateway_admin AS is_gateway_admin,
+            ou.is_device_admin AS is_device_admin,
+            ou.is_admin AS is_admin,
+            json_object(
+                'zones',
+                json((
+                    SELECT json_group_array(
+                        json_object(
+                            'zone_id', z.zone_id,
+                            'zone_name', z.zone_name
+                        )
+                    )
+                    FROM zone z
+                    WHERE z.zone_id IN (SELECT value FROM json_each(u.zone_id_list))
+                ))
+            ) AS zones
+        FROM tenant_user ou
+        INNER JOIN user u ON u.id = ou.user_id
+        WHERE
+            ou.tenant_id = ?1
+            AND ou.is_visible = true
+        ORDER BY u.id
+        LIMIT ?2
+        OFFSET ?3
+    "#
+    } else {
+        r#"
         SELECT
             u.id AS user_id,
             u.email AS email,
@@ -345,7 +402,7 @@ pub async fn get_users(
                         WHEN COUNT(zl.zone_id) = 0 THEN '[]'::json
                         ELSE to_json(ARRAY_AGG(zl.list))
                     END
-                ) AS json
+                ) AS jsonb
             ) AS zones
         FROM tenant_user ou
         INNER JOIN "user" u ON u.id = ou.user_id
@@ -372,12 +429,15 @@ pub async fn get_users(
             ou.is_device_admin,
             ou.is_admin
         ORDER BY u.id
-    "#;
+        LIMIT $2
+        OFFSET $3
+    "#
+    };
 
     let mut conn = get_async_db_conn().await?;
 
     let rows: Vec<UserRow> = sql_query(query)
-        .bind::<SqlUuid, _>(tenant_id)
+        .bind::<SqlUuid, _>(fields::Uuid::from(tenant_id))
         .bind::<diesel::sql_types::BigInt, _>(limit)
         .bind::<diesel::sql_types::BigInt, _>(offset)
         .load(&mut conn)
@@ -386,12 +446,12 @@ pub async fn get_users(
     let users = rows
         .into_iter()
         .map(|row| {
-            let temp_zone: TempZone =
-                serde_json::from_value(row.zones).unwrap_or_else(|_| TempZone { zones: vec![] });
+            let temp_zone: TempZone = serde_json::from_value(row.zones.into())
+                .unwrap_or_else(|_| TempZone { zones: vec![] });
 
             TenantUserListItem {
                 tenant_id: *tenant_id,
-                user_id: row.user_id,
+                user_id: row.user_id.into(),
                 created_at: Utc::now(),
                 updated_at: Utc::now(),
                 email: row.email,
@@ -429,6 +489,15 @@ pub async fn delete_user(tenant_id: &Uuid, user_id: &Uuid) -> Result<(), Error>
     Ok(())
 }
 
+pub async fn get_user_ids(tenant_id: &Uuid) -> Result<Vec<Uuid>, Error> {
+    let items: Vec<fields::Uuid> = tenant_user::dsl::tenant_user
+        .select(tenant_user::dsl::user_id)
+        .filter(tenant_user::dsl::tenant_id.eq(&fields::Uuid::from(tenant_id)))
+        .load(&mut get_async_db_conn().await?)
+        .await?;
+    Ok(items.into_iter().map(|v| v.into()).collect())
+}
+
 pub async fn get_tenant_users_for_user(user_id: &Uuid) -> Result<Vec<TenantUser>, Error> {
     let items = tenant_user::dsl::tenant_user
         .filter(tenant_user::dsl::user_id.eq(&fields::Uuid::from(user_id)))
@@ -471,6 +540,11 @@ pub mod test {
             pro_license: Some(true),
             kitchen_management_license: Some(false),
             sms_count: Some(0),
+            max_message_count: 1000,
+            max_sms_count: 100,
+            over_quota_action: fields::OverQuotaAction::THROTTLE,
+            raw_data_retention_days: 0,
+            hourly_data_retention_months: 0,
         };
         create(t).await.unwrap()
     }
@@ -492,6 +566,10 @@ pub mod test {
 
         // update
         t.name = "new t".into();
+        t.max_message_count = 2000;
+        t.over_quota_action = fields::OverQuotaAction::DROP;
+        t.raw_data_retention_days = 90;
+        t.hourly_data_retention_months = 24;
         t = update(t).await.unwrap();
         let t_get = get(&t.id).await.unwrap();
         assert_eq!(t, t_get);
@@ -620,7 +698,7 @@ pub mod test {
 
         // get users
         let users = get_users(&t.id, 10, 0).await.unwrap();
-        assert_eq!(user.id, users[0].user_id.inner());
+        assert_eq!(Uuid::from(user.id), users[0].user_id);
 
         // delete
         delete_user(&t.id, &user.id).await.unwrap();
@@ -0,0 +1,253 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tracing::info;
+
+use super::schema::uplink_task;
+use super::{db_transaction, error::Error, fields, get_async_db_conn, AsyncDbConnection};
+use lrwn::EUI64;
+
+// Task kinds. The tasks are enqueued in this order for each decoded uplink, such that the
+// alarm rules see the sensor data of the uplink.
+pub const KIND_SENSOR_DATA: &str = "sensor_data";
+pub const KIND_ALARM: &str = "alarm";
+// Computation of a virtual device, enqueued for the virtual devices of which an input device
+// received a reading.
+pub const KIND_VIRTUAL: &str = "virtual_device";
+// Confirmed downlink of which all retries have been exhausted, enqueued by the downlink path
+// (the object holds the failure).
+pub const KIND_DOWNLINK_FAILURE: &str = "downlink_failure";
+
+// Post-decode processing task of an uplink. The tasks of a device are processed in order, a
+// task is only returned by claim when there is no older task of the same device left.
+#[derive(Queryable, QueryableByName, PartialEq, Eq, Debug, Clone)]
+#[diesel(table_name = uplink_task)]
+pub struct UplinkTask {
+    pub id: i64,
+    pub dev_eui: EUI64,
+    pub kind: String,
+    pub object: String,
+    pub created_at: DateTime<Utc>,
+    pub run_after: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = uplink_task)]
+struct NewUplinkTask<'a> {
+    dev_eui: EUI64,
+    kind: &'a str,
+    object: &'a str,
+    created_at: DateTime<Utc>,
+    run_after: DateTime<Utc>,
+}
+
+// Enqueues the given task kinds (in the given order) for the decoded object of the device. It
+// uses the connection of the uplink such that enqueueing does not require an extra connection.
+pub async fn enqueue(
+    conn: &mut AsyncDbConnection,
+    dev_eui: EUI64,
+    kinds: &[&str],
+    object: &serde_json::Value,
+) -> Result<(), Error> {
+    let object = object.to_string();
+    let now = Utc::now();
+    let tasks: Vec<NewUplinkTask> = kinds
+        .iter()
+        .map(|&kind| NewUplinkTask {
+            dev_eui,
+            kind,
+            object: &object,
+            created_at: now,
+            run_after: now,
+        })
+        .collect();
+
+    for task in &tasks {
+        diesel::insert_into(uplink_task::table)
+            .values(task)
+            .execute(conn)
+            .await
+            .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    }
+
+    Ok(())
+}
+
+// Claims at most limit tasks which are ready to be processed. Only the oldest task of each
+// device is returned and the claimed tasks are locked until lock_timeout has passed, such that
+// other workers (or ChirpStack instances) do not process them in the meantime.
+pub async fn claim(limit: usize, lock_timeout: Duration) -> Result<Vec<UplinkTask>> {
+    let mut c = get_async_db_conn().await?;
+    db_transaction::<Vec<UplinkTask>, Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            diesel::sql_query(if cfg!(feature = "sqlite") {
+                r#"
+                    update
+                        uplink_task
+                    set
+                        locked_until = ?3,
+                        attempts = attempts + 1
+                    where
+                        id in (
+                            select
+                                t.id
+                            from
+                                uplink_task t
+                            where
+                                t.run_after <= ?2
+                                and (t.locked_until is null or t.locked_until < ?2)
+                                and not exists (
+                                    select
+                                        1
+                                    from
+                                        uplink_task o
+                                    where
+                                        o.dev_eui = t.dev_eui
+                                        and o.id < t.id
+                                )
+                            order by t.id
+                            limit ?1
+                        )
+                    returning *
+                "#
+            } else {
+                r#"
+                    update
+                        uplink_task
+                    set
+                        locked_until = $3,
+                        attempts = attempts + 1
+                    where
+                        id in (
+                            select
+                                t.id
+                            from
+                                uplink_task t
+                            where
+                                t.run_after <= $2
+                                and (t.locked_until is null or t.locked_until < $2)
+                                and not exists (
+                                    select
+                                        1
+                                    from
+                                        uplink_task o
+                                    where
+                                        o.dev_eui = t.dev_eui
+                                        and o.id < t.id
+                                )
+                            order by t.id
+                            limit $1
+                            for update skip locked
+                        )
+                    returning *
+                "#
+            })
+            .bind::<diesel::sql_types::Integer, _>(limit as i32)
+            .bind::<fields::sql_types::Timestamptz, _>(Utc::now())
+            .bind::<fields::sql_types::Timestamptz, _>(Utc::now() + lock_timeout)
+            .load(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, "".into()))
+        })
+    })
+    .await
+    .context("Claim uplink tasks transaction")
+}
+
+// Deletes the processed task, this releases the next task of the device.
+pub async fn complete(id: i64) -> Result<(), Error> {
+    let mut c = get_async_db_conn().await?;
+    diesel::delete(uplink_task::dsl::uplink_task.find(id))
+        .execute(&mut c)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(())
+}
+
+// Releases the failed task such that it is retried after the given delay. As the task stays the
+// oldest task of the device, the next tasks of the device wait for the retry.
+pub async fn retry(id: i64, delay: Duration, error: &str) -> Result<(), Error> {
+    let mut c = get_async_db_conn().await?;
+    diesel::update(uplink_task::dsl::uplink_task.find(id))
+        .set((
+            uplink_task::run_after.eq(Utc::now() + delay),
+            uplink_task::locked_until.eq(None::<DateTime<Utc>>),
+            uplink_task::last_error.eq(error),
+        ))
+        .execute(&mut c)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    info!(id = id, delay = %delay, "Uplink task scheduled for retry");
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage;
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_uplink_task() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let d1 = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+        let d2 = storage::device::test::create_device(
+            EUI64::from_be_bytes([2, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+        let object = serde_json::json!({"temperature": "4.20"});
+
+        let mut c = get_async_db_conn().await.unwrap();
+        enqueue(&mut c, d1.dev_eui, &[KIND_SENSOR_DATA, KIND_ALARM], &object)
+            .await
+            .unwrap();
+        enqueue(&mut c, d2.dev_eui, &[KIND_SENSOR_DATA], &object)
+            .await
+            .unwrap();
+
+        // only the oldest task of each device is claimed
+        let tasks = claim(10, Duration::minutes(1)).await.unwrap();
+        assert_eq!(2, tasks.len());
+        assert_eq!(d1.dev_eui, tasks[0].dev_eui);
+        assert_eq!(KIND_SENSOR_DATA, tasks[0].kind);
+        assert_eq!(1, tasks[0].attempts);
+        assert_eq!(object.to_string(), tasks[0].object);
+        assert_eq!(d2.dev_eui, tasks[1].dev_eui);
+
+        // claimed tasks are locked
+        assert!(claim(10, Duration::minutes(1)).await.unwrap().is_empty());
+
+        // a task scheduled for retry blocks the next tasks of the device
+        retry(tasks[0].id, Duration::minutes(1), "error")
+            .await
+            .unwrap();
+        complete(tasks[1].id).await.unwrap();
+        assert!(claim(10, Duration::minutes(1)).await.unwrap().is_empty());
+
+        // retry now
+        retry(tasks[0].id, Duration::zero(), "error").await.unwrap();
+        let retried = claim(10, Duration::minutes(1)).await.unwrap();
+        assert_eq!(1, retried.len());
+        assert_eq!(tasks[0].id, retried[0].id);
+        assert_eq!(2, retried[0].attempts);
+        assert_eq!(Some("error".to_string()), retried[0].last_error);
+
+        // completing the task releases the next task of the device
+        complete(retried[0].id).await.unwrap();
+        let tasks = claim(10, Duration::minutes(1)).await.unwrap();
+        assert_eq!(1, tasks.len());
+        assert_eq!(KIND_ALARM, tasks[0].kind);
+    }
+}
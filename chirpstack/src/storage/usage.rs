@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use lrwn::EUI64;
+
+use super::error::Error;
+use super::schema::{application, device, tenant};
+use super::tenant::Tenant;
+use super::{fields, get_async_db_conn, metrics};
+use crate::config;
+
+pub const UPLINK_COUNT: &str = "uplink_count";
+pub const DOWNLINK_COUNT: &str = "downlink_count";
+pub const NOTIFICATION_COUNT: &str = "notification_count";
+pub const SMS_COUNT: &str = "sms_count";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quota {
+    // Uplinks and downlinks.
+    Messages,
+    Sms,
+}
+
+fn get_name(tenant_id: &Uuid) -> String {
+    format!("tenant:{}", tenant_id)
+}
+
+/// Adds the given count to the usage metric of the tenant.
+pub async fn record(tenant_id: &Uuid, metric: &str, count: f64) -> Result<()> {
+    let record = metrics::Record {
+        time: Local::now(),
+        kind: metrics::Kind::ABSOLUTE,
+        metrics: [(metric.to_string(), count)].into_iter().collect(),
+    };
+
+    metrics::save(
+        &get_name(tenant_id),
+        &record,
+        &metrics::Aggregation::default_aggregations(),
+    )
+    .await
+}
+
+/// Adds the given count to the usage metric of the tenant of the device.
+pub async fn record_device(dev_eui: &str, metric: &str, count: f64) -> Result<()> {
+    if let Some(t) = get_tenant_for_dev_eui(dev_eui).await? {
+        record(&t.id, metric, count).await?;
+    }
+
+    Ok(())
+}
+
+/// Returns false when the tenant of the device is over its SMS quota, in which case no SMS
+/// messages must be sent.
+pub async fn check_device_sms_quota(dev_eui: &str) -> Result<bool> {
+    match get_tenant_for_dev_eui(dev_eui).await? {
+        Some(t) => check_quota(&t, Quota::Sms).await,
+        None => Ok(true),
+    }
+}
+
+/// Meters the given number of sent SMS messages for the tenant of the device.
+pub async fn meter_device_sms(dev_eui: &str, count: usize) -> Result<()> {
+    record_device(dev_eui, SMS_COUNT, count as f64).await
+}
+
+pub async fn get(
+    tenant_id: &Uuid,
+    a: metrics::Aggregation,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+) -> Result<Vec<metrics::Record>> {
+    metrics::get(&get_name(tenant_id), metrics::Kind::ABSOLUTE, a, start, end).await
+}
+
+async fn get_current_count(
+    tenant_id: &Uuid,
+    a: metrics::Aggregation,
+    names: &[&str],
+) -> Result<f64> {
+    let now = Local::now();
+    let records = get(tenant_id, a, now, now).await?;
+
+    Ok(records
+        .iter()
+        .flat_map(|r| {
+            names
+                .iter()
+                .map(|n| r.metrics.get(*n).cloned().unwrap_or(0.0))
+        })
+        .sum())
+}
+
+/// Returns if the tenant is allowed to send or receive a message of the given quota, given
+/// the usage of the current month, the quota of the tenant and the over-quota action.
+pub async fn check_quota(t: &Tenant, q: Quota) -> Result<bool> {
+    let conf = config::get();
+
+    let (limit, throttle_limit, names) = match q {
+        Quota::Messages => (
+            t.max_message_count,
+            conf.metering.throttle_hourly_message_count,
+            vec![UPLINK_COUNT, DOWNLINK_COUNT],
+        ),
+        Quota::Sms => (
+            t.max_sms_count,
+            conf.metering.throttle_hourly_sms_count,
+            vec![SMS_COUNT],
+        ),
+    };
+
+    // A quota of 0 means unlimited.
+    if limit == 0 {
+        return Ok(true);
+    }
+
+    let count = get_current_count(&t.id, metrics::Aggregation::MONTH, &names).await?;
+    if count < limit as f64 {
+        return Ok(true);
+    }
+
+    match t.over_quota_action {
+        fields::OverQuotaAction::WARN => {
+            warn!(tenant_id = %t.id, quota = ?q, limit = limit, count = count, "Tenant exceeded quota");
+            Ok(true)
+        }
+        fields::OverQuotaAction::DROP => {
+            info!(tenant_id = %t.id, quota = ?q, limit = limit, count = count, "Tenant exceeded quota, dropping message");
+            Ok(false)
+        }
+        fields::OverQuotaAction::THROTTLE => {
+            let count = get_current_count(&t.id, metrics::Aggregation::HOUR, &names).await?;
+            if count < throttle_limit as f64 {
+                Ok(true)
+            } else {
+                info!(tenant_id = %t.id, quota = ?q, throttle_limit = throttle_limit, count = count, "Tenant exceeded quota, throttling message");
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Returns the usage totals of the given records, by metric name.
+pub fn get_totals(records: &[metrics::Record]) -> HashMap<String, f64> {
+    let mut out: HashMap<String, f64> = HashMap::new();
+    for r in records {
+        for (k, v) in &r.metrics {
+            *out.entry(k.clone()).or_insert(0.0) += v;
+        }
+    }
+    out
+}
+
+async fn get_tenant_for_dev_eui(dev_eui: &str) -> Result<Option<Tenant>, Error> {
+    let dev_eui = EUI64::from_str(dev_eui).map_err(|e| Error::Validation(e.to_string()))?;
+
+    device::table
+        .inner_join(application::table)
+        .inner_join(tenant::table.on(application::dsl::tenant_id.eq(tenant::dsl::id)))
+        .select(tenant::all_columns)
+        .filter(device::dsl::dev_eui.eq(&dev_eui))
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .optional()
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::tenant::test::create_tenant;
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_quota() {
+        let _guard = test::prepare().await;
+
+        let mut t = create_tenant().await;
+        t.max_message_count = 2;
+        t.max_sms_count = 0;
+        t.over_quota_action = fields::OverQuotaAction::DROP;
+        let tenant_id: Uuid = t.id.into();
+
+        // Within quota.
+        record(&tenant_id, UPLINK_COUNT, 1.0).await.unwrap();
+        assert!(check_quota(&t, Quota::Messages).await.unwrap());
+
+        // Over quota.
+        record(&tenant_id, DOWNLINK_COUNT, 1.0).await.unwrap();
+        assert!(!check_quota(&t, Quota::Messages).await.unwrap());
+
+        t.over_quota_action = fields::OverQuotaAction::WARN;
+        assert!(check_quota(&t, Quota::Messages).await.unwrap());
+
+        t.over_quota_action = fields::OverQuotaAction::THROTTLE;
+        assert!(check_quota(&t, Quota::Messages).await.unwrap());
+
+        // Unlimited.
+        record(&tenant_id, SMS_COUNT, 10.0).await.unwrap();
+        assert!(check_quota(&t, Quota::Sms).await.unwrap());
+
+        let now = Local::now();
+        let records = get(&tenant_id, metrics::Aggregation::MONTH, now, now)
+            .await
+            .unwrap();
+        let totals = get_totals(&records);
+        assert_eq!(Some(&1.0), totals.get(UPLINK_COUNT));
+        assert_eq!(Some(&1.0), totals.get(DOWNLINK_COUNT));
+        assert_eq!(Some(&10.0), totals.get(SMS_COUNT));
+    }
+}
@@ -1,4 +1,5 @@
 use super::error::Error;
+use super::fields::sql_types::Uuid as SqlUuid;
 use super::schema::user;
 use super::{fields, get_async_db_conn};
 use anyhow::Result;
@@ -8,11 +9,13 @@ use chirpstack_api::api::LandingDevice;
 use chirpstack_api::api::LandingDeviceProfile;
 use chirpstack_api::api::LandingOrganization;
 use chirpstack_api::api::LandingOrganizationList;
+use chirpstack_api::api::LandingSite;
+use chirpstack_api::api::LandingSiteList;
 use chirpstack_api::api::LandingZone;
 use chirpstack_api::api::LandingZoneList;
 use chrono::{DateTime, Utc};
 use diesel::sql_query;
-use diesel::sql_types::{Nullable, Text, Uuid as SqlUuid};
+use diesel::sql_types::Text;
 use diesel::{dsl, prelude::*};
 use diesel_async::RunQueryDsl;
 use email_address::EmailAddress;
@@ -52,10 +55,11 @@ pub struct User {
     pub phone_number: String,
     pub name: Option<String>,
     pub username: Option<String>,
-    pub zone_id_list: Option<Vec<Option<i64>>>,
+    pub zone_id_list: Option<fields::BigIntArray>,
     pub training: bool,
     pub expo_key: Option<String>,
     pub web_key: Option<String>,
+    pub site_id_list: Option<fields::BigIntArray>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -72,6 +76,28 @@ pub struct SerdeLandingZone {
     pub order: i64,
     pub contentType: i64,
     pub devices: Vec<SerdeLandingDevice>,
+    #[serde(default)]
+    pub site_id: Option<i64>,
+    #[serde(default)]
+    pub site_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SerdeLandingSiteList {
+    #[serde(default, deserialize_with = "null_to_empty_vec")]
+    pub sites: Vec<SerdeLandingSite>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SerdeLandingSite {
+    pub site_id: i64,
+    pub site_name: String,
+    pub org_id: String,
+    pub address: String,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub timezone: String,
+    pub order: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -134,6 +160,7 @@ impl Default for User {
             training: false,
             expo_key: None,
             web_key: None,
+            site_id_list: None,
         }
     }
 }
@@ -155,6 +182,7 @@ pub struct GetLandingResponseSerde {
     pub organization_id_list: Vec<String>,
     pub organizationList: SerdeLandingOrganizationList,
     pub zoneList: SerdeLandingZoneList,
+    pub siteList: SerdeLandingSiteList,
 }
 
 #[derive(Debug, Deserialize)]
@@ -205,6 +233,19 @@ pub async fn get(id: &Uuid) -> Result<User, Error> {
     Ok(u)
 }
 
+// Returns the phone numbers of the given users, the users without phone number are left out.
+pub async fn get_phone_numbers(ids: &[Uuid]) -> Result<Vec<String>, Error> {
+    let ids: Vec<fields::Uuid> = ids.iter().map(fields::Uuid::from).collect();
+    let items = user::dsl::user
+        .select(user::dsl::phone_number)
+        .filter(user::dsl::id.eq_any(&ids))
+        .filter(user::dsl::phone_number.ne(""))
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "phone numbers".to_string()))?;
+    Ok(items)
+}
+
 pub async fn get_by_email(email: &str) -> Result<User, Error> {
     let u = user::dsl::user
         .filter(user::dsl::email.eq(email))
@@ -249,7 +290,116 @@ pub async fn get_by_email_and_pw(username: &str, pw: &str) -> Result<User, Error
 pub async fn get_login(userid: &Uuid) -> Result<GetLandingResponse, Status> {
     println!("🌀 Starting get_login for user: {}", userid);
 
-    let mut query = r#"   WITH device_data_2025 AS (
+    // SQLite has no arrays, the zone devices and user zone / site lists are JSON arrays. The
+    // nested JSON values are wrapped in json() as sub-queries return them as text.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+WITH device_data_2025 AS (
+    SELECT
+        dev.dev_eui,
+        json_object(
+            'device_dev_eui', '\x' || lower(hex(dev.dev_eui)),
+            'device_name', dev.name,
+            'device_description', dev.description,
+            'tags', json(dev.tags),
+            'variables', json(dev.variables),
+            'device_data_time', dev.data_time,
+            'device_created_at', dev.created_at,
+            'device_updated_at', dev.updated_at,
+            'device_profile_id', dev.device_profile_id,
+            'latitude', dev.latitude,
+            'longitude', dev.longitude,
+            'device_application_id', dev.application_id
+        ) AS device_json
+    FROM device AS dev
+),
+zone_data AS (
+    SELECT
+        z.zone_id,
+        z.site_id,
+        json_object(
+            'zone_id', z.zone_id,
+            'zone_name', z.zone_name,
+            'org_id', z.tanent_id,
+            'order', z.zone_order,
+            'contentType', z.content_type,
+            'site_id', z.site_id,
+            'site_name', s.site_name,
+            'devices', json((
+                SELECT json_group_array(json(dd.device_json))
+                FROM json_each(z.devices) AS zd
+                INNER JOIN device_data_2025 AS dd ON '\x' || lower(hex(dd.dev_eui)) = zd.value
+            ))
+        ) AS list
+    FROM zone AS z
+    LEFT JOIN site AS s ON s.site_id = z.site_id
+)
+SELECT json_object(
+    'id', u.id,
+    'email', u.email,
+    'is_active', json(CASE WHEN u.is_active THEN 'true' ELSE 'false' END),
+    'web_key', u.web_key,
+    'android_key', u.android_key,
+    'phone_number', u.phone_number,
+    'name', u.name,
+    'note', u.note,
+    'username', u.username,
+    'training', json(CASE WHEN u.training THEN 'true' ELSE 'false' END),
+    'organization_id_list', json((
+        SELECT json_group_array(ou.tenant_id)
+        FROM tenant_user ou
+        WHERE ou.user_id = u.id
+    )),
+    'organizationList', json_object(
+        'organizations', json((
+            SELECT json_group_array(
+                json_object(
+                    'organization_id', org.id,
+                    'organization_name', org.name
+                )
+            )
+            FROM tenant_user ou
+            JOIN tenant org ON org.id = ou.tenant_id
+            WHERE ou.user_id = u.id
+        ))
+    ),
+    'zoneList', json_object(
+        'zones', json((
+            SELECT json_group_array(json(zd.list))
+            FROM zone_data zd
+            WHERE zd.zone_id IN (SELECT value FROM json_each(u.zone_id_list))
+                OR zd.site_id IN (SELECT value FROM json_each(u.site_id_list))
+        ))
+    ),
+    'siteList', json_object(
+        'sites', json((
+            SELECT json_group_array(json(ss.site))
+            FROM (
+                SELECT json_object(
+                    'site_id', s.site_id,
+                    'site_name', s.site_name,
+                    'org_id', s.tenant_id,
+                    'address', s.address,
+                    'latitude', s.latitude,
+                    'longitude', s.longitude,
+                    'timezone', s.timezone,
+                    'order', s.site_order
+                ) AS site
+                FROM site s
+                WHERE s.site_id IN (SELECT value FROM json_each(u.site_id_list))
+                    OR s.site_id IN (
+                        SELECT z.site_id FROM zone z
+                        WHERE z.zone_id IN (SELECT value FROM json_each(u.zone_id_list))
+                    )
+                ORDER BY s.site_order, s.site_name
+            ) AS ss
+        ))
+    )
+) AS login_response
+FROM user u
+WHERE u.id = ?1;"#
+    } else {
+        r#"   WITH device_data_2025 AS (
     SELECT 
         dev.dev_eui,
         json_build_object(
@@ -274,18 +424,22 @@ pub async fn get_login(userid: &Uuid) -> Result<GetLandingResponse, Status> {
 zone_data AS (
     SELECT 
         z.zone_id,
+        z.site_id,
         json_build_object(
             'zone_id', z.zone_id,
             'zone_name', z.zone_name,
             'org_id', z.tanent_id,
             'order', z.zone_order,
             'contentType', z.content_type,
+            'site_id', z.site_id,
+            'site_name', s.site_name,
             'devices', COALESCE(array_agg(dd.device_json), ARRAY[]::json[])
         ) AS list
     FROM public.zone z
+    LEFT JOIN public.site s ON s.site_id = z.site_id
     LEFT JOIN public.device d ON d.dev_eui::text = ANY(z.devices)
     LEFT JOIN device_data_2025 dd ON d.dev_eui = dd.dev_eui
-    GROUP BY z.zone_id
+    GROUP BY z.zone_id, s.site_name
 )
 SELECT json_build_object(
     'id', u.id,
@@ -320,13 +474,34 @@ SELECT json_build_object(
         'zones', (
             SELECT array_agg(zd.list)
             FROM zone_data zd
-            WHERE zd.zone_id = ANY(u.zone_id_list)
+            WHERE zd.zone_id = ANY(u.zone_id_list) OR zd.site_id = ANY(u.site_id_list)
+        )
+    ),
+    'siteList', json_build_object(
+        'sites', (
+            SELECT array_agg(
+                json_build_object(
+                    'site_id', s.site_id,
+                    'site_name', s.site_name,
+                    'org_id', s.tenant_id,
+                    'address', s.address,
+                    'latitude', s.latitude,
+                    'longitude', s.longitude,
+                    'timezone', s.timezone,
+                    'order', s.site_order
+                ) ORDER BY s.site_order, s.site_name
+            )
+            FROM public.site s
+            WHERE s.site_id = ANY(u.site_id_list)
+                OR s.site_id IN (
+                    SELECT z.site_id FROM public.zone z WHERE z.zone_id = ANY(u.zone_id_list)
+                )
         )
     )
 ) AS login_response
 FROM public.user u
 WHERE u.id = $1;"#
-        .to_string();
+    };
 
     let conn = &mut get_async_db_conn()
         .await
@@ -339,7 +514,7 @@ WHERE u.id = $1;"#
     }
 
     let row: LoginRow = sql_query(query)
-        .bind::<SqlUuid, _>(userid)
+        .bind::<SqlUuid, _>(fields::Uuid::from(userid))
         .get_result(conn)
         .await
         .map_err(|e| Status::internal(format!("Query failed: {e}")))?;
@@ -522,6 +697,7 @@ impl From<GetLandingResponseSerde> for GetLandingResponse {
             organization_id_list: s.organization_id_list,
             organization_list: Some(s.organizationList.into()),
             zone_list: Some(s.zoneList.into()),
+            site_list: Some(s.siteList.into()),
         }
     }
 }
@@ -549,6 +725,27 @@ impl From<SerdeLandingZoneList> for LandingZoneList {
     }
 }
 
+impl From<SerdeLandingSiteList> for LandingSiteList {
+    fn from(s: SerdeLandingSiteList) -> Self {
+        LandingSiteList {
+            sites: s
+                .sites
+                .into_iter()
+                .map(|s| LandingSite {
+                    site_id: s.site_id,
+                    site_name: s.site_name,
+                    org_id: s.org_id,
+                    address: s.address,
+                    latitude: s.latitude.unwrap_or_default(),
+                    longitude: s.longitude.unwrap_or_default(),
+                    timezone: s.timezone,
+                    order: s.order,
+                })
+                .collect(),
+        }
+    }
+}
+
 impl From<SerdeLandingZone> for LandingZone {
     fn from(z: SerdeLandingZone) -> Self {
         LandingZone {
@@ -558,6 +755,8 @@ impl From<SerdeLandingZone> for LandingZone {
             order: z.order,
             content_type: z.contentType,
             devices: z.devices.into_iter().map(|d| d.into()).collect(),
+            site_id: z.site_id.unwrap_or_default(),
+            site_name: z.site_name.unwrap_or_default(),
         }
     }
 }
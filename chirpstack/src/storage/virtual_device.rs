@@ -0,0 +1,568 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tracing::info;
+
+use super::error::Error;
+use super::schema::{measurement, measurement_latest, virtual_device, virtual_device_input};
+use super::{db_transaction, device, get_async_db_conn, measurement as measurement_storage};
+use super::{uplink_task, AsyncDbConnection};
+use crate::codec;
+use lrwn::EUI64;
+
+// Device type of the virtual devices. There is no device_type_tb entry for this type, the
+// device of a virtual device uses the application and device-profile of its first input.
+pub const DEVICE_TYPE: i32 = 100;
+
+// Input aggregates.
+// LATEST: latest value of the measurement kind of the input device.
+// DAY_DELTA: change of the measurement kind since (local) midnight, e.g. the energy of today
+// from a cumulative energy counter.
+// VARIABLE: (numeric) device variable of the input device, the kind is the variable name.
+pub const AGGREGATE_LATEST: &str = "LATEST";
+pub const AGGREGATE_DAY_DELTA: &str = "DAY_DELTA";
+pub const AGGREGATE_VARIABLE: &str = "VARIABLE";
+
+// Max. number of virtual devices between an input device and a virtual device, when virtual
+// devices use other virtual devices as input.
+const MAX_DEPTH: usize = 5;
+
+// Virtual device. Its value is computed using the formula (a JS expression) each time one of
+// its inputs receives a reading. The value is stored as a measurement of the given kind, such
+// that it can be used like a reading of a real device.
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = virtual_device)]
+pub struct VirtualDevice {
+    pub dev_eui: EUI64,
+    pub kind: String,
+    pub unit: String,
+    pub formula: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for VirtualDevice {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        VirtualDevice {
+            dev_eui: EUI64::default(),
+            kind: "".into(),
+            unit: "".into(),
+            formula: "".into(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = virtual_device_input)]
+pub struct Input {
+    pub dev_eui: EUI64,
+    pub name: String,
+    pub input_dev_eui: EUI64,
+    pub kind: String,
+    pub aggregate: String,
+}
+
+impl VirtualDevice {
+    fn validate(&self, inputs: &[Input]) -> Result<(), Error> {
+        if self.kind.trim().is_empty() {
+            return Err(Error::Validation("Measurement kind cannot be empty".into()));
+        }
+        if self.formula.trim().is_empty() {
+            return Err(Error::Validation("Formula cannot be empty".into()));
+        }
+        if !inputs.iter().any(|i| i.aggregate != AGGREGATE_VARIABLE) {
+            return Err(Error::Validation(
+                "At least one measurement input is required".into(),
+            ));
+        }
+
+        let mut names = HashSet::new();
+        for input in inputs {
+            if !is_identifier(&input.name) {
+                return Err(Error::Validation(format!(
+                    "Invalid input name: {}",
+                    input.name
+                )));
+            }
+            if !names.insert(input.name.as_str()) {
+                return Err(Error::Validation(format!(
+                    "Duplicate input name: {}",
+                    input.name
+                )));
+            }
+            if input.input_dev_eui == self.dev_eui {
+                return Err(Error::Validation(
+                    "A virtual device cannot use itself as input".into(),
+                ));
+            }
+            if input.kind.trim().is_empty() {
+                return Err(Error::Validation("Input kind cannot be empty".into()));
+            }
+            if ![AGGREGATE_LATEST, AGGREGATE_DAY_DELTA, AGGREGATE_VARIABLE]
+                .contains(&input.aggregate.as_str())
+            {
+                return Err(Error::Validation(format!(
+                    "Invalid input aggregate: {}",
+                    input.aggregate
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Input names are used as JS variable names in the formula.
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// Creates the device of the virtual device and the virtual device. The device is removed again
+// when the virtual device could not be created.
+pub async fn create(
+    d: device::Device,
+    vd: VirtualDevice,
+    inputs: Vec<Input>,
+) -> Result<VirtualDevice, Error> {
+    vd.validate(&inputs)?;
+
+    let d = device::create(device::Device {
+        device_type: Some(DEVICE_TYPE),
+        ..d
+    })
+    .await?;
+
+    let mut c = get_async_db_conn().await?;
+    let res = db_transaction::<VirtualDevice, Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            let now = Utc::now();
+            let vd: VirtualDevice = diesel::insert_into(virtual_device::table)
+                .values(&VirtualDevice {
+                    created_at: now,
+                    updated_at: now,
+                    ..vd
+                })
+                .get_result(c)
+                .await
+                .map_err(|e| Error::from_diesel(e, vd.dev_eui.to_string()))?;
+
+            for input in &inputs {
+                diesel::insert_into(virtual_device_input::table)
+                    .values(input)
+                    .execute(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, vd.dev_eui.to_string()))?;
+            }
+
+            Ok(vd)
+        })
+    })
+    .await;
+
+    match res {
+        Ok(vd) => {
+            info!(dev_eui = %vd.dev_eui, kind = %vd.kind, "Virtual device created");
+            Ok(vd)
+        }
+        Err(e) => {
+            device::delete(&d.dev_eui).await?;
+            Err(e)
+        }
+    }
+}
+
+pub async fn get(dev_eui: &EUI64) -> Result<VirtualDevice, Error> {
+    let vd = virtual_device::dsl::virtual_device
+        .find(dev_eui)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(vd)
+}
+
+pub async fn get_inputs(dev_eui: &EUI64) -> Result<Vec<Input>, Error> {
+    let items = virtual_device_input::dsl::virtual_device_input
+        .filter(virtual_device_input::dsl::dev_eui.eq(dev_eui))
+        .order_by(virtual_device_input::dsl::name)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(items)
+}
+
+// Updates the formula and inputs of the virtual device. The inputs are replaced.
+pub async fn update(vd: VirtualDevice, inputs: Vec<Input>) -> Result<VirtualDevice, Error> {
+    vd.validate(&inputs)?;
+
+    let mut c = get_async_db_conn().await?;
+    for input in &inputs {
+        if depends_on(&mut c, &input.input_dev_eui, &vd.dev_eui).await? {
+            return Err(Error::Validation(format!(
+                "Input {} depends on the virtual device",
+                input.name
+            )));
+        }
+    }
+
+    let vd = db_transaction::<VirtualDevice, Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            let vd: VirtualDevice =
+                diesel::update(virtual_device::dsl::virtual_device.find(&vd.dev_eui))
+                    .set((
+                        virtual_device::updated_at.eq(Utc::now()),
+                        virtual_device::kind.eq(&vd.kind),
+                        virtual_device::unit.eq(&vd.unit),
+                        virtual_device::formula.eq(&vd.formula),
+                    ))
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, vd.dev_eui.to_string()))?;
+
+            diesel::delete(
+                virtual_device_input::dsl::virtual_device_input
+                    .filter(virtual_device_input::dsl::dev_eui.eq(&vd.dev_eui)),
+            )
+            .execute(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, vd.dev_eui.to_string()))?;
+
+            for input in &inputs {
+                diesel::insert_into(virtual_device_input::table)
+                    .values(input)
+                    .execute(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, vd.dev_eui.to_string()))?;
+            }
+
+            Ok(vd)
+        })
+    })
+    .await?;
+
+    info!(dev_eui = %vd.dev_eui, "Virtual device updated");
+    Ok(vd)
+}
+
+// Returns the virtual devices which use a measurement of the given device as input.
+pub async fn get_dependants(
+    conn: &mut AsyncDbConnection,
+    dev_eui: &EUI64,
+) -> Result<Vec<EUI64>, Error> {
+    let items = virtual_device_input::dsl::virtual_device_input
+        .select(virtual_device_input::dsl::dev_eui)
+        .filter(virtual_device_input::dsl::input_dev_eui.eq(dev_eui))
+        .filter(virtual_device_input::dsl::aggregate.ne(AGGREGATE_VARIABLE))
+        .distinct()
+        .load(conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, dev_eui.to_string()))?;
+    Ok(items)
+}
+
+// Returns true when the given device uses target (directly or through other virtual devices) as
+// input. Chains longer than MAX_DEPTH are reported as dependent, such that these are rejected.
+async fn depends_on(
+    conn: &mut AsyncDbConnection,
+    dev_eui: &EUI64,
+    target: &EUI64,
+) -> Result<bool, Error> {
+    let mut current = vec![*target];
+    for _ in 0..MAX_DEPTH {
+        let mut next = Vec::new();
+        for d in &current {
+            if d == dev_eui {
+                return Ok(true);
+            }
+            next.extend(get_dependants(conn, d).await?);
+        }
+        if next.is_empty() {
+            return Ok(false);
+        }
+        current = next;
+    }
+    Ok(true)
+}
+
+// Enqueues the computation of the virtual devices which use the given device as input. This is
+// called after the reading of the device has been stored.
+pub async fn enqueue_dependants(conn: &mut AsyncDbConnection, dev_eui: &EUI64) -> Result<()> {
+    for d in get_dependants(conn, dev_eui).await? {
+        uplink_task::enqueue(
+            conn,
+            d,
+            &[uplink_task::KIND_VIRTUAL],
+            &serde_json::json!({ "input": dev_eui.to_string() }),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+// Computes and stores the value of the virtual device, using the given time as measurement time.
+// The alarms of the virtual device and the virtual devices which use this virtual device as
+// input are enqueued after storing the value.
+pub async fn process(
+    conn: &mut AsyncDbConnection,
+    d: &device::Device,
+    time: DateTime<Utc>,
+) -> Result<()> {
+    let vd = get(&d.dev_eui).await?;
+    let value = match compute(conn, d, &vd).await? {
+        Some(v) => v,
+        None => {
+            info!(dev_eui = %d.dev_eui, "Not all inputs of virtual device have a value, skipping");
+            return Ok(());
+        }
+    };
+
+    measurement_storage::save(
+        conn,
+        &[measurement_storage::Measurement {
+            dev_eui: d.dev_eui,
+            kind: vd.kind.clone(),
+            time,
+            unit: vd.unit.clone(),
+            value: Some(value),
+            text_value: None,
+        }],
+    )
+    .await?;
+
+    uplink_task::enqueue(
+        conn,
+        d.dev_eui,
+        &[uplink_task::KIND_ALARM],
+        &serde_json::json!({ "kind": vd.kind, "unit": vd.unit, "value": value }),
+    )
+    .await?;
+    enqueue_dependants(conn, &d.dev_eui).await?;
+
+    info!(dev_eui = %d.dev_eui, kind = %vd.kind, value = value, "Virtual device value stored");
+    Ok(())
+}
+
+// Computes the value of the virtual device. The numeric variables of the virtual device are
+// available to the formula next to the inputs. It returns None when an input has no value.
+async fn compute(
+    conn: &mut AsyncDbConnection,
+    d: &device::Device,
+    vd: &VirtualDevice,
+) -> Result<Option<f64>> {
+    let mut values: HashMap<String, f64> = d
+        .variables
+        .iter()
+        .filter_map(|(k, v)| v.trim().parse().ok().map(|v| (k.clone(), v)))
+        .filter(|(k, _)| is_identifier(k))
+        .collect();
+
+    for input in get_inputs(&d.dev_eui).await? {
+        let value = match input.aggregate.as_str() {
+            AGGREGATE_DAY_DELTA => get_day_delta(conn, &input).await?,
+            AGGREGATE_VARIABLE => device::get(&input.input_dev_eui)
+                .await?
+                .variables
+                .get(&input.kind)
+                .and_then(|v| v.trim().parse().ok()),
+            _ => get_latest(conn, &input).await?,
+        };
+
+        match value {
+            Some(v) => values.insert(input.name, v),
+            None => return Ok(None),
+        };
+    }
+
+    Ok(Some(codec::evaluate_formula(&vd.formula, &values).await?))
+}
+
+async fn get_latest(conn: &mut AsyncDbConnection, input: &Input) -> Result<Option<f64>, Error> {
+    let value: Option<Option<f64>> = measurement_latest::dsl::measurement_latest
+        .select(measurement_latest::dsl::value)
+        .filter(measurement_latest::dsl::dev_eui.eq(&input.input_dev_eui))
+        .filter(measurement_latest::dsl::kind.eq(&input.kind))
+        .first(conn)
+        .await
+        .optional()
+        .map_err(|e| Error::from_diesel(e, input.input_dev_eui.to_string()))?;
+    Ok(value.flatten())
+}
+
+async fn get_day_delta(conn: &mut AsyncDbConnection, input: &Input) -> Result<Option<f64>, Error> {
+    let midnight = Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|v| v.and_local_timezone(Local).earliest())
+        .map(|v| v.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now);
+
+    let values: Vec<Option<f64>> = measurement::dsl::measurement
+        .select(measurement::dsl::value)
+        .filter(measurement::dsl::dev_eui.eq(&input.input_dev_eui))
+        .filter(measurement::dsl::kind.eq(&input.kind))
+        .filter(measurement::dsl::time.ge(midnight))
+        .filter(measurement::dsl::value.is_not_null())
+        .order_by(measurement::dsl::time)
+        .load(conn)
+        .await
+        .map_err(|e| Error::from_diesel(e, input.input_dev_eui.to_string()))?;
+
+    Ok(match (values.as_slice().first(), values.last()) {
+        (Some(Some(first)), Some(Some(last))) => Some(last - first),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage;
+    use crate::test;
+
+    #[test]
+    fn test_validate() {
+        let dev_eui = EUI64::from_be_bytes([1, 1, 1, 1, 1, 1, 1, 1]);
+        let vd = VirtualDevice {
+            dev_eui,
+            kind: "dew_point".into(),
+            unit: "°C".into(),
+            formula: "t - (100 - h) / 5".into(),
+            ..Default::default()
+        };
+        let input = |name: &str, aggregate: &str| Input {
+            dev_eui,
+            name: name.into(),
+            input_dev_eui: EUI64::from_be_bytes([2, 2, 2, 2, 2, 2, 2, 2]),
+            kind: "air_temperature".into(),
+            aggregate: aggregate.into(),
+        };
+
+        assert!(vd
+            .validate(&[input("t", AGGREGATE_LATEST), input("h", AGGREGATE_LATEST)])
+            .is_ok());
+        assert!(vd.validate(&[]).is_err());
+        assert!(vd.validate(&[input("t", AGGREGATE_VARIABLE)]).is_err());
+        assert!(vd.validate(&[input("1t", AGGREGATE_LATEST)]).is_err());
+        assert!(vd.validate(&[input("t", "SUM")]).is_err());
+        assert!(vd
+            .validate(&[input("t", AGGREGATE_LATEST), input("t", AGGREGATE_LATEST)])
+            .is_err());
+        assert!(vd
+            .validate(&[Input {
+                input_dev_eui: dev_eui,
+                ..input("t", AGGREGATE_LATEST)
+            }])
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_virtual_device() {
+        let _guard = test::prepare().await;
+        let dp = storage::device_profile::test::create_device_profile(None).await;
+        let mut sensor = storage::device::test::create_device(
+            EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            dp.id.into(),
+            None,
+        )
+        .await;
+        sensor.variables.insert("deepness".into(), "3000".into());
+        let sensor = storage::device::update(sensor).await.unwrap();
+
+        // tank level (mm) from the distance of the sensor to the liquid and the tank depth
+        let dev_eui = EUI64::from_be_bytes([8, 7, 6, 5, 4, 3, 2, 1]);
+        let vd = create(
+            device::Device {
+                dev_eui,
+                name: "tank".into(),
+                application_id: sensor.application_id,
+                device_profile_id: sensor.device_profile_id,
+                ..Default::default()
+            },
+            VirtualDevice {
+                dev_eui,
+                kind: "tank_level".into(),
+                unit: "mm".into(),
+                formula: "deepness - distance".into(),
+                ..Default::default()
+            },
+            vec![
+                Input {
+                    dev_eui,
+                    name: "distance".into(),
+                    input_dev_eui: sensor.dev_eui,
+                    kind: "distance".into(),
+                    aggregate: AGGREGATE_LATEST.into(),
+                },
+                Input {
+                    dev_eui,
+                    name: "deepness".into(),
+                    input_dev_eui: sensor.dev_eui,
+                    kind: "deepness".into(),
+                    aggregate: AGGREGATE_VARIABLE.into(),
+                },
+            ],
+        )
+        .await
+        .unwrap();
+        assert_eq!(vd, get(&dev_eui).await.unwrap());
+        assert_eq!(2, get_inputs(&dev_eui).await.unwrap().len());
+
+        let d = device::get(&dev_eui).await.unwrap();
+        assert_eq!(Some(DEVICE_TYPE), d.device_type);
+
+        let mut c = get_async_db_conn().await.unwrap();
+        assert_eq!(
+            vec![dev_eui],
+            get_dependants(&mut c, &sensor.dev_eui).await.unwrap()
+        );
+
+        // no reading yet
+        assert_eq!(None, compute(&mut c, &d, &vd).await.unwrap());
+
+        measurement_storage::save_values(
+            &mut c,
+            sensor.dev_eui,
+            Utc::now(),
+            &[("distance", Some(measurement_storage::Value::Number(1200.0)))],
+        )
+        .await
+        .unwrap();
+        assert_eq!(Some(1800.0), compute(&mut c, &d, &vd).await.unwrap());
+
+        // an update using the virtual device itself (indirectly) as input would be a cycle
+        assert!(depends_on(&mut c, &dev_eui, &sensor.dev_eui).await.unwrap());
+        assert!(!depends_on(&mut c, &sensor.dev_eui, &dev_eui).await.unwrap());
+
+        // update
+        let vd_up = update(
+            VirtualDevice {
+                formula: "(deepness - distance) / 1000".into(),
+                unit: "m".into(),
+                ..vd.clone()
+            },
+            get_inputs(&dev_eui).await.unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!("m", vd_up.unit);
+        assert_eq!(Some(1.8), compute(&mut c, &d, &vd_up).await.unwrap());
+
+        // deleting the device removes the virtual device
+        device::delete(&dev_eui).await.unwrap();
+        assert!(get(&dev_eui).await.is_err());
+        assert!(get_dependants(&mut c, &sensor.dev_eui)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,1115 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Integer, Nullable, Text};
+use diesel_async::RunQueryDsl;
+use rand::RngCore;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::automation::Automation;
+use super::error::Error;
+use super::escalation;
+use super::fields::sql_types::Uuid as SqlUuid;
+use super::schema::{alarm_state, webhook_delivery, webhook_subscription};
+use super::{db_transaction, fields, get_async_db_conn};
+
+// Event types a webhook can subscribe to.
+pub const EVENT_ALARM_RAISED: &str = "alarm.raised";
+pub const EVENT_ALARM_ACKNOWLEDGED: &str = "alarm.acknowledged";
+pub const EVENT_ALARM_CLEARED: &str = "alarm.cleared";
+pub const EVENT_ALARM_ESCALATED: &str = "alarm.escalated";
+pub const EVENT_AUTOMATION_EXECUTED: &str = "automation.executed";
+pub const EVENT_AUTOMATION_FAILED: &str = "automation.failed";
+
+pub const EVENTS: [&str; 6] = [
+    EVENT_ALARM_RAISED,
+    EVENT_ALARM_ACKNOWLEDGED,
+    EVENT_ALARM_CLEARED,
+    EVENT_ALARM_ESCALATED,
+    EVENT_AUTOMATION_EXECUTED,
+    EVENT_AUTOMATION_FAILED,
+];
+
+// Delivery statuses. A delivery is PENDING until it has been delivered, or DEAD when it could
+// not be delivered within the max attempts.
+pub const STATUS_PENDING: &str = "PENDING";
+pub const STATUS_DELIVERED: &str = "DELIVERED";
+pub const STATUS_DEAD: &str = "DEAD";
+
+// Webhook subscription of a tenant. The events are posted as JSON to the URL, signed with the
+// secret.
+#[derive(Queryable, PartialEq, Debug, Clone)]
+#[diesel(table_name = webhook_subscription)]
+pub struct Subscription {
+    pub id: i32,
+    pub tenant_id: fields::Uuid,
+    pub name: String,
+    pub url: String,
+    pub secret: String,
+    pub events: fields::TextArray,
+    pub is_disabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        Subscription {
+            id: 0,
+            tenant_id: Uuid::nil().into(),
+            name: "".into(),
+            url: "".into(),
+            secret: "".into(),
+            events: fields::TextArray::default(),
+            is_disabled: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+impl Subscription {
+    fn validate(&self) -> Result<(), Error> {
+        if self.name.trim().is_empty() {
+            return Err(Error::Validation("Webhook name cannot be empty".into()));
+        }
+        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            return Err(Error::Validation("Webhook must have a http(s) URL".into()));
+        }
+        if self.events.iter().flatten().next().is_none() {
+            return Err(Error::Validation(
+                "Webhook must subscribe to at least one event".into(),
+            ));
+        }
+        for e in self.events.iter().flatten() {
+            if !EVENTS.contains(&e.as_str()) {
+                return Err(Error::Validation(format!("Unknown webhook event: {}", e)));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_subscribed(&self, event: &str) -> bool {
+        self.events.iter().flatten().any(|e| e == event)
+    }
+}
+
+#[derive(Queryable, QueryableByName, PartialEq, Debug, Clone)]
+#[diesel(table_name = webhook_delivery)]
+pub struct Delivery {
+    pub id: i64,
+    pub subscription_id: i32,
+    pub event: String,
+    pub payload: String,
+    pub status: String,
+    pub attempts: i32,
+    pub run_after: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = webhook_delivery)]
+struct NewDelivery<'a> {
+    subscription_id: i32,
+    event: &'a str,
+    payload: &'a str,
+    status: &'a str,
+    run_after: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+}
+
+// Raised alarm, see alarm_raised and alarm_cleared.
+#[derive(Queryable, Insertable, PartialEq, Debug, Clone)]
+#[diesel(table_name = alarm_state)]
+pub struct AlarmState {
+    pub alarm_id: i32,
+    pub alarm_type: String,
+    pub dev_eui: String,
+    pub value: Option<f32>,
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub acknowledged_by: Option<fields::Uuid>,
+}
+
+// The alarm, device and zone of an alarm event.
+#[derive(QueryableByName, Debug, Clone)]
+pub struct AlarmContext {
+    #[diesel(sql_type = Integer)]
+    pub alarm_id: i32,
+
+    #[diesel(sql_type = SqlUuid)]
+    pub tenant_id: fields::Uuid,
+
+    #[diesel(sql_type = Text)]
+    pub dev_eui: String,
+
+    #[diesel(sql_type = Text)]
+    pub device_name: String,
+
+    #[diesel(sql_type = Nullable<Integer>)]
+    pub zone_id: Option<i32>,
+
+    #[diesel(sql_type = Nullable<Text>)]
+    pub zone_name: Option<String>,
+
+    #[diesel(sql_type = Nullable<Double>)]
+    pub min_threshold: Option<f64>,
+
+    #[diesel(sql_type = Nullable<Double>)]
+    pub max_threshold: Option<f64>,
+}
+
+// Returns a random secret for signing the webhook payloads.
+fn generate_secret() -> String {
+    let mut b = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut b);
+    hex::encode(b)
+}
+
+// Creates the subscription, a secret is generated when none is given.
+pub async fn create(s: Subscription) -> Result<Subscription, Error> {
+    s.validate()?;
+
+    let now = Utc::now();
+    let secret = if s.secret.is_empty() {
+        generate_secret()
+    } else {
+        s.secret.clone()
+    };
+
+    let s: Subscription = diesel::insert_into(webhook_subscription::table)
+        .values((
+            webhook_subscription::tenant_id.eq(&s.tenant_id),
+            webhook_subscription::name.eq(&s.name),
+            webhook_subscription::url.eq(&s.url),
+            webhook_subscription::secret.eq(&secret),
+            webhook_subscription::events.eq(&s.events),
+            webhook_subscription::is_disabled.eq(s.is_disabled),
+            webhook_subscription::created_at.eq(now),
+            webhook_subscription::updated_at.eq(now),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, s.name.clone()))?;
+
+    info!(webhook_id = s.id, tenant_id = %s.tenant_id, "Webhook created");
+    Ok(s)
+}
+
+pub async fn get(id: i32) -> Result<Subscription, Error> {
+    let s = webhook_subscription::dsl::webhook_subscription
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(s)
+}
+
+// Updates the subscription, the secret is only replaced when set.
+pub async fn update(s: Subscription) -> Result<Subscription, Error> {
+    s.validate()?;
+
+    let mut c = get_async_db_conn().await?;
+    let s: Subscription = db_transaction::<Subscription, Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            let secret = s.secret.clone();
+            let updated: Subscription =
+                diesel::update(webhook_subscription::dsl::webhook_subscription.find(s.id))
+                    .set((
+                        webhook_subscription::updated_at.eq(Utc::now()),
+                        webhook_subscription::name.eq(&s.name),
+                        webhook_subscription::url.eq(&s.url),
+                        webhook_subscription::events.eq(&s.events),
+                        webhook_subscription::is_disabled.eq(s.is_disabled),
+                    ))
+                    .get_result(c)
+                    .await
+                    .map_err(|e| Error::from_diesel(e, s.id.to_string()))?;
+
+            if secret.is_empty() {
+                return Ok(updated);
+            }
+
+            diesel::update(webhook_subscription::dsl::webhook_subscription.find(s.id))
+                .set(webhook_subscription::secret.eq(&secret))
+                .get_result(c)
+                .await
+                .map_err(|e| Error::from_diesel(e, s.id.to_string()))
+        })
+    })
+    .await?;
+
+    info!(webhook_id = s.id, "Webhook updated");
+    Ok(s)
+}
+
+pub async fn delete(id: i32) -> Result<(), Error> {
+    let ra = diesel::delete(webhook_subscription::dsl::webhook_subscription.find(id))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    if ra == 0 {
+        return Err(Error::NotFound(id.to_string()));
+    }
+    info!(webhook_id = id, "Webhook deleted");
+    Ok(())
+}
+
+pub async fn list(tenant_id: &Uuid) -> Result<Vec<Subscription>, Error> {
+    let items = webhook_subscription::dsl::webhook_subscription
+        .filter(webhook_subscription::dsl::tenant_id.eq(fields::Uuid::from(tenant_id)))
+        .order_by(webhook_subscription::dsl::name)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+    Ok(items)
+}
+
+// Enqueues the event for the enabled subscriptions of the tenant to the event. It returns the
+// number of enqueued deliveries.
+pub async fn enqueue(tenant_id: &Uuid, event: &str, payload: &Value) -> Result<usize, Error> {
+    let mut c = get_async_db_conn().await?;
+
+    let subs: Vec<Subscription> = webhook_subscription::dsl::webhook_subscription
+        .filter(webhook_subscription::dsl::tenant_id.eq(fields::Uuid::from(tenant_id)))
+        .filter(webhook_subscription::dsl::is_disabled.eq(false))
+        .load(&mut c)
+        .await
+        .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+
+    let payload = payload.to_string();
+    let now = Utc::now();
+    let items: Vec<NewDelivery> = subs
+        .iter()
+        .filter(|s| s.is_subscribed(event))
+        .map(|s| NewDelivery {
+            subscription_id: s.id,
+            event,
+            payload: &payload,
+            status: STATUS_PENDING,
+            run_after: now,
+            created_at: now,
+        })
+        .collect();
+    if items.is_empty() {
+        return Ok(0);
+    }
+
+    let mut count = 0;
+    for item in &items {
+        count += diesel::insert_into(webhook_delivery::table)
+            .values(item)
+            .execute(&mut c)
+            .await
+            .map_err(|e| Error::from_diesel(e, tenant_id.to_string()))?;
+    }
+
+    info!(tenant_id = %tenant_id, event = event, count = count, "Webhook event enqueued");
+    Ok(count)
+}
+
+// Claims at most limit pending deliveries which are due. The claimed deliveries are locked until
+// lock_timeout has passed, such that other ChirpStack instances do not deliver them in the
+// meantime.
+pub async fn claim(limit: usize, lock_timeout: Duration) -> anyhow::Result<Vec<Delivery>> {
+    let mut c = get_async_db_conn().await?;
+    db_transaction::<Vec<Delivery>, Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            diesel::sql_query(if cfg!(feature = "sqlite") {
+                r#"
+                    update
+                        webhook_delivery
+                    set
+                        locked_until = ?3,
+                        attempts = attempts + 1
+                    where
+                        id in (
+                            select
+                                id
+                            from
+                                webhook_delivery
+                            where
+                                status = 'PENDING'
+                                and run_after <= ?2
+                                and (locked_until is null or locked_until < ?2)
+                            order by run_after
+                            limit ?1
+                        )
+                    returning *
+                "#
+            } else {
+                r#"
+                    update
+                        webhook_delivery
+                    set
+                        locked_until = $3,
+                        attempts = attempts + 1
+                    where
+                        id in (
+                            select
+                                id
+                            from
+                                webhook_delivery
+                            where
+                                status = 'PENDING'
+                                and run_after <= $2
+                                and (locked_until is null or locked_until < $2)
+                            order by run_after
+                            limit $1
+                            for update skip locked
+                        )
+                    returning *
+                "#
+            })
+            .bind::<Integer, _>(limit as i32)
+            .bind::<fields::sql_types::Timestamptz, _>(Utc::now())
+            .bind::<fields::sql_types::Timestamptz, _>(Utc::now() + lock_timeout)
+            .load(c)
+            .await
+            .map_err(|e| Error::from_diesel(e, "".into()))
+        })
+    })
+    .await
+    .context("Claim webhook deliveries transaction")
+}
+
+pub async fn set_delivered(id: i64, response_status: i32) -> Result<(), Error> {
+    diesel::update(webhook_delivery::dsl::webhook_delivery.find(id))
+        .set((
+            webhook_delivery::status.eq(STATUS_DELIVERED),
+            webhook_delivery::locked_until.eq(None::<DateTime<Utc>>),
+            webhook_delivery::response_status.eq(Some(response_status)),
+            webhook_delivery::last_error.eq(None::<String>),
+            webhook_delivery::delivered_at.eq(Some(Utc::now())),
+        ))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(())
+}
+
+// Releases the failed delivery such that it is retried after the given delay.
+pub async fn retry(
+    id: i64,
+    delay: Duration,
+    response_status: Option<i32>,
+    error: &str,
+) -> Result<(), Error> {
+    diesel::update(webhook_delivery::dsl::webhook_delivery.find(id))
+        .set((
+            webhook_delivery::run_after.eq(Utc::now() + delay),
+            webhook_delivery::locked_until.eq(None::<DateTime<Utc>>),
+            webhook_delivery::response_status.eq(response_status),
+            webhook_delivery::last_error.eq(Some(error)),
+        ))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    info!(id = id, delay = %delay, "Webhook delivery scheduled for retry");
+    Ok(())
+}
+
+// Dead-letters the delivery, it is no longer retried unless it is requeued.
+pub async fn set_dead(id: i64, response_status: Option<i32>, error: &str) -> Result<(), Error> {
+    diesel::update(webhook_delivery::dsl::webhook_delivery.find(id))
+        .set((
+            webhook_delivery::status.eq(STATUS_DEAD),
+            webhook_delivery::locked_until.eq(None::<DateTime<Utc>>),
+            webhook_delivery::response_status.eq(response_status),
+            webhook_delivery::last_error.eq(Some(error)),
+        ))
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    warn!(id = id, error = error, "Webhook delivery dead-lettered");
+    Ok(())
+}
+
+pub async fn get_delivery(id: i64) -> Result<Delivery, Error> {
+    let d = webhook_delivery::dsl::webhook_delivery
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(d)
+}
+
+// Requeues the dead-lettered delivery, the attempts start over.
+pub async fn requeue(id: i64) -> Result<(), Error> {
+    let ra = diesel::update(
+        webhook_delivery::dsl::webhook_delivery
+            .find(id)
+            .filter(webhook_delivery::dsl::status.eq(STATUS_DEAD)),
+    )
+    .set((
+        webhook_delivery::status.eq(STATUS_PENDING),
+        webhook_delivery::attempts.eq(0),
+        webhook_delivery::run_after.eq(Utc::now()),
+    ))
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    if ra == 0 {
+        return Err(Error::Validation(
+            "Only dead-lettered deliveries can be retried".into(),
+        ));
+    }
+    info!(id = id, "Webhook delivery requeued");
+    Ok(())
+}
+
+// Returns the deliveries of the subscription, the most recent first. An empty status slice
+// returns the deliveries of all statuses.
+pub async fn list_deliveries(
+    subscription_id: i32,
+    status: &[&str],
+    limit: i64,
+) -> Result<Vec<Delivery>, Error> {
+    let mut q = webhook_delivery::dsl::webhook_delivery
+        .filter(webhook_delivery::dsl::subscription_id.eq(subscription_id))
+        .into_boxed();
+    if !status.is_empty() {
+        q = q.filter(webhook_delivery::dsl::status.eq_any(status));
+    }
+
+    let items = q
+        .order_by(webhook_delivery::dsl::created_at.desc())
+        .then_order_by(webhook_delivery::dsl::id.desc())
+        .limit(limit)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, subscription_id.to_string()))?;
+    Ok(items)
+}
+
+// Deletes the delivered and dead-lettered deliveries created before the given timestamp.
+pub async fn delete_deliveries_older_than(before: DateTime<Utc>) -> Result<usize, Error> {
+    let ra = diesel::delete(
+        webhook_delivery::dsl::webhook_delivery
+            .filter(webhook_delivery::dsl::status.ne(STATUS_PENDING))
+            .filter(webhook_delivery::dsl::created_at.lt(before)),
+    )
+    .execute(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, "webhook deliveries".to_string()))?;
+    Ok(ra)
+}
+
+// Stores the state of the raised alarm. It returns false when the alarm was already raised.
+async fn raise_state(s: &AlarmState) -> Result<bool, Error> {
+    let ra = diesel::insert_into(alarm_state::table)
+        .values(s)
+        .on_conflict_do_nothing()
+        .execute(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, s.alarm_id.to_string()))?;
+    Ok(ra != 0)
+}
+
+// Removes the state of the raised alarm of the device, it returns None when the alarm was not
+// raised.
+async fn clear_state(
+    alarm_id: i32,
+    alarm_type: &str,
+    dev_eui: &str,
+) -> Result<Option<AlarmState>, Error> {
+    let items: Vec<AlarmState> = diesel::delete(
+        alarm_state::dsl::alarm_state
+            .filter(alarm_state::dsl::alarm_id.eq(alarm_id))
+            .filter(alarm_state::dsl::alarm_type.eq(alarm_type))
+            .filter(alarm_state::dsl::dev_eui.eq(dev_eui)),
+    )
+    .get_results(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+    Ok(items.into_iter().next())
+}
+
+// Acknowledges the raised states of the alarm which were not acknowledged yet.
+async fn acknowledge_states(alarm_id: i32, user_id: &Uuid) -> Result<Vec<AlarmState>, Error> {
+    let items: Vec<AlarmState> = diesel::update(
+        alarm_state::dsl::alarm_state
+            .filter(alarm_state::dsl::alarm_id.eq(alarm_id))
+            .filter(alarm_state::dsl::acknowledged_at.is_null()),
+    )
+    .set((
+        alarm_state::acknowledged_at.eq(Some(Utc::now())),
+        alarm_state::acknowledged_by.eq(Some(fields::Uuid::from(user_id))),
+    ))
+    .get_results(&mut get_async_db_conn().await?)
+    .await
+    .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+    Ok(items)
+}
+
+async fn get_states(alarm_id: i32) -> Result<Vec<AlarmState>, Error> {
+    let items = alarm_state::dsl::alarm_state
+        .filter(alarm_state::dsl::alarm_id.eq(alarm_id))
+        .order_by(alarm_state::dsl::raised_at.desc())
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+    Ok(items)
+}
+
+// Returns the context of the alarm events of the device, None when the device does not belong
+// to a tenant. For a device alarm the dev_eui is the one of the alarm, for a zone alarm it is
+// the device of the zone which raised the alarm.
+pub async fn get_alarm_context(
+    alarm_id: i32,
+    dev_eui: &str,
+) -> Result<Option<AlarmContext>, Error> {
+    // SQLite has no arrays, the zone devices are a JSON array.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+        SELECT
+            a.id AS alarm_id,
+            d.tenant_id,
+            lower(hex(d.dev_eui)) AS dev_eui,
+            d.name AS device_name,
+            z.zone_id,
+            z.zone_name,
+            a.min_treshold AS min_threshold,
+            a.max_treshold AS max_threshold
+        FROM alarm AS a
+        INNER JOIN device AS d ON lower(hex(d.dev_eui)) = ?2
+        LEFT JOIN (
+            SELECT zone_id, zone_name, zd.value AS dev_eui
+            FROM zone, json_each(zone.devices) AS zd
+        ) AS z ON z.dev_eui = '\x' || lower(hex(d.dev_eui))
+        WHERE a.id = ?1 AND d.tenant_id IS NOT NULL
+        LIMIT 1
+        "#
+    } else {
+        r#"
+        SELECT
+            a.id AS alarm_id,
+            d.tenant_id,
+            encode(d.dev_eui, 'hex') AS dev_eui,
+            d.name AS device_name,
+            z.zone_id,
+            z.zone_name::text AS zone_name,
+            a.min_treshold AS min_threshold,
+            a.max_treshold AS max_threshold
+        FROM alarm AS a
+        INNER JOIN device AS d ON d.dev_eui = decode($2, 'hex')
+        LEFT JOIN LATERAL (
+            SELECT zone_id, zone_name FROM zone WHERE d.dev_eui::text = ANY(zone.devices) LIMIT 1
+        ) AS z ON true
+        WHERE a.id = $1 AND d.tenant_id IS NOT NULL
+        "#
+    };
+
+    let items: Vec<AlarmContext> = diesel::sql_query(query)
+        .bind::<Integer, _>(alarm_id)
+        .bind::<Text, _>(dev_eui)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, alarm_id.to_string()))?;
+    Ok(items.into_iter().next())
+}
+
+// Returns the payload of an alarm event. The extra fields are added to the payload, e.g. the
+// escalation level.
+pub fn alarm_payload(
+    event: &str,
+    ctx: &AlarmContext,
+    alarm_type: &str,
+    value: Option<f32>,
+    message: &str,
+    extra: Value,
+) -> Value {
+    let mut pl = json!({
+        "event": event,
+        "time": Utc::now().to_rfc3339(),
+        "tenantId": ctx.tenant_id,
+        "alarm": {
+            "id": ctx.alarm_id,
+            "type": alarm_type,
+            "minThreshold": ctx.min_threshold,
+            "maxThreshold": ctx.max_threshold,
+            "message": message,
+        },
+        "device": {
+            "devEui": ctx.dev_eui,
+            "name": ctx.device_name,
+        },
+        "zone": ctx.zone_id.map(|id| json!({
+            "id": id,
+            "name": ctx.zone_name,
+        })),
+        "value": value,
+    });
+
+    if let (Some(pl), Value::Object(extra)) = (pl.as_object_mut(), extra) {
+        pl.extend(extra);
+    }
+    pl
+}
+
+async fn enqueue_alarm_event(
+    event: &str,
+    alarm_id: i32,
+    alarm_type: &str,
+    dev_eui: &str,
+    value: Option<f32>,
+    message: &str,
+    extra: Value,
+) -> Result<(), Error> {
+    let ctx = match get_alarm_context(alarm_id, dev_eui).await? {
+        Some(v) => v,
+        None => return Ok(()),
+    };
+    let pl = alarm_payload(event, &ctx, alarm_type, value, message, extra);
+    enqueue(&ctx.tenant_id, event, &pl).await?;
+    Ok(())
+}
+
+// The event functions below are called by the alarm and automation handling. Failing to enqueue
+// an event is logged, it must not fail the alarm or automation itself.
+
+// Enqueues the raised event, when the alarm was not already raised for this alarm type.
+pub async fn alarm_raised(
+    alarm_id: i32,
+    alarm_type: &str,
+    dev_eui: &str,
+    value: Option<f32>,
+    message: &str,
+) {
+    let res = async {
+        let raised = raise_state(&AlarmState {
+            alarm_id,
+            alarm_type: alarm_type.to_string(),
+            dev_eui: dev_eui.to_string(),
+            value,
+            message: message.to_string(),
+            raised_at: Utc::now(),
+            acknowledged_at: None,
+            acknowledged_by: None,
+        })
+        .await?;
+        if raised {
+            enqueue_alarm_event(
+                EVENT_ALARM_RAISED,
+                alarm_id,
+                alarm_type,
+                dev_eui,
+                value,
+                message,
+                json!({}),
+            )
+            .await?;
+        }
+        Ok::<(), Error>(())
+    }
+    .await;
+
+    if let Err(e) = res {
+        warn!(alarm_id = alarm_id, dev_eui = %dev_eui, error = %e, "Enqueueing alarm raised event failed");
+    }
+}
+
+// Enqueues the cleared event, when the alarm was raised for this alarm type and device.
+pub async fn alarm_cleared(alarm_id: i32, alarm_type: &str, dev_eui: &str, value: Option<f32>) {
+    let res = async {
+        if let Some(s) = clear_state(alarm_id, alarm_type, dev_eui).await? {
+            enqueue_alarm_event(
+                EVENT_ALARM_CLEARED,
+                alarm_id,
+                alarm_type,
+                dev_eui,
+                value,
+                &s.message,
+                json!({ "raisedAt": s.raised_at.to_rfc3339() }),
+            )
+            .await?;
+        }
+        Ok::<(), Error>(())
+    }
+    .await;
+
+    if let Err(e) = res {
+        warn!(alarm_id = alarm_id, dev_eui = %dev_eui, error = %e, "Enqueueing alarm cleared event failed");
+    }
+}
+
+// Enqueues the acknowledged events of the raised states of the alarm which were not yet
+// acknowledged (one per device for a zone alarm). When none was raised but the alarm was
+// escalated, the event is enqueued for the escalation.
+pub async fn alarm_acknowledged(
+    alarm_id: i32,
+    user_id: &Uuid,
+    escalation: Option<&escalation::AlarmEscalation>,
+) {
+    let res = async {
+        let states = acknowledge_states(alarm_id, user_id).await?;
+        let extra = json!({ "acknowledgedBy": user_id });
+
+        if states.is_empty() {
+            if let Some(e) = escalation {
+                enqueue_alarm_event(
+                    EVENT_ALARM_ACKNOWLEDGED,
+                    alarm_id,
+                    "",
+                    &e.dev_eui,
+                    None,
+                    &e.message,
+                    extra,
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+
+        for s in &states {
+            enqueue_alarm_event(
+                EVENT_ALARM_ACKNOWLEDGED,
+                alarm_id,
+                &s.alarm_type,
+                &s.dev_eui,
+                s.value,
+                &s.message,
+                extra.clone(),
+            )
+            .await?;
+        }
+        Ok::<(), Error>(())
+    }
+    .await;
+
+    if let Err(e) = res {
+        warn!(alarm_id = alarm_id, error = %e, "Enqueueing alarm acknowledged event failed");
+    }
+}
+
+// Enqueues the escalated event for the executed escalation step.
+pub async fn alarm_escalated(e: &escalation::AlarmEscalation, level: i32, channel: &str) {
+    let res = async {
+        let states = get_states(e.alarm_id).await?;
+        let s = states.iter().find(|s| s.dev_eui == e.dev_eui);
+
+        enqueue_alarm_event(
+            EVENT_ALARM_ESCALATED,
+            e.alarm_id,
+            s.map(|s| s.alarm_type.as_str()).unwrap_or_default(),
+            &e.dev_eui,
+            s.and_then(|s| s.value),
+            &e.message,
+            json!({
+                "level": level,
+                "channel": channel,
+            }),
+        )
+        .await?;
+        Ok::<(), Error>(())
+    }
+    .await;
+
+    if let Err(err) = res {
+        warn!(alarm_id = e.alarm_id, error = %err, "Enqueueing alarm escalated event failed");
+    }
+}
+
+// Returns the payload of an automation event.
+pub fn automation_payload(event: &str, rule: &Automation, error: Option<&str>) -> Value {
+    json!({
+        "event": event,
+        "time": Utc::now().to_rfc3339(),
+        "tenantId": rule.tenant_id,
+        "automation": {
+            "id": rule.id,
+            "triggerType": rule.trigger_type,
+            "condition": rule.condition,
+            "action": rule.action,
+        },
+        "sender": {
+            "devEui": rule.sender_sensor,
+            "name": rule.sender_device_name,
+        },
+        "receiver": {
+            "devEui": rule.receiver_sensor,
+            "name": rule.receiver_device_name,
+        },
+        "error": error,
+    })
+}
+
+// Enqueues the executed event of the automation rule, or the failed event in case of an error.
+pub async fn automation_executed(rule: &Automation, error: Option<&str>) {
+    let tenant_id = match rule.tenant_id {
+        Some(v) => v,
+        None => return,
+    };
+    let event = if error.is_some() {
+        EVENT_AUTOMATION_FAILED
+    } else {
+        EVENT_AUTOMATION_EXECUTED
+    };
+
+    if let Err(e) = enqueue(&tenant_id, event, &automation_payload(event, rule, error)).await {
+        warn!(automation_id = rule.id, error = %e, "Enqueueing automation event failed");
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{alarm, tenant};
+    use crate::test;
+
+    #[test]
+    fn test_validate() {
+        let s = Subscription {
+            name: "BMS".into(),
+            url: "https://bms.example.com/events".into(),
+            events: vec![Some(EVENT_ALARM_RAISED.into())].into(),
+            ..Default::default()
+        };
+        assert!(s.validate().is_ok());
+        assert!(s.is_subscribed(EVENT_ALARM_RAISED));
+        assert!(!s.is_subscribed(EVENT_ALARM_CLEARED));
+
+        // invalid url
+        assert!(Subscription {
+            url: "bms.example.com".into(),
+            ..s.clone()
+        }
+        .validate()
+        .is_err());
+
+        // no events
+        assert!(Subscription {
+            events: fields::TextArray::default(),
+            ..s.clone()
+        }
+        .validate()
+        .is_err());
+
+        // unknown event
+        assert!(Subscription {
+            events: vec![Some("alarm.deleted".into())].into(),
+            ..s.clone()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_alarm_payload() {
+        let ctx = AlarmContext {
+            alarm_id: 1,
+            tenant_id: Uuid::nil().into(),
+            dev_eui: "0102030405060708".into(),
+            device_name: "Soğuk oda".into(),
+            zone_id: Some(2),
+            zone_name: Some("Depo".into()),
+            min_threshold: Some(-20.0),
+            max_threshold: Some(4.0),
+        };
+
+        let pl = alarm_payload(
+            EVENT_ALARM_ESCALATED,
+            &ctx,
+            "ısı",
+            Some(6.5),
+            "test",
+            json!({ "level": 2 }),
+        );
+        assert_eq!(EVENT_ALARM_ESCALATED, pl["event"]);
+        assert_eq!(4.0, pl["alarm"]["maxThreshold"]);
+        assert_eq!("0102030405060708", pl["device"]["devEui"]);
+        assert_eq!("Depo", pl["zone"]["name"]);
+        assert_eq!(6.5, pl["value"]);
+        assert_eq!(2, pl["level"]);
+
+        // no zone
+        let pl = alarm_payload(
+            EVENT_ALARM_RAISED,
+            &AlarmContext {
+                zone_id: None,
+                zone_name: None,
+                ..ctx
+            },
+            "door",
+            None,
+            "test",
+            json!({}),
+        );
+        assert!(pl["zone"].is_null());
+        assert!(pl["value"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_webhook() {
+        let _guard = test::prepare().await;
+
+        let t = tenant::test::create_tenant().await;
+        let tenant_id: Uuid = t.id.into();
+
+        let s = create(Subscription {
+            tenant_id: t.id,
+            name: "BMS".into(),
+            url: "https://bms.example.com/events".into(),
+            events: vec![Some(EVENT_ALARM_RAISED.into())].into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        assert_eq!(64, s.secret.len());
+        assert_eq!(vec![s.clone()], list(&tenant_id).await.unwrap());
+
+        // update keeps the secret
+        let s = update(Subscription {
+            events: vec![
+                Some(EVENT_ALARM_RAISED.into()),
+                Some(EVENT_ALARM_CLEARED.into()),
+            ]
+            .into(),
+            secret: "".into(),
+            ..s
+        })
+        .await
+        .unwrap();
+        assert_eq!(2, s.events.len());
+        assert_eq!(64, s.secret.len());
+
+        // enqueue
+        let pl = json!({ "event": EVENT_ALARM_RAISED });
+        assert_eq!(
+            1,
+            enqueue(&tenant_id, EVENT_ALARM_RAISED, &pl).await.unwrap()
+        );
+        assert_eq!(
+            0,
+            enqueue(&tenant_id, EVENT_ALARM_ESCALATED, &pl)
+                .await
+                .unwrap()
+        );
+
+        // claim
+        let items = claim(10, Duration::minutes(1)).await.unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(1, items[0].attempts);
+        assert!(claim(10, Duration::minutes(1)).await.unwrap().is_empty());
+
+        // retry
+        retry(items[0].id, Duration::zero(), Some(500), "error")
+            .await
+            .unwrap();
+        let items = claim(10, Duration::minutes(1)).await.unwrap();
+        assert_eq!(2, items[0].attempts);
+        assert_eq!(Some(500), items[0].response_status);
+
+        // dead-letter and requeue
+        set_dead(items[0].id, None, "timeout").await.unwrap();
+        assert!(claim(10, Duration::minutes(1)).await.unwrap().is_empty());
+        assert_eq!(
+            1,
+            list_deliveries(s.id, &[STATUS_DEAD], 10)
+                .await
+                .unwrap()
+                .len()
+        );
+        requeue(items[0].id).await.unwrap();
+        assert!(requeue(items[0].id).await.is_err());
+
+        // delivered
+        let items = claim(10, Duration::minutes(1)).await.unwrap();
+        assert_eq!(1, items[0].attempts);
+        set_delivered(items[0].id, 200).await.unwrap();
+        let d = get_delivery(items[0].id).await.unwrap();
+        assert_eq!(STATUS_DELIVERED, d.status);
+        assert!(d.delivered_at.is_some());
+        assert_eq!(1, list_deliveries(s.id, &[], 10).await.unwrap().len());
+        assert_eq!(
+            1,
+            delete_deliveries_older_than(Utc::now() + Duration::minutes(1))
+                .await
+                .unwrap()
+        );
+
+        delete(s.id).await.unwrap();
+        assert!(delete(s.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_alarm_state() {
+        let _guard = test::prepare().await;
+
+        let user_id = Uuid::new_v4();
+        let a = alarm::create(
+            alarm::NewAlarm {
+                dev_eui: "0102030405060708".into(),
+                ..Default::default()
+            },
+            vec![],
+            user_id,
+        )
+        .await
+        .unwrap();
+
+        let s = AlarmState {
+            alarm_id: a.id,
+            alarm_type: "ısı".into(),
+            dev_eui: "0102030405060708".into(),
+            value: Some(6.5),
+            message: "test".into(),
+            raised_at: Utc::now(),
+            acknowledged_at: None,
+            acknowledged_by: None,
+        };
+
+        // raised once
+        assert!(raise_state(&s).await.unwrap());
+        assert!(!raise_state(&s).await.unwrap());
+
+        // other alarm type
+        assert!(raise_state(&AlarmState {
+            alarm_type: "no_data".into(),
+            ..s.clone()
+        })
+        .await
+        .unwrap());
+
+        // acknowledge
+        assert_eq!(2, acknowledge_states(a.id, &user_id).await.unwrap().len());
+        assert!(acknowledge_states(a.id, &user_id).await.unwrap().is_empty());
+
+        // same alarm, other device (zone alarm)
+        assert!(raise_state(&AlarmState {
+            dev_eui: "0102030405060709".into(),
+            ..s.clone()
+        })
+        .await
+        .unwrap());
+
+        // clear
+        assert!(clear_state(a.id, "ısı", "0102030405060708")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(clear_state(a.id, "ısı", "0102030405060708")
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(2, get_states(a.id).await.unwrap().len());
+        assert!(clear_state(a.id, "no_data", "0102030405060708")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(clear_state(a.id, "ısı", "0102030405060709")
+            .await
+            .unwrap()
+            .is_some());
+        assert!(get_states(a.id).await.unwrap().is_empty());
+    }
+}
@@ -1,13 +1,13 @@
 use super::error::Error;
-use super::get_async_db_conn;
-use crate::storage::schema_postgres::zone;
-use crate::storage::schema_postgres::zone::dsl;
+use super::{fields, get_async_db_conn};
+use chrono::{DateTime, Utc};
+use crate::storage::schema::zone;
+use crate::storage::schema::zone::dsl;
 use serde::{Deserialize, Deserializer};
 
 use diesel::prelude::*;
 use diesel::sql_query;
 use diesel::sql_types::Nullable;
-use diesel::sql_types::Uuid as SqlUuid;
 use diesel_async::RunQueryDsl;
 use serde::{ Serialize};
 use std::collections::HashMap;
@@ -23,10 +23,11 @@ use serde_json::Value;
 pub struct Zone {
     pub zone_id: i32,
     pub zone_name: Option<String>,
-    pub zone_order: Option<i64>,   // moved up
-    pub content_type: Option<i64>, // moved up
-    pub tanent_id: Option<Uuid>,   // moved down
-    pub devices: Vec<Option<String>>,
+    pub zone_order: Option<i64>,         // moved up
+    pub content_type: Option<i64>,       // moved up
+    pub tanent_id: Option<fields::Uuid>, // moved down
+    pub devices: fields::TextArray,
+    pub site_id: Option<i32>,
 }
 
 #[derive(Insertable, Debug)]
@@ -35,8 +36,9 @@ pub struct NewZone {
     pub zone_name: Option<String>,
     pub zone_order: Option<i64>,
     pub content_type: Option<i64>,
-    pub tanent_id: Option<Uuid>,
-    devices: Vec<Option<String>>,
+    pub tanent_id: Option<fields::Uuid>,
+    devices: fields::TextArray,
+    pub site_id: Option<i32>,
 }
 
 #[derive(AsChangeset, Debug)]
@@ -45,8 +47,9 @@ pub struct UpdateZone {
     pub zone_name: Option<String>,
     pub zone_order: Option<i64>,
     pub content_type: Option<i64>,
-    pub tanent_id: Option<Uuid>,
-    pub devices: Option<Vec<Option<String>>>,
+    pub tanent_id: Option<fields::Uuid>,
+    pub devices: Option<fields::TextArray>,
+    pub site_id: Option<i32>,
 }
 
 #[derive(Debug, QueryableByName)]
@@ -69,6 +72,24 @@ pub struct GetZonesItemSerde {
     pub order: i64,
     pub devices: Vec<ZoneDeviceSerde>,
     pub contentType: i64,
+    #[serde(default)]
+    pub site_id: Option<i64>,
+    #[serde(default)]
+    pub site_name: Option<String>,
+    #[serde(default)]
+    pub aggregates: ZoneAggregatesSerde,
+}
+
+// Aggregates over the latest data of the zone devices, computed by the zone list query.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ZoneAggregatesSerde {
+    pub min_temperature: Option<f32>,
+    pub avg_temperature: Option<f32>,
+    pub max_temperature: Option<f32>,
+    pub open_door_count: i64,
+    pub leak_count: i64,
+    pub offline_device_count: i64,
+    pub active_alarm_count: i64,
 }
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ZoneDeviceSerde {
@@ -85,6 +106,21 @@ pub struct ZoneDeviceSerde {
     pub humadity_calibration: f64,
     pub variables: HashMap<String, String>,
     pub tags: HashMap<String, String>,
+    #[serde(default)]
+    pub is_offline: bool,
+    // Latest value of each measurement kind, this includes the kinds which are not a
+    // device_data_latest column (e.g. the value of a virtual device).
+    #[serde(default)]
+    pub measurements: Vec<ZoneMeasurementSerde>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ZoneMeasurementSerde {
+    pub kind: String,
+    pub unit: String,
+    pub value: Option<f64>,
+    pub text_value: Option<String>,
+    pub time: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -165,10 +201,11 @@ impl Default for Zone {
         Zone {
             zone_id: 0, // or some sentinel like -1, depending on your logic
             zone_name: Some("".to_string()),
-            tanent_id: Some(Uuid::new_v4()),
+            tanent_id: Some(Uuid::new_v4().into()),
             zone_order: Some(0),
             content_type: Some(0),
-            devices: vec![],
+            devices: fields::TextArray::default(),
+            site_id: None,
         }
     }
 }
@@ -176,10 +213,11 @@ impl Default for NewZone {
     fn default() -> Self {
         NewZone {
             zone_name: Some("".to_string()),
-            tanent_id: Some(Uuid::new_v4()),
+            tanent_id: Some(Uuid::new_v4().into()),
             zone_order: Some(0),
             content_type: Some(0),
-            devices: vec![],
+            devices: fields::TextArray::default(),
+            site_id: None,
         }
     }
 }
@@ -192,6 +230,7 @@ pub async fn create(a: Zone) -> Result<Zone, Error> {
         content_type: a.content_type,
         tanent_id: a.tanent_id,
         devices: a.devices,
+        site_id: a.site_id,
     };
 
     let inserted: Zone = diesel::insert_into(zone::table)
@@ -237,14 +276,163 @@ pub async fn delete(zone_id: i32) -> Result<usize, Error> {
     Ok(deleted_rows)
 }
 
+// Returns the zones of the user, this includes the zones assigned directly to the user and the
+// zones of the sites assigned to the user.
 pub async fn list(
     user_id: Option<Uuid>,
     tanent_id: Option<Uuid>,
+    site_id: Option<i32>,
 ) -> Result<ListZoneResponseSerde, Status> {
-    let mut query = r#"
-       WITH device_data_2025 AS (
+    // SQLite has no arrays, the zone devices and user zone / site lists are JSON arrays and the
+    // zone list is built with the SQLite JSON functions.
+    let query = if cfg!(feature = "sqlite") {
+        r#"
+WITH device_state AS (
+    SELECT
+        dev.dev_eui,
+        COALESCE(datetime(dev.last_seen_at) < datetime('now', printf('-%d minutes', COALESCE(
+            (SELECT MIN(NULLIF(a.no_data_time, 0)) FROM alarm AS a
+                WHERE (a.dev_eui = lower(hex(dev.dev_eui)) OR (a.dev_eui = '' AND EXISTS (
+                    SELECT 1 FROM zone AS az, json_each(az.devices) AS azd
+                    WHERE az.zone_id = a.zone_category AND azd.value = '\x' || lower(hex(dev.dev_eui))
+                ))) AND a.no_data = true AND a.is_active = true),
+            NULLIF(dev.data_time, 0) * 2,
+            60
+        ))), true) AS is_offline,
+        (SELECT count(*) FROM alarm AS a WHERE a.dev_eui = lower(hex(dev.dev_eui)) AND a.is_active = true) AS active_alarm_count
+    FROM device AS dev
+),
+device_measurements AS (
+    SELECT
+        ml.dev_eui,
+        json_group_array(json_object('kind', ml.kind, 'unit', ml.unit, 'value', ml.value, 'text_value', ml.text_value, 'time', ml.time)) AS measurements
+    FROM (SELECT * FROM measurement_latest ORDER BY dev_eui, kind) AS ml
+    GROUP BY ml.dev_eui
+),
+device_data_2025 AS (
+    SELECT
+        dev.dev_eui,
+        ds.is_offline,
+        ds.active_alarm_count,
+        COALESCE(dl.air_temperature, dl.temperature1) AS temperature,
+        COALESCE(dl.door_open_status = 1, false) AS door_open,
+        COALESCE(dl.water_leak_status = 1, false) AS leak,
+        json_object(
+            'device_dev_eui', '\x' || lower(hex(dev.dev_eui)),
+            'device_name', dev.name,
+            'device_description', dev.description,
+            'tags', json(dev.tags),
+            'variables', json(dev.variables),
+            'temperature_calibration', dev.temperature_calibration,
+            'humadity_calibration', dev.humadity_calibration,
+            'device_type', dl.device_type_id,
+            'latitude', dev.latitude,
+            'longitude', dev.longitude,
+            'is_offline', json(CASE WHEN ds.is_offline THEN 'true' ELSE 'false' END),
+            'data', CASE WHEN dl.dev_eui IS NULL THEN json_array() ELSE json_array(json_object(
+                'id', dl.id, 'dev_eui', dl.dev_eui, 'device_type_id', dl.device_type_id, 'org_id', dl.org_id,
+                'air_temperature', dl.air_temperature, 'air_humidity', dl.air_humidity,
+                'sol_temperature', dl.sol_temperature, 'sol_water', dl.sol_water,
+                'sol_conduct_soil', dl.sol_conduct_soil, 'submission_date', dl.submission_date,
+                'water_leak_status', dl.water_leak_status, 'water_leak_times', dl.water_leak_times,
+                'last_water_leak_duration', dl.last_water_leak_duration,
+                'door_open_status', dl.door_open_status, 'door_open_times', dl.door_open_times,
+                'last_door_open_duration', dl.last_door_open_duration, 'batv', dl.batv,
+                'ro1_status', dl.ro1_status, 'ro2_status', dl.ro2_status, 'ph_soil', dl.ph_soil,
+                'co2_ppm', dl.co2_ppm, 'tvoc_ppm', dl.tvoc_ppm, 'sensecap_light', dl.sensecap_light,
+                'barometric_pressure', dl.barometric_pressure, 'power', dl.power,
+                'current', dl."current", 'voltage', dl.voltage, 'factor', dl.factor,
+                'power_sum', dl.power_sum, 'status', dl.status,
+                'power_consumption', dl.power_consumption, 'switch1', dl.switch1,
+                'switch2', dl.switch2, 'switch3', dl.switch3, 'switch4', dl.switch4,
+                'switch5', dl.switch5, 'switch6', dl.switch6, 'switch7', dl.switch7,
+                'switch8', dl.switch8, 'adc_1', dl.adc_1, 'adc_2', dl.adc_2, 'adv_1', dl.adv_1,
+                'gpio_in_1', dl.gpio_in_1, 'gpio_in_2', dl.gpio_in_2, 'gpio_in_3', dl.gpio_in_3,
+                'gpio_in_4', dl.gpio_in_4, 'gpio_out_1', dl.gpio_out_1,
+                'gpio_out_2', dl.gpio_out_2, 'distance', dl.distance, 'position', dl.position,
+                'temperature1', dl.temperature1, 'temperature2', dl.temperature2
+            )) END,
+            'measurements', json(COALESCE(dm.measurements, '[]'))
+        ) AS device_json
+    FROM device AS dev
+    INNER JOIN device_state AS ds ON ds.dev_eui = dev.dev_eui
+    LEFT JOIN device_data_latest dl ON dl.dev_eui = lower(hex(dev.dev_eui))
+    LEFT JOIN device_measurements AS dm ON dm.dev_eui = dev.dev_eui
+),
+zone_data AS (
+    SELECT
+        z.zone_id,
+        z.tanent_id,
+        z.site_id,
+        json_object(
+            'zone_id', z.zone_id,
+            'zone_name', z.zone_name,
+            'org_id', z.tanent_id,
+            'order', z.zone_order,
+            'contentType', z.content_type,
+            'site_id', z.site_id,
+            'site_name', s.site_name,
+            'devices', json_group_array(json(dd.device_json)) FILTER (WHERE dd.device_json IS NOT NULL),
+            'aggregates', json_object(
+                'min_temperature', MIN(dd.temperature),
+                'avg_temperature', AVG(dd.temperature),
+                'max_temperature', MAX(dd.temperature),
+                'open_door_count', count(dd.dev_eui) FILTER (WHERE dd.door_open),
+                'leak_count', count(dd.dev_eui) FILTER (WHERE dd.leak),
+                'offline_device_count', count(dd.dev_eui) FILTER (WHERE dd.is_offline),
+                'active_alarm_count', COALESCE(SUM(dd.active_alarm_count), 0)
+                    + (SELECT count(*) FROM alarm AS a WHERE a.dev_eui = '' AND a.zone_category = z.zone_id AND a.is_active = true)
+            )
+        ) AS list
+    FROM zone AS z
+    LEFT JOIN site AS s ON s.site_id = z.site_id
+    LEFT JOIN json_each(z.devices) AS zd
+    LEFT JOIN device AS dev ON '\x' || lower(hex(dev.dev_eui)) = zd.value
+    LEFT JOIN device_data_2025 dd ON dev.dev_eui = dd.dev_eui
+    GROUP BY z.zone_id
+)
+SELECT json_object(
+        'zones', json_group_array(json(zl.list))
+) AS zones
+FROM "user" AS a
+INNER JOIN zone_data zl ON zl.zone_id IN (SELECT value FROM json_each(a.zone_id_list))
+    OR zl.site_id IN (SELECT value FROM json_each(a.site_id_list))
+        WHERE a.id = ?1
+            AND (?2 IS NULL OR zl.tanent_id = ?2)
+            AND (?3 IS NULL OR zl.site_id = ?3)
+        GROUP BY a.id
+    "#
+    } else {
+        r#"
+       WITH device_state AS (
+    SELECT
+        dev.dev_eui,
+        COALESCE(dev.last_seen_at < now() - make_interval(mins => COALESCE(
+            (SELECT MIN(NULLIF(a.no_data_time, 0)) FROM alarm AS a
+                WHERE ('\x' || a.dev_eui = dev.dev_eui::text OR (a.dev_eui = '' AND EXISTS (
+                    SELECT 1 FROM zone AS az WHERE az.zone_id = a.zone_category AND dev.dev_eui::text = ANY(az.devices)
+                ))) AND a.no_data = true AND a.is_active = true),
+            NULLIF(dev.data_time, 0) * 2,
+            60
+        )), true) AS is_offline,
+        (SELECT count(*) FROM alarm AS a WHERE '\x' || a.dev_eui = dev.dev_eui::text AND a.is_active = true) AS active_alarm_count
+    FROM public.device AS dev
+),
+device_measurements AS (
+    SELECT
+        ml.dev_eui,
+        jsonb_agg(jsonb_build_object('kind', ml.kind, 'unit', ml.unit, 'value', ml.value, 'text_value', ml.text_value, 'time', ml.time) ORDER BY ml.kind) AS measurements
+    FROM measurement_latest AS ml
+    GROUP BY ml.dev_eui
+),
+device_data_2025 AS (
     SELECT 
         dev.dev_eui,
+        ds.is_offline,
+        ds.active_alarm_count,
+        MAX(COALESCE(dl.air_temperature, dl.temperature1))::float8 AS temperature,
+        COALESCE(bool_or(dl.door_open_status = 1), false) AS door_open,
+        COALESCE(bool_or(dl.water_leak_status = 1), false) AS leak,
         json_build_object(
             'device_dev_eui', dev.dev_eui,
             'device_name', dev.name,
@@ -256,11 +444,15 @@ pub async fn list(
             'device_type', dl.device_type_id,
 			'latitude', dev.latitude,
 			'longitude', dev.longitude,
-            'data', COALESCE(array_agg(dl) FILTER (WHERE dl.dev_eui IS NOT NULL), ARRAY[]::device_data_latest[])
+            'is_offline', ds.is_offline,
+            'data', COALESCE(array_agg(dl) FILTER (WHERE dl.dev_eui IS NOT NULL), ARRAY[]::device_data_latest[]),
+            'measurements', COALESCE(dm.measurements, '[]'::jsonb)
         ) AS device_json
     FROM public.device AS dev
+    INNER JOIN device_state AS ds ON ds.dev_eui = dev.dev_eui
     LEFT JOIN device_data_latest dl ON dev.dev_eui::text = '\x' || dl.dev_eui
-    GROUP BY dev.dev_eui, dev.name, dev.tags, dev.variables, dev.temperature_calibration, dev.humadity_calibration, dl.device_type_id
+    LEFT JOIN device_measurements AS dm ON dm.dev_eui = dev.dev_eui
+    GROUP BY dev.dev_eui, dev.name, dev.tags, dev.variables, dev.temperature_calibration, dev.humadity_calibration, ds.is_offline, ds.active_alarm_count, dl.device_type_id, dm.measurements
 ),
 zone_data AS (
     SELECT 
@@ -269,52 +461,57 @@ zone_data AS (
         z.tanent_id,
         z.zone_order,
         z.content_type,
+        z.site_id,
         json_build_object(
             'zone_id', z.zone_id,
             'zone_name', z.zone_name,
             'org_id', z.tanent_id,
             'order', z.zone_order,
             'contentType', z.content_type,
-            'devices', COALESCE(array_agg(dd.device_json) FILTER (WHERE dd.device_json IS NOT NULL), ARRAY[]::json[])
+            'site_id', z.site_id,
+            'site_name', s.site_name,
+            'devices', COALESCE(array_agg(dd.device_json) FILTER (WHERE dd.device_json IS NOT NULL), ARRAY[]::json[]),
+            'aggregates', json_build_object(
+                'min_temperature', MIN(dd.temperature),
+                'avg_temperature', AVG(dd.temperature),
+                'max_temperature', MAX(dd.temperature),
+                'open_door_count', count(dd.dev_eui) FILTER (WHERE dd.door_open),
+                'leak_count', count(dd.dev_eui) FILTER (WHERE dd.leak),
+                'offline_device_count', count(dd.dev_eui) FILTER (WHERE dd.is_offline),
+                'active_alarm_count', COALESCE(SUM(dd.active_alarm_count), 0)
+                    + (SELECT count(*) FROM alarm AS a WHERE a.dev_eui = '' AND a.zone_category = z.zone_id AND a.is_active = true)
+            )
         ) AS list
     FROM public.zone AS z
+    LEFT JOIN public.site AS s ON s.site_id = z.site_id
     LEFT JOIN public.device AS dev ON dev.dev_eui::text = ANY(z.devices)
     LEFT JOIN device_data_2025 dd ON dev.dev_eui = dd.dev_eui
-    GROUP BY z.zone_id, z.zone_name, z.tanent_id, z.zone_order, z.content_type
+    GROUP BY z.zone_id, z.zone_name, z.tanent_id, z.zone_order, z.content_type, z.site_id, s.site_name
 )
 SELECT json_build_object( 
         'zones', COALESCE(array_agg(zl.list), ARRAY[]::json[])
 ) AS zones
 FROM public.user AS a 
-INNER JOIN zone_data zl ON zl.zone_id = ANY(a.zone_id_list)
+INNER JOIN zone_data zl ON zl.zone_id = ANY(a.zone_id_list) OR zl.site_id = ANY(a.site_id_list)
         WHERE a.id = $1
-    "#.to_string();
-
-    if tanent_id.is_some() {
-        query.push_str(" AND zl.tanent_id = $2 GROUP BY a.id");
-    } else {
-        query.push_str(" GROUP BY a.id");
-    }
+            AND ($2::uuid IS NULL OR zl.tanent_id = $2)
+            AND ($3::int4 IS NULL OR zl.site_id = $3)
+        GROUP BY a.id
+    "#
+    };
 
     // DB connection
     let conn = &mut get_async_db_conn()
         .await
         .map_err(|e| Status::internal(format!("DB connection failed: {e}")))?;
 
-    let row: ZoneListRow = if let Some(tanent_id) = tanent_id {
-        sql_query(&query)
-            .bind::<Nullable<SqlUuid>, _>(user_id)
-            .bind::<diesel::sql_types::Uuid, _>(tanent_id)
-            .get_result(conn)
-            .await
-            .map_err(|e| Status::internal(format!("Query failed: {e}")))?
-    } else {
-        sql_query(&query)
-            .bind::<Nullable<SqlUuid>, _>(user_id)
-            .get_result(conn)
-            .await
-            .map_err(|e| Status::internal(format!("Query failed: {e}")))?
-    };
+    let row: ZoneListRow = sql_query(query)
+        .bind::<Nullable<fields::sql_types::Uuid>, _>(user_id.map(fields::Uuid::from))
+        .bind::<Nullable<fields::sql_types::Uuid>, _>(tanent_id.map(fields::Uuid::from))
+        .bind::<Nullable<diesel::sql_types::Integer>, _>(site_id)
+        .get_result(conn)
+        .await
+        .map_err(|e| Status::internal(format!("Query failed: {e}")))?;
 
     let parsed: ListZoneResponseSerde = serde_json::from_str(&row.zones)
         .map_err(|e| Status::internal(format!("Failed to deserialize: {e}")))?;
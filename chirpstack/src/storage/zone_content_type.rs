@@ -0,0 +1,263 @@
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use tracing::info;
+
+use super::error::Error;
+use super::schema::{zone, zone_content_type};
+use super::{db_transaction, fields, get_async_db_conn};
+
+// Content type of a zone, e.g. a cold room or a greenhouse. It carries the default alarm
+// thresholds of the zone devices and the measurement kinds which matter for this type of zone.
+#[derive(Queryable, PartialEq, Debug, Clone)]
+#[diesel(table_name = zone_content_type)]
+pub struct ZoneContentType {
+    pub id: i64,
+    pub name: String,
+    pub min_temperature: Option<f64>,
+    pub max_temperature: Option<f64>,
+    pub min_humidity: Option<f64>,
+    pub max_humidity: Option<f64>,
+    pub measurements: fields::TextArray,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for ZoneContentType {
+    fn default() -> Self {
+        let now = Utc::now();
+
+        ZoneContentType {
+            id: 0,
+            name: "".into(),
+            min_temperature: None,
+            max_temperature: None,
+            min_humidity: None,
+            max_humidity: None,
+            measurements: fields::TextArray::default(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[diesel(table_name = zone_content_type)]
+struct NewZoneContentType {
+    name: String,
+    min_temperature: Option<f64>,
+    max_temperature: Option<f64>,
+    min_humidity: Option<f64>,
+    max_humidity: Option<f64>,
+    measurements: fields::TextArray,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl ZoneContentType {
+    fn validate(&self) -> Result<(), Error> {
+        if self.name.trim().is_empty() {
+            return Err(Error::Validation(
+                "Zone content type name cannot be empty".into(),
+            ));
+        }
+        if let (Some(min), Some(max)) = (self.min_temperature, self.max_temperature) {
+            if min > max {
+                return Err(Error::Validation(
+                    "min_temperature must not be greater than max_temperature".into(),
+                ));
+            }
+        }
+        for v in [self.min_humidity, self.max_humidity].into_iter().flatten() {
+            if !(0.0..=100.0).contains(&v) {
+                return Err(Error::Validation(
+                    "Humidity thresholds must be between 0 and 100".into(),
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_humidity, self.max_humidity) {
+            if min > max {
+                return Err(Error::Validation(
+                    "min_humidity must not be greater than max_humidity".into(),
+                ));
+            }
+        }
+        if self
+            .measurements
+            .iter()
+            .any(|m| m.as_deref().unwrap_or_default().trim().is_empty())
+        {
+            return Err(Error::Validation("Measurement kind cannot be empty".into()));
+        }
+        Ok(())
+    }
+}
+
+pub async fn create(ct: ZoneContentType) -> Result<ZoneContentType, Error> {
+    ct.validate()?;
+
+    let now = Utc::now();
+    let new = NewZoneContentType {
+        name: ct.name,
+        min_temperature: ct.min_temperature,
+        max_temperature: ct.max_temperature,
+        min_humidity: ct.min_humidity,
+        max_humidity: ct.max_humidity,
+        measurements: ct.measurements,
+        created_at: now,
+        updated_at: now,
+    };
+
+    let ct: ZoneContentType = diesel::insert_into(zone_content_type::table)
+        .values(&new)
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, new.name.clone()))?;
+    info!(id = ct.id, name = %ct.name, "Zone content type created");
+    Ok(ct)
+}
+
+pub async fn get(id: i64) -> Result<ZoneContentType, Error> {
+    let ct = zone_content_type::dsl::zone_content_type
+        .find(id)
+        .first(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+    Ok(ct)
+}
+
+pub async fn update(ct: ZoneContentType) -> Result<ZoneContentType, Error> {
+    ct.validate()?;
+
+    let ct: ZoneContentType = diesel::update(zone_content_type::dsl::zone_content_type.find(ct.id))
+        .set((
+            zone_content_type::updated_at.eq(Utc::now()),
+            zone_content_type::name.eq(&ct.name),
+            zone_content_type::min_temperature.eq(&ct.min_temperature),
+            zone_content_type::max_temperature.eq(&ct.max_temperature),
+            zone_content_type::min_humidity.eq(&ct.min_humidity),
+            zone_content_type::max_humidity.eq(&ct.max_humidity),
+            zone_content_type::measurements.eq(&ct.measurements),
+        ))
+        .get_result(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, ct.id.to_string()))?;
+    info!(id = ct.id, "Zone content type updated");
+    Ok(ct)
+}
+
+// Deletes the content type. The zones of this content type are kept, but no longer have a
+// content type.
+pub async fn delete(id: i64) -> Result<(), Error> {
+    let mut c = get_async_db_conn().await?;
+    db_transaction::<(), Error, _>(&mut c, |c| {
+        Box::pin(async move {
+            diesel::update(zone::dsl::zone.filter(zone::dsl::content_type.eq(id)))
+                .set(zone::dsl::content_type.eq(None::<i64>))
+                .execute(c)
+                .await
+                .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+
+            let ra = diesel::delete(zone_content_type::dsl::zone_content_type.find(id))
+                .execute(c)
+                .await
+                .map_err(|e| Error::from_diesel(e, id.to_string()))?;
+            if ra == 0 {
+                return Err(Error::NotFound(id.to_string()));
+            }
+
+            Ok(())
+        })
+    })
+    .await?;
+
+    info!(id = id, "Zone content type deleted");
+    Ok(())
+}
+
+pub async fn list() -> Result<Vec<ZoneContentType>, Error> {
+    let items = zone_content_type::dsl::zone_content_type
+        .order_by(zone_content_type::dsl::name)
+        .load(&mut get_async_db_conn().await?)
+        .await
+        .map_err(|e| Error::from_diesel(e, "".into()))?;
+    Ok(items)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::storage::{tenant, zone as zone_storage};
+    use crate::test;
+
+    #[tokio::test]
+    async fn test_zone_content_type() {
+        let _guard = test::prepare().await;
+
+        // the migration adds the default content types
+        let items = list().await.unwrap();
+        assert_eq!(
+            vec!["Cold room", "Freezer", "Greenhouse", "Office", "Water tank"],
+            items.iter().map(|ct| ct.name.as_str()).collect::<Vec<_>>()
+        );
+
+        // create
+        let mut ct = create(ZoneContentType {
+            name: "Wine cellar".into(),
+            min_temperature: Some(10.0),
+            max_temperature: Some(14.0),
+            min_humidity: Some(60.0),
+            max_humidity: Some(80.0),
+            measurements: vec![Some("air_temperature".into()), Some("air_humidity".into())].into(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        // invalid thresholds
+        assert!(create(ZoneContentType {
+            name: "Invalid".into(),
+            min_temperature: Some(14.0),
+            max_temperature: Some(10.0),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+        assert!(create(ZoneContentType {
+            name: "Invalid".into(),
+            max_humidity: Some(120.0),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+
+        // get
+        let ct_get = get(ct.id).await.unwrap();
+        assert_eq!(ct, ct_get);
+
+        // update
+        ct.name = "Wine cellar (red)".into();
+        ct.max_temperature = Some(16.0);
+        let ct_up = update(ct.clone()).await.unwrap();
+        assert_eq!(ct.name, ct_up.name);
+        assert_eq!(Some(16.0), ct_up.max_temperature);
+
+        // delete clears the content type of the zones
+        let t = tenant::test::create_tenant().await;
+        let z = zone_storage::create(zone_storage::Zone {
+            zone_name: Some("Cellar".into()),
+            tanent_id: Some(t.id.into()),
+            content_type: Some(ct.id),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        delete(ct.id).await.unwrap();
+        assert!(delete(ct.id).await.is_err());
+        assert!(get(ct.id).await.is_err());
+        assert_eq!(
+            None,
+            zone_storage::get(&z.zone_id).await.unwrap().content_type
+        );
+    }
+}
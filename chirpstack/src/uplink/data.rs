@@ -1,31 +1,30 @@
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::time::Instant;
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Duration, Local, Utc};
 use tracing::{debug, error, info, span, trace, warn, Instrument, Level};
 
 use super::error::Error;
-use super::{data_fns, filter_rx_info_by_tenant_id, helpers, RelayContext, UplinkFrameSet};
+use super::{data_fns, filter_rx_info_by_tenant_id, helpers, worker, RelayContext, UplinkFrameSet};
 use crate::api::helpers::ToProto;
 use crate::backend::roaming;
 use crate::helpers::errors::PrintFullError;
-use crate::storage::data_uplink::write_data_from_object_json;
 use crate::storage::error::Error as StorageError;
 use crate::storage::{
-    alarm,
     application,
     device::{self, DeviceClass},
-    device_gateway, device_profile, device_queue, fields,
+    device_gateway, device_link, device_profile, device_queue, fields,
     helpers::get_all_device_data,
-    metrics, tenant,
+    metrics, tenant, uplink_task, usage,
 };
-use crate::{codec, config, downlink, integration, maccommand, region, stream};
-use chirpstack_api::{common, integration as integration_pb, internal, stream as stream_pb};
+use crate::{applayer, codec, config, downlink, integration, maccommand, region, stream};
+use chirpstack_api::{common, gw, integration as integration_pb, internal, stream as stream_pb};
 use lrwn::{AES128Key, EUI64};
 // Add this import:
 use crate::storage::get_async_db_conn;
-use crate::storage::postgres::AsyncPgPoolConnection; // or wherever your `get_async_db_conn` lives
+use crate::storage::AsyncDbPoolConnection;
 
 pub struct Data<'a> {
     uplink_frame_set: UplinkFrameSet,
@@ -37,7 +36,7 @@ pub struct Data<'a> {
     // To avoid reimplementing all functions that read or modify the phy_payload, we copy it in a
     // separate value.
     phy_payload: lrwn::PhyPayload,
-    db_conn: &'a mut AsyncPgPoolConnection,
+    db_conn: &'a mut AsyncDbPoolConnection,
 
     reset: bool,
     retransmission: bool,
@@ -53,6 +52,7 @@ pub struct Data<'a> {
     downlink_mac_commands: Vec<lrwn::MACCommandSet>,
     device_gateway_rx_info: Option<internal::DeviceGatewayRxInfo>,
     device_changeset: device::DeviceChangeset,
+    over_quota: bool,
 }
 
 impl<'a> Data<'a> {
@@ -106,7 +106,7 @@ impl<'a> Data<'a> {
 
     pub async fn _handle(
         ufs: UplinkFrameSet,
-        db_conn: &'a mut AsyncPgPoolConnection,
+        db_conn: &'a mut AsyncDbPoolConnection,
     ) -> Result<()> {
         let mut ctx = Data {
             phy_payload: ufs.phy_payload.clone(),
@@ -126,6 +126,7 @@ impl<'a> Data<'a> {
             downlink_mac_commands: Vec::new(),
             device_gateway_rx_info: None,
             device_changeset: Default::default(),
+            over_quota: false,
             db_conn,
         };
 
@@ -158,9 +159,12 @@ impl<'a> Data<'a> {
         ctx.handle_mac_commands().await?;
         if !ctx._is_roaming() {
             ctx.save_device_gateway_rx_info().await?;
+            ctx.save_link_sample().await?;
         }
         ctx.append_meta_data_to_uplink_history()?;
+        ctx.meter_uplink().await?;
         ctx.send_uplink_event().await?;
+        ctx.handle_applayer().await?;
         ctx.detect_and_save_measurements().await?;
         ctx.sync_uplink_f_cnt()?;
         ctx.set_region_config_id()?;
@@ -181,7 +185,7 @@ impl<'a> Data<'a> {
         relay_ctx: RelayContext,
         dev_gw_rx_info: internal::DeviceGatewayRxInfo,
         ufs: UplinkFrameSet,
-        db_conn: &'a mut AsyncPgPoolConnection,
+        db_conn: &'a mut AsyncDbPoolConnection,
     ) -> Result<()> {
         let mut ctx = Data {
             phy_payload: *relay_ctx.req.payload.clone(),
@@ -201,6 +205,7 @@ impl<'a> Data<'a> {
             must_send_downlink: false,
             downlink_mac_commands: Vec::new(),
             device_changeset: Default::default(),
+            over_quota: false,
             db_conn,
         };
 
@@ -217,7 +222,9 @@ impl<'a> Data<'a> {
         ctx.reset_channels_on_adr_ack_req()?;
         ctx.handle_mac_commands().await?;
         ctx.append_meta_data_to_uplink_history_relayed()?;
+        ctx.meter_uplink().await?;
         ctx.send_uplink_event().await?;
+        ctx.handle_applayer().await?;
         ctx.detect_and_save_measurements().await?;
         ctx.sync_uplink_f_cnt()?;
         ctx.set_region_config_id()?;
@@ -857,6 +864,50 @@ impl<'a> Data<'a> {
         Ok(())
     }
 
+    async fn save_link_sample(&self) -> Result<()> {
+        trace!("Saving link sample for device");
+
+        let dev = self.device.as_ref().unwrap();
+        let rx_info = match self
+            .uplink_frame_set
+            .rx_info_set
+            .iter()
+            .max_by(|a, b| a.snr.total_cmp(&b.snr))
+        {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        let margin = match self
+            .uplink_frame_set
+            .tx_info
+            .modulation
+            .as_ref()
+            .and_then(|v| v.parameters.as_ref())
+        {
+            Some(gw::modulation::Parameters::Lora(pl)) => {
+                config::get_required_snr_for_sf(pl.spreading_factor as u8)
+                    .ok()
+                    .map(|required_snr| rx_info.snr - required_snr)
+            }
+            _ => None,
+        };
+
+        device_link::save_sample(&device_link::LinkSample {
+            dev_eui: dev.dev_eui.to_string(),
+            time: helpers::get_rx_timestamp_chrono(&self.uplink_frame_set.rx_info_set),
+            gateway_id: rx_info.gateway_id.clone(),
+            rssi: rx_info.rssi,
+            snr: rx_info.snr,
+            margin,
+            gateway_count: self.uplink_frame_set.rx_info_set.len() as i32,
+        })
+        .await
+        .context("Save link sample")?;
+
+        Ok(())
+    }
+
     fn append_meta_data_to_uplink_history(&mut self) -> Result<()> {
         let ds = self.device.as_mut().unwrap().get_device_session_mut()?;
 
@@ -932,6 +983,16 @@ impl<'a> Data<'a> {
         Ok(())
     }
 
+    async fn meter_uplink(&mut self) -> Result<()> {
+        trace!("Metering uplink");
+
+        let tenant = self.tenant.as_ref().unwrap();
+        self.over_quota = !usage::check_quota(tenant, usage::Quota::Messages).await?;
+        usage::record(&tenant.id, usage::UPLINK_COUNT, 1.0).await?;
+
+        Ok(())
+    }
+
     async fn send_uplink_event(&mut self) -> Result<()> {
         trace!("Sending uplink event");
 
@@ -976,6 +1037,13 @@ impl<'a> Data<'a> {
             region_config_id: self.uplink_frame_set.region_config_id.clone(),
         };
 
+        if self.over_quota {
+            // The uplink is not decoded and not forwarded to the integrations.
+            warn!(dev_eui = %dev.dev_eui, "Tenant is over quota, uplink event dropped");
+            self.uplink_event = Some(pl);
+            return Ok(());
+        }
+
         if !self._is_end_to_end_encrypted() {
             pl.object = match codec::binary_to_struct(
                 dp.payload_codec_runtime,
@@ -1001,26 +1069,20 @@ impl<'a> Data<'a> {
                         if let Some(pb_struct) = v.as_ref() {
                             match serde_json::to_value(pb_struct) {
                                 Ok(json_val) => {
-                                    if let Err(e) = write_data_from_object_json(
+                                    // The sensor-data and alarm processing is done by the
+                                    // uplink workers, see uplink::worker.
+                                    let start = Instant::now();
+                                    if let Err(e) = uplink_task::enqueue(
                                         self.db_conn.as_mut(),
-                                        device,
+                                        device.dev_eui,
+                                        &[uplink_task::KIND_SENSOR_DATA, uplink_task::KIND_ALARM],
                                         &json_val,
                                     )
                                     .await
                                     {
-                                        warn!(error = %e, "Failed to persist decoded object JSON");
-                                    }
-                                    // ✅ Call check_alarm here
-                                    if let Err(e) = alarm::check_alarm(
-                                        self.db_conn.as_mut(),
-                                        self.application.as_ref().unwrap(),
-                                        device,
-                                        &json_val,
-                                    )
-                                    .await
-                                    {
-                                        warn!(error = %e, "Alarm check failed");
+                                        warn!(error = %e, "Enqueueing uplink tasks failed");
                                     }
+                                    worker::observe_step("enqueue", start);
                                 }
                                 Err(e) => {
                                     warn!(error = %e, "Failed to convert pbjson Struct to serde_json::Value");
@@ -1062,6 +1124,28 @@ impl<'a> Data<'a> {
         Ok(())
     }
 
+    async fn handle_applayer(&self) -> Result<()> {
+        trace!("Handling application-layer packages");
+
+        if self._is_end_to_end_encrypted() {
+            return Ok(());
+        }
+
+        let dp = self.device_profile.as_ref().unwrap();
+        let dev = self.device.as_ref().unwrap();
+        let mac = if let lrwn::Payload::MACPayload(pl) = &self.phy_payload.payload {
+            pl
+        } else {
+            return Err(anyhow!("Expected MacPayload"));
+        };
+
+        if let (Some(f_port), Some(lrwn::FRMPayload::Raw(b))) = (mac.f_port, &mac.frm_payload) {
+            applayer::handle_uplink(dev, dp, &self.uplink_frame_set.rx_info_set, f_port, b).await;
+        }
+
+        Ok(())
+    }
+
     async fn detect_and_save_measurements(&mut self) -> Result<()> {
         trace!("Detecing and saving measurements");
 
@@ -1131,6 +1215,7 @@ impl<'a> Data<'a> {
                     fields::Measurement {
                         kind: fields::MeasurementKind::UNKNOWN,
                         name: "".to_string(),
+                        unit: "".to_string(),
                     },
                 );
             }
@@ -35,6 +35,7 @@ pub mod join_fns;
 pub mod join_sns;
 pub mod mesh;
 pub mod stats;
+pub mod worker;
 
 #[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
 struct UplinkLabels {
@@ -9,6 +9,7 @@ use chrono::{DateTime, Local, Utc};
 use tracing::{error, info, span, trace, warn, Instrument, Level};
 
 use crate::gateway::backend as gateway_backend;
+use crate::gateway::watchdog as gateway_watchdog;
 use crate::helpers::errors::PrintFullError;
 use crate::storage::{error::Error, fields, gateway, metrics};
 use crate::{config, region};
@@ -94,6 +95,10 @@ impl Stats {
                 .context("Update gateway state")?,
         );
 
+        gateway_watchdog::handle_stats(&self.gateway_id)
+            .await
+            .context("Handle gateway online state")?;
+
         Ok(())
     }
 
@@ -0,0 +1,250 @@
+use std::time::Instant;
+
+use anyhow::Result;
+use prometheus_client::encoding::EncodeLabelSet;
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::histogram::Histogram;
+use tokio::time::sleep;
+use tracing::{error, info, trace, warn};
+
+use crate::alerting;
+use crate::config;
+use crate::downlink::retry;
+use crate::monitoring::prometheus;
+use crate::storage::error::Error as StorageError;
+use crate::storage::{
+    alarm, application, calibration, data_uplink, device, get_async_db_conn, uplink_task,
+    virtual_device,
+};
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct TaskLabels {
+    kind: String,
+    outcome: String,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct KindLabels {
+    kind: String,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq, EncodeLabelSet, Debug)]
+struct StepLabels {
+    step: String,
+}
+
+lazy_static! {
+    static ref TASK_COUNTER: Family<TaskLabels, Counter> = {
+        let counter = Family::<TaskLabels, Counter>::default();
+        prometheus::register(
+            "uplink_task_count",
+            "Number of processed uplink tasks by kind and outcome",
+            counter.clone(),
+        );
+        counter
+    };
+    static ref TASK_HISTOGRAM: Family<KindLabels, Histogram> = {
+        let histogram = Family::<KindLabels, Histogram>::new_with_constructor(|| {
+            Histogram::new([0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0].into_iter())
+        });
+        prometheus::register(
+            "uplink_task_duration_seconds",
+            "Duration of processing an uplink task by kind",
+            histogram.clone(),
+        );
+        histogram
+    };
+    static ref LAG_HISTOGRAM: Family<KindLabels, Histogram> = {
+        let histogram = Family::<KindLabels, Histogram>::new_with_constructor(|| {
+            Histogram::new([0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0].into_iter())
+        });
+        prometheus::register(
+            "uplink_task_lag_seconds",
+            "Time between enqueueing and processing an uplink task by kind",
+            histogram.clone(),
+        );
+        histogram
+    };
+    // The enqueue step is observed on the uplink path, the other steps (sensor_data, alarm and
+    // virtual_device) by the worker.
+    static ref STEP_HISTOGRAM: Family<StepLabels, Histogram> = {
+        let histogram = Family::<StepLabels, Histogram>::new_with_constructor(|| {
+            Histogram::new(
+                [
+                    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+                ]
+                .into_iter(),
+            )
+        });
+        prometheus::register(
+            "uplink_step_duration_seconds",
+            "Duration of the uplink processing steps by step",
+            histogram.clone(),
+        );
+        histogram
+    };
+}
+
+// Records the duration of the given uplink processing step, started at the given instant.
+pub fn observe_step(step: &str, start: Instant) {
+    STEP_HISTOGRAM
+        .get_or_create(&StepLabels {
+            step: step.to_string(),
+        })
+        .observe(start.elapsed().as_secs_f64());
+}
+
+pub async fn setup() {
+    let conf = config::get();
+    if conf.uplink_worker.workers == 0 {
+        info!("Uplink workers are disabled");
+        return;
+    }
+
+    info!(
+        workers = conf.uplink_worker.workers,
+        "Setting up uplink worker loops"
+    );
+    for _ in 0..conf.uplink_worker.workers {
+        tokio::spawn(async move {
+            worker_loop().await;
+        });
+    }
+}
+
+pub async fn worker_loop() {
+    let conf = config::get();
+
+    loop {
+        trace!("Starting uplink worker loop run");
+
+        match run(conf.uplink_worker.batch_size).await {
+            // Continue with the next batch without waiting as there might be more tasks.
+            Ok(count) if count > 0 => continue,
+            Ok(_) => trace!("Uplink worker loop run completed successfully"),
+            Err(err) => error!(error = %err, "Uplink worker loop run failed"),
+        }
+
+        sleep(conf.uplink_worker.poll_interval).await;
+    }
+}
+
+// Claims a batch of tasks and processes these. It returns the number of claimed tasks.
+async fn run(size: usize) -> Result<usize> {
+    let conf = config::get();
+    let lock_timeout = chrono::Duration::from_std(conf.uplink_worker.lock_timeout)?;
+    let tasks = uplink_task::claim(size, lock_timeout).await?;
+    let count = tasks.len();
+
+    for task in tasks {
+        let start = Instant::now();
+        let res = process(&task).await;
+        TASK_HISTOGRAM
+            .get_or_create(&KindLabels {
+                kind: task.kind.clone(),
+            })
+            .observe(start.elapsed().as_secs_f64());
+
+        let outcome = match res {
+            Ok(()) => {
+                uplink_task::complete(task.id).await?;
+                "success"
+            }
+            Err(err) if task.attempts as u32 >= conf.uplink_worker.max_attempts => {
+                error!(id = task.id, dev_eui = %task.dev_eui, kind = %task.kind, attempts = task.attempts, error = %err, "Uplink task failed, dropping task");
+                uplink_task::complete(task.id).await?;
+                "dropped"
+            }
+            Err(err) => {
+                warn!(id = task.id, dev_eui = %task.dev_eui, kind = %task.kind, attempts = task.attempts, error = %err, "Uplink task failed");
+                let delay = conf.uplink_worker.retry_delay * task.attempts.max(1) as u32;
+                uplink_task::retry(
+                    task.id,
+                    chrono::Duration::from_std(delay)?,
+                    &err.to_string(),
+                )
+                .await?;
+                "retry"
+            }
+        };
+
+        TASK_COUNTER
+            .get_or_create(&TaskLabels {
+                kind: task.kind.clone(),
+                outcome: outcome.to_string(),
+            })
+            .inc();
+    }
+
+    Ok(count)
+}
+
+async fn process(task: &uplink_task::UplinkTask) -> Result<()> {
+    LAG_HISTOGRAM
+        .get_or_create(&KindLabels {
+            kind: task.kind.clone(),
+        })
+        .observe(
+            (chrono::Utc::now() - task.created_at)
+                .to_std()
+                .unwrap_or_default()
+                .as_secs_f64(),
+        );
+
+    let object: serde_json::Value = serde_json::from_str(&task.object)?;
+    let dev = match device::get(&task.dev_eui).await {
+        Ok(v) => v,
+        Err(StorageError::NotFound(_)) => {
+            // The device has been deleted in the meantime.
+            warn!(id = task.id, dev_eui = %task.dev_eui, "Device of uplink task does not exist");
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // The calibration which was effective when the uplink was received, so that a retried task
+    // is not calibrated using a calibration which was created afterwards.
+    let cals = calibration::get_effective(&dev, task.created_at).await?;
+    // The object is calibrated once, before the readings are stored and evaluated by the alarms.
+    let object = cals.apply_object(dev.device_type, &object);
+    let mut conn = get_async_db_conn().await?;
+
+    match task.kind.as_str() {
+        uplink_task::KIND_SENSOR_DATA => {
+            let start = Instant::now();
+            let res =
+                data_uplink::write_data_from_object_json(&dev, &object, task.created_at).await;
+            observe_step("sensor_data", start);
+            res?;
+
+            // The reading has been stored, retrying the task would store it again.
+            if let Err(e) = virtual_device::enqueue_dependants(&mut conn, &dev.dev_eui).await {
+                warn!(dev_eui = %dev.dev_eui, error = %e, "Enqueueing virtual device tasks failed");
+            }
+            Ok(())
+        }
+        uplink_task::KIND_ALARM => {
+            let app = application::get(&dev.application_id.into()).await?;
+
+            let start = Instant::now();
+            let res = alarm::check_alarm(&mut conn, &app, &dev, &object).await;
+            observe_step("alarm", start);
+            res
+        }
+        uplink_task::KIND_VIRTUAL => {
+            let start = Instant::now();
+            let res = virtual_device::process(&mut conn, &dev, task.created_at).await;
+            observe_step("virtual_device", start);
+            res
+        }
+        uplink_task::KIND_DOWNLINK_FAILURE => {
+            let f: retry::DownlinkFailure = serde_json::from_str(&task.object)?;
+            alerting::downlink::handle_failure(&dev, &f).await
+        }
+        kind => {
+            warn!(id = task.id, kind = %kind, "Unknown uplink task kind");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,669 @@
+//! Application Layer Clock Synchronization (TS003) v1.0.0.
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+pub const PACKAGE_IDENTIFIER: u8 = 1;
+pub const PACKAGE_VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Cid {
+    PackageVersionReq,
+    PackageVersionAns,
+    AppTimeReq,
+    AppTimeAns,
+    DeviceAppTimePeriodicityReq,
+    DeviceAppTimePeriodicityAns,
+    ForceDeviceResyncReq,
+}
+
+impl Cid {
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Cid::PackageVersionReq | Cid::PackageVersionAns => 0x00,
+            Cid::AppTimeReq | Cid::AppTimeAns => 0x01,
+            Cid::DeviceAppTimePeriodicityReq | Cid::DeviceAppTimePeriodicityAns => 0x02,
+            Cid::ForceDeviceResyncReq => 0x03,
+        }
+    }
+
+    pub fn from_u8(uplink: bool, v: u8) -> Result<Cid> {
+        Ok(match uplink {
+            true => match v {
+                0x00 => Cid::PackageVersionAns,
+                0x01 => Cid::AppTimeReq,
+                0x02 => Cid::DeviceAppTimePeriodicityAns,
+                _ => return Err(anyhow!("Invalid CID: {}", v)),
+            },
+            false => match v {
+                0x00 => Cid::PackageVersionReq,
+                0x01 => Cid::AppTimeAns,
+                0x02 => Cid::DeviceAppTimePeriodicityReq,
+                0x03 => Cid::ForceDeviceResyncReq,
+                _ => return Err(anyhow!("Invalid CID: {}", v)),
+            },
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Payload {
+    PackageVersionReq,
+    PackageVersionAns(PackageVersionAnsPayload),
+    AppTimeReq(AppTimeReqPayload),
+    AppTimeAns(AppTimeAnsPayload),
+    DeviceAppTimePeriodicityReq(DeviceAppTimePeriodicityReqPayload),
+    DeviceAppTimePeriodicityAns(DeviceAppTimePeriodicityAnsPayload),
+    ForceDeviceResyncReq(ForceDeviceResyncReqPayload),
+}
+
+impl Payload {
+    pub fn cid(&self) -> Cid {
+        match self {
+            Payload::PackageVersionReq => Cid::PackageVersionReq,
+            Payload::PackageVersionAns(_) => Cid::PackageVersionAns,
+            Payload::AppTimeReq(_) => Cid::AppTimeReq,
+            Payload::AppTimeAns(_) => Cid::AppTimeAns,
+            Payload::DeviceAppTimePeriodicityReq(_) => Cid::DeviceAppTimePeriodicityReq,
+            Payload::DeviceAppTimePeriodicityAns(_) => Cid::DeviceAppTimePeriodicityAns,
+            Payload::ForceDeviceResyncReq(_) => Cid::ForceDeviceResyncReq,
+        }
+    }
+
+    // Decodes the first command of the slice.
+    pub fn from_slice(uplink: bool, b: &[u8]) -> Result<Self> {
+        let (pl, _) = Payload::decode(uplink, b)?;
+        Ok(pl)
+    }
+
+    // Decodes all the (concatenated) commands of the slice.
+    pub fn from_slice_multi(uplink: bool, b: &[u8]) -> Result<Vec<Self>> {
+        let mut out = Vec::new();
+        let mut b = b;
+
+        while !b.is_empty() {
+            let (pl, size) = Payload::decode(uplink, b)?;
+            out.push(pl);
+            b = &b[size..];
+        }
+
+        Ok(out)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut out = vec![self.cid().to_u8()];
+
+        match self {
+            Payload::PackageVersionReq => {}
+            Payload::PackageVersionAns(pl) => out.extend_from_slice(&pl.encode()),
+            Payload::AppTimeReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::AppTimeAns(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::DeviceAppTimePeriodicityReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::DeviceAppTimePeriodicityAns(pl) => out.extend_from_slice(&pl.encode()),
+            Payload::ForceDeviceResyncReq(pl) => out.extend_from_slice(&pl.encode()?),
+        }
+
+        Ok(out)
+    }
+
+    // Encodes the given commands into a single (concatenated) payload.
+    pub fn to_vec_multi(payloads: &[Payload]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for pl in payloads {
+            out.extend_from_slice(&pl.to_vec()?);
+        }
+        Ok(out)
+    }
+
+    // Decodes the first command of the slice and returns it together with the number of bytes
+    // consumed (including the CID).
+    fn decode(uplink: bool, b: &[u8]) -> Result<(Self, usize)> {
+        if b.is_empty() {
+            return Err(anyhow!("at least one byte is expected"));
+        }
+
+        let cid = Cid::from_u8(uplink, b[0])?;
+        let size = match cid {
+            Cid::PackageVersionReq => 0,
+            Cid::PackageVersionAns => PackageVersionAnsPayload::SIZE,
+            Cid::AppTimeReq => AppTimeReqPayload::SIZE,
+            Cid::AppTimeAns => AppTimeAnsPayload::SIZE,
+            Cid::DeviceAppTimePeriodicityReq => DeviceAppTimePeriodicityReqPayload::SIZE,
+            Cid::DeviceAppTimePeriodicityAns => DeviceAppTimePeriodicityAnsPayload::SIZE,
+            Cid::ForceDeviceResyncReq => ForceDeviceResyncReqPayload::SIZE,
+        };
+
+        if b.len() < size + 1 {
+            return Err(anyhow!("{:?} expects {} bytes", cid, size + 1));
+        }
+        let b = &b[1..size + 1];
+
+        let pl = match cid {
+            Cid::PackageVersionReq => Payload::PackageVersionReq,
+            Cid::PackageVersionAns => {
+                Payload::PackageVersionAns(PackageVersionAnsPayload::decode(b))
+            }
+            Cid::AppTimeReq => Payload::AppTimeReq(AppTimeReqPayload::decode(b)),
+            Cid::AppTimeAns => Payload::AppTimeAns(AppTimeAnsPayload::decode(b)),
+            Cid::DeviceAppTimePeriodicityReq => {
+                Payload::DeviceAppTimePeriodicityReq(DeviceAppTimePeriodicityReqPayload::decode(b))
+            }
+            Cid::DeviceAppTimePeriodicityAns => {
+                Payload::DeviceAppTimePeriodicityAns(DeviceAppTimePeriodicityAnsPayload::decode(b))
+            }
+            Cid::ForceDeviceResyncReq => {
+                Payload::ForceDeviceResyncReq(ForceDeviceResyncReqPayload::decode(b))
+            }
+        };
+
+        Ok((pl, size + 1))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PackageVersionAnsPayload {
+    pub package_identifier: u8,
+    pub package_version: u8,
+}
+
+impl PackageVersionAnsPayload {
+    const SIZE: usize = 2;
+
+    fn decode(b: &[u8]) -> Self {
+        PackageVersionAnsPayload {
+            package_identifier: b[0],
+            package_version: b[1],
+        }
+    }
+
+    fn encode(&self) -> [u8; 2] {
+        [self.package_identifier, self.package_version]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct AppTimeReqPayload {
+    // Device time in seconds since the GPS epoch (modulo 2^32).
+    pub device_time: u32,
+    pub param: AppTimeReqPayloadParam,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct AppTimeReqPayloadParam {
+    pub token_req: u8,
+    pub ans_required: bool,
+}
+
+impl AppTimeReqPayload {
+    const SIZE: usize = 5;
+
+    fn decode(b: &[u8]) -> Self {
+        AppTimeReqPayload {
+            device_time: u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            param: AppTimeReqPayloadParam {
+                token_req: b[4] & 0x0f,
+                ans_required: b[4] & 0x10 != 0,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 5]> {
+        if self.param.token_req > 15 {
+            return Err(anyhow!("max token_req value is 15"));
+        }
+
+        let mut b = [0; 5];
+        b[0..4].copy_from_slice(&self.device_time.to_le_bytes());
+        b[4] = self.param.token_req;
+        if self.param.ans_required {
+            b[4] |= 0x10;
+        }
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct AppTimeAnsPayload {
+    // Correction (in seconds) to add to the device time.
+    pub time_correction: i32,
+    pub param: AppTimeAnsPayloadParam,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct AppTimeAnsPayloadParam {
+    pub token_ans: u8,
+}
+
+impl AppTimeAnsPayload {
+    const SIZE: usize = 5;
+
+    fn decode(b: &[u8]) -> Self {
+        AppTimeAnsPayload {
+            time_correction: i32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            param: AppTimeAnsPayloadParam {
+                token_ans: b[4] & 0x0f,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 5]> {
+        if self.param.token_ans > 15 {
+            return Err(anyhow!("max token_ans value is 15"));
+        }
+
+        let mut b = [0; 5];
+        b[0..4].copy_from_slice(&self.time_correction.to_le_bytes());
+        b[4] = self.param.token_ans;
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeviceAppTimePeriodicityReqPayload {
+    // The device sends an AppTimeReq every 128 * 2^period seconds.
+    pub period: u8,
+}
+
+impl DeviceAppTimePeriodicityReqPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        DeviceAppTimePeriodicityReqPayload {
+            period: b[0] & 0x0f,
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        if self.period > 15 {
+            return Err(anyhow!("max period value is 15"));
+        }
+        Ok([self.period])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeviceAppTimePeriodicityAnsPayload {
+    pub status: DeviceAppTimePeriodicityAnsPayloadStatus,
+    // Device time in seconds since the GPS epoch (modulo 2^32).
+    pub time: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DeviceAppTimePeriodicityAnsPayloadStatus {
+    pub not_supported: bool,
+}
+
+impl DeviceAppTimePeriodicityAnsPayload {
+    const SIZE: usize = 5;
+
+    fn decode(b: &[u8]) -> Self {
+        DeviceAppTimePeriodicityAnsPayload {
+            status: DeviceAppTimePeriodicityAnsPayloadStatus {
+                not_supported: b[0] & 0x01 != 0,
+            },
+            time: u32::from_le_bytes([b[1], b[2], b[3], b[4]]),
+        }
+    }
+
+    fn encode(&self) -> [u8; 5] {
+        let mut b = [0; 5];
+        if self.status.not_supported {
+            b[0] = 0x01;
+        }
+        b[1..5].copy_from_slice(&self.time.to_le_bytes());
+        b
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ForceDeviceResyncReqPayload {
+    pub force_conf: ForceDeviceResyncReqPayloadForceConf,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct ForceDeviceResyncReqPayloadForceConf {
+    // Number of AppTimeReq the device must send (0 cancels an ongoing resync).
+    pub nb_transmissions: u8,
+}
+
+impl ForceDeviceResyncReqPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        ForceDeviceResyncReqPayload {
+            force_conf: ForceDeviceResyncReqPayloadForceConf {
+                nb_transmissions: b[0] & 0x07,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        if self.force_conf.nb_transmissions > 7 {
+            return Err(anyhow!("max nb_transmissions value is 7"));
+        }
+        Ok([self.force_conf.nb_transmissions])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct CommandTest {
+        name: String,
+        uplink: bool,
+        command: Payload,
+        bytes: Vec<u8>,
+        expected_error: Option<String>,
+    }
+
+    #[test]
+    fn test_package_version_req() {
+        let encode_tests = [CommandTest {
+            name: "encode PackageVersionReq".into(),
+            uplink: false,
+            command: Payload::PackageVersionReq,
+            bytes: vec![0x00],
+            expected_error: None,
+        }];
+
+        let decode_tests = [CommandTest {
+            name: "decode PackageVersionReq".into(),
+            uplink: false,
+            command: Payload::PackageVersionReq,
+            bytes: vec![0x00],
+            expected_error: None,
+        }];
+
+        run_tests_encode(&encode_tests);
+        run_tests_decode(&decode_tests);
+    }
+
+    #[test]
+    fn test_package_version_ans() {
+        let encode_tests = [CommandTest {
+            name: "encode PackageVersionAns".into(),
+            uplink: true,
+            command: Payload::PackageVersionAns(PackageVersionAnsPayload {
+                package_identifier: PACKAGE_IDENTIFIER,
+                package_version: PACKAGE_VERSION,
+            }),
+            bytes: vec![0x00, 0x01, 0x01],
+            expected_error: None,
+        }];
+
+        let decode_tests = [
+            CommandTest {
+                name: "decode PackageVersionAns".into(),
+                uplink: true,
+                command: Payload::PackageVersionAns(PackageVersionAnsPayload {
+                    package_identifier: PACKAGE_IDENTIFIER,
+                    package_version: PACKAGE_VERSION,
+                }),
+                bytes: vec![0x00, 0x01, 0x01],
+                expected_error: None,
+            },
+            CommandTest {
+                name: "decode PackageVersionAns invalid size".into(),
+                uplink: true,
+                command: Payload::PackageVersionReq,
+                bytes: vec![0x00, 0x01],
+                expected_error: Some("PackageVersionAns expects 3 bytes".into()),
+            },
+        ];
+
+        run_tests_encode(&encode_tests);
+        run_tests_decode(&decode_tests);
+    }
+
+    #[test]
+    fn test_app_time_req() {
+        let encode_tests = [
+            CommandTest {
+                name: "encode AppTimeReq".into(),
+                uplink: true,
+                command: Payload::AppTimeReq(AppTimeReqPayload {
+                    device_time: 1024,
+                    param: AppTimeReqPayloadParam {
+                        token_req: 15,
+                        ans_required: true,
+                    },
+                }),
+                bytes: vec![0x01, 0x00, 0x04, 0x00, 0x00, 0x1f],
+                expected_error: None,
+            },
+            CommandTest {
+                name: "encode AppTimeReq invalid token".into(),
+                uplink: true,
+                command: Payload::AppTimeReq(AppTimeReqPayload {
+                    device_time: 1024,
+                    param: AppTimeReqPayloadParam {
+                        token_req: 16,
+                        ans_required: false,
+                    },
+                }),
+                bytes: vec![],
+                expected_error: Some("max token_req value is 15".into()),
+            },
+        ];
+
+        let decode_tests = [CommandTest {
+            name: "decode AppTimeReq".into(),
+            uplink: true,
+            command: Payload::AppTimeReq(AppTimeReqPayload {
+                device_time: 1024,
+                param: AppTimeReqPayloadParam {
+                    token_req: 15,
+                    ans_required: true,
+                },
+            }),
+            bytes: vec![0x01, 0x00, 0x04, 0x00, 0x00, 0x1f],
+            expected_error: None,
+        }];
+
+        run_tests_encode(&encode_tests);
+        run_tests_decode(&decode_tests);
+    }
+
+    #[test]
+    fn test_app_time_ans() {
+        let encode_tests = [CommandTest {
+            name: "encode AppTimeAns".into(),
+            uplink: false,
+            command: Payload::AppTimeAns(AppTimeAnsPayload {
+                time_correction: -2,
+                param: AppTimeAnsPayloadParam { token_ans: 3 },
+            }),
+            bytes: vec![0x01, 0xfe, 0xff, 0xff, 0xff, 0x03],
+            expected_error: None,
+        }];
+
+        let decode_tests = [CommandTest {
+            name: "decode AppTimeAns".into(),
+            uplink: false,
+            command: Payload::AppTimeAns(AppTimeAnsPayload {
+                time_correction: -2,
+                param: AppTimeAnsPayloadParam { token_ans: 3 },
+            }),
+            bytes: vec![0x01, 0xfe, 0xff, 0xff, 0xff, 0x03],
+            expected_error: None,
+        }];
+
+        run_tests_encode(&encode_tests);
+        run_tests_decode(&decode_tests);
+    }
+
+    #[test]
+    fn test_device_app_time_periodicity_req() {
+        let encode_tests = [
+            CommandTest {
+                name: "encode DeviceAppTimePeriodicityReq".into(),
+                uplink: false,
+                command: Payload::DeviceAppTimePeriodicityReq(DeviceAppTimePeriodicityReqPayload {
+                    period: 15,
+                }),
+                bytes: vec![0x02, 0x0f],
+                expected_error: None,
+            },
+            CommandTest {
+                name: "encode DeviceAppTimePeriodicityReq invalid period".into(),
+                uplink: false,
+                command: Payload::DeviceAppTimePeriodicityReq(DeviceAppTimePeriodicityReqPayload {
+                    period: 16,
+                }),
+                bytes: vec![],
+                expected_error: Some("max period value is 15".into()),
+            },
+        ];
+
+        let decode_tests = [CommandTest {
+            name: "decode DeviceAppTimePeriodicityReq".into(),
+            uplink: false,
+            command: Payload::DeviceAppTimePeriodicityReq(DeviceAppTimePeriodicityReqPayload {
+                period: 15,
+            }),
+            bytes: vec![0x02, 0x0f],
+            expected_error: None,
+        }];
+
+        run_tests_encode(&encode_tests);
+        run_tests_decode(&decode_tests);
+    }
+
+    #[test]
+    fn test_device_app_time_periodicity_ans() {
+        let encode_tests = [CommandTest {
+            name: "encode DeviceAppTimePeriodicityAns".into(),
+            uplink: true,
+            command: Payload::DeviceAppTimePeriodicityAns(DeviceAppTimePeriodicityAnsPayload {
+                status: DeviceAppTimePeriodicityAnsPayloadStatus {
+                    not_supported: true,
+                },
+                time: 1024,
+            }),
+            bytes: vec![0x02, 0x01, 0x00, 0x04, 0x00, 0x00],
+            expected_error: None,
+        }];
+
+        let decode_tests = [CommandTest {
+            name: "decode DeviceAppTimePeriodicityAns".into(),
+            uplink: true,
+            command: Payload::DeviceAppTimePeriodicityAns(DeviceAppTimePeriodicityAnsPayload {
+                status: DeviceAppTimePeriodicityAnsPayloadStatus {
+                    not_supported: true,
+                },
+                time: 1024,
+            }),
+            bytes: vec![0x02, 0x01, 0x00, 0x04, 0x00, 0x00],
+            expected_error: None,
+        }];
+
+        run_tests_encode(&encode_tests);
+        run_tests_decode(&decode_tests);
+    }
+
+    #[test]
+    fn test_force_device_resync_req() {
+        let encode_tests = [
+            CommandTest {
+                name: "encode ForceDeviceResyncReq".into(),
+                uplink: false,
+                command: Payload::ForceDeviceResyncReq(ForceDeviceResyncReqPayload {
+                    force_conf: ForceDeviceResyncReqPayloadForceConf {
+                        nb_transmissions: 7,
+                    },
+                }),
+                bytes: vec![0x03, 0x07],
+                expected_error: None,
+            },
+            CommandTest {
+                name: "encode ForceDeviceResyncReq invalid nb_transmissions".into(),
+                uplink: false,
+                command: Payload::ForceDeviceResyncReq(ForceDeviceResyncReqPayload {
+                    force_conf: ForceDeviceResyncReqPayloadForceConf {
+                        nb_transmissions: 8,
+                    },
+                }),
+                bytes: vec![],
+                expected_error: Some("max nb_transmissions value is 7".into()),
+            },
+        ];
+
+        let decode_tests = [CommandTest {
+            name: "decode ForceDeviceResyncReq".into(),
+            uplink: false,
+            command: Payload::ForceDeviceResyncReq(ForceDeviceResyncReqPayload {
+                force_conf: ForceDeviceResyncReqPayloadForceConf {
+                    nb_transmissions: 7,
+                },
+            }),
+            bytes: vec![0x03, 0x07],
+            expected_error: None,
+        }];
+
+        run_tests_encode(&encode_tests);
+        run_tests_decode(&decode_tests);
+    }
+
+    #[test]
+    fn test_multi() {
+        let payloads = vec![
+            Payload::PackageVersionAns(PackageVersionAnsPayload {
+                package_identifier: PACKAGE_IDENTIFIER,
+                package_version: PACKAGE_VERSION,
+            }),
+            Payload::AppTimeReq(AppTimeReqPayload {
+                device_time: 1024,
+                param: AppTimeReqPayloadParam {
+                    token_req: 1,
+                    ans_required: false,
+                },
+            }),
+        ];
+        let bytes = vec![0x00, 0x01, 0x01, 0x01, 0x00, 0x04, 0x00, 0x00, 0x01];
+
+        assert_eq!(bytes, Payload::to_vec_multi(&payloads).unwrap());
+        assert_eq!(payloads, Payload::from_slice_multi(true, &bytes).unwrap());
+
+        // Invalid CID.
+        assert!(Payload::from_slice_multi(true, &[0x00, 0x01, 0x01, 0x03]).is_err());
+    }
+
+    fn run_tests_encode(tests: &[CommandTest]) {
+        for tst in tests {
+            println!("> {}", tst.name);
+            let resp = tst.command.to_vec();
+            if let Some(e) = &tst.expected_error {
+                assert!(resp.is_err());
+                assert_eq!(e, &resp.err().unwrap().to_string());
+            } else {
+                assert_eq!(tst.bytes, resp.unwrap());
+            }
+        }
+    }
+
+    fn run_tests_decode(tests: &[CommandTest]) {
+        for tst in tests {
+            println!("> {}", tst.name);
+            let resp = Payload::from_slice(tst.uplink, &tst.bytes);
+            if let Some(e) = &tst.expected_error {
+                assert!(resp.is_err());
+                assert_eq!(e, &resp.err().unwrap().to_string());
+            } else {
+                assert_eq!(tst.command, resp.unwrap());
+            }
+        }
+    }
+}
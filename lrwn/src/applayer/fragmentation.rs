@@ -0,0 +1,880 @@
+//! Fragmented Data Block Transport (TS004) v1.0.0.
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+pub const PACKAGE_IDENTIFIER: u8 = 3;
+pub const PACKAGE_VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Cid {
+    PackageVersionReq,
+    PackageVersionAns,
+    FragSessionStatusReq,
+    FragSessionStatusAns,
+    FragSessionSetupReq,
+    FragSessionSetupAns,
+    FragSessionDeleteReq,
+    FragSessionDeleteAns,
+    DataFragment,
+}
+
+impl Cid {
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Cid::PackageVersionReq | Cid::PackageVersionAns => 0x00,
+            Cid::FragSessionStatusReq | Cid::FragSessionStatusAns => 0x01,
+            Cid::FragSessionSetupReq | Cid::FragSessionSetupAns => 0x02,
+            Cid::FragSessionDeleteReq | Cid::FragSessionDeleteAns => 0x03,
+            Cid::DataFragment => 0x08,
+        }
+    }
+
+    pub fn from_u8(uplink: bool, v: u8) -> Result<Cid> {
+        Ok(match uplink {
+            true => match v {
+                0x00 => Cid::PackageVersionAns,
+                0x01 => Cid::FragSessionStatusAns,
+                0x02 => Cid::FragSessionSetupAns,
+                0x03 => Cid::FragSessionDeleteAns,
+                _ => return Err(anyhow!("Invalid CID: {}", v)),
+            },
+            false => match v {
+                0x00 => Cid::PackageVersionReq,
+                0x01 => Cid::FragSessionStatusReq,
+                0x02 => Cid::FragSessionSetupReq,
+                0x03 => Cid::FragSessionDeleteReq,
+                0x08 => Cid::DataFragment,
+                _ => return Err(anyhow!("Invalid CID: {}", v)),
+            },
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Payload {
+    PackageVersionReq,
+    PackageVersionAns(PackageVersionAnsPayload),
+    FragSessionStatusReq(FragSessionStatusReqPayload),
+    FragSessionStatusAns(FragSessionStatusAnsPayload),
+    FragSessionSetupReq(FragSessionSetupReqPayload),
+    FragSessionSetupAns(FragSessionSetupAnsPayload),
+    FragSessionDeleteReq(FragSessionDeleteReqPayload),
+    FragSessionDeleteAns(FragSessionDeleteAnsPayload),
+    DataFragment(DataFragmentPayload),
+}
+
+impl Payload {
+    pub fn cid(&self) -> Cid {
+        match self {
+            Payload::PackageVersionReq => Cid::PackageVersionReq,
+            Payload::PackageVersionAns(_) => Cid::PackageVersionAns,
+            Payload::FragSessionStatusReq(_) => Cid::FragSessionStatusReq,
+            Payload::FragSessionStatusAns(_) => Cid::FragSessionStatusAns,
+            Payload::FragSessionSetupReq(_) => Cid::FragSessionSetupReq,
+            Payload::FragSessionSetupAns(_) => Cid::FragSessionSetupAns,
+            Payload::FragSessionDeleteReq(_) => Cid::FragSessionDeleteReq,
+            Payload::FragSessionDeleteAns(_) => Cid::FragSessionDeleteAns,
+            Payload::DataFragment(_) => Cid::DataFragment,
+        }
+    }
+
+    // Decodes the first command of the slice.
+    pub fn from_slice(uplink: bool, b: &[u8]) -> Result<Self> {
+        let (pl, _) = Payload::decode(uplink, b)?;
+        Ok(pl)
+    }
+
+    // Decodes all the (concatenated) commands of the slice. Note that the DataFragment
+    // command always consumes the remaining bytes.
+    pub fn from_slice_multi(uplink: bool, b: &[u8]) -> Result<Vec<Self>> {
+        let mut out = Vec::new();
+        let mut b = b;
+
+        while !b.is_empty() {
+            let (pl, size) = Payload::decode(uplink, b)?;
+            out.push(pl);
+            b = &b[size..];
+        }
+
+        Ok(out)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut out = vec![self.cid().to_u8()];
+
+        match self {
+            Payload::PackageVersionReq => {}
+            Payload::PackageVersionAns(pl) => out.extend_from_slice(&pl.encode()),
+            Payload::FragSessionStatusReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::FragSessionStatusAns(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::FragSessionSetupReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::FragSessionSetupAns(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::FragSessionDeleteReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::FragSessionDeleteAns(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::DataFragment(pl) => out.extend_from_slice(&pl.encode()?),
+        }
+
+        Ok(out)
+    }
+
+    // Encodes the given commands into a single (concatenated) payload.
+    pub fn to_vec_multi(payloads: &[Payload]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for pl in payloads {
+            out.extend_from_slice(&pl.to_vec()?);
+        }
+        Ok(out)
+    }
+
+    // Decodes the first command of the slice and returns it together with the number of bytes
+    // consumed (including the CID).
+    fn decode(uplink: bool, b: &[u8]) -> Result<(Self, usize)> {
+        if b.is_empty() {
+            return Err(anyhow!("at least one byte is expected"));
+        }
+
+        let cid = Cid::from_u8(uplink, b[0])?;
+        let size = match cid {
+            Cid::PackageVersionReq => 0,
+            Cid::PackageVersionAns => PackageVersionAnsPayload::SIZE,
+            Cid::FragSessionStatusReq => FragSessionStatusReqPayload::SIZE,
+            Cid::FragSessionStatusAns => FragSessionStatusAnsPayload::SIZE,
+            Cid::FragSessionSetupReq => FragSessionSetupReqPayload::SIZE,
+            Cid::FragSessionSetupAns => FragSessionSetupAnsPayload::SIZE,
+            Cid::FragSessionDeleteReq => FragSessionDeleteReqPayload::SIZE,
+            Cid::FragSessionDeleteAns => FragSessionDeleteAnsPayload::SIZE,
+            Cid::DataFragment => {
+                if b.len() < 3 {
+                    return Err(anyhow!("{:?} expects at least 3 bytes", cid));
+                }
+                b.len() - 1
+            }
+        };
+
+        if b.len() < size + 1 {
+            return Err(anyhow!("{:?} expects {} bytes", cid, size + 1));
+        }
+        let b = &b[1..size + 1];
+
+        let pl = match cid {
+            Cid::PackageVersionReq => Payload::PackageVersionReq,
+            Cid::PackageVersionAns => {
+                Payload::PackageVersionAns(PackageVersionAnsPayload::decode(b))
+            }
+            Cid::FragSessionStatusReq => {
+                Payload::FragSessionStatusReq(FragSessionStatusReqPayload::decode(b))
+            }
+            Cid::FragSessionStatusAns => {
+                Payload::FragSessionStatusAns(FragSessionStatusAnsPayload::decode(b))
+            }
+            Cid::FragSessionSetupReq => {
+                Payload::FragSessionSetupReq(FragSessionSetupReqPayload::decode(b))
+            }
+            Cid::FragSessionSetupAns => {
+                Payload::FragSessionSetupAns(FragSessionSetupAnsPayload::decode(b))
+            }
+            Cid::FragSessionDeleteReq => {
+                Payload::FragSessionDeleteReq(FragSessionDeleteReqPayload::decode(b))
+            }
+            Cid::FragSessionDeleteAns => {
+                Payload::FragSessionDeleteAns(FragSessionDeleteAnsPayload::decode(b))
+            }
+            Cid::DataFragment => Payload::DataFragment(DataFragmentPayload::decode(b)),
+        };
+
+        Ok((pl, size + 1))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PackageVersionAnsPayload {
+    pub package_identifier: u8,
+    pub package_version: u8,
+}
+
+impl PackageVersionAnsPayload {
+    const SIZE: usize = 2;
+
+    fn decode(b: &[u8]) -> Self {
+        PackageVersionAnsPayload {
+            package_identifier: b[0],
+            package_version: b[1],
+        }
+    }
+
+    fn encode(&self) -> [u8; 2] {
+        [self.package_identifier, self.package_version]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionStatusReqPayload {
+    pub frag_status_req_param: FragSessionStatusReqPayloadFragStatusReqParam,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionStatusReqPayloadFragStatusReqParam {
+    pub frag_index: u8,
+    // When set, all receivers must answer, else only the receivers missing fragments.
+    pub participants: bool,
+}
+
+impl FragSessionStatusReqPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        FragSessionStatusReqPayload {
+            frag_status_req_param: FragSessionStatusReqPayloadFragStatusReqParam {
+                frag_index: (b[0] >> 1) & 0x03,
+                participants: b[0] & 0x01 != 0,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        let p = &self.frag_status_req_param;
+        if p.frag_index > 3 {
+            return Err(anyhow!("max frag_index value is 3"));
+        }
+
+        let mut b = p.frag_index << 1;
+        if p.participants {
+            b |= 0x01;
+        }
+        Ok([b])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionStatusAnsPayload {
+    pub received_and_index: FragSessionStatusAnsPayloadReceivedAndIndex,
+    pub missing_frag: u8,
+    pub status: FragSessionStatusAnsPayloadStatus,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionStatusAnsPayloadReceivedAndIndex {
+    pub frag_index: u8,
+    pub nb_frag_received: u16,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionStatusAnsPayloadStatus {
+    pub not_enough_matrix_memory: bool,
+}
+
+impl FragSessionStatusAnsPayload {
+    const SIZE: usize = 4;
+
+    fn decode(b: &[u8]) -> Self {
+        let received_and_index = u16::from_le_bytes([b[0], b[1]]);
+
+        FragSessionStatusAnsPayload {
+            received_and_index: FragSessionStatusAnsPayloadReceivedAndIndex {
+                frag_index: (received_and_index >> 14) as u8,
+                nb_frag_received: received_and_index & 0x3fff,
+            },
+            missing_frag: b[2],
+            status: FragSessionStatusAnsPayloadStatus {
+                not_enough_matrix_memory: b[3] & 0x01 != 0,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 4]> {
+        let r = &self.received_and_index;
+        if r.frag_index > 3 {
+            return Err(anyhow!("max frag_index value is 3"));
+        }
+        if r.nb_frag_received > 0x3fff {
+            return Err(anyhow!("max nb_frag_received value is 16383"));
+        }
+
+        let received_and_index = (r.nb_frag_received | ((r.frag_index as u16) << 14)).to_le_bytes();
+        Ok([
+            received_and_index[0],
+            received_and_index[1],
+            self.missing_frag,
+            self.status.not_enough_matrix_memory as u8,
+        ])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionSetupReqPayload {
+    pub frag_session: FragSessionSetupReqPayloadFragSession,
+    pub nb_frag: u16,
+    pub frag_size: u8,
+    pub control: FragSessionSetupReqPayloadControl,
+    pub padding: u8,
+    pub descriptor: [u8; 4],
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionSetupReqPayloadFragSession {
+    pub frag_index: u8,
+    // Multicast groups (max 4) allowed as input for this fragmentation session.
+    pub mc_group_bit_mask: [bool; 4],
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionSetupReqPayloadControl {
+    pub fragmentation_matrix: u8,
+    pub block_ack_delay: u8,
+}
+
+impl FragSessionSetupReqPayload {
+    const SIZE: usize = 10;
+
+    fn decode(b: &[u8]) -> Self {
+        FragSessionSetupReqPayload {
+            frag_session: FragSessionSetupReqPayloadFragSession {
+                frag_index: (b[0] >> 4) & 0x03,
+                mc_group_bit_mask: [
+                    b[0] & 0x01 != 0,
+                    b[0] & 0x02 != 0,
+                    b[0] & 0x04 != 0,
+                    b[0] & 0x08 != 0,
+                ],
+            },
+            nb_frag: u16::from_le_bytes([b[1], b[2]]),
+            frag_size: b[3],
+            control: FragSessionSetupReqPayloadControl {
+                fragmentation_matrix: (b[4] >> 3) & 0x07,
+                block_ack_delay: b[4] & 0x07,
+            },
+            padding: b[5],
+            descriptor: [b[6], b[7], b[8], b[9]],
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        if self.frag_session.frag_index > 3 {
+            return Err(anyhow!("max frag_index value is 3"));
+        }
+        if self.control.fragmentation_matrix > 7 {
+            return Err(anyhow!("max fragmentation_matrix value is 7"));
+        }
+        if self.control.block_ack_delay > 7 {
+            return Err(anyhow!("max block_ack_delay value is 7"));
+        }
+
+        let mut frag_session = self.frag_session.frag_index << 4;
+        for (i, v) in self.frag_session.mc_group_bit_mask.iter().enumerate() {
+            if *v {
+                frag_session |= 1 << i;
+            }
+        }
+
+        let mut b = vec![frag_session];
+        b.extend_from_slice(&self.nb_frag.to_le_bytes());
+        b.push(self.frag_size);
+        b.push((self.control.fragmentation_matrix << 3) | self.control.block_ack_delay);
+        b.push(self.padding);
+        b.extend_from_slice(&self.descriptor);
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionSetupAnsPayload {
+    pub frag_index: u8,
+    pub wrong_descriptor: bool,
+    pub frag_session_index_not_supported: bool,
+    pub not_enough_memory: bool,
+    pub encoding_unsupported: bool,
+}
+
+impl FragSessionSetupAnsPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        FragSessionSetupAnsPayload {
+            frag_index: b[0] >> 6,
+            wrong_descriptor: b[0] & 0x08 != 0,
+            frag_session_index_not_supported: b[0] & 0x04 != 0,
+            not_enough_memory: b[0] & 0x02 != 0,
+            encoding_unsupported: b[0] & 0x01 != 0,
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        if self.frag_index > 3 {
+            return Err(anyhow!("max frag_index value is 3"));
+        }
+
+        let mut b = self.frag_index << 6;
+        if self.wrong_descriptor {
+            b |= 0x08;
+        }
+        if self.frag_session_index_not_supported {
+            b |= 0x04;
+        }
+        if self.not_enough_memory {
+            b |= 0x02;
+        }
+        if self.encoding_unsupported {
+            b |= 0x01;
+        }
+        Ok([b])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionDeleteReqPayload {
+    pub param: FragSessionDeleteReqPayloadParam,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionDeleteReqPayloadParam {
+    pub frag_index: u8,
+}
+
+impl FragSessionDeleteReqPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        FragSessionDeleteReqPayload {
+            param: FragSessionDeleteReqPayloadParam {
+                frag_index: b[0] & 0x03,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        if self.param.frag_index > 3 {
+            return Err(anyhow!("max frag_index value is 3"));
+        }
+        Ok([self.param.frag_index])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct FragSessionDeleteAnsPayload {
+    pub frag_index: u8,
+    pub session_does_not_exist: bool,
+}
+
+impl FragSessionDeleteAnsPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        FragSessionDeleteAnsPayload {
+            frag_index: b[0] & 0x03,
+            session_does_not_exist: b[0] & 0x04 != 0,
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        if self.frag_index > 3 {
+            return Err(anyhow!("max frag_index value is 3"));
+        }
+
+        let mut b = self.frag_index;
+        if self.session_does_not_exist {
+            b |= 0x04;
+        }
+        Ok([b])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DataFragmentPayload {
+    pub index_and_n: DataFragmentPayloadIndexAndN,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct DataFragmentPayloadIndexAndN {
+    pub frag_index: u8,
+    // Fragment number, starting at 1 (see encode for the redundancy fragments).
+    pub n: u16,
+}
+
+impl DataFragmentPayload {
+    fn decode(b: &[u8]) -> Self {
+        let index_and_n = u16::from_le_bytes([b[0], b[1]]);
+
+        DataFragmentPayload {
+            index_and_n: DataFragmentPayloadIndexAndN {
+                frag_index: (index_and_n >> 14) as u8,
+                n: index_and_n & 0x3fff,
+            },
+            data: b[2..].to_vec(),
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        if self.index_and_n.frag_index > 3 {
+            return Err(anyhow!("max frag_index value is 3"));
+        }
+        if self.index_and_n.n > 0x3fff {
+            return Err(anyhow!("max n value is 16383"));
+        }
+
+        let index_and_n = self.index_and_n.n | ((self.index_and_n.frag_index as u16) << 14);
+        let mut b = index_and_n.to_le_bytes().to_vec();
+        b.extend_from_slice(&self.data);
+        Ok(b)
+    }
+}
+
+/// Splits the payload into fragments of fragment_size bytes and appends the given number of
+/// redundancy fragments, using the forward error correction code of the TS004 specification.
+///
+/// The payload is padded with 0x00 to a multiple of the fragment_size. The returned fragments
+/// must be sent with fragment number n = index + 1, the first redundancy fragment thus follows
+/// the last uncoded fragment.
+pub fn encode(payload: &[u8], fragment_size: usize, redundancy: usize) -> Result<Vec<Vec<u8>>> {
+    if fragment_size == 0 {
+        return Err(anyhow!("fragment_size must be greater than 0"));
+    }
+    if payload.is_empty() {
+        return Err(anyhow!("payload must not be empty"));
+    }
+
+    let mut payload = payload.to_vec();
+    if payload.len() % fragment_size != 0 {
+        payload.resize(
+            payload.len() + fragment_size - (payload.len() % fragment_size),
+            0x00,
+        );
+    }
+
+    let uncoded: Vec<Vec<u8>> = payload.chunks(fragment_size).map(|c| c.to_vec()).collect();
+    let m = uncoded.len();
+    if m + redundancy > 0x3fff {
+        return Err(anyhow!("max number of fragments is 16383"));
+    }
+
+    let mut out = uncoded.clone();
+    for n in 1..=redundancy {
+        let mut row = vec![0x00; fragment_size];
+        for (i, v) in matrix_line(n, m).iter().enumerate() {
+            if *v {
+                for (j, b) in uncoded[i].iter().enumerate() {
+                    row[j] ^= b;
+                }
+            }
+        }
+        out.push(row);
+    }
+
+    Ok(out)
+}
+
+/// Returns the line of the parity check matrix for redundancy fragment n (starting at 1), for
+/// a payload of m uncoded fragments. Uncoded fragment i contributes to the redundancy fragment
+/// when the i-th value is set.
+pub fn matrix_line(n: usize, m: usize) -> Vec<bool> {
+    let mut line = vec![false; m];
+    let mm = if is_power2(m) { 1 } else { 0 };
+
+    let mut x = 1 + 1001 * n;
+    for _ in 0..(m / 2) {
+        let mut r = 1 << 16;
+        while r >= m {
+            x = prbs23(x);
+            r = x % (m + mm);
+        }
+        line[r] = true;
+    }
+
+    line
+}
+
+fn prbs23(x: usize) -> usize {
+    let b0 = x & 1;
+    let b1 = (x & 32) / 32;
+    (x >> 1) + ((b0 ^ b1) << 22)
+}
+
+fn is_power2(v: usize) -> bool {
+    v != 0 && (v & (v - 1)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run_round_trip(uplink: bool, pl: Payload, bytes: Vec<u8>) {
+        println!("> {:?}", pl.cid());
+        assert_eq!(bytes, pl.to_vec().unwrap());
+        assert_eq!(pl, Payload::from_slice(uplink, &bytes).unwrap());
+    }
+
+    // Recovers the uncoded fragments from the received (n, data) fragments by solving the
+    // parity check equations using Gaussian elimination (GF(2)).
+    fn decode(m: usize, fragments: &[(usize, Vec<u8>)]) -> Option<Vec<Vec<u8>>> {
+        let mut rows: Vec<(Vec<bool>, Vec<u8>)> = fragments
+            .iter()
+            .map(|(n, data)| {
+                let line = if *n <= m {
+                    let mut line = vec![false; m];
+                    line[n - 1] = true;
+                    line
+                } else {
+                    matrix_line(n - m, m)
+                };
+                (line, data.clone())
+            })
+            .collect();
+
+        for col in 0..m {
+            let pivot = (col..rows.len()).find(|i| rows[*i].0[col])?;
+            rows.swap(col, pivot);
+
+            for i in 0..rows.len() {
+                if i != col && rows[i].0[col] {
+                    let (line, data) = rows[col].clone();
+                    for (a, b) in rows[i].0.iter_mut().zip(line.iter()) {
+                        *a ^= b;
+                    }
+                    for (a, b) in rows[i].1.iter_mut().zip(data.iter()) {
+                        *a ^= b;
+                    }
+                }
+            }
+        }
+
+        Some(rows.into_iter().take(m).map(|(_, data)| data).collect())
+    }
+
+    #[test]
+    fn test_package_version() {
+        run_round_trip(false, Payload::PackageVersionReq, vec![0x00]);
+        run_round_trip(
+            true,
+            Payload::PackageVersionAns(PackageVersionAnsPayload {
+                package_identifier: PACKAGE_IDENTIFIER,
+                package_version: PACKAGE_VERSION,
+            }),
+            vec![0x00, 0x03, 0x01],
+        );
+    }
+
+    #[test]
+    fn test_frag_session_status() {
+        run_round_trip(
+            false,
+            Payload::FragSessionStatusReq(FragSessionStatusReqPayload {
+                frag_status_req_param: FragSessionStatusReqPayloadFragStatusReqParam {
+                    frag_index: 3,
+                    participants: true,
+                },
+            }),
+            vec![0x01, 0x07],
+        );
+
+        run_round_trip(
+            true,
+            Payload::FragSessionStatusAns(FragSessionStatusAnsPayload {
+                received_and_index: FragSessionStatusAnsPayloadReceivedAndIndex {
+                    frag_index: 3,
+                    nb_frag_received: 1024,
+                },
+                missing_frag: 128,
+                status: FragSessionStatusAnsPayloadStatus {
+                    not_enough_matrix_memory: true,
+                },
+            }),
+            vec![0x01, 0x00, 0xc4, 0x80, 0x01],
+        );
+    }
+
+    #[test]
+    fn test_frag_session_setup() {
+        run_round_trip(
+            false,
+            Payload::FragSessionSetupReq(FragSessionSetupReqPayload {
+                frag_session: FragSessionSetupReqPayloadFragSession {
+                    frag_index: 3,
+                    mc_group_bit_mask: [true, false, false, false],
+                },
+                nb_frag: 1024,
+                frag_size: 128,
+                control: FragSessionSetupReqPayloadControl {
+                    fragmentation_matrix: 1,
+                    block_ack_delay: 5,
+                },
+                padding: 64,
+                descriptor: [0x01, 0x02, 0x03, 0x04],
+            }),
+            vec![
+                0x02, 0x31, 0x00, 0x04, 0x80, 0x0d, 0x40, 0x01, 0x02, 0x03, 0x04,
+            ],
+        );
+
+        run_round_trip(
+            true,
+            Payload::FragSessionSetupAns(FragSessionSetupAnsPayload {
+                frag_index: 3,
+                wrong_descriptor: true,
+                frag_session_index_not_supported: false,
+                not_enough_memory: true,
+                encoding_unsupported: false,
+            }),
+            vec![0x02, 0xca],
+        );
+    }
+
+    #[test]
+    fn test_frag_session_delete() {
+        run_round_trip(
+            false,
+            Payload::FragSessionDeleteReq(FragSessionDeleteReqPayload {
+                param: FragSessionDeleteReqPayloadParam { frag_index: 3 },
+            }),
+            vec![0x03, 0x03],
+        );
+
+        run_round_trip(
+            true,
+            Payload::FragSessionDeleteAns(FragSessionDeleteAnsPayload {
+                frag_index: 3,
+                session_does_not_exist: true,
+            }),
+            vec![0x03, 0x07],
+        );
+    }
+
+    #[test]
+    fn test_data_fragment() {
+        run_round_trip(
+            false,
+            Payload::DataFragment(DataFragmentPayload {
+                index_and_n: DataFragmentPayloadIndexAndN {
+                    frag_index: 2,
+                    n: 1024,
+                },
+                data: vec![0x01, 0x02, 0x03, 0x04],
+            }),
+            vec![0x08, 0x00, 0x84, 0x01, 0x02, 0x03, 0x04],
+        );
+
+        // The fragment number must fit in 14 bits.
+        assert!(Payload::DataFragment(DataFragmentPayload {
+            index_and_n: DataFragmentPayloadIndexAndN {
+                frag_index: 0,
+                n: 0x4000,
+            },
+            data: vec![],
+        })
+        .to_vec()
+        .is_err());
+
+        assert!(Payload::from_slice(false, &[0x08, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_multi() {
+        let payloads = vec![
+            Payload::FragSessionSetupAns(FragSessionSetupAnsPayload {
+                frag_index: 1,
+                wrong_descriptor: false,
+                frag_session_index_not_supported: false,
+                not_enough_memory: false,
+                encoding_unsupported: false,
+            }),
+            Payload::FragSessionDeleteAns(FragSessionDeleteAnsPayload {
+                frag_index: 1,
+                session_does_not_exist: false,
+            }),
+        ];
+        let bytes = vec![0x02, 0x40, 0x03, 0x01];
+
+        assert_eq!(bytes, Payload::to_vec_multi(&payloads).unwrap());
+        assert_eq!(payloads, Payload::from_slice_multi(true, &bytes).unwrap());
+    }
+
+    #[test]
+    fn test_encode() {
+        let payload: Vec<u8> = (0..100).map(|v| v as u8).collect();
+        let fragments = encode(&payload, 10, 5).unwrap();
+        assert_eq!(15, fragments.len());
+
+        // The uncoded fragments come first.
+        assert_eq!(payload, fragments[..10].concat());
+
+        // Padding.
+        let fragments = encode(&[0x01, 0x02, 0x03], 2, 0).unwrap();
+        assert_eq!(vec![vec![0x01, 0x02], vec![0x03, 0x00]], fragments);
+
+        assert!(encode(&payload, 0, 1).is_err());
+        assert!(encode(&[], 10, 1).is_err());
+    }
+
+    #[test]
+    fn test_matrix_line() {
+        // Every redundancy fragment combines m / 2 (not necessarily distinct) fragments.
+        for m in [1, 2, 7, 8, 16, 33] {
+            for n in 1..10 {
+                let line = matrix_line(n, m);
+                assert_eq!(m, line.len());
+                assert!(line.iter().filter(|v| **v).count() <= m / 2);
+                if m >= 2 {
+                    assert!(line.iter().any(|v| *v));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_recover() {
+        let payload: Vec<u8> = (0..200).map(|v| (v * 7) as u8).collect();
+        let m = 20;
+        let fragments = encode(&payload, 10, 20).unwrap();
+
+        let received = |lost: &[usize]| -> Vec<(usize, Vec<u8>)> {
+            fragments
+                .iter()
+                .enumerate()
+                .map(|(i, f)| (i + 1, f.clone()))
+                .filter(|(n, _)| !lost.contains(n))
+                .collect()
+        };
+
+        // No loss.
+        assert_eq!(payload, decode(m, &received(&[])).unwrap().concat());
+
+        // Lost uncoded fragments are recovered using the redundancy fragments.
+        assert_eq!(
+            payload,
+            decode(m, &received(&[1, 5, 6, 20])).unwrap().concat()
+        );
+
+        // Lost uncoded and redundancy fragments.
+        assert_eq!(
+            payload,
+            decode(m, &received(&[2, 3, 10, 21, 22, 30]))
+                .unwrap()
+                .concat()
+        );
+
+        // Only redundancy fragments are not enough to recover the payload.
+        let only_redundancy: Vec<(usize, Vec<u8>)> = received(&[])
+            .into_iter()
+            .filter(|(n, _)| *n > m)
+            .take(5)
+            .collect();
+        assert!(decode(m, &only_redundancy).is_none());
+    }
+}
@@ -0,0 +1,7 @@
+//! LoRaWAN Application Layer packages.
+//!
+//! Each package is implemented in its own module, the commands of a package are sent and
+//! received on the FPort assigned to the package (see the LA_FPORT_* constants).
+pub mod clocksync;
+pub mod fragmentation;
+pub mod multicastsetup;
@@ -0,0 +1,1060 @@
+//! Remote Multicast Setup (TS005) v1.0.0.
+#[cfg(feature = "crypto")]
+use aes::cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit};
+#[cfg(feature = "crypto")]
+use aes::{Aes128, Block};
+use anyhow::Result;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::{AES128Key, DevAddr};
+
+pub const PACKAGE_IDENTIFIER: u8 = 2;
+pub const PACKAGE_VERSION: u8 = 1;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Cid {
+    PackageVersionReq,
+    PackageVersionAns,
+    McGroupStatusReq,
+    McGroupStatusAns,
+    McGroupSetupReq,
+    McGroupSetupAns,
+    McGroupDeleteReq,
+    McGroupDeleteAns,
+    McClassCSessionReq,
+    McClassCSessionAns,
+    McClassBSessionReq,
+    McClassBSessionAns,
+}
+
+impl Cid {
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            Cid::PackageVersionReq | Cid::PackageVersionAns => 0x00,
+            Cid::McGroupStatusReq | Cid::McGroupStatusAns => 0x01,
+            Cid::McGroupSetupReq | Cid::McGroupSetupAns => 0x02,
+            Cid::McGroupDeleteReq | Cid::McGroupDeleteAns => 0x03,
+            Cid::McClassCSessionReq | Cid::McClassCSessionAns => 0x04,
+            Cid::McClassBSessionReq | Cid::McClassBSessionAns => 0x05,
+        }
+    }
+
+    pub fn from_u8(uplink: bool, v: u8) -> Result<Cid> {
+        Ok(match uplink {
+            true => match v {
+                0x00 => Cid::PackageVersionAns,
+                0x01 => Cid::McGroupStatusAns,
+                0x02 => Cid::McGroupSetupAns,
+                0x03 => Cid::McGroupDeleteAns,
+                0x04 => Cid::McClassCSessionAns,
+                0x05 => Cid::McClassBSessionAns,
+                _ => return Err(anyhow!("Invalid CID: {}", v)),
+            },
+            false => match v {
+                0x00 => Cid::PackageVersionReq,
+                0x01 => Cid::McGroupStatusReq,
+                0x02 => Cid::McGroupSetupReq,
+                0x03 => Cid::McGroupDeleteReq,
+                0x04 => Cid::McClassCSessionReq,
+                0x05 => Cid::McClassBSessionReq,
+                _ => return Err(anyhow!("Invalid CID: {}", v)),
+            },
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub enum Payload {
+    PackageVersionReq,
+    PackageVersionAns(PackageVersionAnsPayload),
+    McGroupStatusReq(McGroupStatusReqPayload),
+    McGroupStatusAns(McGroupStatusAnsPayload),
+    McGroupSetupReq(McGroupSetupReqPayload),
+    McGroupSetupAns(McGroupSetupAnsPayload),
+    McGroupDeleteReq(McGroupDeleteReqPayload),
+    McGroupDeleteAns(McGroupDeleteAnsPayload),
+    McClassCSessionReq(McClassCSessionReqPayload),
+    McClassCSessionAns(McClassCSessionAnsPayload),
+    McClassBSessionReq(McClassBSessionReqPayload),
+    McClassBSessionAns(McClassBSessionAnsPayload),
+}
+
+impl Payload {
+    pub fn cid(&self) -> Cid {
+        match self {
+            Payload::PackageVersionReq => Cid::PackageVersionReq,
+            Payload::PackageVersionAns(_) => Cid::PackageVersionAns,
+            Payload::McGroupStatusReq(_) => Cid::McGroupStatusReq,
+            Payload::McGroupStatusAns(_) => Cid::McGroupStatusAns,
+            Payload::McGroupSetupReq(_) => Cid::McGroupSetupReq,
+            Payload::McGroupSetupAns(_) => Cid::McGroupSetupAns,
+            Payload::McGroupDeleteReq(_) => Cid::McGroupDeleteReq,
+            Payload::McGroupDeleteAns(_) => Cid::McGroupDeleteAns,
+            Payload::McClassCSessionReq(_) => Cid::McClassCSessionReq,
+            Payload::McClassCSessionAns(_) => Cid::McClassCSessionAns,
+            Payload::McClassBSessionReq(_) => Cid::McClassBSessionReq,
+            Payload::McClassBSessionAns(_) => Cid::McClassBSessionAns,
+        }
+    }
+
+    // Decodes the first command of the slice.
+    pub fn from_slice(uplink: bool, b: &[u8]) -> Result<Self> {
+        let (pl, _) = Payload::decode(uplink, b)?;
+        Ok(pl)
+    }
+
+    // Decodes all the (concatenated) commands of the slice.
+    pub fn from_slice_multi(uplink: bool, b: &[u8]) -> Result<Vec<Self>> {
+        let mut out = Vec::new();
+        let mut b = b;
+
+        while !b.is_empty() {
+            let (pl, size) = Payload::decode(uplink, b)?;
+            out.push(pl);
+            b = &b[size..];
+        }
+
+        Ok(out)
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let mut out = vec![self.cid().to_u8()];
+
+        match self {
+            Payload::PackageVersionReq => {}
+            Payload::PackageVersionAns(pl) => out.extend_from_slice(&pl.encode()),
+            Payload::McGroupStatusReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McGroupStatusAns(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McGroupSetupReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McGroupSetupAns(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McGroupDeleteReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McGroupDeleteAns(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McClassCSessionReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McClassCSessionAns(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McClassBSessionReq(pl) => out.extend_from_slice(&pl.encode()?),
+            Payload::McClassBSessionAns(pl) => out.extend_from_slice(&pl.encode()?),
+        }
+
+        Ok(out)
+    }
+
+    // Encodes the given commands into a single (concatenated) payload.
+    pub fn to_vec_multi(payloads: &[Payload]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        for pl in payloads {
+            out.extend_from_slice(&pl.to_vec()?);
+        }
+        Ok(out)
+    }
+
+    // Decodes the first command of the slice and returns it together with the number of bytes
+    // consumed (including the CID).
+    fn decode(uplink: bool, b: &[u8]) -> Result<(Self, usize)> {
+        if b.is_empty() {
+            return Err(anyhow!("at least one byte is expected"));
+        }
+
+        let cid = Cid::from_u8(uplink, b[0])?;
+        let size = match cid {
+            Cid::PackageVersionReq => 0,
+            Cid::PackageVersionAns => PackageVersionAnsPayload::SIZE,
+            Cid::McGroupStatusReq => McGroupStatusReqPayload::SIZE,
+            Cid::McGroupStatusAns => match b.get(1) {
+                Some(v) => McGroupStatusAnsPayload::size(*v),
+                None => 1,
+            },
+            Cid::McGroupSetupReq => McGroupSetupReqPayload::SIZE,
+            Cid::McGroupSetupAns => McGroupSetupAnsPayload::SIZE,
+            Cid::McGroupDeleteReq => McGroupDeleteReqPayload::SIZE,
+            Cid::McGroupDeleteAns => McGroupDeleteAnsPayload::SIZE,
+            Cid::McClassCSessionReq => McClassCSessionReqPayload::SIZE,
+            Cid::McClassBSessionReq => McClassBSessionReqPayload::SIZE,
+            Cid::McClassCSessionAns | Cid::McClassBSessionAns => match b.get(1) {
+                Some(v) => McClassCSessionAnsPayload::size(*v),
+                None => 1,
+            },
+        };
+
+        if b.len() < size + 1 {
+            return Err(anyhow!("{:?} expects {} bytes", cid, size + 1));
+        }
+        let b = &b[1..size + 1];
+
+        let pl = match cid {
+            Cid::PackageVersionReq => Payload::PackageVersionReq,
+            Cid::PackageVersionAns => {
+                Payload::PackageVersionAns(PackageVersionAnsPayload::decode(b))
+            }
+            Cid::McGroupStatusReq => Payload::McGroupStatusReq(McGroupStatusReqPayload::decode(b)),
+            Cid::McGroupStatusAns => Payload::McGroupStatusAns(McGroupStatusAnsPayload::decode(b)),
+            Cid::McGroupSetupReq => Payload::McGroupSetupReq(McGroupSetupReqPayload::decode(b)?),
+            Cid::McGroupSetupAns => Payload::McGroupSetupAns(McGroupSetupAnsPayload::decode(b)),
+            Cid::McGroupDeleteReq => Payload::McGroupDeleteReq(McGroupDeleteReqPayload::decode(b)),
+            Cid::McGroupDeleteAns => Payload::McGroupDeleteAns(McGroupDeleteAnsPayload::decode(b)),
+            Cid::McClassCSessionReq => {
+                Payload::McClassCSessionReq(McClassCSessionReqPayload::decode(b))
+            }
+            Cid::McClassCSessionAns => {
+                Payload::McClassCSessionAns(McClassCSessionAnsPayload::decode(b))
+            }
+            Cid::McClassBSessionReq => {
+                Payload::McClassBSessionReq(McClassBSessionReqPayload::decode(b))
+            }
+            Cid::McClassBSessionAns => {
+                Payload::McClassBSessionAns(McClassBSessionAnsPayload::decode(b))
+            }
+        };
+
+        Ok((pl, size + 1))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct PackageVersionAnsPayload {
+    pub package_identifier: u8,
+    pub package_version: u8,
+}
+
+impl PackageVersionAnsPayload {
+    const SIZE: usize = 2;
+
+    fn decode(b: &[u8]) -> Self {
+        PackageVersionAnsPayload {
+            package_identifier: b[0],
+            package_version: b[1],
+        }
+    }
+
+    fn encode(&self) -> [u8; 2] {
+        [self.package_identifier, self.package_version]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupStatusReqPayload {
+    pub cmd_mask: McGroupStatusReqPayloadCmdMask,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupStatusReqPayloadCmdMask {
+    // Bitmask of the (max 4) multicast groups of which the status is requested.
+    pub req_group_mask: [bool; 4],
+}
+
+impl McGroupStatusReqPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        McGroupStatusReqPayload {
+            cmd_mask: McGroupStatusReqPayloadCmdMask {
+                req_group_mask: decode_group_mask(b[0]),
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        Ok([encode_group_mask(&self.cmd_mask.req_group_mask)])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupStatusAnsPayload {
+    pub status: McGroupStatusAnsPayloadStatus,
+    // The multicast groups set in the ans_group_mask (ordered by group ID).
+    pub items: Vec<McGroupStatusAnsPayloadItem>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupStatusAnsPayloadStatus {
+    pub ans_group_mask: [bool; 4],
+    pub nb_total_groups: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupStatusAnsPayloadItem {
+    pub mc_group_id: u8,
+    pub mc_addr: DevAddr,
+}
+
+impl McGroupStatusAnsPayload {
+    fn size(status: u8) -> usize {
+        1 + 5 * (status & 0x0f).count_ones() as usize
+    }
+
+    fn decode(b: &[u8]) -> Self {
+        let mut items = Vec::new();
+        for item in b[1..].chunks(5) {
+            items.push(McGroupStatusAnsPayloadItem {
+                mc_group_id: item[0] & 0x03,
+                mc_addr: DevAddr::from_le_bytes([item[1], item[2], item[3], item[4]]),
+            });
+        }
+
+        McGroupStatusAnsPayload {
+            status: McGroupStatusAnsPayloadStatus {
+                ans_group_mask: decode_group_mask(b[0]),
+                nb_total_groups: (b[0] >> 4) & 0x07,
+            },
+            items,
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        if self.status.nb_total_groups > 4 {
+            return Err(anyhow!("max nb_total_groups value is 4"));
+        }
+
+        let mask = encode_group_mask(&self.status.ans_group_mask);
+        if mask.count_ones() as usize != self.items.len() {
+            return Err(anyhow!("number of items must match ans_group_mask"));
+        }
+
+        let mut b = vec![mask | (self.status.nb_total_groups << 4)];
+        for item in &self.items {
+            if item.mc_group_id > 3 {
+                return Err(anyhow!("max mc_group_id value is 3"));
+            }
+            b.push(item.mc_group_id);
+            b.extend_from_slice(&item.mc_addr.to_le_bytes());
+        }
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupSetupReqPayload {
+    pub mc_group_id_header: McGroupSetupReqPayloadMcGroupIdHeader,
+    pub mc_addr: DevAddr,
+    // The McKey, encrypted using the McKEKey (see encrypt_mc_key).
+    pub mc_key_encrypted: AES128Key,
+    pub min_mc_f_count: u32,
+    pub max_mc_f_count: u32,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupSetupReqPayloadMcGroupIdHeader {
+    pub mc_group_id: u8,
+}
+
+impl McGroupSetupReqPayload {
+    const SIZE: usize = 29;
+
+    fn decode(b: &[u8]) -> Result<Self> {
+        Ok(McGroupSetupReqPayload {
+            mc_group_id_header: McGroupSetupReqPayloadMcGroupIdHeader {
+                mc_group_id: b[0] & 0x03,
+            },
+            mc_addr: DevAddr::from_le_bytes([b[1], b[2], b[3], b[4]]),
+            mc_key_encrypted: AES128Key::from_slice(&b[5..21])?,
+            min_mc_f_count: u32::from_le_bytes([b[21], b[22], b[23], b[24]]),
+            max_mc_f_count: u32::from_le_bytes([b[25], b[26], b[27], b[28]]),
+        })
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        if self.mc_group_id_header.mc_group_id > 3 {
+            return Err(anyhow!("max mc_group_id value is 3"));
+        }
+
+        let mut b = vec![self.mc_group_id_header.mc_group_id];
+        b.extend_from_slice(&self.mc_addr.to_le_bytes());
+        b.extend_from_slice(&self.mc_key_encrypted.to_bytes());
+        b.extend_from_slice(&self.min_mc_f_count.to_le_bytes());
+        b.extend_from_slice(&self.max_mc_f_count.to_le_bytes());
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupSetupAnsPayload {
+    pub mc_group_id_header: McGroupSetupAnsPayloadMcGroupIdHeader,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupSetupAnsPayloadMcGroupIdHeader {
+    pub mc_group_id: u8,
+    pub id_error: bool,
+}
+
+impl McGroupSetupAnsPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        McGroupSetupAnsPayload {
+            mc_group_id_header: McGroupSetupAnsPayloadMcGroupIdHeader {
+                mc_group_id: b[0] & 0x03,
+                id_error: b[0] & 0x04 != 0,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        if self.mc_group_id_header.mc_group_id > 3 {
+            return Err(anyhow!("max mc_group_id value is 3"));
+        }
+
+        let mut b = self.mc_group_id_header.mc_group_id;
+        if self.mc_group_id_header.id_error {
+            b |= 0x04;
+        }
+        Ok([b])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupDeleteReqPayload {
+    pub mc_group_id_header: McGroupDeleteReqPayloadMcGroupIdHeader,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupDeleteReqPayloadMcGroupIdHeader {
+    pub mc_group_id: u8,
+}
+
+impl McGroupDeleteReqPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        McGroupDeleteReqPayload {
+            mc_group_id_header: McGroupDeleteReqPayloadMcGroupIdHeader {
+                mc_group_id: b[0] & 0x03,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        if self.mc_group_id_header.mc_group_id > 3 {
+            return Err(anyhow!("max mc_group_id value is 3"));
+        }
+        Ok([self.mc_group_id_header.mc_group_id])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupDeleteAnsPayload {
+    pub mc_group_id_header: McGroupDeleteAnsPayloadMcGroupIdHeader,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McGroupDeleteAnsPayloadMcGroupIdHeader {
+    pub mc_group_id: u8,
+    pub mc_group_undefined: bool,
+}
+
+impl McGroupDeleteAnsPayload {
+    const SIZE: usize = 1;
+
+    fn decode(b: &[u8]) -> Self {
+        McGroupDeleteAnsPayload {
+            mc_group_id_header: McGroupDeleteAnsPayloadMcGroupIdHeader {
+                mc_group_id: b[0] & 0x03,
+                mc_group_undefined: b[0] & 0x04 != 0,
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<[u8; 1]> {
+        if self.mc_group_id_header.mc_group_id > 3 {
+            return Err(anyhow!("max mc_group_id value is 3"));
+        }
+
+        let mut b = self.mc_group_id_header.mc_group_id;
+        if self.mc_group_id_header.mc_group_undefined {
+            b |= 0x04;
+        }
+        Ok([b])
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassCSessionReqPayload {
+    pub mc_group_id_header: McClassCSessionReqPayloadMcGroupIdHeader,
+    // Start of the session, in seconds since the GPS epoch (modulo 2^32).
+    pub session_time: u32,
+    pub session_time_out: McClassCSessionReqPayloadSessionTimeOut,
+    // Downlink frequency (Hz), encoded in steps of 100 Hz.
+    pub dl_frequency: u32,
+    pub dr: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassCSessionReqPayloadMcGroupIdHeader {
+    pub mc_group_id: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassCSessionReqPayloadSessionTimeOut {
+    // The session lasts 2^time_out seconds.
+    pub time_out: u8,
+}
+
+impl McClassCSessionReqPayload {
+    const SIZE: usize = 10;
+
+    fn decode(b: &[u8]) -> Self {
+        McClassCSessionReqPayload {
+            mc_group_id_header: McClassCSessionReqPayloadMcGroupIdHeader {
+                mc_group_id: b[0] & 0x03,
+            },
+            session_time: u32::from_le_bytes([b[1], b[2], b[3], b[4]]),
+            session_time_out: McClassCSessionReqPayloadSessionTimeOut {
+                time_out: b[5] & 0x0f,
+            },
+            dl_frequency: decode_frequency(&b[6..9]),
+            dr: b[9],
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        if self.mc_group_id_header.mc_group_id > 3 {
+            return Err(anyhow!("max mc_group_id value is 3"));
+        }
+        if self.session_time_out.time_out > 15 {
+            return Err(anyhow!("max time_out value is 15"));
+        }
+
+        let mut b = vec![self.mc_group_id_header.mc_group_id];
+        b.extend_from_slice(&self.session_time.to_le_bytes());
+        b.push(self.session_time_out.time_out);
+        b.extend_from_slice(&encode_frequency(self.dl_frequency)?);
+        b.push(self.dr);
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassCSessionAnsPayload {
+    pub status_and_mc_group_id: McClassCSessionAnsPayloadStatusAndMcGroupId,
+    // Seconds until the start of the session, only set when there are no errors.
+    pub time_to_start: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassCSessionAnsPayloadStatusAndMcGroupId {
+    pub mc_group_id: u8,
+    pub dr_error: bool,
+    pub freq_error: bool,
+    pub mc_group_undefined: bool,
+}
+
+impl McClassCSessionAnsPayload {
+    fn size(status: u8) -> usize {
+        if status & 0x1c == 0 {
+            4
+        } else {
+            1
+        }
+    }
+
+    fn decode(b: &[u8]) -> Self {
+        McClassCSessionAnsPayload {
+            status_and_mc_group_id: McClassCSessionAnsPayloadStatusAndMcGroupId {
+                mc_group_id: b[0] & 0x03,
+                dr_error: b[0] & 0x04 != 0,
+                freq_error: b[0] & 0x08 != 0,
+                mc_group_undefined: b[0] & 0x10 != 0,
+            },
+            time_to_start: if b.len() == 4 {
+                Some(u32::from_le_bytes([b[1], b[2], b[3], 0x00]))
+            } else {
+                None
+            },
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let s = &self.status_and_mc_group_id;
+        if s.mc_group_id > 3 {
+            return Err(anyhow!("max mc_group_id value is 3"));
+        }
+
+        let mut status = s.mc_group_id;
+        if s.dr_error {
+            status |= 0x04;
+        }
+        if s.freq_error {
+            status |= 0x08;
+        }
+        if s.mc_group_undefined {
+            status |= 0x10;
+        }
+
+        let mut b = vec![status];
+        match (status & 0x1c == 0, self.time_to_start) {
+            (true, Some(v)) => {
+                if v >= 1 << 24 {
+                    return Err(anyhow!("max time_to_start value is 16777215"));
+                }
+                b.extend_from_slice(&v.to_le_bytes()[0..3]);
+            }
+            (true, None) => {
+                return Err(anyhow!(
+                    "time_to_start must be set when there are no errors"
+                ))
+            }
+            (false, Some(_)) => {
+                return Err(anyhow!(
+                    "time_to_start must not be set when there are errors"
+                ))
+            }
+            (false, None) => {}
+        }
+        Ok(b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassBSessionReqPayload {
+    pub mc_group_id_header: McClassBSessionReqPayloadMcGroupIdHeader,
+    // Start of the session, in seconds since the GPS epoch (modulo 2^32).
+    pub session_time: u32,
+    pub time_out_periodicity: McClassBSessionReqPayloadTimeOutPeriodicity,
+    // Downlink frequency (Hz), encoded in steps of 100 Hz.
+    pub dl_frequency: u32,
+    pub dr: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassBSessionReqPayloadMcGroupIdHeader {
+    pub mc_group_id: u8,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct McClassBSessionReqPayloadTimeOutPeriodicity {
+    // The session lasts 2^time_out beacon periods.
+    pub time_out: u8,
+    // The ping-slot period is 2^periodicity seconds.
+    pub periodicity: u8,
+}
+
+impl McClassBSessionReqPayload {
+    const SIZE: usize = 10;
+
+    fn decode(b: &[u8]) -> Self {
+        McClassBSessionReqPayload {
+            mc_group_id_header: McClassBSessionReqPayloadMcGroupIdHeader {
+                mc_group_id: b[0] & 0x03,
+            },
+            session_time: u32::from_le_bytes([b[1], b[2], b[3], b[4]]),
+            time_out_periodicity: McClassBSessionReqPayloadTimeOutPeriodicity {
+                time_out: b[5] & 0x0f,
+                periodicity: (b[5] >> 4) & 0x07,
+            },
+            dl_frequency: decode_frequency(&b[6..9]),
+            dr: b[9],
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        if self.mc_group_id_header.mc_group_id > 3 {
+            return Err(anyhow!("max mc_group_id value is 3"));
+        }
+        if self.time_out_periodicity.time_out > 15 {
+            return Err(anyhow!("max time_out value is 15"));
+        }
+        if self.time_out_periodicity.periodicity > 7 {
+            return Err(anyhow!("max periodicity value is 7"));
+        }
+
+        let mut b = vec![self.mc_group_id_header.mc_group_id];
+        b.extend_from_slice(&self.session_time.to_le_bytes());
+        b.push(self.time_out_periodicity.time_out | (self.time_out_periodicity.periodicity << 4));
+        b.extend_from_slice(&encode_frequency(self.dl_frequency)?);
+        b.push(self.dr);
+        Ok(b)
+    }
+}
+
+// The McClassBSessionAns uses the same layout as the McClassCSessionAns.
+pub type McClassBSessionAnsPayload = McClassCSessionAnsPayload;
+
+fn decode_group_mask(b: u8) -> [bool; 4] {
+    [b & 0x01 != 0, b & 0x02 != 0, b & 0x04 != 0, b & 0x08 != 0]
+}
+
+fn encode_group_mask(mask: &[bool; 4]) -> u8 {
+    mask.iter()
+        .enumerate()
+        .fold(0, |acc, (i, v)| if *v { acc | (1 << i) } else { acc })
+}
+
+fn decode_frequency(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], 0x00]) * 100
+}
+
+fn encode_frequency(freq: u32) -> Result<[u8; 3]> {
+    if freq % 100 != 0 {
+        return Err(anyhow!("frequency must be a multiple of 100"));
+    }
+
+    let f = freq / 100;
+    if f >= 1 << 24 {
+        return Err(anyhow!("max frequency value is 1677721500"));
+    }
+
+    let b = f.to_le_bytes();
+    Ok([b[0], b[1], b[2]])
+}
+
+/// For LoRaWAN 1.0.x devices, the McRootKey is derived from the GenAppKey.
+#[cfg(feature = "crypto")]
+pub fn get_mc_root_key_for_gen_app_key(gen_app_key: &AES128Key) -> Result<AES128Key> {
+    aes_encrypt(gen_app_key, [0x00; 16])
+}
+
+/// For LoRaWAN 1.1.x devices, the McRootKey is derived from the AppKey.
+#[cfg(feature = "crypto")]
+pub fn get_mc_root_key_for_app_key(app_key: &AES128Key) -> Result<AES128Key> {
+    let mut b = [0x00; 16];
+    b[0] = 0x20;
+    aes_encrypt(app_key, b)
+}
+
+#[cfg(feature = "crypto")]
+pub fn get_mc_ke_key(mc_root_key: &AES128Key) -> Result<AES128Key> {
+    aes_encrypt(mc_root_key, [0x00; 16])
+}
+
+#[cfg(feature = "crypto")]
+pub fn get_mc_app_s_key(mc_key: &AES128Key, mc_addr: &DevAddr) -> Result<AES128Key> {
+    let mut b = [0x00; 16];
+    b[0] = 0x01;
+    b[1..5].copy_from_slice(&mc_addr.to_le_bytes());
+    aes_encrypt(mc_key, b)
+}
+
+#[cfg(feature = "crypto")]
+pub fn get_mc_nwk_s_key(mc_key: &AES128Key, mc_addr: &DevAddr) -> Result<AES128Key> {
+    let mut b = [0x00; 16];
+    b[0] = 0x02;
+    b[1..5].copy_from_slice(&mc_addr.to_le_bytes());
+    aes_encrypt(mc_key, b)
+}
+
+/// Encrypts the McKey for the McGroupSetupReq. Note that the specification uses the AES decrypt
+/// operation for this, so that the device only needs the AES encrypt operation to obtain the
+/// McKey.
+#[cfg(feature = "crypto")]
+pub fn encrypt_mc_key(mc_ke_key: &AES128Key, mc_key: &AES128Key) -> Result<AES128Key> {
+    let key_bytes = mc_ke_key.to_bytes();
+    let cipher = Aes128::new(GenericArray::from_slice(&key_bytes));
+
+    let mut b = mc_key.to_bytes();
+    let block = Block::from_mut_slice(&mut b);
+    cipher.decrypt_block(block);
+    Ok(AES128Key::from_slice(block)?)
+}
+
+#[cfg(feature = "crypto")]
+fn aes_encrypt(key: &AES128Key, mut b: [u8; 16]) -> Result<AES128Key> {
+    let key_bytes = key.to_bytes();
+    let cipher = Aes128::new(GenericArray::from_slice(&key_bytes));
+
+    let block = Block::from_mut_slice(&mut b);
+    cipher.encrypt_block(block);
+    Ok(AES128Key::from_slice(block)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run_round_trip(uplink: bool, pl: Payload, bytes: Vec<u8>) {
+        println!("> {:?}", pl.cid());
+        assert_eq!(bytes, pl.to_vec().unwrap());
+        assert_eq!(pl, Payload::from_slice(uplink, &bytes).unwrap());
+    }
+
+    #[test]
+    fn test_package_version() {
+        run_round_trip(false, Payload::PackageVersionReq, vec![0x00]);
+        run_round_trip(
+            true,
+            Payload::PackageVersionAns(PackageVersionAnsPayload {
+                package_identifier: PACKAGE_IDENTIFIER,
+                package_version: PACKAGE_VERSION,
+            }),
+            vec![0x00, 0x02, 0x01],
+        );
+    }
+
+    #[test]
+    fn test_mc_group_status() {
+        run_round_trip(
+            false,
+            Payload::McGroupStatusReq(McGroupStatusReqPayload {
+                cmd_mask: McGroupStatusReqPayloadCmdMask {
+                    req_group_mask: [true, false, false, true],
+                },
+            }),
+            vec![0x01, 0x09],
+        );
+
+        run_round_trip(
+            true,
+            Payload::McGroupStatusAns(McGroupStatusAnsPayload {
+                status: McGroupStatusAnsPayloadStatus {
+                    ans_group_mask: [true, false, true, false],
+                    nb_total_groups: 2,
+                },
+                items: vec![
+                    McGroupStatusAnsPayloadItem {
+                        mc_group_id: 0,
+                        mc_addr: DevAddr::from_be_bytes([0x01, 0x02, 0x03, 0x04]),
+                    },
+                    McGroupStatusAnsPayloadItem {
+                        mc_group_id: 2,
+                        mc_addr: DevAddr::from_be_bytes([0x05, 0x06, 0x07, 0x08]),
+                    },
+                ],
+            }),
+            vec![
+                0x01, 0x25, 0x00, 0x04, 0x03, 0x02, 0x01, 0x02, 0x08, 0x07, 0x06, 0x05,
+            ],
+        );
+
+        // Item count does not match the mask.
+        assert!(Payload::McGroupStatusAns(McGroupStatusAnsPayload {
+            status: McGroupStatusAnsPayloadStatus {
+                ans_group_mask: [true, false, false, false],
+                nb_total_groups: 1,
+            },
+            items: vec![],
+        })
+        .to_vec()
+        .is_err());
+
+        // Missing item bytes.
+        assert!(Payload::from_slice(true, &[0x01, 0x11, 0x00, 0x04]).is_err());
+    }
+
+    #[test]
+    fn test_mc_group_setup() {
+        let mut bytes = vec![0x02, 0x03, 0x04, 0x03, 0x02, 0x01];
+        bytes.extend_from_slice(&[
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            0x07, 0x08,
+        ]);
+        bytes.extend_from_slice(&[0x0a, 0x00, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00]);
+
+        run_round_trip(
+            false,
+            Payload::McGroupSetupReq(McGroupSetupReqPayload {
+                mc_group_id_header: McGroupSetupReqPayloadMcGroupIdHeader { mc_group_id: 3 },
+                mc_addr: DevAddr::from_be_bytes([0x01, 0x02, 0x03, 0x04]),
+                mc_key_encrypted: AES128Key::from_bytes([
+                    0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x01, 0x02, 0x03, 0x04, 0x05,
+                    0x06, 0x07, 0x08,
+                ]),
+                min_mc_f_count: 10,
+                max_mc_f_count: 20,
+            }),
+            bytes,
+        );
+
+        run_round_trip(
+            true,
+            Payload::McGroupSetupAns(McGroupSetupAnsPayload {
+                mc_group_id_header: McGroupSetupAnsPayloadMcGroupIdHeader {
+                    mc_group_id: 3,
+                    id_error: true,
+                },
+            }),
+            vec![0x02, 0x07],
+        );
+    }
+
+    #[test]
+    fn test_mc_group_delete() {
+        run_round_trip(
+            false,
+            Payload::McGroupDeleteReq(McGroupDeleteReqPayload {
+                mc_group_id_header: McGroupDeleteReqPayloadMcGroupIdHeader { mc_group_id: 2 },
+            }),
+            vec![0x03, 0x02],
+        );
+
+        run_round_trip(
+            true,
+            Payload::McGroupDeleteAns(McGroupDeleteAnsPayload {
+                mc_group_id_header: McGroupDeleteAnsPayloadMcGroupIdHeader {
+                    mc_group_id: 2,
+                    mc_group_undefined: true,
+                },
+            }),
+            vec![0x03, 0x06],
+        );
+    }
+
+    #[test]
+    fn test_mc_class_c_session() {
+        run_round_trip(
+            false,
+            Payload::McClassCSessionReq(McClassCSessionReqPayload {
+                mc_group_id_header: McClassCSessionReqPayloadMcGroupIdHeader { mc_group_id: 1 },
+                session_time: 1024,
+                session_time_out: McClassCSessionReqPayloadSessionTimeOut { time_out: 15 },
+                dl_frequency: 869525000,
+                dr: 3,
+            }),
+            vec![
+                0x04, 0x01, 0x00, 0x04, 0x00, 0x00, 0x0f, 0xd2, 0xad, 0x84, 0x03,
+            ],
+        );
+
+        // No errors, time_to_start is set.
+        run_round_trip(
+            true,
+            Payload::McClassCSessionAns(McClassCSessionAnsPayload {
+                status_and_mc_group_id: McClassCSessionAnsPayloadStatusAndMcGroupId {
+                    mc_group_id: 1,
+                    dr_error: false,
+                    freq_error: false,
+                    mc_group_undefined: false,
+                },
+                time_to_start: Some(1024),
+            }),
+            vec![0x04, 0x01, 0x00, 0x04, 0x00],
+        );
+
+        // Errors, time_to_start is not set.
+        run_round_trip(
+            true,
+            Payload::McClassCSessionAns(McClassCSessionAnsPayload {
+                status_and_mc_group_id: McClassCSessionAnsPayloadStatusAndMcGroupId {
+                    mc_group_id: 1,
+                    dr_error: true,
+                    freq_error: true,
+                    mc_group_undefined: true,
+                },
+                time_to_start: None,
+            }),
+            vec![0x04, 0x1d],
+        );
+
+        // Frequency must be a multiple of 100.
+        assert!(Payload::McClassCSessionReq(McClassCSessionReqPayload {
+            mc_group_id_header: McClassCSessionReqPayloadMcGroupIdHeader { mc_group_id: 1 },
+            session_time: 1024,
+            session_time_out: McClassCSessionReqPayloadSessionTimeOut { time_out: 15 },
+            dl_frequency: 869525050,
+            dr: 3,
+        })
+        .to_vec()
+        .is_err());
+    }
+
+    #[test]
+    fn test_mc_class_b_session() {
+        run_round_trip(
+            false,
+            Payload::McClassBSessionReq(McClassBSessionReqPayload {
+                mc_group_id_header: McClassBSessionReqPayloadMcGroupIdHeader { mc_group_id: 1 },
+                session_time: 1024,
+                time_out_periodicity: McClassBSessionReqPayloadTimeOutPeriodicity {
+                    time_out: 15,
+                    periodicity: 7,
+                },
+                dl_frequency: 869525000,
+                dr: 3,
+            }),
+            vec![
+                0x05, 0x01, 0x00, 0x04, 0x00, 0x00, 0x7f, 0xd2, 0xad, 0x84, 0x03,
+            ],
+        );
+
+        run_round_trip(
+            true,
+            Payload::McClassBSessionAns(McClassBSessionAnsPayload {
+                status_and_mc_group_id: McClassCSessionAnsPayloadStatusAndMcGroupId {
+                    mc_group_id: 1,
+                    dr_error: false,
+                    freq_error: false,
+                    mc_group_undefined: false,
+                },
+                time_to_start: Some(10),
+            }),
+            vec![0x05, 0x01, 0x0a, 0x00, 0x00],
+        );
+    }
+
+    #[test]
+    fn test_multi() {
+        let payloads = vec![
+            Payload::McGroupSetupAns(McGroupSetupAnsPayload {
+                mc_group_id_header: McGroupSetupAnsPayloadMcGroupIdHeader {
+                    mc_group_id: 0,
+                    id_error: false,
+                },
+            }),
+            Payload::McClassCSessionAns(McClassCSessionAnsPayload {
+                status_and_mc_group_id: McClassCSessionAnsPayloadStatusAndMcGroupId {
+                    mc_group_id: 0,
+                    dr_error: true,
+                    freq_error: false,
+                    mc_group_undefined: false,
+                },
+                time_to_start: None,
+            }),
+        ];
+        let bytes = vec![0x02, 0x00, 0x04, 0x04];
+
+        assert_eq!(bytes, Payload::to_vec_multi(&payloads).unwrap());
+        assert_eq!(payloads, Payload::from_slice_multi(true, &bytes).unwrap());
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn test_mc_key() {
+        let app_key = AES128Key::from_bytes([
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            0x07, 0x08,
+        ]);
+        let mc_key = AES128Key::from_bytes([
+            0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03,
+            0x02, 0x01,
+        ]);
+
+        let mc_root_key = get_mc_root_key_for_app_key(&app_key).unwrap();
+        let mc_ke_key = get_mc_ke_key(&mc_root_key).unwrap();
+        let mc_key_encrypted = encrypt_mc_key(&mc_ke_key, &mc_key).unwrap();
+        assert_ne!(mc_key, mc_key_encrypted);
+
+        // The device obtains the McKey by encrypting the received McKey using the McKEKey.
+        assert_eq!(
+            mc_key,
+            aes_encrypt(&mc_ke_key, mc_key_encrypted.to_bytes()).unwrap()
+        );
+
+        let mc_addr = DevAddr::from_be_bytes([0x01, 0x02, 0x03, 0x04]);
+        assert_ne!(
+            get_mc_app_s_key(&mc_key, &mc_addr).unwrap(),
+            get_mc_nwk_s_key(&mc_key, &mc_addr).unwrap()
+        );
+    }
+}
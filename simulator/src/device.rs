@@ -0,0 +1,426 @@
+use anyhow::Result;
+use rand::Rng;
+
+use lrwn::keys;
+use lrwn::{
+    AES128Key, DevAddr, FCtrl, FRMPayload, JoinRequestPayload, JoinType, MACPayload, MACVersion,
+    MType, Major, NetID, Payload, PhyPayload, EUI64, FHDR, MHDR,
+};
+
+/// Session of an activated device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub dev_addr: DevAddr,
+    pub net_id: NetID,
+    pub nwk_s_key: AES128Key,
+    pub app_s_key: AES128Key,
+    pub f_cnt_up: u32,
+    pub n_f_cnt_down: u32,
+    pub rx1_delay: u8,
+}
+
+/// Application payload received by the device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Downlink {
+    pub f_cnt: u32,
+    pub f_port: Option<u8>,
+    pub data: Vec<u8>,
+    pub confirmed: bool,
+    pub ack: bool,
+}
+
+/// Simulated LoRaWAN 1.0.x OTAA device.
+///
+/// The device only implements the frame handling, it is up to the caller to send the returned
+/// PHYPayloads through a (simulated) gateway.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub dev_eui: EUI64,
+    pub join_eui: EUI64,
+    pub app_key: AES128Key,
+    pub session: Option<Session>,
+    dev_nonce: u16,
+    pending_ack: bool,
+}
+
+impl Device {
+    pub fn new(dev_eui: EUI64, join_eui: EUI64, app_key: AES128Key) -> Self {
+        Device {
+            dev_eui,
+            join_eui,
+            app_key,
+            session: None,
+            dev_nonce: rand::thread_rng().gen(),
+            pending_ack: false,
+        }
+    }
+
+    /// Returns a new join-request. Every join-request uses a new DevNonce.
+    pub fn join_request(&mut self) -> Result<PhyPayload> {
+        self.dev_nonce = self.dev_nonce.wrapping_add(1);
+
+        let mut phy = PhyPayload {
+            mhdr: MHDR {
+                m_type: MType::JoinRequest,
+                major: Major::LoRaWANR1,
+            },
+            payload: Payload::JoinRequest(JoinRequestPayload {
+                join_eui: self.join_eui,
+                dev_eui: self.dev_eui,
+                dev_nonce: self.dev_nonce,
+            }),
+            mic: None,
+        };
+        phy.set_join_request_mic(&self.app_key)?;
+
+        Ok(phy)
+    }
+
+    /// Handles the join-accept for the last join-request, this activates the device.
+    pub fn handle_join_accept(&mut self, b: &[u8]) -> Result<()> {
+        let mut phy = PhyPayload::from_slice(b)?;
+        if phy.mhdr.m_type != MType::JoinAccept {
+            return Err(anyhow!("Expected JoinAccept, got: {:?}", phy.mhdr.m_type));
+        }
+
+        phy.decrypt_join_accept_payload(&self.app_key)?;
+        if !phy.validate_join_accept_mic(
+            JoinType::Join,
+            &self.join_eui,
+            self.dev_nonce,
+            &self.app_key,
+        )? {
+            return Err(anyhow!("Invalid join-accept MIC"));
+        }
+
+        let pl = match &phy.payload {
+            Payload::JoinAccept(v) => v,
+            _ => return Err(anyhow!("Expected JoinAccept payload")),
+        };
+
+        self.session = Some(Session {
+            dev_addr: pl.devaddr,
+            net_id: pl.home_netid,
+            nwk_s_key: keys::get_f_nwk_s_int_key(
+                false,
+                &self.app_key,
+                &pl.home_netid,
+                &self.join_eui,
+                pl.join_nonce,
+                self.dev_nonce,
+            )?,
+            app_s_key: keys::get_app_s_key(
+                false,
+                &self.app_key,
+                &pl.home_netid,
+                &self.join_eui,
+                pl.join_nonce,
+                self.dev_nonce,
+            )?,
+            f_cnt_up: 0,
+            n_f_cnt_down: 0,
+            rx1_delay: pl.rx_delay.max(1),
+        });
+        self.pending_ack = false;
+
+        Ok(())
+    }
+
+    /// Returns a new uplink with the given application payload. When the last received
+    /// downlink was confirmed, the uplink acknowledges it.
+    pub fn uplink(&mut self, f_port: u8, data: &[u8], confirmed: bool) -> Result<PhyPayload> {
+        let ack = self.pending_ack;
+        let ds = self
+            .session
+            .as_mut()
+            .ok_or_else(|| anyhow!("Device is not activated"))?;
+
+        let mut phy = PhyPayload {
+            mhdr: MHDR {
+                m_type: if confirmed {
+                    MType::ConfirmedDataUp
+                } else {
+                    MType::UnconfirmedDataUp
+                },
+                major: Major::LoRaWANR1,
+            },
+            payload: Payload::MACPayload(MACPayload {
+                fhdr: FHDR {
+                    devaddr: ds.dev_addr,
+                    f_ctrl: FCtrl {
+                        adr: true,
+                        ack,
+                        ..Default::default()
+                    },
+                    f_cnt: ds.f_cnt_up,
+                    ..Default::default()
+                },
+                f_port: Some(f_port),
+                frm_payload: Some(FRMPayload::Raw(data.to_vec())),
+            }),
+            mic: None,
+        };
+
+        phy.encrypt_frm_payload(if f_port == 0 {
+            &ds.nwk_s_key
+        } else {
+            &ds.app_s_key
+        })?;
+        phy.set_uplink_data_mic(
+            MACVersion::LoRaWAN1_0,
+            0,
+            0,
+            0,
+            &ds.nwk_s_key,
+            &ds.nwk_s_key,
+        )?;
+
+        ds.f_cnt_up += 1;
+        self.pending_ack = false;
+
+        Ok(phy)
+    }
+
+    /// Handles a data downlink. Downlinks for other devices return None.
+    pub fn handle_downlink(&mut self, b: &[u8]) -> Result<Option<Downlink>> {
+        let ds = self
+            .session
+            .as_mut()
+            .ok_or_else(|| anyhow!("Device is not activated"))?;
+
+        let mut phy = PhyPayload::from_slice(b)?;
+        let confirmed = match phy.mhdr.m_type {
+            MType::UnconfirmedDataDown => false,
+            MType::ConfirmedDataDown => true,
+            _ => return Ok(None),
+        };
+
+        let f_cnt = match &mut phy.payload {
+            Payload::MACPayload(pl) => {
+                if pl.fhdr.devaddr != ds.dev_addr {
+                    return Ok(None);
+                }
+
+                // Restore the 32 bit frame-counter from the 16 LSB sent over the air.
+                let mut f_cnt = (ds.n_f_cnt_down & 0xffff0000) | (pl.fhdr.f_cnt & 0xffff);
+                if f_cnt < ds.n_f_cnt_down {
+                    f_cnt += 1 << 16;
+                }
+                pl.fhdr.f_cnt = f_cnt;
+                f_cnt
+            }
+            _ => return Err(anyhow!("Expected MACPayload")),
+        };
+
+        if !phy.validate_downlink_data_mic(MACVersion::LoRaWAN1_0, 0, &ds.nwk_s_key)? {
+            return Err(anyhow!("Invalid downlink MIC"));
+        }
+
+        let f_port = match &phy.payload {
+            Payload::MACPayload(pl) => pl.f_port,
+            _ => None,
+        };
+        phy.decrypt_frm_payload(if f_port == Some(0) {
+            &ds.nwk_s_key
+        } else {
+            &ds.app_s_key
+        })?;
+
+        let (data, ack) = match &phy.payload {
+            Payload::MACPayload(pl) => (
+                match &pl.frm_payload {
+                    Some(FRMPayload::Raw(v)) => v.clone(),
+                    _ => vec![],
+                },
+                pl.fhdr.f_ctrl.ack,
+            ),
+            _ => (vec![], false),
+        };
+
+        ds.n_f_cnt_down = f_cnt + 1;
+        self.pending_ack = confirmed;
+
+        Ok(Some(Downlink {
+            f_cnt,
+            f_port,
+            data,
+            confirmed,
+            ack,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use lrwn::{DLSettings, JoinAcceptPayload};
+
+    use super::*;
+
+    fn activated_device() -> Device {
+        let mut d = Device::new(
+            EUI64::from_str("0102030405060708").unwrap(),
+            EUI64::from_str("0807060504030201").unwrap(),
+            AES128Key::from_str("0102030405060708090a0b0c0d0e0f10").unwrap(),
+        );
+
+        let jr = d.join_request().unwrap();
+        assert!(jr.validate_join_request_mic(&d.app_key).unwrap());
+        let dev_nonce = match &jr.payload {
+            Payload::JoinRequest(v) => v.dev_nonce,
+            _ => panic!("Expected JoinRequest"),
+        };
+
+        // Join-accept as it would be sent by the network-server.
+        let mut ja = PhyPayload {
+            mhdr: MHDR {
+                m_type: MType::JoinAccept,
+                major: Major::LoRaWANR1,
+            },
+            payload: Payload::JoinAccept(JoinAcceptPayload {
+                join_nonce: 65793,
+                home_netid: NetID::from_str("000000").unwrap(),
+                devaddr: DevAddr::from_str("01020304").unwrap(),
+                dl_settings: DLSettings {
+                    opt_neg: false,
+                    rx2_dr: 0,
+                    rx1_dr_offset: 0,
+                },
+                cflist: None,
+                rx_delay: 1,
+            }),
+            mic: None,
+        };
+        ja.set_join_accept_mic(JoinType::Join, &d.join_eui, dev_nonce, &d.app_key)
+            .unwrap();
+        ja.encrypt_join_accept_payload(&d.app_key).unwrap();
+
+        d.handle_join_accept(&ja.to_vec().unwrap()).unwrap();
+        d
+    }
+
+    #[test]
+    fn test_join() {
+        let d = activated_device();
+        let ds = d.session.as_ref().unwrap();
+
+        assert_eq!(DevAddr::from_str("01020304").unwrap(), ds.dev_addr);
+        assert_eq!(
+            keys::get_app_s_key(
+                false,
+                &d.app_key,
+                &NetID::from_str("000000").unwrap(),
+                &d.join_eui,
+                65793,
+                d.dev_nonce
+            )
+            .unwrap(),
+            ds.app_s_key
+        );
+    }
+
+    #[test]
+    fn test_uplink() {
+        let mut d = activated_device();
+        let ds = d.session.clone().unwrap();
+
+        let mut phy = d.uplink(2, &[1, 2, 3], false).unwrap();
+        let phy_decoded = PhyPayload::from_slice(&phy.to_vec().unwrap()).unwrap();
+        assert_eq!(phy, phy_decoded);
+
+        assert!(phy
+            .validate_uplink_data_mic(
+                MACVersion::LoRaWAN1_0,
+                0,
+                0,
+                0,
+                &ds.nwk_s_key,
+                &ds.nwk_s_key
+            )
+            .unwrap());
+        phy.decrypt_frm_payload(&ds.app_s_key).unwrap();
+        assert_eq!(
+            Payload::MACPayload(MACPayload {
+                fhdr: FHDR {
+                    devaddr: ds.dev_addr,
+                    f_ctrl: FCtrl {
+                        adr: true,
+                        ..Default::default()
+                    },
+                    f_cnt: 0,
+                    ..Default::default()
+                },
+                f_port: Some(2),
+                frm_payload: Some(FRMPayload::Raw(vec![1, 2, 3])),
+            }),
+            phy.payload
+        );
+        assert_eq!(1, d.session.as_ref().unwrap().f_cnt_up);
+    }
+
+    #[test]
+    fn test_downlink() {
+        let mut d = activated_device();
+        let ds = d.session.clone().unwrap();
+
+        let mut phy = PhyPayload {
+            mhdr: MHDR {
+                m_type: MType::ConfirmedDataDown,
+                major: Major::LoRaWANR1,
+            },
+            payload: Payload::MACPayload(MACPayload {
+                fhdr: FHDR {
+                    devaddr: ds.dev_addr,
+                    f_cnt: 5,
+                    ..Default::default()
+                },
+                f_port: Some(10),
+                frm_payload: Some(FRMPayload::Raw(vec![4, 5, 6])),
+            }),
+            mic: None,
+        };
+        phy.encrypt_frm_payload(&ds.app_s_key).unwrap();
+        phy.set_downlink_data_mic(MACVersion::LoRaWAN1_0, 0, &ds.nwk_s_key)
+            .unwrap();
+
+        assert_eq!(
+            Some(Downlink {
+                f_cnt: 5,
+                f_port: Some(10),
+                data: vec![4, 5, 6],
+                confirmed: true,
+                ack: false,
+            }),
+            d.handle_downlink(&phy.to_vec().unwrap()).unwrap()
+        );
+        assert_eq!(6, d.session.as_ref().unwrap().n_f_cnt_down);
+
+        // The next uplink acknowledges the confirmed downlink.
+        let phy = d.uplink(2, &[], false).unwrap();
+        match &phy.payload {
+            Payload::MACPayload(pl) => assert!(pl.fhdr.f_ctrl.ack),
+            _ => panic!("Expected MACPayload"),
+        }
+
+        // Downlink for an other device.
+        let mut phy = PhyPayload {
+            mhdr: MHDR {
+                m_type: MType::UnconfirmedDataDown,
+                major: Major::LoRaWANR1,
+            },
+            payload: Payload::MACPayload(MACPayload {
+                fhdr: FHDR {
+                    devaddr: DevAddr::from_str("04030201").unwrap(),
+                    ..Default::default()
+                },
+                f_port: None,
+                frm_payload: None,
+            }),
+            mic: None,
+        };
+        phy.set_downlink_data_mic(MACVersion::LoRaWAN1_0, 0, &ds.nwk_s_key)
+            .unwrap();
+        assert_eq!(None, d.handle_downlink(&phy.to_vec().unwrap()).unwrap());
+    }
+}
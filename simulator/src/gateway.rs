@@ -0,0 +1,317 @@
+use std::io::Cursor;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use prost::Message;
+use rand::Rng;
+use rumqttc::v5::mqttbytes::v5::{ConnectReturnCode, Publish};
+use rumqttc::v5::{mqttbytes::QoS, AsyncClient, Event, Incoming, MqttOptions};
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{sleep, timeout};
+use tracing::{error, info, trace};
+
+use chirpstack_api::gw;
+use lrwn::{PhyPayload, EUI64};
+
+use crate::device::{Device, Downlink};
+use crate::payload;
+
+/// Configuration of the simulated gateway.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// MQTT server, e.g. tcp://127.0.0.1:1883.
+    pub server: String,
+    pub username: String,
+    pub password: String,
+    /// Topic prefix, e.g. eu868 (without trailing slash).
+    pub topic_prefix: String,
+    pub gateway_id: EUI64,
+    /// Publish the events as JSON instead of Protobuf.
+    pub json: bool,
+    /// Uplink frequency (Hz).
+    pub frequency: u32,
+    pub spreading_factor: u32,
+    pub bandwidth: u32,
+    pub rssi: i32,
+    pub snr: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            server: "tcp://127.0.0.1:1883".into(),
+            username: "".into(),
+            password: "".into(),
+            topic_prefix: "".into(),
+            gateway_id: EUI64::from_be_bytes([1, 2, 3, 4, 5, 6, 7, 8]),
+            json: false,
+            frequency: 868100000,
+            spreading_factor: 7,
+            bandwidth: 125000,
+            rssi: -60,
+            snr: 7.0,
+        }
+    }
+}
+
+/// Simulated gateway, connected to the MQTT broker of the ChirpStack MQTT gateway backend.
+///
+/// Each received downlink is acknowledged (using the first item of the downlink frame) and
+/// made available through expect_downlink.
+pub struct Gateway {
+    conf: Config,
+    client: AsyncClient,
+    downlink_rx: Mutex<mpsc::Receiver<gw::DownlinkFrame>>,
+    counter: AtomicU32,
+}
+
+impl Gateway {
+    pub async fn connect(conf: Config) -> Result<Gateway> {
+        let client_id: u64 = rand::thread_rng().gen();
+        let mut mqtt_opts =
+            MqttOptions::parse_url(format!("{}?client_id={:x}", conf.server, client_id))?;
+        mqtt_opts.set_clean_start(true);
+        if !conf.username.is_empty() || !conf.password.is_empty() {
+            mqtt_opts.set_credentials(&conf.username, &conf.password);
+        }
+
+        let (client, mut eventloop) = AsyncClient::new(mqtt_opts, 100);
+        let (downlink_tx, downlink_rx) = mpsc::channel(100);
+        let (connect_tx, mut connect_rx) = mpsc::channel(1);
+
+        let gw = Gateway {
+            conf,
+            client,
+            downlink_rx: Mutex::new(downlink_rx),
+            counter: AtomicU32::new(0),
+        };
+
+        info!(server = %gw.conf.server, gateway_id = %gw.conf.gateway_id, "Connecting simulated gateway");
+
+        // Eventloop
+        tokio::spawn({
+            let client = gw.client.clone();
+            let command_topic = gw.get_topic("command/down");
+            let ack_topic = gw.get_topic("event/ack");
+            let json = gw.conf.json;
+
+            async move {
+                loop {
+                    match eventloop.poll().await {
+                        Ok(v) => {
+                            trace!(event = ?v, "MQTT event");
+
+                            match v {
+                                Event::Incoming(Incoming::Publish(p)) => {
+                                    match handle_downlink(&client, &ack_topic, json, p).await {
+                                        Ok(df) => {
+                                            let _ = downlink_tx.send(df).await;
+                                        }
+                                        Err(e) => {
+                                            error!(error = %e, "Handling downlink error");
+                                        }
+                                    }
+                                }
+                                Event::Incoming(Incoming::ConnAck(v)) => {
+                                    if v.code == ConnectReturnCode::Success {
+                                        if let Err(e) =
+                                            client.subscribe(&command_topic, QoS::AtLeastOnce).await
+                                        {
+                                            error!(error = %e, "MQTT subscribe error");
+                                        }
+                                        let _ = connect_tx.try_send(());
+                                    } else {
+                                        error!(code = ?v.code, "Connection error");
+                                        sleep(Duration::from_secs(1)).await
+                                    }
+                                }
+                                _ => {}
+                            }
+                        }
+                        Err(e) => {
+                            error!(error = %e, "MQTT error");
+                            sleep(Duration::from_secs(1)).await
+                        }
+                    }
+                }
+            }
+        });
+
+        timeout(Duration::from_secs(10), connect_rx.recv())
+            .await
+            .map_err(|_| anyhow!("Timeout connecting to MQTT broker"))?;
+
+        Ok(gw)
+    }
+
+    /// Sends the PHYPayload as uplink frame, as if it was received by the gateway.
+    pub async fn send_uplink(&self, phy: &PhyPayload) -> Result<()> {
+        let counter = self.counter.fetch_add(1, Ordering::Relaxed);
+
+        let uf = gw::UplinkFrame {
+            phy_payload: phy.to_vec()?,
+            tx_info: Some(gw::UplinkTxInfo {
+                frequency: self.conf.frequency,
+                modulation: Some(gw::Modulation {
+                    parameters: Some(gw::modulation::Parameters::Lora(gw::LoraModulationInfo {
+                        bandwidth: self.conf.bandwidth,
+                        spreading_factor: self.conf.spreading_factor,
+                        code_rate: gw::CodeRate::Cr45.into(),
+                        ..Default::default()
+                    })),
+                }),
+            }),
+            rx_info: Some(gw::UplinkRxInfo {
+                gateway_id: self.conf.gateway_id.to_string(),
+                uplink_id: rand::thread_rng().gen(),
+                rssi: self.conf.rssi,
+                snr: self.conf.snr,
+                // The concentrator counter (in us), ChirpStack echoes this context in the
+                // downlink.
+                context: counter.wrapping_mul(1_000_000).to_be_bytes().to_vec(),
+                crc_status: gw::CrcStatus::CrcOk.into(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let b = match self.conf.json {
+            true => serde_json::to_vec(&uf)?,
+            false => uf.encode_to_vec(),
+        };
+
+        let topic = self.get_topic("event/up");
+        info!(gateway_id = %self.conf.gateway_id, topic = %topic, "Sending uplink frame");
+        self.client
+            .publish(topic, QoS::AtLeastOnce, false, b)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the next downlink frame sent to the gateway, or an error on timeout.
+    pub async fn expect_downlink(&self, t: Duration) -> Result<gw::DownlinkFrame> {
+        let mut rx = self.downlink_rx.lock().await;
+        timeout(t, rx.recv())
+            .await
+            .map_err(|_| anyhow!("Timeout waiting for downlink"))?
+            .ok_or_else(|| anyhow!("Downlink channel closed"))
+    }
+
+    /// Performs the OTAA join of the device.
+    pub async fn join(&self, dev: &mut Device, t: Duration) -> Result<()> {
+        let jr = dev.join_request()?;
+        self.send_uplink(&jr).await?;
+
+        let df = self.expect_downlink(t).await?;
+        dev.handle_join_accept(&get_phy_payload(&df)?)
+    }
+
+    /// Sends the encoded sensor payload as unconfirmed uplink of the device.
+    pub async fn send_payload(&self, dev: &mut Device, pl: &payload::Payload) -> Result<()> {
+        let phy = dev.uplink(pl.f_port(), &pl.encode(), false)?;
+        self.send_uplink(&phy).await
+    }
+
+    /// Sends the given number of sensor payloads of the device, with the given interval. The
+    /// payload is generated for every uplink using the given function, which gets the uplink
+    /// index as argument.
+    pub async fn send_periodic<F>(
+        &self,
+        dev: &mut Device,
+        interval: Duration,
+        count: usize,
+        f: F,
+    ) -> Result<()>
+    where
+        F: Fn(usize) -> payload::Payload,
+    {
+        for i in 0..count {
+            if i != 0 {
+                sleep(interval).await;
+            }
+            self.send_payload(dev, &f(i)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the next downlink for the device, skipping downlinks for other devices.
+    pub async fn expect_device_downlink(&self, dev: &mut Device, t: Duration) -> Result<Downlink> {
+        timeout(t, async {
+            loop {
+                let df = self.expect_downlink(t).await?;
+                if let Some(dl) = dev.handle_downlink(&get_phy_payload(&df)?)? {
+                    return Ok(dl);
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow!("Timeout waiting for device downlink"))?
+    }
+
+    fn get_topic(&self, suffix: &str) -> String {
+        let topic = format!("gateway/{}/{}", self.conf.gateway_id, suffix);
+        if self.conf.topic_prefix.is_empty() {
+            topic
+        } else {
+            format!("{}/{}", self.conf.topic_prefix, topic)
+        }
+    }
+}
+
+/// Returns the PHYPayload of the first downlink item (RX1), which is the item that the
+/// simulated gateway acknowledges.
+pub fn get_phy_payload(df: &gw::DownlinkFrame) -> Result<Vec<u8>> {
+    df.items
+        .first()
+        .map(|i| i.phy_payload.clone())
+        .ok_or_else(|| anyhow!("Downlink frame does not contain any items"))
+}
+
+async fn handle_downlink(
+    client: &AsyncClient,
+    ack_topic: &str,
+    json: bool,
+    p: Publish,
+) -> Result<gw::DownlinkFrame> {
+    let df = match payload_is_json(&p.payload) {
+        true => serde_json::from_slice::<gw::DownlinkFrame>(&p.payload)?,
+        false => gw::DownlinkFrame::decode(&mut Cursor::new(&p.payload))?,
+    };
+
+    info!(downlink_id = df.downlink_id, "Downlink frame received");
+
+    let ack = gw::DownlinkTxAck {
+        gateway_id: df.gateway_id.clone(),
+        downlink_id: df.downlink_id,
+        items: df
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, _)| gw::DownlinkTxAckItem {
+                status: if i == 0 {
+                    gw::TxAckStatus::Ok.into()
+                } else {
+                    gw::TxAckStatus::Ignored.into()
+                },
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    let b = match json {
+        true => serde_json::to_vec(&ack)?,
+        false => ack.encode_to_vec(),
+    };
+    client
+        .publish(ack_topic, QoS::AtLeastOnce, false, b)
+        .await?;
+
+    Ok(df)
+}
+
+fn payload_is_json(b: &[u8]) -> bool {
+    String::from_utf8_lossy(b).contains("gatewayId")
+}
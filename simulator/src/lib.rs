@@ -0,0 +1,45 @@
+//! Simulated LoRaWAN devices and gateways, for end-to-end testing of ChirpStack (e.g. the
+//! alarm and automation pipelines) against a local MQTT broker.
+//!
+//! Example:
+//! ```rust,no_run
+//! use std::str::FromStr;
+//! use std::time::Duration;
+//!
+//! use lrwn::{AES128Key, EUI64};
+//! use simulator::{device::Device, gateway, payload};
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let gw = gateway::Gateway::connect(gateway::Config {
+//!     topic_prefix: "eu868".into(),
+//!     ..Default::default()
+//! })
+//! .await?;
+//!
+//! let mut dev = Device::new(
+//!     EUI64::from_str("0102030405060708")?,
+//!     EUI64::from_str("0000000000000000")?,
+//!     AES128Key::from_str("0102030405060708090a0b0c0d0e0f10")?,
+//! );
+//! gw.join(&mut dev, Duration::from_secs(10)).await?;
+//!
+//! gw.send_periodic(&mut dev, Duration::from_secs(5), 3, |i| {
+//!     payload::Payload::Em300th(payload::Em300th {
+//!         battery: 90,
+//!         temperature: 20.0 + i as f32,
+//!         humidity: 50.0,
+//!     })
+//! })
+//! .await?;
+//!
+//! let dl = gw.expect_device_downlink(&mut dev, Duration::from_secs(30)).await?;
+//! assert_eq!(Some(10), dl.f_port);
+//! # Ok(())
+//! # }
+//! ```
+#[macro_use]
+extern crate anyhow;
+
+pub mod device;
+pub mod gateway;
+pub mod payload;
@@ -0,0 +1,169 @@
+//! Encoders for the uplink payloads of the supported sensor models.
+//!
+//! The encoded payloads match the vendor payload formats, such that they are decoded by the
+//! same device-profile codecs as the payloads of the physical devices.
+
+/// Dragino LSN50V2 temperature / humidity sensor (MOD=1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lsn50v2 {
+    pub battery_mv: u16,
+    pub temp_ds18b20: f32,
+    pub adc_mv: u16,
+    pub digital_in: bool,
+    pub temp_sht: f32,
+    pub hum_sht: f32,
+}
+
+impl Lsn50v2 {
+    pub const F_PORT: u8 = 2;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(11);
+        b.extend_from_slice(&self.battery_mv.to_be_bytes());
+        b.extend_from_slice(&encode_i16(self.temp_ds18b20, 10.0).to_be_bytes());
+        b.extend_from_slice(&self.adc_mv.to_be_bytes());
+        b.push(if self.digital_in { 0x02 } else { 0x00 });
+        b.extend_from_slice(&encode_i16(self.temp_sht, 10.0).to_be_bytes());
+        b.extend_from_slice(&encode_u16(self.hum_sht, 10.0).to_be_bytes());
+        b
+    }
+}
+
+/// Dragino LDS01 door sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lds01 {
+    pub battery_mv: u16,
+    pub door_open: bool,
+    pub open_count: u32,
+    /// Duration of the last door open event (minutes).
+    pub last_open_duration: u32,
+    pub alarm: bool,
+}
+
+impl Lds01 {
+    pub const F_PORT: u8 = 10;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut status = self.battery_mv & 0x3fff;
+        if self.door_open {
+            status |= 0x8000;
+        }
+
+        let mut b = Vec::with_capacity(10);
+        b.extend_from_slice(&status.to_be_bytes());
+        b.push(0x01);
+        b.extend_from_slice(&self.open_count.min(0xffffff).to_be_bytes()[1..]);
+        b.extend_from_slice(&self.last_open_duration.min(0xffffff).to_be_bytes()[1..]);
+        b.push(self.alarm as u8);
+        b
+    }
+}
+
+/// Milesight EM300-TH temperature / humidity sensor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Em300th {
+    /// Battery level (%).
+    pub battery: u8,
+    pub temperature: f32,
+    pub humidity: f32,
+}
+
+impl Em300th {
+    pub const F_PORT: u8 = 85;
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut b = Vec::with_capacity(11);
+        b.extend_from_slice(&[0x01, 0x75, self.battery.min(100)]);
+        b.extend_from_slice(&[0x03, 0x67]);
+        b.extend_from_slice(&encode_i16(self.temperature, 10.0).to_le_bytes());
+        b.extend_from_slice(&[0x04, 0x68]);
+        b.push((self.humidity * 2.0).round().clamp(0.0, 255.0) as u8);
+        b
+    }
+}
+
+/// Sensor payload of one of the supported models.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Lsn50v2(Lsn50v2),
+    Lds01(Lds01),
+    Em300th(Em300th),
+}
+
+impl Payload {
+    pub fn f_port(&self) -> u8 {
+        match self {
+            Payload::Lsn50v2(_) => Lsn50v2::F_PORT,
+            Payload::Lds01(_) => Lds01::F_PORT,
+            Payload::Em300th(_) => Em300th::F_PORT,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Payload::Lsn50v2(v) => v.encode(),
+            Payload::Lds01(v) => v.encode(),
+            Payload::Em300th(v) => v.encode(),
+        }
+    }
+}
+
+fn encode_i16(v: f32, scale: f32) -> i16 {
+    (v * scale).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
+
+fn encode_u16(v: f32, scale: f32) -> u16 {
+    (v * scale).round().clamp(0.0, u16::MAX as f32) as u16
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lsn50v2() {
+        let pl = Payload::Lsn50v2(Lsn50v2 {
+            battery_mv: 3600,
+            temp_ds18b20: -4.5,
+            adc_mv: 12,
+            digital_in: false,
+            temp_sht: 21.3,
+            hum_sht: 55.1,
+        });
+        assert_eq!(2, pl.f_port());
+        assert_eq!(
+            vec![0x0e, 0x10, 0xff, 0xd3, 0x00, 0x0c, 0x00, 0x00, 0xd5, 0x02, 0x27],
+            pl.encode()
+        );
+    }
+
+    #[test]
+    fn test_lds01() {
+        let pl = Payload::Lds01(Lds01 {
+            battery_mv: 3000,
+            door_open: true,
+            open_count: 300,
+            last_open_duration: 2,
+            alarm: false,
+        });
+        assert_eq!(10, pl.f_port());
+        assert_eq!(
+            vec![0x8b, 0xb8, 0x01, 0x00, 0x01, 0x2c, 0x00, 0x00, 0x02, 0x00],
+            pl.encode()
+        );
+    }
+
+    #[test]
+    fn test_em300th() {
+        let pl = Payload::Em300th(Em300th {
+            battery: 92,
+            temperature: 27.2,
+            humidity: 46.5,
+        });
+        assert_eq!(85, pl.f_port());
+        assert_eq!(
+            vec![0x01, 0x75, 0x5c, 0x03, 0x67, 0x10, 0x01, 0x04, 0x68, 0x5d],
+            pl.encode()
+        );
+    }
+}